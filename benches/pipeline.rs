@@ -0,0 +1,101 @@
+//! Benchmarks for each stage of the customization pipeline.
+//!
+//! Run with `cargo bench`. The apply benches write to folders in a temporary
+//! directory only. Contexts render the fixture base icon rather than the
+//! extracted one, so results compare across machines.
+
+use criterion::{criterion_group, criterion_main, BatchSize, Criterion};
+use folco_core::color::FolderColor;
+use folco_core::fixtures::{seed_cache, FixturePlatform};
+use folco_core::{
+    benchmark_pipeline, convert_icon_set, profile_with_color, BenchmarkOptions, CacheConfig,
+    CustomizationContext, CustomizationContextBuilder, CustomizationProfile, IconCache,
+};
+use tempfile::TempDir;
+
+/// A context with its cache and data in a temporary directory.
+fn context() -> (TempDir, CustomizationContext) {
+    let temp = tempfile::tempdir().expect("temporary directory");
+    let cache = IconCache::new(CacheConfig::new(temp.path().join("cache")));
+    seed_cache(&cache, FixturePlatform::current()).expect("fixture");
+    let ctx = CustomizationContextBuilder::new()
+        .with_cache_dir(temp.path().join("cache"))
+        .with_data_dir(temp.path().join("data"))
+        .build()
+        .expect("context");
+    (temp, ctx)
+}
+
+fn profile() -> CustomizationProfile {
+    profile_with_color(&CustomizationProfile::default(), FolderColor::Blue).expect("profile")
+}
+
+fn bench_extraction(c: &mut Criterion) {
+    c.bench_function("extract_default_icon", |b| {
+        b.iter_batched(
+            || tempfile::tempdir().expect("temporary directory"),
+            |temp| {
+                let cache = IconCache::new(CacheConfig::new(temp.path()).with_force_refresh(true));
+                cache.get_sys_icon_set().expect("extraction")
+            },
+            BatchSize::PerIteration,
+        )
+    });
+}
+
+fn bench_render(c: &mut Criterion) {
+    let (_temp, mut ctx) = context();
+    ctx.apply_profile(&profile());
+    c.bench_function("render_all", |b| b.iter(|| ctx.render().expect("render")));
+}
+
+fn bench_conversion(c: &mut Criterion) {
+    let (_temp, ctx) = context();
+    let sys_icons = ctx.cache().get_sys_icon_set().expect("cached icons");
+    c.bench_function("convert_to_renderer", |b| {
+        b.iter(|| convert_icon_set(&sys_icons))
+    });
+}
+
+fn bench_apply(c: &mut Criterion) {
+    let (temp, mut ctx) = context();
+    let profile = profile();
+    let mut next = 0usize;
+    c.bench_function("customize_folder", |b| {
+        b.iter_batched(
+            || {
+                next += 1;
+                let folder = temp.path().join("folders").join(format!("folder-{next}"));
+                std::fs::create_dir_all(&folder).expect("scratch folder");
+                folder
+            },
+            |folder| ctx.customize_folder(&folder, &profile).expect("apply"),
+            BatchSize::PerIteration,
+        )
+    });
+}
+
+/// The per-size render timings criterion can't reach through the public
+/// API, printed from [`benchmark_pipeline`] for comparison.
+fn bench_render_sizes(c: &mut Criterion) {
+    let options = BenchmarkOptions::new().with_iterations(20).with_folders(0);
+    let report = benchmark_pipeline(&profile(), &options).expect("pipeline benchmark");
+    for size in &report.render_sizes {
+        println!("render_size/{}: mean {:?}", size.size, size.timing.mean);
+    }
+    c.bench_function("pipeline_in_memory", |b| {
+        b.iter(|| {
+            benchmark_pipeline(&profile(), &options.clone().with_iterations(1)).expect("pipeline")
+        })
+    });
+}
+
+criterion_group!(
+    benches,
+    bench_extraction,
+    bench_render,
+    bench_render_sizes,
+    bench_conversion,
+    bench_apply
+);
+criterion_main!(benches);
@@ -0,0 +1,51 @@
+//! Records the versions of the path dependencies, for `about()`.
+//!
+//! Cargo only exposes the crate's own version through the environment, so
+//! the versions of `folco-renderer` and `icon-sys` are read from the
+//! nearest `Cargo.lock`.
+
+use std::env;
+use std::fs;
+use std::path::PathBuf;
+
+const DEPENDENCIES: &[(&str, &str)] = &[
+    ("folco-renderer", "FOLCO_RENDERER_VERSION"),
+    ("icon-sys", "ICON_SYS_VERSION"),
+];
+
+fn main() {
+    let manifest_dir = PathBuf::from(env::var_os("CARGO_MANIFEST_DIR").unwrap_or_default());
+    // In a workspace, the lock file lives in an ancestor directory
+    let lock = manifest_dir
+        .ancestors()
+        .map(|dir| dir.join("Cargo.lock"))
+        .find(|path| path.exists());
+    let content = match &lock {
+        Some(path) => {
+            println!("cargo:rerun-if-changed={}", path.display());
+            fs::read_to_string(path).unwrap_or_default()
+        }
+        None => String::new(),
+    };
+
+    for (name, var) in DEPENDENCIES {
+        let version = locked_version(&content, name).unwrap_or("unknown");
+        println!("cargo:rustc-env={var}={version}");
+    }
+    println!("cargo:rerun-if-changed=build.rs");
+}
+
+/// Returns the version of package `name` in a `Cargo.lock`.
+fn locked_version<'a>(lock: &'a str, name: &str) -> Option<&'a str> {
+    let mut lines = lock.lines();
+    while let Some(line) = lines.next() {
+        if line.trim() == format!("name = \"{name}\"") {
+            return lines
+                .next()?
+                .trim()
+                .strip_prefix("version = \"")?
+                .strip_suffix('"');
+        }
+    }
+    None
+}
@@ -0,0 +1,207 @@
+//! Version and environment reporting.
+//!
+//! [`about`] collects what a GUI's About dialog or a bug report wants to
+//! know: the versions of folco-core and the crates it glues together, the
+//! operating system and its light or dark theme, which optional features
+//! this build was compiled with, and what works on this platform.
+
+use crate::capabilities::{capabilities, Capabilities};
+use crate::wsl::running_in_wsl;
+
+use serde::{Deserialize, Serialize};
+
+use std::process::{Command, Stdio};
+
+/// Versions, environment and features of the running build.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AboutInfo {
+    /// Version of folco-core.
+    pub core_version: String,
+    /// Version of folco-renderer this build was compiled against.
+    pub renderer_version: String,
+    /// Version of icon-sys this build was compiled against.
+    pub icon_sys_version: String,
+    /// The operating system.
+    pub os: OsInfo,
+    /// The desktop's color theme.
+    pub theme: SystemTheme,
+    /// Optional Cargo features this build was compiled with, such as
+    /// `"watch"`.
+    pub features: Vec<String>,
+    /// What works on this platform.
+    pub capabilities: Capabilities,
+}
+
+/// The operating system folco is running on.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct OsInfo {
+    /// OS family, such as `"windows"`, `"macos"` or `"linux"`.
+    pub family: String,
+    /// CPU architecture, such as `"x86_64"` or `"aarch64"`.
+    pub arch: String,
+    /// OS release, such as `"14.4.1"` on macOS or the distribution name on
+    /// Linux, if it could be determined.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub version: Option<String>,
+    /// Whether this is a Linux build running inside WSL.
+    pub wsl: bool,
+}
+
+/// Whether the desktop uses a light or a dark theme.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum SystemTheme {
+    /// A light theme.
+    Light,
+    /// A dark theme.
+    Dark,
+    /// The theme couldn't be determined.
+    #[default]
+    Unknown,
+}
+
+/// Returns the versions, environment and features of the running build.
+///
+/// Detecting the OS release and theme may run a short system command, such
+/// as `sw_vers` on macOS, so call this when showing the information rather
+/// than on every frame.
+pub fn about() -> AboutInfo {
+    AboutInfo {
+        core_version: env!("CARGO_PKG_VERSION").to_string(),
+        renderer_version: env!("FOLCO_RENDERER_VERSION").to_string(),
+        icon_sys_version: env!("ICON_SYS_VERSION").to_string(),
+        os: OsInfo {
+            family: std::env::consts::OS.to_string(),
+            arch: std::env::consts::ARCH.to_string(),
+            version: os_version(),
+            wsl: running_in_wsl(),
+        },
+        theme: system_theme(),
+        features: enabled_features(),
+        capabilities: capabilities(),
+    }
+}
+
+/// Returns the optional features compiled in.
+fn enabled_features() -> Vec<String> {
+    let features = [
+        ("clap", cfg!(feature = "clap")),
+        ("jsonschema", cfg!(feature = "jsonschema")),
+        ("notifications", cfg!(feature = "notifications")),
+        ("seasonal", cfg!(feature = "seasonal")),
+        ("simulated", cfg!(feature = "simulated")),
+        ("storage-sqlite", cfg!(feature = "storage-sqlite")),
+        ("tracing", cfg!(feature = "tracing")),
+        ("watch", cfg!(feature = "watch")),
+    ];
+    features
+        .into_iter()
+        .filter(|(_, enabled)| *enabled)
+        .map(|(name, _)| name.to_string())
+        .collect()
+}
+
+fn os_version() -> Option<String> {
+    if cfg!(target_os = "windows") {
+        // "Microsoft Windows [Version 10.0.22631.3447]"
+        let ver = command_output("cmd", &["/C", "ver"])?;
+        let start = ver.find("Version ")? + "Version ".len();
+        Some(ver[start..].trim_end_matches(']').to_string())
+    } else if cfg!(target_os = "macos") {
+        command_output("sw_vers", &["-productVersion"])
+    } else {
+        let release = std::fs::read_to_string("/etc/os-release").ok()?;
+        os_release_name(&release)
+    }
+}
+
+/// Returns the `PRETTY_NAME` of an `/etc/os-release` file.
+fn os_release_name(release: &str) -> Option<String> {
+    release.lines().find_map(|line| {
+        let value = line.strip_prefix("PRETTY_NAME=")?;
+        Some(value.trim_matches('"').to_string())
+    })
+}
+
+pub(crate) fn system_theme() -> SystemTheme {
+    if cfg!(target_os = "windows") {
+        let output = command_output(
+            "reg",
+            &[
+                "query",
+                r"HKCU\Software\Microsoft\Windows\CurrentVersion\Themes\Personalize",
+                "/v",
+                "AppsUseLightTheme",
+            ],
+        );
+        match output {
+            Some(output) if output.trim_end().ends_with("0x0") => SystemTheme::Dark,
+            Some(output) if output.trim_end().ends_with("0x1") => SystemTheme::Light,
+            _ => SystemTheme::Unknown,
+        }
+    } else if cfg!(target_os = "macos") {
+        // The key only exists while dark mode is on
+        match command_output("defaults", &["read", "-g", "AppleInterfaceStyle"]) {
+            Some(style) if style.eq_ignore_ascii_case("dark") => SystemTheme::Dark,
+            _ => SystemTheme::Light,
+        }
+    } else {
+        let scheme = command_output(
+            "gsettings",
+            &["get", "org.gnome.desktop.interface", "color-scheme"],
+        );
+        match scheme.as_deref() {
+            Some("'prefer-dark'") => SystemTheme::Dark,
+            Some("'prefer-light'" | "'default'") => SystemTheme::Light,
+            _ => match std::env::var("GTK_THEME") {
+                Ok(theme) if theme.to_lowercase().ends_with(":dark") => SystemTheme::Dark,
+                _ => SystemTheme::Unknown,
+            },
+        }
+    }
+}
+
+/// Runs a command and returns its trimmed standard output, if it succeeded.
+pub(crate) fn command_output(program: &str, args: &[&str]) -> Option<String> {
+    let output = Command::new(program)
+        .args(args)
+        .stdin(Stdio::null())
+        .stderr(Stdio::null())
+        .output()
+        .ok()?;
+    if !output.status.success() {
+        return None;
+    }
+    Some(String::from_utf8_lossy(&output.stdout).trim().to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_about_reports_this_build() {
+        let info = about();
+        assert_eq!(info.core_version, env!("CARGO_PKG_VERSION"));
+        assert_eq!(info.os.family, std::env::consts::OS);
+        assert_eq!(
+            info.features.contains(&"watch".to_string()),
+            cfg!(feature = "watch")
+        );
+
+        let json = serde_json::to_value(&info).unwrap();
+        assert!(json["rendererVersion"].is_string());
+    }
+
+    #[test]
+    fn test_os_release_name() {
+        let release = "NAME=\"Ubuntu\"\nPRETTY_NAME=\"Ubuntu 24.04 LTS\"\nID=ubuntu\n";
+        assert_eq!(
+            os_release_name(release).as_deref(),
+            Some("Ubuntu 24.04 LTS")
+        );
+        assert_eq!(os_release_name("ID=arch\n"), None);
+    }
+}
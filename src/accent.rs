@@ -0,0 +1,142 @@
+//! The operating system's accent color.
+//!
+//! [`FolderColor::SystemAccent`](crate::color::FolderColor::SystemAccent)
+//! follows the accent the user picked for their desktop: the Windows accent
+//! color, the macOS highlight color, or the GNOME accent color. It's read
+//! once and cached, so preset lookups stay cheap; call
+//! [`refresh_system_accent`] to pick up a change. Folders customized with it
+//! are marked in the profile store, and `AutoApply`, with the `watch`
+//! feature, re-applies them when the accent changes.
+
+use crate::about::command_output;
+use crate::oklch::srgb_to_hsl;
+
+use std::sync::Mutex;
+
+/// Accent used when the desktop's can't be read: the default Windows blue.
+pub const DEFAULT_ACCENT: [u8; 3] = [0, 120, 212];
+
+/// The accent color as last read, if it has been.
+static CURRENT: Mutex<Option<[u8; 3]>> = Mutex::new(None);
+
+/// Reads the desktop's accent color in sRGB, bypassing the cache.
+///
+/// Returns `None` on desktops without an accent color, or if it can't be
+/// read.
+pub fn system_accent_color() -> Option<[u8; 3]> {
+    if cfg!(target_os = "windows") {
+        let output = command_output(
+            "reg",
+            &[
+                "query",
+                r"HKCU\Software\Microsoft\Windows\DWM",
+                "/v",
+                "AccentColor",
+            ],
+        )?;
+        parse_windows_accent(&output)
+    } else if cfg!(target_os = "macos") {
+        let output = command_output("defaults", &["read", "-g", "AppleHighlightColor"]);
+        // The key only exists once the user picks a color other than blue
+        output.map_or(Some(MACOS_DEFAULT_HIGHLIGHT), |o| parse_macos_highlight(&o))
+    } else {
+        let output = command_output(
+            "gsettings",
+            &["get", "org.gnome.desktop.interface", "accent-color"],
+        )?;
+        gnome_accent(&output)
+    }
+}
+
+/// Re-reads the desktop's accent color and updates the cached one.
+///
+/// Returns the new accent if it changed since it was last read.
+pub fn refresh_system_accent() -> Option<[u8; 3]> {
+    let accent = system_accent_color().unwrap_or(DEFAULT_ACCENT);
+    let mut current = CURRENT.lock().unwrap_or_else(|e| e.into_inner());
+    let previous = current.replace(accent);
+    (previous.is_some_and(|previous| previous != accent)).then_some(accent)
+}
+
+/// Returns the cached accent color, reading it on first use.
+pub(crate) fn current_accent() -> [u8; 3] {
+    let mut current = CURRENT.lock().unwrap_or_else(|e| e.into_inner());
+    *current.get_or_insert_with(|| system_accent_color().unwrap_or(DEFAULT_ACCENT))
+}
+
+/// Returns the cached accent color as `(hue, saturation, lightness)`.
+pub(crate) fn current_accent_hsl() -> (f32, f32, f32) {
+    srgb_to_hsl(current_accent().map(|c| f32::from(c) / 255.0))
+}
+
+/// The macOS highlight color when none is set.
+const MACOS_DEFAULT_HIGHLIGHT: [u8; 3] = [179, 215, 255];
+
+/// Parses `reg query` output for the `AccentColor` DWORD, stored as
+/// `0xAABBGGRR`.
+fn parse_windows_accent(output: &str) -> Option<[u8; 3]> {
+    let line = output.lines().find(|line| line.contains("AccentColor"))?;
+    let value = line.split_whitespace().last()?.strip_prefix("0x")?;
+    let abgr = u32::from_str_radix(value, 16).ok()?;
+    let [r, g, b, _] = abgr.to_le_bytes();
+    Some([r, g, b])
+}
+
+/// Parses `AppleHighlightColor`, which holds the color's components from 0
+/// to 1 followed by its name, e.g. `0.968627 0.831373 1.000000 Purple`.
+fn parse_macos_highlight(output: &str) -> Option<[u8; 3]> {
+    let mut components = output
+        .split_whitespace()
+        .map(|part| part.parse::<f32>().ok());
+    let mut channel = || -> Option<u8> {
+        let value = components.next()??;
+        Some((value.clamp(0.0, 1.0) * 255.0).round() as u8)
+    };
+    Some([channel()?, channel()?, channel()?])
+}
+
+/// Maps GNOME's named accent colors, as `gsettings` prints them, to the
+/// colors GNOME draws them in.
+fn gnome_accent(output: &str) -> Option<[u8; 3]> {
+    let rgb = match output.trim().trim_matches('\'') {
+        "blue" => [0x35, 0x84, 0xe4],
+        "teal" => [0x21, 0x90, 0xa4],
+        "green" => [0x3a, 0x94, 0x4a],
+        "yellow" => [0xc8, 0x88, 0x00],
+        "orange" => [0xed, 0x5b, 0x00],
+        "red" => [0xe6, 0x2d, 0x42],
+        "pink" => [0xd5, 0x61, 0x99],
+        "purple" => [0x91, 0x41, 0xac],
+        "slate" => [0x6f, 0x83, 0x96],
+        _ => return None,
+    };
+    Some(rgb)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_windows_accent() {
+        let output = "\r\nHKEY_CURRENT_USER\\Software\\Microsoft\\Windows\\DWM\r\n    \
+                      AccentColor    REG_DWORD    0xffd47800\r\n";
+        assert_eq!(parse_windows_accent(output), Some(DEFAULT_ACCENT));
+        assert_eq!(parse_windows_accent("ERROR: not found"), None);
+    }
+
+    #[test]
+    fn test_parse_macos_highlight() {
+        assert_eq!(
+            parse_macos_highlight("1.000000 0.749020 0.823529 Pink"),
+            Some([255, 191, 210])
+        );
+        assert_eq!(parse_macos_highlight("Graphite"), None);
+    }
+
+    #[test]
+    fn test_gnome_accent() {
+        assert_eq!(gnome_accent("'teal'\n"), Some([0x21, 0x90, 0xa4]));
+        assert_eq!(gnome_accent("'mauve'"), None);
+    }
+}
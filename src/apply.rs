@@ -0,0 +1,593 @@
+//! Options controlling how icons are applied to individual folders.
+//!
+//! Applying an icon is more than the platform provider's call: the file
+//! manager may need telling that the folder changed, the files the icon is
+//! stored in may need hiding or moving, and slow or flaky volumes may need
+//! retries and timeouts. [`ApplyOptions`] collects all of this in one place.
+//! Settings that only make sense on one platform live in a sub-struct for
+//! that platform, and are ignored elsewhere.
+
+use crate::backend::FolderIconBackend;
+use crate::error::{Error, Result};
+use crate::ico::LegacyIcoOptions;
+use crate::retry::RetryPolicy;
+
+use icon_sys::IconSet as SysIconSet;
+
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+/// Options for applying and resetting folder icons.
+///
+/// The defaults apply each folder once, without a timeout or verification,
+/// and refresh Explorer's view of the folder on Windows.
+///
+/// # Example
+///
+/// ```
+/// use folco_core::{ApplyOptions, RefreshMode, WindowsApplyOptions};
+/// use std::time::Duration;
+///
+/// let options = ApplyOptions::new()
+///     .with_timeout(Duration::from_secs(10))
+///     .with_retry(3, Duration::from_millis(500))
+///     .with_verify(true)
+///     .with_refresh(RefreshMode::FolderAndParent)
+///     .with_windows(WindowsApplyOptions::new().with_artifact_dir("D:\\folco-icons"));
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ApplyOptions {
+    /// How long to wait for a single folder before giving up, if limited.
+    pub timeout: Option<Duration>,
+    /// How folders that fail for a passing reason are retried.
+    pub retry: RetryPolicy,
+    /// Whether to check that the folder reports a custom icon afterwards
+    /// (or no longer does, after a reset).
+    ///
+    /// [Strict](crate::CustomizationContextBuilder::with_strict) contexts
+    /// always verify.
+    pub verify: bool,
+    /// Which folders the file manager is told to redisplay.
+    pub refresh: RefreshMode,
+    /// Windows-specific options.
+    pub windows: WindowsApplyOptions,
+    /// macOS-specific options.
+    pub macos: MacosApplyOptions,
+}
+
+impl Default for ApplyOptions {
+    fn default() -> Self {
+        Self {
+            timeout: None,
+            retry: RetryPolicy::default(),
+            verify: false,
+            refresh: RefreshMode::platform_default(),
+            windows: WindowsApplyOptions::default(),
+            macos: MacosApplyOptions::default(),
+        }
+    }
+}
+
+impl ApplyOptions {
+    /// Creates the default options.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Gives up on a folder after `timeout`.
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = Some(timeout);
+        self
+    }
+
+    /// Tries each folder up to `attempts` times, waiting `backoff` before
+    /// the first retry and twice as long before each further one.
+    ///
+    /// See [`RetryPolicy`].
+    pub fn with_retry(mut self, attempts: u32, backoff: Duration) -> Self {
+        self.retry = RetryPolicy::new(attempts, backoff);
+        self
+    }
+
+    /// Sets whether the result is checked after each folder.
+    pub fn with_verify(mut self, verify: bool) -> Self {
+        self.verify = verify;
+        self
+    }
+
+    /// Sets which folders the file manager is told to redisplay.
+    pub fn with_refresh(mut self, refresh: RefreshMode) -> Self {
+        self.refresh = refresh;
+        self
+    }
+
+    /// Sets the Windows-specific options.
+    pub fn with_windows(mut self, windows: WindowsApplyOptions) -> Self {
+        self.windows = windows;
+        self
+    }
+
+    /// Sets the macOS-specific options.
+    pub fn with_macos(mut self, macos: MacosApplyOptions) -> Self {
+        self.macos = macos;
+        self
+    }
+}
+
+/// Which folders the file manager is told to redisplay after a change.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum RefreshMode {
+    /// Don't refresh; the file manager picks the change up on its own time.
+    None,
+    /// Refresh the changed folder.
+    Folder,
+    /// Refresh the changed folder and its parent, where its icon is shown.
+    FolderAndParent,
+}
+
+impl RefreshMode {
+    /// The default for this platform.
+    ///
+    /// Explorer caches folder icons aggressively and needs a change
+    /// notification, so Windows refreshes the folder. Elsewhere file managers
+    /// notice on their own, and refreshing means touching modification
+    /// times, so nothing is refreshed.
+    pub fn platform_default() -> Self {
+        if cfg!(target_os = "windows") {
+            RefreshMode::Folder
+        } else {
+            RefreshMode::None
+        }
+    }
+
+    /// Returns the folders to refresh after changing `folder`.
+    fn targets(self, folder: &Path) -> Vec<&Path> {
+        match self {
+            RefreshMode::None => Vec::new(),
+            RefreshMode::Folder => vec![folder],
+            RefreshMode::FolderAndParent => {
+                std::iter::once(folder).chain(folder.parent()).collect()
+            }
+        }
+    }
+}
+
+/// Windows-specific [`ApplyOptions`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct WindowsApplyOptions {
+    /// Mark `desktop.ini` and the icon file as hidden system files, so they
+    /// don't show up in Explorer or clutter the folder.
+    pub hide_artifacts: bool,
+    /// Move icon files out of the customized folders and into this
+    /// directory, referenced from `desktop.ini` by absolute path.
+    ///
+    /// Keeps folders under version control or sync free of `.ico` files;
+    /// the icons break if the directory goes away. By default, icons stay
+    /// next to `desktop.ini`.
+    pub artifact_dir: Option<PathBuf>,
+    /// Extra entries written into the `.ico` file for legacy contexts, such
+    /// as shares browsed from Windows XP, which draw 32-bit icons as black
+    /// squares. By default, the icon only has 32-bit entries.
+    pub legacy_ico: LegacyIcoOptions,
+}
+
+impl Default for WindowsApplyOptions {
+    fn default() -> Self {
+        Self {
+            hide_artifacts: true,
+            artifact_dir: None,
+            legacy_ico: LegacyIcoOptions::default(),
+        }
+    }
+}
+
+impl WindowsApplyOptions {
+    /// Creates the default Windows options.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Sets whether `desktop.ini` and icon files are hidden.
+    pub fn with_hide_artifacts(mut self, hide: bool) -> Self {
+        self.hide_artifacts = hide;
+        self
+    }
+
+    /// Stores icon files in `dir` instead of the customized folders.
+    pub fn with_artifact_dir(mut self, dir: impl Into<PathBuf>) -> Self {
+        self.artifact_dir = Some(dir.into());
+        self
+    }
+
+    /// Sets the legacy entries written into `.ico` files.
+    pub fn with_legacy_ico(mut self, legacy: LegacyIcoOptions) -> Self {
+        self.legacy_ico = legacy;
+        self
+    }
+}
+
+/// macOS-specific [`ApplyOptions`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MacosApplyOptions {
+    /// Set the hidden flag on the `Icon\r` file that holds the icon, so it
+    /// stays out of Finder windows and file dialogs.
+    pub hide_artifacts: bool,
+}
+
+impl Default for MacosApplyOptions {
+    fn default() -> Self {
+        Self {
+            hide_artifacts: true,
+        }
+    }
+}
+
+impl MacosApplyOptions {
+    /// Creates the default macOS options.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Sets whether the `Icon\r` file is flagged hidden.
+    pub fn with_hide_artifacts(mut self, hide: bool) -> Self {
+        self.hide_artifacts = hide;
+        self
+    }
+}
+
+/// Hides or moves the files holding `icons` after the platform provider has
+/// written them to `folder`, as `options` say.
+pub(crate) fn finish_artifacts(
+    folder: &Path,
+    options: &ApplyOptions,
+    icons: &SysIconSet,
+) -> Result<()> {
+    platform::finish_artifacts(folder, options, icons).map_err(|e| match e.kind() {
+        // Keep permission errors recognizable for the elevation fallback
+        std::io::ErrorKind::PermissionDenied => Error::Io(e),
+        _ => Error::FolderCustomization(folder.to_path_buf(), e.to_string()),
+    })
+}
+
+/// Checks that `backend` reports a custom icon on `folder` after applying
+/// one, if `options` ask for verification.
+pub(crate) fn verify_applied(
+    backend: &dyn FolderIconBackend,
+    folder: &Path,
+    options: &ApplyOptions,
+) -> Result<()> {
+    if options.verify && !backend.has_custom_icon(folder) {
+        return Err(Error::FolderCustomization(
+            folder.to_path_buf(),
+            "the icon was applied, but the folder doesn't report a custom icon".to_string(),
+        ));
+    }
+    Ok(())
+}
+
+/// Checks that `backend` no longer reports a custom icon on `folder` after
+/// a reset, if `options` ask for verification.
+pub(crate) fn verify_reset(
+    backend: &dyn FolderIconBackend,
+    folder: &Path,
+    options: &ApplyOptions,
+) -> Result<()> {
+    if options.verify && backend.has_custom_icon(folder) {
+        return Err(Error::FolderReset(
+            folder.to_path_buf(),
+            "the folder still reports a custom icon".to_string(),
+        ));
+    }
+    Ok(())
+}
+
+/// Tells the file manager to redisplay the folders `mode` selects.
+///
+/// Refreshing is best effort: a folder that can't be refreshed still has
+/// its new icon, shown once the file manager notices.
+pub(crate) fn refresh(folder: &Path, mode: RefreshMode) {
+    for target in mode.targets(folder) {
+        platform::refresh(target);
+    }
+}
+
+#[cfg(target_os = "windows")]
+mod platform {
+    use super::*;
+
+    use crate::ico::encode_ico;
+    use crate::profile::fnv1a_64;
+    use crate::sys::windows::decode_ini;
+
+    use std::ffi::c_void;
+    use std::fs;
+    use std::io::Write;
+    use std::os::windows::ffi::OsStrExt;
+
+    const FILE_ATTRIBUTE_READONLY: u32 = 0x1;
+    const FILE_ATTRIBUTE_HIDDEN: u32 = 0x2;
+    const FILE_ATTRIBUTE_SYSTEM: u32 = 0x4;
+    const INVALID_FILE_ATTRIBUTES: u32 = u32::MAX;
+    const SHCNE_UPDATEDIR: i32 = 0x1000;
+    const SHCNF_PATHW: u32 = 0x0005;
+
+    #[link(name = "kernel32")]
+    unsafe extern "system" {
+        fn GetFileAttributesW(path: *const u16) -> u32;
+        fn SetFileAttributesW(path: *const u16, attributes: u32) -> i32;
+    }
+
+    #[link(name = "shell32")]
+    unsafe extern "system" {
+        fn SHChangeNotify(event: i32, flags: u32, item1: *const c_void, item2: *const c_void);
+    }
+
+    fn wide(path: &Path) -> Vec<u16> {
+        path.as_os_str().encode_wide().chain(Some(0)).collect()
+    }
+
+    fn attributes(path: &Path) -> Option<u32> {
+        let path = wide(path);
+        // SAFETY: `path` is a NUL-terminated UTF-16 string.
+        let attributes = unsafe { GetFileAttributesW(path.as_ptr()) };
+        (attributes != INVALID_FILE_ATTRIBUTES).then_some(attributes)
+    }
+
+    fn set_attributes(path: &Path, attributes: u32) -> std::io::Result<()> {
+        let path = wide(path);
+        // SAFETY: `path` is a NUL-terminated UTF-16 string.
+        if unsafe { SetFileAttributesW(path.as_ptr(), attributes) } == 0 {
+            return Err(std::io::Error::last_os_error());
+        }
+        Ok(())
+    }
+
+    pub(super) fn refresh(folder: &Path) {
+        let path = wide(folder);
+        // SAFETY: `path` is a NUL-terminated UTF-16 string that outlives the
+        // call, as SHCNF_PATHW requires.
+        unsafe {
+            SHChangeNotify(
+                SHCNE_UPDATEDIR,
+                SHCNF_PATHW,
+                path.as_ptr().cast(),
+                std::ptr::null(),
+            );
+        }
+    }
+
+    pub(super) fn finish_artifacts(
+        folder: &Path,
+        options: &ApplyOptions,
+        icons: &SysIconSet,
+    ) -> std::io::Result<()> {
+        let ini_path = folder.join("desktop.ini");
+        let Ok(bytes) = fs::read(&ini_path) else {
+            return Ok(());
+        };
+        let ini = decode_ini(&bytes);
+        let mut icon = icon_path(&ini).map(|path| folder.join(path));
+
+        // The provider only writes the standard sizes
+        let legacy = &options.windows.legacy_ico;
+        let jumbo = icons.images.iter().any(|image| image.data.width() > 256);
+        if (legacy.is_enabled() || jumbo)
+            && let Some(path) = &icon
+        {
+            let ico = encode_ico(icons, legacy).map_err(|e| std::io::Error::other(e.to_string()))?;
+            overwrite(path, &ico)?;
+        }
+
+        if let Some(dir) = &options.windows.artifact_dir
+            && let Some(current) = icon.take_if(|path| path.starts_with(folder))
+        {
+            let moved = relocate_icon(folder, &current, dir)?;
+            rewrite_ini(&ini_path, &replace_icon_path(&ini, &moved))?;
+            icon = Some(moved);
+        }
+
+        if options.windows.hide_artifacts {
+            let inside = icon.iter().filter(|path| path.starts_with(folder));
+            for artifact in std::iter::once(&ini_path).chain(inside) {
+                if let Some(current) = attributes(artifact) {
+                    set_attributes(
+                        artifact,
+                        current | FILE_ATTRIBUTE_HIDDEN | FILE_ATTRIBUTE_SYSTEM,
+                    )?;
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Moves `icon` into `dir` under a name derived from `folder`, so
+    /// re-applying replaces the previous icon.
+    fn relocate_icon(folder: &Path, icon: &Path, dir: &Path) -> std::io::Result<PathBuf> {
+        fs::create_dir_all(dir)?;
+        let key = folder.to_string_lossy().to_lowercase();
+        let target = dir.join(format!("{:016x}.ico", fnv1a_64(key.as_bytes())));
+
+        // Hidden, system and read-only files can't be replaced or removed
+        for path in [icon, target.as_path()] {
+            if let Some(current) = attributes(path) {
+                let cleared = current
+                    & !(FILE_ATTRIBUTE_READONLY | FILE_ATTRIBUTE_HIDDEN | FILE_ATTRIBUTE_SYSTEM);
+                set_attributes(path, cleared)?;
+            }
+        }
+        if fs::rename(icon, &target).is_err() {
+            // Across volumes, rename fails; copy instead
+            fs::copy(icon, &target)?;
+            fs::remove_file(icon)?;
+        }
+        Ok(target)
+    }
+
+    /// Writes `ini` back to `path` as UTF-16LE, which Explorer always reads.
+    ///
+    /// The existing file is truncated rather than replaced, since replacing
+    /// a hidden system file fails.
+    fn rewrite_ini(path: &Path, ini: &str) -> std::io::Result<()> {
+        let mut bytes = vec![0xFF, 0xFE];
+        bytes.extend(ini.encode_utf16().flat_map(u16::to_le_bytes));
+        overwrite(path, &bytes)
+    }
+
+    /// Replaces the contents of the existing file at `path`, truncating it
+    /// so hidden system files can be rewritten too.
+    fn overwrite(path: &Path, bytes: &[u8]) -> std::io::Result<()> {
+        fs::OpenOptions::new()
+            .write(true)
+            .truncate(true)
+            .open(path)?
+            .write_all(bytes)
+    }
+
+    /// Returns the icon file named in the `[.ShellClassInfo]` section.
+    fn icon_path(ini: &str) -> Option<&str> {
+        icon_entry(ini).map(|(_, _, path)| path)
+    }
+
+    /// Replaces the icon file named in `[.ShellClassInfo]` with `new_path`,
+    /// keeping its resource index.
+    fn replace_icon_path(ini: &str, new_path: &Path) -> String {
+        let Some((line_index, key, old_path)) = icon_entry(ini) else {
+            return ini.to_string();
+        };
+        ini.split_inclusive('\n')
+            .enumerate()
+            .map(|(index, line)| {
+                if index != line_index {
+                    return line.to_string();
+                }
+                let value = line.split_once('=').map_or("", |(_, value)| value.trim());
+                let suffix = value.strip_prefix(old_path).unwrap_or("");
+                let ending = &line[line.trim_end().len()..];
+                format!("{key}={}{suffix}{ending}", new_path.display())
+            })
+            .collect()
+    }
+
+    /// Finds the `IconResource` (or legacy `IconFile`) entry of the
+    /// `[.ShellClassInfo]` section, returning its line index, key and icon
+    /// file path (without the `,index` suffix).
+    fn icon_entry(ini: &str) -> Option<(usize, &str, &str)> {
+        let mut in_shell_class_info = false;
+        for (index, line) in ini.split_inclusive('\n').enumerate() {
+            let line = line.trim();
+            if line.starts_with('[') {
+                in_shell_class_info = line.eq_ignore_ascii_case("[.ShellClassInfo]");
+                continue;
+            }
+            if !in_shell_class_info {
+                continue;
+            }
+            let Some((key, value)) = line.split_once('=') else {
+                continue;
+            };
+            let key = key.trim();
+            if key.eq_ignore_ascii_case("IconResource") {
+                let path = value.rsplit_once(',').map_or(value, |(path, _)| path);
+                return Some((index, key, path.trim()));
+            }
+            if key.eq_ignore_ascii_case("IconFile") {
+                return Some((index, key, value.trim()));
+            }
+        }
+        None
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use super::*;
+
+        #[test]
+        fn test_replace_icon_path_keeps_index() {
+            let ini = "[.ShellClassInfo]\r\nIconResource=folco.ico,0\r\nInfoTip=hi\r\n";
+            assert_eq!(icon_path(ini), Some("folco.ico"));
+
+            let replaced = replace_icon_path(ini, Path::new(r"D:\icons\abc.ico"));
+            assert_eq!(
+                replaced,
+                "[.ShellClassInfo]\r\nIconResource=D:\\icons\\abc.ico,0\r\nInfoTip=hi\r\n"
+            );
+        }
+    }
+}
+
+#[cfg(target_os = "macos")]
+mod platform {
+    use super::*;
+
+    use std::ffi::{c_char, c_int, CString};
+    use std::os::unix::ffi::OsStrExt;
+
+    const UF_HIDDEN: u32 = 0x8000;
+
+    unsafe extern "C" {
+        fn chflags(path: *const c_char, flags: u32) -> c_int;
+    }
+
+    pub(super) fn refresh(folder: &Path) {
+        touch(folder);
+    }
+
+    pub(super) fn finish_artifacts(
+        folder: &Path,
+        options: &ApplyOptions,
+        _icons: &SysIconSet,
+    ) -> std::io::Result<()> {
+        let icon = folder.join("Icon\r");
+        if !options.macos.hide_artifacts || !icon.exists() {
+            return Ok(());
+        }
+        let path = CString::new(icon.as_os_str().as_bytes())?;
+        // SAFETY: `path` is a valid NUL-terminated C string.
+        if unsafe { chflags(path.as_ptr(), UF_HIDDEN) } != 0 {
+            return Err(std::io::Error::last_os_error());
+        }
+        Ok(())
+    }
+}
+
+#[cfg(not(any(target_os = "windows", target_os = "macos")))]
+mod platform {
+    use super::*;
+
+    pub(super) fn refresh(folder: &Path) {
+        touch(folder);
+    }
+
+    /// The `.directory` file is already hidden by its name.
+    pub(super) fn finish_artifacts(
+        _folder: &Path,
+        _options: &ApplyOptions,
+        _icons: &SysIconSet,
+    ) -> std::io::Result<()> {
+        Ok(())
+    }
+}
+
+/// Bumps the modification time of `folder`, which file managers watching it
+/// take as a cue to redisplay it.
+#[cfg(not(target_os = "windows"))]
+fn touch(folder: &Path) {
+    if let Ok(dir) = std::fs::File::open(folder) {
+        let _ = dir.set_modified(std::time::SystemTime::now());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_refresh_targets() {
+        let folder = Path::new("/a/b");
+        assert!(RefreshMode::None.targets(folder).is_empty());
+        assert_eq!(
+            RefreshMode::FolderAndParent.targets(folder),
+            vec![Path::new("/a/b"), Path::new("/a")]
+        );
+    }
+}
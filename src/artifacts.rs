@@ -0,0 +1,224 @@
+//! Cache of finished icons, keyed by what was rendered.
+//!
+//! Rendering and compositing dominate the cost of applying a profile, yet
+//! the same preset is often applied to new folders again and again. Once a
+//! profile has been rendered, the finished icon set, as handed to the folder
+//! settings provider, is kept in memory and written to the icon cache
+//! directory as an ICO file of PNG images, so applying the profile again,
+//! even after a restart, skips the renderer entirely.
+//!
+//! Renders that draw image files are never cached, since the files can
+//! change behind the same path, and neither are renders that had settings
+//! left out, so their warnings are reported every time. The cache is
+//! emptied whenever the base icons are refreshed.
+
+use crate::branding::ColorRange;
+use crate::curve::LightnessCurve;
+use crate::error::Result;
+use crate::ico::{decode_png_ico, encode_ico, LegacyIcoOptions};
+use crate::profile::fnv1a_64;
+use crate::sharpen::SharpenOptions;
+use crate::sized::SizeOverride;
+use crate::store::write_atomic;
+
+use folco_renderer::CustomizationProfile;
+use icon_sys::IconSet as SysIconSet;
+use serde::Serialize;
+
+use std::collections::{HashMap, VecDeque};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+/// Name of the artifact directory inside the icon cache directory.
+const ARTIFACTS_DIR_NAME: &str = "artifacts";
+
+/// Number of icon sets kept in memory; older ones are read from disk again.
+const MEMORY_ENTRIES: usize = 32;
+
+/// Finished icon sets by render key.
+pub(crate) struct ArtifactCache {
+    /// Where icon sets are written, unless they're kept in memory only.
+    dir: Option<PathBuf>,
+    memory: HashMap<String, Arc<SysIconSet>>,
+    /// Keys in `memory`, oldest first.
+    order: VecDeque<String>,
+}
+
+impl ArtifactCache {
+    /// Creates a cache storing its files in `cache_dir`'s artifact
+    /// directory.
+    pub(crate) fn new(cache_dir: &Path) -> Self {
+        Self {
+            dir: Some(cache_dir.join(ARTIFACTS_DIR_NAME)),
+            memory: HashMap::new(),
+            order: VecDeque::new(),
+        }
+    }
+
+    /// Creates a cache keeping icon sets in memory only.
+    pub(crate) fn in_memory() -> Self {
+        Self {
+            dir: None,
+            memory: HashMap::new(),
+            order: VecDeque::new(),
+        }
+    }
+
+    /// Returns the icon set cached under `key`, if any.
+    ///
+    /// A file that can't be read or decoded counts as a miss.
+    pub(crate) fn get(&mut self, key: &str) -> Option<Arc<SysIconSet>> {
+        if let Some(icons) = self.memory.get(key) {
+            return Some(Arc::clone(icons));
+        }
+        let bytes = fs::read(self.path(key)?).ok()?;
+        let icons = Arc::new(decode_png_ico(&bytes).ok()?);
+        self.remember(key, &icons);
+        Some(icons)
+    }
+
+    /// Returns `true` if an icon set may be cached under `key`, without
+    /// reading it.
+    pub(crate) fn contains(&self, key: &str) -> bool {
+        self.memory.contains_key(key) || self.path(key).is_some_and(|path| path.is_file())
+    }
+
+    /// Caches `icons` under `key`, in memory and, unless the cache is in
+    /// memory only, on disk.
+    pub(crate) fn insert(&mut self, key: &str, icons: &Arc<SysIconSet>) -> Result<()> {
+        self.remember(key, icons);
+        let Some(path) = self.path(key) else {
+            return Ok(());
+        };
+        write_atomic(&path, encode_ico(icons, &LegacyIcoOptions::new())?)
+    }
+
+    /// Forgets every cached icon set, e.g. after the base icons changed.
+    pub(crate) fn clear(&mut self) -> Result<()> {
+        self.memory.clear();
+        self.order.clear();
+        let Some(dir) = &self.dir else {
+            return Ok(());
+        };
+        match fs::remove_dir_all(dir) {
+            Err(e) if e.kind() != std::io::ErrorKind::NotFound => Err(e.into()),
+            _ => Ok(()),
+        }
+    }
+
+    fn remember(&mut self, key: &str, icons: &Arc<SysIconSet>) {
+        if self
+            .memory
+            .insert(key.to_string(), Arc::clone(icons))
+            .is_none()
+        {
+            self.order.push_back(key.to_string());
+        }
+        while self.order.len() > MEMORY_ENTRIES {
+            if let Some(oldest) = self.order.pop_front() {
+                self.memory.remove(&oldest);
+            }
+        }
+    }
+
+    fn path(&self, key: &str) -> Option<PathBuf> {
+        Some(self.dir.as_ref()?.join(format!("{key}.ico")))
+    }
+}
+
+/// Settings outside the profile that change a render.
+#[derive(Default, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct ArtifactSettings<'a> {
+    /// The branding's color range.
+    pub(crate) color_range: Option<&'a ColorRange>,
+    /// Sharpening of small sizes.
+    pub(crate) sharpening: Option<&'a SharpenOptions>,
+    /// The lightness curve, if one applies to the profile.
+    pub(crate) lightness_curve: Option<&'a LightnessCurve>,
+}
+
+/// Returns the key a render of `profile` is cached under.
+///
+/// It covers everything besides the base icons that changes the result:
+/// the profile, its per-size overrides, and `settings`.
+pub(crate) fn artifact_key(
+    profile: &CustomizationProfile,
+    overrides: &[SizeOverride],
+    settings: &ArtifactSettings<'_>,
+) -> String {
+    let json = serde_json::json!({
+        "profile": profile,
+        "overrides": overrides,
+        "settings": settings,
+    });
+    format!("{:016x}", fnv1a_64(json.to_string().as_bytes()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use image::{DynamicImage, Rgba, RgbaImage};
+    use tempfile::tempdir;
+
+    fn icon_set(sizes: &[u32]) -> SysIconSet {
+        SysIconSet {
+            images: sizes
+                .iter()
+                .map(|&size| icon_sys::IconImage {
+                    data: DynamicImage::ImageRgba8(RgbaImage::from_pixel(
+                        size,
+                        size,
+                        Rgba([size as u8, 0, 0, 255]),
+                    )),
+                })
+                .collect(),
+        }
+    }
+
+    #[test]
+    fn test_artifacts_survive_a_restart() {
+        let temp = tempdir().unwrap();
+        let icons = Arc::new(icon_set(&[16]));
+        ArtifactCache::new(temp.path())
+            .insert("abc", &icons)
+            .unwrap();
+
+        let mut cache = ArtifactCache::new(temp.path());
+        assert_eq!(cache.get("abc").unwrap().images.len(), 1);
+        assert!(cache.get("other").is_none());
+
+        cache.clear().unwrap();
+        assert!(cache.get("abc").is_none());
+    }
+
+    #[test]
+    fn test_key_covers_settings() {
+        let profile = CustomizationProfile::default();
+        let range = ColorRange::hues(180.0, 240.0);
+        let sharpening = SharpenOptions::new();
+        let curve = LightnessCurve::new(0.5, 0.0);
+        let plain = artifact_key(&profile, &[], &ArtifactSettings::default());
+        assert_eq!(
+            plain,
+            artifact_key(&profile, &[], &ArtifactSettings::default())
+        );
+        for settings in [
+            ArtifactSettings {
+                color_range: Some(&range),
+                ..ArtifactSettings::default()
+            },
+            ArtifactSettings {
+                sharpening: Some(&sharpening),
+                ..ArtifactSettings::default()
+            },
+            ArtifactSettings {
+                lightness_curve: Some(&curve),
+                ..ArtifactSettings::default()
+            },
+        ] {
+            assert_ne!(plain, artifact_key(&profile, &[], &settings));
+        }
+    }
+}
@@ -0,0 +1,316 @@
+//! Customizing new folders automatically as they appear.
+//!
+//! [`AutoApply`] combines a [`FolderWatcher`] with the context's rules: when a
+//! folder is created under a watched root and a rule matches it, the rule is
+//! applied once the folder has settled for the debounce window. Waiting lets
+//! the usual "New folder, then rename" sequence finish before a rule is
+//! picked.
+//!
+//! It also checks the desktop's accent color every
+//! [`DEFAULT_ACCENT_INTERVAL`], and re-applies folders colored with
+//! [`FolderColor::SystemAccent`](crate::color::FolderColor::SystemAccent)
+//! when it changes.
+//!
+//! With the `notifications` feature, it can also raise a desktop
+//! notification summarizing what each poll applied.
+//!
+//! Only available with the `watch` feature.
+
+use crate::accent::refresh_system_accent;
+use crate::context::CustomizationContext;
+#[cfg(feature = "notifications")]
+use crate::notifications::{notify_batch, BatchNotification};
+use crate::watcher::{FolderWatcher, WatchEvent};
+
+use std::path::PathBuf;
+use std::sync::mpsc::RecvTimeoutError;
+use std::time::{Duration, Instant};
+
+/// Default time a new folder must be left alone before rules are applied.
+pub const DEFAULT_DEBOUNCE: Duration = Duration::from_secs(2);
+
+/// Default time between checks of the desktop's accent color.
+pub const DEFAULT_ACCENT_INTERVAL: Duration = Duration::from_secs(10);
+
+/// Something auto-apply did, or noticed.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum AutoApplyEvent {
+    /// A rule was applied to a new folder.
+    Applied {
+        /// The new folder.
+        path: PathBuf,
+        /// Name of the rule that matched.
+        rule: String,
+    },
+    /// Applying a rule to a new folder failed.
+    Failed {
+        /// The new folder.
+        path: PathBuf,
+        /// Name of the rule that matched.
+        rule: String,
+        /// Description of the failure.
+        error: String,
+    },
+    /// The desktop's accent color changed, and folders following it were
+    /// re-applied.
+    AccentChanged {
+        /// The new accent color in sRGB.
+        color: [u8; 3],
+        /// Number of folders re-applied.
+        reapplied: usize,
+        /// Number of folders that failed to re-apply.
+        failed: usize,
+    },
+    /// An event from the underlying watcher.
+    Watch(WatchEvent),
+}
+
+/// Applies rules to folders as they're created.
+///
+/// `AutoApply` doesn't run on its own thread, because applying needs the
+/// context mutably. Call [`poll`](Self::poll) in a loop, or from the app's
+/// event loop.
+///
+/// # Example
+///
+/// ```ignore
+/// let mut auto = AutoApply::new(ctx.watch_library()?);
+/// loop {
+///     for event in auto.poll(&mut ctx, Duration::from_millis(500)) {
+///         if let AutoApplyEvent::Applied { path, rule } = event {
+///             println!("{rule}: {}", path.display());
+///         }
+///     }
+/// }
+/// ```
+#[derive(Debug)]
+pub struct AutoApply {
+    watcher: FolderWatcher,
+    debounce: Duration,
+    /// New folders and when they were last touched.
+    pending: Vec<(PathBuf, Instant)>,
+    accent_interval: Duration,
+    last_accent_check: Option<Instant>,
+    #[cfg(feature = "notifications")]
+    notifications: bool,
+}
+
+impl AutoApply {
+    /// Creates an auto-apply loop over the given watcher, with the
+    /// [`DEFAULT_DEBOUNCE`] window.
+    pub fn new(watcher: FolderWatcher) -> Self {
+        Self {
+            watcher,
+            debounce: DEFAULT_DEBOUNCE,
+            pending: Vec::new(),
+            accent_interval: DEFAULT_ACCENT_INTERVAL,
+            last_accent_check: None,
+            #[cfg(feature = "notifications")]
+            notifications: false,
+        }
+    }
+
+    /// Sets how long a new folder must be left alone before rules apply.
+    pub fn with_debounce(mut self, debounce: Duration) -> Self {
+        self.debounce = debounce;
+        self
+    }
+
+    /// Sets how often the desktop's accent color is checked.
+    pub fn with_accent_interval(mut self, interval: Duration) -> Self {
+        self.accent_interval = interval;
+        self
+    }
+
+    /// Sets whether each poll that applied or re-applied folders raises a
+    /// desktop notification summarizing it. Off by default.
+    #[cfg(feature = "notifications")]
+    pub fn with_notifications(mut self, notifications: bool) -> Self {
+        self.notifications = notifications;
+        self
+    }
+
+    /// Returns the underlying watcher, e.g. to watch more roots.
+    pub fn watcher_mut(&mut self) -> &mut FolderWatcher {
+        &mut self.watcher
+    }
+
+    /// Returns the number of new folders waiting for the debounce window.
+    pub fn pending_count(&self) -> usize {
+        self.pending.len()
+    }
+
+    /// Processes watch events for up to `wait`, then applies rules to new
+    /// folders that have settled.
+    ///
+    /// Returns the watcher's events followed by what was applied, and an
+    /// [`AutoApplyEvent::AccentChanged`] if the accent color changed.
+    pub fn poll(&mut self, ctx: &mut CustomizationContext, wait: Duration) -> Vec<AutoApplyEvent> {
+        let mut events = Vec::new();
+
+        let deadline = Instant::now() + wait;
+        loop {
+            let timeout = deadline.saturating_duration_since(Instant::now());
+            match self.watcher.events().recv_timeout(timeout) {
+                Ok(event) => {
+                    self.track(&event);
+                    events.push(AutoApplyEvent::Watch(event));
+                }
+                Err(RecvTimeoutError::Timeout | RecvTimeoutError::Disconnected) => break,
+            }
+        }
+
+        events.extend(self.apply_settled(ctx, Instant::now()));
+        events.extend(self.check_accent(ctx, Instant::now()));
+        #[cfg(feature = "notifications")]
+        if self.notifications {
+            notify(ctx, &events);
+        }
+        events
+    }
+
+    /// Re-applies accent-colored folders if the accent changed, checking at
+    /// most once per accent interval.
+    fn check_accent(
+        &mut self,
+        ctx: &mut CustomizationContext,
+        now: Instant,
+    ) -> Option<AutoApplyEvent> {
+        if self
+            .last_accent_check
+            .is_some_and(|checked| now.duration_since(checked) < self.accent_interval)
+        {
+            return None;
+        }
+        self.last_accent_check = Some(now);
+
+        let color = refresh_system_accent()?;
+        let outcome = ctx.reapply_system_accent();
+        Some(AutoApplyEvent::AccentChanged {
+            color,
+            reapplied: outcome.succeeded_count(),
+            failed: outcome.failed_count(),
+        })
+    }
+
+    /// Updates the pending folders for a watch event.
+    fn track(&mut self, event: &WatchEvent) {
+        let now = Instant::now();
+        match event {
+            WatchEvent::FolderCreated { path } => {
+                self.pending.retain(|(p, _)| p != path);
+                self.pending.push((path.clone(), now));
+            }
+            WatchEvent::FolderRenamed { from, to } => {
+                for (path, touched) in &mut self.pending {
+                    if path == from {
+                        *path = to.clone();
+                        *touched = now;
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+
+    /// Applies rules to pending folders untouched since the debounce window.
+    fn apply_settled(&mut self, ctx: &mut CustomizationContext, now: Instant) -> Vec<AutoApplyEvent> {
+        let debounce = self.debounce;
+        let (settled, waiting): (Vec<_>, Vec<_>) = self
+            .pending
+            .drain(..)
+            .partition(|(_, touched)| now.duration_since(*touched) >= debounce);
+        self.pending = waiting;
+
+        let mut events = Vec::new();
+        for (path, _) in settled {
+            // Folders that vanished or were customized meanwhile are left alone
+            if !path.is_dir() || ctx.store().contains(&path) {
+                continue;
+            }
+            let Some(rule) = ctx.rules().evaluate(&path).map(|evaluation| evaluation.rule) else {
+                continue;
+            };
+
+            let outcome = ctx.apply_rule(&[&path], &rule);
+            if outcome.error.is_none() && outcome.results.is_empty() {
+                // Skipped by the conflict policy
+                continue;
+            }
+            let result = outcome.into_single_result();
+            events.push(match result {
+                Ok(()) => AutoApplyEvent::Applied {
+                    path,
+                    rule: rule.name,
+                },
+                Err(e) => AutoApplyEvent::Failed {
+                    path,
+                    rule: rule.name,
+                    error: e.to_string(),
+                },
+            });
+        }
+        events
+    }
+}
+
+/// Raises a notification summarizing what `events` applied, logging the
+/// error if it can't be shown.
+#[cfg(feature = "notifications")]
+fn notify(ctx: &CustomizationContext, events: &[AutoApplyEvent]) {
+    let mut notification = BatchNotification {
+        title: "Folders customized automatically".to_string(),
+        ..Default::default()
+    };
+    for event in events {
+        match event {
+            AutoApplyEvent::Applied { .. } => notification.succeeded += 1,
+            AutoApplyEvent::Failed { path, error, .. } => {
+                notification.failed += 1;
+                notification
+                    .first_error
+                    .get_or_insert_with(|| format!("{}: {error}", path.display()));
+            }
+            AutoApplyEvent::AccentChanged {
+                reapplied, failed, ..
+            } => {
+                notification.succeeded += reapplied;
+                notification.failed += failed;
+            }
+            AutoApplyEvent::Watch(_) => {}
+        }
+    }
+    if let Err(e) = notify_batch(&notification) {
+        ctx.log().warn(format_args!("auto-apply: {e}"));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::store::ProfileStore;
+    use tempfile::tempdir;
+
+    #[test]
+    fn test_rename_updates_pending_folder() {
+        let temp = tempdir().unwrap();
+        let store = ProfileStore::in_data_dir(temp.path()).unwrap();
+        let mut auto = AutoApply::new(FolderWatcher::new(store).unwrap());
+
+        let created = temp.path().join("New folder");
+        let renamed = temp.path().join("Invoices");
+        auto.track(&WatchEvent::FolderCreated {
+            path: created.clone(),
+        });
+        auto.track(&WatchEvent::FolderCreated {
+            path: created.clone(),
+        });
+        auto.track(&WatchEvent::FolderRenamed {
+            from: created,
+            to: renamed.clone(),
+        });
+
+        assert_eq!(auto.pending_count(), 1);
+        assert_eq!(auto.pending[0].0, renamed);
+    }
+}
@@ -0,0 +1,293 @@
+//! Pluggable backends for folder icons.
+//!
+//! A context sets folder icons through a [`FolderIconBackend`] and reads
+//! the default folder icon through a [`DefaultIconBackend`]. A folder
+//! backend owns everything it does to a folder, down to hiding the files
+//! an icon is stored in and refreshing the file manager, so a mock backend
+//! leaves real folders alone. By default
+//! these are the platform's icon-sys providers, [`PlatformFolderBackend`]
+//! and the [icon cache](crate::IconCache). Tests can plug in mocks, and
+//! downstream apps can try experimental backends, with
+//! [`with_folder_backend`](crate::CustomizationContextBuilder::with_folder_backend)
+//! and
+//! [`with_default_icon_backend`](crate::CustomizationContextBuilder::with_default_icon_backend).
+//!
+//! The traits wrap icon-sys's `FolderSettingsProvider` and
+//! `DefaultFolderIconProvider` with folco's own errors, so they can be used
+//! as trait objects and implemented without depending on icon-sys's error
+//! types. A default icon backend also says where the folder sits in its
+//! icons and what color the folder is, which the platform otherwise
+//! provides.
+
+use crate::apply::{ApplyOptions, RefreshMode};
+use crate::convert::convert_icon_set_with;
+use crate::error::{Error, Result};
+
+use folco_renderer::{IconBase, RectPx, SurfaceColor};
+use icon_sys::folder_settings::{FolderSettingsProvider, PlatformFolderSettingsProvider};
+use icon_sys::IconSet as SysIconSet;
+
+use std::path::Path;
+use std::sync::Arc;
+
+/// Sets and resets folder icons.
+pub trait FolderIconBackend: Send + Sync {
+    /// Applies `icons` to `folder`.
+    fn set_icon(&self, folder: &Path, icons: &SysIconSet) -> Result<()>;
+
+    /// Resets `folder` to the default icon.
+    fn reset_icon(&self, folder: &Path) -> Result<()>;
+
+    /// Returns `true` if `folder` has a custom icon, whoever set it.
+    ///
+    /// Defaults to checking the platform's own records, such as
+    /// `desktop.ini` on Windows.
+    fn has_custom_icon(&self, folder: &Path) -> bool {
+        crate::sys::has_custom_folder_icon(folder)
+    }
+
+    /// Applies `icons` to `folder` as `options` say.
+    ///
+    /// This is what a context calls. Defaults to
+    /// [`set_icon`](Self::set_icon), ignoring the options; the platform
+    /// backend also hides or moves the files holding the icon and refreshes
+    /// the file manager.
+    fn set_icon_with_options(
+        &self,
+        folder: &Path,
+        icons: &SysIconSet,
+        _options: &ApplyOptions,
+    ) -> Result<()> {
+        self.set_icon(folder, icons)
+    }
+
+    /// Resets `folder` to the default icon as `options` say.
+    ///
+    /// Defaults to [`reset_icon`](Self::reset_icon), ignoring the options.
+    fn reset_icon_with_options(&self, folder: &Path, _options: &ApplyOptions) -> Result<()> {
+        self.reset_icon(folder)
+    }
+
+    /// Tells the file manager to redisplay `folder` as `mode` says, after
+    /// something else, such as an elevated helper, changed its icon.
+    ///
+    /// Defaults to doing nothing.
+    fn refresh(&self, _folder: &Path, _mode: RefreshMode) {}
+}
+
+/// Provides the default folder icon that profiles are rendered on.
+pub trait DefaultIconBackend: Send + Sync {
+    /// Returns the default folder icon, in every size there is.
+    fn default_folder_icon(&self) -> Result<SysIconSet>;
+
+    /// Returns the region of a `width` × `height` image of the default icon
+    /// that holds the folder, without its padding.
+    ///
+    /// Defaults to the platform's bounds.
+    fn content_bounds(&self, width: u32, height: u32) -> RectPx {
+        crate::sys::get_folder_icon_content_bounds(width, height)
+    }
+
+    /// Returns the color of the folder in the default icon, as hue in
+    /// degrees, saturation and lightness.
+    ///
+    /// Defaults to the platform's folder color.
+    fn surface_hsl(&self) -> (f32, f32, f32) {
+        crate::sys::SURFACE_HSL
+    }
+}
+
+/// The content bounds and folder color base icons are rendered with: those
+/// of a context's [`DefaultIconBackend`], or the platform's without one.
+#[derive(Clone, Default)]
+pub(crate) struct IconStyle {
+    backend: Option<Arc<dyn DefaultIconBackend>>,
+}
+
+impl IconStyle {
+    pub(crate) fn new(backend: Option<Arc<dyn DefaultIconBackend>>) -> Self {
+        Self { backend }
+    }
+
+    /// Returns the folder color, as hue in degrees, saturation and
+    /// lightness.
+    pub(crate) fn surface_hsl(&self) -> (f32, f32, f32) {
+        match &self.backend {
+            Some(backend) => backend.surface_hsl(),
+            None => crate::sys::SURFACE_HSL,
+        }
+    }
+
+    /// Returns `icons` as a renderer base with this style's bounds and
+    /// folder color.
+    pub(crate) fn icon_base(&self, icons: &SysIconSet) -> IconBase {
+        let renderer_icons = convert_icon_set_with(icons, |width, height| match &self.backend {
+            Some(backend) => backend.content_bounds(width, height),
+            None => crate::sys::get_folder_icon_content_bounds(width, height),
+        });
+        let (hue, saturation, lightness) = self.surface_hsl();
+        IconBase::new(renderer_icons, SurfaceColor::new(hue, saturation, lightness))
+    }
+}
+
+impl std::fmt::Debug for IconStyle {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("IconStyle")
+            .field("platform", &self.backend.is_none())
+            .finish()
+    }
+}
+
+/// The platform's folder settings provider.
+pub struct PlatformFolderBackend {
+    provider: PlatformFolderSettingsProvider,
+}
+
+impl PlatformFolderBackend {
+    /// Creates the backend.
+    pub fn new() -> Self {
+        Self {
+            provider: PlatformFolderSettingsProvider::new(),
+        }
+    }
+}
+
+impl Default for PlatformFolderBackend {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl std::fmt::Debug for PlatformFolderBackend {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("PlatformFolderBackend").finish_non_exhaustive()
+    }
+}
+
+impl FolderIconBackend for PlatformFolderBackend {
+    fn set_icon(&self, folder: &Path, icons: &SysIconSet) -> Result<()> {
+        self.provider
+            .set_icon_for_folder(folder, icons)
+            .map_err(|e| Error::FolderCustomization(folder.to_path_buf(), e.to_string()))
+    }
+
+    fn reset_icon(&self, folder: &Path) -> Result<()> {
+        self.provider
+            .reset_icon_for_folder(folder)
+            .map_err(|e| Error::FolderReset(folder.to_path_buf(), e.to_string()))
+    }
+
+    fn set_icon_with_options(
+        &self,
+        folder: &Path,
+        icons: &SysIconSet,
+        options: &ApplyOptions,
+    ) -> Result<()> {
+        self.set_icon(folder, icons)?;
+        crate::apply::finish_artifacts(folder, options, icons)?;
+        crate::apply::refresh(folder, options.refresh);
+        Ok(())
+    }
+
+    fn reset_icon_with_options(&self, folder: &Path, options: &ApplyOptions) -> Result<()> {
+        self.reset_icon(folder)?;
+        crate::apply::refresh(folder, options.refresh);
+        Ok(())
+    }
+
+    fn refresh(&self, folder: &Path, mode: RefreshMode) {
+        crate::apply::refresh(folder, mode);
+    }
+}
+
+/// The backend of a [render-only](crate::CustomizationContextBuilder::render_only)
+/// context, which fails every folder without touching the platform.
+#[derive(Debug, Default)]
+pub(crate) struct RenderOnlyBackend;
+
+/// Why a render-only context can't customize folders.
+pub(crate) const RENDER_ONLY_REASON: &str = "the context is render-only";
+
+impl FolderIconBackend for RenderOnlyBackend {
+    fn set_icon(&self, folder: &Path, _icons: &SysIconSet) -> Result<()> {
+        Err(Error::Unsupported(folder.to_path_buf(), RENDER_ONLY_REASON.to_string()))
+    }
+
+    fn reset_icon(&self, folder: &Path) -> Result<()> {
+        Err(Error::Unsupported(folder.to_path_buf(), RENDER_ONLY_REASON.to_string()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::color::FolderColor;
+    use crate::context::CustomizationContextBuilder;
+    use crate::fixtures::{fixture_icon_set, FixturePlatform, FIXTURE_SURFACE_HSL};
+    use crate::profile::profile_with_color;
+    use folco_renderer::CustomizationProfile;
+    use std::path::PathBuf;
+    use std::sync::{Arc, Mutex};
+
+    #[derive(Default)]
+    struct RecordingBackend {
+        applied: Mutex<Vec<(PathBuf, usize)>>,
+    }
+
+    impl FolderIconBackend for RecordingBackend {
+        fn set_icon(&self, folder: &Path, icons: &SysIconSet) -> Result<()> {
+            let mut applied = self.applied.lock().unwrap();
+            applied.push((folder.to_path_buf(), icons.images.len()));
+            Ok(())
+        }
+
+        fn reset_icon(&self, folder: &Path) -> Result<()> {
+            Err(Error::FolderReset(folder.to_path_buf(), "mock".to_string()))
+        }
+
+        fn has_custom_icon(&self, _folder: &Path) -> bool {
+            false
+        }
+    }
+
+    struct FixtureIcons;
+
+    impl DefaultIconBackend for FixtureIcons {
+        fn default_folder_icon(&self) -> Result<SysIconSet> {
+            Ok(fixture_icon_set(FixturePlatform::Windows))
+        }
+
+        fn content_bounds(&self, width: u32, _height: u32) -> RectPx {
+            FixturePlatform::Windows.content_bounds(width)
+        }
+
+        fn surface_hsl(&self) -> (f32, f32, f32) {
+            FIXTURE_SURFACE_HSL
+        }
+    }
+
+    #[test]
+    fn test_context_uses_injected_backends() {
+        let temp = tempfile::tempdir().unwrap();
+        let folder = temp.path().join("Projects");
+        std::fs::create_dir(&folder).unwrap();
+        let backend = Arc::new(RecordingBackend::default());
+
+        let mut ctx = CustomizationContextBuilder::new()
+            .with_cache_dir(temp.path().join("cache"))
+            .with_data_dir(temp.path().join("data"))
+            .with_folder_backend(Arc::clone(&backend) as Arc<dyn FolderIconBackend>)
+            .with_default_icon_backend(Arc::new(FixtureIcons))
+            .build()
+            .unwrap();
+        let profile =
+            profile_with_color(&CustomizationProfile::default(), FolderColor::Blue).unwrap();
+
+        let outcome = ctx.customize_folders(&[&folder], &profile);
+        assert!(outcome.is_success());
+        let applied = backend.applied.lock().unwrap().clone();
+        assert_eq!(applied.len(), 1);
+        assert!(applied[0].1 > 0);
+        assert!(ctx.reset_folder(&folder).is_err());
+    }
+}
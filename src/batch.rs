@@ -0,0 +1,392 @@
+//! Outcomes of batch folder operations.
+//!
+//! [`BatchOutcome::exit_code`] maps an outcome to the exit status a
+//! command-line tool should end with:
+//!
+//! | Code | Constant            | Meaning                                      |
+//! |------|---------------------|----------------------------------------------|
+//! | 0    | [`EXIT_SUCCESS`]    | Every folder succeeded                       |
+//! | 1    | [`EXIT_PARTIAL`]    | Some folders succeeded and some failed       |
+//! | 2    | [`EXIT_FAILURE`]    | Nothing succeeded                            |
+//! | 77   | [`EXIT_PERMISSION`] | Nothing succeeded, for lack of rights        |
+//! | 130  | [`EXIT_CANCELLED`]  | The batch was cancelled                      |
+//!
+//! [`BatchOutcome::to_result`] turns an outcome into a [`BatchResult`],
+//! which can be serialized, so a GUI can show a table of the folders and
+//! how each one ended.
+
+use crate::conflict::SkippedFolder;
+use crate::error::{Error, Result};
+use crate::paths::MergedDuplicate;
+use crate::privileged::needs_elevation;
+use crate::warning::{push_unique, Warning};
+
+use serde::{Deserialize, Serialize};
+
+use std::fmt;
+use std::path::{Path, PathBuf};
+
+/// Exit status of a batch in which every folder succeeded.
+pub const EXIT_SUCCESS: i32 = 0;
+
+/// Exit status of a batch in which some folders succeeded and some failed.
+pub const EXIT_PARTIAL: i32 = 1;
+
+/// Exit status of a batch in which nothing succeeded.
+pub const EXIT_FAILURE: i32 = 2;
+
+/// Exit status of a batch that failed only because it lacked the rights to
+/// change its folders, like `EX_NOPERM` in `sysexits.h`.
+pub const EXIT_PERMISSION: i32 = 77;
+
+/// Exit status of a cancelled batch, as shells report for an interrupt.
+pub const EXIT_CANCELLED: i32 = 130;
+
+/// What counts as a failure when mapping a [`BatchOutcome`] to an exit
+/// status with [`BatchOutcome::exit_code`].
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct ExitCodePolicy {
+    /// Count folders left untouched by the conflict policy as failed.
+    pub fail_skipped: bool,
+    /// Treat a batch with warnings as at most a partial success.
+    pub fail_on_warnings: bool,
+    /// Treat a batch with no folders at all as failed.
+    pub fail_empty: bool,
+}
+
+impl ExitCodePolicy {
+    /// Creates the default policy, under which only failed folders and
+    /// batch errors count.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Sets whether skipped folders count as failed.
+    pub fn with_fail_skipped(mut self, fail: bool) -> Self {
+        self.fail_skipped = fail;
+        self
+    }
+
+    /// Sets whether warnings make a batch a partial success.
+    pub fn with_fail_on_warnings(mut self, fail: bool) -> Self {
+        self.fail_on_warnings = fail;
+        self
+    }
+
+    /// Sets whether an empty batch counts as failed.
+    pub fn with_fail_empty(mut self, fail: bool) -> Self {
+        self.fail_empty = fail;
+        self
+    }
+}
+
+/// The outcome of a batch operation such as
+/// [`customize_folders`](crate::CustomizationContext::customize_folders).
+///
+/// Folders are processed after normalization and deduplication, so
+/// `results` holds one entry per unique folder, in order of first appearance.
+///
+/// Displays as a one-line summary, such as
+/// `3 folders succeeded, 1 failed, 2 skipped`.
+#[derive(Debug, Default)]
+pub struct BatchOutcome {
+    /// Each processed folder paired with its result.
+    pub results: Vec<(PathBuf, Result<()>)>,
+    /// Folders left untouched because of the conflict policy.
+    pub skipped: Vec<SkippedFolder>,
+    /// Input paths that were merged into another folder of the batch.
+    pub duplicates: Vec<MergedDuplicate>,
+    /// An error affecting the batch as a whole: either one that stopped it
+    /// before any folder was processed, such as a rendering failure, or a
+    /// failure to save the profile store afterwards.
+    pub error: Option<Error>,
+    /// Problems the batch worked around, such as profile settings this
+    /// renderer build can't draw being left out.
+    pub warnings: Vec<Warning>,
+}
+
+impl BatchOutcome {
+    /// Returns the number of folders that were processed successfully.
+    pub fn succeeded_count(&self) -> usize {
+        self.results.iter().filter(|(_, r)| r.is_ok()).count()
+    }
+
+    /// Returns the number of folders that failed.
+    pub fn failed_count(&self) -> usize {
+        self.results.iter().filter(|(_, r)| r.is_err()).count()
+    }
+
+    /// Returns the folders that were processed successfully.
+    pub fn succeeded(&self) -> impl Iterator<Item = &Path> {
+        self.results
+            .iter()
+            .filter(|(_, r)| r.is_ok())
+            .map(|(p, _)| p.as_path())
+    }
+
+    /// Returns the folders that failed, with their errors.
+    pub fn failed(&self) -> impl Iterator<Item = (&Path, &Error)> {
+        self.results
+            .iter()
+            .filter_map(|(p, r)| r.as_ref().err().map(|e| (p.as_path(), e)))
+    }
+
+    /// Returns `true` if the batch ran and every folder succeeded.
+    pub fn is_success(&self) -> bool {
+        self.error.is_none() && self.results.iter().all(|(_, r)| r.is_ok())
+    }
+
+    /// Returns the exit status a command-line tool should end with after
+    /// this batch, as listed in the [module documentation](self).
+    ///
+    /// Cancellation takes precedence over everything else. A batch in
+    /// which nothing succeeded exits with [`EXIT_PERMISSION`] if every
+    /// failure was refused for lack of rights, and [`EXIT_FAILURE`]
+    /// otherwise. An error after some folders succeeded, such as a failure
+    /// to save the profile store, makes the batch partial.
+    pub fn exit_code(&self, policy: &ExitCodePolicy) -> i32 {
+        let errors: Vec<&Error> = self
+            .results
+            .iter()
+            .filter_map(|(_, r)| r.as_ref().err())
+            .chain(&self.error)
+            .collect();
+        if errors.iter().any(|e| matches!(e, Error::Cancelled(_))) {
+            return EXIT_CANCELLED;
+        }
+
+        let skipped = if policy.fail_skipped {
+            self.skipped.len()
+        } else {
+            0
+        };
+        let failed = errors.len() + skipped;
+        let succeeded = self.succeeded_count();
+
+        if failed == 0 {
+            if succeeded == 0 && self.skipped.is_empty() && policy.fail_empty {
+                return EXIT_FAILURE;
+            }
+            if policy.fail_on_warnings && !self.warnings.is_empty() {
+                return EXIT_PARTIAL;
+            }
+            return EXIT_SUCCESS;
+        }
+        if succeeded > 0 {
+            return EXIT_PARTIAL;
+        }
+        if skipped == 0 && errors.iter().all(|e| needs_elevation(e)) {
+            EXIT_PERMISSION
+        } else {
+            EXIT_FAILURE
+        }
+    }
+
+    /// Returns the result for a specific (normalized) folder path.
+    pub fn result_for(&self, path: &Path) -> Option<&Result<()>> {
+        self.results
+            .iter()
+            .find(|(p, _)| p == path)
+            .map(|(_, r)| r)
+    }
+
+    /// Returns a serializable copy of the outcome, with errors as their
+    /// messages.
+    pub fn to_result(&self) -> BatchResult {
+        BatchResult {
+            folders: self
+                .results
+                .iter()
+                .map(|(path, result)| FolderResult {
+                    path: path.clone(),
+                    error: result.as_ref().err().map(|e| e.to_string()),
+                    error_kind: result.as_ref().err().map(|e| e.kind().to_string()),
+                })
+                .collect(),
+            skipped: self.skipped.clone(),
+            duplicates: self.duplicates.clone(),
+            error: self.error.as_ref().map(|e| e.to_string()),
+            warnings: self.warnings.clone(),
+        }
+    }
+
+    /// Appends the results of another batch to this one.
+    ///
+    /// The first batch-level error is kept.
+    pub(crate) fn extend(&mut self, other: BatchOutcome) {
+        self.results.extend(other.results);
+        self.skipped.extend(other.skipped);
+        self.duplicates.extend(other.duplicates);
+        if self.error.is_none() {
+            self.error = other.error;
+        }
+        for warning in other.warnings {
+            push_unique(&mut self.warnings, warning);
+        }
+    }
+
+    /// Converts the outcome into the result of its first folder.
+    ///
+    /// Used by the single-folder convenience methods.
+    pub(crate) fn into_single_result(self) -> Result<()> {
+        if let Some(error) = self.error {
+            return Err(error);
+        }
+        self.results
+            .into_iter()
+            .next()
+            .map(|(_, r)| r)
+            .unwrap_or(Ok(()))
+    }
+}
+
+impl fmt::Display for BatchOutcome {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let succeeded = self.succeeded_count();
+        let noun = if succeeded == 1 { "folder" } else { "folders" };
+        write!(f, "{succeeded} {noun} succeeded, {} failed", self.failed_count())?;
+        if !self.skipped.is_empty() {
+            write!(f, ", {} skipped", self.skipped.len())?;
+        }
+        if let Some(error) = &self.error {
+            write!(f, " ({error})")?;
+        }
+        Ok(())
+    }
+}
+
+/// How one folder of a [`BatchResult`] ended.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct FolderResult {
+    /// The normalized folder path.
+    pub path: PathBuf,
+    /// Why the folder failed, or `None` if it succeeded.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+    /// The [kind](Error::kind) of the error, such as `"timeout"`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error_kind: Option<String>,
+}
+
+/// A serializable record of a finished batch, from
+/// [`BatchOutcome::to_result`].
+///
+/// It only carries data; the accessors and the summary are
+/// [`BatchOutcome`]'s.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BatchResult {
+    /// Each processed folder, in the order of
+    /// [`BatchOutcome::results`].
+    pub folders: Vec<FolderResult>,
+    /// Folders left untouched because of the conflict policy.
+    #[serde(default)]
+    pub skipped: Vec<SkippedFolder>,
+    /// Input paths that were merged into another folder of the batch.
+    #[serde(default)]
+    pub duplicates: Vec<MergedDuplicate>,
+    /// The message of the error affecting the batch as a whole, if any.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+    /// Problems the batch worked around.
+    #[serde(default)]
+    pub warnings: Vec<Warning>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_counts() {
+        let outcome = BatchOutcome {
+            results: vec![
+                (PathBuf::from("/a"), Ok(())),
+                (
+                    PathBuf::from("/b"),
+                    Err(Error::FolderReset(PathBuf::from("/b"), "denied".to_string())),
+                ),
+                (PathBuf::from("/c"), Ok(())),
+            ],
+            ..Default::default()
+        };
+
+        assert_eq!(outcome.succeeded_count(), 2);
+        assert_eq!(outcome.failed_count(), 1);
+        assert!(!outcome.is_success());
+        assert!(outcome.result_for(Path::new("/b")).unwrap().is_err());
+        assert_eq!(outcome.succeeded().collect::<Vec<_>>(), ["/a", "/c"].map(Path::new));
+        assert_eq!(outcome.failed().next().unwrap().0, Path::new("/b"));
+        assert_eq!(outcome.to_string(), "2 folders succeeded, 1 failed");
+    }
+
+    #[test]
+    fn test_result_round_trips() {
+        let outcome = BatchOutcome {
+            results: vec![
+                (PathBuf::from("/a"), Ok(())),
+                (
+                    PathBuf::from("/b"),
+                    Err(Error::Timeout(PathBuf::from("/b"), std::time::Duration::from_secs(5))),
+                ),
+            ],
+            skipped: vec![SkippedFolder {
+                path: PathBuf::from("/c"),
+                conflict: crate::conflict::ConflictKind::ForeignIcon,
+            }],
+            ..Default::default()
+        };
+        assert_eq!(outcome.to_string(), "1 folder succeeded, 1 failed, 1 skipped");
+        let result = outcome.to_result();
+        assert_eq!(result.folders[1].error_kind.as_deref(), Some("timeout"));
+
+        let json = serde_json::to_value(&result).unwrap();
+        assert!(json["folders"][0].get("error").is_none());
+        let parsed: BatchResult = serde_json::from_value(json).unwrap();
+        assert_eq!(parsed, result);
+    }
+
+    #[test]
+    fn test_exit_code() {
+        let policy = ExitCodePolicy::new();
+        let denied = || Err(Error::FolderReset(PathBuf::from("/b"), "Access is denied".into()));
+        let failed = || Err(Error::FolderReset(PathBuf::from("/b"), "in use".into()));
+        let outcome = |results: Vec<Result<()>>| BatchOutcome {
+            results: results
+                .into_iter()
+                .map(|r| (PathBuf::from("/a"), r))
+                .collect(),
+            ..Default::default()
+        };
+
+        assert_eq!(outcome(vec![Ok(()), Ok(())]).exit_code(&policy), EXIT_SUCCESS);
+        assert_eq!(outcome(vec![Ok(()), failed()]).exit_code(&policy), EXIT_PARTIAL);
+        assert_eq!(outcome(vec![failed(), denied()]).exit_code(&policy), EXIT_FAILURE);
+        assert_eq!(outcome(vec![denied(), denied()]).exit_code(&policy), EXIT_PERMISSION);
+
+        let cancelled = outcome(vec![Ok(()), Err(Error::Cancelled("declined".into()))]);
+        assert_eq!(cancelled.exit_code(&policy), EXIT_CANCELLED);
+
+        let empty = BatchOutcome::default();
+        assert_eq!(empty.exit_code(&policy), EXIT_SUCCESS);
+        let strict = policy.with_fail_empty(true);
+        assert_eq!(empty.exit_code(&strict), EXIT_FAILURE);
+
+        let mut unsaved = outcome(vec![Ok(())]);
+        unsaved.error = Some(Error::Cache("disk full".into()));
+        assert_eq!(unsaved.exit_code(&strict), EXIT_PARTIAL);
+    }
+
+    #[test]
+    fn test_batch_error_fails_single_result() {
+        let outcome = BatchOutcome {
+            error: Some(Error::NotInitialized("no icons".to_string())),
+            ..Default::default()
+        };
+
+        assert!(!outcome.is_success());
+        assert!(outcome.into_single_result().is_err());
+    }
+}
@@ -0,0 +1,297 @@
+//! Timing of the customization pipeline on the current machine.
+//!
+//! [`benchmark_pipeline`] runs every stage a customization goes through
+//! (extracting the system icon, rendering each size, converting to system
+//! format, and applying to and resetting folders) and reports how long each
+//! took. Folders are created in a scratch directory and removed afterwards,
+//! so nothing the user owns is touched. The criterion benches in `benches/`
+//! cover the same stages for catching regressions during development.
+
+use crate::convert::{convert_icon_set, convert_icon_set_to_sys};
+use crate::error::{Error, Result};
+use crate::extract::{extract_folder_icon, IconSource};
+
+use folco_renderer::{CustomizationProfile, IconBase, IconCustomizer};
+use icon_sys::folder_settings::{FolderSettingsProvider, PlatformFolderSettingsProvider};
+use icon_sys::IconSet as SysIconSet;
+use serde::{Deserialize, Serialize};
+
+use std::fs;
+use std::path::PathBuf;
+use std::time::{Duration, Instant};
+
+/// Options for [`benchmark_pipeline`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BenchmarkOptions {
+    /// How many times the extraction, render and conversion stages run.
+    pub iterations: u32,
+    /// How many scratch folders to apply to and reset. Zero skips the
+    /// apply stage, so the benchmark doesn't write anything.
+    pub folders: usize,
+    /// Directory to create the scratch folders in. Defaults to the system
+    /// temporary directory; point it at a network share or external drive
+    /// to measure that volume.
+    pub scratch_dir: Option<PathBuf>,
+}
+
+impl Default for BenchmarkOptions {
+    fn default() -> Self {
+        Self {
+            iterations: 5,
+            folders: 10,
+            scratch_dir: None,
+        }
+    }
+}
+
+impl BenchmarkOptions {
+    /// Creates the default options: five iterations and ten folders in the
+    /// system temporary directory.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Sets how many times each in-memory stage runs.
+    pub fn with_iterations(mut self, iterations: u32) -> Self {
+        self.iterations = iterations;
+        self
+    }
+
+    /// Sets how many folders the apply stage customizes.
+    pub fn with_folders(mut self, folders: usize) -> Self {
+        self.folders = folders;
+        self
+    }
+
+    /// Creates the scratch folders in `dir`.
+    pub fn with_scratch_dir(mut self, dir: impl Into<PathBuf>) -> Self {
+        self.scratch_dir = Some(dir.into());
+        self
+    }
+}
+
+/// Timing statistics for one stage, over all of its runs.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct StageTiming {
+    /// How many times the stage ran.
+    pub runs: u32,
+    /// Mean duration of a run.
+    pub mean: Duration,
+    /// Fastest run.
+    pub min: Duration,
+    /// Slowest run.
+    pub max: Duration,
+}
+
+impl StageTiming {
+    fn from_samples(samples: &[Duration]) -> Self {
+        let runs = samples.len() as u32;
+        if runs == 0 {
+            return Self::default();
+        }
+        Self {
+            runs,
+            mean: samples.iter().sum::<Duration>() / runs,
+            min: samples.iter().copied().min().unwrap_or_default(),
+            max: samples.iter().copied().max().unwrap_or_default(),
+        }
+    }
+}
+
+/// Render timing for a single icon size.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SizeTiming {
+    /// Icon width in pixels.
+    pub size: u32,
+    /// Time to render this size alone.
+    pub timing: StageTiming,
+}
+
+/// The result of [`benchmark_pipeline`].
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PipelineBenchmark {
+    /// Where the default folder icon was extracted from.
+    pub source: IconSource,
+    /// Extracting and verifying the default folder icon.
+    pub extraction: StageTiming,
+    /// Rendering each size on its own.
+    pub render_sizes: Vec<SizeTiming>,
+    /// Rendering all sizes together, as batches do.
+    pub render_all: StageTiming,
+    /// Converting a rendered set to system format.
+    pub conversion: StageTiming,
+    /// Applying the icon to a single folder, if folders were applied to.
+    pub apply: Option<StageTiming>,
+    /// Resetting a single folder, if folders were applied to.
+    pub reset: Option<StageTiming>,
+}
+
+/// Times each stage of customizing folders with `profile`.
+///
+/// The extraction, render and conversion stages run
+/// [`iterations`](BenchmarkOptions::iterations) times each. The apply and
+/// reset stages run once per scratch folder, through the platform provider
+/// directly, so the profile store and file manager refreshes aren't
+/// included.
+///
+/// # Errors
+///
+/// Fails if the default icon can't be extracted, rendering fails, or the
+/// scratch directory can't be created. A folder that fails to apply or
+/// reset fails the benchmark, since its timings would be meaningless.
+pub fn benchmark_pipeline(
+    profile: &CustomizationProfile,
+    options: &BenchmarkOptions,
+) -> Result<PipelineBenchmark> {
+    let iterations = options.iterations.max(1) as usize;
+
+    let mut samples = Vec::with_capacity(iterations);
+    let mut extracted = None;
+    for _ in 0..iterations {
+        let (elapsed, result) = timed(extract_folder_icon);
+        samples.push(elapsed);
+        extracted = Some(result?);
+    }
+    let extraction = StageTiming::from_samples(&samples);
+    let Some((sys_icons, source)) = extracted else {
+        unreachable!("at least one extraction runs");
+    };
+
+    let mut render_sizes = Vec::with_capacity(sys_icons.images.len());
+    for image in &sys_icons.images {
+        let single = SysIconSet {
+            images: vec![icon_sys::IconImage {
+                data: image.data.clone(),
+            }],
+        };
+        let mut customizer = customizer_for(&single, profile);
+        let samples = run_stage(iterations, || customizer.render_all())?;
+        render_sizes.push(SizeTiming {
+            size: image.data.width(),
+            timing: StageTiming::from_samples(&samples),
+        });
+    }
+
+    let mut customizer = customizer_for(&sys_icons, profile);
+    let render_samples = run_stage(iterations, || customizer.render_all())?;
+    let rendered = customizer.render_all()?;
+    let conversion_samples = run_stage(iterations, || {
+        Ok::<_, Error>(convert_icon_set_to_sys(&rendered))
+    })?;
+
+    let (apply, reset) = if options.folders > 0 {
+        let applied = convert_icon_set_to_sys(&rendered);
+        let (apply, reset) = benchmark_apply(&applied, options)?;
+        (Some(apply), Some(reset))
+    } else {
+        (None, None)
+    };
+
+    Ok(PipelineBenchmark {
+        source,
+        extraction,
+        render_sizes,
+        render_all: StageTiming::from_samples(&render_samples),
+        conversion: StageTiming::from_samples(&conversion_samples),
+        apply,
+        reset,
+    })
+}
+
+/// Applies `icons` to fresh scratch folders and resets them, timing each.
+fn benchmark_apply(
+    icons: &SysIconSet,
+    options: &BenchmarkOptions,
+) -> Result<(StageTiming, StageTiming)> {
+    let parent = options
+        .scratch_dir
+        .clone()
+        .unwrap_or_else(std::env::temp_dir);
+    let scratch = parent.join(format!("folco-benchmark-{}", std::process::id()));
+    fs::create_dir_all(&scratch)?;
+
+    let result = (|| {
+        let provider = PlatformFolderSettingsProvider::new();
+        let folders: Vec<PathBuf> = (0..options.folders)
+            .map(|i| scratch.join(format!("folder-{i}")))
+            .collect();
+        for folder in &folders {
+            fs::create_dir_all(folder)?;
+        }
+
+        let mut apply = Vec::with_capacity(folders.len());
+        for folder in &folders {
+            let (elapsed, result) = timed(|| provider.set_icon_for_folder(folder, icons));
+            result.map_err(|e| Error::FolderCustomization(folder.clone(), e.to_string()))?;
+            apply.push(elapsed);
+        }
+        let mut reset = Vec::with_capacity(folders.len());
+        for folder in &folders {
+            let (elapsed, result) = timed(|| provider.reset_icon_for_folder(folder));
+            result.map_err(|e| Error::FolderReset(folder.clone(), e.to_string()))?;
+            reset.push(elapsed);
+        }
+        Ok((
+            StageTiming::from_samples(&apply),
+            StageTiming::from_samples(&reset),
+        ))
+    })();
+
+    // Customized folders may carry read-only attributes, which is fine to
+    // leave behind in the temporary directory if removal fails
+    let _ = fs::remove_dir_all(&scratch);
+    result
+}
+
+fn customizer_for(icons: &SysIconSet, profile: &CustomizationProfile) -> IconCustomizer {
+    let base = IconBase::new(convert_icon_set(icons), crate::sys::SURFACE_COLOR);
+    let mut customizer = IconCustomizer::new(base);
+    customizer.apply_profile(profile);
+    customizer
+}
+
+/// Runs `op` `iterations` times, returning the duration of each run.
+fn run_stage<T, E>(
+    iterations: usize,
+    mut op: impl FnMut() -> std::result::Result<T, E>,
+) -> Result<Vec<Duration>>
+where
+    Error: From<E>,
+{
+    (0..iterations)
+        .map(|_| {
+            let (elapsed, result) = timed(&mut op);
+            result.map(|_| elapsed).map_err(Error::from)
+        })
+        .collect()
+}
+
+fn timed<T>(op: impl FnOnce() -> T) -> (Duration, T) {
+    let start = Instant::now();
+    let result = op();
+    (start.elapsed(), result)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_stage_timing_from_samples() {
+        let samples = [
+            Duration::from_millis(10),
+            Duration::from_millis(30),
+            Duration::from_millis(20),
+        ];
+        let timing = StageTiming::from_samples(&samples);
+        assert_eq!(timing.runs, 3);
+        assert_eq!(timing.mean, Duration::from_millis(20));
+        assert_eq!(timing.min, Duration::from_millis(10));
+        assert_eq!(timing.max, Duration::from_millis(30));
+
+        assert_eq!(StageTiming::from_samples(&[]), StageTiming::default());
+    }
+}
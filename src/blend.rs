@@ -0,0 +1,135 @@
+//! Blend modes for decal layers.
+//!
+//! A layer normally covers what's below it. Other modes combine its colors
+//! with the folder's instead, e.g. [`BlendMode::Multiply`] lets the folder's
+//! shading show through a glyph. Like the color presets, the modes can be
+//! exported with display metadata so a frontend can offer them without
+//! knowing how they're computed.
+
+use serde::{Deserialize, Serialize};
+
+/// How a layer's colors combine with the pixels below it.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum BlendMode {
+    /// The layer covers what's below.
+    #[default]
+    Normal,
+    /// Colors are multiplied, darkening: white leaves the folder unchanged.
+    Multiply,
+    /// Inverted colors are multiplied, lightening: black leaves the folder
+    /// unchanged.
+    Screen,
+    /// Multiply on dark areas and screen on light ones, adding contrast.
+    Overlay,
+}
+
+impl BlendMode {
+    /// Returns all blend modes.
+    pub fn all() -> &'static [BlendMode] {
+        &[
+            BlendMode::Normal,
+            BlendMode::Multiply,
+            BlendMode::Screen,
+            BlendMode::Overlay,
+        ]
+    }
+
+    /// Returns the machine-readable identifier, as used in JSON.
+    pub fn id(&self) -> &'static str {
+        match self {
+            BlendMode::Normal => "normal",
+            BlendMode::Multiply => "multiply",
+            BlendMode::Screen => "screen",
+            BlendMode::Overlay => "overlay",
+        }
+    }
+
+    /// Returns the human-readable name.
+    pub fn display_name(&self) -> &'static str {
+        match self {
+            BlendMode::Normal => "Normal",
+            BlendMode::Multiply => "Multiply",
+            BlendMode::Screen => "Screen",
+            BlendMode::Overlay => "Overlay",
+        }
+    }
+
+    /// Returns a one-line description for tooltips.
+    pub fn description(&self) -> &'static str {
+        match self {
+            BlendMode::Normal => "Draws the decal as is",
+            BlendMode::Multiply => "Darkens the folder with the decal's colors",
+            BlendMode::Screen => "Lightens the folder with the decal's colors",
+            BlendMode::Overlay => "Adds contrast, keeping the folder's highlights and shadows",
+        }
+    }
+
+    /// Returns all blend modes with their metadata.
+    pub fn all_with_metadata() -> Vec<BlendModeMetadata> {
+        Self::all()
+            .iter()
+            .map(|mode| BlendModeMetadata {
+                id: *mode,
+                display_name: mode.display_name().to_string(),
+                description: mode.description().to_string(),
+            })
+            .collect()
+    }
+
+    /// Blends one channel of `top` onto `bottom`, both from 0 to 1.
+    pub(crate) fn blend_channel(&self, bottom: f32, top: f32) -> f32 {
+        match self {
+            BlendMode::Normal => top,
+            BlendMode::Multiply => bottom * top,
+            BlendMode::Screen => 1.0 - (1.0 - bottom) * (1.0 - top),
+            BlendMode::Overlay => {
+                if bottom <= 0.5 {
+                    2.0 * bottom * top
+                } else {
+                    1.0 - 2.0 * (1.0 - bottom) * (1.0 - top)
+                }
+            }
+        }
+    }
+}
+
+impl std::fmt::Display for BlendMode {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.display_name())
+    }
+}
+
+/// Metadata for a blend mode, for presenting it in a frontend.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BlendModeMetadata {
+    /// Machine-readable identifier (kebab-case).
+    pub id: BlendMode,
+    /// Human-readable name.
+    pub display_name: String,
+    /// One-line description.
+    pub description: String,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_blend_channel() {
+        assert_eq!(BlendMode::Normal.blend_channel(0.2, 0.7), 0.7);
+        assert_eq!(BlendMode::Multiply.blend_channel(0.5, 1.0), 0.5);
+        assert_eq!(BlendMode::Screen.blend_channel(0.5, 0.0), 0.5);
+        assert_eq!(BlendMode::Overlay.blend_channel(0.25, 0.5), 0.25);
+        assert_eq!(BlendMode::Overlay.blend_channel(0.75, 0.5), 0.75);
+    }
+
+    #[test]
+    fn test_id_matches_serde() {
+        for mode in BlendMode::all() {
+            let json = serde_json::to_value(mode).unwrap();
+            assert_eq!(json.as_str(), Some(mode.id()));
+        }
+    }
+}
@@ -0,0 +1,264 @@
+//! Security-scoped bookmarks for sandboxed macOS apps.
+//!
+//! A sandboxed app (such as a Mac App Store build of folco-gui) may only
+//! write to folders the user picked, and only while it holds access to them.
+//! The file picker hands out a security-scoped bookmark for each pick; the
+//! GUI passes those to the context with
+//! [`add_bookmark`](crate::CustomizationContext::add_bookmark), and access is
+//! started before each folder operation and stopped after it.
+//!
+//! A bookmark for a folder also covers everything inside it. Bookmarks are
+//! kept in memory only; the GUI is responsible for persisting them.
+//!
+//! On other platforms bookmarks are accepted and ignored, so callers don't
+//! need platform checks.
+
+use crate::error::{Error, Result};
+use crate::paths::normalize_folder_path;
+
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+/// Bookmark data by the folder it grants access to.
+#[derive(Debug, Clone, Default)]
+pub(crate) struct BookmarkSet {
+    bookmarks: HashMap<PathBuf, Arc<[u8]>>,
+}
+
+impl BookmarkSet {
+    pub(crate) fn insert(&mut self, folder: &Path, data: Vec<u8>) {
+        self.bookmarks.insert(normalize_folder_path(folder), data.into());
+    }
+
+    pub(crate) fn remove(&mut self, folder: &Path) -> bool {
+        self.bookmarks.remove(&normalize_folder_path(folder)).is_some()
+    }
+
+    /// Returns the bookmark covering `folder`: its own, or that of its
+    /// nearest bookmarked ancestor.
+    pub(crate) fn covering(&self, folder: &Path) -> Option<Arc<[u8]>> {
+        let folder = normalize_folder_path(folder);
+        folder
+            .ancestors()
+            .find_map(|ancestor| self.bookmarks.get(ancestor))
+            .cloned()
+    }
+
+    pub(crate) fn len(&self) -> usize {
+        self.bookmarks.len()
+    }
+}
+
+/// A resolved security-scoped bookmark.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ResolvedBookmark {
+    /// The folder the bookmark points to.
+    pub path: PathBuf,
+    /// Whether the bookmark should be recreated. Stale bookmarks still work,
+    /// but may stop working after the folder moves again.
+    pub is_stale: bool,
+}
+
+/// Resolves security-scoped bookmark data to the folder it points to.
+///
+/// # Errors
+///
+/// Returns [`Error::Bookmark`] if the data can't be resolved, or on
+/// platforms without security-scoped bookmarks.
+pub fn resolve_bookmark(data: &[u8]) -> Result<ResolvedBookmark> {
+    let access = platform::ScopedAccess::resolve(data, false)?;
+    Ok(ResolvedBookmark {
+        path: access.path()?,
+        is_stale: access.is_stale(),
+    })
+}
+
+/// Runs `op` while holding access to the folder granted by `bookmark`.
+///
+/// Without a bookmark, `op` runs as is.
+pub(crate) fn with_access<T>(bookmark: Option<&[u8]>, op: impl FnOnce() -> Result<T>) -> Result<T> {
+    let Some(data) = bookmark else {
+        return op();
+    };
+    let _access = platform::ScopedAccess::resolve(data, true)?;
+    op()
+}
+
+#[cfg(target_os = "macos")]
+mod platform {
+    use super::*;
+
+    use std::ffi::{c_void, CStr, OsStr};
+    use std::os::unix::ffi::OsStrExt;
+
+    type CFTypeRef = *const c_void;
+    type CFIndex = isize;
+    type Boolean = u8;
+
+    const BOOKMARK_RESOLUTION_WITHOUT_UI: usize = 1 << 8;
+    const BOOKMARK_RESOLUTION_WITH_SECURITY_SCOPE: usize = 1 << 10;
+    const PATH_MAX: usize = 1024;
+
+    #[link(name = "CoreFoundation", kind = "framework")]
+    unsafe extern "C" {
+        fn CFDataCreate(allocator: CFTypeRef, bytes: *const u8, length: CFIndex) -> CFTypeRef;
+        fn CFURLCreateByResolvingBookmarkData(
+            allocator: CFTypeRef,
+            bookmark: CFTypeRef,
+            options: usize,
+            relative_to: CFTypeRef,
+            resource_properties: CFTypeRef,
+            is_stale: *mut Boolean,
+            error: *mut CFTypeRef,
+        ) -> CFTypeRef;
+        fn CFURLStartAccessingSecurityScopedResource(url: CFTypeRef) -> Boolean;
+        fn CFURLStopAccessingSecurityScopedResource(url: CFTypeRef);
+        fn CFURLGetFileSystemRepresentation(
+            url: CFTypeRef,
+            resolve_against_base: Boolean,
+            buffer: *mut u8,
+            max_length: CFIndex,
+        ) -> Boolean;
+        fn CFRelease(value: CFTypeRef);
+    }
+
+    /// A resolved bookmark URL, with access started if requested. Access is
+    /// stopped and the URL released on drop.
+    pub(super) struct ScopedAccess {
+        url: CFTypeRef,
+        is_stale: bool,
+        accessing: bool,
+    }
+
+    impl ScopedAccess {
+        pub(super) fn resolve(data: &[u8], start: bool) -> Result<Self> {
+            let length = CFIndex::try_from(data.len())
+                .map_err(|_| Error::Bookmark("bookmark data is too large".to_string()))?;
+            let mut is_stale: Boolean = 0;
+            // SAFETY: `data` outlives the call, and every object created here
+            // is released exactly once, here or in `Drop`.
+            let url = unsafe {
+                let bookmark = CFDataCreate(std::ptr::null(), data.as_ptr(), length);
+                if bookmark.is_null() {
+                    return Err(Error::Bookmark("failed to copy bookmark data".to_string()));
+                }
+                let url = CFURLCreateByResolvingBookmarkData(
+                    std::ptr::null(),
+                    bookmark,
+                    BOOKMARK_RESOLUTION_WITHOUT_UI | BOOKMARK_RESOLUTION_WITH_SECURITY_SCOPE,
+                    std::ptr::null(),
+                    std::ptr::null(),
+                    &mut is_stale,
+                    std::ptr::null_mut(),
+                );
+                CFRelease(bookmark);
+                url
+            };
+            if url.is_null() {
+                return Err(Error::Bookmark("bookmark could not be resolved".to_string()));
+            }
+
+            let mut access = Self {
+                url,
+                is_stale: is_stale != 0,
+                accessing: false,
+            };
+            if start {
+                // SAFETY: `url` is a valid CFURL owned by `access`.
+                access.accessing = unsafe { CFURLStartAccessingSecurityScopedResource(url) } != 0;
+                if !access.accessing {
+                    return Err(Error::Bookmark(
+                        "access to the bookmarked folder was denied".to_string(),
+                    ));
+                }
+            }
+            Ok(access)
+        }
+
+        pub(super) fn path(&self) -> Result<PathBuf> {
+            let mut buffer = [0u8; PATH_MAX];
+            // SAFETY: the buffer length passed matches the buffer.
+            let ok = unsafe {
+                CFURLGetFileSystemRepresentation(self.url, 1, buffer.as_mut_ptr(), PATH_MAX as CFIndex)
+            };
+            let path = CStr::from_bytes_until_nul(&buffer)
+                .ok()
+                .filter(|_| ok != 0)
+                .ok_or_else(|| Error::Bookmark("bookmark has no file path".to_string()))?;
+            Ok(PathBuf::from(OsStr::from_bytes(path.to_bytes())))
+        }
+
+        pub(super) fn is_stale(&self) -> bool {
+            self.is_stale
+        }
+    }
+
+    impl Drop for ScopedAccess {
+        fn drop(&mut self) {
+            // SAFETY: `url` is valid until released here.
+            unsafe {
+                if self.accessing {
+                    CFURLStopAccessingSecurityScopedResource(self.url);
+                }
+                CFRelease(self.url);
+            }
+        }
+    }
+}
+
+#[cfg(not(target_os = "macos"))]
+mod platform {
+    use super::*;
+
+    /// Stands in for macOS bookmark access, which other platforms don't need.
+    pub(super) struct ScopedAccess;
+
+    impl ScopedAccess {
+        /// Access never needs starting here, so operations simply proceed;
+        /// only explicit resolution fails.
+        pub(super) fn resolve(_data: &[u8], start: bool) -> Result<Self> {
+            if start {
+                return Ok(Self);
+            }
+            Err(Error::Bookmark(
+                "security-scoped bookmarks are only supported on macOS".to_string(),
+            ))
+        }
+
+        pub(super) fn path(&self) -> Result<PathBuf> {
+            Err(Error::Bookmark(
+                "security-scoped bookmarks are only supported on macOS".to_string(),
+            ))
+        }
+
+        pub(super) fn is_stale(&self) -> bool {
+            false
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_bookmark_covers_descendants() {
+        let mut bookmarks = BookmarkSet::default();
+        bookmarks.insert(Path::new("/Users/me/Projects"), vec![1, 2, 3]);
+
+        let covering = bookmarks.covering(Path::new("/Users/me/Projects/app/src"));
+        assert_eq!(covering.as_deref(), Some(&[1, 2, 3][..]));
+        assert!(bookmarks.covering(Path::new("/Users/me/Documents")).is_none());
+
+        assert!(bookmarks.remove(Path::new("/Users/me/Projects/")));
+        assert_eq!(bookmarks.len(), 0);
+    }
+
+    #[cfg(not(target_os = "macos"))]
+    #[test]
+    fn test_access_is_a_no_op_elsewhere() {
+        assert_eq!(with_access(Some(&[0u8; 4][..]), || Ok(7)).unwrap(), 7);
+        assert!(matches!(resolve_bookmark(&[1, 2]), Err(Error::Bookmark(_))));
+    }
+}
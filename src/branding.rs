@@ -0,0 +1,222 @@
+//! Organization branding applied to every customized folder.
+//!
+//! A [`Branding`] is configured once, in the app config, and folco-core
+//! merges it into every profile it applies: a logo layer drawn over each
+//! icon, and a color range that users' color choices are kept within. Users
+//! still pick their own colors and decals; branding only constrains them,
+//! so a company's folders stay recognizably its own.
+
+use crate::color::FolderColor;
+use crate::layers::DecalLayer;
+
+use folco_renderer::HslMutationSettings;
+use serde::{Deserialize, Serialize};
+
+/// Branding merged into every applied profile.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct Branding {
+    /// Logo drawn over every icon, above the profile's own layers.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub logo: Option<DecalLayer>,
+    /// Colors a profile may use. Colors outside it are moved to the nearest
+    /// color inside.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub color_range: Option<ColorRange>,
+}
+
+impl Branding {
+    /// Creates empty branding, which changes nothing.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Draws `logo` over every icon.
+    pub fn with_logo(mut self, logo: DecalLayer) -> Self {
+        self.logo = Some(logo);
+        self
+    }
+
+    /// Keeps colors within `range`.
+    pub fn with_color_range(mut self, range: ColorRange) -> Self {
+        self.color_range = Some(range);
+        self
+    }
+
+    /// Returns `true` if the branding changes nothing.
+    pub fn is_empty(&self) -> bool {
+        self.logo.is_none() && self.color_range.is_none()
+    }
+
+    /// Returns `settings` moved into the color range, if there is one.
+    pub fn constrain(&self, settings: &HslMutationSettings) -> HslMutationSettings {
+        match &self.color_range {
+            Some(range) => range.constrain(settings),
+            None => settings.clone(),
+        }
+    }
+}
+
+/// A range of HSL colors.
+///
+/// Hues run from `min_hue` clockwise to `max_hue`, so a range from 330 to
+/// 30 covers the reds either side of 0°.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct ColorRange {
+    /// Start of the hue range in degrees (0–360).
+    pub min_hue: f32,
+    /// End of the hue range in degrees (0–360).
+    pub max_hue: f32,
+    /// Smallest saturation (0.0–1.0).
+    pub min_saturation: f32,
+    /// Largest saturation (0.0–1.0).
+    pub max_saturation: f32,
+    /// Smallest lightness (0.0–1.0).
+    pub min_lightness: f32,
+    /// Largest lightness (0.0–1.0).
+    pub max_lightness: f32,
+}
+
+impl Default for ColorRange {
+    fn default() -> Self {
+        Self {
+            min_hue: 0.0,
+            max_hue: 360.0,
+            min_saturation: 0.0,
+            max_saturation: 1.0,
+            min_lightness: 0.0,
+            max_lightness: 1.0,
+        }
+    }
+}
+
+impl ColorRange {
+    /// Creates a range of hues from `min_hue` clockwise to `max_hue`, with any
+    /// saturation and lightness.
+    pub fn hues(min_hue: f32, max_hue: f32) -> Self {
+        Self {
+            min_hue: min_hue.rem_euclid(360.0),
+            max_hue: if max_hue >= 360.0 {
+                360.0
+            } else {
+                max_hue.rem_euclid(360.0)
+            },
+            ..Self::default()
+        }
+    }
+
+    /// Limits saturation to `min..=max`.
+    pub fn with_saturation(mut self, min: f32, max: f32) -> Self {
+        self.min_saturation = min;
+        self.max_saturation = max;
+        self
+    }
+
+    /// Limits lightness to `min..=max`.
+    pub fn with_lightness(mut self, min: f32, max: f32) -> Self {
+        self.min_lightness = min;
+        self.max_lightness = max;
+        self
+    }
+
+    /// Returns `true` if the hue is within the range.
+    pub fn contains_hue(&self, hue: f32) -> bool {
+        let hue = hue.rem_euclid(360.0);
+        if self.min_hue <= self.max_hue {
+            (self.min_hue..=self.max_hue).contains(&hue)
+        } else {
+            hue >= self.min_hue || hue <= self.max_hue
+        }
+    }
+
+    /// Returns `true` if the color is within the range.
+    pub fn contains(&self, hue: f32, saturation: f32, lightness: f32) -> bool {
+        self.contains_hue(hue)
+            && (self.min_saturation..=self.max_saturation).contains(&saturation)
+            && (self.min_lightness..=self.max_lightness).contains(&lightness)
+    }
+
+    /// Returns `settings` moved to the nearest color in the range.
+    ///
+    /// Hues outside the range snap to whichever end is closer around the
+    /// wheel; saturation and lightness are clamped. Disabled settings are
+    /// returned unchanged.
+    pub fn constrain(&self, settings: &HslMutationSettings) -> HslMutationSettings {
+        let mut constrained = settings.clone();
+        if !settings.enabled {
+            return constrained;
+        }
+        if !self.contains_hue(settings.target_hue) {
+            let to_min = hue_distance(settings.target_hue, self.min_hue);
+            let to_max = hue_distance(settings.target_hue, self.max_hue);
+            constrained.target_hue = if to_min <= to_max {
+                self.min_hue
+            } else {
+                self.max_hue
+            };
+        }
+        constrained.target_saturation = settings
+            .target_saturation
+            .clamp(self.min_saturation, self.max_saturation.max(self.min_saturation));
+        constrained.target_lightness = settings
+            .target_lightness
+            .clamp(self.min_lightness, self.max_lightness.max(self.min_lightness));
+        constrained
+    }
+
+    /// Returns the color presets within the range, for a color picker to
+    /// offer.
+    pub fn allowed_colors(&self) -> Vec<FolderColor> {
+        FolderColor::all()
+            .iter()
+            .copied()
+            .filter(|color| {
+                let (hue, sat, light) = color.target_hsl();
+                self.contains(hue, sat, light)
+            })
+            .collect()
+    }
+}
+
+/// Distance between two hues around the wheel, in degrees.
+fn hue_distance(a: f32, b: f32) -> f32 {
+    let distance = (a - b).rem_euclid(360.0);
+    distance.min(360.0 - distance)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_wrapping_hue_range() {
+        let reds = ColorRange::hues(330.0, 30.0);
+        assert!(reds.contains_hue(0.0));
+        assert!(reds.contains_hue(345.0));
+        assert!(!reds.contains_hue(180.0));
+        assert!(ColorRange::default().contains_hue(200.0));
+    }
+
+    #[test]
+    fn test_constrain_snaps_to_nearest_end() {
+        let blues = ColorRange::hues(190.0, 240.0).with_lightness(0.3, 0.6);
+
+        let teal = blues.constrain(&FolderColor::Teal.to_hsl_mutation_settings());
+        assert_eq!(teal.target_hue, 190.0);
+        assert!((teal.target_lightness - 0.3).abs() < 1e-6);
+
+        let purple = blues.constrain(&FolderColor::Purple.to_hsl_mutation_settings());
+        assert_eq!(purple.target_hue, 240.0);
+
+        let blue = blues.constrain(&FolderColor::Blue.to_hsl_mutation_settings());
+        assert_eq!(FolderColor::from_hsl_mutation_settings(&blue), Some(FolderColor::Blue));
+    }
+
+    #[test]
+    fn test_allowed_colors() {
+        let colors = ColorRange::hues(190.0, 240.0).allowed_colors();
+        assert!(colors.contains(&FolderColor::Blue));
+        assert!(!colors.contains(&FolderColor::Red));
+    }
+}
@@ -6,14 +6,18 @@
 
 use crate::convert::convert_icon_set;
 use crate::error::{Error, Result};
+use crate::extract::{self, ExtractionReport, IconSource};
+use crate::migrate::Migrations;
 
 use folco_renderer::IconSet as RendererIconSet;
-use icon_sys::folder_settings::{DefaultFolderIconProvider, PlatformDefaultFolderIconProvider};
 use icon_sys::IconSet as SysIconSet;
 
 use std::fs;
 use std::path::{Path, PathBuf};
 
+/// Upgrade path of the cache manifest.
+static MANIFEST_MIGRATIONS: Migrations = Migrations::new("cache manifest", &[]);
+
 /// Configuration for the icon cache.
 #[derive(Debug, Clone)]
 pub struct CacheConfig {
@@ -60,6 +64,23 @@ impl CacheConfig {
     }
 }
 
+/// Summary of the icon cache's contents.
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CacheInfo {
+    /// The cache directory.
+    pub cache_dir: PathBuf,
+    /// Whether a valid cache exists.
+    pub is_cached: bool,
+    /// Number of files in the cache directory.
+    pub file_count: usize,
+    /// Total size of those files in bytes.
+    pub total_bytes: u64,
+    /// Where the cached icon was extracted from, if the cache records it.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub source: Option<IconSource>,
+}
+
 /// Manages caching of system folder icons.
 ///
 /// The cache stores the default system folder icon to avoid repeatedly
@@ -142,18 +163,25 @@ impl IconCache {
     }
 
     /// Fetches the system folder icon and caches it.
+    ///
+    /// Candidate sources are tried in turn until one yields an icon set that
+    /// passes verification; see [`diagnose`](Self::diagnose).
     fn fetch_and_cache(&self) -> Result<SysIconSet> {
-        self.ensure_cache_dir()?;
+        let (icon_set, source) = extract::extract_folder_icon()?;
+        self.store(&icon_set, source)?;
+        Ok(icon_set)
+    }
 
-        // Dump the default folder icon from the system
-        let provider = PlatformDefaultFolderIconProvider;
-        let icon_set = provider.dump_default_folder_icon()?;
+    /// Writes `icon_set` to the cache, recording where it came from.
+    pub(crate) fn store(&self, icon_set: &SysIconSet, source: IconSource) -> Result<()> {
+        self.ensure_cache_dir()?;
 
         // Cache each image
         let mut manifest = CacheManifest {
-            version: 1,
+            version: MANIFEST_MIGRATIONS.current(),
             icon_count: icon_set.images.len(),
             icons: Vec::new(),
+            source: Some(source),
         };
 
         for (index, image) in icon_set.images.iter().enumerate() {
@@ -175,14 +203,20 @@ impl IconCache {
             .map_err(|e| Error::Serialization(e.to_string()))?;
         fs::write(self.manifest_path(), manifest_json)?;
 
-        Ok(icon_set)
+        Ok(())
     }
 
     /// Loads the icon set from cache.
     fn load_from_cache(&self) -> Result<SysIconSet> {
-        let manifest_content = fs::read_to_string(self.manifest_path())?;
-        let manifest: CacheManifest = serde_json::from_str(&manifest_content)
-            .map_err(|e| Error::Serialization(e.to_string()))?;
+        // The cache can always be rebuilt, so one written by a newer
+        // version is replaced rather than refused
+        let document = match MANIFEST_MIGRATIONS.load_file(&self.manifest_path()) {
+            Ok(Some(document)) => document,
+            Ok(None) | Err(Error::Migration(_)) => return self.fetch_and_cache(),
+            Err(e) => return Err(e),
+        };
+        let manifest: CacheManifest =
+            serde_json::from_value(document).map_err(|e| Error::Serialization(e.to_string()))?;
 
         let mut images = Vec::with_capacity(manifest.icon_count);
 
@@ -200,6 +234,46 @@ impl IconCache {
         Ok(SysIconSet { images })
     }
 
+    /// Tries every source the default folder icon can be extracted from and
+    /// reports how each fared. Nothing is cached.
+    ///
+    /// Useful for support when fetching fails on an unusual Windows install.
+    pub fn diagnose(&self) -> ExtractionReport {
+        extract::diagnose()
+    }
+
+    /// Returns the cache's manifest as written, if there's a cache.
+    pub(crate) fn manifest(&self) -> Option<serde_json::Value> {
+        let content = fs::read_to_string(self.manifest_path()).ok()?;
+        serde_json::from_str(&content).ok()
+    }
+
+    /// Returns a summary of the cache's contents.
+    pub fn info(&self) -> CacheInfo {
+        let (file_count, total_bytes) = fs::read_dir(&self.config.cache_dir)
+            .map(|entries| {
+                entries
+                    .flatten()
+                    .filter_map(|entry| entry.metadata().ok())
+                    .filter(|metadata| metadata.is_file())
+                    .fold((0, 0), |(count, bytes), metadata| (count + 1, bytes + metadata.len()))
+            })
+            .unwrap_or((0, 0));
+
+        let source = fs::read_to_string(self.manifest_path())
+            .ok()
+            .and_then(|content| serde_json::from_str::<CacheManifest>(&content).ok())
+            .and_then(|manifest| manifest.source);
+
+        CacheInfo {
+            cache_dir: self.config.cache_dir.clone(),
+            is_cached: self.is_cached(),
+            file_count,
+            total_bytes,
+            source,
+        }
+    }
+
     /// Clears the cache, forcing a refresh on next access.
     pub fn clear(&self) -> Result<()> {
         if self.config.cache_dir.exists() {
@@ -221,6 +295,9 @@ struct CacheManifest {
     version: u32,
     icon_count: usize,
     icons: Vec<CachedIconInfo>,
+    /// Missing from caches written before sources were recorded.
+    #[serde(default)]
+    source: Option<IconSource>,
 }
 
 /// Information about a cached icon.
@@ -268,4 +345,17 @@ mod tests {
         cache.ensure_cache_dir().unwrap();
         assert!(cache_path.exists());
     }
+
+    #[test]
+    fn test_info_counts_files() {
+        let temp_dir = tempdir().unwrap();
+        let cache = IconCache::new(CacheConfig::new(temp_dir.path()));
+        assert_eq!(cache.info().file_count, 0);
+        assert!(!cache.info().is_cached);
+
+        fs::write(cache.icon_path(16, 0), [0u8; 10]).unwrap();
+        let info = cache.info();
+        assert_eq!(info.file_count, 1);
+        assert_eq!(info.total_bytes, 10);
+    }
 }
@@ -0,0 +1,122 @@
+//! What works on the current platform.
+//!
+//! Not every platform supports every kind of customization, and some of
+//! folco's platform code is still incomplete. [`capabilities`] reports the
+//! state of each feature for the running build, so a UI can hide or
+//! explain a feature up front instead of letting the user run into an
+//! error, or a missing implementation, halfway through a batch.
+
+use crate::wsl::running_in_wsl;
+
+use serde::{Deserialize, Serialize};
+
+/// Whether a feature is available.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "status", rename_all = "kebab-case")]
+pub enum Support {
+    /// The feature works.
+    Supported,
+    /// The feature works with limitations.
+    Partial {
+        /// The limitation, suitable for showing to the user.
+        note: String,
+    },
+    /// The feature doesn't work here.
+    Unsupported {
+        /// Why, suitable for showing to the user.
+        reason: String,
+    },
+}
+
+impl Support {
+    /// Returns `true` unless the feature is unsupported.
+    pub fn is_supported(&self) -> bool {
+        !matches!(self, Support::Unsupported { .. })
+    }
+
+    fn partial(note: &str) -> Self {
+        Support::Partial {
+            note: note.to_string(),
+        }
+    }
+
+    fn unsupported(reason: &str) -> Self {
+        Support::Unsupported {
+            reason: reason.to_string(),
+        }
+    }
+}
+
+/// The support for each feature on the current platform.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Capabilities {
+    /// Setting a custom icon on an individual folder.
+    pub per_folder_icons: Support,
+    /// Replacing the default icon of every folder at once.
+    pub global_override: Support,
+    /// Setting custom icons on drives and volumes.
+    pub volume_icons: Support,
+    /// Tagging folders with the file manager's own tags or labels.
+    pub tags: Support,
+    /// Telling the file manager to redisplay a changed folder.
+    pub refresh: Support,
+    /// Detecting whether a folder already has a custom icon.
+    pub read_back: Support,
+}
+
+impl Capabilities {
+    /// Returns the capabilities of the platform folco was built for.
+    pub fn current() -> Self {
+        let per_folder_icons = if cfg!(target_os = "windows") {
+            Support::Supported
+        } else if running_in_wsl() {
+            Support::unsupported(
+                "the layout of Linux folder icons isn't known yet; \
+                 Windows drives need a Windows-side helper",
+            )
+        } else {
+            Support::unsupported("the layout of this platform's folder icons isn't known yet")
+        };
+        let refresh = if cfg!(target_os = "windows") {
+            Support::Supported
+        } else {
+            Support::partial("the file manager is prompted by touching the folder")
+        };
+
+        Self {
+            per_folder_icons,
+            global_override: Support::unsupported("not implemented"),
+            volume_icons: Support::unsupported("not implemented"),
+            tags: Support::unsupported("not implemented"),
+            refresh,
+            read_back: Support::Supported,
+        }
+    }
+}
+
+/// Returns what works on the current platform.
+pub fn capabilities() -> Capabilities {
+    Capabilities::current()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_capabilities_serialize_with_status() {
+        let json = serde_json::to_value(capabilities()).unwrap();
+        assert_eq!(json["readBack"]["status"], "supported");
+        assert_eq!(json["volumeIcons"]["status"], "unsupported");
+        assert!(json["volumeIcons"]["reason"].is_string());
+    }
+
+    #[test]
+    fn test_per_folder_icons_match_platform() {
+        assert_eq!(
+            capabilities().per_folder_icons.is_supported(),
+            cfg!(target_os = "windows")
+        );
+    }
+}
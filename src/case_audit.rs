@@ -0,0 +1,258 @@
+//! Detection of case-only mismatches between the profile store and disk.
+//!
+//! Syncing folders between case-sensitive and case-insensitive volumes (or
+//! renaming `Projects` to `projects` on a case-insensitive one) leaves store
+//! entries whose spelling no longer matches the folder on disk. On
+//! case-insensitive volumes the entry still works, but the next
+//! customization can record the folder a second time under the new
+//! spelling; on case-sensitive ones the entry looks missing.
+//! [`audit_store_case`] finds both situations and can fix them.
+
+use crate::error::Result;
+use crate::store::ProfileStore;
+
+use serde::{Deserialize, Serialize};
+
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Component, Path, PathBuf};
+
+/// A store entry whose spelling differs from the folder on disk only in case.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CaseMismatch {
+    /// The path as recorded in the store.
+    pub stored: PathBuf,
+    /// The path as spelled on disk.
+    pub actual: PathBuf,
+}
+
+/// Store entries that all name the same folder on disk.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CaseDuplicate {
+    /// The folder as spelled on disk.
+    pub actual: PathBuf,
+    /// The recorded spellings, oldest first.
+    pub stored: Vec<PathBuf>,
+    /// The spelling whose record is kept: the most recently applied one.
+    pub kept: PathBuf,
+}
+
+/// The result of [`audit_store_case`].
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CaseAuditReport {
+    /// Number of store entries checked.
+    pub checked: usize,
+    /// Single entries spelled differently from their folder.
+    pub mismatches: Vec<CaseMismatch>,
+    /// Groups of entries for the same folder.
+    pub duplicates: Vec<CaseDuplicate>,
+    /// Whether the store was updated to match the filesystem.
+    pub fixed: bool,
+}
+
+impl CaseAuditReport {
+    /// Returns `true` if every entry matches its folder's spelling exactly.
+    pub fn is_clean(&self) -> bool {
+        self.mismatches.is_empty() && self.duplicates.is_empty()
+    }
+}
+
+/// Compares the spelling of every store entry with the folder on disk.
+///
+/// Entries whose folder can't be found, even ignoring case, are left to
+/// [`reconcile_store`](crate::reconcile_store). With `fix`, mismatched
+/// entries are renamed to the on-disk spelling and duplicates are collapsed
+/// into their most recently applied record, and the store is saved.
+pub fn audit_store_case(store: &ProfileStore, fix: bool) -> Result<CaseAuditReport> {
+    let entries = store.entries();
+    let mut report = CaseAuditReport {
+        checked: entries.len(),
+        ..Default::default()
+    };
+
+    // Group entries by the folder they resolve to, keeping store order
+    let mut groups: Vec<(PathBuf, Vec<(PathBuf, u64)>)> = Vec::new();
+    let mut index: HashMap<PathBuf, usize> = HashMap::new();
+    for (stored, profile) in entries {
+        let Some(actual) = on_disk_spelling(&stored) else {
+            continue;
+        };
+        let slot = *index.entry(actual.clone()).or_insert_with(|| {
+            groups.push((actual, Vec::new()));
+            groups.len() - 1
+        });
+        groups[slot].1.push((stored, profile.applied_at));
+    }
+
+    for (actual, mut members) in groups {
+        if let [(stored, _)] = members.as_slice() {
+            if *stored != actual {
+                report.mismatches.push(CaseMismatch {
+                    stored: stored.clone(),
+                    actual,
+                });
+            }
+            continue;
+        }
+        // Ties go to the entry later in store order
+        let kept = members
+            .iter()
+            .enumerate()
+            .max_by_key(|(i, (_, applied_at))| (*applied_at, *i))
+            .map(|(_, (stored, _))| stored.clone())
+            .unwrap_or_else(|| actual.clone());
+        members.sort_by_key(|(_, applied_at)| *applied_at);
+        report.duplicates.push(CaseDuplicate {
+            actual,
+            stored: members.into_iter().map(|(stored, _)| stored).collect(),
+            kept,
+        });
+    }
+
+    if fix && !report.is_clean() {
+        for mismatch in &report.mismatches {
+            store.remap(&mismatch.stored, &mismatch.actual);
+        }
+        for duplicate in &report.duplicates {
+            for stored in duplicate.stored.iter().filter(|s| **s != duplicate.kept) {
+                store.remove(stored);
+            }
+            store.remap(&duplicate.kept, &duplicate.actual);
+        }
+        store.save()?;
+        report.fixed = true;
+    }
+
+    Ok(report)
+}
+
+/// Returns `path` as spelled on disk, matching each component ignoring case.
+///
+/// An exact match is preferred. Returns `None` if a component doesn't exist
+/// or is ambiguous, as on case-sensitive volumes holding both `a` and `A`
+/// but not the spelling asked for.
+pub fn on_disk_spelling(path: &Path) -> Option<PathBuf> {
+    let mut actual = PathBuf::new();
+    for component in path.components() {
+        let Component::Normal(name) = component else {
+            actual.push(component.as_os_str());
+            continue;
+        };
+        let exact = actual.join(name);
+        if fs::symlink_metadata(&exact).is_ok() && listed(&actual, name) {
+            actual = exact;
+            continue;
+        }
+
+        let wanted = name.to_string_lossy().to_lowercase();
+        let mut matches = fs::read_dir(&actual)
+            .ok()?
+            .flatten()
+            .map(|entry| entry.file_name())
+            .filter(|candidate| candidate.to_string_lossy().to_lowercase() == wanted);
+        let found = matches.next()?;
+        if matches.next().is_some() {
+            return None;
+        }
+        actual.push(found);
+    }
+    Some(actual)
+}
+
+/// Returns `true` if `dir` lists an entry spelled exactly `name`.
+///
+/// Case-insensitive volumes answer lookups for any spelling, so only the
+/// listing tells the real one.
+fn listed(dir: &Path, name: &std::ffi::OsStr) -> bool {
+    let dir = if dir.as_os_str().is_empty() {
+        Path::new(".")
+    } else {
+        dir
+    };
+    fs::read_dir(dir)
+        .map(|entries| entries.flatten().any(|entry| entry.file_name() == name))
+        // Unlistable directories (such as a root on some systems) are trusted
+        .unwrap_or(true)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use folco_renderer::CustomizationProfile;
+    use tempfile::tempdir;
+
+    #[test]
+    fn test_exact_spelling_is_clean() {
+        let temp = tempdir().unwrap();
+        let folder = temp.path().join("Projects");
+        fs::create_dir(&folder).unwrap();
+
+        let store = ProfileStore::in_data_dir(temp.path()).unwrap();
+        store.insert(&folder, &CustomizationProfile::default());
+
+        let report = audit_store_case(&store, false).unwrap();
+        assert_eq!(report.checked, 1);
+        assert!(report.is_clean());
+    }
+
+    #[test]
+    fn test_case_mismatch_is_fixed() {
+        let temp = tempdir().unwrap();
+        let actual = temp.path().join("projects");
+        let stored = temp.path().join("Projects");
+        fs::create_dir(&actual).unwrap();
+
+        let store = ProfileStore::in_data_dir(temp.path()).unwrap();
+        store.insert(&stored, &CustomizationProfile::default());
+
+        let report = audit_store_case(&store, true).unwrap();
+        assert_eq!(
+            report.mismatches,
+            vec![CaseMismatch {
+                stored: stored.clone(),
+                actual: actual.clone(),
+            }]
+        );
+        assert!(report.fixed);
+        assert_eq!(store.entries()[0].0, actual);
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn test_duplicates_keep_latest() {
+        let temp = tempdir().unwrap();
+        let actual = temp.path().join("projects");
+        fs::create_dir(&actual).unwrap();
+
+        let store = ProfileStore::in_data_dir(temp.path()).unwrap();
+        store.insert(
+            &temp.path().join("PROJECTS"),
+            &CustomizationProfile::default(),
+        );
+        store.insert(
+            &temp.path().join("Projects"),
+            &CustomizationProfile::default(),
+        );
+
+        let report = audit_store_case(&store, true).unwrap();
+        assert_eq!(report.duplicates.len(), 1);
+        assert_eq!(report.duplicates[0].stored.len(), 2);
+        // Applied last, and sorted last if applied in the same second
+        assert_eq!(report.duplicates[0].kept, temp.path().join("Projects"));
+        assert_eq!(store.len(), 1);
+        assert!(store.contains(&actual));
+    }
+
+    #[test]
+    fn test_missing_folder_is_ignored() {
+        let temp = tempdir().unwrap();
+        let store = ProfileStore::in_data_dir(temp.path()).unwrap();
+        store.insert(&temp.path().join("gone"), &CustomizationProfile::default());
+
+        let report = audit_store_case(&store, false).unwrap();
+        assert!(report.is_clean());
+    }
+}
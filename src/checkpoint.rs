@@ -0,0 +1,288 @@
+//! Persisted progress of long-running async batches.
+//!
+//! Every [`checkpoint_interval`](crate::CustomizationContext::checkpoint_interval)
+//! folders, the async batches save a [`ProgressSnapshot`] to the state store
+//! and send [`Progress::Checkpoint`]. A finished batch removes its
+//! snapshot, so the snapshots still there when an app starts belong to
+//! batches that were interrupted.
+//! [`interrupted_batches`](crate::CustomizationContext::interrupted_batches)
+//! lists them, so a GUI that runs the batch again can start its progress
+//! bar at `completed` of `total` rather than at zero.
+
+use crate::error::{Error, Result};
+use crate::log::CoreLog;
+use crate::progress::{Progress, ProgressSender};
+use crate::state::StateStore;
+use crate::store::now_unix_secs;
+use crate::telemetry::OperationKind;
+
+use serde::{Deserialize, Serialize};
+
+use std::collections::BTreeMap;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+
+/// Name of the blob holding the snapshots in the state store.
+pub(crate) const CHECKPOINTS_FILE_NAME: &str = "checkpoints.json";
+
+/// Default [`checkpoint_interval`](crate::CustomizationContext::checkpoint_interval):
+/// a snapshot every 50 folders.
+pub const DEFAULT_CHECKPOINT_INTERVAL: usize = 50;
+
+/// How far a batch got when it last saved its progress.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ProgressSnapshot {
+    /// Identifies the batch, as in [`Progress::Checkpoint::snapshot_id`].
+    pub id: String,
+    /// What the batch does.
+    pub operation: OperationKind,
+    /// Number of unique folders in the batch.
+    pub total: usize,
+    /// Folders finished, whether they succeeded, failed or were skipped.
+    pub completed: usize,
+    /// Folders that succeeded.
+    pub succeeded: usize,
+    /// Folders that failed.
+    pub failed: usize,
+    /// Folders skipped because of the conflict policy.
+    pub skipped: usize,
+    /// When the batch started, in seconds since the Unix epoch.
+    pub started_at: u64,
+    /// When the snapshot was saved, in seconds since the Unix epoch.
+    pub updated_at: u64,
+}
+
+impl ProgressSnapshot {
+    /// Returns the finished share of the batch, from `0.0` to `1.0`.
+    pub fn fraction(&self) -> f64 {
+        if self.total == 0 {
+            return 1.0;
+        }
+        self.completed as f64 / self.total as f64
+    }
+}
+
+/// How one folder of a batch ended.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum FolderOutcome {
+    Succeeded,
+    Failed,
+    Skipped,
+}
+
+impl From<&Result<()>> for FolderOutcome {
+    fn from(result: &Result<()>) -> Self {
+        if result.is_ok() {
+            Self::Succeeded
+        } else {
+            Self::Failed
+        }
+    }
+}
+
+/// Counts the folders of a running batch, saving a snapshot every
+/// `interval` of them.
+pub(crate) struct Checkpoints {
+    state: Arc<dyn StateStore>,
+    log: CoreLog,
+    interval: usize,
+    snapshot: ProgressSnapshot,
+    since_saved: usize,
+    saved: bool,
+}
+
+impl Checkpoints {
+    /// Starts counting a batch of `total` folders. An `interval` of 0 never
+    /// saves a snapshot.
+    pub(crate) fn start(
+        state: Arc<dyn StateStore>,
+        log: CoreLog,
+        interval: usize,
+        operation: OperationKind,
+        total: usize,
+    ) -> Self {
+        // Batches starting within the same millisecond still get their own
+        static SEQUENCE: AtomicU64 = AtomicU64::new(0);
+        let millis = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .map(|elapsed| elapsed.as_millis())
+            .unwrap_or(0);
+        let sequence = SEQUENCE.fetch_add(1, Ordering::Relaxed);
+        let now = now_unix_secs();
+        Self {
+            state,
+            log,
+            interval,
+            snapshot: ProgressSnapshot {
+                id: format!("{operation}-{millis}-{sequence}"),
+                operation,
+                total,
+                completed: 0,
+                succeeded: 0,
+                failed: 0,
+                skipped: 0,
+                started_at: now,
+                updated_at: now,
+            },
+            since_saved: 0,
+            saved: false,
+        }
+    }
+
+    /// Counts a finished folder, returning the checkpoint event to send if
+    /// a snapshot is due.
+    ///
+    /// No snapshot is saved for the last folder, since the batch is about
+    /// to finish.
+    pub(crate) fn record(&mut self, outcome: FolderOutcome) -> Option<Progress> {
+        let snapshot = &mut self.snapshot;
+        snapshot.completed += 1;
+        match outcome {
+            FolderOutcome::Succeeded => snapshot.succeeded += 1,
+            FolderOutcome::Failed => snapshot.failed += 1,
+            FolderOutcome::Skipped => snapshot.skipped += 1,
+        }
+        self.since_saved += 1;
+        if self.interval == 0 || self.since_saved < self.interval {
+            return None;
+        }
+        if self.snapshot.completed >= self.snapshot.total {
+            return None;
+        }
+        self.since_saved = 0;
+        self.snapshot.updated_at = now_unix_secs();
+        if let Err(e) = self.save() {
+            self.log.warn(format_args!("can't save batch progress: {e}"));
+        }
+        Some(Progress::Checkpoint {
+            completed: self.snapshot.completed,
+            snapshot_id: self.snapshot.id.clone(),
+        })
+    }
+
+    /// Counts a finished folder like [`record`](Self::record), sending the
+    /// checkpoint event through `progress`.
+    pub(crate) async fn record_async(&mut self, outcome: FolderOutcome, progress: &ProgressSender) {
+        if let Some(event) = self.record(outcome) {
+            let _ = progress.send(event).await;
+        }
+    }
+
+    /// Removes the batch's snapshot, now that it finished.
+    pub(crate) fn finish(self) {
+        if !self.saved {
+            return;
+        }
+        let removed = update_snapshots(self.state.as_ref(), |snapshots| {
+            snapshots.remove(&self.snapshot.id);
+        });
+        if let Err(e) = removed {
+            self.log.warn(format_args!("can't remove batch progress: {e}"));
+        }
+    }
+
+    fn save(&mut self) -> Result<()> {
+        let snapshot = self.snapshot.clone();
+        update_snapshots(self.state.as_ref(), |snapshots| {
+            snapshots.insert(snapshot.id.clone(), snapshot);
+        })?;
+        self.saved = true;
+        Ok(())
+    }
+}
+
+/// Returns the saved snapshots, oldest batch first.
+pub(crate) fn load_snapshots(state: &dyn StateStore) -> Result<Vec<ProgressSnapshot>> {
+    let mut snapshots: Vec<_> = read_snapshots(state)?.into_values().collect();
+    snapshots.sort_by(|a, b| (a.started_at, &a.id).cmp(&(b.started_at, &b.id)));
+    Ok(snapshots)
+}
+
+/// Removes the snapshot `id`, returning `true` if there was one.
+pub(crate) fn remove_snapshot(state: &dyn StateStore, id: &str) -> Result<bool> {
+    let mut removed = false;
+    update_snapshots(state, |snapshots| removed = snapshots.remove(id).is_some())?;
+    Ok(removed)
+}
+
+fn read_snapshots(state: &dyn StateStore) -> Result<BTreeMap<String, ProgressSnapshot>> {
+    match state.read_blob(CHECKPOINTS_FILE_NAME)? {
+        Some(data) => {
+            serde_json::from_slice(&data).map_err(|e| Error::Serialization(e.to_string()))
+        }
+        None => Ok(BTreeMap::new()),
+    }
+}
+
+fn update_snapshots(
+    state: &dyn StateStore,
+    update: impl FnOnce(&mut BTreeMap<String, ProgressSnapshot>),
+) -> Result<()> {
+    let mut snapshots = read_snapshots(state)?;
+    update(&mut snapshots);
+    if snapshots.is_empty() {
+        return state.remove_blob(CHECKPOINTS_FILE_NAME);
+    }
+    let data =
+        serde_json::to_vec_pretty(&snapshots).map_err(|e| Error::Serialization(e.to_string()))?;
+    state.write_blob(CHECKPOINTS_FILE_NAME, &data)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::state::MemoryStateStore;
+
+    fn start(state: &Arc<dyn StateStore>, interval: usize, total: usize) -> Checkpoints {
+        let state = Arc::clone(state);
+        Checkpoints::start(state, CoreLog::disabled(), interval, OperationKind::Customize, total)
+    }
+
+    #[test]
+    fn test_snapshots_are_saved_every_interval() {
+        let state: Arc<dyn StateStore> = Arc::new(MemoryStateStore::new());
+        let mut checkpoints = start(&state, 2, 4);
+
+        assert!(checkpoints.record(FolderOutcome::Succeeded).is_none());
+        let Some(Progress::Checkpoint {
+            completed,
+            snapshot_id,
+        }) = checkpoints.record(FolderOutcome::Failed)
+        else {
+            panic!("expected a checkpoint");
+        };
+        assert_eq!(completed, 2);
+
+        let snapshots = load_snapshots(state.as_ref()).unwrap();
+        assert_eq!(snapshots.len(), 1);
+        assert_eq!(snapshots[0].id, snapshot_id);
+        assert_eq!((snapshots[0].succeeded, snapshots[0].failed), (1, 1));
+        assert_eq!(snapshots[0].fraction(), 0.5);
+
+        // The last folder finishes the batch rather than saving it
+        assert!(checkpoints.record(FolderOutcome::Skipped).is_none());
+        assert!(checkpoints.record(FolderOutcome::Succeeded).is_none());
+        assert_eq!(load_snapshots(state.as_ref()).unwrap()[0].completed, 2);
+
+        checkpoints.finish();
+        assert!(load_snapshots(state.as_ref()).unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_interrupted_batches_remain() {
+        let state: Arc<dyn StateStore> = Arc::new(MemoryStateStore::new());
+        let mut first = start(&state, 1, 10);
+        first.record(FolderOutcome::Succeeded);
+        let mut second = start(&state, 0, 10);
+        second.record(FolderOutcome::Succeeded);
+        second.finish();
+        // Dropping a batch without finishing it leaves its snapshot
+        drop(first);
+
+        let snapshots = load_snapshots(state.as_ref()).unwrap();
+        assert_eq!(snapshots.len(), 1);
+        assert!(remove_snapshot(state.as_ref(), &snapshots[0].id).unwrap());
+        assert!(!remove_snapshot(state.as_ref(), &snapshots[0].id).unwrap());
+    }
+}
@@ -16,12 +16,22 @@
 //!
 //! The full list of available colors, including their target HSL values,
 //! can be serialized to JSON via [`FolderColor::all_with_metadata`] so that
-//! a frontend can present a color picker.
+//! a frontend can present a color picker. [`Palette::harmonies`] suggests
+//! colors that go together, for coloring a group of folders, and
+//! [`FolderColor::adjusted`] fine-tunes a preset without losing its name.
+//!
+//! Targets can also be expressed in OKLCH, a perceptual color space, with
+//! [`FolderColor::target`]. Adjustments made with
+//! [`FolderColor::adjusted_in`] and [`ColorSpace::Oklch`] lighten or darken
+//! every hue by the same perceived amount, which in HSL they don't. Either
+//! way, the renderer is handed HSL.
 
 use serde::{Deserialize, Serialize};
 
 use folco_renderer::HslMutationSettings;
 
+pub use crate::oklch::Oklch;
+
 /// A named folder color preset.
 ///
 /// Each variant maps to a target HSL color that recolors a standard
@@ -50,6 +60,8 @@ pub enum FolderColor {
     BlueGrey,
     White,
     Black,
+    /// The desktop's accent color, read when the color is applied.
+    SystemAccent,
 }
 
 impl FolderColor {
@@ -77,9 +89,39 @@ impl FolderColor {
             FolderColor::BlueGrey,
             FolderColor::White,
             FolderColor::Black,
+            FolderColor::SystemAccent,
         ]
     }
 
+    /// Machine-readable identifier (kebab-case), as used in JSON and on the
+    /// command line.
+    pub fn id(&self) -> &'static str {
+        match self {
+            FolderColor::Red => "red",
+            FolderColor::Pink => "pink",
+            FolderColor::Purple => "purple",
+            FolderColor::DeepPurple => "deep-purple",
+            FolderColor::Indigo => "indigo",
+            FolderColor::Blue => "blue",
+            FolderColor::LightBlue => "light-blue",
+            FolderColor::Cyan => "cyan",
+            FolderColor::Teal => "teal",
+            FolderColor::Green => "green",
+            FolderColor::LightGreen => "light-green",
+            FolderColor::Lime => "lime",
+            FolderColor::Yellow => "yellow",
+            FolderColor::Amber => "amber",
+            FolderColor::Orange => "orange",
+            FolderColor::DeepOrange => "deep-orange",
+            FolderColor::Brown => "brown",
+            FolderColor::Grey => "grey",
+            FolderColor::BlueGrey => "blue-grey",
+            FolderColor::White => "white",
+            FolderColor::Black => "black",
+            FolderColor::SystemAccent => "system-accent",
+        }
+    }
+
     /// Human-readable display name.
     pub fn display_name(&self) -> &'static str {
         match self {
@@ -104,6 +146,7 @@ impl FolderColor {
             FolderColor::BlueGrey => "Blue Grey",
             FolderColor::White => "White",
             FolderColor::Black => "Black",
+            FolderColor::SystemAccent => "System Accent",
         }
     }
 
@@ -127,6 +170,9 @@ impl FolderColor {
     ///
     /// - Hue is in degrees (0–360).
     /// - Saturation and lightness are fractions (0.0–1.0).
+    ///
+    /// [`SystemAccent`](Self::SystemAccent) resolves to the desktop's accent
+    /// color as last read; see [`crate::refresh_system_accent`].
     pub fn target_hsl(&self) -> (f32, f32, f32) {
         match self {
             //                                    hue       sat      light
@@ -151,6 +197,74 @@ impl FolderColor {
             FolderColor::BlueGrey =>         (199.53,   0.1830,   0.4608),
             FolderColor::White =>            (  0.00,   0.0000,   0.9333),
             FolderColor::Black =>            (  0.00,   0.0000,   0.2588),
+            FolderColor::SystemAccent =>     crate::accent::current_accent_hsl(),
+        }
+    }
+
+    /// Returns the target color in OKLCH.
+    pub fn target_oklch(&self) -> Oklch {
+        let (hue, sat, light) = self.target_hsl();
+        Oklch::from_hsl(hue, sat, light)
+    }
+
+    /// Returns the target color expressed in `space`.
+    pub fn target(&self, space: ColorSpace) -> TargetColor {
+        match space {
+            ColorSpace::Hsl => {
+                let (hue, saturation, lightness) = self.target_hsl();
+                TargetColor::Hsl {
+                    hue,
+                    saturation,
+                    lightness,
+                }
+            }
+            ColorSpace::Oklch => TargetColor::Oklch(self.target_oklch()),
+        }
+    }
+
+    /// Returns the preset whose target color matches the given settings.
+    ///
+    /// This recognizes which preset a stored profile's color came from.
+    /// Returns `None` for disabled settings and custom colors.
+    pub fn from_hsl_mutation_settings(settings: &HslMutationSettings) -> Option<Self> {
+        if !settings.enabled {
+            return None;
+        }
+        Self::all().iter().copied().find(|color| {
+            let (hue, sat, light) = color.target_hsl();
+            (settings.target_hue - hue).abs() < 0.01
+                && (settings.target_saturation - sat).abs() < 0.001
+                && (settings.target_lightness - light).abs() < 0.001
+        })
+    }
+
+    /// Returns this preset nudged lighter or darker and more or less vivid.
+    ///
+    /// Deltas are added to the target lightness and saturation, which stay
+    /// within 0.0–1.0; e.g. `adjusted(-0.1, 0.0)` is a slightly darker
+    /// shade. The result remembers which preset it came from, so it can
+    /// still be shown as "Blue (darker)".
+    pub fn adjusted(&self, lightness_delta: f32, saturation_delta: f32) -> AdjustedColor {
+        self.adjusted_in(ColorSpace::Hsl, lightness_delta, saturation_delta)
+    }
+
+    /// Returns this preset nudged like [`adjusted`](Self::adjusted), with
+    /// the deltas applied in `space`.
+    ///
+    /// In [`ColorSpace::Oklch`], the lightness delta is added to the
+    /// perceived lightness, and the saturation delta scales the chroma,
+    /// e.g. `-0.5` halves it. The hue stays put.
+    pub fn adjusted_in(
+        &self,
+        space: ColorSpace,
+        lightness_delta: f32,
+        saturation_delta: f32,
+    ) -> AdjustedColor {
+        AdjustedColor {
+            base: *self,
+            lightness_delta,
+            saturation_delta,
+            space,
         }
     }
 
@@ -210,6 +324,350 @@ pub struct FolderColorMetadata {
     pub target_lightness: f32,
 }
 
+/// A color space targets can be expressed and adjusted in.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum ColorSpace {
+    /// Hue, saturation and lightness, as the renderer takes them.
+    #[default]
+    Hsl,
+    /// OKLCH, whose lightness matches perceived brightness across hues.
+    Oklch,
+}
+
+/// A target color in either color space.
+///
+/// Serialized with a `space` tag, e.g.
+/// `{"space": "oklch", "lightness": 0.66, "chroma": 0.17, "hue": 248.8}`.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "space", rename_all = "kebab-case")]
+pub enum TargetColor {
+    /// An HSL color, with hue in degrees and the rest as fractions.
+    Hsl {
+        /// Hue in degrees (0–360).
+        hue: f32,
+        /// Saturation (0.0–1.0).
+        saturation: f32,
+        /// Lightness (0.0–1.0).
+        lightness: f32,
+    },
+    /// An OKLCH color.
+    Oklch(Oklch),
+}
+
+impl TargetColor {
+    /// Returns the color space the target is expressed in.
+    pub fn space(&self) -> ColorSpace {
+        match self {
+            TargetColor::Hsl { .. } => ColorSpace::Hsl,
+            TargetColor::Oklch(_) => ColorSpace::Oklch,
+        }
+    }
+
+    /// Returns the target as HSL `(hue, saturation, lightness)`.
+    ///
+    /// OKLCH colors outside sRGB lose chroma until they fit.
+    pub fn to_hsl(&self) -> (f32, f32, f32) {
+        match self {
+            TargetColor::Hsl {
+                hue,
+                saturation,
+                lightness,
+            } => (*hue, *saturation, *lightness),
+            TargetColor::Oklch(color) => color.to_hsl(),
+        }
+    }
+
+    /// Converts the target to HSL mutation settings for the renderer.
+    pub fn to_hsl_mutation_settings(&self) -> HslMutationSettings {
+        let (target_hue, target_saturation, target_lightness) = self.to_hsl();
+        HslMutationSettings {
+            target_hue,
+            target_saturation,
+            target_lightness,
+            enabled: true,
+        }
+    }
+}
+
+impl From<Oklch> for TargetColor {
+    fn from(color: Oklch) -> Self {
+        TargetColor::Oklch(color)
+    }
+}
+
+/// Deltas smaller than this are treated as no adjustment.
+const ADJUSTMENT_EPSILON: f32 = 0.001;
+
+/// Deltas at least this large are described as "much" lighter, darker, etc.
+const LARGE_ADJUSTMENT: f32 = 0.2;
+
+/// A color preset with fine-tuned lightness and saturation.
+///
+/// Created with [`FolderColor::adjusted`].
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AdjustedColor {
+    /// The preset the color is based on.
+    pub base: FolderColor,
+    /// Added to the preset's target lightness.
+    pub lightness_delta: f32,
+    /// Added to the preset's target saturation, or in OKLCH, the relative
+    /// change of its chroma.
+    pub saturation_delta: f32,
+    /// The color space the deltas are applied in.
+    #[serde(default)]
+    pub space: ColorSpace,
+}
+
+impl AdjustedColor {
+    /// Returns the adjusted target `(hue, saturation, lightness)` tuple.
+    pub fn target_hsl(&self) -> (f32, f32, f32) {
+        match self.space {
+            ColorSpace::Hsl => {
+                let (hue, sat, light) = self.base.target_hsl();
+                (
+                    hue,
+                    (sat + self.saturation_delta).clamp(0.0, 1.0),
+                    (light + self.lightness_delta).clamp(0.0, 1.0),
+                )
+            }
+            ColorSpace::Oklch => self.target_oklch().to_hsl(),
+        }
+    }
+
+    /// Returns the adjusted target color in OKLCH.
+    pub fn target_oklch(&self) -> Oklch {
+        match self.space {
+            ColorSpace::Hsl => {
+                let (hue, sat, light) = self.target_hsl();
+                Oklch::from_hsl(hue, sat, light)
+            }
+            ColorSpace::Oklch => {
+                let base = self.base.target_oklch();
+                Oklch::new(
+                    (base.lightness + self.lightness_delta).clamp(0.0, 1.0),
+                    base.chroma * (1.0 + self.saturation_delta).max(0.0),
+                    base.hue,
+                )
+                .in_gamut()
+            }
+        }
+    }
+
+    /// Converts the adjusted color to HSL mutation settings.
+    pub fn to_hsl_mutation_settings(&self) -> HslMutationSettings {
+        let (target_hue, target_saturation, target_lightness) = self.target_hsl();
+        HslMutationSettings {
+            target_hue,
+            target_saturation,
+            target_lightness,
+            enabled: true,
+        }
+    }
+
+    /// Recognizes settings produced by
+    /// [`to_hsl_mutation_settings`](Self::to_hsl_mutation_settings).
+    ///
+    /// Matches on hue, so presets sharing a hue (the neutrals) are ambiguous
+    /// and resolve to the one with the closest lightness. Returns `None` for
+    /// disabled settings and hues that don't belong to a preset. The result
+    /// is always an HSL adjustment; OKLCH adjustments shift the HSL hue
+    /// slightly and usually aren't recognized.
+    pub fn from_hsl_mutation_settings(settings: &HslMutationSettings) -> Option<Self> {
+        if !settings.enabled {
+            return None;
+        }
+        let base = FolderColor::all()
+            .iter()
+            .copied()
+            .filter(|color| (settings.target_hue - color.target_hsl().0).abs() < 0.01)
+            .min_by(|a, b| {
+                let distance =
+                    |c: &FolderColor| (settings.target_lightness - c.target_hsl().2).abs();
+                distance(a).total_cmp(&distance(b))
+            })?;
+        let (_, sat, light) = base.target_hsl();
+        Some(AdjustedColor {
+            base,
+            lightness_delta: settings.target_lightness - light,
+            saturation_delta: settings.target_saturation - sat,
+            space: ColorSpace::Hsl,
+        })
+    }
+
+    /// Returns `true` if the color differs from its preset.
+    pub fn is_adjusted(&self) -> bool {
+        self.lightness_delta.abs() >= ADJUSTMENT_EPSILON
+            || self.saturation_delta.abs() >= ADJUSTMENT_EPSILON
+    }
+
+    /// Returns a human-readable name, such as "Blue (darker)" or
+    /// "Red (much lighter, muted)". Unadjusted colors use the preset's name.
+    pub fn display_name(&self) -> String {
+        let describe = |delta: f32, up: &str, down: &str| {
+            if delta.abs() < ADJUSTMENT_EPSILON {
+                return None;
+            }
+            let word = if delta > 0.0 { up } else { down };
+            Some(if delta.abs() >= LARGE_ADJUSTMENT {
+                format!("much {word}")
+            } else {
+                word.to_string()
+            })
+        };
+        let parts: Vec<String> = [
+            describe(self.lightness_delta, "lighter", "darker"),
+            describe(self.saturation_delta, "more vivid", "muted"),
+        ]
+        .into_iter()
+        .flatten()
+        .collect();
+
+        if parts.is_empty() {
+            self.base.display_name().to_string()
+        } else {
+            format!("{} ({})", self.base.display_name(), parts.join(", "))
+        }
+    }
+
+    /// Returns metadata for the adjusted color, in the same shape as the
+    /// presets' so a frontend can show both the same way.
+    pub fn metadata(&self) -> FolderColorMetadata {
+        let (target_hue, target_saturation, target_lightness) = self.target_hsl();
+        FolderColorMetadata {
+            id: self.base,
+            display_name: self.display_name(),
+            target_hue,
+            target_saturation,
+            target_lightness,
+        }
+    }
+}
+
+impl From<FolderColor> for AdjustedColor {
+    fn from(color: FolderColor) -> Self {
+        color.adjusted(0.0, 0.0)
+    }
+}
+
+impl std::fmt::Display for AdjustedColor {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(&self.display_name())
+    }
+}
+
+/// Saturation below which a preset counts as neutral for harmonies.
+const NEUTRAL_SATURATION: f32 = 0.3;
+
+/// A color harmony: a rule for picking colors that go with a base color.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum Harmony {
+    /// The base and the color opposite it.
+    Complementary,
+    /// The base and its neighbours 30° either side.
+    Analogous,
+    /// Three colors evenly spaced around the wheel.
+    Triadic,
+    /// The base and the two colors either side of its complement.
+    SplitComplementary,
+    /// Four colors evenly spaced around the wheel.
+    Tetradic,
+}
+
+impl Harmony {
+    /// Returns all harmonies, in the order [`Palette::harmonies`] uses.
+    pub fn all() -> &'static [Harmony] {
+        &[
+            Harmony::Complementary,
+            Harmony::Analogous,
+            Harmony::Triadic,
+            Harmony::SplitComplementary,
+            Harmony::Tetradic,
+        ]
+    }
+
+    /// Hue offsets in degrees from the base color, excluding the base.
+    fn offsets(&self) -> &'static [f32] {
+        match self {
+            Harmony::Complementary => &[180.0],
+            Harmony::Analogous => &[-30.0, 30.0],
+            Harmony::Triadic => &[120.0, 240.0],
+            Harmony::SplitComplementary => &[150.0, 210.0],
+            Harmony::Tetradic => &[90.0, 180.0, 270.0],
+        }
+    }
+}
+
+/// A set of coordinated folder colors, such as for a group of sibling
+/// folders.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Palette {
+    /// The harmony the colors were picked with.
+    pub harmony: Harmony,
+    /// The colors, base first. Never contains duplicates.
+    pub colors: Vec<FolderColor>,
+}
+
+impl Palette {
+    /// Returns a palette for every [`Harmony`] of `base`.
+    ///
+    /// Neutral bases such as grey, white and black have no hue to build a
+    /// harmony from, so they get no palettes.
+    pub fn harmonies(base: FolderColor) -> Vec<Palette> {
+        if is_neutral(base) {
+            return Vec::new();
+        }
+        Harmony::all()
+            .iter()
+            .map(|harmony| Self::harmony(base, *harmony))
+            .collect()
+    }
+
+    /// Returns the palette of one harmony of `base`.
+    ///
+    /// Each target hue is snapped to the closest unused preset, preferring
+    /// ones with a lightness like the base's, so the palette only contains
+    /// named colors. A neutral base yields a palette of just the base.
+    pub fn harmony(base: FolderColor, harmony: Harmony) -> Palette {
+        let mut colors = vec![base];
+        if !is_neutral(base) {
+            let (hue, _, lightness) = base.target_hsl();
+            for offset in harmony.offsets() {
+                let target = (hue + offset).rem_euclid(360.0);
+                if let Some(color) = closest_preset(target, lightness, &colors) {
+                    colors.push(color);
+                }
+            }
+        }
+        Palette { harmony, colors }
+    }
+}
+
+/// Returns `true` for presets too unsaturated to have a meaningful hue.
+pub(crate) fn is_neutral(color: FolderColor) -> bool {
+    color.target_hsl().1 < NEUTRAL_SATURATION
+}
+
+/// Returns the chromatic preset closest to `hue`, skipping `used` and the
+/// system accent, whose hue isn't fixed.
+fn closest_preset(hue: f32, lightness: f32, used: &[FolderColor]) -> Option<FolderColor> {
+    let score = |color: &FolderColor| {
+        let (h, _, l) = color.target_hsl();
+        let distance = (h - hue).abs();
+        distance.min(360.0 - distance) + 60.0 * (l - lightness).abs()
+    };
+    FolderColor::all()
+        .iter()
+        .filter(|color| {
+            **color != FolderColor::SystemAccent && !is_neutral(**color) && !used.contains(color)
+        })
+        .min_by(|a, b| score(a).total_cmp(&score(b)))
+        .copied()
+}
+
 #[cfg(feature = "clap")]
 fn hsl_to_rgb(h: f32, s: f32, l: f32) -> (u8, u8, u8) {
     use palette::{FromColor, Hsl, Srgb};
@@ -231,29 +689,7 @@ impl clap::ValueEnum for FolderColor {
     }
 
     fn to_possible_value(&self) -> Option<clap::builder::PossibleValue> {
-        let name = match self {
-            FolderColor::Red => "red",
-            FolderColor::Pink => "pink",
-            FolderColor::Purple => "purple",
-            FolderColor::DeepPurple => "deep-purple",
-            FolderColor::Indigo => "indigo",
-            FolderColor::Blue => "blue",
-            FolderColor::LightBlue => "light-blue",
-            FolderColor::Cyan => "cyan",
-            FolderColor::Teal => "teal",
-            FolderColor::Green => "green",
-            FolderColor::LightGreen => "light-green",
-            FolderColor::Lime => "lime",
-            FolderColor::Yellow => "yellow",
-            FolderColor::Amber => "amber",
-            FolderColor::Orange => "orange",
-            FolderColor::DeepOrange => "deep-orange",
-            FolderColor::Brown => "brown",
-            FolderColor::Grey => "grey",
-            FolderColor::BlueGrey => "blue-grey",
-            FolderColor::White => "white",
-            FolderColor::Black => "black",
-        };
+        let name = self.id();
 
         let (h, s, l) = self.target_hsl();
         let (r, g, b) = hsl_to_rgb(h, s, l);
@@ -298,6 +734,7 @@ impl std::str::FromStr for FolderColor {
             "bluegrey" | "bluegray" => Ok(FolderColor::BlueGrey),
             "white" => Ok(FolderColor::White),
             "black" => Ok(FolderColor::Black),
+            "systemaccent" | "accent" => Ok(FolderColor::SystemAccent),
             _ => Err(format!("Unknown folder color: '{}'", s)),
         }
     }
@@ -365,4 +802,116 @@ mod tests {
         assert!((settings.target_saturation - 0.8962).abs() < 0.001);
         assert!((settings.target_lightness - 0.5843).abs() < 0.001);
     }
+
+    #[test]
+    fn id_matches_serde() {
+        for color in FolderColor::all() {
+            let json = serde_json::to_value(color).unwrap();
+            assert_eq!(json.as_str(), Some(color.id()));
+            assert_eq!(color.id().parse::<FolderColor>().unwrap(), *color);
+        }
+    }
+
+    #[test]
+    fn from_hsl_mutation_settings_roundtrip() {
+        for color in FolderColor::all() {
+            let settings = color.to_hsl_mutation_settings();
+            assert_eq!(FolderColor::from_hsl_mutation_settings(&settings), Some(*color));
+        }
+
+        let mut custom = FolderColor::Red.to_hsl_mutation_settings();
+        custom.target_hue = 100.0;
+        assert_eq!(FolderColor::from_hsl_mutation_settings(&custom), None);
+
+        let mut disabled = FolderColor::Red.to_hsl_mutation_settings();
+        disabled.enabled = false;
+        assert_eq!(FolderColor::from_hsl_mutation_settings(&disabled), None);
+    }
+
+    #[test]
+    fn adjusted_colors() {
+        let darker = FolderColor::Blue.adjusted(-0.1, 0.0);
+        assert_eq!(darker.display_name(), "Blue (darker)");
+        let (_, _, light) = darker.target_hsl();
+        assert!((light - (0.5412 - 0.1)).abs() < 0.001);
+
+        let washed = FolderColor::Red.adjusted(0.3, -0.05);
+        assert_eq!(washed.display_name(), "Red (much lighter, muted)");
+        assert_eq!(FolderColor::White.adjusted(0.5, 0.0).target_hsl().2, 1.0);
+
+        let plain = AdjustedColor::from(FolderColor::Teal);
+        assert!(!plain.is_adjusted());
+        assert_eq!(plain.display_name(), "Teal");
+    }
+
+    #[test]
+    fn adjusted_settings_roundtrip() {
+        let adjusted = FolderColor::Indigo.adjusted(0.12, -0.2);
+        let settings = adjusted.to_hsl_mutation_settings();
+        let parsed = AdjustedColor::from_hsl_mutation_settings(&settings).unwrap();
+        assert_eq!(parsed.base, FolderColor::Indigo);
+        assert!((parsed.lightness_delta - 0.12).abs() < 0.001);
+        assert!((parsed.saturation_delta + 0.2).abs() < 0.001);
+        assert_eq!(FolderColor::from_hsl_mutation_settings(&settings), None);
+    }
+
+    #[test]
+    fn harmonies_are_named_and_distinct() {
+        for color in FolderColor::all() {
+            for palette in Palette::harmonies(*color) {
+                assert_eq!(palette.colors[0], *color);
+                assert_eq!(palette.colors.len(), palette.harmony.offsets().len() + 1);
+                for (i, c) in palette.colors.iter().enumerate() {
+                    assert!(!palette.colors[..i].contains(c), "{palette:?}");
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn complementary_is_opposite() {
+        let palette = Palette::harmony(FolderColor::Blue, Harmony::Complementary);
+        let (hue, _, _) = palette.colors[1].target_hsl();
+        let distance = (hue - FolderColor::Blue.target_hsl().0).abs();
+        assert!((150.0..=210.0).contains(&distance), "{palette:?}");
+    }
+
+    #[test]
+    fn targets_convert_between_spaces() {
+        for color in FolderColor::all() {
+            let (hue, sat, light) = color.target(ColorSpace::Oklch).to_hsl();
+            let (h, s, l) = color.target_hsl();
+            assert!((light - l).abs() < 0.002, "{color:?}");
+            assert!((sat - s).abs() < 0.005, "{color:?}");
+            if s > 0.01 {
+                let distance = (hue - h).abs();
+                assert!(distance.min(360.0 - distance) < 0.5, "{color:?}");
+            }
+        }
+
+        let json = serde_json::to_value(FolderColor::Blue.target(ColorSpace::Oklch)).unwrap();
+        assert_eq!(json["space"], "oklch");
+        assert!(json["chroma"].as_f64().unwrap() > 0.1);
+    }
+
+    #[test]
+    fn oklch_adjustments_are_perceptually_even() {
+        for color in [FolderColor::Yellow, FolderColor::Blue] {
+            let darker = color.adjusted_in(ColorSpace::Oklch, -0.1, 0.0);
+            let step = color.target_oklch().lightness - darker.target_oklch().lightness;
+            assert!((step - 0.1).abs() < 0.002, "{color:?}");
+            let hue_shift = (darker.target_oklch().hue - color.target_oklch().hue).abs();
+            assert!(hue_shift < 0.5, "{color:?}");
+        }
+        assert_eq!(FolderColor::Red.adjusted(0.1, 0.0).space, ColorSpace::Hsl);
+    }
+
+    #[test]
+    fn neutral_colors_have_no_harmonies() {
+        assert!(Palette::harmonies(FolderColor::Grey).is_empty());
+        assert_eq!(
+            Palette::harmony(FolderColor::Black, Harmony::Triadic).colors,
+            vec![FolderColor::Black]
+        );
+    }
 }
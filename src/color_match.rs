@@ -0,0 +1,171 @@
+//! Predicting how closely a target color can be matched on the base icon.
+//!
+//! The renderer recolors by shifting each surface pixel by the difference
+//! between the target and the nominal surface color, and clamps what falls
+//! outside HSL's range. Shading pushed past white or black is lost, and a
+//! base icon darker than the nominal surface stays darker than the target,
+//! so some colors can't be matched exactly. [`ColorMatch`] runs the same
+//! shift over the base icon's surface and reports the color it averages out
+//! to, and how far that is from the request, so a GUI can warn before the
+//! user applies it.
+
+use crate::color::{Oklch, TargetColor};
+use crate::oklch::{hsl_to_srgb, srgb_to_hsl, to_linear};
+
+use icon_sys::IconSet as SysIconSet;
+use serde::{Deserialize, Serialize};
+
+/// Delta E below which an achieved color counts as matching the request.
+pub const MATCH_TOLERANCE: f32 = 2.0;
+
+/// How far a base pixel's hue may be from the nominal surface hue for the
+/// pixel to count as part of the surface, in degrees.
+const SURFACE_HUE_TOLERANCE: f32 = 25.0;
+
+/// Minimum saturation of a surface pixel; greyer pixels are outlines and
+/// shading art the recolor barely moves.
+const SURFACE_MIN_SATURATION: f32 = 0.35;
+
+/// The predicted outcome of recoloring the base icon to a target color.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ColorMatch {
+    /// The requested color.
+    pub requested: Oklch,
+    /// The color the icon's surface is predicted to average out to.
+    pub achieved: Oklch,
+    /// Perceptual distance between the two, as by [`Oklch::delta_e`].
+    pub delta_e: f32,
+}
+
+impl ColorMatch {
+    /// Returns `true` if the achieved color is within [`MATCH_TOLERANCE`]
+    /// of the request.
+    pub fn is_match(&self) -> bool {
+        self.delta_e < MATCH_TOLERANCE
+    }
+}
+
+/// Predicts the outcome of recoloring `base` to `target`, with deltas
+/// computed from the nominal `surface` color as `(hue, saturation,
+/// lightness)`.
+///
+/// Uses the largest image of `base`. Returns `None` if it has no pixels
+/// close enough to the surface color to recolor.
+pub(crate) fn predict_color_match(
+    base: &SysIconSet,
+    surface: (f32, f32, f32),
+    target: &TargetColor,
+) -> Option<ColorMatch> {
+    let image = base
+        .images
+        .iter()
+        .max_by_key(|image| image.data.width())?
+        .data
+        .to_rgba8();
+    let (target_hue, target_saturation, target_lightness) = target.to_hsl();
+    let (surface_hue, surface_saturation, surface_lightness) = surface;
+
+    let mut sum = [0.0f32; 3];
+    let mut weight = 0.0f32;
+    for pixel in image.pixels() {
+        let [r, g, b, a] = pixel.0;
+        if a < 128 {
+            continue;
+        }
+        let (hue, saturation, lightness) = srgb_to_hsl([r, g, b].map(|c| f32::from(c) / 255.0));
+        let distance = (hue - surface_hue).abs();
+        if distance.min(360.0 - distance) > SURFACE_HUE_TOLERANCE
+            || saturation < SURFACE_MIN_SATURATION
+        {
+            continue;
+        }
+
+        let shifted = hsl_to_srgb(
+            hue + target_hue - surface_hue,
+            (saturation + target_saturation - surface_saturation).clamp(0.0, 1.0),
+            (lightness + target_lightness - surface_lightness).clamp(0.0, 1.0),
+        );
+        let alpha = f32::from(a) / 255.0;
+        for (total, channel) in sum.iter_mut().zip(shifted) {
+            *total += to_linear(channel) * alpha;
+        }
+        weight += alpha;
+    }
+    if weight == 0.0 {
+        return None;
+    }
+
+    let requested = match target {
+        TargetColor::Oklch(color) => *color,
+        TargetColor::Hsl { .. } => Oklch::from_hsl(target_hue, target_saturation, target_lightness),
+    };
+    let achieved = Oklch::from_linear_srgb(sum.map(|total| total / weight));
+    Some(ColorMatch {
+        requested,
+        achieved,
+        delta_e: requested.delta_e(&achieved),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::color::{ColorSpace, FolderColor};
+    use image::{DynamicImage, Rgba, RgbaImage};
+
+    const SURFACE: (f32, f32, f32) = (44.0, 1.0, 0.72);
+
+    fn base(pixels: &[[u8; 3]]) -> SysIconSet {
+        let mut image = RgbaImage::new(pixels.len() as u32, 1);
+        for (x, [r, g, b]) in pixels.iter().enumerate() {
+            image.put_pixel(x as u32, 0, Rgba([*r, *g, *b, 255]));
+        }
+        SysIconSet {
+            images: vec![icon_sys::IconImage {
+                data: DynamicImage::ImageRgba8(image),
+            }],
+        }
+    }
+
+    /// The surface color HSL(44°, 100%, 72%) in sRGB.
+    const FLAT_SURFACE: [u8; 3] = [255, 217, 112];
+
+    #[test]
+    fn test_presets_round_trip_on_a_flat_surface() {
+        let base = base(&[FLAT_SURFACE; 4]);
+        for color in FolderColor::all() {
+            for space in [ColorSpace::Hsl, ColorSpace::Oklch] {
+                let prediction = predict_color_match(&base, SURFACE, &color.target(space)).unwrap();
+                assert!(prediction.is_match(), "{color:?} {space:?}: {prediction:?}");
+            }
+        }
+    }
+
+    #[test]
+    fn test_dark_base_misses_light_targets() {
+        // The surface color at half its lightness
+        let dark = base(&[[184, 135, 0]; 4]);
+        let target = FolderColor::White.target(ColorSpace::Hsl);
+        let prediction = predict_color_match(&dark, SURFACE, &target).unwrap();
+        assert!(!prediction.is_match());
+        assert!(prediction.achieved.lightness < prediction.requested.lightness);
+    }
+
+    #[test]
+    fn test_clamped_highlights_miss_the_target() {
+        // Highlights brighter than the surface clip when shifted to white
+        let shaded = base(&[FLAT_SURFACE, [255, 240, 204], [255, 240, 204]]);
+        let target = FolderColor::White.target(ColorSpace::Hsl);
+        let prediction = predict_color_match(&shaded, SURFACE, &target).unwrap();
+        assert!(!prediction.is_match());
+        assert!(prediction.achieved.lightness > prediction.requested.lightness);
+    }
+
+    #[test]
+    fn test_no_surface_pixels() {
+        let grey = base(&[[128, 128, 128]]);
+        let target = FolderColor::Blue.target(ColorSpace::Hsl);
+        assert!(predict_color_match(&grey, SURFACE, &target).is_none());
+    }
+}
@@ -0,0 +1,234 @@
+//! Conditions that restrict a rule to folders in a certain state.
+//!
+//! A [`Rule`](crate::Rule) matches on a folder's name; its conditions look at
+//! the folder itself, e.g. "empty folders" or "repositories with uncommitted
+//! changes". Conditions are checked when the rule is evaluated, so they
+//! reflect the folder as it is at apply time.
+
+use serde::{Deserialize, Serialize};
+
+use std::fs;
+use std::path::Path;
+use std::process::{Command, Stdio};
+use std::time::{Duration, SystemTime};
+
+const SECONDS_PER_DAY: u64 = 24 * 60 * 60;
+
+/// A folder's state in git.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum GitState {
+    /// The folder is the root of a git working tree.
+    Repository,
+    /// The folder isn't the root of a git working tree.
+    NotRepository,
+    /// The folder is a repository with no uncommitted or untracked changes.
+    Clean,
+    /// The folder is a repository with uncommitted or untracked changes.
+    Dirty,
+}
+
+/// Something that must be true of a folder for a rule to match it.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum RuleCondition {
+    /// The folder was last modified at least this many days ago.
+    OlderThanDays(u64),
+    /// The folder was last modified less than this many days ago.
+    NewerThanDays(u64),
+    /// Everything in the folder adds up to more than this many bytes.
+    LargerThan(u64),
+    /// Everything in the folder adds up to at most this many bytes.
+    SmallerThan(u64),
+    /// The folder is empty (`true`) or has something in it (`false`).
+    Empty(bool),
+    /// The folder is owned by this user, given as a name or numeric ID.
+    ///
+    /// Only supported on Unix; elsewhere the condition never holds.
+    Owner(String),
+    /// The folder is in this state in git. Requires `git` on the `PATH`.
+    Git(GitState),
+}
+
+impl RuleCondition {
+    /// Returns `true` if the condition holds for `folder`.
+    ///
+    /// Conditions that can't be checked, e.g. because the folder can't be
+    /// read, don't hold.
+    pub fn check(&self, folder: &Path) -> bool {
+        match self {
+            Self::OlderThanDays(days) => {
+                age(folder).is_some_and(|age| age >= days_to_duration(*days))
+            }
+            Self::NewerThanDays(days) => {
+                age(folder).is_some_and(|age| age < days_to_duration(*days))
+            }
+            Self::LargerThan(bytes) => folder_size(folder).is_some_and(|size| size > *bytes),
+            Self::SmallerThan(bytes) => folder_size(folder).is_some_and(|size| size <= *bytes),
+            Self::Empty(empty) => {
+                fs::read_dir(folder).is_ok_and(|mut entries| entries.next().is_none() == *empty)
+            }
+            Self::Owner(owner) => is_owned_by(folder, owner),
+            Self::Git(state) => git_state(folder).is_some_and(|actual| match state {
+                GitState::NotRepository => actual == GitState::NotRepository,
+                GitState::Repository => actual != GitState::NotRepository,
+                GitState::Clean | GitState::Dirty => actual == *state,
+            }),
+        }
+    }
+}
+
+fn days_to_duration(days: u64) -> Duration {
+    Duration::from_secs(days.saturating_mul(SECONDS_PER_DAY))
+}
+
+/// Time since the folder was last modified.
+fn age(folder: &Path) -> Option<Duration> {
+    let modified = fs::metadata(folder).ok()?.modified().ok()?;
+    // Modification times in the future count as brand new
+    Some(SystemTime::now().duration_since(modified).unwrap_or_default())
+}
+
+/// Total size of the files below `folder`. Symlinks are not followed.
+fn folder_size(folder: &Path) -> Option<u64> {
+    let mut total = 0u64;
+    let mut pending = vec![folder.to_path_buf()];
+    while let Some(dir) = pending.pop() {
+        for entry in fs::read_dir(&dir).ok()?.flatten() {
+            let Ok(metadata) = entry.metadata() else {
+                continue;
+            };
+            if metadata.is_dir() {
+                pending.push(entry.path());
+            } else if metadata.is_file() {
+                total = total.saturating_add(metadata.len());
+            }
+        }
+    }
+    Some(total)
+}
+
+#[cfg(unix)]
+fn is_owned_by(folder: &Path, owner: &str) -> bool {
+    use std::os::unix::fs::MetadataExt;
+
+    let Ok(metadata) = fs::metadata(folder) else {
+        return false;
+    };
+    let uid = metadata.uid();
+    if owner.parse::<u32>().is_ok_and(|id| id == uid) {
+        return true;
+    }
+    user_id(owner).is_some_and(|id| id == uid)
+}
+
+#[cfg(not(unix))]
+fn is_owned_by(_folder: &Path, _owner: &str) -> bool {
+    false
+}
+
+/// Looks up a user's ID in `/etc/passwd`.
+#[cfg(unix)]
+fn user_id(name: &str) -> Option<u32> {
+    let passwd = fs::read_to_string("/etc/passwd").ok()?;
+    passwd.lines().find_map(|line| {
+        let mut fields = line.split(':');
+        if fields.next()? != name {
+            return None;
+        }
+        fields.nth(1)?.parse().ok()
+    })
+}
+
+/// Asks `git` for the state of a folder. Returns `None` if git isn't
+/// available.
+fn git_state(folder: &Path) -> Option<GitState> {
+    // Only the root of a working tree counts, not every folder inside one
+    if !folder.join(".git").exists() {
+        return Some(GitState::NotRepository);
+    }
+    let output = Command::new("git")
+        .arg("-C")
+        .arg(folder)
+        .args(["status", "--porcelain"])
+        .stdin(Stdio::null())
+        .stderr(Stdio::null())
+        .output()
+        .ok()?;
+    if !output.status.success() {
+        return Some(GitState::NotRepository);
+    }
+    Some(if output.stdout.is_empty() {
+        GitState::Clean
+    } else {
+        GitState::Dirty
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    #[test]
+    fn test_empty_and_size() {
+        let temp = tempdir().unwrap();
+        assert!(RuleCondition::Empty(true).check(temp.path()));
+
+        fs::create_dir(temp.path().join("sub")).unwrap();
+        fs::write(temp.path().join("sub").join("data.bin"), [0u8; 100]).unwrap();
+        assert!(RuleCondition::Empty(false).check(temp.path()));
+        assert!(RuleCondition::LargerThan(99).check(temp.path()));
+        assert!(RuleCondition::SmallerThan(100).check(temp.path()));
+        assert!(!RuleCondition::LargerThan(100).check(temp.path()));
+    }
+
+    #[test]
+    fn test_age() {
+        let temp = tempdir().unwrap();
+        assert!(RuleCondition::NewerThanDays(1).check(temp.path()));
+        assert!(!RuleCondition::OlderThanDays(1).check(temp.path()));
+        assert!(RuleCondition::OlderThanDays(0).check(temp.path()));
+    }
+
+    #[test]
+    fn test_missing_folder_fails_conditions() {
+        let temp = tempdir().unwrap();
+        let missing = temp.path().join("missing");
+        assert!(!RuleCondition::Empty(true).check(&missing));
+        assert!(!RuleCondition::SmallerThan(u64::MAX).check(&missing));
+        assert!(!RuleCondition::NewerThanDays(1).check(&missing));
+    }
+
+    #[test]
+    fn test_not_repository() {
+        let temp = tempdir().unwrap();
+        assert!(RuleCondition::Git(GitState::NotRepository).check(temp.path()));
+        assert!(!RuleCondition::Git(GitState::Repository).check(temp.path()));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_owner_by_id() {
+        use std::os::unix::fs::MetadataExt;
+
+        let temp = tempdir().unwrap();
+        let uid = fs::metadata(temp.path()).unwrap().uid();
+        assert!(RuleCondition::Owner(uid.to_string()).check(temp.path()));
+        assert!(!RuleCondition::Owner((uid + 1).to_string()).check(temp.path()));
+    }
+
+    #[test]
+    fn test_serde_shape() {
+        let json = r#"[{"empty": true}, {"git": "dirty"}, {"older-than-days": 30}]"#;
+        let conditions: Vec<RuleCondition> = serde_json::from_str(json).unwrap();
+        assert_eq!(
+            conditions,
+            vec![
+                RuleCondition::Empty(true),
+                RuleCondition::Git(GitState::Dirty),
+                RuleCondition::OlderThanDays(30),
+            ]
+        );
+    }
+}
@@ -0,0 +1,186 @@
+//! Persistent app configuration.
+//!
+//! [`AppConfig`] holds user settings that outlive a single context, such as
+//! the library roots, the default preset, the rules and any organization
+//! branding. It's stored as JSON, by default in the app data directory,
+//! as a blob of the context's [`StateStore`].
+
+use crate::branding::Branding;
+use crate::curve::LightnessCurve;
+use crate::error::{Error, Result};
+use crate::hooks::CommandHook;
+use crate::library::Library;
+use crate::migrate::Migrations;
+use crate::palettes::UserPalette;
+use crate::rules::RuleSet;
+use crate::sharpen::SharpenOptions;
+use crate::state::StateStore;
+use crate::store::write_atomic;
+
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use std::path::Path;
+
+/// File name of the app config inside the app data directory.
+pub(crate) const CONFIG_FILE_NAME: &str = "config.json";
+
+/// Upgrade path of the config file.
+static CONFIG_MIGRATIONS: Migrations = Migrations::new("config", &[]);
+
+/// User settings persisted across sessions.
+///
+/// Unknown fields are ignored and missing fields take their defaults, so
+/// configs written by older or newer versions still load. Only a change
+/// that older versions can't read bumps the config's format version.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AppConfig {
+    /// The user's library roots.
+    #[serde(default)]
+    pub library: Library,
+    /// Name of the preset applied by
+    /// [`quick_apply`](crate::CustomizationContext::quick_apply).
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub default_preset: Option<String>,
+    /// Rules for customizing folders by name.
+    #[serde(default)]
+    pub rules: RuleSet,
+    /// Organization branding merged into every applied profile.
+    #[serde(default, skip_serializing_if = "Branding::is_empty")]
+    pub branding: Branding,
+    /// Glyphs, such as emoji, offered first when picking a decal.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub glyphs: Vec<String>,
+    /// Palettes the user imported, such as brand color books.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub palettes: Vec<UserPalette>,
+    /// Sharpening of small sizes after rendering, if turned on.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub small_icon_sharpening: Option<SharpenOptions>,
+    /// Lightness curve for grey, white and black colors, unless the applied
+    /// preset has its own.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub lightness_curve: Option<LightnessCurve>,
+    /// Commands run after each folder is customized or reset.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub command_hooks: Vec<CommandHook>,
+    /// Version of folco-core that last initialized the app data.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub last_version: Option<String>,
+}
+
+impl AppConfig {
+    /// Loads the config at `path`, or returns the defaults if it doesn't exist.
+    ///
+    /// A config written by an older version is upgraded in place, keeping
+    /// the old file as a backup.
+    pub fn load(path: &Path) -> Result<Self> {
+        Self::from_document(CONFIG_MIGRATIONS.load_file(path)?)
+    }
+
+    /// Loads the config from the given app data directory.
+    pub fn in_data_dir(data_dir: &Path) -> Result<Self> {
+        Self::load(&data_dir.join(CONFIG_FILE_NAME))
+    }
+
+    /// Loads the config kept by `state`, or returns the defaults if there
+    /// is none.
+    ///
+    /// With [`JsonStateStore`](crate::JsonStateStore), this reads the
+    /// `config.json` file in its directory.
+    pub fn load_from(state: &dyn StateStore) -> Result<Self> {
+        Self::from_document(CONFIG_MIGRATIONS.load_blob(state, CONFIG_FILE_NAME)?)
+    }
+
+    fn from_document(document: Option<Value>) -> Result<Self> {
+        match document {
+            Some(document) => {
+                serde_json::from_value(document).map_err(|e| Error::Serialization(e.to_string()))
+            }
+            None => Ok(Self::default()),
+        }
+    }
+
+    /// Writes the config to `path`, replacing any previous version atomically.
+    pub fn save(&self, path: &Path) -> Result<()> {
+        write_atomic(path, self.to_json()?)
+    }
+
+    /// Writes the config to `state`, replacing any previous version.
+    pub fn save_to(&self, state: &dyn StateStore) -> Result<()> {
+        state.write_blob(CONFIG_FILE_NAME, self.to_json()?.as_bytes())
+    }
+
+    fn to_json(&self) -> Result<String> {
+        let mut document =
+            serde_json::to_value(self).map_err(|e| Error::Serialization(e.to_string()))?;
+        document["version"] = Value::from(CONFIG_MIGRATIONS.current());
+        serde_json::to_string_pretty(&document).map_err(|e| Error::Serialization(e.to_string()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::state::JsonStateStore;
+    use std::fs;
+    use tempfile::tempdir;
+
+    #[test]
+    fn test_missing_config_is_default() {
+        let temp = tempdir().unwrap();
+        let config = AppConfig::in_data_dir(temp.path()).unwrap();
+        assert_eq!(config, AppConfig::default());
+    }
+
+    #[test]
+    fn test_save_and_load() {
+        let temp = tempdir().unwrap();
+        let path = temp.path().join(CONFIG_FILE_NAME);
+
+        let mut config = AppConfig {
+            default_preset: Some("Blue".to_string()),
+            ..Default::default()
+        };
+        config.library.add_root("Data", temp.path()).unwrap();
+        config.save(&path).unwrap();
+
+        assert_eq!(AppConfig::load(&path).unwrap(), config);
+    }
+
+    #[test]
+    fn test_unknown_fields_are_ignored() {
+        let temp = tempdir().unwrap();
+        let path = temp.path().join(CONFIG_FILE_NAME);
+        fs::write(&path, r#"{"somethingNew": true}"#).unwrap();
+
+        assert!(AppConfig::load(&path).unwrap().library.is_empty());
+    }
+
+    #[test]
+    fn test_newer_format_version_is_refused() {
+        let temp = tempdir().unwrap();
+        let path = temp.path().join(CONFIG_FILE_NAME);
+        AppConfig::default().save(&path).unwrap();
+        assert!(fs::read_to_string(&path).unwrap().contains("\"version\": 1"));
+
+        fs::write(&path, r#"{"version": 99}"#).unwrap();
+        assert!(matches!(AppConfig::load(&path), Err(Error::Migration(_))));
+    }
+
+    #[test]
+    fn test_state_store_blob_is_the_config_file() {
+        let temp = tempdir().unwrap();
+        let state = JsonStateStore::new(temp.path());
+        assert_eq!(AppConfig::load_from(&state).unwrap(), AppConfig::default());
+
+        let config = AppConfig {
+            default_preset: Some("Blue".to_string()),
+            ..Default::default()
+        };
+        config.save_to(&state).unwrap();
+        assert_eq!(AppConfig::in_data_dir(temp.path()).unwrap(), config);
+        assert_eq!(AppConfig::load_from(&state).unwrap(), config);
+    }
+}
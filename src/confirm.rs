@@ -0,0 +1,135 @@
+//! Confirmation of destructive operations.
+//!
+//! A context asks its [`ConfirmationHandler`] before it does something
+//! that's hard to undo: a batch of more folders than its
+//! [confirmation threshold](crate::CustomizationContext::confirm_threshold),
+//! or resetting every customized folder and deleting the app data when
+//! uninstalling. folco-cli prompts on the terminal and a GUI shows a
+//! dialog, while scripts keep the default [`AutoConfirm`]. An operation
+//! that isn't confirmed fails with [`Error::Cancelled`] before anything
+//! changed.
+
+use crate::error::{Error, Result};
+use crate::telemetry::OperationKind;
+
+use serde::{Deserialize, Serialize};
+
+use std::fmt;
+
+/// Default [`confirm_threshold`](crate::CustomizationContext::confirm_threshold):
+/// batches of more folders than this need confirmation.
+pub const DEFAULT_CONFIRM_THRESHOLD: usize = 50;
+
+/// An operation that needs confirmation.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "kebab-case")]
+pub enum RiskyOperation {
+    /// A batch of more folders than the confirmation threshold.
+    #[serde(rename_all = "camelCase")]
+    LargeBatch {
+        /// What the batch does.
+        operation: OperationKind,
+        /// Number of unique folders in the batch.
+        folders: usize,
+    },
+    /// Resetting every folder in the profile store, when uninstalling.
+    #[serde(rename_all = "camelCase")]
+    ResetAll {
+        /// Number of customized folders that still exist.
+        folders: usize,
+    },
+    /// Deleting the app data and icon cache, when uninstalling.
+    RemoveAppData,
+}
+
+impl fmt::Display for RiskyOperation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::LargeBatch { operation, folders } => write!(f, "{operation} {folders} folders"),
+            Self::ResetAll { folders } => write!(f, "reset all {folders} customized folders"),
+            Self::RemoveAppData => f.write_str("delete the app data"),
+        }
+    }
+}
+
+/// Decides whether a [`RiskyOperation`] goes ahead.
+///
+/// Closures taking a `&RiskyOperation` and returning a `bool` are handlers
+/// too.
+///
+/// # Example
+///
+/// ```ignore
+/// use folco_core::RiskyOperation;
+///
+/// let ctx = CustomizationContextBuilder::new()
+///     .with_confirmation_handler(Arc::new(|operation: &RiskyOperation| {
+///         prompt_yes_no(&format!("Really {operation}?"))
+///     }))
+///     .build()?;
+/// ```
+pub trait ConfirmationHandler: Send + Sync {
+    /// Returns `true` if `operation` may go ahead.
+    fn confirm(&self, operation: &RiskyOperation) -> bool;
+}
+
+impl<F> ConfirmationHandler for F
+where
+    F: Fn(&RiskyOperation) -> bool + Send + Sync,
+{
+    fn confirm(&self, operation: &RiskyOperation) -> bool {
+        self(operation)
+    }
+}
+
+/// Confirms every operation, for scripts and other unattended use.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct AutoConfirm;
+
+impl ConfirmationHandler for AutoConfirm {
+    fn confirm(&self, _operation: &RiskyOperation) -> bool {
+        true
+    }
+}
+
+/// Asks `handler` about `operation`, failing with [`Error::Cancelled`] if
+/// it declines.
+pub(crate) fn confirm(handler: &dyn ConfirmationHandler, operation: &RiskyOperation) -> Result<()> {
+    if handler.confirm(operation) {
+        Ok(())
+    } else {
+        Err(Error::Cancelled(format!("declined to {operation}")))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_confirm() {
+        let batch = RiskyOperation::LargeBatch {
+            operation: OperationKind::Reset,
+            folders: 120,
+        };
+        assert!(confirm(&AutoConfirm, &batch).is_ok());
+
+        let decline = |_: &RiskyOperation| false;
+        let error = confirm(&decline, &batch).unwrap_err();
+        assert!(matches!(error, Error::Cancelled(_)));
+        assert_eq!(
+            error.to_string(),
+            "cancelled: declined to reset 120 folders"
+        );
+    }
+
+    #[test]
+    fn test_closure_sees_operation() {
+        let only_small = |operation: &RiskyOperation| match operation {
+            RiskyOperation::ResetAll { folders } => *folders < 10,
+            _ => false,
+        };
+        assert!(confirm(&only_small, &RiskyOperation::ResetAll { folders: 3 }).is_ok());
+        assert!(confirm(&only_small, &RiskyOperation::RemoveAppData).is_err());
+    }
+}
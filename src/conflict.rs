@@ -0,0 +1,190 @@
+//! Handling of folders that are already customized.
+//!
+//! Before customizing a folder, batch operations check whether it already
+//! has a folco profile or a custom icon set by something else, and consult
+//! the context's [`ConflictPolicy`] to decide what to do.
+
+use crate::store::StoredProfile;
+
+use serde::{Deserialize, Serialize};
+
+use std::fmt;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+/// An existing customization found on a folder about to be customized.
+#[derive(Debug, Clone)]
+pub enum FolderConflict {
+    /// The folder was customized by folco with the given profile.
+    FolcoProfile(StoredProfile),
+    /// The folder has a custom icon that folco didn't apply.
+    ForeignIcon,
+}
+
+impl FolderConflict {
+    /// Returns the kind of this conflict.
+    pub fn kind(&self) -> ConflictKind {
+        match self {
+            FolderConflict::FolcoProfile(_) => ConflictKind::FolcoProfile,
+            FolderConflict::ForeignIcon => ConflictKind::ForeignIcon,
+        }
+    }
+}
+
+/// The kind of a [`FolderConflict`], without its details.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum ConflictKind {
+    /// The folder was customized by folco.
+    FolcoProfile,
+    /// The folder has a custom icon that folco didn't apply.
+    ForeignIcon,
+}
+
+impl fmt::Display for ConflictKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ConflictKind::FolcoProfile => f.write_str("already customized by folco"),
+            ConflictKind::ForeignIcon => f.write_str("has a custom icon not set by folco"),
+        }
+    }
+}
+
+/// What to do with a single conflicting folder.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum ConflictResolution {
+    /// Replace the existing customization.
+    Overwrite,
+    /// Leave the folder untouched.
+    Skip,
+    /// Layer the new profile over the folder's existing folco profile.
+    ///
+    /// Folders with foreign icons can't be merged and are skipped.
+    Merge,
+}
+
+/// Callback deciding how to resolve a conflict, for [`ConflictPolicy::Ask`].
+pub type ConflictCallback =
+    Arc<dyn Fn(&Path, &FolderConflict) -> ConflictResolution + Send + Sync>;
+
+/// Policy for folders that are already customized.
+///
+/// The default, [`ConflictPolicy::Overwrite`], replaces existing icons
+/// without checking.
+///
+/// # Example
+///
+/// ```ignore
+/// use folco_core::{ConflictPolicy, ConflictResolution, FolderConflict};
+///
+/// // Keep hand-set icons, but update folders folco customized before
+/// ctx.set_conflict_policy(ConflictPolicy::ask(|_path, conflict| match conflict {
+///     FolderConflict::FolcoProfile(_) => ConflictResolution::Overwrite,
+///     FolderConflict::ForeignIcon => ConflictResolution::Skip,
+/// }));
+/// ```
+#[derive(Clone, Default)]
+pub enum ConflictPolicy {
+    /// Replace existing customizations.
+    #[default]
+    Overwrite,
+    /// Skip folders that are already customized.
+    Skip,
+    /// Merge into existing folco profiles; skip foreign icons.
+    Merge,
+    /// Decide per folder with a callback.
+    Ask(ConflictCallback),
+}
+
+impl ConflictPolicy {
+    /// Creates an [`Ask`](Self::Ask) policy from a closure.
+    pub fn ask<F>(callback: F) -> Self
+    where
+        F: Fn(&Path, &FolderConflict) -> ConflictResolution + Send + Sync + 'static,
+    {
+        ConflictPolicy::Ask(Arc::new(callback))
+    }
+
+    /// Returns `true` if folders have to be checked for conflicts at all.
+    pub(crate) fn needs_detection(&self) -> bool {
+        !matches!(self, ConflictPolicy::Overwrite)
+    }
+
+    /// Decides what to do with a conflicting folder.
+    pub(crate) fn resolve(&self, path: &Path, conflict: &FolderConflict) -> ConflictResolution {
+        let resolution = match self {
+            ConflictPolicy::Overwrite => ConflictResolution::Overwrite,
+            ConflictPolicy::Skip => ConflictResolution::Skip,
+            ConflictPolicy::Merge => ConflictResolution::Merge,
+            ConflictPolicy::Ask(callback) => callback(path, conflict),
+        };
+
+        // There is no folco profile to merge into
+        match (resolution, conflict) {
+            (ConflictResolution::Merge, FolderConflict::ForeignIcon) => ConflictResolution::Skip,
+            (resolution, _) => resolution,
+        }
+    }
+}
+
+impl fmt::Debug for ConflictPolicy {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ConflictPolicy::Overwrite => f.write_str("Overwrite"),
+            ConflictPolicy::Skip => f.write_str("Skip"),
+            ConflictPolicy::Merge => f.write_str("Merge"),
+            ConflictPolicy::Ask(_) => f.write_str("Ask(..)"),
+        }
+    }
+}
+
+/// A folder that a batch left untouched because of a conflict.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SkippedFolder {
+    /// The folder path.
+    pub path: PathBuf,
+    /// Why it was skipped.
+    pub conflict: ConflictKind,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_default_overwrites_without_detection() {
+        let policy = ConflictPolicy::default();
+        assert!(!policy.needs_detection());
+        assert_eq!(
+            policy.resolve(Path::new("/a"), &FolderConflict::ForeignIcon),
+            ConflictResolution::Overwrite
+        );
+    }
+
+    #[test]
+    fn test_merge_skips_foreign_icons() {
+        let policy = ConflictPolicy::Merge;
+        assert!(policy.needs_detection());
+        assert_eq!(
+            policy.resolve(Path::new("/a"), &FolderConflict::ForeignIcon),
+            ConflictResolution::Skip
+        );
+    }
+
+    #[test]
+    fn test_ask_calls_back() {
+        let policy = ConflictPolicy::ask(|path, conflict| {
+            assert_eq!(path, Path::new("/a"));
+            assert_eq!(conflict.kind(), ConflictKind::ForeignIcon);
+            ConflictResolution::Overwrite
+        });
+
+        assert_eq!(
+            policy.resolve(Path::new("/a"), &FolderConflict::ForeignIcon),
+            ConflictResolution::Overwrite
+        );
+        assert_eq!(format!("{:?}", policy), "Ask(..)");
+    }
+}
@@ -1,18 +1,100 @@
 //! Customization context for folder icon operations.
 //!
 //! This module provides the main entry point for all folder icon customization
-//! operations. It manages the icon customizer, folder settings provider, and
-//! icon cache.
+//! operations. It manages the icon customizer, folder icon backend,
+//! icon cache, and profile store.
 
+use crate::about::about;
+use crate::apply::{verify_applied, verify_reset, ApplyOptions};
+use crate::artifacts::{artifact_key, ArtifactCache, ArtifactSettings};
+use crate::backend::{
+    DefaultIconBackend, FolderIconBackend, IconStyle, PlatformFolderBackend, RenderOnlyBackend,
+    RENDER_ONLY_REASON,
+};
+use crate::batch::BatchOutcome;
+use crate::bookmarks::{with_access, BookmarkSet};
+use crate::branding::Branding;
 use crate::cache::{CacheConfig, IconCache};
-use crate::convert::{convert_icon_set, convert_icon_set_to_sys};
+use crate::case_audit::{audit_store_case, CaseAuditReport};
+use crate::color::{FolderColor, TargetColor};
+use crate::color_match::{predict_color_match, ColorMatch};
+use crate::curve::{targets_grey, LightnessCurve};
+use crate::checkpoint::{
+    load_snapshots, remove_snapshot, Checkpoints, FolderOutcome, ProgressSnapshot,
+    DEFAULT_CHECKPOINT_INTERVAL,
+};
+use crate::config::{AppConfig, CONFIG_FILE_NAME};
+use crate::confirm::{
+    confirm, AutoConfirm, ConfirmationHandler, RiskyOperation, DEFAULT_CONFIRM_THRESHOLD,
+};
+use crate::conflict::{ConflictKind, ConflictPolicy, ConflictResolution, FolderConflict, SkippedFolder};
+use crate::convert::convert_icon_set_to_sys;
+use crate::diff::{diff_icon_sets, IconDiff};
 use crate::error::{Error, Result};
+use crate::estimate::BatchEstimate;
+use crate::extract::extract_folder_icon;
+use crate::hooks::{CommandHook, Hook, Hooks};
+use crate::icon_state::FolderIconState;
+use crate::init::{InitReport, StarterContent, APP_VERSION};
+use crate::layers::{composite_layers, DecalLayer, LayeredProfile};
+use crate::library::{Library, LibraryRoot};
+use crate::manifest::ManifestEntry;
+use crate::palettes::{read_palette, PaletteImport, UserPalette};
+use crate::parallel::{run_pool, Job, PoolEvent};
+use crate::paths::{normalize_folder_path, normalize_folders, NormalizedFolders};
+use crate::limits::PayloadLimits;
+use crate::log::{CoreLog, LogConfig, LogEntry};
+use crate::policy::{Policy, POLICY_FILE_NAME};
+use crate::presets::{Preset, PresetLibrary};
+use crate::preview::{render_profiles, PreviewContext};
+use crate::privileged::{needs_elevation, PrivilegedExecutor};
+use crate::profile::{
+    merge_profiles, profile_hash, profile_hsl, profile_optional_settings, profile_with_color,
+    profile_with_hsl, profile_without_settings,
+};
 use crate::progress::{Progress, ProgressSender};
+use crate::queue::{Priority, WorkQueue};
+use crate::reconcile::{reconcile_store, ReconcileOptions, ReconcileReport};
+use crate::retry::{run_folder_op, run_folder_op_async, FolderOp, RetryPolicy};
+use crate::rules::{subfolders, Rule, RuleConflict, RuleMatch, RuleSet};
+use crate::ruleset::{export_ruleset, read_ruleset, RuleImportReport};
+use crate::sanitize::{sanitized_for_render, sanitized_or_default};
+use crate::scan::{scan_customized, CustomizedFolder, ScanOptions};
+use crate::search::{search_customized, SearchPage, SearchQuery};
+use crate::selection::FolderSelector;
+use crate::sharpen::{sharpen_small_icons, SharpenOptions};
+use crate::state::{JsonStateStore, MemoryStateStore, ReadOnlyStateStore, StateStore};
+use crate::sized::{flatten_for_size, SizeOverride, SizedProfile};
+use crate::stats::{LibraryStats, OperationStats};
+use crate::support::{to_value, write_support_bundle, SupportChecks, SUPPORT_LOG_ENTRIES};
+use crate::sync::{
+    machine_name, merge_state, read_synced_state, remote_sync_files, write_synced_state,
+    SyncReport, SyncState,
+};
+use crate::synthesize::synthesize_missing_sizes;
+use crate::telemetry::{OperationKind, OperationMetrics, TelemetrySink};
+use crate::store::{HistoryEntry, ProfileStore};
+use crate::throttle::{Throttle, ThrottleConfig};
+use crate::timeout::run_blocking_async;
+use crate::thumbnails::{
+    encode_thumbnail, thumbnail_from_icons, ThumbnailCache, THUMBNAILS_DIR_NAME,
+};
+use crate::uninstall::{remove_app_files, UninstallOptions, UninstallReport};
+use crate::warning::{push_unique, Warning};
+use crate::wsl::{classify_path, to_windows_path, ApplyCapability, PathLocation};
 
-use folco_renderer::{Configurable, CustomizationProfile, IconBase, IconCustomizer, IconSet as RendererIconSet};
-use icon_sys::folder_settings::{FolderSettingsProvider, PlatformFolderSettingsProvider};
+use folco_renderer::{
+    Configurable, CustomizationProfile, IconCustomizer, IconSet as RendererIconSet, RenderError,
+};
+use icon_sys::IconSet as SysIconSet;
+use image::RgbaImage;
 
+use std::borrow::Cow;
+use std::collections::{BTreeMap, HashMap};
+use std::fs;
 use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
 
 /// Application identification for determining data directories.
 ///
@@ -41,6 +123,35 @@ impl AppInfo {
             application: application.into(),
         }
     }
+
+    /// Returns the platform-appropriate app data directory for this app.
+    ///
+    /// The icon cache, profile store and other state live inside it.
+    pub fn data_dir(&self) -> Result<PathBuf> {
+        directories::ProjectDirs::from(&self.qualifier, &self.organization, &self.application)
+            .map(|dirs| dirs.data_dir().to_path_buf())
+            .ok_or_else(|| Error::AppDataDir("failed to determine app data directory".to_string()))
+    }
+
+    /// Returns where administrators place the [`Policy`] for this app.
+    ///
+    /// The location is system-wide and normally writable only by
+    /// administrators: `%ProgramData%\<organization>\<application>` on
+    /// Windows, `/Library/Application Support/<application>` on macOS and
+    /// `/etc/<application>` elsewhere.
+    pub fn policy_path(&self) -> PathBuf {
+        let dir = if cfg!(target_os = "windows") {
+            let program_data = std::env::var_os("ProgramData")
+                .map(PathBuf::from)
+                .unwrap_or_else(|| PathBuf::from(r"C:\ProgramData"));
+            program_data.join(&self.organization).join(&self.application)
+        } else if cfg!(target_os = "macos") {
+            Path::new("/Library/Application Support").join(&self.application)
+        } else {
+            Path::new("/etc").join(&self.application)
+        };
+        dir.join(POLICY_FILE_NAME)
+    }
 }
 
 impl Default for AppInfo {
@@ -72,7 +183,30 @@ impl Default for AppInfo {
 pub struct CustomizationContextBuilder {
     app_info: AppInfo,
     cache_dir: Option<PathBuf>,
+    data_dir: Option<PathBuf>,
+    policy_path: Option<PathBuf>,
     force_cache_refresh: bool,
+    jumbo_sizes: bool,
+    throttle: ThrottleConfig,
+    work_queue: Option<Arc<WorkQueue>>,
+    priority: Priority,
+    apply_options: ApplyOptions,
+    conflict_policy: ConflictPolicy,
+    payload_limits: PayloadLimits,
+    privileged: Option<Arc<dyn PrivilegedExecutor>>,
+    host: Option<Arc<dyn PrivilegedExecutor>>,
+    folder_backend: Option<Arc<dyn FolderIconBackend>>,
+    default_icons: Option<Arc<dyn DefaultIconBackend>>,
+    telemetry: Option<Arc<dyn TelemetrySink>>,
+    log: Option<CoreLog>,
+    hooks: Hooks,
+    confirmation: Arc<dyn ConfirmationHandler>,
+    confirm_threshold: usize,
+    checkpoint_interval: usize,
+    state: Option<Arc<dyn StateStore>>,
+    read_only: bool,
+    strict: bool,
+    render_only: bool,
 }
 
 impl CustomizationContextBuilder {
@@ -83,7 +217,30 @@ impl CustomizationContextBuilder {
         Self {
             app_info: AppInfo::default(),
             cache_dir: None,
+            data_dir: None,
+            policy_path: None,
             force_cache_refresh: false,
+            jumbo_sizes: false,
+            throttle: ThrottleConfig::default(),
+            work_queue: None,
+            priority: Priority::default(),
+            apply_options: ApplyOptions::default(),
+            conflict_policy: ConflictPolicy::default(),
+            payload_limits: PayloadLimits::default(),
+            privileged: None,
+            host: None,
+            folder_backend: None,
+            default_icons: None,
+            telemetry: None,
+            log: None,
+            hooks: Hooks::default(),
+            confirmation: Arc::new(AutoConfirm),
+            confirm_threshold: DEFAULT_CONFIRM_THRESHOLD,
+            checkpoint_interval: DEFAULT_CHECKPOINT_INTERVAL,
+            state: None,
+            read_only: false,
+            strict: false,
+            render_only: false,
         }
     }
 
@@ -106,20 +263,292 @@ impl CustomizationContextBuilder {
         self
     }
 
+    /// Sets a custom app data directory for persistent state.
+    ///
+    /// The profile store and other state files are kept here. This
+    /// overrides the app info if both are set. The icon cache location is
+    /// controlled separately by [`with_cache_dir`](Self::with_cache_dir).
+    pub fn with_data_dir(mut self, data_dir: impl Into<PathBuf>) -> Self {
+        self.data_dir = Some(data_dir.into());
+        self
+    }
+
+    /// Sets where the admin [`Policy`] is read from.
+    ///
+    /// By default it's read from the app info's
+    /// [`policy_path`](AppInfo::policy_path).
+    pub fn with_policy_path(mut self, path: impl Into<PathBuf>) -> Self {
+        self.policy_path = Some(path.into());
+        self
+    }
+
     /// Forces the cache to be refreshed on build.
     pub fn with_force_cache_refresh(mut self, force: bool) -> Self {
         self.force_cache_refresh = force;
         self
     }
 
+    /// Sets whether icons also get 512- and 768-pixel sizes, which
+    /// Explorer's extra-large views and some third-party shells show.
+    ///
+    /// They're scaled up from the 256-pixel system icon and written into
+    /// the generated `.ico` files. Windows only; ignored elsewhere. Off by
+    /// default, since they make every icon file several times larger.
+    pub fn with_jumbo_sizes(mut self, enabled: bool) -> Self {
+        self.jumbo_sizes = enabled;
+        self
+    }
+
+    /// Sets the throttling applied to batch operations.
+    ///
+    /// By default, batch operations are not throttled.
+    pub fn with_throttle(mut self, throttle: ThrottleConfig) -> Self {
+        self.throttle = throttle;
+        self
+    }
+
+    /// Shares a work queue with other contexts.
+    ///
+    /// Background batches on any context sharing the queue yield while an
+    /// interactive batch is running. By default, each context gets its own
+    /// queue.
+    pub fn with_work_queue(mut self, work_queue: Arc<WorkQueue>) -> Self {
+        self.work_queue = Some(work_queue);
+        self
+    }
+
+    /// Sets the priority of batch operations run by this context.
+    ///
+    /// Defaults to [`Priority::Interactive`].
+    pub fn with_priority(mut self, priority: Priority) -> Self {
+        self.priority = priority;
+        self
+    }
+
+    /// Sets how long a single folder may take before it's reported as timed out.
+    ///
+    /// A timed-out folder fails with [`Error::Timeout`] and the batch moves on
+    /// to the next folder. By default, folder operations never time out.
+    ///
+    /// Shorthand for setting [`ApplyOptions::timeout`].
+    pub fn with_folder_timeout(mut self, timeout: Duration) -> Self {
+        self.apply_options.timeout = Some(timeout);
+        self
+    }
+
+    /// Sets how icons are applied to each folder: timeouts, retries,
+    /// verification, refreshing and platform specifics.
+    ///
+    /// Replaces any timeout set with
+    /// [`with_folder_timeout`](Self::with_folder_timeout) and any policy set
+    /// with [`with_retry_policy`](Self::with_retry_policy).
+    pub fn with_apply_options(mut self, options: ApplyOptions) -> Self {
+        self.apply_options = options;
+        self
+    }
+
+    /// Sets how customization batches retry folders that fail for a
+    /// passing reason, such as files held open on a network share.
+    ///
+    /// By default, each folder is tried once. This sets the
+    /// [`retry`](ApplyOptions::retry) of the default apply options.
+    pub fn with_retry_policy(mut self, policy: RetryPolicy) -> Self {
+        self.apply_options.retry = policy;
+        self
+    }
+
+    /// Sets how batch operations treat folders that are already customized.
+    ///
+    /// Defaults to [`ConflictPolicy::Overwrite`].
+    pub fn with_conflict_policy(mut self, policy: ConflictPolicy) -> Self {
+        self.conflict_policy = policy;
+        self
+    }
+
+    /// Sets the limits on decal size, decal count and render dimensions
+    /// that profiles must stay within.
+    ///
+    /// Defaults to [`PayloadLimits::default`].
+    pub fn with_payload_limits(mut self, limits: PayloadLimits) -> Self {
+        self.payload_limits = limits;
+        self
+    }
+
+    /// Sets the executor that retries folder operations refused for lack of
+    /// rights, such as a [`HelperProcess`](crate::HelperProcess).
+    ///
+    /// By default, such folders simply fail.
+    pub fn with_privileged_executor(mut self, executor: Arc<dyn PrivilegedExecutor>) -> Self {
+        self.privileged = Some(executor);
+        self
+    }
+
+    /// Sets the executor for Windows drives reached from WSL, typically a
+    /// [`HelperProcess`](crate::HelperProcess) running the Windows build of
+    /// the helper through interop.
+    ///
+    /// It receives Windows paths, such as `C:\Users\me` for
+    /// `/mnt/c/Users/me`. By default, such folders can't be customized, since
+    /// Explorer wouldn't show the Linux provider's icons.
+    pub fn with_host_executor(mut self, executor: Arc<dyn PrivilegedExecutor>) -> Self {
+        self.host = Some(executor);
+        self
+    }
+
+    /// Sets the backend folder icons are set and reset through, such as a
+    /// mock for tests.
+    ///
+    /// By default, this is the platform's [`PlatformFolderBackend`].
+    pub fn with_folder_backend(mut self, backend: Arc<dyn FolderIconBackend>) -> Self {
+        self.folder_backend = Some(backend);
+        self
+    }
+
+    /// Sets the backend the default folder icon is read from, in place of
+    /// the icon cache and extraction from the system.
+    ///
+    /// The icons it provides aren't cached, and
+    /// [`refresh_cache`](CustomizationContext::refresh_cache) reads them
+    /// again.
+    pub fn with_default_icon_backend(mut self, backend: Arc<dyn DefaultIconBackend>) -> Self {
+        self.default_icons = Some(backend);
+        self
+    }
+
+    /// Sets the backend that persists the profile store, presets, app
+    /// config and preset thumbnails, such as a
+    /// [`MemoryStateStore`](crate::MemoryStateStore) for tests or a
+    /// [`SqliteStateStore`](crate::SqliteStateStore).
+    ///
+    /// By default, they're kept as JSON files and PNGs in the app data
+    /// directory.
+    pub fn with_state_store(mut self, state: Arc<dyn StateStore>) -> Self {
+        self.state = Some(state);
+        self
+    }
+
+    /// Sets the sink that receives anonymized metrics after every batch.
+    ///
+    /// By default no telemetry is recorded; frontends should only set a
+    /// sink once the user has opted in.
+    pub fn with_telemetry_sink(mut self, sink: Arc<dyn TelemetrySink>) -> Self {
+        self.telemetry = Some(sink);
+        self
+    }
+
+    /// Sets the log that batches and watchers write to.
+    ///
+    /// Defaults to [`LOG_FILE_NAME`](crate::LOG_FILE_NAME) in the app data
+    /// directory with the default [`LogConfig`], or a
+    /// [disabled](CoreLog::disabled) log for read-only contexts.
+    pub fn with_log(mut self, log: CoreLog) -> Self {
+        self.log = Some(log);
+        self
+    }
+
+    /// Adds a hook called around each folder that batches customize or
+    /// reset.
+    ///
+    /// Hooks are called in the order they were added.
+    pub fn with_hook(mut self, hook: Arc<dyn Hook>) -> Self {
+        self.hooks.push(hook);
+        self
+    }
+
+    /// Sets the handler asked before destructive operations, such as a
+    /// batch of more folders than the
+    /// [confirmation threshold](Self::with_confirm_threshold).
+    ///
+    /// By default, [`AutoConfirm`] confirms everything.
+    pub fn with_confirmation_handler(mut self, handler: Arc<dyn ConfirmationHandler>) -> Self {
+        self.confirmation = handler;
+        self
+    }
+
+    /// Sets the number of folders a batch may have before it needs
+    /// confirmation.
+    ///
+    /// By default, this is [`DEFAULT_CONFIRM_THRESHOLD`].
+    pub fn with_confirm_threshold(mut self, folders: usize) -> Self {
+        self.confirm_threshold = folders;
+        self
+    }
+
+    /// Sets how many folders async batches process between saving their
+    /// progress, or 0 to never save it.
+    ///
+    /// By default, this is [`DEFAULT_CHECKPOINT_INTERVAL`].
+    pub fn with_checkpoint_interval(mut self, folders: usize) -> Self {
+        self.checkpoint_interval = folders;
+        self
+    }
+
+    /// Sets whether the context is read-only, for viewers and audit tools.
+    ///
+    /// A read-only context inspects, renders, previews and exports as usual,
+    /// but every operation that would change folders or the saved state
+    /// fails with [`Error::ReadOnly`].
+    pub fn with_read_only(mut self, read_only: bool) -> Self {
+        self.read_only = read_only;
+        self
+    }
+
+    /// Sets whether the context is strict, for users and CI that need
+    /// exact results.
+    ///
+    /// By default, a render works around what it can: it leaves out
+    /// settings and sizes that fail, scales sizes the base icons lack and
+    /// sanitizes SVG decals, reporting a [`Warning`] for each. A strict
+    /// context fails with [`Error::Strict`] instead, so a folder gets
+    /// exactly the icon its profile describes or none at all. It also
+    /// [verifies](ApplyOptions::verify) every folder it customizes or
+    /// resets, whatever its [`ApplyOptions`] say, so a folder that doesn't
+    /// report the change counts as failed. Unlike
+    /// [`ExitCodePolicy::fail_on_warnings`](crate::ExitCodePolicy::fail_on_warnings),
+    /// this stops the icons from being applied.
+    pub fn with_strict(mut self, strict: bool) -> Self {
+        self.strict = strict;
+        self
+    }
+
+    /// Builds a context that only renders and previews, such as for a
+    /// preset editor pane.
+    ///
+    /// The platform's folder backend is never set up, so building costs
+    /// no platform initialization and asks for no permissions. Nothing is
+    /// read from or written to the data directory either: state is kept in
+    /// memory unless [`with_state_store`](Self::with_state_store) is used,
+    /// the default folder icon and finished icons are kept in memory
+    /// rather than in the icon cache unless
+    /// [`with_cache_dir`](Self::with_cache_dir) is used, preset thumbnails
+    /// are only handed out as bytes by
+    /// [`preset_thumbnail_png`](CustomizationContext::preset_thumbnail_png),
+    /// the app config and admin policy are the defaults, and nothing is
+    /// logged unless [`with_log`](Self::with_log) is used. Every
+    /// folder a render-only context is asked to customize or reset fails
+    /// with [`Error::Unsupported`], and
+    /// [`apply_capability`](CustomizationContext::apply_capability) says
+    /// why. This overrides [`with_folder_backend`](Self::with_folder_backend).
+    pub fn render_only(mut self) -> Self {
+        self.render_only = true;
+        self
+    }
+
     /// Builds the [`CustomizationContext`].
     ///
     /// This will:
     /// 1. Set up the icon cache
     /// 2. Load or fetch the default system folder icon
     /// 3. Initialize the icon customizer
-    /// 4. Initialize the folder settings provider
+    /// 4. Initialize the folder icon backend
+    /// 5. Open the profile store and preset library, and load the app config
+    ///    and admin policy, unless the context is
+    ///    [render-only](Self::render_only)
     pub fn build(self) -> Result<CustomizationContext> {
+        // Render-only contexts keep the default cache, which lives in the
+        // data directory, in memory
+        let cache_in_memory = self.render_only && self.cache_dir.is_none();
+
         // Determine cache configuration
         let cache_config = if let Some(cache_dir) = self.cache_dir {
             CacheConfig::new(cache_dir).with_force_refresh(self.force_cache_refresh)
@@ -134,19 +563,101 @@ impl CustomizationContextBuilder {
 
         // Create cache and load icons
         let cache = IconCache::new(cache_config);
-        let renderer_icons = cache.get_renderer_icon_set()?;
+        let base_icons = match &self.default_icons {
+            Some(backend) => backend.default_folder_icon()?,
+            None if cache_in_memory => extract_folder_icon()?.0,
+            None => cache.get_sys_icon_set()?,
+        };
+        let base_icons = Arc::new(if self.jumbo_sizes {
+            crate::sys::add_jumbo_sizes(base_icons)
+        } else {
+            base_icons
+        });
 
-        // Create the customizer with the platform-specific surface color
-        let icon_base = IconBase::new(renderer_icons, crate::sys::SURFACE_COLOR);
-        let customizer = IconCustomizer::new(icon_base);
+        // Create the customizer with the backend's or the platform's surface color
+        let style = IconStyle::new(self.default_icons.clone());
+        let customizer = IconCustomizer::new(style.icon_base(&base_icons));
 
-        // Create the folder settings provider
-        let folder_provider = PlatformFolderSettingsProvider::new();
+        let folder_backend: Arc<dyn FolderIconBackend> = if self.render_only {
+            Arc::new(RenderOnlyBackend)
+        } else {
+            self.folder_backend
+                .unwrap_or_else(|| Arc::new(PlatformFolderBackend::new()))
+        };
+
+        // Open the record of customized folders
+        let data_dir = match self.data_dir {
+            Some(data_dir) => data_dir,
+            None => self.app_info.data_dir()?,
+        };
+        let mut state = self.state.unwrap_or_else(|| {
+            if self.render_only {
+                Arc::new(MemoryStateStore::new())
+            } else {
+                Arc::new(JsonStateStore::new(&data_dir))
+            }
+        });
+        if self.read_only {
+            // Also covers saves through store() and presets_mut()
+            state = Arc::new(ReadOnlyStateStore::new(state));
+        }
+        let store = ProfileStore::with_state_store(Arc::clone(&state))?;
+        let config = AppConfig::load_from(state.as_ref())?;
+        let presets = PresetLibrary::with_state_store(Arc::clone(&state))?;
+        let policy_path = self.policy_path.unwrap_or_else(|| self.app_info.policy_path());
+        let policy = if self.render_only {
+            Policy::default()
+        } else {
+            Policy::load(&policy_path)?
+        };
+        let log = self.log.unwrap_or_else(|| {
+            if self.read_only || self.render_only {
+                CoreLog::disabled()
+            } else {
+                CoreLog::in_data_dir(&data_dir, LogConfig::default())
+            }
+        });
 
         Ok(CustomizationContext {
+            artifacts: if cache_in_memory {
+                ArtifactCache::in_memory()
+            } else {
+                ArtifactCache::new(cache.cache_dir())
+            },
+            cache_in_memory,
             cache,
+            base_icons,
+            jumbo_sizes: self.jumbo_sizes,
             customizer,
-            folder_provider,
+            folder_backend,
+            default_icons: self.default_icons,
+            throttle: self.throttle,
+            work_queue: self.work_queue.unwrap_or_default(),
+            priority: self.priority,
+            apply_options: self.apply_options,
+            conflict_policy: self.conflict_policy,
+            payload_limits: self.payload_limits,
+            privileged: self.privileged,
+            host: self.host,
+            telemetry: self.telemetry,
+            log,
+            hooks: self.hooks,
+            confirmation: self.confirmation,
+            confirm_threshold: self.confirm_threshold,
+            checkpoint_interval: self.checkpoint_interval,
+            state,
+            data_dir,
+            store,
+            config,
+            policy,
+            policy_path,
+            presets,
+            stats: Mutex::new(OperationStats::default()),
+            bookmarks: BookmarkSet::default(),
+            render_warnings: Vec::new(),
+            read_only: self.read_only,
+            strict: self.strict,
+            render_only: self.render_only,
         })
     }
 }
@@ -188,8 +699,113 @@ impl Default for CustomizationContextBuilder {
 /// ```
 pub struct CustomizationContext {
     cache: IconCache,
+    artifacts: ArtifactCache,
+    /// Whether the default icon and finished icons stay out of the cache
+    /// directory.
+    cache_in_memory: bool,
+    base_icons: Arc<SysIconSet>,
+    jumbo_sizes: bool,
     customizer: IconCustomizer,
-    folder_provider: PlatformFolderSettingsProvider,
+    folder_backend: Arc<dyn FolderIconBackend>,
+    default_icons: Option<Arc<dyn DefaultIconBackend>>,
+    throttle: ThrottleConfig,
+    work_queue: Arc<WorkQueue>,
+    priority: Priority,
+    apply_options: ApplyOptions,
+    conflict_policy: ConflictPolicy,
+    payload_limits: PayloadLimits,
+    privileged: Option<Arc<dyn PrivilegedExecutor>>,
+    host: Option<Arc<dyn PrivilegedExecutor>>,
+    telemetry: Option<Arc<dyn TelemetrySink>>,
+    log: CoreLog,
+    hooks: Hooks,
+    confirmation: Arc<dyn ConfirmationHandler>,
+    confirm_threshold: usize,
+    checkpoint_interval: usize,
+    state: Arc<dyn StateStore>,
+    data_dir: PathBuf,
+    store: ProfileStore,
+    config: AppConfig,
+    policy: Policy,
+    policy_path: PathBuf,
+    presets: PresetLibrary,
+    stats: Mutex<OperationStats>,
+    bookmarks: BookmarkSet,
+    render_warnings: Vec<Warning>,
+    read_only: bool,
+    strict: bool,
+    render_only: bool,
+}
+
+/// How a batch handles a single folder, after consulting the conflict policy.
+enum FolderPlan {
+    /// Apply the batch's profile.
+    Apply,
+    /// Apply this profile, merged from the folder's existing one.
+    Merge(CustomizationProfile),
+    /// Leave the folder untouched.
+    Skip(ConflictKind),
+}
+
+/// Which mechanism customizes a single folder.
+enum FolderRoute {
+    /// The folder icon backend.
+    Direct,
+    /// The host executor, with the folder's path on the host.
+    Host(Arc<dyn PrivilegedExecutor>, PathBuf),
+    /// Nothing can; the reason is shown to the user.
+    Unsupported(String),
+}
+
+/// Settings folco-core applies around the renderer, for one batch.
+#[derive(Clone, Copy, Default)]
+struct RenderExtras<'a> {
+    /// Size-conditional overrides flattened into the profile per size.
+    overrides: &'a [SizeOverride],
+    /// Image layers drawn over the render.
+    layers: &'a [DecalLayer],
+    /// Lightness curve replacing the config's, e.g. a preset's.
+    lightness_curve: Option<&'a LightnessCurve>,
+    /// Whether each folder's stored profile is merged under the batch's,
+    /// whatever the conflict policy.
+    stacked: bool,
+}
+
+/// Calls `hooks` after `folder` was processed, then runs `command_hooks` if
+/// it succeeded, logging their failures to `log`.
+fn run_after_hooks(
+    hooks: &Hooks,
+    command_hooks: &[CommandHook],
+    log: &CoreLog,
+    operation: OperationKind,
+    folder: &Path,
+    result: &Result<()>,
+) {
+    hooks.after(operation, folder, result);
+    if result.is_err() {
+        return;
+    }
+    for hook in command_hooks {
+        if !hook.runs_after(operation) {
+            continue;
+        }
+        if let Err(e) = hook.run(operation, folder) {
+            log.warn(format_args!("after {operation} of {}: {e}", folder.display()));
+        }
+    }
+}
+
+/// Takes the icon set out of an `Arc`, copying it if it's still shared.
+fn unshare_icons(icons: Arc<SysIconSet>) -> SysIconSet {
+    Arc::try_unwrap(icons).unwrap_or_else(|shared| SysIconSet {
+        images: shared
+            .images
+            .iter()
+            .map(|image| icon_sys::IconImage {
+                data: image.data.clone(),
+            })
+            .collect(),
+    })
 }
 
 impl CustomizationContext {
@@ -207,6 +823,60 @@ impl CustomizationContext {
         &mut self.customizer
     }
 
+    /// Returns a render-only handle with its own customizer, configured
+    /// like this context's customizer is now.
+    ///
+    /// The handle shares this context's base icons, so forking is cheap.
+    /// Changes to either customizer don't affect the other, which lets a GUI
+    /// render A/B previews of candidate profiles, even on separate threads,
+    /// while the main customizer keeps its layers. Forks made before
+    /// [`refresh_cache`](Self::refresh_cache) keep the old base icons.
+    pub fn fork_for_preview(&self) -> PreviewContext {
+        PreviewContext::new(
+            Arc::clone(&self.base_icons),
+            self.icon_style(),
+            self.export_profile(),
+        )
+    }
+
+    /// Predicts how closely `target` can be matched on this context's base
+    /// icons.
+    ///
+    /// This is cheap, since nothing is rendered, so a color picker can call
+    /// it as the user drags and warn when the result isn't a
+    /// [`ColorMatch::is_match`].
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Error::NotInitialized`] if the base icons have no surface
+    /// in the folder color to recolor.
+    pub fn predict_color_match(&self, target: &TargetColor) -> Result<ColorMatch> {
+        let surface = self.icon_style().surface_hsl();
+        predict_color_match(&self.base_icons, surface, target).ok_or_else(|| {
+            Error::NotInitialized("the base icons have no folder-colored surface".to_string())
+        })
+    }
+
+    /// Returns the content bounds and folder color of the base icons.
+    fn icon_style(&self) -> IconStyle {
+        IconStyle::new(self.default_icons.clone())
+    }
+
+    /// Renders each of `profiles` as a `size` × `size` image, such as a strip
+    /// of every color preset or a comparison grid.
+    ///
+    /// This is much faster than rendering the profiles one after another:
+    /// the base is decoded once, only the base image closest to `size` is
+    /// rendered, and identical profiles are rendered once. The customizer
+    /// is untouched.
+    pub fn render_profiles(
+        &self,
+        profiles: &[CustomizationProfile],
+        size: u32,
+    ) -> Result<Vec<RgbaImage>> {
+        render_profiles(&self.base_icons, &self.icon_style(), profiles, size)
+    }
+
     /// Returns a reference to the icon cache.
     pub fn cache(&self) -> &IconCache {
         &self.cache
@@ -217,98 +887,2274 @@ impl CustomizationContext {
         &mut self.cache
     }
 
-    /// Returns the base (uncustomized) icon set in renderer format.
+    /// Returns the app data directory holding persistent state.
+    pub fn data_dir(&self) -> &Path {
+        &self.data_dir
+    }
+
+    /// Returns the log that batches and watchers write to.
+    pub fn log(&self) -> &CoreLog {
+        &self.log
+    }
+
+    /// Returns the last `count` log entries, oldest first.
     ///
-    /// This is useful for folco-gui to pass to the WASM renderer.
-    pub fn base_icons(&self) -> &RendererIconSet {
-        self.customizer.base_icons()
+    /// See [`CoreLog::recent`].
+    pub fn recent_logs(&self, count: usize) -> Vec<LogEntry> {
+        self.log.recent(count)
     }
 
-    /// Applies a customization profile to the customizer.
+    /// Returns `true` if the context was built
+    /// [read-only](CustomizationContextBuilder::with_read_only).
+    pub fn is_read_only(&self) -> bool {
+        self.read_only
+    }
+
+    /// Returns `true` if the context was built
+    /// [render-only](CustomizationContextBuilder::render_only).
+    pub fn is_render_only(&self) -> bool {
+        self.render_only
+    }
+
+    /// Returns `true` if renders fail rather than work around problems.
     ///
-    /// This configures all layers according to the profile settings.
-    pub fn apply_profile(&mut self, profile: &CustomizationProfile) {
-        self.customizer.apply_profile(profile);
+    /// See [`CustomizationContextBuilder::with_strict`].
+    pub fn is_strict(&self) -> bool {
+        self.strict
     }
 
-    /// Exports the current customizer settings as a profile.
-    pub fn export_profile(&self) -> CustomizationProfile {
-        self.customizer.export_profile()
+    /// Sets whether renders fail rather than work around problems.
+    pub fn set_strict(&mut self, strict: bool) {
+        self.strict = strict;
     }
 
-    /// Renders and returns the customized icon set.
+    /// Returns the record of folders customized by folco.
+    pub fn store(&self) -> &ProfileStore {
+        &self.store
+    }
+
+    /// Returns the user's saved presets.
+    pub fn presets(&self) -> &PresetLibrary {
+        &self.presets
+    }
+
+    /// Returns a mutable reference to the saved presets.
     ///
-    /// This applies all active customizations and returns the result.
-    /// The returned icon set is in `folco-renderer` format.
-    pub fn render(&mut self) -> Result<RendererIconSet> {
-        Ok(self.customizer.render_all()?)
+    /// Call [`PresetLibrary::save`] to persist changes. Presets changed
+    /// this way don't get a new thumbnail; use
+    /// [`save_preset`](Self::save_preset) for that.
+    pub fn presets_mut(&mut self) -> &mut PresetLibrary {
+        &mut self.presets
     }
 
-    /// Customizes the icons for the specified folders.
+    /// Saves `profile` as a preset and renders its thumbnail.
     ///
-    /// This method:
-    /// 1. Applies the profile to the customizer
-    /// 2. Renders the customized icon set
-    /// 3. Converts to system format
-    /// 4. Applies to each folder
+    /// The preset is persisted even if its thumbnail fails to render, so a
+    /// rendering error leaves the preset saved without a thumbnail; one is
+    /// rendered on the next [`preset_thumbnail`](Self::preset_thumbnail) call.
+    pub fn save_preset(&mut self, name: &str, profile: &CustomizationProfile) -> Result<&Preset> {
+        self.ensure_writable("save presets")?;
+        let rendered = self.render_thumbnail(profile);
+        let thumbnails = self.thumbnails();
+        let preset = self.presets.insert_and_save(name, profile.clone())?;
+        if let Some(thumbnail) = rendered? {
+            thumbnails.store(name, &thumbnail)?;
+        }
+        Ok(preset)
+    }
+
+    /// Removes a preset and its thumbnails.
     ///
-    /// # Arguments
+    /// If it was the default preset, the default is cleared.
+    pub fn remove_preset(&mut self, name: &str) -> Result<Option<Preset>> {
+        self.ensure_writable("remove presets")?;
+        let removed = self.presets.remove(name);
+        if removed.is_some() {
+            self.presets.save()?;
+            self.thumbnails().remove(name)?;
+            if self.default_preset() == Some(name) {
+                self.set_default_preset(None)?;
+            }
+        }
+        Ok(removed)
+    }
+
+    /// Returns the path of a preset's thumbnail PNG at `size` pixels square.
     ///
-    /// * `folders` - Collection of folder paths to customize
-    /// * `profile` - The customization profile to apply
+    /// Thumbnails are cached, so this is cheap after the first call for a
+    /// given size. A preset saved without a thumbnail has one rendered now.
     ///
-    /// # Returns
+    /// # Errors
     ///
-    /// A vector of results, one for each folder. This allows partial success
-    /// where some folders succeed and others fail.
-    pub fn customize_folders<P: AsRef<Path>>(
-        &mut self,
-        folders: &[P],
-        profile: &CustomizationProfile,
-    ) -> Vec<Result<()>> {
-        // Apply the profile
+    /// Returns [`Error::PresetNotFound`] if there is no such preset,
+    /// [`Error::ReadOnly`] on read-only contexts, and [`Error::Cache`] on
+    /// [render-only](CustomizationContextBuilder::render_only) contexts,
+    /// neither of which writes thumbnail files; use
+    /// [`preset_thumbnail_png`](Self::preset_thumbnail_png) there.
+    pub fn preset_thumbnail(&mut self, name: &str, size: u32) -> Result<PathBuf> {
+        self.ensure_writable("write thumbnail files")?;
+        let profile = self.presets.profile(name)?.clone();
+        let thumbnails = self.thumbnails();
+        if !thumbnails.has_files() {
+            return Err(Error::Cache(
+                "this context writes no thumbnail files; use preset_thumbnail_png".to_string(),
+            ));
+        }
+        if let Some(path) = thumbnails.get(name, size)? {
+            return Ok(path);
+        }
+
+        self.render_preset_thumbnail(name, &profile)?;
+        thumbnails.get(name, size)?.ok_or_else(|| {
+            Error::NotInitialized(format!("no thumbnail could be rendered for preset '{name}'"))
+        })
+    }
+
+    /// Returns a preset's thumbnail at `size` pixels square as PNG bytes.
+    ///
+    /// Unlike [`preset_thumbnail`](Self::preset_thumbnail), this writes no
+    /// files, so it works on every context. A preset saved without a
+    /// thumbnail has one rendered now, which a read-only context doesn't
+    /// keep.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Error::PresetNotFound`] if there is no such preset.
+    pub fn preset_thumbnail_png(&mut self, name: &str, size: u32) -> Result<Vec<u8>> {
+        let profile = self.presets.profile(name)?.clone();
+        if let Some(png) = self.thumbnails().png(name, size)? {
+            return Ok(png);
+        }
+
+        let thumbnail = self.render_thumbnail(&profile)?.ok_or_else(|| {
+            Error::NotInitialized(format!("no thumbnail could be rendered for preset '{name}'"))
+        })?;
+        if !self.read_only {
+            self.thumbnails().store(name, &thumbnail)?;
+        }
+        encode_thumbnail(&thumbnail, size)
+    }
+
+    fn thumbnails(&self) -> ThumbnailCache {
+        if self.read_only || self.render_only {
+            ThumbnailCache::in_memory(Arc::clone(&self.state))
+        } else {
+            ThumbnailCache::new(Arc::clone(&self.state), &self.data_dir)
+        }
+    }
+
+    /// Renders and caches a preset's base thumbnail.
+    fn render_preset_thumbnail(&mut self, name: &str, profile: &CustomizationProfile) -> Result<()> {
+        if let Some(thumbnail) = self.render_thumbnail(profile)? {
+            self.thumbnails().store(name, &thumbnail)?;
+        }
+        Ok(())
+    }
+
+    /// Renders a base thumbnail of `profile`, leaving the customizer
+    /// configured as before.
+    fn render_thumbnail(&mut self, profile: &CustomizationProfile) -> Result<Option<RgbaImage>> {
+        let previous = self.export_profile();
         self.apply_profile(profile);
+        let rendered = self.render();
+        self.apply_profile(&previous);
+        Ok(thumbnail_from_icons(&rendered?))
+    }
 
-        // Render the customized icons
-        let rendered = match self.render() {
-            Ok(icons) => icons,
-            Err(e) => return vec![Err(e)],
+    /// Returns the name of the preset used by [`quick_apply`](Self::quick_apply).
+    pub fn default_preset(&self) -> Option<&str> {
+        self.config.default_preset.as_deref()
+    }
+
+    /// Sets or clears the default preset and saves the config.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Error::PresetNotFound`] if there is no preset with that name.
+    pub fn set_default_preset(&mut self, name: Option<&str>) -> Result<()> {
+        self.ensure_writable("change the default preset")?;
+        if let Some(name) = name {
+            self.presets.profile(name)?;
+        }
+        self.config.default_preset = name.map(str::to_string);
+        self.save_config()
+    }
+
+    /// Prepares the app data directory, seeding starter content on the
+    /// first run.
+    ///
+    /// Creates the data, icon cache and thumbnail directories if needed.
+    /// When no config has been saved yet, the default config is written
+    /// with a set of starter glyphs, and the starter presets are added,
+    /// except any whose name is taken or that the admin policy forbids;
+    /// their thumbnails are rendered when first requested. Every run
+    /// records the running version in the config.
+    ///
+    /// Frontends call this once at startup. Later calls create nothing
+    /// new.
+    pub fn initialize_app_data(&mut self) -> Result<InitReport> {
+        self.ensure_writable("initialize the app data")?;
+        let mut report = InitReport {
+            version: APP_VERSION.to_string(),
+            ..Default::default()
         };
+        let dirs = [
+            self.data_dir.clone(),
+            self.cache.cache_dir().to_path_buf(),
+            self.data_dir.join(THUMBNAILS_DIR_NAME),
+        ];
+        for dir in dirs {
+            if !dir.exists() {
+                fs::create_dir_all(&dir)?;
+                report.created_dirs.push(dir);
+            }
+        }
+
+        let first_run = self.state.read_blob(CONFIG_FILE_NAME)?.is_none();
+        if first_run {
+            let starter = StarterContent::bundled()?;
+            for (name, profile) in starter.presets()? {
+                let taken = self.presets.get(&name).is_some();
+                if taken || self.validate_profile(&profile).is_err() {
+                    continue;
+                }
+                self.presets.insert(name.clone(), profile);
+                report.seeded_presets.push(name);
+            }
+            if !report.seeded_presets.is_empty() {
+                self.presets.save()?;
+            }
+            for glyph in starter.glyphs {
+                if !self.config.glyphs.contains(&glyph) {
+                    self.config.glyphs.push(glyph.clone());
+                    report.seeded_glyphs.push(glyph);
+                }
+            }
+            report.wrote_config = true;
+        }
+
+        report.previous_version = self.config.last_version.replace(APP_VERSION.to_string());
+        if first_run || report.previous_version.as_deref() != Some(APP_VERSION) {
+            self.save_config()?;
+        }
+        Ok(report)
+    }
+
+    /// Resets customized folders and deletes folco's data, for uninstalling.
+    ///
+    /// With `options.reset_folders`, every folder in the profile store is
+    /// reset; recorded folders that no longer exist are just forgotten. With
+    /// `options.remove_app_data`, the profile store, presets, config and
+    /// thumbnails are cleared from the state store, folco's files are
+    /// deleted from the app data directory, which is deleted too if nothing
+    /// else is in it, and the icon cache is deleted. Unless `options.force` is set,
+    /// nothing is deleted while folders remain customized, so they can still
+    /// be reset later. The report lists whatever remains, including an admin
+    /// policy file, which only an administrator can remove.
+    ///
+    /// Resetting the folders and removing the app data are each confirmed
+    /// with the [`ConfirmationHandler`] first. If it declines either, this
+    /// fails with [`Error::Cancelled`] and nothing changes.
+    ///
+    /// After removing the app data, the context should only be dropped.
+    pub fn uninstall_cleanup(&mut self, options: &UninstallOptions) -> Result<UninstallReport> {
+        self.ensure_writable("uninstall")?;
+        let mut report = UninstallReport::default();
+        let (existing, missing): (Vec<PathBuf>, Vec<PathBuf>) = self
+            .store
+            .entries()
+            .into_iter()
+            .map(|(folder, _)| folder)
+            .partition(|folder| folder.exists());
+
+        // Ask before changing anything, so declining leaves everything as is
+        if options.reset_folders && !existing.is_empty() {
+            self.ask_confirmation(&RiskyOperation::ResetAll {
+                folders: existing.len(),
+            })?;
+        }
+        if options.remove_app_data {
+            self.ask_confirmation(&RiskyOperation::RemoveAppData)?;
+        }
+
+        if options.reset_folders {
+            report.reset = self.reset_normalized(normalize_folders(&existing));
+            report.remaining_folders = report
+                .reset
+                .results
+                .iter()
+                .filter(|(_, result)| result.is_err())
+                .map(|(folder, _)| folder.clone())
+                .collect();
+            for folder in &missing {
+                self.store.remove(folder);
+            }
+            self.store.save()?;
+            report.forgotten = missing;
+        } else {
+            report.remaining_folders = existing;
+        }
+
+        let remove = options.remove_app_data
+            && (options.force || report.remaining_folders.is_empty());
+        if remove {
+            // Cleared through the state store too, for backends that don't
+            // keep their data in the app data directory
+            for (folder, _) in self.store.entries() {
+                self.store.remove(&folder);
+            }
+            self.store.save()?;
+            let names: Vec<String> =
+                self.presets.iter().map(|preset| preset.name.clone()).collect();
+            for name in names {
+                self.thumbnails().remove(&name)?;
+                self.presets.remove(&name);
+            }
+            self.presets.save()?;
+            self.config = AppConfig::default();
+            self.state.remove_blob(CONFIG_FILE_NAME)?;
+
+            if self.cache.cache_dir().exists() {
+                report.removed.push(self.cache.cache_dir().to_path_buf());
+                self.cache.clear()?;
+            }
+            report.removed.extend(remove_app_files(&self.data_dir)?);
+        }
+
+        for path in [self.cache.cache_dir(), self.data_dir.as_path(), self.policy_path.as_path()] {
+            if path.exists() {
+                report.remaining_paths.push(path.to_path_buf());
+            }
+        }
+        Ok(report)
+    }
+
+    /// Returns the persisted app config.
+    pub fn config(&self) -> &AppConfig {
+        &self.config
+    }
+
+    /// Returns the admin policy loaded when the context was built.
+    pub fn policy(&self) -> &Policy {
+        &self.policy
+    }
+
+    /// Checks that `profile` complies with the admin policy and is within
+    /// the [payload limits](Self::payload_limits).
+    ///
+    /// Applying a profile checks this too; frontends can call it first to
+    /// explain the problem before the user starts a batch.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Error::Policy`] if the policy forbids the profile, or
+    /// [`Error::PayloadLimit`] if it's too large.
+    pub fn validate_profile(&self, profile: &CustomizationProfile) -> Result<()> {
+        self.policy.validate_profile(profile)?;
+        self.payload_limits.validate_profile(profile)
+    }
+
+    /// Returns the limits profiles must stay within.
+    pub fn payload_limits(&self) -> &PayloadLimits {
+        &self.payload_limits
+    }
+
+    /// Sets the limits profiles must stay within.
+    pub fn set_payload_limits(&mut self, limits: PayloadLimits) {
+        self.payload_limits = limits;
+    }
+
+    /// Returns the organization branding merged into every applied profile.
+    pub fn branding(&self) -> &Branding {
+        &self.config.branding
+    }
+
+    /// Sets the organization branding and saves the config.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Error::Layer`] if the logo layer is invalid.
+    pub fn set_branding(&mut self, branding: Branding) -> Result<()> {
+        self.ensure_writable("change the branding")?;
+        if let Some(logo) = &branding.logo {
+            logo.validate()?;
+        }
+        self.config.branding = branding;
+        self.save_config()
+    }
+
+    /// Returns the sharpening applied to small sizes after rendering, if
+    /// it's turned on.
+    pub fn small_icon_sharpening(&self) -> Option<&SharpenOptions> {
+        self.config.small_icon_sharpening.as_ref()
+    }
+
+    /// Turns sharpening of small sizes on, with `options`, or off, and saves
+    /// the config.
+    ///
+    /// It applies to icons rendered from now on; customized folders keep
+    /// their icons until they're applied again.
+    pub fn set_small_icon_sharpening(&mut self, options: Option<SharpenOptions>) -> Result<()> {
+        self.ensure_writable("change the config")?;
+        self.config.small_icon_sharpening = options;
+        self.save_config()
+    }
+
+    /// Returns the lightness curve for grey, white and black colors, if one
+    /// is set.
+    pub fn lightness_curve(&self) -> Option<&LightnessCurve> {
+        self.config.lightness_curve.as_ref()
+    }
+
+    /// Sets or clears the lightness curve used for grey, white and black
+    /// colors, and saves the config.
+    ///
+    /// Presets with a curve of their own use that one instead.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Error::LightnessCurve`] if the curve is invalid.
+    pub fn set_lightness_curve(&mut self, curve: Option<LightnessCurve>) -> Result<()> {
+        self.ensure_writable("change the config")?;
+        if let Some(curve) = &curve {
+            curve.validate()?;
+        }
+        self.config.lightness_curve = curve;
+        self.save_config()
+    }
+
+    /// Returns the commands run after each folder is customized or reset.
+    ///
+    /// Empty if the admin policy forbids command hooks, whatever the app
+    /// config lists.
+    pub fn command_hooks(&self) -> &[CommandHook] {
+        if self.policy.forbid_command_hooks {
+            return &[];
+        }
+        &self.config.command_hooks
+    }
+
+    /// Sets the commands run after each folder is customized or reset, and
+    /// saves the config.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Error::Policy`] if the admin policy forbids command hooks,
+    /// or [`Error::Hook`] if a hook is invalid.
+    pub fn set_command_hooks(&mut self, hooks: Vec<CommandHook>) -> Result<()> {
+        self.ensure_writable("change the config")?;
+        self.policy.check_command_hooks(&hooks)?;
+        for hook in &hooks {
+            hook.validate()?;
+        }
+        self.config.command_hooks = hooks;
+        self.save_config()
+    }
+
+    /// Returns the palettes the user imported.
+    pub fn palettes(&self) -> &[UserPalette] {
+        &self.config.palettes
+    }
+
+    /// Imports the ASE or GIMP palette at `path` and saves the config.
+    ///
+    /// A palette with the same name is replaced. Entries in color models
+    /// folco can't convert are listed in the result rather than failing
+    /// the import.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Error::Palette`] if the file can't be read as a palette.
+    pub fn import_palette(&mut self, path: impl AsRef<Path>) -> Result<PaletteImport> {
+        self.ensure_writable("import palettes")?;
+        let import = read_palette(path.as_ref())?;
+        let palettes = &mut self.config.palettes;
+        match palettes.iter_mut().find(|p| p.name == import.palette.name) {
+            Some(existing) => *existing = import.palette.clone(),
+            None => palettes.push(import.palette.clone()),
+        }
+        self.save_config()?;
+        Ok(import)
+    }
+
+    /// Removes a palette by name and saves the config.
+    pub fn remove_palette(&mut self, name: &str) -> Result<Option<UserPalette>> {
+        self.ensure_writable("remove palettes")?;
+        let Some(index) = self.config.palettes.iter().position(|p| p.name == name) else {
+            return Ok(None);
+        };
+        let removed = self.config.palettes.remove(index);
+        self.save_config()?;
+        Ok(Some(removed))
+    }
+
+    /// Returns the user's library roots.
+    pub fn library(&self) -> &Library {
+        &self.config.library
+    }
+
+    /// Registers a library root and saves the config.
+    ///
+    /// See [`Library::add_root`] for the rules a root must satisfy.
+    pub fn add_library_root(&mut self, name: impl Into<String>, path: impl AsRef<Path>) -> Result<LibraryRoot> {
+        self.ensure_writable("change the library")?;
+        let root = self.config.library.add_root(name, path)?.clone();
+        self.save_config()?;
+        Ok(root)
+    }
+
+    /// Unregisters a library root and saves the config.
+    ///
+    /// Returns the removed root, or `None` if no root had that name.
+    pub fn remove_library_root(&mut self, name: &str) -> Result<Option<LibraryRoot>> {
+        self.ensure_writable("change the library")?;
+        let removed = self.config.library.remove_root(name);
+        if removed.is_some() {
+            self.save_config()?;
+        }
+        Ok(removed)
+    }
+
+    /// Registers a security-scoped bookmark for a folder picked by the user.
+    ///
+    /// On macOS, access to the folder is started from the bookmark before
+    /// each operation on it, or on anything inside it, and stopped after.
+    /// This is what lets a sandboxed app write custom icons. A bookmark also
+    /// covers the folders inside the one it was made for. Elsewhere the
+    /// bookmark is ignored.
+    ///
+    /// Use [`resolve_bookmark`](crate::resolve_bookmark) to check for stale
+    /// bookmarks.
+    pub fn add_bookmark(&mut self, folder: impl AsRef<Path>, data: Vec<u8>) {
+        self.bookmarks.insert(folder.as_ref(), data);
+    }
+
+    /// Forgets the bookmark for a folder. Returns `true` if there was one.
+    pub fn remove_bookmark(&mut self, folder: impl AsRef<Path>) -> bool {
+        self.bookmarks.remove(folder.as_ref())
+    }
+
+    /// Returns the number of registered bookmarks.
+    pub fn bookmark_count(&self) -> usize {
+        self.bookmarks.len()
+    }
+
+    fn save_config(&self) -> Result<()> {
+        self.config.save_to(self.state.as_ref())
+    }
+
+    /// Fails with [`Error::ReadOnly`] if the context is read-only.
+    fn ensure_writable(&self, operation: &str) -> Result<()> {
+        if self.read_only {
+            return Err(Error::ReadOnly(operation.to_string()));
+        }
+        Ok(())
+    }
+
+    /// Returns how batch operations treat folders that are already customized.
+    pub fn conflict_policy(&self) -> &ConflictPolicy {
+        &self.conflict_policy
+    }
+
+    /// Sets how batch operations treat folders that are already customized.
+    pub fn set_conflict_policy(&mut self, policy: ConflictPolicy) {
+        self.conflict_policy = policy;
+    }
+
+    /// Sets or clears the executor for folder operations that need elevation.
+    pub fn set_privileged_executor(&mut self, executor: Option<Arc<dyn PrivilegedExecutor>>) {
+        self.privileged = executor;
+    }
+
+    /// Returns `true` if folder operations refused for lack of rights are
+    /// retried with a privileged executor.
+    pub fn has_privileged_executor(&self) -> bool {
+        self.privileged.is_some()
+    }
+
+    /// Sets or clears the executor for Windows drives reached from WSL.
+    ///
+    /// See [`CustomizationContextBuilder::with_host_executor`].
+    pub fn set_host_executor(&mut self, executor: Option<Arc<dyn PrivilegedExecutor>>) {
+        self.host = executor;
+    }
+
+    /// Sets or clears the telemetry sink, such as when the user opts in or
+    /// out.
+    pub fn set_telemetry_sink(&mut self, sink: Option<Arc<dyn TelemetrySink>>) {
+        self.telemetry = sink;
+    }
+
+    /// Returns `true` if batch metrics are reported to a telemetry sink.
+    pub fn has_telemetry_sink(&self) -> bool {
+        self.telemetry.is_some()
+    }
+
+    /// Adds a hook called around each folder that batches customize or
+    /// reset, after the ones already added.
+    ///
+    /// See [`CustomizationContextBuilder::with_hook`].
+    pub fn add_hook(&mut self, hook: Arc<dyn Hook>) {
+        self.hooks.push(hook);
+    }
+
+    /// Removes every hook.
+    pub fn clear_hooks(&mut self) {
+        self.hooks.clear();
+    }
+
+    /// Returns the number of hooks added.
+    pub fn hook_count(&self) -> usize {
+        self.hooks.len()
+    }
+
+    /// Replaces the handler asked before destructive operations.
+    ///
+    /// See [`CustomizationContextBuilder::with_confirmation_handler`].
+    pub fn set_confirmation_handler(&mut self, handler: Arc<dyn ConfirmationHandler>) {
+        self.confirmation = handler;
+    }
+
+    /// Returns the number of folders a batch may have before it needs
+    /// confirmation.
+    pub fn confirm_threshold(&self) -> usize {
+        self.confirm_threshold
+    }
+
+    /// Sets the number of folders a batch may have before it needs
+    /// confirmation.
+    pub fn set_confirm_threshold(&mut self, folders: usize) {
+        self.confirm_threshold = folders;
+    }
+
+    /// Returns how many folders async batches process between saving their
+    /// progress, or 0 if they never save it.
+    pub fn checkpoint_interval(&self) -> usize {
+        self.checkpoint_interval
+    }
+
+    /// Sets how many folders async batches process between saving their
+    /// progress.
+    pub fn set_checkpoint_interval(&mut self, folders: usize) {
+        self.checkpoint_interval = folders;
+    }
+
+    /// Returns the saved progress of async batches that didn't finish,
+    /// such as because the app quit or crashed, oldest first.
+    pub fn interrupted_batches(&self) -> Result<Vec<ProgressSnapshot>> {
+        load_snapshots(self.state.as_ref())
+    }
+
+    /// Forgets the saved progress of an interrupted batch, e.g. once it was
+    /// run again. Returns `true` if there was a snapshot `id`.
+    pub fn discard_interrupted_batch(&self, id: &str) -> Result<bool> {
+        self.ensure_writable("discard batch progress")?;
+        remove_snapshot(self.state.as_ref(), id)
+    }
+
+    /// Starts counting the folders of an async batch for checkpoints.
+    fn start_checkpoints(&self, operation: OperationKind, total: usize) -> Checkpoints {
+        let interval = if self.read_only {
+            0
+        } else {
+            self.checkpoint_interval
+        };
+        Checkpoints::start(Arc::clone(&self.state), self.log.clone(), interval, operation, total)
+    }
+
+    /// Asks the confirmation handler about `operation`, logging it if the
+    /// handler declines.
+    fn ask_confirmation(&self, operation: &RiskyOperation) -> Result<()> {
+        confirm(self.confirmation.as_ref(), operation).inspect_err(|e| self.log.info(e))
+    }
+
+    /// Asks for confirmation of a batch of `folders` unique folders, if
+    /// there are more than the confirmation threshold.
+    fn confirm_batch(&self, operation: OperationKind, folders: usize) -> Result<()> {
+        if folders <= self.confirm_threshold {
+            return Ok(());
+        }
+        self.ask_confirmation(&RiskyOperation::LargeBatch { operation, folders })
+    }
+
+    /// Asks for confirmation of a batch like
+    /// [`confirm_batch`](Self::confirm_batch), reporting a cancelled batch
+    /// through `progress`.
+    ///
+    /// Returns `true` if the batch may go ahead.
+    async fn confirm_batch_async(
+        &self,
+        operation: OperationKind,
+        folders: usize,
+        progress: &ProgressSender,
+    ) -> bool {
+        let Err(e) = self.confirm_batch(operation, folders) else {
+            return true;
+        };
+        let _ = progress
+            .send(Progress::Cancelled {
+                reason: e.to_string(),
+            })
+            .await;
+        let _ = progress
+            .send(Progress::Completed {
+                succeeded: 0,
+                failed: 0,
+                skipped: 0,
+            })
+            .await;
+        false
+    }
+
+    /// Reports whether `folder` can be customized so that its host's file
+    /// manager shows the icon, and by which mechanism.
+    ///
+    /// Folders that can't are failed with [`Error::Unsupported`] by batch
+    /// operations; GUIs can check up front to explain why.
+    pub fn apply_capability(&self, folder: &Path) -> ApplyCapability {
+        match self.route_folder(&normalize_folder_path(folder)) {
+            FolderRoute::Direct => ApplyCapability::Native,
+            FolderRoute::Host(..) => ApplyCapability::Host,
+            FolderRoute::Unsupported(reason) => ApplyCapability::Unsupported { reason },
+        }
+    }
+
+    /// Returns the throttling applied to batch operations.
+    pub fn throttle(&self) -> &ThrottleConfig {
+        &self.throttle
+    }
+
+    /// Sets the throttling applied to batch operations.
+    pub fn set_throttle(&mut self, throttle: ThrottleConfig) {
+        self.throttle = throttle;
+    }
+
+    /// Returns the work queue shared with other contexts.
+    pub fn work_queue(&self) -> &Arc<WorkQueue> {
+        &self.work_queue
+    }
+
+    /// Returns the priority of batch operations run by this context.
+    pub fn priority(&self) -> Priority {
+        self.priority
+    }
+
+    /// Sets the priority of batch operations run by this context.
+    pub fn set_priority(&mut self, priority: Priority) {
+        self.priority = priority;
+    }
+
+    /// Returns the per-folder timeout for batch operations, if any.
+    pub fn folder_timeout(&self) -> Option<Duration> {
+        self.apply_options.timeout
+    }
+
+    /// Sets the per-folder timeout for batch operations.
+    ///
+    /// Pass `None` to let folder operations run indefinitely.
+    pub fn set_folder_timeout(&mut self, timeout: Option<Duration>) {
+        self.apply_options.timeout = timeout;
+    }
+
+    /// Returns the options batch operations apply folders with.
+    pub fn apply_options(&self) -> &ApplyOptions {
+        &self.apply_options
+    }
+
+    /// Sets the options batch operations apply folders with.
+    pub fn set_apply_options(&mut self, options: ApplyOptions) {
+        self.apply_options = options;
+    }
+
+    /// Returns how customization batches retry folders that fail for a
+    /// passing reason.
+    pub fn retry_policy(&self) -> RetryPolicy {
+        self.apply_options.retry
+    }
+
+    /// Sets how customization batches retry folders that fail for a
+    /// passing reason.
+    pub fn set_retry_policy(&mut self, policy: RetryPolicy) {
+        self.apply_options.retry = policy;
+    }
+
+    /// Returns the base (uncustomized) icon set in renderer format.
+    ///
+    /// This is useful for folco-gui to pass to the WASM renderer.
+    pub fn base_icons(&self) -> &RendererIconSet {
+        self.customizer.base_icons()
+    }
+
+    /// Applies a customization profile to the customizer.
+    ///
+    /// This configures all layers according to the profile settings. Inline
+    /// SVG decals are [sanitized](crate::sanitize_svg) first, and a
+    /// [`Warning::SanitizedSvg`] lists anything removed.
+    pub fn apply_profile(&mut self, profile: &CustomizationProfile) {
+        if self.apply_sanitized(profile).is_err() {
+            self.customizer.apply_profile(&sanitized_or_default(profile));
+        }
+    }
+
+    /// Applies `profile` with its inline SVG decals sanitized, warning
+    /// about anything removed.
+    fn apply_sanitized(&mut self, profile: &CustomizationProfile) -> Result<()> {
+        let (sanitized, report) = sanitized_for_render(profile)?;
+        if !report.is_clean() {
+            push_unique(
+                &mut self.render_warnings,
+                Warning::SanitizedSvg {
+                    removed: report.removed,
+                },
+            );
+        }
+        self.customizer.apply_profile(&sanitized);
+        Ok(())
+    }
+
+    /// Exports the current customizer settings as a profile.
+    pub fn export_profile(&self) -> CustomizationProfile {
+        self.customizer.export_profile()
+    }
+
+    /// Renders and returns the customized icon set.
+    ///
+    /// This applies all active customizations and returns the result.
+    /// The returned icon set is in `folco-renderer` format.
+    pub fn render(&mut self) -> Result<RendererIconSet> {
+        Ok(self.customizer.render_all()?)
+    }
+
+    /// Returns and clears the warnings of renders since the last call, such
+    /// as profile settings that were left out because this renderer build
+    /// can't draw them.
+    ///
+    /// Batches report their own warnings in [`BatchOutcome::warnings`]; this
+    /// is for the methods returning an icon set, such as
+    /// [`render_layered`](Self::render_layered).
+    pub fn take_warnings(&mut self) -> Vec<Warning> {
+        std::mem::take(&mut self.render_warnings)
+    }
+
+    /// Renders `profile` and compares the result with `actual`, such as an
+    /// icon read back from a customized folder.
+    ///
+    /// The customizer is left configured with `profile`.
+    pub fn diff_render(&mut self, profile: &CustomizationProfile, actual: &SysIconSet) -> Result<IconDiff> {
+        let expected = self.render_sys_icons(profile)?;
+        Ok(diff_icon_sets(&expected, actual))
+    }
+
+    /// Customizes the icons for the specified folders.
+    ///
+    /// This method:
+    /// 1. Normalizes and deduplicates the folder list
+    /// 2. Applies the profile to the customizer
+    /// 3. Renders the customized icon set
+    /// 4. Converts to system format
+    /// 5. Applies to each folder, consulting the [`ConflictPolicy`] for
+    ///    folders that are already customized
+    /// 6. Records the applied profiles in the [`ProfileStore`]
+    ///
+    /// # Arguments
+    ///
+    /// * `folders` - Collection of folder paths to customize
+    /// * `profile` - The customization profile to apply
+    ///
+    /// # Returns
+    ///
+    /// A [`BatchOutcome`] with one result per unique folder. This allows
+    /// partial success where some folders succeed and others fail.
+    pub fn customize_folders<P: AsRef<Path>>(
+        &mut self,
+        folders: &[P],
+        profile: &CustomizationProfile,
+    ) -> BatchOutcome {
+        let options = self.apply_options.clone();
+        self.customize_folders_with_options(folders, profile, &options)
+    }
+
+    /// Customizes folders like [`customize_folders`](Self::customize_folders),
+    /// with `options` in place of the context's [`ApplyOptions`].
+    pub fn customize_folders_with_options<P: AsRef<Path>>(
+        &mut self,
+        folders: &[P],
+        profile: &CustomizationProfile,
+        options: &ApplyOptions,
+    ) -> BatchOutcome {
+        self.customize_folders_inner(folders, profile, RenderExtras::default(), options)
+    }
+
+    /// Customizes folders like [`customize_folders`](Self::customize_folders),
+    /// applying `overlay` on top of each folder's current customization
+    /// rather than on the default icon.
+    ///
+    /// Settings `overlay` leaves unset are kept from the profile the store
+    /// recorded for the folder, so an overlay with only a decal adds the
+    /// decal and keeps the folder's color. The store records the merged
+    /// profile, so overlays stack. Folders folco didn't customize get
+    /// `overlay` as it is, and custom icons folco didn't set are handled
+    /// by the [`ConflictPolicy`] as usual.
+    pub fn customize_folders_stacked<P: AsRef<Path>>(
+        &mut self,
+        folders: &[P],
+        overlay: &CustomizationProfile,
+    ) -> BatchOutcome {
+        let options = self.apply_options.clone();
+        let extras = RenderExtras {
+            stacked: true,
+            ..RenderExtras::default()
+        };
+        self.customize_folders_inner(folders, overlay, extras, &options)
+    }
+
+    /// Customizes folders like [`customize_folders`](Self::customize_folders),
+    /// rendering each size with its overrides flattened in.
+    ///
+    /// The store records the sized profile's base profile, since that's what
+    /// conflicts are merged against.
+    pub fn customize_folders_sized<P: AsRef<Path>>(
+        &mut self,
+        folders: &[P],
+        sized: &SizedProfile,
+    ) -> BatchOutcome {
+        let options = self.apply_options.clone();
+        let extras = RenderExtras {
+            overrides: &sized.overrides,
+            ..RenderExtras::default()
+        };
+        self.customize_folders_inner(folders, &sized.profile, extras, &options)
+    }
+
+    /// Renders a sized profile, flattening its overrides for each size.
+    ///
+    /// Each distinct flattened profile is rendered once. The customizer is
+    /// left configured with the base profile.
+    pub fn render_sized(&mut self, sized: &SizedProfile) -> Result<SysIconSet> {
+        let extras = RenderExtras {
+            overrides: &sized.overrides,
+            ..RenderExtras::default()
+        };
+        let icons = self.render_extended(&sized.profile, extras)?;
+        Ok(unshare_icons(icons))
+    }
+
+    /// Customizes folders like [`customize_folders`](Self::customize_folders),
+    /// drawing the profile's layers over every size.
+    ///
+    /// The store records the layered profile's base profile.
+    pub fn customize_folders_layered<P: AsRef<Path>>(
+        &mut self,
+        folders: &[P],
+        layered: &LayeredProfile,
+    ) -> BatchOutcome {
+        let options = self.apply_options.clone();
+        let extras = RenderExtras {
+            layers: &layered.layers,
+            ..RenderExtras::default()
+        };
+        self.customize_folders_inner(folders, &layered.profile, extras, &options)
+    }
+
+    /// Renders a layered profile: the profile itself, with its layers drawn
+    /// on top in z-order.
+    ///
+    /// The customizer is left configured with the base profile.
+    pub fn render_layered(&mut self, layered: &LayeredProfile) -> Result<SysIconSet> {
+        let extras = RenderExtras {
+            layers: &layered.layers,
+            ..RenderExtras::default()
+        };
+        let icons = self.render_extended(&layered.profile, extras)?;
+        Ok(unshare_icons(icons))
+    }
+
+    /// Renders `profile` as the apply paths do, for
+    /// [`render_with_base`](crate::render_with_base).
+    pub(crate) fn render_finished(&mut self, profile: &CustomizationProfile) -> Result<SysIconSet> {
+        let icons = self.render_extended(profile, RenderExtras::default())?;
+        Ok(unshare_icons(icons))
+    }
+
+    fn customize_folders_inner<P: AsRef<Path>>(
+        &mut self,
+        folders: &[P],
+        profile: &CustomizationProfile,
+        extras: RenderExtras<'_>,
+        options: &ApplyOptions,
+    ) -> BatchOutcome {
+        let started = Instant::now();
+        let work = self.work_queue.enter(self.priority);
+        let normalized = normalize_folders(folders);
+        let mut outcome = BatchOutcome {
+            duplicates: normalized.duplicates,
+            ..Default::default()
+        };
+        if let Err(e) = self.confirm_batch(OperationKind::Customize, normalized.folders.len()) {
+            outcome.error = Some(e);
+            return outcome;
+        }
+        self.render_warnings.clear();
+
+        // Render the customized icons
+        let sys_icons = match self.render_extended(profile, extras) {
+            Ok(icons) => icons,
+            Err(e) => {
+                outcome.error = Some(e);
+                self.report_batch(OperationKind::Customize, &outcome, started);
+                return outcome;
+            }
+        };
+        let mut merged_renders = HashMap::new();
+
+        // Apply to each folder
+        let mut throttle = Throttle::new(self.throttle.clone());
+        for folder in normalized.folders {
+            work.yield_to_interactive();
+
+            let (icons, applied) = match self.plan_folder(&folder, profile, extras.stacked) {
+                Ok(FolderPlan::Apply) => (Arc::clone(&sys_icons), Cow::Borrowed(profile)),
+                Ok(FolderPlan::Merge(merged)) => {
+                    match self.render_merged(&merged, extras, &mut merged_renders) {
+                        Ok(icons) => (icons, Cow::Owned(merged)),
+                        Err(e) => {
+                            outcome.results.push((folder, Err(e)));
+                            continue;
+                        }
+                    }
+                }
+                Ok(FolderPlan::Skip(conflict)) => {
+                    outcome.skipped.push(SkippedFolder {
+                        path: folder,
+                        conflict,
+                    });
+                    continue;
+                }
+                Err(e) => {
+                    outcome.results.push((folder, Err(e)));
+                    continue;
+                }
+            };
+
+            std::thread::sleep(throttle.delay());
+            let applying = Instant::now();
+            let result = self
+                .hooks
+                .before(OperationKind::Customize, &folder)
+                .and_then(|()| {
+                    let op = self.set_icon_op(&folder, &icons, options);
+                    run_folder_op(&options.retry, &folder, options.timeout, &op, &self.log)
+                });
+            if result.is_ok() {
+                self.update_stats(|stats| stats.record_apply(applying.elapsed()));
+            }
+            self.after_folder(OperationKind::Customize, &folder, &result);
+            if result.is_ok() {
+                self.store.insert(&folder, &applied);
+            }
+            outcome.results.push((folder, result));
+        }
+
+        // Leave the customizer configured with the requested profile
+        if !merged_renders.is_empty() {
+            self.apply_profile(profile);
+        }
+
+        self.update_stats(|stats| {
+            stats.record_customize(
+                outcome.succeeded_count(),
+                outcome.failed_count(),
+                outcome.skipped.len(),
+            )
+        });
+        if outcome.succeeded_count() > 0 {
+            outcome.error = self.store.save().err();
+        }
+        outcome.warnings = self.take_warnings();
+
+        self.report_batch(OperationKind::Customize, &outcome, started);
+        outcome
+    }
+
+    /// Resets the icons for the specified folders to the system default.
+    ///
+    /// Successfully reset folders are removed from the [`ProfileStore`].
+    ///
+    /// # Arguments
+    ///
+    /// * `folders` - Collection of folder paths to reset
+    ///
+    /// # Returns
+    ///
+    /// A [`BatchOutcome`] with one result per unique folder.
+    pub fn reset_folders<P: AsRef<Path>>(&self, folders: &[P]) -> BatchOutcome {
+        let normalized = normalize_folders(folders);
+        if let Err(e) = self.confirm_batch(OperationKind::Reset, normalized.folders.len()) {
+            return BatchOutcome {
+                duplicates: normalized.duplicates,
+                error: Some(e),
+                ..Default::default()
+            };
+        }
+        self.reset_normalized(normalized)
+    }
+
+    /// Resets already normalized folders, without asking for confirmation.
+    fn reset_normalized(&self, normalized: NormalizedFolders) -> BatchOutcome {
+        let started = Instant::now();
+        let work = self.work_queue.enter(self.priority);
+        let mut outcome = BatchOutcome {
+            duplicates: normalized.duplicates,
+            ..Default::default()
+        };
+
+        let mut throttle = Throttle::new(self.throttle.clone());
+        for folder in normalized.folders {
+            work.yield_to_interactive();
+            std::thread::sleep(throttle.delay());
+            let options = &self.apply_options;
+            let op = self.reset_icon_op(&folder, options);
+            let result = self
+                .ensure_writable("reset folders")
+                .and_then(|()| self.policy.check_folder(&folder))
+                .and_then(|()| self.hooks.before(OperationKind::Reset, &folder))
+                .and_then(|()| {
+                    run_folder_op(&options.retry, &folder, options.timeout, &op, &self.log)
+                });
+            self.after_folder(OperationKind::Reset, &folder, &result);
+            if result.is_ok() {
+                self.store.remove(&folder);
+            }
+            outcome.results.push((folder, result));
+        }
+
+        self.update_stats(|stats| {
+            stats.record_reset(outcome.succeeded_count(), outcome.failed_count())
+        });
+        if outcome.succeeded_count() > 0 {
+            outcome.error = self.store.save().err();
+        }
+
+        self.report_batch(OperationKind::Reset, &outcome, started);
+        outcome
+    }
+
+    /// Customizes the folders `selector` selects, like
+    /// [`customize_folders`](Self::customize_folders).
+    ///
+    /// Inputs the selector rejects, such as missing paths or patterns that
+    /// match nothing, become failed results with [`Error::Selection`]. Files
+    /// a pattern matched are left out; call [`FolderSelector::resolve`] to
+    /// list them.
+    pub fn customize_selected(
+        &mut self,
+        selector: &FolderSelector,
+        profile: &CustomizationProfile,
+    ) -> BatchOutcome {
+        let selection = selector.resolve();
+        let mut outcome = self.customize_folders(&selection.folders, profile);
+        selection.merge_into(&mut outcome);
+        outcome
+    }
+
+    /// Resets the folders `selector` selects, like
+    /// [`reset_folders`](Self::reset_folders).
+    ///
+    /// Rejected inputs are reported as in
+    /// [`customize_selected`](Self::customize_selected).
+    pub fn reset_selected(&self, selector: &FolderSelector) -> BatchOutcome {
+        let selection = selector.resolve();
+        let mut outcome = self.reset_folders(&selection.folders);
+        selection.merge_into(&mut outcome);
+        outcome
+    }
+
+    /// Estimates how long customizing `folders` with `profile` will take
+    /// and how much it will write, without changing anything.
+    ///
+    /// Timings come from the renders and folders this context has timed
+    /// so far, so estimates get closer to the truth as batches run. The
+    /// render is left out if its icons are already cached.
+    pub fn estimate_batch<P: AsRef<Path>>(
+        &self,
+        folders: &[P],
+        profile: &CustomizationProfile,
+    ) -> BatchEstimate {
+        let folders = normalize_folders(folders).folders.len();
+        let extras = RenderExtras::default();
+        let curve = self.render_curve(profile, extras);
+        let cached = self
+            .render_key(profile, extras, curve.as_ref())
+            .is_some_and(|key| self.artifacts.contains(&key));
+        BatchEstimate::new(folders, !cached, &self.operation_stats(), &self.base_icons)
+    }
+
+    /// Applies a customization manifest, such as one parsed with
+    /// [`parse_manifest_csv`](crate::parse_manifest_csv).
+    ///
+    /// Each entry's profile is its preset, recolored with its color if it has
+    /// one. Entries resolving to the same profile are applied as one batch,
+    /// and folders customized from a preset are recorded with its name.
+    /// Entries naming an unknown preset, or with neither a color nor a
+    /// preset, fail without being applied.
+    pub fn apply_manifest(&mut self, entries: &[ManifestEntry]) -> BatchOutcome {
+        let mut outcome = BatchOutcome::default();
+
+        // Group folders by the profile and preset they resolve to
+        let mut groups: Vec<(CustomizationProfile, Option<String>, Vec<PathBuf>)> = Vec::new();
+        let mut group_index: HashMap<(String, Option<String>), usize> = HashMap::new();
+        for entry in entries {
+            match self.manifest_profile(entry) {
+                Ok(profile) => {
+                    let key = (profile_hash(&profile), entry.preset.clone());
+                    let index = *group_index.entry(key).or_insert_with(|| {
+                        groups.push((profile, entry.preset.clone(), Vec::new()));
+                        groups.len() - 1
+                    });
+                    groups[index].2.push(entry.path.clone());
+                }
+                Err(e) => outcome
+                    .results
+                    .push((normalize_folder_path(&entry.path), Err(e))),
+            }
+        }
+
+        let mut recorded_presets = false;
+        for (profile, preset, folders) in groups {
+            let batch = self.customize_folders(&folders, &profile);
+            if let Some(name) = &preset {
+                recorded_presets |= self.record_preset_use(&batch, name);
+            }
+            outcome.extend(batch);
+        }
+
+        if recorded_presets && outcome.error.is_none() {
+            outcome.error = self.save_preset_usage().err();
+        }
+
+        outcome
+    }
+
+    /// Re-applies every folder colored with [`FolderColor::SystemAccent`] in
+    /// the current accent color.
+    ///
+    /// Call this after [`refresh_system_accent`](crate::refresh_system_accent)
+    /// reports a change; `AutoApply`, with the `watch` feature, does so on
+    /// its own.
+    /// Each folder keeps the rest of its profile and the preset it was
+    /// recorded with.
+    pub fn reapply_system_accent(&mut self) -> BatchOutcome {
+        let mut outcome = BatchOutcome::default();
+        let accent = FolderColor::SystemAccent.to_hsl_mutation_settings();
+
+        // Group folders by their recolored profile and preset
+        let mut groups: Vec<(CustomizationProfile, Option<String>, Vec<PathBuf>)> = Vec::new();
+        let mut group_index: HashMap<(String, Option<String>), usize> = HashMap::new();
+        for (path, stored) in self.store.entries() {
+            if !stored.system_accent {
+                continue;
+            }
+            match profile_with_hsl(&stored.profile, &accent) {
+                Ok(profile) => {
+                    let key = (profile_hash(&profile), stored.preset.clone());
+                    let index = *group_index.entry(key).or_insert_with(|| {
+                        groups.push((profile, stored.preset.clone(), Vec::new()));
+                        groups.len() - 1
+                    });
+                    groups[index].2.push(path);
+                }
+                Err(e) => outcome.results.push((path, Err(e))),
+            }
+        }
+
+        for (profile, preset, folders) in groups {
+            let batch = self.customize_folders(&folders, &profile);
+            if preset.is_some() {
+                for (path, _) in batch.results.iter().filter(|(_, r)| r.is_ok()) {
+                    self.store.set_preset(path, preset.clone());
+                }
+            }
+            outcome.extend(batch);
+        }
+
+        if outcome.succeeded_count() > 0 && outcome.error.is_none() {
+            outcome.error = self.store.save().err();
+        }
+        outcome
+    }
+
+    /// Customizes folders with a saved preset.
+    ///
+    /// The folders are recorded as customized from the preset, and the
+    /// preset's usage is counted.
+    pub fn apply_preset<P: AsRef<Path>>(&mut self, folders: &[P], name: &str) -> BatchOutcome {
+        let profile = match self.presets.profile(name) {
+            Ok(profile) => profile.clone(),
+            Err(e) => {
+                return BatchOutcome {
+                    error: Some(e),
+                    ..Default::default()
+                };
+            }
+        };
+
+        let curve = self.presets.get(name).and_then(|preset| preset.lightness_curve);
+        let options = self.apply_options.clone();
+        let extras = RenderExtras {
+            lightness_curve: curve.as_ref(),
+            ..RenderExtras::default()
+        };
+        let mut outcome = self.customize_folders_inner(folders, &profile, extras, &options);
+        if self.record_preset_use(&outcome, name) && outcome.error.is_none() {
+            outcome.error = self.save_preset_usage().err();
+        }
+        outcome
+    }
+
+    /// Customizes folders with the default preset.
+    ///
+    /// This is the one-click "make it blue" flow: the caller doesn't need a
+    /// profile, only folders. Fails with [`Error::NoDefaultPreset`] if no
+    /// default preset is configured.
+    pub fn quick_apply<P: AsRef<Path>>(&mut self, folders: &[P]) -> BatchOutcome {
+        match self.config.default_preset.clone() {
+            Some(name) => self.apply_preset(folders, &name),
+            None => BatchOutcome {
+                error: Some(Error::NoDefaultPreset),
+                ..Default::default()
+            },
+        }
+    }
+
+    /// Records the successful folders of a batch as customized from a
+    /// preset and counts the use. Returns `false` if nothing succeeded.
+    fn record_preset_use(&mut self, batch: &BatchOutcome, name: &str) -> bool {
+        if batch.succeeded_count() == 0 {
+            return false;
+        }
+        for (path, _) in batch.results.iter().filter(|(_, r)| r.is_ok()) {
+            self.store.set_preset(path, Some(name.to_string()));
+        }
+        self.presets.record_use(name);
+        true
+    }
+
+    fn save_preset_usage(&self) -> Result<()> {
+        self.store.save()?;
+        self.presets.save()
+    }
+
+    /// Resolves the profile a manifest entry asks for.
+    fn manifest_profile(&self, entry: &ManifestEntry) -> Result<CustomizationProfile> {
+        if entry.color.is_none() && entry.preset.is_none() {
+            return Err(Error::Manifest(format!(
+                "no color or preset given for '{}'",
+                entry.path.display()
+            )));
+        }
+        self.resolve_profile(entry.preset.as_deref(), entry.color)
+    }
+
+    /// Builds the profile for a preset recolored with a color preset. Either
+    /// may be omitted; with neither, the default profile is returned.
+    pub(crate) fn resolve_profile(
+        &self,
+        preset: Option<&str>,
+        color: Option<FolderColor>,
+    ) -> Result<CustomizationProfile> {
+        let base = match preset {
+            Some(name) => self.presets.profile(name)?.clone(),
+            None => CustomizationProfile::default(),
+        };
+        match color {
+            Some(color) => profile_with_color(&base, color),
+            None => Ok(base),
+        }
+    }
+
+    /// Returns the rules for customizing folders by name.
+    pub fn rules(&self) -> &RuleSet {
+        &self.config.rules
+    }
+
+    /// Replaces the rules and saves the config.
+    pub fn set_rules(&mut self, rules: RuleSet) -> Result<()> {
+        self.ensure_writable("change the rules")?;
+        self.config.rules = rules;
+        self.save_config()
+    }
+
+    /// Writes the rules to a portable ruleset file.
+    pub fn export_rules(&self, path: &Path) -> Result<()> {
+        export_ruleset(self.rules(), path)
+    }
+
+    /// Replaces the rules with those in a ruleset file.
+    ///
+    /// The file is validated as a whole first. With `dry_run`, nothing is
+    /// changed and the report only describes what the import would do.
+    ///
+    /// # Errors
+    ///
+    /// Fails if the file can't be read, is from a newer format version or has
+    /// invalid rules, or if the config can't be saved.
+    pub fn import_rules(&mut self, path: &Path, dry_run: bool) -> Result<RuleImportReport> {
+        if !dry_run {
+            self.ensure_writable("import rules")?;
+        }
+        let imported = read_ruleset(path)?;
+        let mut report = RuleImportReport::compare(self.rules(), &imported);
+
+        let mut missing: Vec<String> = imported
+            .rules()
+            .iter()
+            .filter_map(|rule| rule.preset.clone())
+            .filter(|name| self.presets.get(name).is_none())
+            .collect();
+        missing.sort();
+        missing.dedup();
+        report.missing_presets = missing;
+
+        if !dry_run && *self.rules() != imported {
+            self.set_rules(imported)?;
+            report.applied = true;
+        }
+        Ok(report)
+    }
+
+    /// Exports the presets, rules and profile store for syncing to other
+    /// machines.
+    pub fn export_synced_state(&self) -> SyncState {
+        SyncState::export(&machine_name(), &self.presets, self.rules(), &self.store)
+    }
+
+    /// Merges another machine's presets and rules into this one's.
+    ///
+    /// New presets and rules are added, and conflicting changes resolved as
+    /// described in [`SyncReport`]. Folders customized more recently on the
+    /// other machine are only listed, in [`SyncReport::to_apply`].
+    ///
+    /// # Errors
+    ///
+    /// Fails if the presets or the config can't be saved.
+    pub fn merge_synced_state(&mut self, remote: &SyncState) -> Result<SyncReport> {
+        self.ensure_writable("merge synced state")?;
+        let mut rules = self.config.rules.clone();
+        let report = merge_state(&mut self.presets, &mut rules, &self.store, remote);
+        if report.changed_presets() {
+            self.presets.save()?;
+        }
+        if !report.added_rules.is_empty() {
+            self.set_rules(rules)?;
+        }
+        Ok(report)
+    }
+
+    /// Syncs with the other machines sharing `dir`, such as a folder synced
+    /// with Dropbox or Syncthing.
+    ///
+    /// Every other machine's sync file in `dir` is merged, then this
+    /// machine's is rewritten. Machines only ever write their own file, so
+    /// syncing never produces conflicting copies of it.
+    ///
+    /// # Errors
+    ///
+    /// Fails if `dir` can't be read or written, a sync file can't be read,
+    /// or the merged state can't be saved.
+    pub fn sync_with_dir(&mut self, dir: &Path) -> Result<Vec<SyncReport>> {
+        self.ensure_writable("sync")?;
+        let machine = machine_name();
+        let mut reports = Vec::new();
+        if dir.is_dir() {
+            for path in remote_sync_files(dir, &machine)? {
+                let remote = read_synced_state(&path)?;
+                reports.push(self.merge_synced_state(&remote)?);
+            }
+        }
+        write_synced_state(&self.export_synced_state(), dir)?;
+        Ok(reports)
+    }
+
+    /// Shows what a rules pass over `root` would do, without applying anything.
+    ///
+    /// Every folder below `root` is checked against the rules; each folder
+    /// matched by a rule is returned with the customization the rules decide
+    /// on, the profile it would apply and any conflict between the rules.
+    ///
+    /// # Errors
+    ///
+    /// Fails if a matching rule names a preset that doesn't exist.
+    pub fn simulate_rules(&self, root: &Path) -> Result<Vec<RuleMatch>> {
+        let rules = self.rules();
+        let mut profiles: HashMap<(Option<String>, Option<FolderColor>), CustomizationProfile> =
+            HashMap::new();
+        let mut matches = Vec::new();
+
+        for path in subfolders(root) {
+            let Some(evaluation) = rules.evaluate(&path) else {
+                continue;
+            };
+            let rule = evaluation.rule;
+            let key = (rule.preset.clone(), rule.color);
+            let profile = match profiles.get(&key) {
+                Some(profile) => profile.clone(),
+                None => {
+                    let profile = self.resolve_profile(rule.preset.as_deref(), rule.color)?;
+                    profiles.insert(key, profile.clone());
+                    profile
+                }
+            };
+            matches.push(RuleMatch {
+                already_customized: self.store.resolve(&path).is_some(),
+                path,
+                rule,
+                applied: evaluation.applied,
+                profile,
+                conflict: evaluation.conflict,
+            });
+        }
+
+        Ok(matches)
+    }
+
+    /// Applies the rules to every folder below `root`.
+    ///
+    /// This applies exactly what [`simulate_rules`](Self::simulate_rules)
+    /// reports, one batch per distinct customization.
+    pub fn apply_rules(&mut self, root: &Path) -> Result<BatchOutcome> {
+        let mut by_rule: Vec<(Rule, Vec<PathBuf>)> = Vec::new();
+        for matched in self.simulate_rules(root)? {
+            match by_rule.iter_mut().find(|(rule, _)| *rule == matched.rule) {
+                Some((_, folders)) => folders.push(matched.path),
+                None => by_rule.push((matched.rule, vec![matched.path])),
+            }
+        }
+
+        let mut outcome = BatchOutcome::default();
+        for (rule, folders) in by_rule {
+            outcome.extend(self.apply_rule(&folders, &rule));
+        }
+        Ok(outcome)
+    }
+
+    /// Lists the folders below `root` matched by rules with different colors.
+    pub fn rule_conflicts(&self, root: &Path) -> Vec<RuleConflict> {
+        subfolders(root)
+            .iter()
+            .filter_map(|path| self.rules().evaluate(path)?.conflict)
+            .collect()
+    }
+
+    /// Customizes folders with the color and preset of a rule.
+    ///
+    /// Folders are recorded with the rule's preset, if it has one.
+    pub fn apply_rule<P: AsRef<Path>>(&mut self, folders: &[P], rule: &Rule) -> BatchOutcome {
+        if let Some(name) = &rule.preset
+            && rule.color.is_none()
+        {
+            return self.apply_preset(folders, name);
+        }
+
+        let profile = match self.resolve_profile(rule.preset.as_deref(), rule.color) {
+            Ok(profile) => profile,
+            Err(e) => {
+                return BatchOutcome {
+                    error: Some(e),
+                    ..Default::default()
+                };
+            }
+        };
+        let mut outcome = self.customize_folders(folders, &profile);
+        if let Some(name) = &rule.preset
+            && self.record_preset_use(&outcome, name)
+            && outcome.error.is_none()
+        {
+            outcome.error = self.save_preset_usage().err();
+        }
+        outcome
+    }
+
+    /// Reconciles the profile store with the filesystem.
+    ///
+    /// If `options` has no search roots, moved folders are searched for under
+    /// the library roots. See [`reconcile_store`](crate::reconcile_store) for
+    /// details.
+    pub fn reconcile_store(&self, options: &ReconcileOptions) -> Result<ReconcileReport> {
+        self.ensure_writable("reconcile the profile store")?;
+        if options.search_roots.is_empty() && !self.library().is_empty() {
+            let options = ReconcileOptions {
+                search_roots: self.library().paths(),
+                ..options.clone()
+            };
+            return reconcile_store(&self.store, &options);
+        }
+        reconcile_store(&self.store, options)
+    }
+
+    /// Finds profile store entries spelled differently from their folder on
+    /// disk, and with `fix`, renames or collapses them.
+    ///
+    /// See [`audit_store_case`](crate::audit_store_case).
+    pub fn audit_store_case(&self, fix: bool) -> Result<CaseAuditReport> {
+        if fix {
+            self.ensure_writable("fix the profile store")?;
+        }
+        audit_store_case(&self.store, fix)
+    }
+
+    /// Starts tracking renames below `roots`, keeping the profile store in sync.
+    ///
+    /// The returned watcher shares this context's store and stops when dropped.
+    #[cfg(feature = "watch")]
+    pub fn watch_folders<P: AsRef<Path>>(&self, roots: &[P]) -> Result<crate::watcher::FolderWatcher> {
+        let mut watcher =
+            crate::watcher::FolderWatcher::with_log(self.store.clone(), self.log.clone())?;
+        for root in roots {
+            watcher.watch(root.as_ref())?;
+        }
+        Ok(watcher)
+    }
+
+    /// Returns counts of batch operation results since the context was created.
+    pub fn operation_stats(&self) -> OperationStats {
+        *self.stats.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    /// Summarizes the customized folders, recent activity and cache for a
+    /// dashboard.
+    pub fn library_stats(&self) -> LibraryStats {
+        LibraryStats::collect(&self.store, self.library(), self.operation_stats(), &self.cache)
+    }
+
+    /// Writes a zip file of diagnostics to `path`, for attaching to a bug
+    /// report.
+    ///
+    /// The bundle has these JSON files:
+    ///
+    /// - `about.json`: versions, OS, features and the capability matrix, from
+    ///   [`about`](crate::about)
+    /// - `stats.json`: the [`library_stats`](Self::library_stats), with the
+    ///   most recently customized folders and operation counts
+    /// - `cache-manifest.json`: the icon cache's manifest, or `null` without
+    ///   a cache
+    /// - `checks.json`: [`SupportChecks`] of the profile store, which change
+    ///   nothing
+    /// - `logs.json`: the last [`SUPPORT_LOG_ENTRIES`](crate::SUPPORT_LOG_ENTRIES)
+    ///   [log entries](Self::recent_logs)
+    ///
+    /// Paths below the user's home directory are written starting with `~`,
+    /// and elsewhere the user and machine names are replaced with `<user>`
+    /// and `<machine>` where they make up a whole path component, as in
+    /// `/media/alice/USB`. Names in another case, or inside other text,
+    /// are left as they are.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the store can't be checked or the file can't be
+    /// written.
+    pub fn create_support_bundle(&self, path: impl AsRef<Path>) -> Result<()> {
+        let checks = SupportChecks::run(&self.store, audit_store_case(&self.store, false)?);
+        let files = [
+            ("about.json", to_value(&about())?),
+            ("stats.json", to_value(&self.library_stats())?),
+            ("cache-manifest.json", self.cache.manifest().unwrap_or_default()),
+            ("checks.json", to_value(&checks)?),
+            ("logs.json", to_value(&self.recent_logs(SUPPORT_LOG_ENTRIES))?),
+        ];
+        write_support_bundle(path.as_ref(), &files)
+    }
+
+    /// Calls the hooks after `folder` was processed, then runs the command
+    /// hooks if it succeeded, logging their failures.
+    fn after_folder(&self, operation: OperationKind, folder: &Path, result: &Result<()>) {
+        let command_hooks = self.command_hooks();
+        run_after_hooks(&self.hooks, command_hooks, &self.log, operation, folder, result);
+    }
+
+    /// Calls the hooks before `folder` is processed like
+    /// [`Hooks::before`], on a thread of their own so app code can't stall
+    /// the async runtime.
+    async fn before_folder_async(&self, operation: OperationKind, folder: &Path) -> Result<()> {
+        let hooks = self.hooks.clone();
+        let folder = folder.to_path_buf();
+        run_blocking_async(move || hooks.before(operation, &folder)).await
+    }
+
+    /// Like [`after_folder`](Self::after_folder), on a thread of its own so
+    /// hooks and commands can't stall the async runtime. Hands `result`
+    /// back.
+    async fn after_folder_async(
+        &self,
+        operation: OperationKind,
+        folder: &Path,
+        result: Result<()>,
+    ) -> Result<()> {
+        let hooks = self.hooks.clone();
+        let command_hooks = self.command_hooks().to_vec();
+        let log = self.log.clone();
+        let folder = folder.to_path_buf();
+        run_blocking_async(move || {
+            run_after_hooks(&hooks, &command_hooks, &log, operation, &folder, &result);
+            result
+        })
+        .await
+    }
+
+    /// Logs a finished batch and reports it to the telemetry sink, if there
+    /// is one.
+    fn report_batch(&self, operation: OperationKind, outcome: &BatchOutcome, started: Instant) {
+        for (folder, result) in &outcome.results {
+            if let Err(e) = result {
+                self.log.warn(format_args!("failed to {operation} {}: {e}", folder.display()));
+            }
+        }
+        if let Some(e) = &outcome.error {
+            self.log.error(format_args!("{operation} batch failed: {e}"));
+        }
+        let metrics = OperationMetrics::from_outcome(operation, outcome, started.elapsed());
+        self.report_metrics(&metrics);
+    }
+
+    /// Logs the metrics of a batch and reports them to the telemetry sink.
+    ///
+    /// Async batches, which have no [`BatchOutcome`], log their own failures
+    /// as they happen.
+    fn report_metrics(&self, metrics: &OperationMetrics) {
+        self.log.info(format_args!(
+            "{} batch: {} succeeded, {} failed, {} skipped in {}ms",
+            metrics.operation,
+            metrics.succeeded,
+            metrics.failed,
+            metrics.skipped,
+            metrics.duration_ms
+        ));
+        if let Some(sink) = &self.telemetry {
+            sink.record(metrics);
+        }
+    }
+
+    fn update_stats(&self, update: impl FnOnce(&mut OperationStats)) {
+        update(&mut self.stats.lock().unwrap_or_else(|e| e.into_inner()));
+    }
+
+    /// Searches the folders customized by folco.
+    ///
+    /// See [`search_customized`](crate::search_customized) for details.
+    pub fn search_customized(&self, query: &SearchQuery) -> SearchPage {
+        search_customized(&self.store, query)
+    }
+
+    /// Walks `root` and returns every folder below it, and `root` itself,
+    /// that shows an icon folco applied, with the profile it applied.
+    ///
+    /// Unlike [`search_customized`](Self::search_customized), this checks
+    /// the folders on disk: store records whose icon is gone are left out,
+    /// and with [`ScanOptions::include_foreign`], icons folco didn't apply
+    /// are reported too.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if `root` can't be read.
+    pub fn scan_customized(
+        &self,
+        root: &Path,
+        options: &ScanOptions,
+    ) -> Result<Vec<CustomizedFolder>> {
+        let backend = &self.folder_backend;
+        scan_customized(root, options, &self.store, |folder| backend.has_custom_icon(folder))
+    }
+
+    /// Starts tracking renames below every library root.
+    #[cfg(feature = "watch")]
+    pub fn watch_library(&self) -> Result<crate::watcher::FolderWatcher> {
+        self.watch_folders(&self.library().paths())
+    }
+
+    /// Checks whether a folder is already customized.
+    ///
+    /// Returns [`FolderConflict::FolcoProfile`] if the profile store has a
+    /// record for the folder, [`FolderConflict::ForeignIcon`] if the folder
+    /// has a custom icon that folco didn't apply, and `None` otherwise.
+    pub fn detect_conflict(&self, folder: &Path) -> Option<FolderConflict> {
+        if let Some((_, stored)) = self.store.resolve(folder) {
+            return Some(FolderConflict::FolcoProfile(stored));
+        }
+        self.folder_backend
+            .has_custom_icon(folder)
+            .then_some(FolderConflict::ForeignIcon)
+    }
+
+    /// Returns what `folder`'s icon currently is.
+    ///
+    /// Unlike [`detect_conflict`](Self::detect_conflict), this checks that
+    /// the icon is still there, so a folder whose folco icon was removed by
+    /// something else is reported as [`FolderIconState::Default`].
+    pub fn folder_icon_state(&self, folder: &Path) -> FolderIconState {
+        let folder = normalize_folder_path(folder);
+        let stored = self.store.resolve(&folder).map(|(_, stored)| stored);
+        let has_custom_icon = self.folder_backend.has_custom_icon(&folder);
+        FolderIconState::from_parts(stored.as_ref(), has_custom_icon)
+    }
+
+    /// Decides how to customize a single folder according to the conflict policy.
+    ///
+    /// When `stacked`, the folder's folco profile is always merged under
+    /// `profile`, and only foreign icons are left to the policy.
+    fn plan_folder(
+        &self,
+        folder: &Path,
+        profile: &CustomizationProfile,
+        stacked: bool,
+    ) -> Result<FolderPlan> {
+        self.ensure_writable("customize folders")?;
+        self.policy.check_folder(folder)?;
+        if !stacked && !self.conflict_policy.needs_detection() {
+            return Ok(FolderPlan::Apply);
+        }
+        let Some(conflict) = self.detect_conflict(folder) else {
+            return Ok(FolderPlan::Apply);
+        };
+        if stacked && let FolderConflict::FolcoProfile(stored) = &conflict {
+            return Ok(FolderPlan::Merge(merge_profiles(&stored.profile, profile)?));
+        }
+
+        match (self.conflict_policy.resolve(folder, &conflict), conflict) {
+            (ConflictResolution::Overwrite, _) => Ok(FolderPlan::Apply),
+            (ConflictResolution::Merge, FolderConflict::FolcoProfile(stored)) => {
+                Ok(FolderPlan::Merge(merge_profiles(&stored.profile, profile)?))
+            }
+            (_, conflict) => Ok(FolderPlan::Skip(conflict.kind())),
+        }
+    }
+
+    /// Renders `profile` and converts the result to system format.
+    ///
+    /// Settings the renderer rejects are left out, with a warning, as long
+    /// as the rest of the profile renders.
+    fn render_sys_icons(&mut self, profile: &CustomizationProfile) -> Result<Arc<SysIconSet>> {
+        let branded = self.branded_color(profile)?;
+        let profile = branded.as_ref().unwrap_or(profile);
+        match self.render_converted(profile) {
+            Err(Error::Render(error)) => match self.render_degraded(profile, error) {
+                Err(Error::Render(error)) => self.render_each_size(profile, error),
+                result => result,
+            },
+            result => result,
+        }
+    }
+
+    /// Renders exactly `profile` and converts the result to system format.
+    fn render_converted(&mut self, profile: &CustomizationProfile) -> Result<Arc<SysIconSet>> {
+        self.apply_sanitized(profile)?;
+        let rendered = self.render()?;
+        Ok(Arc::new(self.finish_render(&rendered)))
+    }
+
+    /// Converts rendered icons to system format, sharpening the small sizes
+    /// if the config asks for it.
+    fn finish_render(&self, rendered: &RendererIconSet) -> SysIconSet {
+        let mut icons = convert_icon_set_to_sys(rendered);
+        if let Some(options) = &self.config.small_icon_sharpening {
+            sharpen_small_icons(&mut icons, options);
+        }
+        icons
+    }
+
+    /// Renders `profile` one size at a time, after rendering all sizes
+    /// together failed with `error`, leaving out the sizes that fail.
+    ///
+    /// File managers scale the nearest size for the ones left out, which
+    /// beats not customizing the folder at all. If no size renders, `error`
+    /// is returned.
+    fn render_each_size(
+        &mut self,
+        profile: &CustomizationProfile,
+        error: RenderError,
+    ) -> Result<Arc<SysIconSet>> {
+        let (sanitized, _) = sanitized_for_render(profile)?;
+        let mut images = Vec::with_capacity(self.base_icons.images.len());
+        let mut warnings = Vec::new();
+        for image in &self.base_icons.images {
+            let single = SysIconSet {
+                images: vec![icon_sys::IconImage {
+                    data: image.data.clone(),
+                }],
+            };
+            let mut customizer = IconCustomizer::new(self.icon_style().icon_base(&single));
+            customizer.apply_profile(&sanitized);
+            match customizer.render_all() {
+                Ok(rendered) => images.extend(self.finish_render(&rendered).images),
+                Err(reason) => warnings.push(Warning::PartialRender {
+                    size: image.data.width(),
+                    reason: reason.to_string(),
+                }),
+            }
+        }
+        if images.is_empty() {
+            return Err(error.into());
+        }
+        for warning in warnings {
+            push_unique(&mut self.render_warnings, warning);
+        }
+        Ok(Arc::new(SysIconSet { images }))
+    }
+
+    /// Renders `profile` without the settings this renderer build can't
+    /// draw, after rendering all of it failed with `error`.
+    ///
+    /// Each optional setting is tried on its own, on top of the color, so
+    /// one unsupported feature doesn't take the supported ones down with
+    /// it. If the profile fails even without them, or only fails with all
+    /// of them together, the problem isn't a missing feature and `error` is
+    /// returned.
+    fn render_degraded(
+        &mut self,
+        profile: &CustomizationProfile,
+        error: RenderError,
+    ) -> Result<Arc<SysIconSet>> {
+        let optional = profile_optional_settings(profile);
+        if optional.is_empty() {
+            return Err(error.into());
+        }
+        match self.render_converted(&profile_without_settings(profile, &optional)?) {
+            Ok(_) => {}
+            Err(Error::Render(_)) => return Err(error.into()),
+            Err(e) => return Err(e),
+        }
+
+        let mut rejected = Vec::new();
+        let mut warnings = Vec::new();
+        for setting in &optional {
+            let others: Vec<String> =
+                optional.iter().filter(|other| *other != setting).cloned().collect();
+            match self.render_converted(&profile_without_settings(profile, &others)?) {
+                Ok(_) => {}
+                Err(Error::Render(reason)) => {
+                    rejected.push(setting.clone());
+                    warnings.push(Warning::DegradedFeature {
+                        setting: setting.clone(),
+                        reason: reason.to_string(),
+                    });
+                }
+                Err(e) => return Err(e),
+            }
+        }
+        if rejected.is_empty() {
+            return Err(error.into());
+        }
+
+        let icons = match self.render_converted(&profile_without_settings(profile, &rejected)?) {
+            Ok(icons) => icons,
+            Err(Error::Render(_)) => return Err(error.into()),
+            Err(e) => return Err(e),
+        };
+        for warning in warnings {
+            push_unique(&mut self.render_warnings, warning);
+        }
+        Ok(icons)
+    }
+
+    /// Returns `profile` with its color moved into the branding's color
+    /// range, or `None` if it's already inside or there's no range.
+    fn branded_color(&self, profile: &CustomizationProfile) -> Result<Option<CustomizationProfile>> {
+        let Some(range) = &self.config.branding.color_range else {
+            return Ok(None);
+        };
+        let Some(settings) = profile_hsl(profile).filter(|settings| settings.enabled) else {
+            return Ok(None);
+        };
+        let (hue, saturation, lightness) =
+            (settings.target_hue, settings.target_saturation, settings.target_lightness);
+        if range.contains(hue, saturation, lightness) {
+            return Ok(None);
+        }
+        profile_with_hsl(profile, &range.constrain(&settings)).map(Some)
+    }
+
+    /// Renders a merged profile, reusing earlier renders of the same settings.
+    fn render_merged(
+        &mut self,
+        merged: &CustomizationProfile,
+        extras: RenderExtras<'_>,
+        renders: &mut HashMap<String, Arc<SysIconSet>>,
+    ) -> Result<Arc<SysIconSet>> {
+        let hash = profile_hash(merged);
+        if let Some(icons) = renders.get(&hash) {
+            return Ok(Arc::clone(icons));
+        }
+        let icons = self.render_extended(merged, extras)?;
+        renders.insert(hash, Arc::clone(&icons));
+        Ok(icons)
+    }
+
+    /// Renders `profile` with `extras` applied on top of what the renderer
+    /// draws.
+    ///
+    /// Every apply path renders through here, so this is where the admin
+    /// policy's profile restrictions and the payload limits are enforced,
+    /// and where finished icons are cached.
+    fn render_extended(
+        &mut self,
+        profile: &CustomizationProfile,
+        extras: RenderExtras<'_>,
+    ) -> Result<Arc<SysIconSet>> {
+        self.validate_profile(profile)?;
+        let curve = self.render_curve(profile, extras);
+        if let Some(curve) = &curve {
+            curve.validate()?;
+        }
+        let key = self.render_key(profile, extras, curve.as_ref());
+        if let Some(key) = &key {
+            let cached = self.artifacts.get(key);
+            self.update_stats(|stats| stats.record_artifact_lookup(cached.is_some()));
+            if let Some(icons) = cached {
+                self.apply_profile(profile);
+                return Ok(icons);
+            }
+        }
+
+        let earlier = std::mem::take(&mut self.render_warnings);
+        let render_started = Instant::now();
+        let rendered = self
+            .render_sized_sys_icons(profile, extras.overrides)
+            .map(|icons| self.synthesize_sizes(icons));
+        if rendered.is_ok() {
+            self.update_stats(|stats| stats.record_render(render_started.elapsed()));
+        }
+        let warnings = std::mem::replace(&mut self.render_warnings, earlier);
+        if self.strict
+            && rendered.is_ok()
+            && let Some(warning) = warnings.first()
+        {
+            return Err(Error::Strict(warning.clone()));
+        }
+        let degraded = !warnings.is_empty();
+        for warning in warnings {
+            push_unique(&mut self.render_warnings, warning);
+        }
+        let mut icons = rendered?;
+        if let Some(curve) = &curve {
+            let mut adjusted = unshare_icons(icons);
+            curve.apply(&mut adjusted);
+            icons = Arc::new(adjusted);
+        }
+        if let Some(key) = &key {
+            // Degraded renders are redone, so their warnings are reported
+            // again. A cache that can't be written only costs a render later
+            if !degraded {
+                let _ = self.artifacts.insert(key, &icons);
+            }
+            return Ok(icons);
+        }
+
+        let logo = self.config.branding.logo.as_slice();
+        let layered = composite_layers(&icons, extras.layers)?;
+        // The logo goes over everything, whatever the profile's z-indexes
+        Ok(Arc::new(composite_layers(&layered, logo)?))
+    }
+
+    /// Adds the sizes the platform prefers that `icons` lacks, scaled from
+    /// the sizes it has, warning about them.
+    fn synthesize_sizes(&mut self, icons: Arc<SysIconSet>) -> Arc<SysIconSet> {
+        match synthesize_missing_sizes(&icons, crate::sys::PREFERRED_SIZES) {
+            Some((filled, sizes)) => {
+                push_unique(&mut self.render_warnings, Warning::SynthesizedSizes { sizes });
+                Arc::new(filled)
+            }
+            None => icons,
+        }
+    }
+
+    /// Returns the lightness curve a render of `profile` applies, if any.
+    fn render_curve(
+        &self,
+        profile: &CustomizationProfile,
+        extras: RenderExtras<'_>,
+    ) -> Option<LightnessCurve> {
+        extras
+            .lightness_curve
+            .or(self.config.lightness_curve.as_ref())
+            .filter(|curve| !curve.is_identity() && targets_grey(profile))
+            .copied()
+    }
+
+    /// Returns the key the finished icons of a render of `profile` are
+    /// cached under, or `None` if they aren't cached.
+    fn render_key(
+        &self,
+        profile: &CustomizationProfile,
+        extras: RenderExtras<'_>,
+        curve: Option<&LightnessCurve>,
+    ) -> Option<String> {
+        // Image layers are read from files that can change behind the same
+        // path, so only renders without them are cached
+        let cacheable = extras.layers.is_empty() && self.config.branding.logo.is_none();
+        cacheable.then(|| {
+            let settings = ArtifactSettings {
+                color_range: self.config.branding.color_range.as_ref(),
+                sharpening: self.config.small_icon_sharpening.as_ref(),
+                lightness_curve: curve,
+            };
+            artifact_key(profile, extras.overrides, &settings)
+        })
+    }
+
+    /// Renders `profile`, replacing each size that `overrides` cover with a
+    /// render of the flattened profile for that size.
+    fn render_sized_sys_icons(
+        &mut self,
+        profile: &CustomizationProfile,
+        overrides: &[SizeOverride],
+    ) -> Result<Arc<SysIconSet>> {
+        let base = self.render_sys_icons(profile)?;
+        if overrides.is_empty() {
+            return Ok(base);
+        }
+
+        let mut renders = HashMap::new();
+        renders.insert(profile_hash(profile), Arc::clone(&base));
+        let mut images = Vec::with_capacity(base.images.len());
+        for image in &base.images {
+            let (width, height) = (image.data.width(), image.data.height());
+            let flattened = flatten_for_size(profile, overrides, width)?;
+            let hash = profile_hash(&flattened);
+            let rendered = match renders.get(&hash) {
+                Some(rendered) => Arc::clone(rendered),
+                None => {
+                    let rendered = self.render_sys_icons(&flattened)?;
+                    renders.insert(hash, Arc::clone(&rendered));
+                    rendered
+                }
+            };
+            let data = rendered
+                .images
+                .iter()
+                .find(|other| other.data.width() == width && other.data.height() == height)
+                .map_or_else(|| image.data.clone(), |other| other.data.clone());
+            images.push(icon_sys::IconImage { data });
+        }
 
-        // Convert to system format
-        let sys_icons = convert_icon_set_to_sys(&rendered);
+        // Leave the customizer configured with the requested profile
+        if renders.len() > 1 {
+            self.apply_profile(profile);
+        }
+        Ok(Arc::new(SysIconSet { images }))
+    }
 
-        // Apply to each folder
-        folders
-            .iter()
-            .map(|folder| {
-                self.folder_provider
-                    .set_icon_for_folder(folder.as_ref(), &sys_icons)
-                    .map_err(|e| {
-                        Error::FolderCustomization(folder.as_ref().to_path_buf(), e.to_string())
-                    })
-            })
-            .collect()
+    /// Decides which mechanism customizes `folder`, by where it lives
+    /// relative to the WSL boundary.
+    fn route_folder(&self, folder: &Path) -> FolderRoute {
+        if self.render_only {
+            return FolderRoute::Unsupported(RENDER_ONLY_REASON.to_string());
+        }
+        match classify_path(folder) {
+            PathLocation::Native => FolderRoute::Direct,
+            PathLocation::WslDistribution { distro } => FolderRoute::Unsupported(format!(
+                "Explorer can't show custom icons for folders inside the {distro} WSL distribution"
+            )),
+            PathLocation::WindowsDrive { drive } => {
+                let Some(host) = &self.host else {
+                    return FolderRoute::Unsupported(format!(
+                        "the folder is on Windows drive {drive}:, which needs a host executor to customize from WSL"
+                    ));
+                };
+                match to_windows_path(folder) {
+                    Some(path) => FolderRoute::Host(Arc::clone(host), path),
+                    None => FolderRoute::Unsupported(
+                        "the folder's path can't be translated to a Windows path".to_string(),
+                    ),
+                }
+            }
+        }
     }
 
-    /// Resets the icons for the specified folders to the system default.
-    ///
-    /// # Arguments
-    ///
-    /// * `folders` - Collection of folder paths to reset
+    /// Builds the operation that applies `icons` to a single folder.
     ///
-    /// # Returns
-    ///
-    /// A vector of results, one for each folder.
-    pub fn reset_folders<P: AsRef<Path>>(&self, folders: &[P]) -> Vec<Result<()>> {
-        folders
-            .iter()
-            .map(|folder| {
-                self.folder_provider
-                    .reset_icon_for_folder(folder.as_ref())
-                    .map_err(|e| Error::FolderReset(folder.as_ref().to_path_buf(), e.to_string()))
-            })
-            .collect()
+    /// The operation owns everything it needs so it can run on another thread
+    /// when a folder timeout is configured, and can be run again for each
+    /// retry. Folders across the WSL boundary are routed as
+    /// [`route_folder`](Self::route_folder) decides; if the folder refuses
+    /// the change for lack of rights, it's retried with the privileged
+    /// executor.
+    fn set_icon_op(
+        &self,
+        folder: &Path,
+        icons: &Arc<SysIconSet>,
+        options: &ApplyOptions,
+    ) -> FolderOp {
+        let backend = Arc::clone(&self.folder_backend);
+        let icons = Arc::clone(icons);
+        let bookmark = self.bookmarks.covering(folder);
+        let privileged = self.privileged.clone();
+        let route = self.route_folder(folder);
+        let options = self.folder_options(options);
+        let folder = folder.to_path_buf();
+        Arc::new(move || {
+            match &route {
+                FolderRoute::Direct => {}
+                FolderRoute::Host(host, path) => return host.set_icon(path, &icons),
+                FolderRoute::Unsupported(reason) => {
+                    return Err(Error::Unsupported(folder.clone(), reason.clone()));
+                }
+            }
+            let result = with_access(bookmark.as_deref(), || {
+                backend.set_icon_with_options(&folder, &icons, &options)
+            });
+            let result = match (result, &privileged) {
+                (Err(e), Some(executor)) if needs_elevation(&e) => {
+                    executor.set_icon(&folder, &icons)?;
+                    // The helper's files are out of reach, so leave them be
+                    backend.refresh(&folder, options.refresh);
+                    Ok(())
+                }
+                (result, _) => result,
+            };
+            result.and_then(|()| verify_applied(backend.as_ref(), &folder, &options))
+        })
+    }
+
+    /// Returns `options` as single folders are applied with them: strict
+    /// contexts always verify.
+    fn folder_options(&self, options: &ApplyOptions) -> ApplyOptions {
+        ApplyOptions {
+            verify: options.verify || self.strict,
+            ..options.clone()
+        }
+    }
+
+    /// Builds the operation that resets a single folder to the default icon.
+    fn reset_icon_op(
+        &self,
+        folder: &Path,
+        options: &ApplyOptions,
+    ) -> FolderOp {
+        let backend = Arc::clone(&self.folder_backend);
+        let bookmark = self.bookmarks.covering(folder);
+        let privileged = self.privileged.clone();
+        let route = self.route_folder(folder);
+        let options = self.folder_options(options);
+        let folder = folder.to_path_buf();
+        Arc::new(move || {
+            match &route {
+                FolderRoute::Direct => {}
+                FolderRoute::Host(host, path) => return host.reset_icon(path),
+                FolderRoute::Unsupported(reason) => {
+                    return Err(Error::Unsupported(folder.clone(), reason.clone()));
+                }
+            }
+            let result = with_access(bookmark.as_deref(), || {
+                backend.reset_icon_with_options(&folder, &options)
+            });
+            let result = match (result, &privileged) {
+                (Err(e), Some(executor)) if needs_elevation(&e) => {
+                    executor.reset_icon(&folder)?;
+                    backend.refresh(&folder, options.refresh);
+                    Ok(())
+                }
+                (result, _) => result,
+            };
+            result.and_then(|()| verify_reset(backend.as_ref(), &folder, &options))
+        })
     }
 
     /// Customizes a single folder with the given profile.
@@ -320,19 +3166,37 @@ impl CustomizationContext {
         profile: &CustomizationProfile,
     ) -> Result<()> {
         self.customize_folders(&[folder], profile)
-            .into_iter()
-            .next()
-            .unwrap_or(Ok(()))
+            .into_single_result()
     }
 
     /// Resets a single folder to the system default icon.
     ///
     /// Convenience method for resetting a single folder.
     pub fn reset_folder<P: AsRef<Path>>(&self, folder: P) -> Result<()> {
-        self.reset_folders(&[folder])
-            .into_iter()
-            .next()
-            .unwrap_or(Ok(()))
+        self.reset_folders(&[folder]).into_single_result()
+    }
+
+    /// Returns the profiles folco applied to `folder`, most recent first,
+    /// starting with the current one.
+    ///
+    /// The history is kept with the folder's record, so it's empty once
+    /// the folder is reset.
+    pub fn folder_history(&self, folder: &Path) -> Vec<HistoryEntry> {
+        self.store.history(folder)
+    }
+
+    /// Applies the profile of a [`folder_history`](Self::folder_history)
+    /// entry to its folder again, along with the preset it came from.
+    ///
+    /// This is recorded like any other customization, so the look it
+    /// replaces joins the history and can be reverted to in turn.
+    pub fn revert_to(&mut self, entry: &HistoryEntry) -> Result<()> {
+        self.customize_folder(&entry.folder, &entry.profile)?;
+        if entry.preset.is_some() {
+            self.store.set_preset(&entry.folder, entry.preset.clone());
+            self.store.save()?;
+        }
+        Ok(())
     }
 
     /// Resets the icons for the specified folders to system default with progress reporting.
@@ -360,17 +3224,39 @@ impl CustomizationContext {
         folders: Vec<P>,
         progress: ProgressSender,
     ) {
+        let started = Instant::now();
+        let work = self.work_queue.enter(self.priority);
+        let normalized = normalize_folders(&folders);
+        let folders = normalized.folders;
         let total = folders.len();
 
         // Send started event
         let _ = progress.send(Progress::Started { total }).await;
+        if !normalized.duplicates.is_empty() {
+            let _ = progress
+                .send(Progress::DuplicatesMerged {
+                    duplicates: normalized.duplicates,
+                })
+                .await;
+        }
+
+        if !self.confirm_batch_async(OperationKind::Reset, total, &progress).await {
+            return;
+        }
 
         let mut succeeded = 0usize;
         let mut failed = 0usize;
+        let mut metrics = OperationMetrics::new(OperationKind::Reset);
+        let mut checkpoints = self.start_checkpoints(OperationKind::Reset, total);
 
         // Process each folder
-        for (index, folder) in folders.iter().enumerate() {
-            let path = folder.as_ref().to_path_buf();
+        let mut throttle = Throttle::new(self.throttle.clone());
+        for (index, path) in folders.into_iter().enumerate() {
+            work.yield_to_interactive_async().await;
+            let delay = throttle.delay();
+            if !delay.is_zero() {
+                tokio::time::sleep(delay).await;
+            }
 
             // Send processing event
             let _ = progress
@@ -381,15 +3267,36 @@ impl CustomizationContext {
                 .await;
 
             // Reset the icon
-            match self.folder_provider.reset_icon_for_folder(folder.as_ref()) {
+            let options = &self.apply_options;
+            let op = self.reset_icon_op(&path, options);
+            let checked = self
+                .ensure_writable("reset folders")
+                .and_then(|()| self.policy.check_folder(&path));
+            let checked = match checked {
+                Ok(()) => self.before_folder_async(OperationKind::Reset, &path).await,
+                Err(e) => Err(e),
+            };
+            let result = match checked {
+                Ok(()) => {
+                    run_folder_op_async(&options.retry, &path, options.timeout, &op, &self.log)
+                        .await
+                }
+                Err(e) => Err(e),
+            };
+            let result = self.after_folder_async(OperationKind::Reset, &path, result).await;
+            let outcome = FolderOutcome::from(&result);
+            match result {
                 Ok(()) => {
                     succeeded += 1;
+                    self.store.remove(&path);
                     let _ = progress
                         .send(Progress::FolderComplete { index, path })
                         .await;
                 }
                 Err(e) => {
                     failed += 1;
+                    metrics.count_error(&e);
+                    self.log.warn(format_args!("failed to reset {}: {e}", path.display()));
                     let _ = progress
                         .send(Progress::FolderFailed {
                             index,
@@ -399,19 +3306,53 @@ impl CustomizationContext {
                         .await;
                 }
             }
+            checkpoints.record_async(outcome, &progress).await;
+        }
+        checkpoints.finish();
+
+        self.update_stats(|stats| stats.record_reset(succeeded, failed));
+        if succeeded > 0 {
+            self.save_store_async(&progress).await;
         }
+        metrics.succeeded = succeeded;
+        metrics.failed = failed;
+        self.report_metrics(&metrics.with_duration(started.elapsed()));
 
         // Send completed event
-        let _ = progress.send(Progress::Completed { succeeded, failed }).await;
+        let _ = progress
+            .send(Progress::Completed {
+                succeeded,
+                failed,
+                skipped: 0,
+            })
+            .await;
+    }
+
+    /// Saves the profile store, reporting a failure through `progress`.
+    async fn save_store_async(&self, progress: &ProgressSender) {
+        if let Err(e) = self.store.save() {
+            let _ = progress
+                .send(Progress::StoreSaveFailed {
+                    error: e.to_string(),
+                })
+                .await;
+        }
     }
 
     /// Clears the icon cache and refreshes from system resources.
     pub fn refresh_cache(&mut self) -> Result<()> {
-        let sys_icons = self.cache.refresh()?;
-        let renderer_icons = convert_icon_set(&sys_icons);
-        let icon_base = IconBase::new(renderer_icons, crate::sys::SURFACE_COLOR);
-        self.customizer = IconCustomizer::new(icon_base);
-        Ok(())
+        self.ensure_writable("refresh the icon cache")?;
+        let mut sys_icons = match &self.default_icons {
+            Some(backend) => backend.default_folder_icon()?,
+            None if self.cache_in_memory => extract_folder_icon()?.0,
+            None => self.cache.refresh()?,
+        };
+        if self.jumbo_sizes {
+            sys_icons = crate::sys::add_jumbo_sizes(sys_icons);
+        }
+        self.customizer = IconCustomizer::new(self.icon_style().icon_base(&sys_icons));
+        self.base_icons = Arc::new(sys_icons);
+        self.artifacts.clear()
     }
 
     /// Customizes the icons for the specified folders with progress reporting.
@@ -451,39 +3392,45 @@ impl CustomizationContext {
         profile: &CustomizationProfile,
         progress: ProgressSender,
     ) {
+        let started = Instant::now();
+        let work = self.work_queue.enter(self.priority);
+        let normalized = normalize_folders(&folders);
+        let folders = normalized.folders;
         let total = folders.len();
 
         // Send started event
         let _ = progress.send(Progress::Started { total }).await;
+        if !normalized.duplicates.is_empty() {
+            let _ = progress
+                .send(Progress::DuplicatesMerged {
+                    duplicates: normalized.duplicates,
+                })
+                .await;
+        }
+
+        if !self.confirm_batch_async(OperationKind::Customize, total, &progress).await {
+            return;
+        }
 
         // Apply the profile and render
-        let _ = progress.send(Progress::Rendering).await;
-        self.apply_profile(profile);
-        let rendered = match self.render() {
-            Ok(icons) => icons,
-            Err(e) => {
-                let _ = progress
-                    .send(Progress::RenderFailed {
-                        error: e.to_string(),
-                    })
-                    .await;
-                let _ = progress
-                    .send(Progress::Completed {
-                        succeeded: 0,
-                        failed: total,
-                    })
-                    .await;
-                return;
-            }
+        let mut metrics = OperationMetrics::new(OperationKind::Customize);
+        let Some(sys_icons) = self
+            .render_batch_async(profile, total, started, &mut metrics, &progress)
+            .await
+        else {
+            return;
         };
-        let sys_icons = convert_icon_set_to_sys(&rendered);
+        let mut merged_renders = HashMap::new();
+        let mut checkpoints = self.start_checkpoints(OperationKind::Customize, total);
 
         let mut succeeded = 0usize;
         let mut failed = 0usize;
+        let mut skipped = 0usize;
 
         // Process each folder
-        for (index, folder) in folders.iter().enumerate() {
-            let path = folder.as_ref().to_path_buf();
+        let mut throttle = Throttle::new(self.throttle.clone());
+        for (index, path) in folders.into_iter().enumerate() {
+            work.yield_to_interactive_async().await;
 
             // Send processing event
             let _ = progress
@@ -493,11 +3440,60 @@ impl CustomizationContext {
                 })
                 .await;
 
-            // Apply the icon
-            match self
-                .folder_provider
-                .set_icon_for_folder(folder.as_ref(), &sys_icons)
-            {
+            let planned = match self.plan_folder(&path, profile, false) {
+                Ok(FolderPlan::Apply) => Ok((Arc::clone(&sys_icons), Cow::Borrowed(profile))),
+                Ok(FolderPlan::Merge(merged)) => self
+                    .render_merged(&merged, RenderExtras::default(), &mut merged_renders)
+                    .map(|icons| (icons, Cow::Owned(merged))),
+                Ok(FolderPlan::Skip(conflict)) => {
+                    skipped += 1;
+                    let _ = progress
+                        .send(Progress::FolderSkipped {
+                            index,
+                            path,
+                            conflict,
+                        })
+                        .await;
+                    checkpoints.record_async(FolderOutcome::Skipped, &progress).await;
+                    continue;
+                }
+                Err(e) => Err(e),
+            };
+
+            let result = match planned {
+                Ok((icons, applied)) => {
+                    let delay = throttle.delay();
+                    if !delay.is_zero() {
+                        tokio::time::sleep(delay).await;
+                    }
+
+                    // Apply the icon
+                    let applying = Instant::now();
+                    let before = self.before_folder_async(OperationKind::Customize, &path).await;
+                    let result = match before {
+                        Ok(()) => {
+                            let options = &self.apply_options;
+                            let op = self.set_icon_op(&path, &icons, options);
+                            let (retry, timeout) = (&options.retry, options.timeout);
+                            run_folder_op_async(retry, &path, timeout, &op, &self.log).await
+                        }
+                        Err(e) => Err(e),
+                    };
+                    if result.is_ok() {
+                        self.update_stats(|stats| stats.record_apply(applying.elapsed()));
+                    }
+                    let result =
+                        self.after_folder_async(OperationKind::Customize, &path, result).await;
+                    if result.is_ok() {
+                        self.store.insert(&path, &applied);
+                    }
+                    result
+                }
+                Err(e) => Err(e),
+            };
+
+            let outcome = FolderOutcome::from(&result);
+            match result {
                 Ok(()) => {
                     succeeded += 1;
                     let _ = progress
@@ -509,6 +3505,8 @@ impl CustomizationContext {
                 }
                 Err(e) => {
                     failed += 1;
+                    metrics.count_error(&e);
+                    self.log.warn(format_args!("failed to customize {}: {e}", path.display()));
                     let _ = progress
                         .send(Progress::FolderFailed {
                             index,
@@ -518,16 +3516,279 @@ impl CustomizationContext {
                         .await;
                 }
             }
+            checkpoints.record_async(outcome, &progress).await;
+        }
+        checkpoints.finish();
+
+        // Leave the customizer configured with the requested profile
+        if !merged_renders.is_empty() {
+            self.apply_profile(profile);
+        }
+
+        metrics.succeeded = succeeded;
+        metrics.failed = failed;
+        metrics.skipped = skipped;
+        self.finish_customize_async(metrics, started, &progress).await;
+    }
+
+    /// Customizes folders like
+    /// [`customize_folders_async`](Self::customize_folders_async), applying
+    /// the icon to up to `max_in_flight` folders at once.
+    ///
+    /// Folders are planned against the conflict policy and rendered in
+    /// order, then applied on a pool of worker threads. Each folder's
+    /// [`Progress::Processing`] is sent when a worker starts on it and its
+    /// [`Progress::FolderComplete`] or [`Progress::FolderFailed`] when it
+    /// finishes, so the events of different folders interleave; their
+    /// `index` ties them together. [`Hook::before_apply`] runs on the worker
+    /// threads, and the other hook methods as each folder finishes.
+    ///
+    /// The context's throttle doesn't apply; `max_in_flight` bounds the
+    /// load instead. A `max_in_flight` of 0 counts as 1. In a
+    /// [background](Priority::Background) context, each worker waits for
+    /// interactive work to finish before it starts on its next folder.
+    pub async fn customize_folders_parallel<P: AsRef<std::path::Path>>(
+        &mut self,
+        folders: Vec<P>,
+        profile: &CustomizationProfile,
+        max_in_flight: usize,
+        progress: ProgressSender,
+    ) {
+        let started = Instant::now();
+        let work = Arc::new(self.work_queue.enter(self.priority));
+        let normalized = normalize_folders(&folders);
+        let folders = normalized.folders;
+        let total = folders.len();
+
+        let _ = progress.send(Progress::Started { total }).await;
+        if !normalized.duplicates.is_empty() {
+            let _ = progress
+                .send(Progress::DuplicatesMerged {
+                    duplicates: normalized.duplicates,
+                })
+                .await;
+        }
+        if !self.confirm_batch_async(OperationKind::Customize, total, &progress).await {
+            return;
+        }
+
+        let mut metrics = OperationMetrics::new(OperationKind::Customize);
+        let Some(sys_icons) = self
+            .render_batch_async(profile, total, started, &mut metrics, &progress)
+            .await
+        else {
+            return;
+        };
+        let mut merged_renders = HashMap::new();
+
+        // Plan every folder up front; folders that can't be applied become
+        // jobs that fail right away, without consulting the hooks
+        let mut checkpoints = self.start_checkpoints(OperationKind::Customize, total);
+        let mut jobs: Vec<(usize, Job)> = Vec::with_capacity(total);
+        let mut pending = BTreeMap::new();
+        for (index, path) in folders.into_iter().enumerate() {
+            let planned = match self.plan_folder(&path, profile, false) {
+                Ok(FolderPlan::Apply) => Ok((Arc::clone(&sys_icons), Cow::Borrowed(profile))),
+                Ok(FolderPlan::Merge(merged)) => self
+                    .render_merged(&merged, RenderExtras::default(), &mut merged_renders)
+                    .map(|icons| (icons, Cow::Owned(merged))),
+                Ok(FolderPlan::Skip(conflict)) => {
+                    metrics.skipped += 1;
+                    let _ = progress
+                        .send(Progress::FolderSkipped {
+                            index,
+                            path,
+                            conflict,
+                        })
+                        .await;
+                    checkpoints.record_async(FolderOutcome::Skipped, &progress).await;
+                    continue;
+                }
+                Err(e) => Err(e),
+            };
+            let job: Job = match planned {
+                Ok((icons, applied)) => {
+                    let op = self.set_icon_op(&path, &icons, &self.apply_options);
+                    let retry = self.apply_options.retry;
+                    let timeout = self.apply_options.timeout;
+                    let hooks = self.hooks.clone();
+                    let log = self.log.clone();
+                    let folder = path.clone();
+                    pending.insert(index, (path, Some(applied)));
+                    Box::new(move || {
+                        hooks
+                            .before(OperationKind::Customize, &folder)
+                            .and_then(|()| run_folder_op(&retry, &folder, timeout, &op, &log))
+                    })
+                }
+                Err(e) => {
+                    pending.insert(index, (path, None));
+                    Box::new(move || Err(e))
+                }
+            };
+            jobs.push((index, job));
+        }
+
+        let mut events = run_pool(jobs, max_in_flight, Arc::clone(&work));
+        while let Some(event) = events.recv().await {
+            let (index, result, elapsed) = match event {
+                PoolEvent::Started { index } => {
+                    if let Some((path, _)) = pending.get(&index) {
+                        let _ = progress
+                            .send(Progress::Processing {
+                                current: index,
+                                path: path.clone(),
+                            })
+                            .await;
+                    }
+                    continue;
+                }
+                PoolEvent::Finished {
+                    index,
+                    result,
+                    elapsed,
+                } => (index, result, elapsed),
+            };
+            let Some((path, applied)) = pending.remove(&index) else {
+                continue;
+            };
+            let result = match &applied {
+                Some(applied) => {
+                    if result.is_ok() {
+                        self.update_stats(|stats| stats.record_apply(elapsed));
+                    }
+                    let result =
+                        self.after_folder_async(OperationKind::Customize, &path, result).await;
+                    if result.is_ok() {
+                        self.store.insert(&path, applied);
+                    }
+                    result
+                }
+                None => result,
+            };
+            let outcome = FolderOutcome::from(&result);
+            self.send_customize_result_async(index, path, result, &mut metrics, &progress)
+                .await;
+            checkpoints.record_async(outcome, &progress).await;
+        }
+        // Whatever is left panicked on its worker
+        for (index, (path, _)) in pending {
+            let e = Error::FolderCustomization(path.clone(), "the operation panicked".to_string());
+            self.send_customize_result_async(index, path, Err(e), &mut metrics, &progress)
+                .await;
+        }
+        checkpoints.finish();
+
+        // Leave the customizer configured with the requested profile
+        if !merged_renders.is_empty() {
+            self.apply_profile(profile);
+        }
+        self.finish_customize_async(metrics, started, &progress).await;
+    }
+
+    /// Renders the icons of an async customize batch of `total` folders,
+    /// reporting a failure through `progress`.
+    async fn render_batch_async(
+        &mut self,
+        profile: &CustomizationProfile,
+        total: usize,
+        started: Instant,
+        metrics: &mut OperationMetrics,
+        progress: &ProgressSender,
+    ) -> Option<Arc<SysIconSet>> {
+        let _ = progress.send(Progress::Rendering).await;
+        self.render_warnings.clear();
+        let e = match self.render_extended(profile, RenderExtras::default()) {
+            Ok(icons) => return Some(icons),
+            Err(e) => e,
+        };
+        self.log.error(format_args!("customize batch failed: {e}"));
+        metrics.count_error(&e);
+        metrics.failed = total;
+        self.report_metrics(&metrics.clone().with_duration(started.elapsed()));
+        let _ = progress
+            .send(Progress::RenderFailed {
+                error: e.to_string(),
+            })
+            .await;
+        let _ = progress
+            .send(Progress::Completed {
+                succeeded: 0,
+                failed: total,
+                skipped: 0,
+            })
+            .await;
+        None
+    }
+
+    /// Counts and reports the result of one folder of an async customize
+    /// batch.
+    async fn send_customize_result_async(
+        &self,
+        index: usize,
+        path: PathBuf,
+        result: Result<()>,
+        metrics: &mut OperationMetrics,
+        progress: &ProgressSender,
+    ) {
+        let event = match result {
+            Ok(()) => {
+                metrics.succeeded += 1;
+                Progress::FolderComplete { index, path }
+            }
+            Err(e) => {
+                metrics.failed += 1;
+                metrics.count_error(&e);
+                self.log.warn(format_args!("failed to customize {}: {e}", path.display()));
+                Progress::FolderFailed {
+                    index,
+                    path,
+                    error: e.to_string(),
+                }
+            }
+        };
+        let _ = progress.send(event).await;
+    }
+
+    /// Records the counts of a finished async customize batch, saves the
+    /// store and sends the closing events.
+    async fn finish_customize_async(
+        &mut self,
+        metrics: OperationMetrics,
+        started: Instant,
+        progress: &ProgressSender,
+    ) {
+        let (succeeded, failed, skipped) = (metrics.succeeded, metrics.failed, metrics.skipped);
+        self.update_stats(|stats| stats.record_customize(succeeded, failed, skipped));
+        if succeeded > 0 {
+            self.save_store_async(progress).await;
+        }
+        self.report_metrics(&metrics.with_duration(started.elapsed()));
+
+        let warnings = self.take_warnings();
+        if !warnings.is_empty() {
+            let _ = progress.send(Progress::Warnings { warnings }).await;
         }
 
         // Send completed event
-        let _ = progress.send(Progress::Completed { succeeded, failed }).await;
+        let _ = progress
+            .send(Progress::Completed {
+                succeeded,
+                failed,
+                skipped,
+            })
+            .await;
     }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::fixtures::{fixture_icon_set, FixturePlatform, FIXTURE_SURFACE_HSL};
+    use crate::profile::profile_with_decal;
+    use folco_renderer::{DecalSettings, RectPx};
+    use std::collections::HashSet;
+    use std::sync::atomic::{AtomicU32, Ordering};
 
     #[test]
     fn test_builder_has_default_app_info() {
@@ -549,6 +3810,61 @@ mod tests {
         assert!(builder.force_cache_refresh);
     }
 
+    #[test]
+    fn test_builder_with_throttle() {
+        let throttle = ThrottleConfig::new().with_max_per_second(5.0);
+        let builder = CustomizationContextBuilder::new().with_throttle(throttle.clone());
+
+        assert_eq!(builder.throttle, throttle);
+    }
+
+    #[test]
+    fn test_builder_with_priority() {
+        let queue = Arc::new(WorkQueue::new());
+        let builder = CustomizationContextBuilder::new()
+            .with_work_queue(queue.clone())
+            .with_priority(Priority::Background);
+
+        assert_eq!(builder.priority, Priority::Background);
+        assert!(Arc::ptr_eq(builder.work_queue.as_ref().unwrap(), &queue));
+    }
+
+    #[test]
+    fn test_builder_with_folder_timeout() {
+        let builder =
+            CustomizationContextBuilder::new().with_folder_timeout(Duration::from_secs(30));
+
+        assert_eq!(builder.apply_options.timeout, Some(Duration::from_secs(30)));
+    }
+
+    #[test]
+    fn test_builder_with_data_dir_and_conflict_policy() {
+        let builder = CustomizationContextBuilder::new()
+            .with_data_dir("/tmp/test_folco_data")
+            .with_conflict_policy(ConflictPolicy::Skip);
+
+        assert_eq!(builder.data_dir, Some(PathBuf::from("/tmp/test_folco_data")));
+        assert!(matches!(builder.conflict_policy, ConflictPolicy::Skip));
+    }
+
+    #[test]
+    fn test_builder_with_read_only() {
+        assert!(!CustomizationContextBuilder::new().read_only);
+        assert!(CustomizationContextBuilder::new().with_read_only(true).read_only);
+    }
+
+    #[test]
+    fn test_builder_render_only() {
+        assert!(!CustomizationContextBuilder::new().render_only);
+        assert!(CustomizationContextBuilder::new().render_only().render_only);
+    }
+
+    #[test]
+    fn test_builder_with_strict() {
+        assert!(!CustomizationContextBuilder::new().strict);
+        assert!(CustomizationContextBuilder::new().with_strict(true).strict);
+    }
+
     #[test]
     fn test_builder_with_custom_app_info() {
         let builder = CustomizationContextBuilder::new()
@@ -566,4 +3882,263 @@ mod tests {
         assert_eq!(info.organization, "ecoates2");
         assert_eq!(info.application, "folco");
     }
+
+    /// A folder backend that keeps icons in memory and fails or stalls on
+    /// demand, and serves the fixture icon on every platform.
+    #[derive(Default)]
+    struct MockBackend {
+        customized: Mutex<HashSet<PathBuf>>,
+        failures: AtomicU32,
+        delay: Option<Duration>,
+        /// Accept icons without reporting them, like a volume that drops
+        /// `desktop.ini`.
+        forgetful: bool,
+    }
+
+    impl FolderIconBackend for MockBackend {
+        fn set_icon(&self, folder: &Path, _icons: &SysIconSet) -> Result<()> {
+            if let Some(delay) = self.delay {
+                std::thread::sleep(delay);
+            }
+            let failing = self.failures.fetch_update(Ordering::SeqCst, Ordering::SeqCst, |n| {
+                n.checked_sub(1)
+            });
+            if failing.is_ok() {
+                return Err(Error::FolderCustomization(
+                    folder.to_path_buf(),
+                    "the file is being used by another process".to_string(),
+                ));
+            }
+            if self.forgetful {
+                return Ok(());
+            }
+            self.customized.lock().unwrap().insert(folder.to_path_buf());
+            Ok(())
+        }
+
+        fn reset_icon(&self, folder: &Path) -> Result<()> {
+            self.customized.lock().unwrap().remove(folder);
+            Ok(())
+        }
+
+        fn has_custom_icon(&self, folder: &Path) -> bool {
+            self.customized.lock().unwrap().contains(folder)
+        }
+    }
+
+    impl DefaultIconBackend for MockBackend {
+        fn default_folder_icon(&self) -> Result<SysIconSet> {
+            Ok(fixture_icon_set(FixturePlatform::Windows))
+        }
+
+        fn content_bounds(&self, width: u32, _height: u32) -> RectPx {
+            FixturePlatform::Windows.content_bounds(width)
+        }
+
+        fn surface_hsl(&self) -> (f32, f32, f32) {
+            FIXTURE_SURFACE_HSL
+        }
+    }
+
+    /// Returns a builder for a context on `backend`, keeping its files in
+    /// `dir`, and a folder in `dir` to customize.
+    fn mock_context(
+        backend: &Arc<MockBackend>,
+        dir: &Path,
+    ) -> (CustomizationContextBuilder, PathBuf) {
+        let folder = dir.join("Projects");
+        std::fs::create_dir_all(&folder).unwrap();
+        let builder = CustomizationContextBuilder::new()
+            .with_cache_dir(dir.join("cache"))
+            .with_data_dir(dir.join("data"))
+            .with_folder_backend(Arc::clone(backend) as _)
+            .with_default_icon_backend(Arc::clone(backend) as _);
+        (builder, folder)
+    }
+
+    fn blue() -> CustomizationProfile {
+        profile_with_color(&CustomizationProfile::default(), FolderColor::Blue).unwrap()
+    }
+
+    fn first_error(outcome: &BatchOutcome) -> &'static str {
+        outcome.failed().next().map(|(_, e)| e.kind()).unwrap_or("none")
+    }
+
+    #[test]
+    fn test_read_only_context_leaves_folders_alone() {
+        let temp = tempfile::tempdir().unwrap();
+        let backend = Arc::new(MockBackend::default());
+        let (builder, folder) = mock_context(&backend, temp.path());
+        let mut ctx = builder.with_read_only(true).build().unwrap();
+
+        let outcome = ctx.customize_folders(&[&folder], &blue());
+        assert_eq!(first_error(&outcome), "read-only");
+        assert_eq!(first_error(&ctx.reset_folders(&[&folder])), "read-only");
+        assert!(backend.customized.lock().unwrap().is_empty());
+        assert!(ctx.store().is_empty());
+    }
+
+    #[test]
+    fn test_read_only_context_renders_thumbnails_into_memory() {
+        let temp = tempfile::tempdir().unwrap();
+        let backend = Arc::new(MockBackend::default());
+        let (builder, _) = mock_context(&backend, temp.path());
+        builder.build().unwrap().save_preset("Work", &blue()).unwrap();
+        let thumbnails = temp.path().join("data").join(THUMBNAILS_DIR_NAME);
+        std::fs::remove_dir_all(&thumbnails).unwrap();
+
+        let (builder, _) = mock_context(&backend, temp.path());
+        let mut ctx = builder.with_read_only(true).build().unwrap();
+        let png = ctx.preset_thumbnail_png("Work", 64).unwrap();
+        assert_eq!(image::load_from_memory(&png).unwrap().width(), 64);
+        assert_eq!(ctx.preset_thumbnail("Work", 64).unwrap_err().kind(), "read-only");
+        assert!(!thumbnails.exists());
+    }
+
+    #[test]
+    fn test_strict_context_fails_instead_of_sanitizing() {
+        let temp = tempfile::tempdir().unwrap();
+        let backend = Arc::new(MockBackend::default());
+        let (builder, folder) = mock_context(&backend, temp.path());
+        let mut ctx = builder.with_strict(true).build().unwrap();
+        let decal: DecalSettings = serde_json::from_value(serde_json::json!({
+            "source": { "svg": "<svg onclick=\"x()\"><circle r=\"4\"/></svg>" }
+        }))
+        .unwrap();
+        let profile = profile_with_decal(&blue(), Some(&decal)).unwrap();
+
+        let outcome = ctx.customize_folders(&[&folder], &profile);
+        assert_eq!(outcome.error.as_ref().map(Error::kind), Some("strict"));
+        assert!(backend.customized.lock().unwrap().is_empty());
+
+        ctx.set_strict(false);
+        let outcome = ctx.customize_folders(&[&folder], &profile);
+        assert!(outcome.is_success());
+        let sanitized = |w: &Warning| matches!(w, Warning::SanitizedSvg { .. });
+        assert!(outcome.warnings.iter().any(sanitized));
+    }
+
+    #[test]
+    fn test_strict_context_verifies_folders() {
+        let temp = tempfile::tempdir().unwrap();
+        let backend = Arc::new(MockBackend {
+            forgetful: true,
+            ..Default::default()
+        });
+        let (builder, folder) = mock_context(&backend, temp.path());
+        let mut ctx = builder.build().unwrap();
+        assert!(ctx.customize_folders(&[&folder], &blue()).is_success());
+
+        ctx.set_strict(true);
+        let outcome = ctx.customize_folders(&[&folder], &blue());
+        assert_eq!(first_error(&outcome), "folder-customization");
+    }
+
+    #[test]
+    fn test_skip_policy_keeps_foreign_icons() {
+        let temp = tempfile::tempdir().unwrap();
+        let backend = Arc::new(MockBackend::default());
+        let (builder, folder) = mock_context(&backend, temp.path());
+        let mut ctx = builder.with_conflict_policy(ConflictPolicy::Skip).build().unwrap();
+        let folder = normalize_folder_path(&folder);
+        backend.customized.lock().unwrap().insert(folder.clone());
+
+        let outcome = ctx.customize_folders(&[&folder], &blue());
+        assert_eq!(outcome.skipped.len(), 1);
+        assert!(matches!(outcome.skipped[0].conflict, FolderConflict::ForeignIcon));
+        assert!(!ctx.store().contains(&folder));
+
+        ctx.set_conflict_policy(ConflictPolicy::Overwrite);
+        assert!(ctx.customize_folders(&[&folder], &blue()).is_success());
+        assert!(ctx.store().contains(&folder));
+    }
+
+    #[test]
+    fn test_render_only_context_fails_every_folder() {
+        let temp = tempfile::tempdir().unwrap();
+        let backend = Arc::new(MockBackend::default());
+        let (builder, folder) = mock_context(&backend, temp.path());
+        let mut ctx = builder.render_only().build().unwrap();
+
+        assert!(ctx.render().is_ok());
+        let outcome = ctx.customize_folders(&[&folder], &blue());
+        assert_eq!(first_error(&outcome), "unsupported");
+        assert_eq!(first_error(&ctx.reset_folders(&[&folder])), "unsupported");
+        assert!(backend.customized.lock().unwrap().is_empty());
+        assert!(!temp.path().join("data").exists());
+    }
+
+    #[test]
+    fn test_render_only_context_writes_nothing() {
+        let temp = tempfile::tempdir().unwrap();
+        let backend = Arc::new(MockBackend::default());
+        let app = format!("folco-render-only-{}", std::process::id());
+        let mut ctx = CustomizationContextBuilder::new()
+            .with_app_info(AppInfo::new("org", "folco-tests", &app))
+            .with_data_dir(temp.path().join("data"))
+            .with_default_icon_backend(backend)
+            .render_only()
+            .build()
+            .unwrap();
+
+        ctx.render_sized(&SizedProfile::new(blue())).unwrap();
+        ctx.save_preset("Work", &blue()).unwrap();
+        let png = ctx.preset_thumbnail_png("Work", 64).unwrap();
+        assert_eq!(image::load_from_memory(&png).unwrap().width(), 64);
+        assert_eq!(ctx.preset_thumbnail("Work", 64).unwrap_err().kind(), "cache");
+
+        assert!(!ctx.cache().cache_dir().exists());
+        assert!(!temp.path().join("data").exists());
+    }
+
+    #[test]
+    fn test_reset_removes_the_stored_profile() {
+        let temp = tempfile::tempdir().unwrap();
+        let backend = Arc::new(MockBackend::default());
+        let (builder, folder) = mock_context(&backend, temp.path());
+        let mut ctx = builder.build().unwrap();
+        let folder = normalize_folder_path(&folder);
+
+        assert!(ctx.customize_folders(&[&folder], &blue()).is_success());
+        assert!(ctx.store().contains(&folder));
+        assert!(backend.has_custom_icon(&folder));
+
+        assert!(ctx.reset_folders(&[&folder]).is_success());
+        assert!(!ctx.store().contains(&folder));
+        assert!(!backend.has_custom_icon(&folder));
+    }
+
+    #[test]
+    fn test_busy_folders_are_retried() {
+        let temp = tempfile::tempdir().unwrap();
+        let backend = Arc::new(MockBackend {
+            failures: AtomicU32::new(2),
+            ..Default::default()
+        });
+        let (builder, folder) = mock_context(&backend, temp.path());
+        let mut ctx = builder.build().unwrap();
+
+        let outcome = ctx.customize_folders(&[&folder], &blue());
+        assert_eq!(first_error(&outcome), "folder-customization");
+
+        backend.failures.store(2, Ordering::SeqCst);
+        ctx.set_retry_policy(RetryPolicy::new(3, Duration::from_millis(1)));
+        assert!(ctx.customize_folders(&[&folder], &blue()).is_success());
+        assert_eq!(backend.failures.load(Ordering::SeqCst), 0);
+    }
+
+    #[test]
+    fn test_stalled_folders_time_out() {
+        let temp = tempfile::tempdir().unwrap();
+        let backend = Arc::new(MockBackend {
+            delay: Some(Duration::from_millis(500)),
+            ..Default::default()
+        });
+        let (builder, folder) = mock_context(&backend, temp.path());
+        let mut ctx = builder.with_folder_timeout(Duration::from_millis(20)).build().unwrap();
+
+        let outcome = ctx.customize_folders(&[&folder], &blue());
+        assert_eq!(first_error(&outcome), "timeout");
+        assert!(ctx.store().is_empty());
+    }
 }
@@ -9,7 +9,7 @@
 //! - `folco-renderer::IconSet` uses `image::RgbaImage` with additional metadata
 //!   (scale factor, content bounds) for rendering operations
 
-use folco_renderer::{IconImage as RendererIconImage, IconSet as RendererIconSet};
+use folco_renderer::{IconImage as RendererIconImage, IconSet as RendererIconSet, RectPx};
 use icon_sys::IconSet as SysIconSet;
 
 use crate::sys::get_folder_icon_content_bounds;
@@ -39,6 +39,16 @@ use crate::sys::get_folder_icon_content_bounds;
 /// let renderer_icons = convert_icon_set(&sys_icons);
 /// ```
 pub fn convert_icon_set(sys_icon_set: &SysIconSet) -> RendererIconSet {
+    convert_icon_set_with(sys_icon_set, get_folder_icon_content_bounds)
+}
+
+/// Converts an `icon-sys` IconSet to a `folco-renderer` IconSet, with the
+/// content bounds `content_bounds` returns for each image's width and
+/// height.
+pub(crate) fn convert_icon_set_with(
+    sys_icon_set: &SysIconSet,
+    content_bounds: impl Fn(u32, u32) -> RectPx,
+) -> RendererIconSet {
     let images: Vec<RendererIconImage> = sys_icon_set
         .images
         .iter()
@@ -46,11 +56,10 @@ pub fn convert_icon_set(sys_icon_set: &SysIconSet) -> RendererIconSet {
             // Convert DynamicImage to RgbaImage
             let rgba = sys_image.data.to_rgba8();
 
-            // Get platform-specific content bounds for this icon size
-            let content_bounds = get_folder_icon_content_bounds(rgba.width(), rgba.height());
+            let bounds = content_bounds(rgba.width(), rgba.height());
 
             // System icons use scale 1.0
-            RendererIconImage::new(rgba, 1.0, content_bounds)
+            RendererIconImage::new(rgba, 1.0, bounds)
         })
         .collect();
 
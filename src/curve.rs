@@ -0,0 +1,182 @@
+//! Lightness curves for near-grey colors.
+//!
+//! The renderer retargets a folder's colors by shifting them linearly in
+//! HSL. That works for chromatic targets, but shifting the base icon's
+//! mid-tone surface all the way to white or black squeezes its shading
+//! into a few code values: the White preset loses its highlights and Black
+//! its shadows. A [`LightnessCurve`] spreads those tones back out after
+//! rendering, on the neutral pixels of icons whose target is grey, white or
+//! black, and leaves pure black and pure white where they are.
+
+use crate::color::FolderColor;
+use crate::error::{Error, Result};
+use crate::profile::profile_hsl;
+
+use folco_renderer::CustomizationProfile;
+use icon_sys::IconSet as SysIconSet;
+use image::DynamicImage;
+use serde::{Deserialize, Serialize};
+
+/// Target saturation below which a color counts as grey.
+const GREY_SATURATION: f32 = 0.05;
+
+/// Largest channel spread of a pixel the curve adjusts, so colored decals
+/// drawn over a grey folder keep their colors.
+const NEUTRAL_CHROMA: u8 = 24;
+
+/// A tone curve applied to renders of near-grey colors.
+///
+/// Both amounts range from 0 (no change) to 1, and the curve always keeps
+/// black, white and the order of tones.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct LightnessCurve {
+    /// How much dark tones are lifted, bringing back shadow detail.
+    pub shadows: f32,
+    /// How much light tones are lowered, bringing back highlight detail.
+    pub highlights: f32,
+}
+
+impl LightnessCurve {
+    /// Creates a curve with the given shadow and highlight amounts.
+    pub fn new(shadows: f32, highlights: f32) -> Self {
+        Self {
+            shadows,
+            highlights,
+        }
+    }
+
+    /// Returns the recommended curve for a color preset, or `None` for
+    /// presets that render well without one.
+    pub fn for_color(color: FolderColor) -> Option<Self> {
+        match color {
+            FolderColor::White => Some(Self::new(0.0, 0.6)),
+            FolderColor::Black => Some(Self::new(0.6, 0.0)),
+            FolderColor::Grey => Some(Self::new(0.3, 0.3)),
+            _ => None,
+        }
+    }
+
+    /// Checks that both amounts are within 0–1.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Error::LightnessCurve`] for an amount out of range.
+    pub fn validate(&self) -> Result<()> {
+        for (name, amount) in [("shadows", self.shadows), ("highlights", self.highlights)] {
+            if !(0.0..=1.0).contains(&amount) {
+                return Err(Error::LightnessCurve(format!(
+                    "{name} must be between 0 and 1, not {amount}"
+                )));
+            }
+        }
+        Ok(())
+    }
+
+    /// Returns `true` if the curve changes nothing.
+    pub fn is_identity(&self) -> bool {
+        self.shadows == 0.0 && self.highlights == 0.0
+    }
+
+    /// Maps a lightness in 0–1 through the curve.
+    ///
+    /// The shadow term peaks at a third and the highlight term at two
+    /// thirds, and both vanish at 0 and 1.
+    pub fn map(&self, lightness: f32) -> f32 {
+        let l = lightness.clamp(0.0, 1.0);
+        let lifted = self.shadows * l * (1.0 - l) * (1.0 - l);
+        let lowered = self.highlights * l * l * (1.0 - l);
+        (l + lifted - lowered).clamp(0.0, 1.0)
+    }
+
+    /// Applies the curve to the neutral pixels of `icons`.
+    pub(crate) fn apply(&self, icons: &mut SysIconSet) {
+        let table: Vec<u8> = (0..=255u8)
+            .map(|value| (self.map(f32::from(value) / 255.0) * 255.0).round() as u8)
+            .collect();
+        for image in &mut icons.images {
+            let mut rgba = image.data.to_rgba8();
+            for pixel in rgba.pixels_mut() {
+                let [r, g, b, a] = pixel.0;
+                let spread = r.max(g).max(b) - r.min(g).min(b);
+                if a > 0 && spread <= NEUTRAL_CHROMA {
+                    pixel.0 = [table[r as usize], table[g as usize], table[b as usize], a];
+                }
+            }
+            image.data = DynamicImage::ImageRgba8(rgba);
+        }
+    }
+}
+
+/// Returns `true` if `profile` recolors the folder to a grey, white or
+/// black, the targets a lightness curve is meant for.
+pub(crate) fn targets_grey(profile: &CustomizationProfile) -> bool {
+    profile_hsl(profile)
+        .is_some_and(|settings| settings.enabled && settings.target_saturation < GREY_SATURATION)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::profile::profile_with_color;
+    use image::{Rgba, RgbaImage};
+
+    #[test]
+    fn test_curve_keeps_endpoints_and_order() {
+        let curve = LightnessCurve::new(1.0, 1.0);
+        assert_eq!(curve.map(0.0), 0.0);
+        assert_eq!(curve.map(1.0), 1.0);
+        let mut previous = 0.0;
+        for step in 1..=100 {
+            let mapped = curve.map(step as f32 / 100.0);
+            assert!(mapped > previous);
+            previous = mapped;
+        }
+        assert!(LightnessCurve::new(0.5, 0.0).map(0.2) > 0.2);
+        assert!(LightnessCurve::new(0.0, 0.5).map(0.8) < 0.8);
+    }
+
+    #[test]
+    fn test_validate_rejects_out_of_range_amounts() {
+        assert!(LightnessCurve::for_color(FolderColor::White)
+            .unwrap()
+            .validate()
+            .is_ok());
+        assert!(matches!(
+            LightnessCurve::new(1.5, 0.0).validate(),
+            Err(Error::LightnessCurve(_))
+        ));
+        assert!(LightnessCurve::new(0.0, -0.1).validate().is_err());
+    }
+
+    #[test]
+    fn test_only_neutral_pixels_are_adjusted() {
+        let mut image = RgbaImage::from_pixel(2, 1, Rgba([50, 50, 50, 255]));
+        image.put_pixel(1, 0, Rgba([200, 40, 40, 255]));
+        let mut icons = SysIconSet {
+            images: vec![icon_sys::IconImage {
+                data: DynamicImage::ImageRgba8(image),
+            }],
+        };
+        LightnessCurve::new(0.6, 0.0).apply(&mut icons);
+        let adjusted = icons.images[0].data.to_rgba8();
+        assert!(adjusted.get_pixel(0, 0).0[0] > 50);
+        assert_eq!(adjusted.get_pixel(1, 0).0, [200, 40, 40, 255]);
+    }
+
+    #[test]
+    fn test_targets_grey() {
+        let profile = CustomizationProfile::default();
+        assert!(!targets_grey(&profile));
+        for (color, grey) in [
+            (FolderColor::White, true),
+            (FolderColor::Black, true),
+            (FolderColor::Grey, true),
+            (FolderColor::BlueGrey, false),
+            (FolderColor::Blue, false),
+        ] {
+            let profile = profile_with_color(&profile, color).unwrap();
+            assert_eq!(targets_grey(&profile), grey, "{color}");
+        }
+    }
+}
@@ -0,0 +1,249 @@
+//! Pixel-level comparison of icon sets.
+//!
+//! [`diff_icon_sets`] compares two icon sets size by size, reporting how many
+//! pixels differ and by how much, along with an image highlighting where.
+//! It's meant for checking an applied icon against the expected render, for
+//! golden tests of the renderer, and for support requests ("send me the diff
+//! of expected vs applied").
+
+use crate::error::Result;
+
+use icon_sys::IconSet as SysIconSet;
+use image::{Rgba, RgbaImage};
+use serde::{Deserialize, Serialize};
+
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// The difference between two icon sets.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct IconDiff {
+    /// Comparisons of the sizes present in both sets, smallest first.
+    pub sizes: Vec<SizeDiff>,
+    /// Sizes, as `(width, height)`, only in the first set.
+    pub only_in_first: Vec<(u32, u32)>,
+    /// Sizes, as `(width, height)`, only in the second set.
+    pub only_in_second: Vec<(u32, u32)>,
+}
+
+impl IconDiff {
+    /// Returns `true` if both sets have the same sizes with the same pixels.
+    pub fn is_identical(&self) -> bool {
+        self.only_in_first.is_empty()
+            && self.only_in_second.is_empty()
+            && self.sizes.iter().all(|size| size.differing_pixels == 0)
+    }
+
+    /// Returns the largest per-channel difference across all sizes.
+    pub fn max_delta(&self) -> u8 {
+        self.sizes
+            .iter()
+            .map(|size| size.max_delta)
+            .max()
+            .unwrap_or(0)
+    }
+
+    /// Returns `true` if every shared size stays within `max_delta` and at
+    /// most `max_fraction` of its pixels differ, and no size is missing.
+    ///
+    /// Useful for golden tests, where rendering may vary slightly between
+    /// platforms.
+    pub fn is_within(&self, max_delta: u8, max_fraction: f64) -> bool {
+        self.only_in_first.is_empty()
+            && self.only_in_second.is_empty()
+            && self.sizes.iter().all(|size| {
+                size.max_delta <= max_delta && size.differing_fraction() <= max_fraction
+            })
+    }
+
+    /// Writes each size's highlight image to `dir` as
+    /// `diff-<width>x<height>.png`, creating the directory if needed.
+    ///
+    /// Returns the paths written.
+    pub fn save_highlights(&self, dir: &Path) -> Result<Vec<PathBuf>> {
+        fs::create_dir_all(dir)?;
+        self.sizes
+            .iter()
+            .map(|size| {
+                let path = dir.join(format!("diff-{}x{}.png", size.width, size.height));
+                size.highlight.save(&path)?;
+                Ok(path)
+            })
+            .collect()
+    }
+}
+
+/// The difference between the images of one size.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SizeDiff {
+    /// Image width in pixels.
+    pub width: u32,
+    /// Image height in pixels.
+    pub height: u32,
+    /// Number of pixels that differ in any channel.
+    pub differing_pixels: u64,
+    /// The largest difference in any channel of any pixel, from 0 to 255.
+    pub max_delta: u8,
+    /// The mean over all pixels of each pixel's largest channel difference.
+    pub mean_delta: f64,
+    /// The second image with differing pixels marked in red, more opaque
+    /// the larger the difference, over a faded copy of the icon.
+    #[serde(skip)]
+    pub highlight: RgbaImage,
+}
+
+impl SizeDiff {
+    /// Returns the fraction of pixels that differ, from 0 to 1.
+    pub fn differing_fraction(&self) -> f64 {
+        let total = u64::from(self.width) * u64::from(self.height);
+        if total == 0 {
+            return 0.0;
+        }
+        self.differing_pixels as f64 / total as f64
+    }
+}
+
+/// Compares two icon sets size by size.
+///
+/// Images are paired by their exact dimensions. If a set has several images
+/// of one size, the first is used.
+pub fn diff_icon_sets(first: &SysIconSet, second: &SysIconSet) -> IconDiff {
+    let first = by_size(first);
+    let second = by_size(second);
+
+    let mut diff = IconDiff::default();
+    for (size, a) in &first {
+        match second.iter().find(|(other, _)| other == size) {
+            Some((_, b)) => diff.sizes.push(diff_images(a, b)),
+            None => diff.only_in_first.push(*size),
+        }
+    }
+    diff.only_in_second = second
+        .iter()
+        .map(|(size, _)| *size)
+        .filter(|size| !first.iter().any(|(other, _)| other == size))
+        .collect();
+    diff
+}
+
+/// Returns the set's images as RGBA by `(width, height)`, smallest first.
+fn by_size(set: &SysIconSet) -> Vec<((u32, u32), RgbaImage)> {
+    let mut images: Vec<((u32, u32), RgbaImage)> = Vec::new();
+    for image in &set.images {
+        let size = (image.data.width(), image.data.height());
+        if !images.iter().any(|(other, _)| *other == size) {
+            images.push((size, image.data.to_rgba8()));
+        }
+    }
+    images.sort_by_key(|(size, _)| *size);
+    images
+}
+
+/// Compares two images of the same dimensions.
+fn diff_images(a: &RgbaImage, b: &RgbaImage) -> SizeDiff {
+    let (width, height) = b.dimensions();
+    let mut highlight = RgbaImage::new(width, height);
+    let mut differing_pixels = 0u64;
+    let mut max_delta = 0u8;
+    let mut delta_sum = 0u64;
+
+    for (x, y, pb) in b.enumerate_pixels() {
+        let pa = a.get_pixel(x, y);
+        let delta =
+            pa.0.iter()
+                .zip(pb.0.iter())
+                .map(|(ca, cb)| ca.abs_diff(*cb))
+                .max()
+                .unwrap_or(0);
+
+        delta_sum += u64::from(delta);
+        max_delta = max_delta.max(delta);
+        let marked = if delta > 0 {
+            differing_pixels += 1;
+            Rgba([255, 0, 0, 128 + delta / 2])
+        } else {
+            faded(pb)
+        };
+        highlight.put_pixel(x, y, marked);
+    }
+
+    let total = u64::from(width) * u64::from(height);
+    SizeDiff {
+        width,
+        height,
+        differing_pixels,
+        max_delta,
+        mean_delta: if total == 0 {
+            0.0
+        } else {
+            delta_sum as f64 / total as f64
+        },
+        highlight,
+    }
+}
+
+/// Returns a light grey version of `pixel`, as context for the marked
+/// differences.
+fn faded(pixel: &Rgba<u8>) -> Rgba<u8> {
+    let [r, g, b, a] = pixel.0;
+    let luma = (u32::from(r) * 299 + u32::from(g) * 587 + u32::from(b) * 114) / 1000;
+    let light = (192 + luma / 4) as u8;
+    Rgba([light, light, light, a / 2])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use image::DynamicImage;
+
+    fn set(images: Vec<RgbaImage>) -> SysIconSet {
+        SysIconSet {
+            images: images
+                .into_iter()
+                .map(|image| icon_sys::IconImage {
+                    data: DynamicImage::ImageRgba8(image),
+                })
+                .collect(),
+        }
+    }
+
+    #[test]
+    fn test_identical_sets() {
+        let image = RgbaImage::from_pixel(16, 16, Rgba([10, 20, 30, 255]));
+        let diff = diff_icon_sets(&set(vec![image.clone()]), &set(vec![image]));
+
+        assert!(diff.is_identical());
+        assert_eq!(diff.sizes.len(), 1);
+        assert_eq!(diff.max_delta(), 0);
+    }
+
+    #[test]
+    fn test_differing_pixels_are_counted_and_marked() {
+        let a = RgbaImage::from_pixel(4, 4, Rgba([100, 100, 100, 255]));
+        let mut b = a.clone();
+        b.put_pixel(1, 2, Rgba([100, 140, 100, 255]));
+
+        let diff = diff_icon_sets(&set(vec![a]), &set(vec![b]));
+        let size = &diff.sizes[0];
+        assert_eq!(size.differing_pixels, 1);
+        assert_eq!(size.max_delta, 40);
+        assert!((size.mean_delta - 40.0 / 16.0).abs() < 1e-9);
+        assert_eq!(size.highlight.get_pixel(1, 2).0[0], 255);
+        assert!(!diff.is_identical());
+        assert!(diff.is_within(40, 0.1));
+        assert!(!diff.is_within(39, 1.0));
+    }
+
+    #[test]
+    fn test_missing_sizes_are_reported() {
+        let small = RgbaImage::new(16, 16);
+        let large = RgbaImage::new(32, 32);
+        let diff = diff_icon_sets(&set(vec![small.clone(), large]), &set(vec![small]));
+
+        assert_eq!(diff.only_in_first, vec![(32, 32)]);
+        assert!(diff.only_in_second.is_empty());
+        assert!(!diff.is_identical());
+    }
+}
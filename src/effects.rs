@@ -0,0 +1,291 @@
+//! Shadow and emboss effects for decal layers.
+//!
+//! A glyph composited flat onto the folder looks stamped on. A soft drop
+//! shadow lifts it off the surface, and an emboss shades its edges as if lit
+//! from one side. Effects are sized relative to the decal, so they look the
+//! same at 16px and 256px. The presets cover the common looks; the fields
+//! are public for anything else.
+
+use crate::blend::BlendMode;
+use crate::layers::blit;
+
+use image::imageops;
+use image::{Rgba, RgbaImage};
+use serde::{Deserialize, Serialize};
+
+/// A drop shadow behind a decal.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct ShadowEffect {
+    /// Horizontal offset as a fraction of the decal height; positive is
+    /// right.
+    pub offset_x: f32,
+    /// Vertical offset as a fraction of the decal height; positive is down.
+    pub offset_y: f32,
+    /// Blur radius as a fraction of the decal height.
+    pub blur: f32,
+    /// Opacity from 0 to 1.
+    pub opacity: f32,
+    /// Shadow color as RGB.
+    pub color: [u8; 3],
+}
+
+impl Default for ShadowEffect {
+    fn default() -> Self {
+        Self::soft()
+    }
+}
+
+impl ShadowEffect {
+    /// A soft shadow falling slightly down and right.
+    pub fn soft() -> Self {
+        Self {
+            offset_x: 0.03,
+            offset_y: 0.05,
+            blur: 0.06,
+            opacity: 0.45,
+            color: [0, 0, 0],
+        }
+    }
+
+    /// A crisp, unblurred shadow, for a sticker-like look.
+    pub fn hard() -> Self {
+        Self {
+            offset_x: 0.04,
+            offset_y: 0.04,
+            blur: 0.0,
+            opacity: 0.6,
+            color: [0, 0, 0],
+        }
+    }
+
+    /// A wide, faint glow around the decal, for light glyphs on dark
+    /// folders.
+    pub fn glow() -> Self {
+        Self {
+            offset_x: 0.0,
+            offset_y: 0.0,
+            blur: 0.1,
+            opacity: 0.6,
+            color: [255, 255, 255],
+        }
+    }
+}
+
+/// Edge shading that makes a decal look raised or pressed in.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct EmbossEffect {
+    /// Width of the shaded edge as a fraction of the decal height.
+    pub depth: f32,
+    /// Direction the light comes from, in degrees clockwise from straight
+    /// up; 315 lights from the top left.
+    pub light_angle: f32,
+    /// How strongly edges are lightened and darkened, from 0 to 1.
+    pub strength: f32,
+}
+
+impl Default for EmbossEffect {
+    fn default() -> Self {
+        Self::subtle()
+    }
+}
+
+impl EmbossEffect {
+    /// A gentle raised edge.
+    pub fn subtle() -> Self {
+        Self {
+            depth: 0.03,
+            light_angle: 315.0,
+            strength: 0.35,
+        }
+    }
+
+    /// A pronounced raised edge.
+    pub fn pronounced() -> Self {
+        Self {
+            depth: 0.05,
+            light_angle: 315.0,
+            strength: 0.7,
+        }
+    }
+
+    /// Lit from below, so the decal looks pressed into the folder.
+    pub fn debossed() -> Self {
+        Self {
+            depth: 0.03,
+            light_angle: 135.0,
+            strength: 0.5,
+        }
+    }
+}
+
+/// The effects drawn with a decal layer.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct LayerEffects {
+    /// Drop shadow, drawn under the decal.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub shadow: Option<ShadowEffect>,
+    /// Emboss, applied to the decal itself.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub emboss: Option<EmbossEffect>,
+}
+
+impl LayerEffects {
+    /// No effects.
+    pub fn none() -> Self {
+        Self::default()
+    }
+
+    /// A soft shadow with a subtle emboss: the decal sits on the folder.
+    pub fn raised() -> Self {
+        Self {
+            shadow: Some(ShadowEffect::soft()),
+            emboss: Some(EmbossEffect::subtle()),
+        }
+    }
+
+    /// A hard shadow without emboss, like a sticker.
+    pub fn sticker() -> Self {
+        Self {
+            shadow: Some(ShadowEffect::hard()),
+            emboss: None,
+        }
+    }
+
+    /// A debossed decal without shadow, pressed into the folder.
+    pub fn engraved() -> Self {
+        Self {
+            shadow: None,
+            emboss: Some(EmbossEffect::debossed()),
+        }
+    }
+
+    /// Returns `true` if no effect is set.
+    pub fn is_empty(&self) -> bool {
+        self.shadow.is_none() && self.emboss.is_none()
+    }
+}
+
+/// Draws `shadow` for `decal`, which will be drawn at `(x, y)` on `canvas`.
+pub(crate) fn draw_shadow(
+    canvas: &mut RgbaImage,
+    decal: &RgbaImage,
+    x: u32,
+    y: u32,
+    shadow: &ShadowEffect,
+    opacity: f32,
+    mask: Option<&RgbaImage>,
+) {
+    let height = decal.height() as f32;
+    let sigma = (shadow.blur * height / 2.0).max(0.0);
+    let pad = (sigma * 3.0).ceil() as u32;
+
+    let [r, g, b] = shadow.color;
+    let mut silhouette = RgbaImage::new(decal.width() + 2 * pad, decal.height() + 2 * pad);
+    for (sx, sy, pixel) in decal.enumerate_pixels() {
+        silhouette.put_pixel(sx + pad, sy + pad, Rgba([r, g, b, pixel.0[3]]));
+    }
+    let blurred = if sigma > 0.0 {
+        imageops::blur(&silhouette, sigma)
+    } else {
+        silhouette
+    };
+
+    let dx = (shadow.offset_x * height).round() as i64;
+    let dy = (shadow.offset_y * height).round() as i64;
+    blit(
+        canvas,
+        &blurred,
+        i64::from(x) + dx - i64::from(pad),
+        i64::from(y) + dy - i64::from(pad),
+        shadow.opacity.clamp(0.0, 1.0) * opacity,
+        BlendMode::Normal,
+        mask,
+    );
+}
+
+/// Shades the edges of `decal` in place.
+pub(crate) fn apply_emboss(decal: &mut RgbaImage, emboss: &EmbossEffect) {
+    let depth = (emboss.depth * decal.height() as f32).round().max(1.0);
+    let angle = emboss.light_angle.to_radians();
+    // Offset towards the light, in image coordinates where y points down
+    let (lx, ly) = (angle.sin() * depth, -angle.cos() * depth);
+    let (lx, ly) = (lx.round() as i64, ly.round() as i64);
+    let strength = emboss.strength.clamp(0.0, 1.0);
+
+    let source = decal.clone();
+    let alpha = |x: i64, y: i64| -> f32 {
+        if x < 0 || y < 0 || x >= i64::from(source.width()) || y >= i64::from(source.height()) {
+            return 0.0;
+        }
+        f32::from(source.get_pixel(x as u32, y as u32).0[3]) / 255.0
+    };
+
+    for (x, y, pixel) in decal.enumerate_pixels_mut() {
+        if pixel.0[3] == 0 {
+            continue;
+        }
+        let (x, y) = (i64::from(x), i64::from(y));
+        // Positive where the decal ends towards the light, so the pixel is
+        // on an edge facing it; negative on edges facing away
+        let slope = alpha(x - lx, y - ly) - alpha(x + lx, y + ly);
+        let amount = (slope * strength).clamp(-1.0, 1.0);
+        let target = if amount > 0.0 { 255.0 } else { 0.0 };
+        for channel in &mut pixel.0[..3] {
+            let value = f32::from(*channel);
+            *channel = (value + (target - value) * amount.abs())
+                .round()
+                .clamp(0.0, 255.0) as u8;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_hard_shadow_is_offset() {
+        let decal = RgbaImage::from_pixel(20, 20, Rgba([255, 255, 255, 255]));
+        let mut canvas = RgbaImage::from_pixel(40, 40, Rgba([255, 0, 0, 255]));
+        draw_shadow(
+            &mut canvas,
+            &decal,
+            10,
+            10,
+            &ShadowEffect::hard(),
+            1.0,
+            None,
+        );
+
+        // 0.04 × 20px = 1px down and right
+        assert_eq!(*canvas.get_pixel(10, 10), Rgba([255, 0, 0, 255]));
+        assert_ne!(*canvas.get_pixel(30, 30), Rgba([255, 0, 0, 255]));
+        assert_eq!(*canvas.get_pixel(31, 31), Rgba([255, 0, 0, 255]));
+    }
+
+    #[test]
+    fn test_emboss_lightens_lit_edge_and_darkens_far_edge() {
+        let mut decal = RgbaImage::new(20, 20);
+        for y in 5..15 {
+            for x in 5..15 {
+                decal.put_pixel(x, y, Rgba([128, 128, 128, 255]));
+            }
+        }
+        apply_emboss(&mut decal, &EmbossEffect::pronounced());
+
+        let centre = decal.get_pixel(10, 10).0[0];
+        assert_eq!(centre, 128);
+        assert!(decal.get_pixel(5, 5).0[0] > centre);
+        assert!(decal.get_pixel(14, 14).0[0] < centre);
+    }
+
+    #[test]
+    fn test_presets() {
+        assert!(LayerEffects::none().is_empty());
+        assert!(LayerEffects::raised().shadow.is_some());
+        assert!(LayerEffects::engraved().shadow.is_none());
+    }
+}
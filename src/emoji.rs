@@ -0,0 +1,350 @@
+//! Emoji decals.
+//!
+//! Most emoji people pick aren't a single `char`. A thumbs up with a skin
+//! tone is two code points, a family or a person at a laptop is several
+//! joined with zero-width joiners (ZWJ), a flag is a pair of regional
+//! indicators, and symbols like ❤ need a variation selector to show as
+//! emoji at all. Pasted text also carries stray selectors and spaces.
+//! [`normalize_emoji`] checks that a string is exactly one emoji sequence
+//! and puts it in the form the renderer's emoji set is keyed by, and
+//! [`profile_with_emoji`] uses it to set a profile's decal.
+
+use crate::error::{Error, Result};
+use crate::profile::profile_with_decal;
+
+use folco_renderer::{CustomizationProfile, DecalSettings};
+use serde_json::json;
+
+/// Zero-width joiner, gluing emoji into one sequence.
+const ZWJ: char = '\u{200D}';
+
+/// Variation selector 15, asking for text presentation.
+const TEXT_SELECTOR: char = '\u{FE0E}';
+
+/// Variation selector 16, asking for emoji presentation.
+const EMOJI_SELECTOR: char = '\u{FE0F}';
+
+/// Combining enclosing keycap, as in 1️⃣.
+const KEYCAP: char = '\u{20E3}';
+
+/// The black flag that subdivision flags like 🏴󠁧󠁢󠁳󠁣󠁴󠁿 are built on.
+const BLACK_FLAG: char = '\u{1F3F4}';
+
+/// Ends the tag characters of a subdivision flag.
+const CANCEL_TAG: char = '\u{E007F}';
+
+/// Checks that `input` is a single emoji and returns it normalized.
+///
+/// Surrounding whitespace is trimmed. Skin tones, ZWJ sequences, flags,
+/// subdivision flags and keycaps are kept whole. Variation selectors are
+/// normalized, since decals always draw emoji rather than text:
+///
+/// - a text presentation selector becomes an emoji presentation selector
+/// - symbols below U+1F000 that default to text presentation, like ❤ and
+///   ©, get an emoji presentation selector if they have none
+/// - a selector next to a skin tone modifier, which already implies emoji
+///   presentation, is dropped
+/// - keycaps always get one, as in 1️⃣
+///
+/// # Errors
+///
+/// Returns [`Error::Emoji`] naming the problem if `input` is empty, has
+/// more than one emoji, contains text that isn't an emoji, or has a
+/// modifier, selector, joiner or regional indicator out of place.
+pub fn normalize_emoji(input: &str) -> Result<String> {
+    let chars: Vec<char> = input.trim().chars().collect();
+    let unsupported = |reason: String| Error::Emoji(format!("{input:?}: {reason}"));
+    if chars.is_empty() {
+        return Err(unsupported("there's no emoji".to_string()));
+    }
+
+    if is_regional_indicator(chars[0]) {
+        if !chars.get(1).is_some_and(|&c| is_regional_indicator(c)) {
+            return Err(unsupported(
+                "a flag needs two regional indicators".to_string(),
+            ));
+        }
+        if let Some(&extra) = chars.get(2) {
+            return Err(unsupported(after_emoji(extra)));
+        }
+        return Ok(chars.iter().collect());
+    }
+
+    let mut normalized = String::new();
+    let mut i = 0;
+    loop {
+        i = push_element(&chars, i, &mut normalized).map_err(unsupported)?;
+        match chars.get(i) {
+            None => return Ok(normalized),
+            Some(&ZWJ) if i + 1 == chars.len() => {
+                return Err(unsupported("it ends with a zero-width joiner".to_string()));
+            }
+            Some(&ZWJ) => {
+                normalized.push(ZWJ);
+                i += 1;
+            }
+            Some(&c) if is_skin_tone(c) => {
+                return Err(unsupported(
+                    "it has two skin tone modifiers in a row".to_string(),
+                ));
+            }
+            Some(&c) => return Err(unsupported(after_emoji(c))),
+        }
+    }
+}
+
+/// Returns `profile` with its decal replaced by `emoji`, once
+/// [normalized](normalize_emoji).
+///
+/// # Errors
+///
+/// Returns [`Error::Emoji`] if `emoji` isn't a single emoji, or an error
+/// if the profile has no decal settings.
+pub fn profile_with_emoji(
+    profile: &CustomizationProfile,
+    emoji: &str,
+) -> Result<CustomizationProfile> {
+    let emoji = normalize_emoji(emoji)?;
+    let decal: DecalSettings = serde_json::from_value(json!({ "source": { "emoji": emoji } }))
+        .map_err(|e| Error::Serialization(e.to_string()))?;
+    profile_with_decal(profile, Some(&decal))
+}
+
+/// Pushes the normalized emoji starting at `chars[i]`, up to the next joiner
+/// or the end, and returns the index after it.
+fn push_element(
+    chars: &[char],
+    mut i: usize,
+    out: &mut String,
+) -> std::result::Result<usize, String> {
+    let base = chars[i];
+    match base {
+        ZWJ => return Err("a zero-width joiner needs an emoji before it".to_string()),
+        TEXT_SELECTOR | EMOJI_SELECTOR => {
+            return Err("a variation selector needs a character before it".to_string());
+        }
+        c if is_skin_tone(c) => {
+            return Err("a skin tone modifier needs an emoji before it".to_string());
+        }
+        c if is_regional_indicator(c) => {
+            return Err("flags can't be joined with other emoji".to_string());
+        }
+        c if is_keycap_base(c) => {
+            i += 1;
+            if matches!(chars.get(i), Some(&(TEXT_SELECTOR | EMOJI_SELECTOR))) {
+                i += 1;
+            }
+            if chars.get(i) != Some(&KEYCAP) {
+                return Err(format!("{} isn't an emoji", code_point(c)));
+            }
+            out.extend([c, EMOJI_SELECTOR, KEYCAP]);
+            return Ok(i + 1);
+        }
+        c if !is_pictographic(c) => return Err(format!("{} isn't an emoji", code_point(c))),
+        _ => {}
+    }
+    out.push(base);
+    i += 1;
+
+    let selected = matches!(chars.get(i), Some(&(TEXT_SELECTOR | EMOJI_SELECTOR)));
+    if selected {
+        i += 1;
+    }
+    match chars.get(i) {
+        Some(&tone) if is_skin_tone(tone) => {
+            out.push(tone);
+            i += 1;
+            if matches!(chars.get(i), Some(&(TEXT_SELECTOR | EMOJI_SELECTOR))) {
+                i += 1;
+            }
+        }
+        _ if selected || defaults_to_text(base) => out.push(EMOJI_SELECTOR),
+        _ => {}
+    }
+
+    if chars.get(i).is_some_and(|&c| is_tag(c)) {
+        if base != BLACK_FLAG {
+            return Err(format!("tag characters can't follow {}", code_point(base)));
+        }
+        while let Some(&tag) = chars.get(i).filter(|&&c| is_tag(c)) {
+            out.push(tag);
+            i += 1;
+            if tag == CANCEL_TAG {
+                return Ok(i);
+            }
+        }
+        return Err("its subdivision flag has no cancel tag".to_string());
+    }
+    Ok(i)
+}
+
+fn after_emoji(c: char) -> String {
+    format!(
+        "{} follows the first emoji; decals take a single emoji",
+        code_point(c)
+    )
+}
+
+fn code_point(c: char) -> String {
+    format!("U+{:04X}", u32::from(c))
+}
+
+fn is_regional_indicator(c: char) -> bool {
+    ('\u{1F1E6}'..='\u{1F1FF}').contains(&c)
+}
+
+fn is_skin_tone(c: char) -> bool {
+    ('\u{1F3FB}'..='\u{1F3FF}').contains(&c)
+}
+
+fn is_keycap_base(c: char) -> bool {
+    c.is_ascii_digit() || c == '#' || c == '*'
+}
+
+fn is_tag(c: char) -> bool {
+    ('\u{E0020}'..=CANCEL_TAG).contains(&c)
+}
+
+/// Returns `true` if `c` can start an emoji: roughly Unicode's
+/// Extended_Pictographic property.
+fn is_pictographic(c: char) -> bool {
+    matches!(
+        c,
+        '\u{A9}'
+            | '\u{AE}'
+            | '\u{203C}'
+            | '\u{2049}'
+            | '\u{2122}'
+            | '\u{2139}'
+            | '\u{2194}'..='\u{21AA}'
+            | '\u{231A}'..='\u{23FF}'
+            | '\u{24C2}'
+            | '\u{25AA}'..='\u{27BF}'
+            | '\u{2934}'..='\u{2935}'
+            | '\u{2B05}'..='\u{2B55}'
+            | '\u{3030}'
+            | '\u{303D}'
+            | '\u{3297}'
+            | '\u{3299}'
+            | '\u{1F000}'..='\u{1FAFF}'
+    )
+}
+
+/// Returns `true` for symbols below U+1F000 that show as text unless asked
+/// otherwise. The few with emoji presentation by default, like ⌚ and ⭐,
+/// are left alone.
+fn defaults_to_text(c: char) -> bool {
+    let emoji_by_default = matches!(
+        c,
+        '\u{231A}'..='\u{231B}'
+            | '\u{23E9}'..='\u{23EC}'
+            | '\u{23F0}'
+            | '\u{23F3}'
+            | '\u{25FD}'..='\u{25FE}'
+            | '\u{2614}'..='\u{2615}'
+            | '\u{2648}'..='\u{2653}'
+            | '\u{267F}'
+            | '\u{2693}'
+            | '\u{26A1}'
+            | '\u{26AA}'..='\u{26AB}'
+            | '\u{26BD}'..='\u{26BE}'
+            | '\u{26C4}'..='\u{26C5}'
+            | '\u{26CE}'
+            | '\u{26D4}'
+            | '\u{26EA}'
+            | '\u{26F2}'..='\u{26F3}'
+            | '\u{26F5}'
+            | '\u{26FA}'
+            | '\u{26FD}'
+            | '\u{2705}'
+            | '\u{270A}'..='\u{270B}'
+            | '\u{2728}'
+            | '\u{274C}'
+            | '\u{274E}'
+            | '\u{2753}'..='\u{2755}'
+            | '\u{2757}'
+            | '\u{2795}'..='\u{2797}'
+            | '\u{27B0}'
+            | '\u{27BF}'
+            | '\u{2B1B}'..='\u{2B1C}'
+            | '\u{2B50}'
+            | '\u{2B55}'
+    );
+    c < '\u{1F000}' && !emoji_by_default
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn normalized(input: &str) -> String {
+        normalize_emoji(input).unwrap()
+    }
+
+    #[test]
+    fn test_single_code_points() {
+        assert_eq!(normalized("🚀"), "🚀");
+        assert_eq!(normalized("  📁\n"), "📁");
+        assert_eq!(normalized("⭐"), "⭐");
+    }
+
+    #[test]
+    fn test_variation_selectors() {
+        assert_eq!(normalized("\u{2764}"), "\u{2764}\u{FE0F}");
+        assert_eq!(normalized("\u{2764}\u{FE0E}"), "\u{2764}\u{FE0F}");
+        assert_eq!(normalized("\u{2764}\u{FE0F}"), "\u{2764}\u{FE0F}");
+        assert_eq!(normalized("\u{A9}"), "\u{A9}\u{FE0F}");
+        assert_eq!(normalized("1\u{20E3}"), "1\u{FE0F}\u{20E3}");
+    }
+
+    #[test]
+    fn test_skin_tones() {
+        assert_eq!(normalized("👍🏽"), "👍🏽");
+        assert_eq!(normalized("\u{270C}\u{FE0F}\u{1F3FB}"), "\u{270C}\u{1F3FB}");
+        assert!(normalize_emoji("🏽").is_err());
+        assert!(normalize_emoji("👍🏽🏽").is_err());
+    }
+
+    #[test]
+    fn test_zwj_sequences() {
+        for emoji in [
+            "👨\u{200D}💻",
+            "🧑🏽\u{200D}🤝\u{200D}🧑🏻",
+            "\u{2764}\u{FE0F}\u{200D}🔥",
+            "🏳\u{FE0F}\u{200D}🌈",
+        ] {
+            assert_eq!(normalized(emoji), emoji);
+        }
+        assert!(normalize_emoji("👨\u{200D}").is_err());
+        assert!(normalize_emoji("\u{200D}💻").is_err());
+    }
+
+    #[test]
+    fn test_flags() {
+        assert_eq!(normalized("🇳🇿"), "🇳🇿");
+        let scotland = "🏴\u{E0067}\u{E0062}\u{E0073}\u{E0063}\u{E0074}\u{E007F}";
+        assert_eq!(normalized(scotland), scotland);
+        assert!(normalize_emoji("🇳").is_err());
+        assert!(normalize_emoji("🇳🇿🇳").is_err());
+        assert!(normalize_emoji("🏴\u{E0067}\u{E0062}").is_err());
+    }
+
+    #[test]
+    fn test_rejects_text_and_multiple_emoji() {
+        for input in ["", "  ", "a", "7", "🚀🚀", "🚀 📁", "\u{FE0F}"] {
+            let err = normalize_emoji(input).unwrap_err();
+            assert!(matches!(err, Error::Emoji(_)), "{input:?}: {err}");
+        }
+        let err = normalize_emoji("🚀x").unwrap_err();
+        assert!(err.to_string().contains("U+0078"), "{err}");
+    }
+
+    #[test]
+    fn test_profile_with_emoji() {
+        let profile =
+            profile_with_emoji(&CustomizationProfile::default(), "\u{2764}\u{FE0E}").unwrap();
+        let value = serde_json::to_string(&profile).unwrap();
+        assert!(value.contains("\u{2764}\u{FE0F}"));
+        assert!(profile_with_emoji(&CustomizationProfile::default(), "hi").is_err());
+    }
+}
@@ -1,6 +1,7 @@
 //! Error types for folco-core.
 
 use std::path::PathBuf;
+use std::time::Duration;
 use thiserror::Error;
 
 /// Result type alias using [`Error`].
@@ -17,6 +18,10 @@ pub enum Error {
     #[error("icon system error: {0}")]
     IconSys(#[from] icon_sys::Error),
 
+    /// No candidate source yielded a valid default folder icon.
+    #[error("failed to extract the default folder icon: {0}")]
+    IconExtraction(crate::extract::ExtractionReport),
+
     /// Error during icon caching.
     #[error("cache error: {0}")]
     Cache(String),
@@ -33,6 +38,106 @@ pub enum Error {
     #[error("failed to reset folder '{0}': {1}")]
     FolderReset(PathBuf, String),
 
+    /// A folder operation did not finish within the configured timeout.
+    #[error("timed out after {1:?} processing folder '{0}'")]
+    Timeout(PathBuf, Duration),
+
+    /// The folder can't be customized so that its file manager shows the
+    /// icon, such as a WSL folder seen from Windows.
+    #[error("folder '{0}' can't be customized from here: {1}")]
+    Unsupported(PathBuf, String),
+
+    /// Invalid library root operation.
+    #[error("library error: {0}")]
+    Library(String),
+
+    /// No preset with the given name exists.
+    #[error("preset not found: {0}")]
+    PresetNotFound(String),
+
+    /// Quick apply was requested but no default preset is configured.
+    #[error("no default preset is configured")]
+    NoDefaultPreset,
+
+    /// The elevated helper failed or broke the protocol.
+    #[error("helper error: {0}")]
+    Helper(String),
+
+    /// A security-scoped bookmark couldn't be resolved or accessed.
+    #[error("bookmark error: {0}")]
+    Bookmark(String),
+
+    /// Invalid rule.
+    #[error("rule error: {0}")]
+    Rule(String),
+
+    /// Invalid customization manifest.
+    #[error("invalid manifest: {0}")]
+    Manifest(String),
+
+    /// Invalid decal layer.
+    #[error("layer error: {0}")]
+    Layer(String),
+
+    /// A string that isn't a single emoji the renderer can draw.
+    #[error("unsupported emoji {0}")]
+    Emoji(String),
+
+    /// Invalid lightness curve.
+    #[error("invalid lightness curve: {0}")]
+    LightnessCurve(String),
+
+    /// Invalid or unsupported palette file.
+    #[error("palette error: {0}")]
+    Palette(String),
+
+    /// Unreadable or unsupported sync file.
+    #[error("sync error: {0}")]
+    Sync(String),
+
+    /// The desktop wallpaper couldn't be found.
+    #[error("wallpaper error: {0}")]
+    Wallpaper(String),
+
+    /// The context is read-only and the operation would change folders or
+    /// saved state.
+    #[error("read-only context can't {0}")]
+    ReadOnly(String),
+
+    /// The admin policy forbids the operation.
+    #[error("blocked by policy: {0}")]
+    Policy(String),
+
+    /// A profile is over one of the context's payload limits.
+    #[error("profile too large: {0}")]
+    PayloadLimit(crate::limits::LimitExceeded),
+
+    /// A [`Hook`](crate::Hook) refused a folder or failed.
+    #[error("hook failed: {0}")]
+    Hook(String),
+
+    /// A [`FolderSelector`](crate::FolderSelector) input couldn't be used.
+    #[error("can't select {0}")]
+    Selection(crate::selection::SelectionReject),
+
+    /// The operation was cancelled before it finished.
+    #[error("cancelled: {0}")]
+    Cancelled(String),
+
+    /// A render had to work around a problem, and the context is
+    /// [strict](crate::CustomizationContextBuilder::with_strict).
+    #[error("strict mode: {0}")]
+    Strict(crate::warning::Warning),
+
+    /// A desktop notification couldn't be shown.
+    #[error("notification error: {0}")]
+    Notification(String),
+
+    /// Stored state couldn't be upgraded to the current format, e.g.
+    /// because a newer version wrote it.
+    #[error("migration error: {0}")]
+    Migration(String),
+
     /// Image processing error.
     #[error("image error: {0}")]
     Image(#[from] image::ImageError),
@@ -49,7 +154,69 @@ pub enum Error {
     #[error("folder settings error: {0}")]
     FolderSettings(#[from] icon_sys::folder_settings::FolderSettingsError),
 
+    /// Error from the filesystem watcher.
+    #[cfg(feature = "watch")]
+    #[error("watch error: {0}")]
+    Watch(#[from] notify::Error),
+
+    /// Error from the SQLite state backend.
+    #[cfg(feature = "storage-sqlite")]
+    #[error("database error: {0}")]
+    Database(#[from] rusqlite::Error),
+
     /// Icon rendering error from folco-renderer.
     #[error("rendering error: {0}")]
     Render(#[from] folco_renderer::RenderError),
 }
+
+impl Error {
+    /// Returns a short, stable name for the kind of error, such as
+    /// `"timeout"`.
+    ///
+    /// Unlike the message, it never contains paths or other user data, so
+    /// it's safe to report in telemetry.
+    pub fn kind(&self) -> &'static str {
+        match self {
+            Error::AppDataDir(_) => "app-data-dir",
+            Error::IconSys(_) => "icon-sys",
+            Error::IconExtraction(_) => "icon-extraction",
+            Error::Cache(_) => "cache",
+            Error::Io(_) => "io",
+            Error::FolderCustomization(..) => "folder-customization",
+            Error::FolderReset(..) => "folder-reset",
+            Error::Timeout(..) => "timeout",
+            Error::Unsupported(..) => "unsupported",
+            Error::Library(_) => "library",
+            Error::PresetNotFound(_) => "preset-not-found",
+            Error::NoDefaultPreset => "no-default-preset",
+            Error::Helper(_) => "helper",
+            Error::Bookmark(_) => "bookmark",
+            Error::Rule(_) => "rule",
+            Error::Manifest(_) => "manifest",
+            Error::Layer(_) => "layer",
+            Error::Emoji(_) => "emoji",
+            Error::LightnessCurve(_) => "lightness-curve",
+            Error::Palette(_) => "palette",
+            Error::Sync(_) => "sync",
+            Error::Wallpaper(_) => "wallpaper",
+            Error::ReadOnly(_) => "read-only",
+            Error::Policy(_) => "policy",
+            Error::PayloadLimit(_) => "payload-limit",
+            Error::Hook(_) => "hook",
+            Error::Selection(_) => "selection",
+            Error::Cancelled(_) => "cancelled",
+            Error::Strict(_) => "strict",
+            Error::Notification(_) => "notification",
+            Error::Migration(_) => "migration",
+            Error::Image(_) => "image",
+            Error::NotInitialized(_) => "not-initialized",
+            Error::Serialization(_) => "serialization",
+            Error::FolderSettings(_) => "folder-settings",
+            #[cfg(feature = "watch")]
+            Error::Watch(_) => "watch",
+            #[cfg(feature = "storage-sqlite")]
+            Error::Database(_) => "database",
+            Error::Render(_) => "render",
+        }
+    }
+}
@@ -0,0 +1,147 @@
+//! Estimates of what a batch will cost before it runs.
+//!
+//! [`estimate_batch`](crate::CustomizationContext::estimate_batch) lets a
+//! GUI warn that a batch will take about four minutes and write about
+//! 300 MB before starting it. Timings are the means of the renders and
+//! folder applications the context has timed so far, or
+//! [`DEFAULT_RENDER_TIME`] and [`DEFAULT_APPLY_TIME`] until it has timed
+//! any.
+
+use crate::stats::OperationStats;
+
+use icon_sys::IconSet as SysIconSet;
+use serde::{Deserialize, Serialize};
+
+use std::time::Duration;
+
+/// Render time assumed before the context has timed a render.
+pub const DEFAULT_RENDER_TIME: Duration = Duration::from_millis(250);
+
+/// Time to apply an icon to one folder assumed before the context has
+/// timed one.
+pub const DEFAULT_APPLY_TIME: Duration = Duration::from_millis(40);
+
+/// The expected cost of customizing a batch of folders.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BatchEstimate {
+    /// Number of unique folders in the batch.
+    pub folders: usize,
+    /// Time to render the icons, zero if they're already cached.
+    pub render_time: Duration,
+    /// Time to apply the icons to every folder.
+    pub apply_time: Duration,
+    /// Bytes written to disk for all folders together.
+    ///
+    /// An upper bound, since icons are counted as uncompressed icon files
+    /// and most platforms compress the larger sizes.
+    pub disk_bytes: u64,
+    /// `true` if the timings come from operations the context timed, and
+    /// `false` if they're the defaults.
+    pub measured: bool,
+}
+
+impl BatchEstimate {
+    pub(crate) fn new(
+        folders: usize,
+        render: bool,
+        stats: &OperationStats,
+        icons: &SysIconSet,
+    ) -> Self {
+        if folders == 0 {
+            return Self::default();
+        }
+        let render_time = if render {
+            stats.mean_render_time().unwrap_or(DEFAULT_RENDER_TIME)
+        } else {
+            Duration::ZERO
+        };
+        let apply_time = stats.mean_apply_time().unwrap_or(DEFAULT_APPLY_TIME);
+        Self {
+            folders,
+            render_time,
+            apply_time: apply_time * u32::try_from(folders).unwrap_or(u32::MAX),
+            disk_bytes: icon_file_bytes(icons) * folders as u64,
+            measured: stats.mean_apply_time().is_some()
+                && (!render || stats.mean_render_time().is_some()),
+        }
+    }
+
+    /// Returns the expected time of the whole batch.
+    pub fn total_time(&self) -> Duration {
+        self.render_time + self.apply_time
+    }
+}
+
+/// Returns the size of `icons` written as an icon file of uncompressed
+/// bitmaps, with 32-bit color and a 1-bit mask per size.
+fn icon_file_bytes(icons: &SysIconSet) -> u64 {
+    // The file header, then a directory entry and bitmap header per size
+    let headers = 6 + icons.images.len() as u64 * (16 + 40);
+    let bitmaps: u64 = icons
+        .images
+        .iter()
+        .map(|image| {
+            let (width, height) = (
+                u64::from(image.data.width()),
+                u64::from(image.data.height()),
+            );
+            let mask_row = width.div_ceil(32) * 4;
+            (width * 4 + mask_row) * height
+        })
+        .sum();
+    headers + bitmaps
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use image::{DynamicImage, RgbaImage};
+
+    fn icon_set(sizes: &[u32]) -> SysIconSet {
+        SysIconSet {
+            images: sizes
+                .iter()
+                .map(|&size| icon_sys::IconImage {
+                    data: DynamicImage::ImageRgba8(RgbaImage::new(size, size)),
+                })
+                .collect(),
+        }
+    }
+
+    #[test]
+    fn test_defaults_before_anything_was_timed() {
+        let icons = icon_set(&[16, 32]);
+        let estimate = BatchEstimate::new(10, true, &OperationStats::default(), &icons);
+        assert_eq!(estimate.render_time, DEFAULT_RENDER_TIME);
+        assert_eq!(estimate.apply_time, DEFAULT_APPLY_TIME * 10);
+        assert!(!estimate.measured);
+
+        // 16x16: 64-byte rows and a 4-byte mask row; 32x32: 128 and 4
+        let per_folder = 6 + 2 * 56 + 68 * 16 + 132 * 32;
+        assert_eq!(estimate.disk_bytes, per_folder * 10);
+    }
+
+    #[test]
+    fn test_measured_timings() {
+        let mut stats = OperationStats::default();
+        stats.record_apply(Duration::from_millis(10));
+        stats.record_apply(Duration::from_millis(30));
+        let icons = icon_set(&[16]);
+
+        // A cached render needs no render timing
+        let estimate = BatchEstimate::new(3, false, &stats, &icons);
+        assert_eq!(estimate.render_time, Duration::ZERO);
+        assert_eq!(estimate.apply_time, Duration::from_millis(60));
+        assert!(estimate.measured);
+        assert_eq!(estimate.total_time(), Duration::from_millis(60));
+
+        stats.record_render(Duration::from_millis(500));
+        let estimate = BatchEstimate::new(3, true, &stats, &icons);
+        assert_eq!(estimate.total_time(), Duration::from_millis(560));
+        assert_eq!(
+            BatchEstimate::new(0, true, &stats, &icons),
+            BatchEstimate::default()
+        );
+    }
+}
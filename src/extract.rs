@@ -0,0 +1,391 @@
+//! Extracting the default folder icon, with fallbacks.
+//!
+//! The platform provider usually returns the right icon, but some Windows
+//! variants (Server Core, ARM64, insider builds) number shell32's resources
+//! differently and hand back the wrong art, or none. Each extracted icon set
+//! is verified before it's cached: it must have the expected sizes, and on
+//! Windows its surface must be the familiar folder yellow. When it isn't,
+//! the next candidate source is tried, ending with `imageres.dll`. If every
+//! candidate fails, the [`ExtractionReport`] lists what went wrong with each.
+
+use crate::error::{Error, Result};
+
+use icon_sys::folder_settings::{DefaultFolderIconProvider, PlatformDefaultFolderIconProvider};
+use icon_sys::IconSet as SysIconSet;
+use serde::{Deserialize, Serialize};
+
+use std::fmt;
+use std::fs;
+use std::path::PathBuf;
+
+/// Somewhere the default folder icon can be extracted from.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(
+    tag = "kind",
+    rename_all = "kebab-case",
+    rename_all_fields = "camelCase"
+)]
+pub enum IconSource {
+    /// The platform's default folder icon provider.
+    SystemDefault,
+    /// An icon group in the resources of a PE file, such as `shell32.dll`.
+    Resource {
+        /// The file holding the resources.
+        path: PathBuf,
+        /// Resource ID of the icon group.
+        group_id: u16,
+    },
+    /// A synthetic icon set from [`fixtures`](crate::fixtures), seeded
+    /// into the cache rather than extracted.
+    Fixture,
+}
+
+impl fmt::Display for IconSource {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::SystemDefault => f.write_str("system default"),
+            // The notation used by desktop.ini and the registry
+            Self::Resource { path, group_id } => write!(f, "{},-{group_id}", path.display()),
+            Self::Fixture => f.write_str("fixture"),
+        }
+    }
+}
+
+/// The outcome of trying one icon source.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ExtractionAttempt {
+    /// The source that was tried.
+    pub source: IconSource,
+    /// Why the source was rejected, or `None` if it was used.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+/// Every icon source tried while extracting the default folder icon.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ExtractionReport {
+    /// The attempts, in the order they were made.
+    pub attempts: Vec<ExtractionAttempt>,
+}
+
+impl ExtractionReport {
+    /// Returns the first source that produced a valid icon set, if any did.
+    pub fn succeeded(&self) -> Option<&IconSource> {
+        self.attempts
+            .iter()
+            .find(|attempt| attempt.error.is_none())
+            .map(|attempt| &attempt.source)
+    }
+}
+
+impl fmt::Display for ExtractionReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let attempts: Vec<String> = self
+            .attempts
+            .iter()
+            .map(|attempt| match &attempt.error {
+                Some(error) => format!("{}: {error}", attempt.source),
+                None => format!("{}: ok", attempt.source),
+            })
+            .collect();
+        f.write_str(&attempts.join("; "))
+    }
+}
+
+/// Returns the sources to try, in order.
+#[cfg(target_os = "windows")]
+pub fn candidate_sources() -> Vec<IconSource> {
+    let system_root =
+        PathBuf::from(std::env::var_os("SystemRoot").unwrap_or_else(|| "C:\\Windows".into()));
+    let resources = system_root.join("SystemResources");
+    let system32 = system_root.join("System32");
+
+    // The closed folder is icon group 4 in shell32 and 3 in imageres. Since
+    // Windows 10 1903 the art itself lives in the .mun files.
+    vec![
+        IconSource::SystemDefault,
+        IconSource::Resource {
+            path: resources.join("shell32.dll.mun"),
+            group_id: 4,
+        },
+        IconSource::Resource {
+            path: system32.join("shell32.dll"),
+            group_id: 4,
+        },
+        IconSource::Resource {
+            path: resources.join("imageres.dll.mun"),
+            group_id: 3,
+        },
+        IconSource::Resource {
+            path: system32.join("imageres.dll"),
+            group_id: 3,
+        },
+    ]
+}
+
+/// Returns the sources to try, in order.
+#[cfg(not(target_os = "windows"))]
+pub fn candidate_sources() -> Vec<IconSource> {
+    vec![IconSource::SystemDefault]
+}
+
+/// Extracts the default folder icon from the first candidate source that
+/// yields a valid icon set.
+///
+/// # Errors
+///
+/// Returns [`Error::IconExtraction`] with every attempt if none does.
+pub(crate) fn extract_folder_icon() -> Result<(SysIconSet, IconSource)> {
+    let mut report = ExtractionReport::default();
+    for source in candidate_sources() {
+        match try_source(&source) {
+            Ok(icon_set) => return Ok((icon_set, source)),
+            Err(error) => report.attempts.push(ExtractionAttempt {
+                source,
+                error: Some(error),
+            }),
+        }
+    }
+    Err(Error::IconExtraction(report))
+}
+
+/// Tries every candidate source and reports how each fared, without caching
+/// anything.
+pub(crate) fn diagnose() -> ExtractionReport {
+    let attempts = candidate_sources()
+        .into_iter()
+        .map(|source| {
+            let error = try_source(&source).err();
+            ExtractionAttempt { source, error }
+        })
+        .collect();
+    ExtractionReport { attempts }
+}
+
+fn try_source(source: &IconSource) -> std::result::Result<SysIconSet, String> {
+    let icon_set = match source {
+        IconSource::SystemDefault => PlatformDefaultFolderIconProvider
+            .dump_default_folder_icon()
+            .map_err(|e| e.to_string())?,
+        IconSource::Resource { path, group_id } => {
+            let bytes = fs::read(path).map_err(|e| e.to_string())?;
+            let images = crate::pe_icons::read_group_icon(&bytes, *group_id)?
+                .into_iter()
+                .filter(|image| is_supported_size(image.width()))
+                .map(|data| icon_sys::IconImage { data })
+                .collect();
+            SysIconSet { images }
+        }
+        IconSource::Fixture => return Err("fixtures are seeded, not extracted".to_string()),
+    };
+    verify_icon_set(&icon_set)?;
+    Ok(icon_set)
+}
+
+/// Sizes every valid Windows folder icon set has.
+#[cfg(target_os = "windows")]
+const REQUIRED_SIZES: &[u32] = &[16, 32, 48, 256];
+
+#[cfg(not(target_os = "windows"))]
+const REQUIRED_SIZES: &[u32] = &[];
+
+#[cfg(target_os = "windows")]
+fn is_supported_size(dimension: u32) -> bool {
+    icon_sys::icon::sys::windows::WindowsIconSize::from_dimension(dimension).is_some()
+        || crate::sys::windows::JUMBO_SIZES.contains(&dimension)
+}
+
+#[cfg(not(target_os = "windows"))]
+fn is_supported_size(_dimension: u32) -> bool {
+    true
+}
+
+/// Checks that an extracted icon set looks like the default folder icon.
+fn verify_icon_set(icon_set: &SysIconSet) -> std::result::Result<(), String> {
+    if icon_set.images.is_empty() {
+        return Err("no images".to_string());
+    }
+
+    let mut sizes = Vec::new();
+    for image in &icon_set.images {
+        let (width, height) = (image.data.width(), image.data.height());
+        if width != height {
+            return Err(format!("{width}x{height} image is not square"));
+        }
+        if !is_supported_size(width) {
+            return Err(format!("unsupported size {width}px"));
+        }
+        if image.data.to_rgba8().pixels().all(|pixel| pixel.0[3] == 0) {
+            return Err(format!("{width}px image is blank"));
+        }
+        sizes.push(width);
+    }
+
+    let missing: Vec<String> = REQUIRED_SIZES
+        .iter()
+        .filter(|size| !sizes.contains(size))
+        .map(u32::to_string)
+        .collect();
+    if !missing.is_empty() {
+        return Err(format!("missing sizes: {}", missing.join(", ")));
+    }
+
+    #[cfg(target_os = "windows")]
+    {
+        let largest = icon_set
+            .images
+            .iter()
+            .max_by_key(|image| image.data.width())
+            .map(|image| image.data.to_rgba8())
+            .ok_or_else(|| "no images".to_string())?;
+        let bounds = crate::sys::get_folder_icon_content_bounds(largest.width(), largest.height());
+        let (hue, _, _) = crate::sys::windows::SURFACE_HSL;
+        if !surface_matches(
+            &largest,
+            (bounds.x, bounds.y, bounds.width, bounds.height),
+            hue,
+        ) {
+            return Err("surface isn't the default folder color".to_string());
+        }
+    }
+
+    Ok(())
+}
+
+/// How far the mean surface hue may be from the expected one, in degrees.
+#[cfg(target_os = "windows")]
+const SURFACE_HUE_TOLERANCE: f32 = 25.0;
+
+/// Minimum mean surface saturation; anything below is treated as grey art.
+#[cfg(target_os = "windows")]
+const SURFACE_MIN_SATURATION: f32 = 0.35;
+
+/// Returns `true` if the opaque pixels in `bounds` average out to roughly
+/// `expected_hue`.
+#[cfg(target_os = "windows")]
+fn surface_matches(
+    image: &image::RgbaImage,
+    bounds: (u32, u32, u32, u32),
+    expected_hue: f32,
+) -> bool {
+    let (x, y, width, height) = bounds;
+    let (mut hue_x, mut hue_y, mut saturation, mut count) = (0.0f32, 0.0f32, 0.0f32, 0u32);
+    for py in y..(y + height).min(image.height()) {
+        for px in x..(x + width).min(image.width()) {
+            let [r, g, b, a] = image.get_pixel(px, py).0;
+            if a < 128 {
+                continue;
+            }
+            let (h, s) = hue_saturation(r, g, b);
+            // Averaged as vectors so hues either side of 0° don't cancel out
+            hue_x += h.to_radians().cos() * s;
+            hue_y += h.to_radians().sin() * s;
+            saturation += s;
+            count += 1;
+        }
+    }
+    if count == 0 || saturation / (count as f32) < SURFACE_MIN_SATURATION {
+        return false;
+    }
+
+    let mean_hue = hue_y.atan2(hue_x).to_degrees().rem_euclid(360.0);
+    let distance = (mean_hue - expected_hue).abs();
+    distance.min(360.0 - distance) <= SURFACE_HUE_TOLERANCE
+}
+
+/// Returns the HSL hue in degrees and saturation of an sRGB color.
+#[cfg(target_os = "windows")]
+fn hue_saturation(r: u8, g: u8, b: u8) -> (f32, f32) {
+    let (r, g, b) = (
+        f32::from(r) / 255.0,
+        f32::from(g) / 255.0,
+        f32::from(b) / 255.0,
+    );
+    let max = r.max(g).max(b);
+    let min = r.min(g).min(b);
+    let delta = max - min;
+    if delta == 0.0 {
+        return (0.0, 0.0);
+    }
+
+    let lightness = (max + min) / 2.0;
+    let saturation = delta / (1.0 - (2.0 * lightness - 1.0).abs());
+    let hue = if max == r {
+        60.0 * ((g - b) / delta).rem_euclid(6.0)
+    } else if max == g {
+        60.0 * ((b - r) / delta + 2.0)
+    } else {
+        60.0 * ((r - g) / delta + 4.0)
+    };
+    (hue, saturation)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use image::{DynamicImage, Rgba, RgbaImage};
+
+    fn solid_set(sizes: &[u32], color: [u8; 4]) -> SysIconSet {
+        SysIconSet {
+            images: sizes
+                .iter()
+                .map(|size| icon_sys::IconImage {
+                    data: DynamicImage::ImageRgba8(RgbaImage::from_pixel(
+                        *size,
+                        *size,
+                        Rgba(color),
+                    )),
+                })
+                .collect(),
+        }
+    }
+
+    #[test]
+    fn test_blank_and_empty_sets_are_rejected() {
+        assert!(verify_icon_set(&SysIconSet { images: Vec::new() }).is_err());
+
+        let error = verify_icon_set(&solid_set(&[16, 32, 48, 256], [0, 0, 0, 0])).unwrap_err();
+        assert!(error.contains("blank"));
+    }
+
+    #[test]
+    fn test_report_display() {
+        let report = ExtractionReport {
+            attempts: vec![
+                ExtractionAttempt {
+                    source: IconSource::SystemDefault,
+                    error: Some("missing sizes: 256".to_string()),
+                },
+                ExtractionAttempt {
+                    source: IconSource::Resource {
+                        path: PathBuf::from("imageres.dll"),
+                        group_id: 3,
+                    },
+                    error: None,
+                },
+            ],
+        };
+        assert_eq!(
+            report.to_string(),
+            "system default: missing sizes: 256; imageres.dll,-3: ok"
+        );
+        assert_eq!(report.succeeded(), Some(&report.attempts[1].source));
+    }
+
+    #[cfg(target_os = "windows")]
+    #[test]
+    fn test_surface_color_check() {
+        let yellow = solid_set(&[16, 32, 48, 256], [255, 216, 112, 255]);
+        assert!(verify_icon_set(&yellow).is_ok());
+
+        let blue = solid_set(&[16, 32, 48, 256], [40, 120, 220, 255]);
+        assert!(verify_icon_set(&blue).unwrap_err().contains("surface"));
+
+        let incomplete = solid_set(&[16, 32], [255, 216, 112, 255]);
+        assert!(verify_icon_set(&incomplete)
+            .unwrap_err()
+            .contains("48, 256"));
+    }
+}
@@ -0,0 +1,147 @@
+//! Stable filesystem identifiers for folders.
+//!
+//! A folder's path changes when it's moved or renamed, but its identity on
+//! the filesystem doesn't. Recording the identity next to the path lets folco
+//! find customized folders again after the user reorganizes them.
+//!
+//! On Unix, including macOS, the identity is the device and inode number;
+//! on Windows, it's the volume serial number and NTFS file index.
+
+use serde::{Deserialize, Serialize};
+
+use std::path::Path;
+
+/// Identifies a folder independently of its path.
+///
+/// Identifiers are only meaningful on the machine and volume they were read
+/// from.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct FileId {
+    /// Device (volume) the folder lives on.
+    pub device: u64,
+    /// Inode or file index on that device.
+    pub inode: u64,
+}
+
+impl FileId {
+    /// Reads the identifier of an existing path, without following symlinks.
+    ///
+    /// Returns `None` if the path doesn't exist or the platform has no
+    /// stable identifier available.
+    #[cfg(not(windows))]
+    pub fn of(path: &Path) -> Option<Self> {
+        let metadata = std::fs::symlink_metadata(path).ok()?;
+        Self::from_metadata(&metadata)
+    }
+
+    /// Reads the identifier of an existing path, without following symlinks.
+    ///
+    /// Returns `None` if the path doesn't exist or can't be opened.
+    #[cfg(windows)]
+    pub fn of(path: &Path) -> Option<Self> {
+        windows::file_id(path)
+    }
+
+    /// Extracts the identifier from already-read metadata.
+    #[cfg(unix)]
+    pub fn from_metadata(metadata: &std::fs::Metadata) -> Option<Self> {
+        use std::os::unix::fs::MetadataExt;
+
+        Some(Self {
+            device: metadata.dev(),
+            inode: metadata.ino(),
+        })
+    }
+
+    /// Extracts the identifier from already-read metadata.
+    ///
+    /// The standard library doesn't expose file indices on this platform
+    /// yet; use [`of`](Self::of), which opens the path.
+    #[cfg(not(unix))]
+    pub fn from_metadata(_metadata: &std::fs::Metadata) -> Option<Self> {
+        None
+    }
+}
+
+#[cfg(windows)]
+mod windows {
+    use super::FileId;
+
+    use std::ffi::c_void;
+    use std::fs::OpenOptions;
+    use std::os::windows::fs::OpenOptionsExt;
+    use std::os::windows::io::AsRawHandle;
+    use std::path::Path;
+
+    const FILE_READ_ATTRIBUTES: u32 = 0x80;
+    const FILE_SHARE_ALL: u32 = 0x1 | 0x2 | 0x4;
+    const FILE_FLAG_BACKUP_SEMANTICS: u32 = 0x0200_0000;
+    const FILE_FLAG_OPEN_REPARSE_POINT: u32 = 0x0020_0000;
+
+    /// `BY_HANDLE_FILE_INFORMATION`.
+    #[repr(C)]
+    #[derive(Default)]
+    struct FileInformation {
+        file_attributes: u32,
+        creation_time: [u32; 2],
+        last_access_time: [u32; 2],
+        last_write_time: [u32; 2],
+        volume_serial_number: u32,
+        file_size_high: u32,
+        file_size_low: u32,
+        number_of_links: u32,
+        file_index_high: u32,
+        file_index_low: u32,
+    }
+
+    #[link(name = "kernel32")]
+    unsafe extern "system" {
+        fn GetFileInformationByHandle(file: *mut c_void, information: *mut FileInformation)
+        -> i32;
+    }
+
+    pub(super) fn file_id(path: &Path) -> Option<FileId> {
+        // Backup semantics are needed to open a directory at all
+        let file = OpenOptions::new()
+            .access_mode(FILE_READ_ATTRIBUTES)
+            .share_mode(FILE_SHARE_ALL)
+            .custom_flags(FILE_FLAG_BACKUP_SEMANTICS | FILE_FLAG_OPEN_REPARSE_POINT)
+            .open(path)
+            .ok()?;
+        let mut information = FileInformation::default();
+        // SAFETY: the handle is open for the duration of the call, and
+        // `information` matches the layout the function writes.
+        let ok = unsafe { GetFileInformationByHandle(file.as_raw_handle(), &mut information) };
+        (ok != 0).then(|| FileId {
+            device: u64::from(information.volume_serial_number),
+            inode: u64::from(information.file_index_high) << 32
+                | u64::from(information.file_index_low),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    #[test]
+    fn test_missing_path_has_no_id() {
+        let temp = tempdir().unwrap();
+        assert!(FileId::of(&temp.path().join("missing")).is_none());
+    }
+
+    #[cfg(any(unix, windows))]
+    #[test]
+    fn test_id_survives_rename() {
+        let temp = tempdir().unwrap();
+        let before = temp.path().join("before");
+        let after = temp.path().join("after");
+        std::fs::create_dir(&before).unwrap();
+
+        let id = FileId::of(&before).unwrap();
+        std::fs::rename(&before, &after).unwrap();
+
+        assert_eq!(FileId::of(&after), Some(id));
+    }
+}
@@ -0,0 +1,254 @@
+//! Synthetic base icon sets for tests and benches.
+//!
+//! Extracting the real folder icon depends on the machine: the Windows
+//! build, the icon theme, or whether there's a desktop at all. The fixtures
+//! here are drawn instead, as a flat folder at every size a platform uses,
+//! with known content bounds and the surface filled in exactly
+//! [`FIXTURE_SURFACE_HSL`], so tests and benches give the same results on
+//! every machine and in CI.
+//!
+//! ```ignore
+//! use folco_core::fixtures::{fixture_icon_base, FixturePlatform};
+//! use folco_core::IconCustomizer;
+//!
+//! let customizer = IconCustomizer::new(fixture_icon_base(FixturePlatform::Windows));
+//! ```
+
+use crate::cache::IconCache;
+use crate::error::Result;
+use crate::extract::IconSource;
+use crate::oklch::hsl_to_srgb;
+
+use folco_renderer::{
+    IconBase, IconImage as RendererIconImage, IconSet as RendererIconSet, RectPx, SurfaceColor,
+};
+use icon_sys::IconSet as SysIconSet;
+use image::{DynamicImage, Rgba, RgbaImage};
+
+/// Surface color of every fixture as `(hue, saturation, lightness)`, the
+/// Windows folder yellow.
+pub const FIXTURE_SURFACE_HSL: (f32, f32, f32) = (44.0, 1.0, 0.72);
+
+/// [`FIXTURE_SURFACE_HSL`] as the renderer's surface color.
+pub const FIXTURE_SURFACE_COLOR: SurfaceColor = SurfaceColor::new(
+    FIXTURE_SURFACE_HSL.0,
+    FIXTURE_SURFACE_HSL.1,
+    FIXTURE_SURFACE_HSL.2,
+);
+
+/// Color of the back panel and tab, a darker shade of the surface.
+const BACK_HSL: (f32, f32, f32) = (40.0, 0.9, 0.55);
+
+/// The platform whose icon sizes and bounds a fixture mimics.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum FixturePlatform {
+    /// The shell32 folder icon's sizes and content bounds.
+    Windows,
+    /// The sizes of a macOS `.icns` folder icon.
+    Macos,
+    /// The sizes of a freedesktop icon theme.
+    Linux,
+}
+
+impl FixturePlatform {
+    /// Returns the platform whose icons this build uses: the one it runs on,
+    /// or Linux for any other.
+    pub fn current() -> Self {
+        if cfg!(target_os = "windows") {
+            Self::Windows
+        } else if cfg!(target_os = "macos") {
+            Self::Macos
+        } else {
+            Self::Linux
+        }
+    }
+
+    /// Returns the icon sizes of the platform, smallest first.
+    pub fn sizes(self) -> &'static [u32] {
+        match self {
+            Self::Windows => &[16, 20, 24, 32, 40, 48, 64, 256],
+            Self::Macos => &[16, 32, 64, 128, 256, 512, 1024],
+            Self::Linux => &[16, 22, 24, 32, 48, 64, 96, 128, 256],
+        }
+    }
+
+    /// Returns the content bounds of the fixture's `size` pixel image.
+    ///
+    /// The Windows sizes get the shell32 icon's bounds; every other size
+    /// gets the 256-pixel bounds, scaled.
+    pub fn content_bounds(self, size: u32) -> RectPx {
+        match (self, size) {
+            (Self::Windows, 16) => RectPx::new(0, 4, 16, 9),
+            (Self::Windows, 20) => RectPx::new(1, 6, 18, 10),
+            (Self::Windows, 24) => RectPx::new(1, 6, 22, 13),
+            (Self::Windows, 32) => RectPx::new(2, 8, 28, 17),
+            (Self::Windows, 40) => RectPx::new(2, 10, 38, 22),
+            (Self::Windows, 48) => RectPx::new(3, 11, 42, 27),
+            (Self::Windows, 64) => RectPx::new(4, 16, 56, 36),
+            _ => {
+                let scale = |value: u32| value * size / 256;
+                RectPx::new(scale(16), scale(62), scale(224), scale(144))
+            }
+        }
+    }
+}
+
+/// Draws the fixture base icon set of `platform`.
+pub fn fixture_icon_set(platform: FixturePlatform) -> SysIconSet {
+    SysIconSet {
+        images: platform
+            .sizes()
+            .iter()
+            .map(|&size| draw_folder(size, platform.content_bounds(size)))
+            .collect(),
+    }
+}
+
+/// Draws the fixture base icon set of `platform` in the renderer's format,
+/// with the fixture's own content bounds.
+pub fn fixture_renderer_icon_set(platform: FixturePlatform) -> RendererIconSet {
+    let images = fixture_icon_set(platform)
+        .images
+        .into_iter()
+        .map(|image| {
+            let rgba = image.data.to_rgba8();
+            let bounds = platform.content_bounds(rgba.width());
+            RendererIconImage::new(rgba, 1.0, bounds)
+        })
+        .collect();
+    RendererIconSet::from_images(images)
+}
+
+/// Returns an icon base for `platform`'s fixture, ready for an
+/// `IconCustomizer`.
+pub fn fixture_icon_base(platform: FixturePlatform) -> IconBase {
+    IconBase::new(fixture_renderer_icon_set(platform), FIXTURE_SURFACE_COLOR)
+}
+
+/// Stores `platform`'s fixture in `cache`, so contexts built on it skip
+/// system extraction.
+///
+/// Contexts convert the cached icons with the running platform's bounds,
+/// so seed the [current](FixturePlatform::current) platform's fixture for
+/// them. Refreshing the cache replaces the fixture with the extracted icon.
+///
+/// # Errors
+///
+/// Returns an error if the cache can't be written.
+pub fn seed_cache(cache: &IconCache, platform: FixturePlatform) -> Result<SysIconSet> {
+    let icon_set = fixture_icon_set(platform);
+    cache.store(&icon_set, IconSource::Fixture)?;
+    Ok(icon_set)
+}
+
+/// Draws a folder `size` pixels square: a tab and back panel behind a front
+/// panel filling `bounds` in the surface color.
+pub(crate) fn draw_folder(size: u32, bounds: RectPx) -> icon_sys::IconImage {
+    let front = rgba(FIXTURE_SURFACE_HSL);
+    let back = rgba(BACK_HSL);
+    let lip = (size / 16).max(1);
+    let tab_top = bounds.y.saturating_sub(2 * lip);
+
+    let mut image = RgbaImage::new(size, size);
+    let right = bounds.x + bounds.width;
+    let bottom = bounds.y + bounds.height;
+    for y in tab_top..bottom.min(size) {
+        for x in bounds.x..right.min(size) {
+            let pixel = if y >= bounds.y {
+                front
+            } else if y + lip >= bounds.y || x < bounds.x + bounds.width * 2 / 5 {
+                back
+            } else {
+                continue;
+            };
+            image.put_pixel(x, y, pixel);
+        }
+    }
+    icon_sys::IconImage {
+        data: DynamicImage::ImageRgba8(image),
+    }
+}
+
+fn rgba((hue, saturation, lightness): (f32, f32, f32)) -> Rgba<u8> {
+    let [r, g, b] = hsl_to_srgb(hue, saturation, lightness).map(|c| (c * 255.0).round() as u8);
+    Rgba([r, g, b, 255])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cache::CacheConfig;
+    use crate::oklch::srgb_to_hsl;
+    use tempfile::tempdir;
+
+    const PLATFORMS: [FixturePlatform; 3] = [
+        FixturePlatform::Windows,
+        FixturePlatform::Macos,
+        FixturePlatform::Linux,
+    ];
+
+    #[test]
+    fn test_fixtures_have_every_size() {
+        for platform in PLATFORMS {
+            let sizes: Vec<u32> = fixture_icon_set(platform)
+                .images
+                .iter()
+                .map(|image| image.data.width())
+                .collect();
+            assert_eq!(sizes, platform.sizes());
+        }
+    }
+
+    #[test]
+    fn test_surface_fills_the_bounds() {
+        for platform in PLATFORMS {
+            for image in fixture_icon_set(platform).images {
+                let image = image.data.to_rgba8();
+                let bounds = platform.content_bounds(image.width());
+                let corners = [
+                    (bounds.x, bounds.y),
+                    (bounds.x + bounds.width - 1, bounds.y + bounds.height - 1),
+                ];
+                for (x, y) in corners {
+                    let [r, g, b, a] = image.get_pixel(x, y).0;
+                    assert_eq!(a, 255);
+                    let (hue, saturation, _) = srgb_to_hsl([r, g, b].map(|c| f32::from(c) / 255.0));
+                    assert!(
+                        (hue - FIXTURE_SURFACE_HSL.0).abs() < 2.0,
+                        "{platform:?}: {hue}"
+                    );
+                    assert!(saturation > 0.95);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_fixtures_are_deterministic() {
+        let first = fixture_icon_set(FixturePlatform::Linux);
+        let second = fixture_icon_set(FixturePlatform::Linux);
+        for (a, b) in first.images.iter().zip(&second.images) {
+            assert_eq!(a.data.to_rgba8(), b.data.to_rgba8());
+        }
+    }
+
+    #[test]
+    fn test_bounds_scale_for_other_sizes() {
+        let bounds = FixturePlatform::Macos.content_bounds(128);
+        assert_eq!(
+            (bounds.x, bounds.y, bounds.width, bounds.height),
+            (8, 31, 112, 72)
+        );
+    }
+
+    #[test]
+    fn test_seed_cache() {
+        let temp = tempdir().unwrap();
+        let cache = IconCache::new(CacheConfig::new(temp.path()));
+        seed_cache(&cache, FixturePlatform::Windows).unwrap();
+        assert!(cache.is_cached());
+        assert_eq!(cache.info().source, Some(IconSource::Fixture));
+        let cached = cache.get_sys_icon_set().unwrap();
+        assert_eq!(cached.images.len(), FixturePlatform::Windows.sizes().len());
+    }
+}
@@ -0,0 +1,437 @@
+//! Custom behavior around each folder of a batch.
+//!
+//! A [`Hook`] registered on a context is called for every folder that a
+//! batch customizes or resets, before the icon is changed and after it
+//! succeeded or failed. Apps can notify the user, sync another system or
+//! run a script from there without reimplementing the batch loop, and
+//! [`before_apply`](Hook::before_apply) can refuse a folder.
+//!
+//! Users can get the same without code: [`CommandHook`]s in the app config
+//! name a program to run after each folder is customized or reset, with
+//! the folder's path filled into its arguments. An admin
+//! [`Policy`](crate::Policy) can turn them off.
+
+use crate::error::{Error, Result};
+use crate::telemetry::OperationKind;
+
+use serde::{Deserialize, Serialize};
+
+use std::path::Path;
+use std::process::{Command, Stdio};
+use std::sync::Arc;
+use std::thread;
+use std::time::{Duration, Instant};
+
+/// Default [`CommandHook::timeout_ms`]: 10 seconds.
+pub const DEFAULT_COMMAND_HOOK_TIMEOUT_MS: u64 = 10_000;
+
+/// Called around each folder a batch customizes or resets.
+///
+/// Hooks run between folders, so a slow hook slows the batch down. Async
+/// batches call them on a thread of their own, as they do command hooks,
+/// so they never block the async runtime. Every method does nothing by
+/// default.
+///
+/// # Example
+///
+/// ```ignore
+/// use folco_core::{Hook, OperationKind};
+///
+/// struct Announce;
+///
+/// impl Hook for Announce {
+///     fn after_apply(&self, operation: OperationKind, folder: &Path) {
+///         println!("{operation}: {}", folder.display());
+///     }
+/// }
+///
+/// let ctx = CustomizationContextBuilder::new()
+///     .with_hook(Arc::new(Announce))
+///     .build()?;
+/// ```
+pub trait Hook: Send + Sync {
+    /// Called before the icon of `folder` is changed.
+    ///
+    /// Returning an error fails the folder with it, without changing the
+    /// icon or calling the remaining hooks.
+    fn before_apply(&self, operation: OperationKind, folder: &Path) -> Result<()> {
+        let _ = (operation, folder);
+        Ok(())
+    }
+
+    /// Called after the icon of `folder` was changed.
+    fn after_apply(&self, operation: OperationKind, folder: &Path) {
+        let _ = (operation, folder);
+    }
+
+    /// Called when `folder` failed once the hooks were consulted with
+    /// [`before_apply`](Self::before_apply), including when one of them
+    /// refused it.
+    fn on_error(&self, operation: OperationKind, folder: &Path, error: &Error) {
+        let _ = (operation, folder, error);
+    }
+}
+
+/// The hooks of a context, called in the order they were added.
+#[derive(Clone, Default)]
+pub(crate) struct Hooks {
+    hooks: Vec<Arc<dyn Hook>>,
+}
+
+impl Hooks {
+    pub(crate) fn push(&mut self, hook: Arc<dyn Hook>) {
+        self.hooks.push(hook);
+    }
+
+    pub(crate) fn clear(&mut self) {
+        self.hooks.clear();
+    }
+
+    pub(crate) fn len(&self) -> usize {
+        self.hooks.len()
+    }
+
+    /// Calls [`Hook::before_apply`] on each hook until one refuses the
+    /// folder.
+    pub(crate) fn before(&self, operation: OperationKind, folder: &Path) -> Result<()> {
+        self.hooks
+            .iter()
+            .try_for_each(|hook| hook.before_apply(operation, folder))
+    }
+
+    /// Calls [`Hook::after_apply`] or [`Hook::on_error`] on every hook,
+    /// depending on `result`.
+    pub(crate) fn after(&self, operation: OperationKind, folder: &Path, result: &Result<()>) {
+        for hook in &self.hooks {
+            match result {
+                Ok(()) => hook.after_apply(operation, folder),
+                Err(e) => hook.on_error(operation, folder, e),
+            }
+        }
+    }
+}
+
+/// An external command run after each folder is customized or reset.
+///
+/// Each argument can include these placeholders, replaced for each folder:
+///
+/// - `{folder}`: the folder's full path
+/// - `{name}`: the folder's name
+/// - `{parent}`: the path of the folder it's in
+/// - `{operation}`: `customize` or `reset`
+///
+/// The arguments are passed to the program as they are, not through a
+/// shell, so paths with spaces stay one argument. The command runs once the
+/// folder's icon has changed; if it fails or times out, the failure is
+/// [logged](crate::CoreLog) and the folder still counts as done.
+///
+/// # Example
+///
+/// ```
+/// use folco_core::{CommandHook, OperationKind};
+///
+/// // notify-send "Customized Projects"
+/// let hook = CommandHook::new("notify-send")
+///     .with_arg("Customized {name}")
+///     .with_operation(OperationKind::Customize);
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CommandHook {
+    /// The program to run, found on the `PATH` unless it's a path.
+    pub program: String,
+    /// Arguments, with placeholders.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub args: Vec<String>,
+    /// Operations after which the command runs; every operation if empty.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub operations: Vec<OperationKind>,
+    /// How long the command may run before it's killed, in milliseconds.
+    #[serde(default = "default_timeout_ms")]
+    pub timeout_ms: u64,
+}
+
+fn default_timeout_ms() -> u64 {
+    DEFAULT_COMMAND_HOOK_TIMEOUT_MS
+}
+
+impl CommandHook {
+    /// Creates a hook running `program` without arguments after every
+    /// operation.
+    pub fn new(program: impl Into<String>) -> Self {
+        Self {
+            program: program.into(),
+            args: Vec::new(),
+            operations: Vec::new(),
+            timeout_ms: DEFAULT_COMMAND_HOOK_TIMEOUT_MS,
+        }
+    }
+
+    /// Adds an argument, which may include placeholders.
+    pub fn with_arg(mut self, arg: impl Into<String>) -> Self {
+        self.args.push(arg.into());
+        self
+    }
+
+    /// Runs the command after `operation`, instead of after every
+    /// operation once called.
+    pub fn with_operation(mut self, operation: OperationKind) -> Self {
+        if !self.operations.contains(&operation) {
+            self.operations.push(operation);
+        }
+        self
+    }
+
+    /// Sets how long the command may run before it's killed.
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.timeout_ms = u64::try_from(timeout.as_millis()).unwrap_or(u64::MAX);
+        self
+    }
+
+    /// Returns `true` if the command runs after `operation`.
+    pub fn runs_after(&self, operation: OperationKind) -> bool {
+        self.operations.is_empty() || self.operations.contains(&operation)
+    }
+
+    /// Returns the arguments for `folder`, with the placeholders replaced.
+    ///
+    /// Each argument is expanded in a single pass, so placeholders in the
+    /// folder's own name are passed through as they are.
+    pub fn expand_args(&self, operation: OperationKind, folder: &Path) -> Vec<String> {
+        let name = folder
+            .file_name()
+            .map(|name| name.to_string_lossy())
+            .unwrap_or_default();
+        let parent = folder
+            .parent()
+            .map(|parent| parent.to_string_lossy())
+            .unwrap_or_default();
+        let folder = folder.to_string_lossy();
+        let operation = operation.to_string();
+        let values = [
+            ("{folder}", &*folder),
+            ("{name}", &*name),
+            ("{parent}", &*parent),
+            ("{operation}", operation.as_str()),
+        ];
+        self.args.iter().map(|arg| expand(arg, &values)).collect()
+    }
+
+    /// Checks that the hook names a program and has a timeout.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Error::Hook`] describing the problem.
+    pub fn validate(&self) -> Result<()> {
+        if self.program.trim().is_empty() {
+            return Err(Error::Hook("command hook has no program".to_string()));
+        }
+        if self.timeout_ms == 0 {
+            return Err(Error::Hook(format!(
+                "command hook `{}` has no time to run",
+                self.program
+            )));
+        }
+        Ok(())
+    }
+
+    /// Runs the command for `folder` and waits for it to finish.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Error::Hook`] if the program can't be started, exits
+    /// unsuccessfully or is killed for running past its timeout.
+    pub fn run(&self, operation: OperationKind, folder: &Path) -> Result<()> {
+        let mut child = Command::new(&self.program)
+            .args(self.expand_args(operation, folder))
+            .stdin(Stdio::null())
+            .stdout(Stdio::null())
+            .stderr(Stdio::null())
+            .spawn()
+            .map_err(|e| Error::Hook(format!("can't run `{}`: {e}", self.program)))?;
+
+        let deadline = Instant::now() + Duration::from_millis(self.timeout_ms);
+        loop {
+            let status = child
+                .try_wait()
+                .map_err(|e| Error::Hook(format!("`{}`: {e}", self.program)))?;
+            match status {
+                Some(status) if status.success() => return Ok(()),
+                Some(status) => {
+                    return Err(Error::Hook(format!("`{}` failed: {status}", self.program)));
+                }
+                None if Instant::now() >= deadline => {
+                    let _ = child.kill();
+                    let _ = child.wait();
+                    return Err(Error::Hook(format!(
+                        "`{}` timed out after {}ms",
+                        self.program, self.timeout_ms
+                    )));
+                }
+                None => thread::sleep(Duration::from_millis(10)),
+            }
+        }
+    }
+}
+
+/// Returns `arg` with each placeholder of `values` replaced by its value.
+fn expand(arg: &str, values: &[(&str, &str)]) -> String {
+    let mut expanded = String::with_capacity(arg.len());
+    let mut rest = arg;
+    while let Some(start) = rest.find('{') {
+        expanded.push_str(&rest[..start]);
+        rest = &rest[start..];
+        match values.iter().find(|(placeholder, _)| rest.starts_with(placeholder)) {
+            Some((placeholder, value)) => {
+                expanded.push_str(value);
+                rest = &rest[placeholder.len()..];
+            }
+            None => {
+                expanded.push('{');
+                rest = &rest[1..];
+            }
+        }
+    }
+    expanded.push_str(rest);
+    expanded
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Mutex;
+
+    #[derive(Default)]
+    struct Recorder {
+        calls: Mutex<Vec<String>>,
+        refuse: bool,
+    }
+
+    impl Recorder {
+        fn record(&self, call: String) {
+            self.calls.lock().unwrap().push(call);
+        }
+    }
+
+    impl Hook for Recorder {
+        fn before_apply(&self, operation: OperationKind, _folder: &Path) -> Result<()> {
+            self.record(format!("before {operation}"));
+            if self.refuse {
+                return Err(Error::Hook("refused".to_string()));
+            }
+            Ok(())
+        }
+
+        fn after_apply(&self, operation: OperationKind, _folder: &Path) {
+            self.record(format!("after {operation}"));
+        }
+
+        fn on_error(&self, _operation: OperationKind, _folder: &Path, error: &Error) {
+            self.record(format!("error {error}"));
+        }
+    }
+
+    #[test]
+    fn test_hooks_run_in_order() {
+        let first = Arc::new(Recorder::default());
+        let second = Arc::new(Recorder::default());
+        let mut hooks = Hooks::default();
+        hooks.push(first.clone());
+        hooks.push(second.clone());
+
+        let folder = Path::new("/folders/a");
+        assert!(hooks.before(OperationKind::Customize, folder).is_ok());
+        hooks.after(OperationKind::Customize, folder, &Ok(()));
+        for recorder in [&first, &second] {
+            assert_eq!(
+                *recorder.calls.lock().unwrap(),
+                ["before customize", "after customize"]
+            );
+        }
+    }
+
+    #[test]
+    fn test_refusing_hook_stops_the_folder() {
+        let refusing = Arc::new(Recorder {
+            refuse: true,
+            ..Default::default()
+        });
+        let later = Arc::new(Recorder::default());
+        let mut hooks = Hooks::default();
+        hooks.push(refusing.clone());
+        hooks.push(later.clone());
+
+        let folder = Path::new("/folders/a");
+        let result = hooks.before(OperationKind::Reset, folder);
+        assert!(matches!(result, Err(Error::Hook(_))));
+        hooks.after(OperationKind::Reset, folder, &result);
+        assert_eq!(
+            *refusing.calls.lock().unwrap(),
+            ["before reset", "error hook failed: refused"]
+        );
+        assert_eq!(*later.calls.lock().unwrap(), ["error hook failed: refused"]);
+    }
+
+    #[test]
+    fn test_command_hook_args() {
+        let hook = CommandHook::new("notify")
+            .with_arg("{operation}: {name}")
+            .with_arg("--in={parent}")
+            .with_arg("{folder}");
+        let folder = Path::new("/folders/My Projects");
+        assert_eq!(
+            hook.expand_args(OperationKind::Reset, folder),
+            [
+                "reset: My Projects",
+                "--in=/folders",
+                "/folders/My Projects"
+            ]
+        );
+    }
+
+    #[test]
+    fn test_command_hook_args_are_expanded_once() {
+        let hook = CommandHook::new("notify").with_arg("{name} in {parent}");
+        let folder = Path::new("/{operation}/{folder}");
+        assert_eq!(
+            hook.expand_args(OperationKind::Customize, folder),
+            ["{folder} in /{operation}"]
+        );
+    }
+
+    #[test]
+    fn test_command_hook_operations() {
+        let every = CommandHook::new("true");
+        assert!(every.runs_after(OperationKind::Customize));
+        assert!(every.runs_after(OperationKind::Reset));
+
+        let customize = CommandHook::new("true").with_operation(OperationKind::Customize);
+        assert!(customize.runs_after(OperationKind::Customize));
+        assert!(!customize.runs_after(OperationKind::Reset));
+
+        let hook: CommandHook = serde_json::from_str(r#"{ "program": "true" }"#).unwrap();
+        assert_eq!(hook, every);
+        assert!(CommandHook::new(" ").validate().is_err());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_run_command_hook() {
+        let temp = tempfile::tempdir().unwrap();
+        let hook = CommandHook::new("touch").with_arg("{folder}/marker");
+        hook.run(OperationKind::Customize, temp.path()).unwrap();
+        assert!(temp.path().join("marker").exists());
+
+        assert!(
+            CommandHook::new("false")
+                .run(OperationKind::Reset, temp.path())
+                .is_err()
+        );
+        let slow = CommandHook::new("sleep")
+            .with_arg("5")
+            .with_timeout(Duration::from_millis(50));
+        let started = Instant::now();
+        assert!(slow.run(OperationKind::Reset, temp.path()).is_err());
+        assert!(started.elapsed() < Duration::from_secs(5));
+    }
+}
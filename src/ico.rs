@@ -0,0 +1,406 @@
+//! ICO encoding, including entries for legacy Windows.
+//!
+//! Modern Windows reads 32-bit icons, stored as PNG. Windows XP can't read
+//! PNG entries, and some contexts, such as network shares browsed from old
+//! systems or certain launchers, only show palette icons and draw 32-bit
+//! ones as black squares. [`LegacyIcoOptions`] adds the entries they need
+//! to an icon: small sizes as bitmaps with a 1-bit transparency mask, and
+//! 256- and 16-color variants of them.
+
+use crate::error::{Error, Result};
+
+use icon_sys::IconSet as SysIconSet;
+use image::{ImageFormat, RgbaImage};
+
+use std::io::Cursor;
+
+/// Size of the ICO header and of each directory entry.
+const HEADER_LEN: usize = 6;
+const ENTRY_LEN: usize = 16;
+
+/// Size of a `BITMAPINFOHEADER`.
+const BITMAP_HEADER_LEN: u32 = 40;
+
+/// Largest size given palette variants, like the classic Windows icons.
+const LEGACY_MAX_SIZE: u32 = 48;
+
+/// Smallest size stored as PNG when bitmap entries are requested; Windows
+/// Vista introduced both the PNG entries and the 256-pixel size.
+const PNG_MIN_SIZE: u32 = 256;
+
+/// Alpha below which a pixel is transparent in the 1-bit mask.
+const MASK_THRESHOLD: u8 = 128;
+
+/// The 16 colors of the standard Windows palette.
+const PALETTE_16: [[u8; 3]; 16] = [
+    [0x00, 0x00, 0x00],
+    [0x80, 0x00, 0x00],
+    [0x00, 0x80, 0x00],
+    [0x80, 0x80, 0x00],
+    [0x00, 0x00, 0x80],
+    [0x80, 0x00, 0x80],
+    [0x00, 0x80, 0x80],
+    [0xC0, 0xC0, 0xC0],
+    [0x80, 0x80, 0x80],
+    [0xFF, 0x00, 0x00],
+    [0x00, 0xFF, 0x00],
+    [0xFF, 0xFF, 0x00],
+    [0x00, 0x00, 0xFF],
+    [0xFF, 0x00, 0xFF],
+    [0x00, 0xFF, 0xFF],
+    [0xFF, 0xFF, 0xFF],
+];
+
+/// Extra entries written into `.ico` files for legacy contexts.
+///
+/// All off by default, which writes what the platform provider writes.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub struct LegacyIcoOptions {
+    /// Add 256-color variants of the sizes up to 48 pixels.
+    pub palette_8bit: bool,
+    /// Add 16-color variants, in the standard Windows palette, of the sizes
+    /// up to 48 pixels.
+    pub palette_4bit: bool,
+    /// Store sizes below 256 pixels as bitmaps with a transparency mask
+    /// instead of PNG, for Windows XP.
+    pub bitmap_entries: bool,
+}
+
+impl LegacyIcoOptions {
+    /// Creates options that add nothing.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Enables every legacy entry, for icons that must show up anywhere.
+    pub fn all() -> Self {
+        Self {
+            palette_8bit: true,
+            palette_4bit: true,
+            bitmap_entries: true,
+        }
+    }
+
+    /// Sets whether 256-color variants are added.
+    pub fn with_palette_8bit(mut self, enabled: bool) -> Self {
+        self.palette_8bit = enabled;
+        self
+    }
+
+    /// Sets whether 16-color variants are added.
+    pub fn with_palette_4bit(mut self, enabled: bool) -> Self {
+        self.palette_4bit = enabled;
+        self
+    }
+
+    /// Sets whether small sizes are stored as masked bitmaps.
+    pub fn with_bitmap_entries(mut self, enabled: bool) -> Self {
+        self.bitmap_entries = enabled;
+        self
+    }
+
+    /// Returns `true` if any legacy entry is enabled.
+    pub fn is_enabled(&self) -> bool {
+        self.palette_8bit || self.palette_4bit || self.bitmap_entries
+    }
+}
+
+/// One image of an ICO file.
+struct Entry {
+    width: u32,
+    height: u32,
+    /// Colors in the palette, or 0 for none or 256.
+    color_count: u8,
+    bit_count: u16,
+    data: Vec<u8>,
+}
+
+/// Encodes `icons` as an ICO file, with the legacy entries `legacy` asks
+/// for.
+///
+/// Every size is stored in 32 bits, as PNG unless `legacy` asks for
+/// bitmaps. Sizes above 255 pixels are recorded as 0 in the directory, as
+/// for 256; readers take the real size from the PNG.
+pub(crate) fn encode_ico(icons: &SysIconSet, legacy: &LegacyIcoOptions) -> Result<Vec<u8>> {
+    let mut entries = Vec::new();
+    let mut palettized = Vec::new();
+    for image in &icons.images {
+        let rgba = image.data.to_rgba8();
+        let (width, height) = rgba.dimensions();
+        if legacy.bitmap_entries && width < PNG_MIN_SIZE {
+            entries.push(bitmap_entry(&rgba, 32));
+        } else {
+            let mut png = Vec::new();
+            image
+                .data
+                .write_to(&mut Cursor::new(&mut png), ImageFormat::Png)
+                .map_err(|e| Error::Serialization(format!("encoding icon: {e}")))?;
+            entries.push(Entry {
+                width,
+                height,
+                color_count: 0,
+                bit_count: 32,
+                data: png,
+            });
+        }
+
+        if width <= LEGACY_MAX_SIZE {
+            if legacy.palette_8bit {
+                palettized.push(bitmap_entry(&rgba, 8));
+            }
+            if legacy.palette_4bit {
+                palettized.push(bitmap_entry(&rgba, 4));
+            }
+        }
+    }
+    // Readers that understand 32 bits find those first
+    entries.extend(palettized);
+    write_ico(&entries)
+}
+
+fn write_ico(entries: &[Entry]) -> Result<Vec<u8>> {
+    let count = u16::try_from(entries.len())
+        .map_err(|_| Error::Serialization("too many icon sizes".to_string()))?;
+    let mut out = Vec::new();
+    out.extend_from_slice(&0u16.to_le_bytes());
+    out.extend_from_slice(&1u16.to_le_bytes());
+    out.extend_from_slice(&count.to_le_bytes());
+    let mut offset = HEADER_LEN + ENTRY_LEN * entries.len();
+    for entry in entries {
+        // 0 stands for 256 and larger
+        out.push(u8::try_from(entry.width).unwrap_or(0));
+        out.push(u8::try_from(entry.height).unwrap_or(0));
+        out.push(entry.color_count);
+        out.push(0);
+        out.extend_from_slice(&1u16.to_le_bytes());
+        out.extend_from_slice(&entry.bit_count.to_le_bytes());
+        out.extend_from_slice(&(entry.data.len() as u32).to_le_bytes());
+        out.extend_from_slice(&(offset as u32).to_le_bytes());
+        offset += entry.data.len();
+    }
+    for entry in entries {
+        out.extend_from_slice(&entry.data);
+    }
+    Ok(out)
+}
+
+/// Encodes `image` as a bitmap entry with `bit_count` bits per pixel (32,
+/// 8 or 4), followed by its transparency mask.
+fn bitmap_entry(image: &RgbaImage, bit_count: u16) -> Entry {
+    let (width, height) = image.dimensions();
+    let palette: Vec<[u8; 3]> = match bit_count {
+        8 => palette_256(),
+        4 => PALETTE_16.to_vec(),
+        _ => Vec::new(),
+    };
+    let color_stride = row_stride(width, bit_count);
+    let mask_stride = row_stride(width, 1);
+
+    let mut colors = vec![0u8; color_stride * height as usize];
+    let mut mask = vec![0u8; mask_stride * height as usize];
+    // Bitmaps are stored bottom row first
+    for (row, y) in (0..height).rev().enumerate() {
+        let color_row = &mut colors[row * color_stride..(row + 1) * color_stride];
+        let mask_row = &mut mask[row * mask_stride..(row + 1) * mask_stride];
+        for x in 0..width {
+            let [r, g, b, a] = image.get_pixel(x, y).0;
+            let transparent = a < MASK_THRESHOLD;
+            if transparent {
+                mask_row[x as usize / 8] |= 0x80 >> (x % 8);
+            }
+            let x = x as usize;
+            match bit_count {
+                32 => color_row[x * 4..x * 4 + 4].copy_from_slice(&[b, g, r, a]),
+                8 if !transparent => color_row[x] = nearest(&palette, [r, g, b]),
+                4 if !transparent => {
+                    let index = nearest(&palette, [r, g, b]);
+                    color_row[x / 2] |= if x % 2 == 0 { index << 4 } else { index };
+                }
+                _ => {}
+            }
+        }
+    }
+
+    let mut data = Vec::new();
+    data.extend_from_slice(&BITMAP_HEADER_LEN.to_le_bytes());
+    data.extend_from_slice(&(width as i32).to_le_bytes());
+    // The height covers the color bitmap and the mask
+    data.extend_from_slice(&(height as i32 * 2).to_le_bytes());
+    data.extend_from_slice(&1u16.to_le_bytes());
+    data.extend_from_slice(&bit_count.to_le_bytes());
+    data.extend_from_slice(&0u32.to_le_bytes());
+    data.extend_from_slice(&((colors.len() + mask.len()) as u32).to_le_bytes());
+    data.extend_from_slice(&0i32.to_le_bytes());
+    data.extend_from_slice(&0i32.to_le_bytes());
+    data.extend_from_slice(&(palette.len() as u32).to_le_bytes());
+    data.extend_from_slice(&0u32.to_le_bytes());
+    for [r, g, b] in &palette {
+        data.extend_from_slice(&[*b, *g, *r, 0]);
+    }
+    data.extend_from_slice(&colors);
+    data.extend_from_slice(&mask);
+
+    Entry {
+        width,
+        height,
+        color_count: if bit_count == 4 { 16 } else { 0 },
+        bit_count,
+        data,
+    }
+}
+
+/// Returns the bytes per bitmap row, which are padded to 4 bytes.
+fn row_stride(width: u32, bit_count: u16) -> usize {
+    (width as usize * usize::from(bit_count)).div_ceil(32) * 4
+}
+
+/// Returns a fixed 256-color palette: a 6×6×6 color cube and 40 grays.
+fn palette_256() -> Vec<[u8; 3]> {
+    const LEVELS: [u8; 6] = [0, 51, 102, 153, 204, 255];
+    let mut palette = Vec::with_capacity(256);
+    for r in LEVELS {
+        for g in LEVELS {
+            for b in LEVELS {
+                palette.push([r, g, b]);
+            }
+        }
+    }
+    for step in 1..=40u32 {
+        let gray = (step * 255 / 41) as u8;
+        palette.push([gray, gray, gray]);
+    }
+    palette
+}
+
+/// Returns the index of the palette color closest to `color`.
+fn nearest(palette: &[[u8; 3]], color: [u8; 3]) -> u8 {
+    let distance = |entry: &[u8; 3]| -> u32 {
+        entry
+            .iter()
+            .zip(color)
+            .map(|(&a, b)| (i32::from(a) - i32::from(b)).pow(2) as u32)
+            .sum()
+    };
+    (0..palette.len())
+        .min_by_key(|&index| distance(&palette[index]))
+        .unwrap_or(0) as u8
+}
+
+/// Decodes an ICO file of PNG entries, as written without legacy entries.
+pub(crate) fn decode_png_ico(bytes: &[u8]) -> Result<SysIconSet> {
+    let invalid = || Error::Serialization("invalid icon file".to_string());
+    let u16_at = |offset: usize| -> Result<u16> {
+        let field = bytes.get(offset..offset + 2).ok_or_else(invalid)?;
+        Ok(u16::from_le_bytes([field[0], field[1]]))
+    };
+    let u32_at = |offset: usize| -> Result<u32> {
+        let field = bytes.get(offset..offset + 4).ok_or_else(invalid)?;
+        Ok(u32::from_le_bytes([field[0], field[1], field[2], field[3]]))
+    };
+    if u16_at(0)? != 0 || u16_at(2)? != 1 {
+        return Err(invalid());
+    }
+
+    let count = usize::from(u16_at(4)?);
+    let mut images = Vec::with_capacity(count);
+    for index in 0..count {
+        let entry = HEADER_LEN + ENTRY_LEN * index;
+        let len = u32_at(entry + 8)? as usize;
+        let start = u32_at(entry + 12)? as usize;
+        let png = bytes.get(start..start + len).ok_or_else(invalid)?;
+        let data = image::load_from_memory_with_format(png, ImageFormat::Png)
+            .map_err(|e| Error::Serialization(format!("decoding icon: {e}")))?;
+        images.push(icon_sys::IconImage { data });
+    }
+    Ok(SysIconSet { images })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use image::{DynamicImage, Rgba};
+
+    fn icon_set(sizes: &[u32]) -> SysIconSet {
+        SysIconSet {
+            images: sizes
+                .iter()
+                .map(|&size| {
+                    let mut image = RgbaImage::from_pixel(size, size, Rgba([200, 30, 30, 255]));
+                    image.put_pixel(0, 0, Rgba([0, 0, 0, 0]));
+                    icon_sys::IconImage {
+                        data: DynamicImage::ImageRgba8(image),
+                    }
+                })
+                .collect(),
+        }
+    }
+
+    /// Returns `(width, color count, bit count)` of each directory entry.
+    fn directory(ico: &[u8]) -> Vec<(u8, u8, u16)> {
+        let count = u16::from_le_bytes([ico[4], ico[5]]) as usize;
+        (0..count)
+            .map(|index| {
+                let entry = &ico[HEADER_LEN + ENTRY_LEN * index..];
+                (entry[0], entry[2], u16::from_le_bytes([entry[6], entry[7]]))
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_png_round_trip() {
+        let icons = icon_set(&[16, 32, 256]);
+        let ico = encode_ico(&icons, &LegacyIcoOptions::new()).unwrap();
+        let decoded = decode_png_ico(&ico).unwrap();
+        assert_eq!(decoded.images.len(), 3);
+        for (original, decoded) in icons.images.iter().zip(&decoded.images) {
+            assert_eq!(original.data.to_rgba8(), decoded.data.to_rgba8());
+        }
+        assert!(decode_png_ico(b"not an icon").is_err());
+    }
+
+    #[test]
+    fn test_legacy_entries() {
+        let icons = icon_set(&[16, 48, 256]);
+        let ico = encode_ico(&icons, &LegacyIcoOptions::all()).unwrap();
+        assert_eq!(
+            directory(&ico),
+            vec![
+                (16, 0, 32),
+                (48, 0, 32),
+                (0, 0, 32),
+                (16, 0, 8),
+                (16, 16, 4),
+                (48, 0, 8),
+                (48, 16, 4),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_bitmap_entry_layout() {
+        let icons = icon_set(&[16]);
+        let entry = bitmap_entry(&icons.images[0].data.to_rgba8(), 4);
+        // Header, 16 colors, 16 rows of 8 color bytes and of 4 mask bytes
+        assert_eq!(entry.data.len(), 40 + 16 * 4 + 16 * 8 + 16 * 4);
+        assert_eq!(
+            i32::from_le_bytes(entry.data[8..12].try_into().unwrap()),
+            32
+        );
+
+        // The transparent top-left pixel is the first bit of the last mask row
+        let mask = &entry.data[entry.data.len() - 16 * 4..];
+        assert_eq!(mask[15 * 4], 0x80);
+        assert_eq!(mask[0], 0);
+    }
+
+    #[test]
+    fn test_nearest_palette_color() {
+        assert_eq!(nearest(&PALETTE_16, [250, 5, 5]), 9);
+        let palette = palette_256();
+        assert_eq!(palette.len(), 256);
+        assert_eq!(
+            palette[nearest(&palette, [128, 128, 128]) as usize],
+            [130, 130, 130]
+        );
+    }
+}
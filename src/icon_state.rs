@@ -0,0 +1,88 @@
+//! What a folder's icon currently is, for badges and apply buttons.
+//!
+//! [`folder_icon_state`](crate::CustomizationContext::folder_icon_state)
+//! combines the platform's own records of a custom icon, such as
+//! `desktop.ini` on Windows, `Icon\r` on macOS or `.directory` on Linux,
+//! with folco's profile store. A GUI can then show which folders folco
+//! customized and offer "update" rather than "apply" for them.
+
+use crate::store::StoredProfile;
+
+use serde::{Deserialize, Serialize};
+
+/// Whether a folder shows the default icon, one folco applied, or one set
+/// by something else.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(
+    tag = "kind",
+    rename_all = "kebab-case",
+    rename_all_fields = "camelCase"
+)]
+pub enum FolderIconState {
+    /// The folder shows the default icon.
+    Default,
+    /// The folder shows an icon folco applied.
+    CustomizedByFolco {
+        /// Hash of the applied profile, see [`profile_hash`](crate::profile_hash).
+        profile_hash: String,
+        /// When the profile was applied, in seconds since the Unix epoch.
+        applied_at: u64,
+    },
+    /// The folder has a custom icon that folco didn't apply.
+    CustomizedExternally,
+}
+
+impl FolderIconState {
+    /// Returns the state of a folder with the record `stored`, given
+    /// whether the platform reports a custom icon.
+    ///
+    /// A record without a custom icon is stale, since the icon was removed
+    /// behind folco's back, so the folder counts as default.
+    pub(crate) fn from_parts(stored: Option<&StoredProfile>, has_custom_icon: bool) -> Self {
+        match (stored, has_custom_icon) {
+            (_, false) => FolderIconState::Default,
+            (Some(stored), true) => FolderIconState::CustomizedByFolco {
+                profile_hash: stored.profile_hash.clone(),
+                applied_at: stored.applied_at,
+            },
+            (None, true) => FolderIconState::CustomizedExternally,
+        }
+    }
+
+    /// Returns `true` unless the folder shows the default icon.
+    pub fn is_customized(&self) -> bool {
+        !matches!(self, FolderIconState::Default)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use folco_renderer::CustomizationProfile;
+
+    #[test]
+    fn test_state_from_store_and_platform() {
+        let stored = StoredProfile::new(CustomizationProfile::default());
+        let state = FolderIconState::from_parts(Some(&stored), true);
+        assert_eq!(
+            state,
+            FolderIconState::CustomizedByFolco {
+                profile_hash: stored.profile_hash.clone(),
+                applied_at: stored.applied_at,
+            }
+        );
+        assert_eq!(
+            FolderIconState::from_parts(None, true),
+            FolderIconState::CustomizedExternally
+        );
+        assert_eq!(
+            FolderIconState::from_parts(Some(&stored), false),
+            FolderIconState::Default
+        );
+        assert!(!FolderIconState::from_parts(None, false).is_customized());
+
+        let json = serde_json::to_value(&state).unwrap();
+        assert_eq!(json["kind"], "customized-by-folco");
+        assert!(json["profileHash"].is_string());
+    }
+}
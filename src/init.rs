@@ -0,0 +1,124 @@
+//! First-run setup of the app data directory.
+//!
+//! [`CustomizationContext::initialize_app_data`](crate::CustomizationContext::initialize_app_data)
+//! creates folco's directories and, on the first run, writes a default
+//! config and seeds it with a few starter presets and decal glyphs, so a
+//! new user doesn't start from an empty gallery. On every run it records
+//! the running version, which tells frontends when to show what's new.
+
+use crate::color::FolderColor;
+use crate::error::{Error, Result};
+use crate::profile::{profile_with_color, profile_with_decal};
+
+use folco_renderer::{CustomizationProfile, DecalSettings};
+use serde::{Deserialize, Serialize};
+
+use std::path::PathBuf;
+
+/// Version of folco-core recorded in the config.
+pub(crate) const APP_VERSION: &str = env!("CARGO_PKG_VERSION");
+
+/// What [`initialize_app_data`](crate::CustomizationContext::initialize_app_data)
+/// created.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct InitReport {
+    /// Directories that didn't exist before.
+    pub created_dirs: Vec<PathBuf>,
+    /// Whether a default config was written, which happens on the first run
+    /// only.
+    pub wrote_config: bool,
+    /// Names of the starter presets added.
+    pub seeded_presets: Vec<String>,
+    /// Glyphs added to the config's decal picker.
+    pub seeded_glyphs: Vec<String>,
+    /// Version that last ran with this data, or `None` on the first run or
+    /// if the data predates version tracking.
+    pub previous_version: Option<String>,
+    /// Version now recorded.
+    pub version: String,
+}
+
+impl InitReport {
+    /// Returns `true` if this was the first run with this app data.
+    pub fn is_first_run(&self) -> bool {
+        self.wrote_config
+    }
+
+    /// Returns `true` if a different version ran with this data before,
+    /// e.g. to show release notes after an update.
+    pub fn is_upgrade(&self) -> bool {
+        self.previous_version
+            .as_ref()
+            .is_some_and(|previous| *previous != self.version)
+    }
+}
+
+/// Bundled starter content.
+#[derive(Debug, Deserialize)]
+pub(crate) struct StarterContent {
+    presets: Vec<StarterPreset>,
+    pub(crate) glyphs: Vec<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct StarterPreset {
+    name: String,
+    color: FolderColor,
+    #[serde(default)]
+    decal: Option<DecalSettings>,
+}
+
+impl StarterContent {
+    /// Loads the bundled starter content.
+    ///
+    /// # Errors
+    ///
+    /// Fails if the bundled file can't be parsed, which means it and the
+    /// renderer's decal format have drifted apart.
+    pub(crate) fn bundled() -> Result<Self> {
+        serde_json::from_str(include_str!("../assets/starter.json"))
+            .map_err(|e| Error::Serialization(format!("starter content: {e}")))
+    }
+
+    /// Returns the starter presets as `(name, profile)`.
+    pub(crate) fn presets(&self) -> Result<Vec<(String, CustomizationProfile)>> {
+        self.presets
+            .iter()
+            .map(|preset| {
+                let profile = profile_with_color(&CustomizationProfile::default(), preset.color)?;
+                let profile = match &preset.decal {
+                    Some(decal) => profile_with_decal(&profile, Some(decal))?,
+                    None => profile,
+                };
+                Ok((preset.name.clone(), profile))
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_bundled_starter_content_parses() {
+        let content = StarterContent::bundled().unwrap();
+        assert!(!content.glyphs.is_empty());
+        let presets = content.presets().unwrap();
+        assert!(presets.iter().any(|(name, _)| name == "Work"));
+    }
+
+    #[test]
+    fn test_upgrade_detection() {
+        let mut report = InitReport {
+            version: "1.2.0".to_string(),
+            ..Default::default()
+        };
+        assert!(!report.is_upgrade());
+        report.previous_version = Some("1.1.0".to_string());
+        assert!(report.is_upgrade());
+        report.previous_version = Some("1.2.0".to_string());
+        assert!(!report.is_upgrade());
+    }
+}
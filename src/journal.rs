@@ -0,0 +1,361 @@
+//! Crash-safe persistence for keyed state.
+//!
+//! The profile store and preset library are maps from a key to a JSON
+//! value. Rewriting the whole map on every save means a crash or power cut
+//! at the wrong moment can leave nothing usable behind, so they're
+//! persisted through a [`Journal`] instead: a snapshot file in the usual
+//! JSON format, plus a write-ahead log next to it (`<snapshot>.wal`) that
+//! each save appends only the changed entries to. Records are grouped into
+//! batches ending with a commit marker and flushed to disk before the save
+//! returns; on open, the snapshot is loaded and every committed batch
+//! replayed over it, and a torn batch at the end of the log is discarded.
+//! Once the log grows past [`COMPACTION_THRESHOLD`] records, the next save
+//! writes a fresh snapshot atomically and starts a new log.
+
+use crate::error::{Error, Result};
+use crate::profile::fnv1a_64;
+use crate::store::write_atomic;
+
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use std::collections::BTreeMap;
+use std::fs::{self, OpenOptions};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+/// Number of log records after which a save compacts the log into a new
+/// snapshot.
+pub(crate) const COMPACTION_THRESHOLD: usize = 512;
+
+/// One line of the write-ahead log.
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(tag = "op", rename_all = "kebab-case")]
+enum Record {
+    /// The entry under `key` was added or changed.
+    Put { key: String, value: Value },
+    /// The entry under `key` was removed.
+    Delete { key: String },
+    /// Ends a batch; records are only applied once their batch is committed.
+    Commit,
+}
+
+/// A snapshot file and its write-ahead log.
+#[derive(Debug)]
+pub(crate) struct Journal {
+    snapshot: PathBuf,
+    log: PathBuf,
+    /// Hash of each persisted entry's serialized value, to find what changed.
+    persisted: BTreeMap<String, u64>,
+    /// Records in the log since the last compaction.
+    appended: usize,
+}
+
+impl Journal {
+    /// Opens the journal for `snapshot` and returns it with the persisted
+    /// entries.
+    ///
+    /// `parse_snapshot` extracts the entries from the snapshot's contents,
+    /// which keeps each caller's file format. Committed log batches are
+    /// replayed over them; an uncommitted or torn tail is cut off the log.
+    pub(crate) fn open<V: DeserializeOwned + Serialize>(
+        snapshot: PathBuf,
+        parse_snapshot: impl FnOnce(&str) -> Result<BTreeMap<String, V>>,
+    ) -> Result<(Self, BTreeMap<String, V>)> {
+        let mut entries = if snapshot.exists() {
+            parse_snapshot(&fs::read_to_string(&snapshot)?)?
+        } else {
+            BTreeMap::new()
+        };
+
+        let log = log_path(&snapshot);
+        let appended = if log.exists() {
+            replay(&log, &mut entries)?
+        } else {
+            0
+        };
+
+        let persisted = entry_hashes(&entries)?;
+        let journal = Self {
+            snapshot,
+            log,
+            persisted,
+            appended,
+        };
+        Ok((journal, entries))
+    }
+
+    /// Persists `entries`, appending whatever changed since the last save.
+    ///
+    /// Compacts instead once the log is long enough, with `to_snapshot`
+    /// producing the snapshot's contents.
+    pub(crate) fn save<V: Serialize>(
+        &mut self,
+        entries: &BTreeMap<String, V>,
+        to_snapshot: impl FnOnce() -> Result<String>,
+    ) -> Result<()> {
+        let diff = diff_entries(&self.persisted, entries)?;
+        if diff.is_empty() && self.snapshot.exists() {
+            return Ok(());
+        }
+
+        let mut records: Vec<Record> = diff
+            .puts
+            .into_iter()
+            .map(|(key, value)| Record::Put { key, value })
+            .chain(diff.deletes.into_iter().map(|key| Record::Delete { key }))
+            .collect();
+        if !self.snapshot.exists() || self.appended + records.len() >= COMPACTION_THRESHOLD {
+            self.write_snapshot(to_snapshot)?;
+        } else {
+            records.push(Record::Commit);
+            self.append(&records)?;
+        }
+        self.persisted = diff.hashes;
+        Ok(())
+    }
+
+    /// Writes a fresh snapshot and clears the log.
+    ///
+    /// The snapshot must already reflect everything in the log.
+    pub(crate) fn write_snapshot(
+        &mut self,
+        to_snapshot: impl FnOnce() -> Result<String>,
+    ) -> Result<()> {
+        write_atomic(&self.snapshot, &to_snapshot()?)?;
+        // A crash before this point replays the old log over the new
+        // snapshot, which changes nothing since it already contains it
+        if self.log.exists() {
+            fs::remove_file(&self.log)?;
+        }
+        self.appended = 0;
+        Ok(())
+    }
+
+    /// Returns the number of records in the log.
+    pub(crate) fn log_len(&self) -> usize {
+        self.appended
+    }
+
+    fn append(&mut self, records: &[Record]) -> Result<()> {
+        let mut batch = String::new();
+        for record in records {
+            let line =
+                serde_json::to_string(record).map_err(|e| Error::Serialization(e.to_string()))?;
+            batch.push_str(&line);
+            batch.push('\n');
+        }
+        let mut file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&self.log)?;
+        file.write_all(batch.as_bytes())?;
+        file.sync_data()?;
+        self.appended += records.len();
+        Ok(())
+    }
+}
+
+/// Returns the path of the log for `snapshot`.
+pub(crate) fn log_path(snapshot: &Path) -> PathBuf {
+    let mut name = snapshot.as_os_str().to_owned();
+    name.push(".wal");
+    PathBuf::from(name)
+}
+
+/// Applies the committed batches in `log` to `entries` and returns the
+/// number of records kept.
+///
+/// Anything after the last commit marker is a batch a crash interrupted;
+/// it's truncated away so later appends start on a clean line.
+fn replay<V: DeserializeOwned>(log: &Path, entries: &mut BTreeMap<String, V>) -> Result<usize> {
+    let content = fs::read(log)?;
+    let mut pending = Vec::new();
+    let mut committed_len = 0;
+    let mut kept = 0;
+    let mut offset = 0;
+
+    for line in content.split_inclusive(|&b| b == b'\n') {
+        offset += line.len();
+        if !line.ends_with(b"\n") {
+            break;
+        }
+        let Ok(record) = serde_json::from_slice::<Record>(line) else {
+            break;
+        };
+        match record {
+            Record::Commit => {
+                kept += pending.len() + 1;
+                for record in pending.drain(..) {
+                    apply(entries, record)?;
+                }
+                committed_len = offset;
+            }
+            record => pending.push(record),
+        }
+    }
+
+    if committed_len < content.len() {
+        OpenOptions::new()
+            .write(true)
+            .open(log)?
+            .set_len(committed_len as u64)?;
+    }
+    Ok(kept)
+}
+
+fn apply<V: DeserializeOwned>(entries: &mut BTreeMap<String, V>, record: Record) -> Result<()> {
+    match record {
+        Record::Put { key, value } => {
+            let entry =
+                serde_json::from_value(value).map_err(|e| Error::Serialization(e.to_string()))?;
+            entries.insert(key, entry);
+        }
+        Record::Delete { key } => {
+            entries.remove(&key);
+        }
+        Record::Commit => {}
+    }
+    Ok(())
+}
+
+/// The changes between the persisted entries and the current ones.
+#[derive(Debug, Default)]
+pub(crate) struct EntryDiff {
+    /// Entries added or changed, serialized.
+    pub(crate) puts: Vec<(String, Value)>,
+    /// Keys of removed entries.
+    pub(crate) deletes: Vec<String>,
+    /// Hashes of the current entries, to diff the next save against.
+    pub(crate) hashes: BTreeMap<String, u64>,
+}
+
+impl EntryDiff {
+    /// Returns `true` if nothing changed.
+    pub(crate) fn is_empty(&self) -> bool {
+        self.puts.is_empty() && self.deletes.is_empty()
+    }
+}
+
+/// Returns the hash of each entry's serialized value.
+pub(crate) fn entry_hashes<V: Serialize>(
+    entries: &BTreeMap<String, V>,
+) -> Result<BTreeMap<String, u64>> {
+    entries
+        .iter()
+        .map(|(key, entry)| Ok((key.clone(), hash_value(&to_value(entry)?))))
+        .collect()
+}
+
+/// Compares `entries` with the hashes of the persisted ones.
+pub(crate) fn diff_entries<V: Serialize>(
+    persisted: &BTreeMap<String, u64>,
+    entries: &BTreeMap<String, V>,
+) -> Result<EntryDiff> {
+    let mut diff = EntryDiff::default();
+    for (key, entry) in entries {
+        let value = to_value(entry)?;
+        let hash = hash_value(&value);
+        if persisted.get(key) != Some(&hash) {
+            diff.puts.push((key.clone(), value));
+        }
+        diff.hashes.insert(key.clone(), hash);
+    }
+    diff.deletes = persisted
+        .keys()
+        .filter(|key| !diff.hashes.contains_key(*key))
+        .cloned()
+        .collect();
+    Ok(diff)
+}
+
+fn to_value<V: Serialize>(entry: &V) -> Result<Value> {
+    serde_json::to_value(entry).map_err(|e| Error::Serialization(e.to_string()))
+}
+
+fn hash_value(value: &Value) -> u64 {
+    fnv1a_64(value.to_string().as_bytes())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    fn parse(content: &str) -> Result<BTreeMap<String, u32>> {
+        serde_json::from_str(content).map_err(|e| Error::Serialization(e.to_string()))
+    }
+
+    fn save(journal: &mut Journal, entries: &BTreeMap<String, u32>) {
+        journal
+            .save(entries, || Ok(serde_json::to_string(entries).unwrap()))
+            .unwrap();
+    }
+
+    #[test]
+    fn test_changes_are_appended_and_replayed() {
+        let temp = tempdir().unwrap();
+        let snapshot = temp.path().join("state.json");
+        let (mut journal, mut entries) = Journal::open(snapshot.clone(), parse).unwrap();
+
+        entries.insert("a".to_string(), 1);
+        save(&mut journal, &entries);
+        assert_eq!(journal.log_len(), 0);
+
+        entries.insert("b".to_string(), 2);
+        entries.remove("a");
+        save(&mut journal, &entries);
+        // put b, delete a, commit
+        assert_eq!(journal.log_len(), 3);
+        assert!(parse(&fs::read_to_string(&snapshot).unwrap())
+            .unwrap()
+            .contains_key("a"));
+
+        let (reopened, replayed) = Journal::open(snapshot, parse).unwrap();
+        assert_eq!(replayed, entries);
+        assert_eq!(reopened.log_len(), 3);
+    }
+
+    #[test]
+    fn test_torn_batch_is_discarded() {
+        let temp = tempdir().unwrap();
+        let snapshot = temp.path().join("state.json");
+        let (mut journal, mut entries) = Journal::open(snapshot.clone(), parse).unwrap();
+        entries.insert("a".to_string(), 1);
+        save(&mut journal, &entries);
+        entries.insert("a".to_string(), 2);
+        save(&mut journal, &entries);
+
+        // A crash mid-append leaves an uncommitted record and half a line
+        let log = log_path(&snapshot);
+        let mut file = OpenOptions::new().append(true).open(&log).unwrap();
+        file.write_all(b"{\"op\":\"put\",\"key\":\"a\",\"value\":3}\n{\"op\":\"co")
+            .unwrap();
+
+        let (_, replayed) = Journal::open(snapshot.clone(), parse).unwrap();
+        assert_eq!(replayed.get("a"), Some(&2));
+        assert!(fs::read_to_string(&log)
+            .unwrap()
+            .ends_with("{\"op\":\"commit\"}\n"));
+    }
+
+    #[test]
+    fn test_long_log_is_compacted() {
+        let temp = tempdir().unwrap();
+        let snapshot = temp.path().join("state.json");
+        let (mut journal, mut entries) = Journal::open(snapshot.clone(), parse).unwrap();
+        save(&mut journal, &entries);
+
+        for i in 0..COMPACTION_THRESHOLD as u32 {
+            entries.insert("counter".to_string(), i);
+            save(&mut journal, &entries);
+        }
+        assert!(journal.log_len() < COMPACTION_THRESHOLD);
+
+        let snapshotted = parse(&fs::read_to_string(&snapshot).unwrap()).unwrap();
+        assert!(snapshotted.contains_key("counter"));
+        let (_, replayed) = Journal::open(snapshot, parse).unwrap();
+        assert_eq!(replayed, entries);
+    }
+}
@@ -0,0 +1,625 @@
+//! Image decals stacked on top of rendered icons.
+//!
+//! The renderer draws a single decal per profile. A [`LayeredProfile`] adds
+//! any number of [`DecalLayer`]s, such as a category glyph plus a numbered
+//! corner badge, which folco-core composites onto every rendered size. Each
+//! layer is placed with a [`DecalLayout`], so it lands in the same spot
+//! relative to the folder at every size, and has its own z-order and
+//! opacity. Layers can be masked to the folder's silhouette, so a large
+//! badge never spills past the tab or the folder's rounded edges.
+
+use crate::blend::{BlendMode, BlendModeMetadata};
+use crate::effects::{apply_emboss, draw_shadow, LayerEffects};
+use crate::error::{Error, Result};
+use crate::layout::DecalLayout;
+
+use folco_renderer::CustomizationProfile;
+use icon_sys::IconSet as SysIconSet;
+use image::codecs::gif::GifDecoder;
+use image::codecs::png::PngDecoder;
+use image::codecs::webp::WebPDecoder;
+use image::imageops::{self, FilterType};
+use image::{AnimationDecoder, Frames, ImageFormat, ImageReader, Rgba, RgbaImage};
+use serde::{Deserialize, Serialize};
+
+use std::path::{Path, PathBuf};
+
+/// A raster image drawn on top of the rendered icon.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DecalLayer {
+    /// Image file to draw, in any format the `image` crate reads.
+    pub image: PathBuf,
+    /// Where the image goes and how big it is.
+    #[serde(default)]
+    pub layout: DecalLayout,
+    /// Opacity from 0 (invisible) to 1 (opaque).
+    #[serde(default = "default_opacity")]
+    pub opacity: f32,
+    /// How the image's colors combine with the icon below.
+    #[serde(default)]
+    pub blend_mode: BlendMode,
+    /// Stacking order: higher layers are drawn over lower ones. Layers with
+    /// equal z-indexes are drawn in list order.
+    #[serde(default)]
+    pub z_index: i32,
+    /// Shadow and emboss drawn with the image.
+    #[serde(default, skip_serializing_if = "LayerEffects::is_empty")]
+    pub effects: LayerEffects,
+    /// What the layer, and its shadow, are clipped to.
+    #[serde(default)]
+    pub mask: LayerMask,
+    /// How the image file is read.
+    #[serde(default, skip_serializing_if = "DecalImageOptions::is_default")]
+    pub image_options: DecalImageOptions,
+}
+
+fn default_opacity() -> f32 {
+    1.0
+}
+
+/// How a layer's image file is read.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct DecalImageOptions {
+    /// Frame of an animated GIF, PNG or WebP to draw, counting from 0.
+    ///
+    /// Animated images are rejected unless a frame is chosen, since an icon
+    /// can only show one. Still images only have frame 0.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub frame: Option<usize>,
+}
+
+impl DecalImageOptions {
+    /// Returns `true` if these are the default options.
+    pub fn is_default(&self) -> bool {
+        *self == Self::default()
+    }
+}
+
+/// What a layer is clipped to.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum LayerMask {
+    /// Only the icon's canvas: the layer may cover transparent areas around
+    /// the folder.
+    #[default]
+    None,
+    /// The folder's alpha silhouette at each size, taken from the icon
+    /// before any layers are drawn. Partly transparent edges fade the layer
+    /// out with them.
+    Silhouette,
+}
+
+impl DecalLayer {
+    /// Creates an opaque layer drawing `image` with `layout`.
+    pub fn new(image: impl Into<PathBuf>, layout: DecalLayout) -> Self {
+        Self {
+            image: image.into(),
+            layout,
+            opacity: 1.0,
+            blend_mode: BlendMode::Normal,
+            z_index: 0,
+            effects: LayerEffects::none(),
+            mask: LayerMask::None,
+            image_options: DecalImageOptions::default(),
+        }
+    }
+
+    /// Sets the opacity, from 0 to 1.
+    pub fn with_opacity(mut self, opacity: f32) -> Self {
+        self.opacity = opacity;
+        self
+    }
+
+    /// Sets the blend mode.
+    pub fn with_blend_mode(mut self, blend_mode: BlendMode) -> Self {
+        self.blend_mode = blend_mode;
+        self
+    }
+
+    /// Sets the z-index.
+    pub fn with_z_index(mut self, z_index: i32) -> Self {
+        self.z_index = z_index;
+        self
+    }
+
+    /// Sets the shadow and emboss effects.
+    pub fn with_effects(mut self, effects: LayerEffects) -> Self {
+        self.effects = effects;
+        self
+    }
+
+    /// Sets what the layer is clipped to.
+    pub fn with_mask(mut self, mask: LayerMask) -> Self {
+        self.mask = mask;
+        self
+    }
+
+    /// Draws `frame` of an animated image, counting from 0.
+    pub fn with_frame(mut self, frame: usize) -> Self {
+        self.image_options.frame = Some(frame);
+        self
+    }
+
+    /// Checks that the layer's settings are in range.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Error::Layer`] describing the first invalid setting.
+    pub fn validate(&self) -> Result<()> {
+        if !(0.0..=1.0).contains(&self.opacity) {
+            return Err(Error::Layer(format!(
+                "opacity {} of '{}' is outside 0–1",
+                self.opacity,
+                self.image.display()
+            )));
+        }
+        if !(self.layout.height_fraction > 0.0 && self.layout.height_fraction <= 1.0) {
+            return Err(Error::Layer(format!(
+                "height fraction {} of '{}' is outside 0–1",
+                self.layout.height_fraction,
+                self.image.display()
+            )));
+        }
+        Ok(())
+    }
+}
+
+/// A profile with image decals stacked on top.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct LayeredProfile {
+    /// The profile the renderer draws.
+    pub profile: CustomizationProfile,
+    /// Layers drawn over the rendered profile, including its own decal.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub layers: Vec<DecalLayer>,
+}
+
+impl LayeredProfile {
+    /// Creates a layered profile without any layers.
+    pub fn new(profile: CustomizationProfile) -> Self {
+        Self {
+            profile,
+            layers: Vec::new(),
+        }
+    }
+
+    /// Adds a layer.
+    pub fn with_layer(mut self, layer: DecalLayer) -> Self {
+        self.layers.push(layer);
+        self
+    }
+
+    /// Checks every layer and that each image can be read.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Error::Layer`] for an invalid layer or an animated image
+    /// without a valid frame chosen, or the error reading an image.
+    pub fn validate(&self) -> Result<()> {
+        for layer in &self.layers {
+            layer.validate()?;
+            load_decal_image(&layer.image, &layer.image_options)?;
+        }
+        Ok(())
+    }
+
+    /// Returns the layers in drawing order, bottom first.
+    pub fn ordered_layers(&self) -> Vec<&DecalLayer> {
+        ordered(&self.layers)
+    }
+}
+
+impl From<CustomizationProfile> for LayeredProfile {
+    fn from(profile: CustomizationProfile) -> Self {
+        Self::new(profile)
+    }
+}
+
+/// Returns `layers` sorted by z-index, keeping list order for ties.
+fn ordered(layers: &[DecalLayer]) -> Vec<&DecalLayer> {
+    let mut ordered: Vec<&DecalLayer> = layers.iter().collect();
+    ordered.sort_by_key(|layer| layer.z_index);
+    ordered
+}
+
+/// Draws `layers` onto every image of `icons`.
+///
+/// Each layer is placed against the content bounds of the icon it's drawn
+/// on, before any layers are added.
+pub(crate) fn composite_layers(icons: &SysIconSet, layers: &[DecalLayer]) -> Result<SysIconSet> {
+    let ordered = ordered(layers);
+    for layer in &ordered {
+        layer.validate()?;
+    }
+    let sources = ordered
+        .iter()
+        .map(|layer| load_decal_image(&layer.image, &layer.image_options))
+        .collect::<Result<Vec<RgbaImage>>>()?;
+
+    let images = icons
+        .images
+        .iter()
+        .map(|icon| {
+            let base = icon.data.to_rgba8();
+            let mut canvas = base.clone();
+            for (layer, source) in ordered.iter().zip(&sources) {
+                draw_layer(&mut canvas, &base, layer, source);
+            }
+            icon_sys::IconImage {
+                data: canvas.into(),
+            }
+        })
+        .collect();
+    Ok(SysIconSet { images })
+}
+
+/// Reads the image at `path`, picking a frame of an animated image as
+/// `options` say.
+///
+/// # Errors
+///
+/// Returns [`Error::Layer`] if the image is animated and no frame is
+/// chosen, or the chosen frame doesn't exist, or the error reading the
+/// image.
+pub(crate) fn load_decal_image(path: &Path, options: &DecalImageOptions) -> Result<RgbaImage> {
+    let Some(frames) = animation_frames(path)? else {
+        if let Some(frame) = options.frame.filter(|&frame| frame > 0) {
+            return Err(Error::Layer(format!(
+                "'{}' isn't animated, so it has no frame {frame}",
+                path.display()
+            )));
+        }
+        return Ok(image::open(path)?.to_rgba8());
+    };
+
+    // Only decode as far as the chosen frame, or far enough to tell a
+    // still image saved as an animation from a real one
+    let needed = options.frame.map_or(2, |frame| frame + 1);
+    let mut decoded = frames
+        .take(needed)
+        .map(|frame| Ok(frame?.into_buffer()))
+        .collect::<Result<Vec<RgbaImage>>>()?;
+    match options.frame {
+        None if decoded.len() > 1 => Err(Error::Layer(format!(
+            "'{}' is animated; choose which of its frames to draw",
+            path.display()
+        ))),
+        Some(frame) if frame >= decoded.len() => Err(Error::Layer(format!(
+            "'{}' has no frame {frame}, only {}",
+            path.display(),
+            decoded.len()
+        ))),
+        frame => {
+            let frame = frame.unwrap_or(0);
+            if decoded.is_empty() {
+                return Err(Error::Layer(format!("'{}' has no frames", path.display())));
+            }
+            Ok(decoded.swap_remove(frame))
+        }
+    }
+}
+
+/// Returns the frames of the image at `path` if it's an animated GIF, PNG
+/// or WebP.
+fn animation_frames(path: &Path) -> Result<Option<Frames<'static>>> {
+    let reader = ImageReader::open(path)?.with_guessed_format()?;
+    let format = reader.format();
+    let file = reader.into_inner();
+    let frames = match format {
+        Some(ImageFormat::Gif) => Some(GifDecoder::new(file)?.into_frames()),
+        Some(ImageFormat::Png) => {
+            let decoder = PngDecoder::new(file)?;
+            if decoder.is_apng()? {
+                Some(decoder.apng()?.into_frames())
+            } else {
+                None
+            }
+        }
+        Some(ImageFormat::WebP) => {
+            let decoder = WebPDecoder::new(file)?;
+            decoder.has_animation().then(|| decoder.into_frames())
+        }
+        _ => None,
+    };
+    Ok(frames)
+}
+
+/// Draws one layer onto `canvas`, placed relative to `base`.
+fn draw_layer(canvas: &mut RgbaImage, base: &RgbaImage, layer: &DecalLayer, source: &RgbaImage) {
+    if source.width() == 0 || source.height() == 0 || layer.opacity <= 0.0 {
+        return;
+    }
+    let aspect = source.width() as f32 / source.height() as f32;
+    let Some(rect) = layer.layout.place_on(base, aspect) else {
+        return;
+    };
+    let mut scaled = imageops::resize(source, rect.width, rect.height, FilterType::Lanczos3);
+    if let Some(emboss) = &layer.effects.emboss {
+        apply_emboss(&mut scaled, emboss);
+    }
+    let mask = match layer.mask {
+        LayerMask::None => None,
+        LayerMask::Silhouette => Some(base),
+    };
+    if let Some(shadow) = &layer.effects.shadow {
+        draw_shadow(canvas, &scaled, rect.x, rect.y, shadow, layer.opacity, mask);
+    }
+    blit(
+        canvas,
+        &scaled,
+        i64::from(rect.x),
+        i64::from(rect.y),
+        layer.opacity,
+        layer.blend_mode,
+        mask,
+    );
+}
+
+/// Composites `image` onto `canvas` with its top-left corner at `(x, y)`,
+/// clipping whatever falls outside the canvas.
+///
+/// With a `mask` the same size as the canvas, each pixel's opacity is also
+/// scaled by the mask's alpha there.
+pub(crate) fn blit(
+    canvas: &mut RgbaImage,
+    image: &RgbaImage,
+    x: i64,
+    y: i64,
+    opacity: f32,
+    blend_mode: BlendMode,
+    mask: Option<&RgbaImage>,
+) {
+    for (ix, iy, pixel) in image.enumerate_pixels() {
+        let (cx, cy) = (x + i64::from(ix), y + i64::from(iy));
+        if cx < 0 || cy < 0 || cx >= i64::from(canvas.width()) || cy >= i64::from(canvas.height()) {
+            continue;
+        }
+        let (cx, cy) = (cx as u32, cy as u32);
+        let coverage = match mask {
+            Some(mask) if cx < mask.width() && cy < mask.height() => {
+                f32::from(mask.get_pixel(cx, cy).0[3]) / 255.0
+            }
+            Some(_) => 0.0,
+            None => 1.0,
+        };
+        if coverage <= 0.0 {
+            continue;
+        }
+        let below = *canvas.get_pixel(cx, cy);
+        let top = blend(below, *pixel, blend_mode);
+        canvas.put_pixel(cx, cy, source_over(below, top, opacity * coverage));
+    }
+}
+
+/// Returns `top` with its colors blended onto `bottom` by `mode`, ready to
+/// be composited with [`source_over`].
+///
+/// Where `bottom` is transparent there's nothing to blend with, so `top`'s
+/// own colors are kept.
+fn blend(bottom: Rgba<u8>, top: Rgba<u8>, mode: BlendMode) -> Rgba<u8> {
+    if mode == BlendMode::Normal {
+        return top;
+    }
+    let bottom_alpha = f32::from(bottom.0[3]) / 255.0;
+    let channel = |i: usize| {
+        let (b, t) = (f32::from(bottom.0[i]) / 255.0, f32::from(top.0[i]) / 255.0);
+        let mixed = (1.0 - bottom_alpha) * t + bottom_alpha * mode.blend_channel(b, t);
+        (mixed * 255.0).round().clamp(0.0, 255.0) as u8
+    };
+    Rgba([channel(0), channel(1), channel(2), top.0[3]])
+}
+
+/// Composites `top` over `bottom` with `opacity`, in straight alpha.
+pub(crate) fn source_over(bottom: Rgba<u8>, top: Rgba<u8>, opacity: f32) -> Rgba<u8> {
+    let top_alpha = f32::from(top.0[3]) / 255.0 * opacity.clamp(0.0, 1.0);
+    let bottom_alpha = f32::from(bottom.0[3]) / 255.0;
+    let alpha = top_alpha + bottom_alpha * (1.0 - top_alpha);
+    if alpha <= 0.0 {
+        return Rgba([0, 0, 0, 0]);
+    }
+    let channel = |i: usize| {
+        let value = (f32::from(top.0[i]) * top_alpha
+            + f32::from(bottom.0[i]) * bottom_alpha * (1.0 - top_alpha))
+            / alpha;
+        value.round().clamp(0.0, 255.0) as u8
+    };
+    Rgba([
+        channel(0),
+        channel(1),
+        channel(2),
+        (alpha * 255.0).round() as u8,
+    ])
+}
+
+/// The range and default of a numeric layer control.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RangeMetadata {
+    /// Smallest allowed value.
+    pub min: f32,
+    /// Largest allowed value.
+    pub max: f32,
+    /// Suggested slider step.
+    pub step: f32,
+    /// Value of a new layer.
+    pub default: f32,
+}
+
+/// What a layer editor needs to build its controls, serialized for a
+/// frontend.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct LayerControls {
+    /// The [`DecalLayer::opacity`] control.
+    pub opacity: RangeMetadata,
+    /// The [`DecalLayout::height_fraction`] control.
+    pub height_fraction: RangeMetadata,
+    /// The available blend modes; the first is the default.
+    pub blend_modes: Vec<BlendModeMetadata>,
+}
+
+impl LayerControls {
+    /// Returns the controls for the current layer settings.
+    pub fn new() -> Self {
+        let layout = DecalLayout::default();
+        Self {
+            opacity: RangeMetadata {
+                min: 0.0,
+                max: 1.0,
+                step: 0.05,
+                default: default_opacity(),
+            },
+            height_fraction: RangeMetadata {
+                min: 0.05,
+                max: 1.0,
+                step: 0.05,
+                default: layout.height_fraction,
+            },
+            blend_modes: BlendMode::all_with_metadata(),
+        }
+    }
+
+    /// Serializes the controls to a JSON string.
+    pub fn to_json(&self) -> std::result::Result<String, serde_json::Error> {
+        serde_json::to_string(self)
+    }
+}
+
+impl Default for LayerControls {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::layout::DecalAnchor;
+
+    #[test]
+    fn test_source_over() {
+        let red = Rgba([255, 0, 0, 255]);
+        let blue = Rgba([0, 0, 255, 255]);
+        assert_eq!(source_over(red, blue, 1.0), blue);
+        assert_eq!(source_over(red, blue, 0.0), red);
+        assert_eq!(source_over(red, blue, 0.5), Rgba([128, 0, 128, 255]));
+        assert_eq!(source_over(Rgba([0, 0, 0, 0]), blue, 1.0), blue);
+    }
+
+    #[test]
+    fn test_multiply_keeps_white_transparent_to_the_folder() {
+        let folder = Rgba([40, 120, 200, 255]);
+        let white = Rgba([255, 255, 255, 255]);
+        let mixed = blend(folder, white, BlendMode::Multiply);
+        assert_eq!(source_over(folder, mixed, 1.0), folder);
+        assert_eq!(blend(Rgba([0, 0, 0, 0]), white, BlendMode::Multiply), white);
+    }
+
+    #[test]
+    fn test_layer_controls_defaults_match_new_layers() {
+        let controls = LayerControls::new();
+        let layer = DecalLayer::new("a.png", DecalLayout::default());
+        assert_eq!(controls.opacity.default, layer.opacity);
+        assert_eq!(controls.blend_modes[0].id, layer.blend_mode);
+        assert!(controls.to_json().unwrap().contains("\"blendModes\""));
+    }
+
+    #[test]
+    fn test_layers_sort_by_z_index_stably() {
+        let layered = LayeredProfile::new(CustomizationProfile::default())
+            .with_layer(DecalLayer::new("a.png", DecalLayout::default()).with_z_index(2))
+            .with_layer(DecalLayer::new("b.png", DecalLayout::default()))
+            .with_layer(DecalLayer::new("c.png", DecalLayout::default()));
+        let order: Vec<_> = layered
+            .ordered_layers()
+            .iter()
+            .map(|layer| layer.image.to_string_lossy().into_owned())
+            .collect();
+        assert_eq!(order, ["b.png", "c.png", "a.png"]);
+    }
+
+    #[test]
+    fn test_validate_rejects_out_of_range_opacity() {
+        let layer = DecalLayer::new("a.png", DecalLayout::default()).with_opacity(1.5);
+        assert!(matches!(layer.validate(), Err(Error::Layer(_))));
+    }
+
+    #[test]
+    fn test_composite_draws_layer_in_place() {
+        let temp = tempfile::tempdir().unwrap();
+        let badge = temp.path().join("badge.png");
+        RgbaImage::from_pixel(4, 4, Rgba([0, 255, 0, 255]))
+            .save(&badge)
+            .unwrap();
+
+        let icons = SysIconSet {
+            images: vec![icon_sys::IconImage {
+                data: RgbaImage::from_pixel(32, 32, Rgba([200, 200, 200, 255])).into(),
+            }],
+        };
+        let layout = DecalLayout::new(DecalAnchor::BottomRight)
+            .with_height_fraction(0.25)
+            .with_margin_fraction(0.0);
+        let layered = composite_layers(&icons, &[DecalLayer::new(&badge, layout)]).unwrap();
+        let image = layered.images[0].data.to_rgba8();
+        assert_eq!(*image.get_pixel(30, 30), Rgba([0, 255, 0, 255]));
+        assert_eq!(*image.get_pixel(2, 2), Rgba([200, 200, 200, 255]));
+    }
+
+    #[test]
+    fn test_animated_images_need_a_frame() {
+        use image::codecs::gif::GifEncoder;
+        use image::Frame;
+
+        let temp = tempfile::tempdir().unwrap();
+        let gif = temp.path().join("spinner.gif");
+        let frames = [Rgba([255, 0, 0, 255]), Rgba([0, 0, 255, 255])]
+            .map(|color| Frame::new(RgbaImage::from_pixel(4, 4, color)));
+        GifEncoder::new(std::fs::File::create(&gif).unwrap())
+            .encode_frames(frames)
+            .unwrap();
+
+        let options = DecalImageOptions::default();
+        assert!(matches!(
+            load_decal_image(&gif, &options),
+            Err(Error::Layer(_))
+        ));
+        let second = load_decal_image(&gif, &DecalImageOptions { frame: Some(1) }).unwrap();
+        let [r, _, b, _] = second.get_pixel(0, 0).0;
+        assert!(b > 200 && r < 50);
+        assert!(load_decal_image(&gif, &DecalImageOptions { frame: Some(2) }).is_err());
+
+        let still = temp.path().join("still.png");
+        RgbaImage::from_pixel(4, 4, Rgba([0, 255, 0, 255]))
+            .save(&still)
+            .unwrap();
+        assert!(load_decal_image(&still, &options).is_ok());
+        assert!(load_decal_image(&still, &DecalImageOptions { frame: Some(0) }).is_ok());
+        assert!(load_decal_image(&still, &DecalImageOptions { frame: Some(1) }).is_err());
+    }
+
+    #[test]
+    fn test_silhouette_mask_clips_to_opaque_pixels() {
+        let mut base = RgbaImage::new(8, 8);
+        for x in 0..4 {
+            base.put_pixel(x, 0, Rgba([200, 200, 200, 255]));
+        }
+        let mut canvas = base.clone();
+        let decal = RgbaImage::from_pixel(8, 1, Rgba([0, 255, 0, 255]));
+        blit(
+            &mut canvas,
+            &decal,
+            0,
+            0,
+            1.0,
+            BlendMode::Normal,
+            Some(&base),
+        );
+
+        assert_eq!(*canvas.get_pixel(1, 0), Rgba([0, 255, 0, 255]));
+        assert_eq!(*canvas.get_pixel(6, 0), Rgba([0, 0, 0, 0]));
+    }
+}
@@ -0,0 +1,280 @@
+//! Decal placement from semantic anchors.
+//!
+//! Folder icons don't fill their canvas, and how much of it they fill
+//! changes between sizes and platforms, so a decal placed in absolute pixels
+//! lands somewhere different on every size. A [`DecalLayout`] instead says
+//! what the placement means, such as "badge in the bottom right at 40% of
+//! the folder's height", and [`DecalLayout::place`] turns that into pixels
+//! against each size's content bounds, snapped to whole pixels and kept
+//! inside a safe area.
+
+use image::RgbaImage;
+use serde::{Deserialize, Serialize};
+
+/// Alpha at or above which a pixel counts as part of the icon's content.
+const CONTENT_ALPHA_THRESHOLD: u8 = 16;
+
+/// A rectangle in pixels.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct Rect {
+    /// Left edge.
+    pub x: u32,
+    /// Top edge.
+    pub y: u32,
+    /// Width.
+    pub width: u32,
+    /// Height.
+    pub height: u32,
+}
+
+impl Rect {
+    /// Creates a rectangle.
+    pub fn new(x: u32, y: u32, width: u32, height: u32) -> Self {
+        Self {
+            x,
+            y,
+            width,
+            height,
+        }
+    }
+
+    /// Returns the rectangle covering a whole `width` × `height` image.
+    pub fn full(width: u32, height: u32) -> Self {
+        Self::new(0, 0, width, height)
+    }
+
+    /// Right edge, exclusive.
+    pub fn right(&self) -> u32 {
+        self.x + self.width
+    }
+
+    /// Bottom edge, exclusive.
+    pub fn bottom(&self) -> u32 {
+        self.y + self.height
+    }
+
+    /// Returns `true` if the rectangle has no area.
+    pub fn is_empty(&self) -> bool {
+        self.width == 0 || self.height == 0
+    }
+
+    /// Returns `true` if `other` lies entirely inside this rectangle.
+    pub fn contains_rect(&self, other: &Rect) -> bool {
+        other.x >= self.x
+            && other.y >= self.y
+            && other.right() <= self.right()
+            && other.bottom() <= self.bottom()
+    }
+}
+
+/// Where a decal sits relative to the icon's content.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum DecalAnchor {
+    /// Centered on the content, like a category glyph.
+    #[default]
+    Center,
+    /// In the bottom-right corner, like a status badge.
+    BottomRight,
+    /// In the bottom-left corner.
+    BottomLeft,
+    /// In the top-left corner, like a tag.
+    TopLeft,
+    /// In the top-right corner.
+    TopRight,
+}
+
+impl DecalAnchor {
+    /// Returns the anchor's position as fractions of the free space
+    /// horizontally and vertically.
+    fn alignment(&self) -> (f32, f32) {
+        match self {
+            DecalAnchor::Center => (0.5, 0.5),
+            DecalAnchor::BottomRight => (1.0, 1.0),
+            DecalAnchor::BottomLeft => (0.0, 1.0),
+            DecalAnchor::TopLeft => (0.0, 0.0),
+            DecalAnchor::TopRight => (1.0, 0.0),
+        }
+    }
+}
+
+/// A size-independent description of where a decal goes and how big it is.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct DecalLayout {
+    /// Where the decal sits.
+    pub anchor: DecalAnchor,
+    /// Decal height as a fraction of the content height, from 0 to 1.
+    pub height_fraction: f32,
+    /// Gap kept between the decal and the content edges, as a fraction of
+    /// the content height.
+    pub margin_fraction: f32,
+    /// Smallest decal height in pixels. Sizes too small to fit it keep the
+    /// decal at the largest height that does fit.
+    pub min_height: u32,
+}
+
+impl Default for DecalLayout {
+    fn default() -> Self {
+        Self {
+            anchor: DecalAnchor::Center,
+            height_fraction: 0.5,
+            margin_fraction: 0.05,
+            min_height: 6,
+        }
+    }
+}
+
+impl DecalLayout {
+    /// Creates a layout at `anchor` with the default size and margin.
+    pub fn new(anchor: DecalAnchor) -> Self {
+        Self {
+            anchor,
+            ..Self::default()
+        }
+    }
+
+    /// A corner badge: bottom right at 40% of the content height.
+    pub fn badge() -> Self {
+        Self::new(DecalAnchor::BottomRight).with_height_fraction(0.4)
+    }
+
+    /// A tag: top left at 30% of the content height.
+    pub fn tag() -> Self {
+        Self::new(DecalAnchor::TopLeft).with_height_fraction(0.3)
+    }
+
+    /// Sets the decal height as a fraction of the content height.
+    pub fn with_height_fraction(mut self, fraction: f32) -> Self {
+        self.height_fraction = fraction.clamp(0.0, 1.0);
+        self
+    }
+
+    /// Sets the margin as a fraction of the content height.
+    pub fn with_margin_fraction(mut self, fraction: f32) -> Self {
+        self.margin_fraction = fraction.clamp(0.0, 0.5);
+        self
+    }
+
+    /// Sets the smallest decal height in pixels.
+    pub fn with_min_height(mut self, pixels: u32) -> Self {
+        self.min_height = pixels;
+        self
+    }
+
+    /// Places a decal with the given width-to-height `aspect` ratio within
+    /// `content`.
+    ///
+    /// The result is snapped to whole pixels and always lies inside the
+    /// content bounds shrunk by the margin, shrinking the decal if the
+    /// requested size doesn't fit. Returns `None` if no area is left, such
+    /// as for degenerate content bounds.
+    pub fn place(&self, content: Rect, aspect: f32) -> Option<Rect> {
+        let aspect = if aspect.is_finite() && aspect > 0.0 {
+            aspect
+        } else {
+            1.0
+        };
+        let margin = (content.height as f32 * self.margin_fraction).round() as u32;
+        let safe = Rect::new(
+            content.x + margin,
+            content.y + margin,
+            content.width.saturating_sub(2 * margin),
+            content.height.saturating_sub(2 * margin),
+        );
+        if safe.is_empty() {
+            return None;
+        }
+
+        let requested = (content.height as f32 * self.height_fraction).round() as u32;
+        let mut height = requested.max(self.min_height).min(safe.height);
+        let mut width = (height as f32 * aspect).round() as u32;
+        if width > safe.width {
+            width = safe.width;
+            height = ((width as f32 / aspect).round() as u32).clamp(1, safe.height);
+        }
+        if width == 0 || height == 0 {
+            return None;
+        }
+
+        let (ax, ay) = self.anchor.alignment();
+        let x = safe.x + ((safe.width - width) as f32 * ax).round() as u32;
+        let y = safe.y + ((safe.height - height) as f32 * ay).round() as u32;
+        Some(Rect::new(x, y, width, height))
+    }
+
+    /// Places a decal on `icon`, relative to its [`content_bounds`].
+    pub fn place_on(&self, icon: &RgbaImage, aspect: f32) -> Option<Rect> {
+        self.place(content_bounds(icon)?, aspect)
+    }
+}
+
+/// Returns the bounding box of an image's visible pixels, or `None` if it's
+/// fully transparent.
+pub fn content_bounds(image: &RgbaImage) -> Option<Rect> {
+    let (mut left, mut top) = (u32::MAX, u32::MAX);
+    let (mut right, mut bottom) = (0u32, 0u32);
+    for (x, y, pixel) in image.enumerate_pixels() {
+        if pixel.0[3] >= CONTENT_ALPHA_THRESHOLD {
+            left = left.min(x);
+            top = top.min(y);
+            right = right.max(x + 1);
+            bottom = bottom.max(y + 1);
+        }
+    }
+    (left < right).then(|| Rect::new(left, top, right - left, bottom - top))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use image::Rgba;
+
+    #[test]
+    fn test_content_bounds() {
+        let mut image = RgbaImage::new(16, 16);
+        assert_eq!(content_bounds(&image), None);
+        image.put_pixel(2, 3, Rgba([0, 0, 0, 255]));
+        image.put_pixel(10, 12, Rgba([0, 0, 0, 255]));
+        image.put_pixel(14, 14, Rgba([0, 0, 0, 4]));
+        assert_eq!(content_bounds(&image), Some(Rect::new(2, 3, 9, 10)));
+    }
+
+    #[test]
+    fn test_badge_sits_bottom_right_inside_margin() {
+        let content = Rect::new(0, 20, 200, 160);
+        let placed = DecalLayout::badge().place(content, 1.0).unwrap();
+        assert_eq!(placed.height, 64);
+        assert_eq!(placed.right(), 200 - 8);
+        assert_eq!(placed.bottom(), 180 - 8);
+    }
+
+    #[test]
+    fn test_placement_stays_in_bounds_at_every_size() {
+        for anchor in [
+            DecalAnchor::Center,
+            DecalAnchor::BottomRight,
+            DecalAnchor::BottomLeft,
+            DecalAnchor::TopLeft,
+            DecalAnchor::TopRight,
+        ] {
+            for size in [16u32, 24, 32, 48, 256] {
+                let content = Rect::new(size / 16, size / 8, size - size / 8, size - size / 4);
+                let layout = DecalLayout::new(anchor).with_height_fraction(0.9);
+                let placed = layout.place(content, 3.0).unwrap();
+                assert!(
+                    content.contains_rect(&placed),
+                    "{anchor:?} {size}: {placed:?}"
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn test_degenerate_content() {
+        assert_eq!(
+            DecalLayout::default().place(Rect::new(0, 0, 0, 0), 1.0),
+            None
+        );
+    }
+}
@@ -7,11 +7,21 @@
 //! # Features
 //!
 //! - **CustomizationContext**: Main entry point for all icon customization operations
-//! - **Folder customization**: Apply custom icons to directories
+//! - **Folder customization**: Apply custom icons to directories, in batches, async or in parallel
 //! - **Reset to default**: Restore system default folder icons
+//! - **Batch outcomes**: Per-folder results, warnings and exit codes of every batch
+//! - **Profile store**: Remember which folders folco customized, and how, alongside saved presets
+//! - **Rendering**: Preview profiles without applying them, even without a context
 //! - **Icon caching**: Cache system resources in app data directory
 //! - **Type conversion**: Convert between `icon-sys` and `folco-renderer` icon types
 //!
+//! Optional Cargo features add command-line argument types (`clap`), JSON
+//! schemas (`jsonschema`), desktop notifications (`notifications`),
+//! seasonal presets (`seasonal`), an in-memory folder backend and test
+//! sandbox (`simulated`), SQLite state storage (`storage-sqlite`), a
+//! `tracing` bridge for the core log (`tracing`) and folder watching
+//! (`watch`).
+//!
 //! # Example
 //!
 //! ```ignore
@@ -31,18 +41,228 @@
 //! ctx.reset_folders(&folders)?;
 //! ```
 
+mod about;
+mod accent;
+mod apply;
+mod artifacts;
+#[cfg(feature = "watch")]
+mod autoapply;
+mod backend;
+mod batch;
+mod benchmark;
+mod blend;
+mod bookmarks;
+mod branding;
 mod cache;
+mod capabilities;
+mod case_audit;
+mod checkpoint;
 pub mod color;
+mod color_match;
+mod conditions;
+mod config;
+mod confirm;
+mod conflict;
 mod context;
 mod convert;
+mod curve;
+mod diff;
+mod effects;
+mod emoji;
 mod error;
+mod estimate;
+mod extract;
+mod file_id;
+pub mod fixtures;
+mod hooks;
+mod ico;
+mod icon_state;
+mod init;
+mod journal;
+mod layers;
+mod layout;
+mod library;
+mod limits;
+mod log;
+mod manifest;
+mod migrate;
+#[cfg(feature = "notifications")]
+mod notifications;
+mod oklch;
+mod palettes;
+mod parallel;
+mod path_display;
+mod paths;
+mod policy;
+mod pe_icons;
+mod presets;
+mod preview;
+mod privileged;
+mod profile;
 pub mod progress;
+mod queue;
+mod random;
+mod reconcile;
+mod retry;
+mod rules;
+mod ruleset;
+#[cfg(feature = "simulated")]
+pub mod sandbox;
+mod sanitize;
+mod scan;
+#[cfg(feature = "seasonal")]
+mod seasonal;
+mod search;
+mod selection;
+mod sharpen;
+#[cfg(feature = "simulated")]
+mod simulated;
+mod sized;
+#[cfg(feature = "storage-sqlite")]
+mod sqlite;
+mod state;
+mod stats;
+mod store;
+mod support;
+mod sync;
+mod synthesize;
 mod sys;
+mod telemetry;
+mod throttle;
+mod thumbnails;
+mod timeout;
+mod uninstall;
+mod wallpaper;
+mod warning;
+#[cfg(feature = "watch")]
+mod watcher;
+mod wsl;
 
-pub use cache::{CacheConfig, IconCache};
+pub use about::{about, AboutInfo, OsInfo, SystemTheme};
+pub use accent::{refresh_system_accent, system_accent_color, DEFAULT_ACCENT};
+pub use apply::{ApplyOptions, MacosApplyOptions, RefreshMode, WindowsApplyOptions};
+#[cfg(feature = "watch")]
+pub use autoapply::{AutoApply, AutoApplyEvent, DEFAULT_ACCENT_INTERVAL, DEFAULT_DEBOUNCE};
+pub use backend::{DefaultIconBackend, FolderIconBackend, PlatformFolderBackend};
+pub use batch::{
+    BatchOutcome, BatchResult, ExitCodePolicy, FolderResult, EXIT_CANCELLED, EXIT_FAILURE,
+    EXIT_PARTIAL, EXIT_PERMISSION, EXIT_SUCCESS,
+};
+pub use benchmark::{
+    benchmark_pipeline, BenchmarkOptions, PipelineBenchmark, SizeTiming, StageTiming,
+};
+pub use blend::{BlendMode, BlendModeMetadata};
+pub use bookmarks::{resolve_bookmark, ResolvedBookmark};
+pub use branding::{Branding, ColorRange};
+pub use cache::{CacheConfig, CacheInfo, IconCache};
+pub use capabilities::{capabilities, Capabilities, Support};
+pub use case_audit::{
+    audit_store_case, on_disk_spelling, CaseAuditReport, CaseDuplicate, CaseMismatch,
+};
+pub use checkpoint::{ProgressSnapshot, DEFAULT_CHECKPOINT_INTERVAL};
+pub use color_match::{ColorMatch, MATCH_TOLERANCE};
+pub use conditions::{GitState, RuleCondition};
+pub use config::AppConfig;
+pub use confirm::{AutoConfirm, ConfirmationHandler, RiskyOperation, DEFAULT_CONFIRM_THRESHOLD};
+pub use conflict::{
+    ConflictCallback, ConflictKind, ConflictPolicy, ConflictResolution, FolderConflict,
+    SkippedFolder,
+};
 pub use context::{AppInfo, CustomizationContext, CustomizationContextBuilder};
 pub use convert::convert_icon_set;
+pub use curve::LightnessCurve;
+pub use diff::{diff_icon_sets, IconDiff, SizeDiff};
+pub use effects::{EmbossEffect, LayerEffects, ShadowEffect};
+pub use emoji::{normalize_emoji, profile_with_emoji};
 pub use error::{Error, Result};
+pub use estimate::{BatchEstimate, DEFAULT_APPLY_TIME, DEFAULT_RENDER_TIME};
+pub use extract::{candidate_sources, ExtractionAttempt, ExtractionReport, IconSource};
+pub use file_id::FileId;
+pub use hooks::{CommandHook, Hook, DEFAULT_COMMAND_HOOK_TIMEOUT_MS};
+pub use icon_state::FolderIconState;
+pub use ico::LegacyIcoOptions;
+pub use init::InitReport;
+pub use layers::{
+    DecalImageOptions, DecalLayer, LayerControls, LayerMask, LayeredProfile, RangeMetadata,
+};
+pub use layout::{content_bounds, DecalAnchor, DecalLayout, Rect};
+pub use library::{Library, LibraryRoot};
+pub use limits::{
+    LimitExceeded, PayloadLimit, PayloadLimits, DEFAULT_MAX_DECALS, DEFAULT_MAX_DECAL_BYTES,
+    DEFAULT_MAX_RENDER_DIMENSION,
+};
+pub use log::{
+    CoreLog, LogConfig, LogEntry, LogLevel, DEFAULT_MAX_LOG_BYTES, DEFAULT_MAX_LOG_FILES,
+    LOG_FILE_NAME,
+};
+#[cfg(feature = "tracing")]
+pub use log::CoreLogLayer;
+pub use manifest::{export_store_csv, parse_manifest_csv, ManifestEntry};
+#[cfg(feature = "notifications")]
+pub use notifications::{notify_batch, notify_desktop, BatchNotification};
+pub use palettes::{read_palette, PaletteColor, PaletteFormat, PaletteImport, UserPalette};
+pub use path_display::{truncate_middle, PathDisplay, DEFAULT_PATH_WIDTH};
+pub use paths::{normalize_folder_path, normalize_folders, MergedDuplicate, NormalizedFolders};
+pub use policy::Policy;
+pub use presets::{Preset, PresetLibrary, PresetQuery, PresetSort};
+pub use preview::{render_with_base, PreviewContext};
+pub use privileged::{
+    serve_helper, DirectExecutor, HelperProcess, HelperRequest, HelperResponse, PrivilegedExecutor,
+    HELPER_PROTOCOL_VERSION,
+};
+pub use profile::{merge_profiles, profile_hash, profile_with_color, profile_with_target};
+pub use queue::{Priority, WorkGuard, WorkQueue};
+pub use random::{RandomConstraints, RandomProfile};
+pub use reconcile::{
+    reconcile_store, ReconcileOptions, ReconcileReport, RemappedFolder, TrashedFolder,
+};
+pub use retry::RetryPolicy;
+pub use rules::{Rule, RuleConflict, RuleEvaluation, RuleMatch, RuleMode, RuleSet};
+pub use ruleset::{
+    export_ruleset, read_ruleset, RuleImportReport, RULESET_FILE_NAME, RULESET_FORMAT_VERSION,
+};
+#[cfg(feature = "seasonal")]
+pub use seasonal::{
+    current_seasonal_presets, seasonal_packs, MonthDay, SeasonalPack, SeasonalPreset,
+    SeasonalWindow,
+};
+pub use sanitize::{
+    sanitize_profile_svgs, sanitize_svg, SanitizeRemoval, SanitizeReport, MAX_SVG_BYTES,
+    MAX_SVG_DIMENSION, MAX_SVG_ELEMENTS,
+};
+pub use scan::{CustomizedFolder, ScanOptions};
+pub use search::{search_customized, SearchPage, SearchQuery, SearchResult, DEFAULT_PAGE_SIZE};
+pub use selection::{
+    resolve_folder_selection, FolderSelection, FolderSelector, RejectReason, SelectionReject,
+    SelectorEntry,
+};
+pub use sharpen::SharpenOptions;
+#[cfg(feature = "simulated")]
+pub use simulated::SimulatedBackend;
+pub use sized::{SizeOverride, SizedProfile};
+#[cfg(feature = "storage-sqlite")]
+pub use sqlite::{SqliteStateStore, SQLITE_FILE_NAME};
+pub use state::{JsonStateStore, MemoryStateStore, StateCollection, StateStore};
+pub use stats::{
+    ColorCount, LibraryStats, OperationStats, PresetCount, RecentFolder, RootCount,
+    RECENT_FOLDER_COUNT,
+};
+pub use store::{HistoryEntry, ProfileStore, StoredProfile, FOLDER_HISTORY_LIMIT};
+pub use support::{SupportChecks, SUPPORT_LOG_ENTRIES};
+pub use sync::{
+    machine_name, read_synced_state, write_synced_state, SyncConflict, SyncEntry, SyncItem,
+    SyncReport, SyncState, SYNC_FILE_EXTENSION, SYNC_FORMAT_VERSION,
+};
+pub use telemetry::{OperationKind, OperationMetrics, TelemetrySink};
+pub use throttle::ThrottleConfig;
+pub use uninstall::{UninstallOptions, UninstallReport};
+pub use wallpaper::{
+    suggest_palette_from_image, suggest_palette_from_wallpaper, wallpaper_path, WallpaperPalette,
+};
+pub use warning::Warning;
+#[cfg(feature = "watch")]
+pub use watcher::{FolderWatcher, WatchEvent};
+pub use wsl::{classify_path, running_in_wsl, to_windows_path, ApplyCapability, PathLocation};
 
 // Re-export key types from folco-renderer for convenience
 // This allows consumers to use profiles without importing the renderer crate directly
@@ -0,0 +1,175 @@
+//! User-registered library roots.
+//!
+//! A [`Library`] is the set of top-level directories the user organizes
+//! their folders under, such as "Projects", "Clients" and "Archive". Scanning,
+//! watching, reconciliation and reporting operate over these roots. The
+//! library is persisted in the app config (see [`AppConfig`](crate::AppConfig)).
+
+use crate::error::{Error, Result};
+use crate::paths::normalize_folder_path;
+use crate::store::now_unix_secs;
+
+use serde::{Deserialize, Serialize};
+
+use std::path::{Path, PathBuf};
+
+/// A named directory registered with the library.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct LibraryRoot {
+    /// Display name, unique within the library.
+    pub name: String,
+    /// Normalized path of the root directory.
+    pub path: PathBuf,
+    /// When the root was added, in seconds since the Unix epoch.
+    pub added_at: u64,
+}
+
+/// The user's library roots, in the order they were added.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct Library {
+    roots: Vec<LibraryRoot>,
+}
+
+impl Library {
+    /// Creates an empty library.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Registers a directory as a library root.
+    ///
+    /// # Arguments
+    ///
+    /// * `name` - Display name for the root, e.g. "Projects"
+    /// * `path` - An existing directory
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Error::Library`] if the name is empty or already used, if the
+    /// path isn't a directory, or if it's inside (or contains) another root.
+    pub fn add_root(&mut self, name: impl Into<String>, path: impl AsRef<Path>) -> Result<&LibraryRoot> {
+        let name = name.into().trim().to_string();
+        if name.is_empty() {
+            return Err(Error::Library("root name cannot be empty".to_string()));
+        }
+        if self.get(&name).is_some() {
+            return Err(Error::Library(format!("a root named '{name}' already exists")));
+        }
+
+        let path = normalize_folder_path(path.as_ref());
+        if !path.is_dir() {
+            return Err(Error::Library(format!(
+                "'{}' is not a directory",
+                path.display()
+            )));
+        }
+        if let Some(existing) = self
+            .roots
+            .iter()
+            .find(|root| path.starts_with(&root.path) || root.path.starts_with(&path))
+        {
+            return Err(Error::Library(format!(
+                "'{}' overlaps the existing root '{}'",
+                path.display(),
+                existing.name
+            )));
+        }
+
+        let index = self.roots.len();
+        self.roots.push(LibraryRoot {
+            name,
+            path,
+            added_at: now_unix_secs(),
+        });
+        Ok(&self.roots[index])
+    }
+
+    /// Removes the root with the given name, returning it if it existed.
+    ///
+    /// Only the registration is removed; the directory is untouched.
+    pub fn remove_root(&mut self, name: &str) -> Option<LibraryRoot> {
+        let index = self.roots.iter().position(|root| root.name == name)?;
+        Some(self.roots.remove(index))
+    }
+
+    /// Returns the root with the given name.
+    pub fn get(&self, name: &str) -> Option<&LibraryRoot> {
+        self.roots.iter().find(|root| root.name == name)
+    }
+
+    /// Returns all roots, in the order they were added.
+    pub fn roots(&self) -> &[LibraryRoot] {
+        &self.roots
+    }
+
+    /// Returns the root that contains `folder`, if any.
+    pub fn root_for(&self, folder: &Path) -> Option<&LibraryRoot> {
+        let folder = normalize_folder_path(folder);
+        self.roots.iter().find(|root| folder.starts_with(&root.path))
+    }
+
+    /// Returns the paths of all roots.
+    pub fn paths(&self) -> Vec<PathBuf> {
+        self.roots.iter().map(|root| root.path.clone()).collect()
+    }
+
+    /// Returns `true` if no roots are registered.
+    pub fn is_empty(&self) -> bool {
+        self.roots.is_empty()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+    use tempfile::tempdir;
+
+    #[test]
+    fn test_add_list_remove() {
+        let temp = tempdir().unwrap();
+        let projects = temp.path().join("Projects");
+        fs::create_dir(&projects).unwrap();
+
+        let mut library = Library::new();
+        library.add_root("Projects", &projects).unwrap();
+        assert_eq!(library.roots().len(), 1);
+        assert_eq!(library.get("Projects").unwrap().path, normalize_folder_path(&projects));
+
+        assert!(library.remove_root("Projects").is_some());
+        assert!(library.is_empty());
+        assert!(library.remove_root("Projects").is_none());
+    }
+
+    #[test]
+    fn test_rejects_invalid_roots() {
+        let temp = tempdir().unwrap();
+        let projects = temp.path().join("Projects");
+        fs::create_dir_all(projects.join("client")).unwrap();
+
+        let mut library = Library::new();
+        library.add_root("Projects", &projects).unwrap();
+
+        assert!(library.add_root("  ", &projects).is_err());
+        assert!(library.add_root("Projects", temp.path()).is_err());
+        assert!(library.add_root("Client", projects.join("client")).is_err());
+        assert!(library.add_root("Everything", temp.path()).is_err());
+        assert!(library.add_root("Missing", temp.path().join("missing")).is_err());
+    }
+
+    #[test]
+    fn test_root_for() {
+        let temp = tempdir().unwrap();
+        let projects = temp.path().join("Projects");
+        fs::create_dir(&projects).unwrap();
+
+        let mut library = Library::new();
+        library.add_root("Projects", &projects).unwrap();
+
+        let root = library.root_for(&projects.join("app").join("src")).unwrap();
+        assert_eq!(root.name, "Projects");
+        assert!(library.root_for(temp.path()).is_none());
+    }
+}
@@ -0,0 +1,297 @@
+//! Limits on the size of profile payloads.
+//!
+//! Profiles come from presets, sync files and manifests that anyone can
+//! write, and a decal can embed a whole image. A 200MB inline image, or a
+//! thousand decals, would stall every apply that renders the profile.
+//! [`PayloadLimits`] bounds what a profile may carry, and contexts check it
+//! whenever a profile is validated or applied, failing with an
+//! [`Error::PayloadLimit`] that says which limit was exceeded and by how
+//! much.
+
+use crate::error::{Error, Result};
+use crate::profile::profile_decals;
+
+use folco_renderer::CustomizationProfile;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use std::fmt;
+
+/// Default [`PayloadLimits::max_decal_bytes`]: 4 MiB.
+pub const DEFAULT_MAX_DECAL_BYTES: usize = 4 * 1024 * 1024;
+
+/// Default [`PayloadLimits::max_decals`].
+pub const DEFAULT_MAX_DECALS: usize = 16;
+
+/// Default [`PayloadLimits::max_render_dimension`], in pixels.
+pub const DEFAULT_MAX_RENDER_DIMENSION: u32 = 4096;
+
+/// Bounds on what a profile may carry.
+///
+/// # Example
+///
+/// ```
+/// use folco_core::PayloadLimits;
+///
+/// // Allow larger embedded images, but only a single decal
+/// let limits = PayloadLimits::new()
+///     .with_max_decal_bytes(16 * 1024 * 1024)
+///     .with_max_decals(1);
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct PayloadLimits {
+    /// Largest size of a single decal's settings, in bytes, including any
+    /// embedded SVG or image data.
+    pub max_decal_bytes: usize,
+    /// Most decals a profile may have.
+    pub max_decals: usize,
+    /// Largest width, height or size a profile may set, in pixels.
+    pub max_render_dimension: u32,
+}
+
+impl Default for PayloadLimits {
+    fn default() -> Self {
+        Self {
+            max_decal_bytes: DEFAULT_MAX_DECAL_BYTES,
+            max_decals: DEFAULT_MAX_DECALS,
+            max_render_dimension: DEFAULT_MAX_RENDER_DIMENSION,
+        }
+    }
+}
+
+impl PayloadLimits {
+    /// Creates the default limits.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Limits that accept any profile.
+    pub fn unlimited() -> Self {
+        Self {
+            max_decal_bytes: usize::MAX,
+            max_decals: usize::MAX,
+            max_render_dimension: u32::MAX,
+        }
+    }
+
+    /// Sets the largest size of a single decal, in bytes.
+    pub fn with_max_decal_bytes(mut self, bytes: usize) -> Self {
+        self.max_decal_bytes = bytes;
+        self
+    }
+
+    /// Sets the most decals a profile may have.
+    pub fn with_max_decals(mut self, count: usize) -> Self {
+        self.max_decals = count;
+        self
+    }
+
+    /// Sets the largest width, height or size a profile may set, in pixels.
+    pub fn with_max_render_dimension(mut self, pixels: u32) -> Self {
+        self.max_render_dimension = pixels;
+        self
+    }
+
+    /// Checks that `profile` is within the limits.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Error::PayloadLimit`] for the first limit the profile
+    /// exceeds.
+    pub fn validate_profile(&self, profile: &CustomizationProfile) -> Result<()> {
+        let decals = profile_decals(profile);
+        check(PayloadLimit::DecalCount, decals.len(), self.max_decals)?;
+        for decal in &decals {
+            let bytes = serde_json::to_vec(decal)
+                .map_err(|e| Error::Serialization(e.to_string()))?
+                .len();
+            check(PayloadLimit::DecalBytes, bytes, self.max_decal_bytes)?;
+        }
+
+        let value =
+            serde_json::to_value(profile).map_err(|e| Error::Serialization(e.to_string()))?;
+        if let Some(dimension) = largest_dimension(&value) {
+            if dimension > f64::from(self.max_render_dimension) {
+                return Err(Error::PayloadLimit(LimitExceeded {
+                    limit: PayloadLimit::RenderDimension,
+                    actual: dimension.ceil() as u64,
+                    max: u64::from(self.max_render_dimension),
+                }));
+            }
+        }
+        Ok(())
+    }
+}
+
+/// A limit in [`PayloadLimits`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum PayloadLimit {
+    /// [`PayloadLimits::max_decal_bytes`].
+    DecalBytes,
+    /// [`PayloadLimits::max_decals`].
+    DecalCount,
+    /// [`PayloadLimits::max_render_dimension`].
+    RenderDimension,
+}
+
+impl fmt::Display for PayloadLimit {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Self::DecalBytes => "decal size",
+            Self::DecalCount => "decal count",
+            Self::RenderDimension => "render dimension",
+        })
+    }
+}
+
+/// A profile over one of its [`PayloadLimits`].
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct LimitExceeded {
+    /// The limit that was exceeded.
+    pub limit: PayloadLimit,
+    /// The profile's value: bytes, decals or pixels.
+    pub actual: u64,
+    /// The largest value allowed.
+    pub max: u64,
+}
+
+impl fmt::Display for LimitExceeded {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let unit = match self.limit {
+            PayloadLimit::DecalBytes => " bytes",
+            PayloadLimit::DecalCount => "",
+            PayloadLimit::RenderDimension => "px",
+        };
+        write!(
+            f,
+            "{} of {}{unit} is over the limit of {}{unit}",
+            self.limit, self.actual, self.max
+        )
+    }
+}
+
+fn check(limit: PayloadLimit, actual: usize, max: usize) -> Result<()> {
+    if actual <= max {
+        return Ok(());
+    }
+    Err(Error::PayloadLimit(LimitExceeded {
+        limit,
+        actual: actual as u64,
+        max: max as u64,
+    }))
+}
+
+/// Returns the largest number under a key naming a width, height or size,
+/// anywhere in `value`.
+fn largest_dimension(value: &Value) -> Option<f64> {
+    match value {
+        Value::Object(map) => map
+            .iter()
+            .filter_map(|(key, item)| {
+                let key = key.to_ascii_lowercase();
+                let is_dimension = ["width", "height", "size"]
+                    .iter()
+                    .any(|name| key.ends_with(name));
+                match item {
+                    Value::Number(number) if is_dimension => number.as_f64(),
+                    _ => largest_dimension(item),
+                }
+            })
+            .reduce(f64::max),
+        Value::Array(items) => items.iter().filter_map(largest_dimension).reduce(f64::max),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::profile::profile_with_decal;
+    use folco_renderer::DecalSettings;
+    use serde_json::json;
+
+    fn decal(source: Value) -> DecalSettings {
+        serde_json::from_value(json!({ "source": source })).unwrap()
+    }
+
+    #[test]
+    fn test_default_profile_is_within_limits() {
+        let profile = CustomizationProfile::default();
+        assert!(PayloadLimits::default().validate_profile(&profile).is_ok());
+    }
+
+    #[test]
+    fn test_decal_bytes_limit() {
+        let svg = format!("<svg>{}</svg>", " ".repeat(4096));
+        let profile = profile_with_decal(
+            &CustomizationProfile::default(),
+            Some(&decal(json!({ "svg": svg }))),
+        )
+        .unwrap();
+
+        assert!(PayloadLimits::default().validate_profile(&profile).is_ok());
+        let err = PayloadLimits::new()
+            .with_max_decal_bytes(1024)
+            .validate_profile(&profile)
+            .unwrap_err();
+        let Error::PayloadLimit(exceeded) = err else {
+            panic!("unexpected error: {err}");
+        };
+        assert_eq!(exceeded.limit, PayloadLimit::DecalBytes);
+        assert!(exceeded.actual > 4096);
+        assert_eq!(exceeded.max, 1024);
+    }
+
+    #[test]
+    fn test_decal_count_limit() {
+        let profile = profile_with_decal(
+            &CustomizationProfile::default(),
+            Some(&decal(json!({ "emoji": "🚀" }))),
+        )
+        .unwrap();
+
+        assert!(
+            PayloadLimits::new()
+                .with_max_decals(1)
+                .validate_profile(&profile)
+                .is_ok()
+        );
+        assert!(matches!(
+            PayloadLimits::new()
+                .with_max_decals(0)
+                .validate_profile(&profile),
+            Err(Error::PayloadLimit(LimitExceeded {
+                limit: PayloadLimit::DecalCount,
+                actual: 1,
+                max: 0,
+            }))
+        ));
+    }
+
+    #[test]
+    fn test_largest_dimension() {
+        let value = json!({
+            "decal": { "width": 64, "scale": 9000 },
+            "layers": [{ "fontSize": 12.5 }, { "maxHeight": 8192 }],
+            "size": "huge",
+        });
+        assert_eq!(largest_dimension(&value), Some(8192.0));
+        assert_eq!(largest_dimension(&json!({ "scale": 2 })), None);
+    }
+
+    #[test]
+    fn test_limit_exceeded_message() {
+        let exceeded = LimitExceeded {
+            limit: PayloadLimit::DecalBytes,
+            actual: 209_715_200,
+            max: 4_194_304,
+        };
+        assert_eq!(
+            exceeded.to_string(),
+            "decal size of 209715200 bytes is over the limit of 4194304 bytes"
+        );
+    }
+}
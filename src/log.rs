@@ -0,0 +1,503 @@
+//! A persistent log of what core did.
+//!
+//! Batches and the folder watcher often run in the background, where
+//! nobody sees their errors when they happen. Contexts write a line to a
+//! [`CoreLog`] in the app data directory for every finished batch, every
+//! folder that failed and everything the watcher noticed, so an issue can
+//! be diagnosed afterwards with
+//! [`recent_logs`](crate::CustomizationContext::recent_logs).
+//!
+//! The log is plain text, one `<seconds since the epoch> <LEVEL> <message>`
+//! line per entry. Once it reaches [`LogConfig::max_bytes`], it's renamed to
+//! `folco.log.1`, older files move up one number, and only
+//! [`LogConfig::max_files`] rotated files are kept.
+//!
+//! With the `tracing` feature, every entry is also emitted as a `tracing`
+//! event with a `folco_core` target, and [`CoreLog::layer`] returns a
+//! `tracing-subscriber` layer that writes the application's own events into
+//! the same file.
+
+use serde::{Deserialize, Serialize};
+
+use std::fmt;
+use std::fs::{self, OpenOptions};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex, MutexGuard};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// Name of the log file in the app data directory.
+pub const LOG_FILE_NAME: &str = "folco.log";
+
+/// Default [`LogConfig::max_bytes`]: 1 MiB.
+pub const DEFAULT_MAX_LOG_BYTES: u64 = 1024 * 1024;
+
+/// Default [`LogConfig::max_files`].
+pub const DEFAULT_MAX_LOG_FILES: usize = 3;
+
+/// How important a log entry is.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum LogLevel {
+    /// Details for following an operation step by step.
+    Debug,
+    /// Something finished normally.
+    Info,
+    /// Something failed, but the operation carried on.
+    Warn,
+    /// An operation failed as a whole.
+    Error,
+}
+
+impl LogLevel {
+    fn as_str(self) -> &'static str {
+        match self {
+            Self::Debug => "DEBUG",
+            Self::Info => "INFO",
+            Self::Warn => "WARN",
+            Self::Error => "ERROR",
+        }
+    }
+
+    fn parse(text: &str) -> Option<Self> {
+        [Self::Debug, Self::Info, Self::Warn, Self::Error]
+            .into_iter()
+            .find(|level| level.as_str() == text)
+    }
+}
+
+impl fmt::Display for LogLevel {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+/// Size, rotation and level settings of a [`CoreLog`].
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct LogConfig {
+    /// Size the log file may reach before it's rotated, in bytes.
+    pub max_bytes: u64,
+    /// Number of rotated files to keep besides the current one.
+    pub max_files: usize,
+    /// Least important level written; entries below it are dropped.
+    pub level: LogLevel,
+}
+
+impl Default for LogConfig {
+    fn default() -> Self {
+        Self {
+            max_bytes: DEFAULT_MAX_LOG_BYTES,
+            max_files: DEFAULT_MAX_LOG_FILES,
+            level: LogLevel::Info,
+        }
+    }
+}
+
+impl LogConfig {
+    /// Creates the default settings.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Sets the size the log file may reach before it's rotated.
+    pub fn with_max_bytes(mut self, bytes: u64) -> Self {
+        self.max_bytes = bytes;
+        self
+    }
+
+    /// Sets the number of rotated files to keep.
+    pub fn with_max_files(mut self, count: usize) -> Self {
+        self.max_files = count;
+        self
+    }
+
+    /// Sets the least important level written.
+    pub fn with_level(mut self, level: LogLevel) -> Self {
+        self.level = level;
+        self
+    }
+}
+
+/// One line of the log.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct LogEntry {
+    /// When the entry was written, in seconds since the Unix epoch.
+    pub time: u64,
+    /// How important it is.
+    pub level: LogLevel,
+    /// What happened.
+    pub message: String,
+}
+
+impl LogEntry {
+    fn parse(line: &str) -> Option<Self> {
+        let mut parts = line.splitn(3, ' ');
+        Some(Self {
+            time: parts.next()?.parse().ok()?,
+            level: LogLevel::parse(parts.next()?)?,
+            message: parts.next().unwrap_or_default().to_string(),
+        })
+    }
+}
+
+impl fmt::Display for LogEntry {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} {} {}", self.time, self.level, self.message)
+    }
+}
+
+/// A rotating log file, shared by clones.
+///
+/// Writing never fails: a log that can't be written to loses the entry
+/// rather than failing the operation it describes.
+#[derive(Clone)]
+pub struct CoreLog {
+    inner: Option<Arc<Mutex<LogFile>>>,
+}
+
+struct LogFile {
+    path: PathBuf,
+    config: LogConfig,
+}
+
+impl CoreLog {
+    /// Creates a log written to `path`.
+    pub fn new(path: impl Into<PathBuf>, config: LogConfig) -> Self {
+        Self {
+            inner: Some(Arc::new(Mutex::new(LogFile {
+                path: path.into(),
+                config,
+            }))),
+        }
+    }
+
+    /// Creates a log written to [`LOG_FILE_NAME`] in `data_dir`.
+    pub fn in_data_dir(data_dir: &Path, config: LogConfig) -> Self {
+        Self::new(data_dir.join(LOG_FILE_NAME), config)
+    }
+
+    /// Creates a log that drops every entry.
+    pub fn disabled() -> Self {
+        Self { inner: None }
+    }
+
+    /// Returns the path of the current log file, or `None` if the log is
+    /// disabled.
+    pub fn path(&self) -> Option<PathBuf> {
+        Some(self.lock()?.path.clone())
+    }
+
+    /// Writes an entry at `level`, if the log's level lets it through.
+    pub fn log(&self, level: LogLevel, message: impl fmt::Display) {
+        #[cfg(feature = "tracing")]
+        match level {
+            LogLevel::Debug => tracing::debug!("{message}"),
+            LogLevel::Info => tracing::info!("{message}"),
+            LogLevel::Warn => tracing::warn!("{message}"),
+            LogLevel::Error => tracing::error!("{message}"),
+        }
+        self.write_entry(level, message);
+    }
+
+    /// Writes an entry to the file only, without emitting a `tracing` event.
+    fn write_entry(&self, level: LogLevel, message: impl fmt::Display) {
+        let Some(file) = self.lock() else {
+            return;
+        };
+        if level < file.config.level {
+            return;
+        }
+        let time = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|elapsed| elapsed.as_secs())
+            .unwrap_or(0);
+        // One entry per line, whatever the message holds
+        let message = message.to_string().replace(['\r', '\n'], " ");
+        let entry = LogEntry {
+            time,
+            level,
+            message,
+        };
+        let _ = file.write(&format!("{entry}\n"));
+    }
+
+    /// Writes a [`LogLevel::Debug`] entry.
+    pub fn debug(&self, message: impl fmt::Display) {
+        self.log(LogLevel::Debug, message);
+    }
+
+    /// Writes a [`LogLevel::Info`] entry.
+    pub fn info(&self, message: impl fmt::Display) {
+        self.log(LogLevel::Info, message);
+    }
+
+    /// Writes a [`LogLevel::Warn`] entry.
+    pub fn warn(&self, message: impl fmt::Display) {
+        self.log(LogLevel::Warn, message);
+    }
+
+    /// Writes a [`LogLevel::Error`] entry.
+    pub fn error(&self, message: impl fmt::Display) {
+        self.log(LogLevel::Error, message);
+    }
+
+    /// Returns the last `count` entries, oldest first, reading into the
+    /// rotated files as needed.
+    ///
+    /// Lines that aren't log entries, such as one cut short by a crash, are
+    /// skipped.
+    pub fn recent(&self, count: usize) -> Vec<LogEntry> {
+        let Some(file) = self.lock() else {
+            return Vec::new();
+        };
+        let mut entries = Vec::new();
+        for index in 0..=file.config.max_files {
+            if entries.len() >= count {
+                break;
+            }
+            let Ok(content) = fs::read_to_string(file.rotated_path(index)) else {
+                continue;
+            };
+            let older: Vec<_> = content.lines().filter_map(LogEntry::parse).collect();
+            entries.splice(0..0, older);
+        }
+        let skip = entries.len().saturating_sub(count);
+        entries.split_off(skip)
+    }
+
+    fn lock(&self) -> Option<MutexGuard<'_, LogFile>> {
+        let inner = self.inner.as_ref()?;
+        Some(inner.lock().unwrap_or_else(|e| e.into_inner()))
+    }
+}
+
+/// A `tracing-subscriber` layer that writes events into a [`CoreLog`].
+///
+/// Created with [`CoreLog::layer`]. Events are written as
+/// `<target>: <message> <field>=<value>...` at the closest [`LogLevel`],
+/// `TRACE` counting as debug. Events from `folco_core` itself are skipped,
+/// since the log already writes them.
+#[cfg(feature = "tracing")]
+#[derive(Debug, Clone)]
+pub struct CoreLogLayer {
+    log: CoreLog,
+}
+
+#[cfg(feature = "tracing")]
+impl CoreLog {
+    /// Returns a layer that writes the application's `tracing` events into
+    /// this log.
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// use folco_core::{CoreLog, LogConfig};
+    /// use tracing_subscriber::layer::SubscriberExt;
+    ///
+    /// let log = CoreLog::new("/tmp/folco.log", LogConfig::new());
+    /// let subscriber = tracing_subscriber::registry().with(log.layer());
+    /// tracing::subscriber::set_global_default(subscriber).unwrap();
+    /// ```
+    pub fn layer(&self) -> CoreLogLayer {
+        CoreLogLayer { log: self.clone() }
+    }
+}
+
+#[cfg(feature = "tracing")]
+impl<S: tracing::Subscriber> tracing_subscriber::Layer<S> for CoreLogLayer {
+    fn on_event(
+        &self,
+        event: &tracing::Event<'_>,
+        _ctx: tracing_subscriber::layer::Context<'_, S>,
+    ) {
+        let metadata = event.metadata();
+        let target = metadata.target();
+        if target == env!("CARGO_CRATE_NAME")
+            || target.starts_with(concat!(env!("CARGO_CRATE_NAME"), "::"))
+        {
+            return;
+        }
+        let level = match *metadata.level() {
+            tracing::Level::ERROR => LogLevel::Error,
+            tracing::Level::WARN => LogLevel::Warn,
+            tracing::Level::INFO => LogLevel::Info,
+            _ => LogLevel::Debug,
+        };
+        let mut fields = EventFields::default();
+        event.record(&mut fields);
+        self.log.write_entry(
+            level,
+            format_args!("{target}: {}{}", fields.message, fields.rest),
+        );
+    }
+}
+
+/// The message and other fields of a `tracing` event.
+#[cfg(feature = "tracing")]
+#[derive(Default)]
+struct EventFields {
+    message: String,
+    rest: String,
+}
+
+#[cfg(feature = "tracing")]
+impl tracing::field::Visit for EventFields {
+    fn record_debug(&mut self, field: &tracing::field::Field, value: &dyn fmt::Debug) {
+        use std::fmt::Write as _;
+
+        if field.name() == "message" {
+            let _ = write!(self.message, "{value:?}");
+        } else {
+            let _ = write!(self.rest, " {}={value:?}", field.name());
+        }
+    }
+
+    fn record_str(&mut self, field: &tracing::field::Field, value: &str) {
+        self.record_debug(field, &format_args!("{value}"));
+    }
+}
+
+impl fmt::Debug for CoreLog {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("CoreLog")
+            .field("path", &self.path())
+            .finish_non_exhaustive()
+    }
+}
+
+impl LogFile {
+    /// Returns the path of the current file for `index` 0, and of the
+    /// rotated file numbered `index` otherwise.
+    fn rotated_path(&self, index: usize) -> PathBuf {
+        if index == 0 {
+            return self.path.clone();
+        }
+        let mut name = self.path.as_os_str().to_owned();
+        name.push(format!(".{index}"));
+        PathBuf::from(name)
+    }
+
+    fn write(&self, line: &str) -> std::io::Result<()> {
+        let size = fs::metadata(&self.path).map(|m| m.len()).unwrap_or(0);
+        if size > 0 && size + line.len() as u64 > self.config.max_bytes {
+            self.rotate()?;
+        }
+        if let Some(parent) = self.path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&self.path)?
+            .write_all(line.as_bytes())
+    }
+
+    fn rotate(&self) -> std::io::Result<()> {
+        if self.config.max_files == 0 {
+            return fs::remove_file(&self.path);
+        }
+        let _ = fs::remove_file(self.rotated_path(self.config.max_files));
+        for index in (0..self.config.max_files).rev() {
+            let from = self.rotated_path(index);
+            if from.exists() {
+                fs::rename(&from, self.rotated_path(index + 1))?;
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    #[test]
+    fn test_write_and_read_back() {
+        let temp = tempdir().unwrap();
+        let log = CoreLog::in_data_dir(temp.path(), LogConfig::new());
+        log.debug("dropped");
+        log.info("customize batch: 2 succeeded");
+        log.warn("failed:\nline two");
+
+        let entries = log.recent(10);
+        assert_eq!(entries.len(), 2);
+        assert_eq!(entries[0].level, LogLevel::Info);
+        assert_eq!(entries[1].message, "failed: line two");
+        assert_eq!(log.recent(1), entries[1..]);
+        assert_eq!(log.path(), Some(temp.path().join(LOG_FILE_NAME)));
+    }
+
+    #[test]
+    fn test_rotation() {
+        let temp = tempdir().unwrap();
+        let config = LogConfig::new().with_max_bytes(64).with_max_files(2);
+        let log = CoreLog::in_data_dir(temp.path(), config);
+        for index in 0..20 {
+            log.info(format!("entry {index:02}"));
+        }
+
+        let path = temp.path().join(LOG_FILE_NAME);
+        assert!(fs::metadata(&path).unwrap().len() <= 64);
+        assert!(temp.path().join("folco.log.2").exists());
+        assert!(!temp.path().join("folco.log.3").exists());
+
+        // Only what the kept files hold survives, newest last
+        let entries = log.recent(100);
+        assert!(entries.len() < 20);
+        assert_eq!(entries.last().unwrap().message, "entry 19");
+        let messages: Vec<_> = entries.iter().map(|e| e.message.clone()).collect();
+        let mut sorted = messages.clone();
+        sorted.sort();
+        assert_eq!(messages, sorted);
+    }
+
+    #[test]
+    fn test_disabled_log() {
+        let log = CoreLog::disabled();
+        log.error("nowhere");
+        assert!(log.recent(10).is_empty());
+        assert_eq!(log.path(), None);
+    }
+
+    #[cfg(feature = "tracing")]
+    #[test]
+    fn test_layer_writes_application_events() {
+        use tracing_subscriber::layer::SubscriberExt;
+
+        let temp = tempdir().unwrap();
+        let log = CoreLog::in_data_dir(temp.path(), LogConfig::new());
+        let subscriber = tracing_subscriber::registry().with(log.layer());
+        tracing::subscriber::with_default(subscriber, || {
+            tracing::warn!(target: "app::sync", folder = "Music", "upload failed");
+            tracing::debug!(target: "app::sync", "dropped");
+            // Written once, not again by the layer
+            log.info("customize batch: 1 succeeded");
+        });
+
+        let messages: Vec<_> = log.recent(10).into_iter().map(|e| e.message).collect();
+        assert_eq!(
+            messages,
+            [
+                "app::sync: upload failed folder=Music",
+                "customize batch: 1 succeeded"
+            ]
+        );
+    }
+
+    #[test]
+    fn test_parse_skips_partial_lines() {
+        assert_eq!(
+            LogEntry::parse("12 WARN"),
+            Some(LogEntry {
+                time: 12,
+                level: LogLevel::Warn,
+                message: String::new(),
+            })
+        );
+        assert_eq!(LogEntry::parse("12 WA"), None);
+        assert_eq!(LogEntry::parse("garbage"), None);
+    }
+}
@@ -0,0 +1,248 @@
+//! CSV import and export of folder customizations.
+//!
+//! Admins who manage folder colors in a spreadsheet can export the profile
+//! store with [`export_store_csv`], edit it, and apply the result with
+//! [`CustomizationContext::apply_manifest`](crate::CustomizationContext::apply_manifest).
+//!
+//! The CSV has a header row with the columns `path`, `color`, `preset` and
+//! `applied_at`. Column names are case-insensitive, `applied-at` and
+//! `appliedAt` are accepted, and unknown columns are ignored. Only `path` is
+//! required.
+//!
+//! Exports also have a `notes` column and a `meta:<key>` column for each
+//! metadata key in the store, for spreadsheets to carry along. Applying a
+//! manifest ignores them.
+
+use crate::color::FolderColor;
+use crate::error::{Error, Result};
+use crate::profile::profile_color;
+use crate::selection::expand_home;
+use crate::store::ProfileStore;
+
+use serde::{Deserialize, Serialize};
+
+use std::collections::BTreeSet;
+use std::io::{Read, Write};
+use std::path::PathBuf;
+
+/// One row of a customization manifest.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ManifestEntry {
+    /// The folder to customize.
+    pub path: PathBuf,
+    /// Color preset to apply, on top of the preset if both are given.
+    pub color: Option<FolderColor>,
+    /// Name of a saved preset to apply.
+    pub preset: Option<String>,
+    /// When the folder was customized, in seconds since the Unix epoch.
+    ///
+    /// Informational: it's written on export and ignored when applying.
+    pub applied_at: Option<u64>,
+}
+
+impl ManifestEntry {
+    /// Creates an entry for `path` with nothing to apply yet.
+    pub fn new(path: impl Into<PathBuf>) -> Self {
+        Self {
+            path: path.into(),
+            color: None,
+            preset: None,
+            applied_at: None,
+        }
+    }
+
+    /// Sets the color preset to apply.
+    pub fn with_color(mut self, color: FolderColor) -> Self {
+        self.color = Some(color);
+        self
+    }
+
+    /// Sets the saved preset to apply.
+    pub fn with_preset(mut self, preset: impl Into<String>) -> Self {
+        self.preset = Some(preset.into());
+        self
+    }
+}
+
+/// Writes every folder in the profile store as a CSV row.
+///
+/// Folders whose profile doesn't use a color preset have an empty `color`
+/// column, and metadata keys a folder doesn't have are left empty.
+pub fn export_store_csv(store: &ProfileStore, writer: impl Write) -> Result<()> {
+    let entries = store.entries();
+    let keys: BTreeSet<&str> = entries
+        .iter()
+        .flat_map(|(_, stored)| stored.metadata.keys().map(String::as_str))
+        .collect();
+
+    let mut csv = csv::Writer::from_writer(writer);
+    let mut header = ["path", "color", "preset", "applied_at", "notes"].map(String::from).to_vec();
+    header.extend(keys.iter().map(|key| format!("meta:{key}")));
+    csv.write_record(&header).map_err(csv_error)?;
+
+    for (path, stored) in &entries {
+        let path = path.to_string_lossy();
+        let color = profile_color(&stored.profile).map_or("", |c| c.id());
+        let applied_at = stored.applied_at.to_string();
+        let mut record = vec![
+            &*path,
+            color,
+            stored.preset.as_deref().unwrap_or_default(),
+            applied_at.as_str(),
+            stored.notes.as_deref().unwrap_or_default(),
+        ];
+        record.extend(
+            keys.iter()
+                .map(|&key| stored.metadata.get(key).map_or("", String::as_str)),
+        );
+        csv.write_record(record).map_err(csv_error)?;
+    }
+
+    csv.flush()?;
+    Ok(())
+}
+
+/// Parses a customization manifest from CSV.
+///
+/// # Errors
+///
+/// Returns [`Error::Manifest`] if there is no `path` column, or if a row has
+/// an empty path, an unknown color or an invalid timestamp. The error names
+/// the offending line.
+pub fn parse_manifest_csv(reader: impl Read) -> Result<Vec<ManifestEntry>> {
+    let mut csv = csv::ReaderBuilder::new()
+        .trim(csv::Trim::All)
+        .flexible(true)
+        .from_reader(reader);
+
+    let headers = csv.headers().map_err(csv_error)?.clone();
+    let column = |name: &str| {
+        headers
+            .iter()
+            .position(|header| normalize_header(header) == name)
+    };
+    let path_column = column("path")
+        .ok_or_else(|| Error::Manifest("missing 'path' column".to_string()))?;
+    let color_column = column("color");
+    let preset_column = column("preset");
+    let applied_column = column("appliedat");
+
+    let mut entries = Vec::new();
+    for record in csv.records() {
+        let record = record.map_err(csv_error)?;
+        let line = record.position().map_or(0, |p| p.line());
+        let field = |column: Option<usize>| {
+            column
+                .and_then(|index| record.get(index))
+                .filter(|value| !value.is_empty())
+        };
+
+        let path = field(Some(path_column))
+            .ok_or_else(|| Error::Manifest(format!("line {line}: empty path")))?;
+        let color = field(color_column)
+            .map(|value| value.parse::<FolderColor>())
+            .transpose()
+            .map_err(|e| Error::Manifest(format!("line {line}: {e}")))?;
+        let applied_at = field(applied_column)
+            .map(|value| value.parse::<u64>())
+            .transpose()
+            .map_err(|e| Error::Manifest(format!("line {line}: invalid applied_at: {e}")))?;
+
+        entries.push(ManifestEntry {
+            path: expand_home(path),
+            color,
+            preset: field(preset_column).map(str::to_string),
+            applied_at,
+        });
+    }
+
+    Ok(entries)
+}
+
+fn normalize_header(header: &str) -> String {
+    header
+        .chars()
+        .filter(|c| c.is_alphanumeric())
+        .map(|c| c.to_ascii_lowercase())
+        .collect()
+}
+
+fn csv_error(e: csv::Error) -> Error {
+    Error::Manifest(e.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use folco_renderer::CustomizationProfile;
+    use tempfile::tempdir;
+
+    #[test]
+    fn test_parse_manifest() {
+        let csv = "Path, Color, Preset, applied-at, notes\n\
+                   /srv/a, red, ,123, something\n\
+                   /srv/b, , Work\n";
+        let entries = parse_manifest_csv(csv.as_bytes()).unwrap();
+
+        assert_eq!(
+            entries,
+            vec![
+                ManifestEntry {
+                    applied_at: Some(123),
+                    ..ManifestEntry::new("/srv/a").with_color(FolderColor::Red)
+                },
+                ManifestEntry::new("/srv/b").with_preset("Work"),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_parse_errors() {
+        assert!(matches!(
+            parse_manifest_csv("color\nred\n".as_bytes()),
+            Err(Error::Manifest(_))
+        ));
+
+        let err = parse_manifest_csv("path,color\n/a,mauve\n".as_bytes()).unwrap_err();
+        assert!(err.to_string().contains("line 2"));
+    }
+
+    #[test]
+    fn test_export_roundtrip() {
+        let temp = tempdir().unwrap();
+        let store = ProfileStore::in_data_dir(temp.path()).unwrap();
+        let folder = temp.path().join("folder, with comma");
+        store.insert(&folder, &CustomizationProfile::default());
+        store.set_preset(&folder, Some("Work".to_string()));
+
+        let mut out = Vec::new();
+        export_store_csv(&store, &mut out).unwrap();
+        let entries = parse_manifest_csv(out.as_slice()).unwrap();
+
+        assert_eq!(entries.len(), 1);
+        assert_eq!(entries[0].path, store.entries()[0].0);
+        assert_eq!(entries[0].preset.as_deref(), Some("Work"));
+        assert!(entries[0].applied_at.is_some());
+    }
+
+    #[test]
+    fn test_export_includes_notes_and_metadata() {
+        let temp = tempdir().unwrap();
+        let store = ProfileStore::in_data_dir(temp.path()).unwrap();
+        let (a, b) = (temp.path().join("a"), temp.path().join("b"));
+        store.insert(&a, &CustomizationProfile::default());
+        store.insert(&b, &CustomizationProfile::default());
+        store.set_notes(&a, Some("Shared with finance".to_string()));
+        store.set_metadata(&b, "ticket", Some("OPS-42".to_string()));
+
+        let mut out = Vec::new();
+        export_store_csv(&store, &mut out).unwrap();
+        let csv = String::from_utf8(out).unwrap();
+        let lines: Vec<&str> = csv.lines().collect();
+        assert_eq!(lines[0], "path,color,preset,applied_at,notes,meta:ticket");
+        assert!(lines[1].ends_with(",Shared with finance,"));
+        assert!(lines[2].ends_with(",,OPS-42"));
+        assert_eq!(parse_manifest_csv(csv.as_bytes()).unwrap().len(), 2);
+    }
+}
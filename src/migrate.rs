@@ -0,0 +1,246 @@
+//! Upgrades of on-disk state formats.
+//!
+//! Every JSON document folco persists carries a `version` field: the cache
+//! manifest, the profile store and preset library snapshots, and the app
+//! config. Each format has a [`Migrations`] list with one step per version
+//! bump. Documents are upgraded when they're first read; before an upgraded
+//! document replaces the old one, the old one is copied to a backup next to
+//! it (e.g. `config.json.v1.bak`), so a bad migration never loses data.
+//! Documents written by a newer version are refused rather than
+//! overwritten.
+//!
+//! Changing a format means bumping its current version and appending a
+//! step that turns the previous layout into the new one.
+
+use crate::error::{Error, Result};
+use crate::state::StateStore;
+use crate::store::write_atomic;
+
+use serde_json::Value;
+
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// Version assumed for documents without a `version` field, which predate
+/// versioning.
+const UNVERSIONED: u32 = 1;
+
+/// Turns a document of one version into the next version's layout.
+pub(crate) type MigrationStep = fn(Value) -> Result<Value>;
+
+/// The upgrade path of one document format.
+#[derive(Debug)]
+pub(crate) struct Migrations {
+    format: &'static str,
+    /// `steps[i]` upgrades version `i + 1` to `i + 2`.
+    steps: &'static [MigrationStep],
+}
+
+impl Migrations {
+    /// Creates the upgrade path of `format`, whose current version is one
+    /// more than the number of steps.
+    pub(crate) const fn new(format: &'static str, steps: &'static [MigrationStep]) -> Self {
+        Self { format, steps }
+    }
+
+    /// Returns the version documents are written with.
+    pub(crate) fn current(&self) -> u32 {
+        UNVERSIONED + self.steps.len() as u32
+    }
+
+    /// Returns the version of `document`.
+    pub(crate) fn version_of(&self, document: &Value) -> u32 {
+        document
+            .get("version")
+            .and_then(Value::as_u64)
+            .and_then(|version| u32::try_from(version).ok())
+            .unwrap_or(UNVERSIONED)
+    }
+
+    /// Upgrades `document` from `version` to the current version, setting
+    /// its `version` field.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Error::Migration`] if `version` is newer than the current
+    /// version, or if a step fails.
+    pub(crate) fn upgrade(&self, mut document: Value, version: u32) -> Result<Value> {
+        self.check_supported(version)?;
+        let first = version.saturating_sub(UNVERSIONED) as usize;
+        for (index, step) in self.steps.iter().enumerate().skip(first) {
+            let from = UNVERSIONED + index as u32;
+            document = step(document).map_err(|e| {
+                Error::Migration(format!(
+                    "upgrading {} from version {from} to {}: {e}",
+                    self.format,
+                    from + 1
+                ))
+            })?;
+        }
+        if let Value::Object(fields) = &mut document {
+            fields.insert("version".to_string(), Value::from(self.current()));
+        }
+        Ok(document)
+    }
+
+    /// Returns an error if documents of `version` can't be read.
+    pub(crate) fn check_supported(&self, version: u32) -> Result<()> {
+        if version > self.current() {
+            return Err(Error::Migration(format!(
+                "{} version {version} is newer than the supported version {}",
+                self.format,
+                self.current()
+            )));
+        }
+        Ok(())
+    }
+
+    /// Reads the document at `path`, upgrading the file first if it has an
+    /// old version. Returns `None` if the file doesn't exist.
+    pub(crate) fn load_file(&self, path: &Path) -> Result<Option<Value>> {
+        let content = match fs::read(path) {
+            Ok(content) => content,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(None),
+            Err(e) => return Err(e.into()),
+        };
+        let document = parse(&content)?;
+        let version = self.version_of(&document);
+        if version == self.current() {
+            return Ok(Some(document));
+        }
+
+        let upgraded = self.upgrade(document, version)?;
+        backup_file(path, version)?;
+        write_atomic(path, to_json(&upgraded)?)?;
+        Ok(Some(upgraded))
+    }
+
+    /// Reads the document stored as the blob `key` of `state`, upgrading the
+    /// blob first if it has an old version. Returns `None` if there is no
+    /// such blob.
+    pub(crate) fn load_blob(&self, state: &dyn StateStore, key: &str) -> Result<Option<Value>> {
+        let Some(content) = state.read_blob(key)? else {
+            return Ok(None);
+        };
+        let document = parse(&content)?;
+        let version = self.version_of(&document);
+        if version == self.current() {
+            return Ok(Some(document));
+        }
+
+        let upgraded = self.upgrade(document, version)?;
+        let backup = format!("{key}.v{version}.bak");
+        if state.read_blob(&backup)?.is_none() {
+            state.write_blob(&backup, &content)?;
+        }
+        state.write_blob(key, to_json(&upgraded)?.as_bytes())?;
+        Ok(Some(upgraded))
+    }
+}
+
+/// Returns the path the version `version` of `path` is backed up to.
+pub(crate) fn backup_path(path: &Path, version: u32) -> PathBuf {
+    let mut name = path.as_os_str().to_owned();
+    name.push(format!(".v{version}.bak"));
+    PathBuf::from(name)
+}
+
+/// Copies `path` to its backup for `version`, if it exists and isn't
+/// backed up yet. An earlier backup is kept, since it's the one closest to
+/// what the user last had working.
+pub(crate) fn backup_file(path: &Path, version: u32) -> Result<()> {
+    let backup = backup_path(path, version);
+    if path.exists() && !backup.exists() {
+        fs::copy(path, &backup)?;
+    }
+    Ok(())
+}
+
+fn parse(content: &[u8]) -> Result<Value> {
+    serde_json::from_slice(content).map_err(|e| Error::Serialization(e.to_string()))
+}
+
+fn to_json(document: &Value) -> Result<String> {
+    serde_json::to_string_pretty(document).map_err(|e| Error::Serialization(e.to_string()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::state::MemoryStateStore;
+    use serde_json::json;
+    use tempfile::tempdir;
+
+    /// Version 1 had a single `color`; version 2 has a list of `colors`.
+    fn split_colors(mut document: Value) -> Result<Value> {
+        let color = document
+            .as_object_mut()
+            .and_then(|fields| fields.remove("color"))
+            .ok_or_else(|| Error::Serialization("missing color".to_string()))?;
+        document["colors"] = json!([color]);
+        Ok(document)
+    }
+
+    const TEST_MIGRATIONS: Migrations = Migrations::new("test file", &[split_colors]);
+
+    #[test]
+    fn test_old_file_is_upgraded_and_backed_up() {
+        let temp = tempdir().unwrap();
+        let path = temp.path().join("test.json");
+        fs::write(&path, r#"{"color": "blue"}"#).unwrap();
+
+        let document = TEST_MIGRATIONS.load_file(&path).unwrap().unwrap();
+        assert_eq!(document, json!({ "version": 2, "colors": ["blue"] }));
+
+        let backup = fs::read_to_string(backup_path(&path, 1)).unwrap();
+        assert_eq!(backup, r#"{"color": "blue"}"#);
+        let rewritten: Value = serde_json::from_str(&fs::read_to_string(&path).unwrap()).unwrap();
+        assert_eq!(rewritten, document);
+
+        // Reading again changes nothing
+        assert_eq!(TEST_MIGRATIONS.load_file(&path).unwrap().unwrap(), document);
+    }
+
+    #[test]
+    fn test_newer_file_is_refused() {
+        let temp = tempdir().unwrap();
+        let path = temp.path().join("test.json");
+        fs::write(&path, r#"{"version": 3}"#).unwrap();
+
+        assert!(matches!(
+            TEST_MIGRATIONS.load_file(&path),
+            Err(Error::Migration(_))
+        ));
+        assert_eq!(fs::read_to_string(&path).unwrap(), r#"{"version": 3}"#);
+        assert!(!backup_path(&path, 3).exists());
+    }
+
+    #[test]
+    fn test_failed_step_leaves_file_alone() {
+        let temp = tempdir().unwrap();
+        let path = temp.path().join("test.json");
+        fs::write(&path, r#"{"version": 1}"#).unwrap();
+
+        assert!(TEST_MIGRATIONS.load_file(&path).is_err());
+        assert_eq!(fs::read_to_string(&path).unwrap(), r#"{"version": 1}"#);
+    }
+
+    #[test]
+    fn test_old_blob_is_upgraded_and_backed_up() {
+        let state = MemoryStateStore::new();
+        state
+            .write_blob("test.json", br#"{"color": "red"}"#)
+            .unwrap();
+
+        let document = TEST_MIGRATIONS
+            .load_blob(&state, "test.json")
+            .unwrap()
+            .unwrap();
+        assert_eq!(document["colors"], json!(["red"]));
+        assert_eq!(
+            state.read_blob("test.json.v1.bak").unwrap(),
+            Some(br#"{"color": "red"}"#.to_vec())
+        );
+        assert_eq!(TEST_MIGRATIONS.load_blob(&state, "missing").unwrap(), None);
+    }
+}
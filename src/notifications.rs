@@ -0,0 +1,185 @@
+//! Desktop notifications for batches nobody is watching, with the
+//! `notifications` feature.
+//!
+//! [`AutoApply`](crate::AutoApply) and other background work customize
+//! folders while no window is open. [`notify_batch`] tells the user how a
+//! batch went with a native notification instead: through `notify-send`
+//! on Linux, `osascript` on macOS and a PowerShell toast on Windows, so no
+//! extra library is needed.
+
+use crate::batch::BatchOutcome;
+use crate::error::{Error, Result};
+
+use serde::{Deserialize, Serialize};
+
+use std::process::{Command, Stdio};
+
+/// Name notifications are shown under.
+const APP_NAME: &str = "folco";
+
+/// A summary of a finished batch, for a notification.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BatchNotification {
+    /// The notification's title, such as `"Folders customized"`.
+    pub title: String,
+    /// Number of folders that succeeded.
+    pub succeeded: usize,
+    /// Number of folders that failed.
+    pub failed: usize,
+    /// Description of the first failure, if any.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub first_error: Option<String>,
+}
+
+impl BatchNotification {
+    /// Summarizes `outcome` under `title`.
+    ///
+    /// An error affecting the whole batch, such as a failed render, is the
+    /// first error if no folder failed.
+    pub fn from_outcome(title: impl Into<String>, outcome: &BatchOutcome) -> Self {
+        let folder_error = outcome.results.iter().find_map(|(folder, result)| {
+            let e = result.as_ref().err()?;
+            Some(format!("{}: {e}", folder.display()))
+        });
+        Self {
+            title: title.into(),
+            succeeded: outcome.succeeded_count(),
+            failed: outcome.failed_count(),
+            first_error: folder_error.or_else(|| outcome.error.as_ref().map(|e| e.to_string())),
+        }
+    }
+
+    /// Returns `true` if there's nothing to report.
+    pub fn is_empty(&self) -> bool {
+        self.succeeded == 0 && self.failed == 0 && self.first_error.is_none()
+    }
+
+    /// Returns the notification's text, such as `"3 succeeded, 1 failed"`
+    /// followed by the first error on its own line.
+    pub fn body(&self) -> String {
+        let mut body = format!("{} succeeded, {} failed", self.succeeded, self.failed);
+        if let Some(error) = &self.first_error {
+            body.push('\n');
+            body.push_str(error);
+        }
+        body
+    }
+}
+
+/// Shows `notification`, unless it's [empty](BatchNotification::is_empty).
+///
+/// # Errors
+///
+/// Returns [`Error::Notification`] if the platform's notifier can't be run
+/// or fails.
+pub fn notify_batch(notification: &BatchNotification) -> Result<()> {
+    if notification.is_empty() {
+        return Ok(());
+    }
+    notify_desktop(&notification.title, &notification.body())
+}
+
+/// Shows a desktop notification with `title` and `body`.
+///
+/// # Errors
+///
+/// Returns [`Error::Notification`] if the platform's notifier can't be run
+/// or fails.
+pub fn notify_desktop(title: &str, body: &str) -> Result<()> {
+    let (program, args) = notifier_command(title, body);
+    let status = Command::new(program)
+        .args(&args)
+        .stdin(Stdio::null())
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .status()
+        .map_err(|e| Error::Notification(format!("can't run {program}: {e}")))?;
+    if !status.success() {
+        return Err(Error::Notification(format!("{program} failed: {status}")));
+    }
+    Ok(())
+}
+
+/// Returns the program and arguments showing a notification on this
+/// platform.
+fn notifier_command(title: &str, body: &str) -> (&'static str, Vec<String>) {
+    if cfg!(target_os = "windows") {
+        let script = format!(
+            "$manager = [Windows.UI.Notifications.ToastNotificationManager, \
+             Windows.UI.Notifications, ContentType = WindowsRuntime]; \
+             $xml = $manager::GetTemplateContent(\
+             [Windows.UI.Notifications.ToastTemplateType]::ToastText02); \
+             $text = $xml.GetElementsByTagName('text'); \
+             $text.Item(0).AppendChild($xml.CreateTextNode({})) > $null; \
+             $text.Item(1).AppendChild($xml.CreateTextNode({})) > $null; \
+             $manager::CreateToastNotifier({}).Show(\
+             [Windows.UI.Notifications.ToastNotification]::new($xml))",
+            powershell_string(title),
+            powershell_string(body),
+            powershell_string(APP_NAME),
+        );
+        (
+            "powershell",
+            vec![
+                "-NoProfile".into(),
+                "-NonInteractive".into(),
+                "-Command".into(),
+                script,
+            ],
+        )
+    } else if cfg!(target_os = "macos") {
+        let script = format!(
+            "display notification {} with title {}",
+            applescript_string(body),
+            applescript_string(title)
+        );
+        ("osascript", vec!["-e".into(), script])
+    } else {
+        (
+            "notify-send",
+            vec![format!("--app-name={APP_NAME}"), title.into(), body.into()],
+        )
+    }
+}
+
+/// Quotes `text` as a single-quoted PowerShell string.
+fn powershell_string(text: &str) -> String {
+    format!("'{}'", text.replace('\'', "''"))
+}
+
+/// Quotes `text` as an AppleScript string.
+fn applescript_string(text: &str) -> String {
+    format!("\"{}\"", text.replace('\\', "\\\\").replace('"', "\\\""))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::PathBuf;
+
+    #[test]
+    fn test_from_outcome() {
+        let outcome = BatchOutcome {
+            results: vec![
+                (PathBuf::from("/a"), Ok(())),
+                (PathBuf::from("/b"), Err(Error::Hook("refused".to_string()))),
+            ],
+            ..Default::default()
+        };
+        let notification = BatchNotification::from_outcome("Folders customized", &outcome);
+        assert_eq!(notification.succeeded, 1);
+        assert_eq!(notification.failed, 1);
+        assert_eq!(
+            notification.body(),
+            "1 succeeded, 1 failed\n/b: hook failed: refused"
+        );
+        assert!(BatchNotification::from_outcome("Nothing", &BatchOutcome::default()).is_empty());
+    }
+
+    #[test]
+    fn test_quoting() {
+        assert_eq!(powershell_string("it's"), "'it''s'");
+        assert_eq!(applescript_string(r#"a "b" \c"#), r#""a \"b\" \\c""#);
+    }
+}
@@ -0,0 +1,258 @@
+//! OKLCH colors and their conversion to HSL.
+//!
+//! OKLCH is a cylindrical form of Oklab, a perceptual color space: equal
+//! steps in its lightness look equally large for every hue, which HSL's
+//! don't. A yellow and a blue with the same HSL lightness look nothing
+//! alike in brightness, so nudging them by the same amount does too. The
+//! renderer only takes HSL targets, so colors chosen in OKLCH are converted
+//! before they're handed over, with the chroma reduced as far as needed for
+//! the color to exist in sRGB.
+
+use folco_renderer::HslMutationSettings;
+use serde::{Deserialize, Serialize};
+
+/// Tolerance for linear sRGB channels counting as in gamut.
+const GAMUT_EPSILON: f32 = 1e-4;
+
+/// A color in OKLCH.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Oklch {
+    /// Perceived lightness, from 0.0 (black) to 1.0 (white).
+    pub lightness: f32,
+    /// Colorfulness, from 0.0 (grey); sRGB colors stay below about 0.33.
+    pub chroma: f32,
+    /// Hue in degrees (0–360).
+    pub hue: f32,
+}
+
+impl Oklch {
+    /// Creates a color from its lightness, chroma and hue.
+    pub fn new(lightness: f32, chroma: f32, hue: f32) -> Self {
+        Self {
+            lightness,
+            chroma,
+            hue,
+        }
+    }
+
+    /// Converts an HSL color, with hue in degrees and saturation and
+    /// lightness as fractions, to OKLCH.
+    pub fn from_hsl(hue: f32, saturation: f32, lightness: f32) -> Self {
+        Self::from_linear_srgb(hsl_to_srgb(hue, saturation, lightness).map(to_linear))
+    }
+
+    /// Converts the color to HSL `(hue, saturation, lightness)`.
+    ///
+    /// Colors outside sRGB keep their lightness and hue and lose chroma
+    /// until they fit.
+    pub fn to_hsl(&self) -> (f32, f32, f32) {
+        let rgb = self
+            .in_gamut()
+            .to_linear_srgb()
+            .map(|c| from_linear(c.clamp(0.0, 1.0)));
+        srgb_to_hsl(rgb)
+    }
+
+    /// Converts the color to HSL mutation settings for the renderer.
+    pub fn to_hsl_mutation_settings(&self) -> HslMutationSettings {
+        let (target_hue, target_saturation, target_lightness) = self.to_hsl();
+        HslMutationSettings {
+            target_hue,
+            target_saturation,
+            target_lightness,
+            enabled: true,
+        }
+    }
+
+    /// Returns `true` if the color exists in sRGB.
+    pub fn is_in_gamut(&self) -> bool {
+        self.to_linear_srgb()
+            .iter()
+            .all(|c| (-GAMUT_EPSILON..=1.0 + GAMUT_EPSILON).contains(c))
+    }
+
+    /// Returns the color with its chroma reduced until it exists in sRGB.
+    pub fn in_gamut(&self) -> Self {
+        let lightness = self.lightness.clamp(0.0, 1.0);
+        let fitted = Self::new(lightness, self.chroma.max(0.0), self.hue);
+        if fitted.is_in_gamut() {
+            return fitted;
+        }
+        let (mut low, mut high) = (0.0, fitted.chroma);
+        for _ in 0..24 {
+            let mid = (low + high) / 2.0;
+            if Self::new(lightness, mid, self.hue).is_in_gamut() {
+                low = mid;
+            } else {
+                high = mid;
+            }
+        }
+        Self::new(lightness, low, self.hue)
+    }
+
+    /// Returns the perceptual distance to `other`: the Euclidean distance in
+    /// Oklab, scaled by 100 so that about 2 is a just-noticeable difference.
+    pub fn delta_e(&self, other: &Oklch) -> f32 {
+        let [l1, a1, b1] = self.to_oklab();
+        let [l2, a2, b2] = other.to_oklab();
+        100.0 * ((l1 - l2).powi(2) + (a1 - a2).powi(2) + (b1 - b2).powi(2)).sqrt()
+    }
+
+    /// Converts a linear sRGB color to OKLCH.
+    pub(crate) fn from_linear_srgb(rgb: [f32; 3]) -> Self {
+        let [l, a, b] = linear_srgb_to_oklab(rgb);
+        let chroma = a.hypot(b);
+        let hue = if chroma < 1e-4 {
+            0.0
+        } else {
+            b.atan2(a).to_degrees().rem_euclid(360.0)
+        };
+        Self::new(l, chroma, hue)
+    }
+
+    fn to_oklab(self) -> [f32; 3] {
+        let hue = self.hue.to_radians();
+        [
+            self.lightness,
+            self.chroma * hue.cos(),
+            self.chroma * hue.sin(),
+        ]
+    }
+
+    fn to_linear_srgb(self) -> [f32; 3] {
+        oklab_to_linear_srgb(self.to_oklab())
+    }
+}
+
+// Björn Ottosson's matrices, computed in f64 so round trips stay exact to
+// well below an 8-bit step
+fn linear_srgb_to_oklab(rgb: [f32; 3]) -> [f32; 3] {
+    let [r, g, b] = rgb.map(f64::from);
+    let l = (0.412_221_470_8 * r + 0.536_332_536_3 * g + 0.051_445_992_9 * b).cbrt();
+    let m = (0.211_903_498_2 * r + 0.680_699_545_1 * g + 0.107_396_956_6 * b).cbrt();
+    let s = (0.088_302_461_9 * r + 0.281_718_837_6 * g + 0.629_978_700_5 * b).cbrt();
+    [
+        0.210_454_255_3 * l + 0.793_617_785_0 * m - 0.004_072_046_8 * s,
+        1.977_998_495_1 * l - 2.428_592_205_0 * m + 0.450_593_709_9 * s,
+        0.025_904_037_1 * l + 0.782_771_766_2 * m - 0.808_675_766_0 * s,
+    ]
+    .map(|c| c as f32)
+}
+
+fn oklab_to_linear_srgb(lab: [f32; 3]) -> [f32; 3] {
+    let [l, a, b] = lab.map(f64::from);
+    let l_ = (l + 0.396_337_777_4 * a + 0.215_803_757_3 * b).powi(3);
+    let m_ = (l - 0.105_561_345_8 * a - 0.063_854_172_8 * b).powi(3);
+    let s_ = (l - 0.089_484_177_5 * a - 1.291_485_548_0 * b).powi(3);
+    [
+        4.076_741_662_1 * l_ - 3.307_711_591_3 * m_ + 0.230_969_929_2 * s_,
+        -1.268_438_004_6 * l_ + 2.609_757_401_1 * m_ - 0.341_319_396_5 * s_,
+        -0.004_196_086_3 * l_ - 0.703_418_614_7 * m_ + 1.707_614_701_0 * s_,
+    ]
+    .map(|c| c as f32)
+}
+
+pub(crate) fn to_linear(c: f32) -> f32 {
+    if c <= 0.040_45 {
+        c / 12.92
+    } else {
+        ((c + 0.055) / 1.055).powf(2.4)
+    }
+}
+
+pub(crate) fn from_linear(c: f32) -> f32 {
+    if c <= 0.003_130_8 {
+        c * 12.92
+    } else {
+        1.055 * c.powf(1.0 / 2.4) - 0.055
+    }
+}
+
+pub(crate) fn hsl_to_srgb(hue: f32, saturation: f32, lightness: f32) -> [f32; 3] {
+    let chroma = (1.0 - (2.0 * lightness - 1.0).abs()) * saturation;
+    let sector = hue.rem_euclid(360.0) / 60.0;
+    let x = chroma * (1.0 - (sector % 2.0 - 1.0).abs());
+    let (r, g, b) = match sector as u32 {
+        0 => (chroma, x, 0.0),
+        1 => (x, chroma, 0.0),
+        2 => (0.0, chroma, x),
+        3 => (0.0, x, chroma),
+        4 => (x, 0.0, chroma),
+        _ => (chroma, 0.0, x),
+    };
+    let m = lightness - chroma / 2.0;
+    [r + m, g + m, b + m]
+}
+
+pub(crate) fn srgb_to_hsl([r, g, b]: [f32; 3]) -> (f32, f32, f32) {
+    let max = r.max(g).max(b);
+    let min = r.min(g).min(b);
+    let lightness = (max + min) / 2.0;
+    let delta = max - min;
+    if delta < 1e-6 {
+        return (0.0, 0.0, lightness);
+    }
+    let saturation = delta / (1.0 - (2.0 * lightness - 1.0).abs());
+    let hue = if max == r {
+        60.0 * ((g - b) / delta).rem_euclid(6.0)
+    } else if max == g {
+        60.0 * ((b - r) / delta + 2.0)
+    } else {
+        60.0 * ((r - g) / delta + 4.0)
+    };
+    (hue, saturation.clamp(0.0, 1.0), lightness)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_known_values() {
+        let white = Oklch::from_hsl(0.0, 0.0, 1.0);
+        assert!((white.lightness - 1.0).abs() < 1e-3);
+        assert!(white.chroma < 1e-3);
+
+        // sRGB red is about oklch(0.628 0.258 29.2)
+        let red = Oklch::from_hsl(0.0, 1.0, 0.5);
+        assert!((red.lightness - 0.628).abs() < 0.002);
+        assert!((red.chroma - 0.258).abs() < 0.002);
+        assert!((red.hue - 29.2).abs() < 0.2);
+    }
+
+    #[test]
+    fn test_hsl_round_trip() {
+        for (h, s, l) in [
+            (206.57, 0.8974, 0.5412),
+            (53.88, 1.0, 0.6157),
+            (0.0, 0.0, 0.26),
+        ] {
+            let (hue, sat, light) = Oklch::from_hsl(h, s, l).to_hsl();
+            assert!((light - l).abs() < 1e-3, "{h} {s} {l}");
+            assert!((sat - s).abs() < 2e-3, "{h} {s} {l}");
+            if s > 0.0 {
+                assert!((hue - h).abs() < 0.1, "{h} {s} {l}");
+            }
+        }
+    }
+
+    #[test]
+    fn test_delta_e() {
+        let blue = Oklch::from_hsl(206.57, 0.8974, 0.5412);
+        assert_eq!(blue.delta_e(&blue), 0.0);
+        let darker = Oklch::new(blue.lightness - 0.05, blue.chroma, blue.hue);
+        assert!((blue.delta_e(&darker) - 5.0).abs() < 1e-3);
+    }
+
+    #[test]
+    fn test_out_of_gamut_colors_lose_chroma() {
+        let vivid = Oklch::new(0.9, 0.4, 264.0);
+        assert!(!vivid.is_in_gamut());
+        let fitted = vivid.in_gamut();
+        assert!(fitted.is_in_gamut());
+        assert!(fitted.chroma < vivid.chroma);
+        assert_eq!(fitted.lightness, 0.9);
+        assert_eq!(fitted.hue, 264.0);
+    }
+}
@@ -0,0 +1,434 @@
+//! User palettes imported from color book files.
+//!
+//! Designers keep corporate colors in palette files exported from their
+//! tools: Adobe Swatch Exchange (`.ase`) from Illustrator and Photoshop,
+//! and GIMP palettes (`.gpl`), which Inkscape and Krita also write.
+//! [`read_palette`] turns either into a [`UserPalette`] of exact sRGB
+//! colors, which the context keeps in its config so brand colors can be
+//! applied to folders by name.
+
+use crate::color::TargetColor;
+use crate::error::{Error, Result};
+use crate::oklch::srgb_to_hsl;
+
+use serde::{Deserialize, Serialize};
+
+use std::fs;
+use std::path::Path;
+
+/// Signature at the start of an Adobe Swatch Exchange file.
+const ASE_SIGNATURE: &[u8; 4] = b"ASEF";
+
+/// ASE block holding a color entry.
+const ASE_COLOR_BLOCK: u16 = 0x0001;
+
+/// ASE block starting a named group of colors.
+const ASE_GROUP_START: u16 = 0xC001;
+
+/// Header line of a GIMP palette file.
+const GPL_HEADER: &str = "GIMP Palette";
+
+/// A named collection of exact colors, such as a company's brand colors.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct UserPalette {
+    /// Name of the palette, unique among the user's palettes.
+    pub name: String,
+    /// The colors, in file order.
+    pub colors: Vec<PaletteColor>,
+}
+
+impl UserPalette {
+    /// Returns the color with the given name, ignoring case.
+    pub fn color(&self, name: &str) -> Option<&PaletteColor> {
+        self.colors.iter().find(|color| {
+            color
+                .name
+                .as_deref()
+                .is_some_and(|n| n.eq_ignore_ascii_case(name))
+        })
+    }
+}
+
+/// One color of a [`UserPalette`].
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PaletteColor {
+    /// The color's name in the palette file, if it has one.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+    /// The color in 8-bit sRGB.
+    pub rgb: [u8; 3],
+}
+
+impl PaletteColor {
+    /// Returns the color as a `#rrggbb` string.
+    pub fn hex(&self) -> String {
+        let [r, g, b] = self.rgb;
+        format!("#{r:02x}{g:02x}{b:02x}")
+    }
+
+    /// Returns the color as a target for recoloring folders.
+    pub fn target(&self) -> TargetColor {
+        let (hue, saturation, lightness) = srgb_to_hsl(self.rgb.map(|c| f32::from(c) / 255.0));
+        TargetColor::Hsl {
+            hue,
+            saturation,
+            lightness,
+        }
+    }
+}
+
+/// A palette read from a file, with the entries that couldn't be used.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PaletteImport {
+    /// The palette.
+    pub palette: UserPalette,
+    /// Names of entries left out because their color model isn't
+    /// supported.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub skipped: Vec<String>,
+}
+
+/// Format of a palette file.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum PaletteFormat {
+    /// Adobe Swatch Exchange.
+    Ase,
+    /// GIMP palette.
+    Gpl,
+}
+
+impl PaletteFormat {
+    /// Returns the format of the file at `path`, judging by its extension.
+    pub fn from_path(path: &Path) -> Option<Self> {
+        let extension = path.extension()?.to_str()?;
+        if extension.eq_ignore_ascii_case("ase") {
+            Some(PaletteFormat::Ase)
+        } else if extension.eq_ignore_ascii_case("gpl") {
+            Some(PaletteFormat::Gpl)
+        } else {
+            None
+        }
+    }
+}
+
+/// Reads the ASE or GIMP palette at `path`.
+///
+/// The palette is named as in the file, or after the file if the file
+/// doesn't say.
+///
+/// # Errors
+///
+/// Returns [`Error::Palette`] if the file isn't a palette of a known format,
+/// is malformed, or holds no usable colors.
+pub fn read_palette(path: &Path) -> Result<PaletteImport> {
+    let format = PaletteFormat::from_path(path).ok_or_else(|| {
+        Error::Palette(format!(
+            "'{}' isn't an .ase or .gpl palette",
+            path.display()
+        ))
+    })?;
+    let fallback_name = path
+        .file_stem()
+        .map(|stem| stem.to_string_lossy().into_owned())
+        .unwrap_or_else(|| "Palette".to_string());
+    let import = match format {
+        PaletteFormat::Ase => parse_ase(&fs::read(path)?, fallback_name)?,
+        PaletteFormat::Gpl => parse_gpl(&fs::read_to_string(path)?, fallback_name)?,
+    };
+    if import.palette.colors.is_empty() {
+        return Err(Error::Palette(format!(
+            "'{}' has no RGB, CMYK, Lab or grey colors",
+            path.display()
+        )));
+    }
+    Ok(import)
+}
+
+/// Parses a GIMP palette.
+fn parse_gpl(text: &str, fallback_name: String) -> Result<PaletteImport> {
+    let mut lines = text.lines();
+    if lines.next().map(str::trim) != Some(GPL_HEADER) {
+        return Err(Error::Palette(
+            "missing the GIMP Palette header".to_string(),
+        ));
+    }
+
+    let mut name = None;
+    let mut colors = Vec::new();
+    for (index, line) in lines.enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        if let Some(value) = line.strip_prefix("Name:") {
+            name = Some(value.trim().to_string());
+            continue;
+        }
+        if line.starts_with("Columns:") {
+            continue;
+        }
+
+        let mut parts = line.split_whitespace();
+        let mut channel = || -> Result<u8> {
+            parts
+                .next()
+                .and_then(|part| part.parse().ok())
+                .ok_or_else(|| Error::Palette(format!("line {}: expected R G B", index + 2)))
+        };
+        let rgb = [channel()?, channel()?, channel()?];
+        let label = parts.collect::<Vec<_>>().join(" ");
+        colors.push(PaletteColor {
+            // GIMP names unnamed colors "Untitled"
+            name: (!label.is_empty() && label != "Untitled").then_some(label),
+            rgb,
+        });
+    }
+    Ok(PaletteImport {
+        palette: UserPalette {
+            name: name.filter(|n| !n.is_empty()).unwrap_or(fallback_name),
+            colors,
+        },
+        skipped: Vec::new(),
+    })
+}
+
+/// Parses an Adobe Swatch Exchange file.
+///
+/// Groups are flattened; the first group's name names the palette.
+fn parse_ase(bytes: &[u8], fallback_name: String) -> Result<PaletteImport> {
+    let mut reader = AseReader { bytes, offset: 0 };
+    if reader.take(4)? != ASE_SIGNATURE {
+        return Err(Error::Palette(
+            "not an Adobe Swatch Exchange file".to_string(),
+        ));
+    }
+    let _version = reader.take(4)?;
+    let block_count = reader.u32()?;
+
+    let mut name = None;
+    let mut colors = Vec::new();
+    let mut skipped = Vec::new();
+    for _ in 0..block_count {
+        let block_type = reader.u16()?;
+        let length = reader.u32()? as usize;
+        let mut block = AseReader {
+            bytes: reader.take(length)?,
+            offset: 0,
+        };
+        match block_type {
+            ASE_COLOR_BLOCK => {
+                let label = block.utf16_name()?;
+                let model = block.take(4)?;
+                let rgb = match model {
+                    b"RGB " => Some([block.f32()?, block.f32()?, block.f32()?]),
+                    b"CMYK" => {
+                        let [c, m, y, k] = [block.f32()?, block.f32()?, block.f32()?, block.f32()?];
+                        Some([c, m, y].map(|v| (1.0 - v) * (1.0 - k)))
+                    }
+                    b"LAB " => Some(lab_to_srgb(block.f32()?, block.f32()?, block.f32()?)),
+                    b"Gray" => Some([block.f32()?; 3]),
+                    _ => None,
+                };
+                match rgb {
+                    Some(rgb) => colors.push(PaletteColor {
+                        name: (!label.is_empty()).then_some(label),
+                        rgb: rgb.map(|c| (c.clamp(0.0, 1.0) * 255.0).round() as u8),
+                    }),
+                    None => skipped.push(label),
+                }
+            }
+            // Group ends and unknown blocks carry nothing needed
+            ASE_GROUP_START if name.is_none() => {
+                name = Some(block.utf16_name()?).filter(|n| !n.is_empty());
+            }
+            _ => {}
+        }
+    }
+    Ok(PaletteImport {
+        palette: UserPalette {
+            name: name.unwrap_or(fallback_name),
+            colors,
+        },
+        skipped,
+    })
+}
+
+/// Converts CIE Lab, as ASE stores it (L from 0 to 1, D50 white), to sRGB
+/// channels from 0 to 1.
+fn lab_to_srgb(l: f32, a: f32, b: f32) -> [f32; 3] {
+    const WHITE: [f32; 3] = [0.9642, 1.0, 0.8251];
+    let fy = (l * 100.0 + 16.0) / 116.0;
+    let f = [fy + a / 500.0, fy, fy - b / 200.0];
+    let [x, y, z] = [0, 1, 2].map(|i| {
+        let t = f[i];
+        let linear = if t > 6.0 / 29.0 {
+            t * t * t
+        } else {
+            3.0 * (6.0f32 / 29.0).powi(2) * (t - 4.0 / 29.0)
+        };
+        linear * WHITE[i]
+    });
+    // XYZ (D50) to linear sRGB, with Bradford adaptation to D65
+    let linear = [
+        3.133_856 * x - 1.616_867 * y - 0.490_615 * z,
+        -0.978_768 * x + 1.916_142 * y + 0.033_454 * z,
+        0.071_945 * x - 0.228_991 * y + 1.405_243 * z,
+    ];
+    linear.map(|c| {
+        let c = c.clamp(0.0, 1.0);
+        if c <= 0.003_130_8 {
+            c * 12.92
+        } else {
+            1.055 * c.powf(1.0 / 2.4) - 0.055
+        }
+    })
+}
+
+/// Big-endian reader over an ASE file or block.
+struct AseReader<'a> {
+    bytes: &'a [u8],
+    offset: usize,
+}
+
+impl<'a> AseReader<'a> {
+    fn take(&mut self, len: usize) -> Result<&'a [u8]> {
+        let end = self
+            .offset
+            .checked_add(len)
+            .filter(|&end| end <= self.bytes.len());
+        let end = end.ok_or_else(|| Error::Palette("the ASE file is truncated".to_string()))?;
+        let taken = &self.bytes[self.offset..end];
+        self.offset = end;
+        Ok(taken)
+    }
+
+    fn u16(&mut self) -> Result<u16> {
+        let bytes = self.take(2)?;
+        Ok(u16::from_be_bytes([bytes[0], bytes[1]]))
+    }
+
+    fn u32(&mut self) -> Result<u32> {
+        let bytes = self.take(4)?;
+        Ok(u32::from_be_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]))
+    }
+
+    fn f32(&mut self) -> Result<f32> {
+        Ok(f32::from_bits(self.u32()?))
+    }
+
+    /// Reads a length-prefixed, null-terminated UTF-16 name.
+    fn utf16_name(&mut self) -> Result<String> {
+        let units = self.u16()? as usize;
+        let bytes = self.take(units * 2)?;
+        let units: Vec<u16> = bytes
+            .chunks_exact(2)
+            .map(|pair| u16::from_be_bytes([pair[0], pair[1]]))
+            .take_while(|&unit| unit != 0)
+            .collect();
+        Ok(String::from_utf16_lossy(&units))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    /// ASE block ending a group.
+    const ASE_GROUP_END: u16 = 0xC002;
+
+    fn ase_name(name: &str) -> Vec<u8> {
+        let units: Vec<u16> = name.encode_utf16().chain([0]).collect();
+        let mut bytes = (units.len() as u16).to_be_bytes().to_vec();
+        for unit in units {
+            bytes.extend(unit.to_be_bytes());
+        }
+        bytes
+    }
+
+    fn ase_block(block_type: u16, body: Vec<u8>) -> Vec<u8> {
+        let mut bytes = block_type.to_be_bytes().to_vec();
+        bytes.extend((body.len() as u32).to_be_bytes());
+        bytes.extend(body);
+        bytes
+    }
+
+    fn ase_color(name: &str, model: &[u8; 4], values: &[f32]) -> Vec<u8> {
+        let mut body = ase_name(name);
+        body.extend(model);
+        for value in values {
+            body.extend(value.to_be_bytes());
+        }
+        body.extend(2u16.to_be_bytes());
+        ase_block(ASE_COLOR_BLOCK, body)
+    }
+
+    #[test]
+    fn test_gpl() {
+        let text = "GIMP Palette\nName: Acme Brand\nColumns: 2\n# comment\n\
+                    0 82 147\tAcme Blue\n255 255 255 Untitled\n";
+        let import = parse_gpl(text, "file".to_string()).unwrap();
+        assert_eq!(import.palette.name, "Acme Brand");
+        assert_eq!(import.palette.colors.len(), 2);
+        let blue = import.palette.color("acme blue").unwrap();
+        assert_eq!(blue.hex(), "#005293");
+        assert_eq!(import.palette.colors[1].name, None);
+
+        assert!(parse_gpl("0 0 0 Black\n", "file".to_string()).is_err());
+        assert!(parse_gpl("GIMP Palette\n0 0\n", "file".to_string()).is_err());
+    }
+
+    #[test]
+    fn test_ase() {
+        let blocks = [
+            ase_block(ASE_GROUP_START, ase_name("Acme")),
+            ase_color("Red", b"RGB ", &[1.0, 0.0, 0.0]),
+            ase_color("Ink", b"CMYK", &[0.0, 0.0, 0.0, 1.0]),
+            ase_color("Paper", b"LAB ", &[1.0, 0.0, 0.0]),
+            ase_color("Mid", b"Gray", &[0.5]),
+            ase_color("Odd", b"HSV ", &[0.0, 0.0, 0.0]),
+            ase_block(ASE_GROUP_END, Vec::new()),
+        ];
+        let mut bytes = ASE_SIGNATURE.to_vec();
+        bytes.extend([0, 1, 0, 0]);
+        bytes.extend((blocks.len() as u32).to_be_bytes());
+        for block in &blocks {
+            bytes.extend(block);
+        }
+
+        let import = parse_ase(&bytes, "file".to_string()).unwrap();
+        assert_eq!(import.palette.name, "Acme");
+        let hex: Vec<String> = import
+            .palette
+            .colors
+            .iter()
+            .map(PaletteColor::hex)
+            .collect();
+        assert_eq!(hex, ["#ff0000", "#000000", "#ffffff", "#808080"]);
+        assert_eq!(import.skipped, ["Odd"]);
+
+        assert!(parse_ase(&bytes[..bytes.len() - 3], "file".to_string()).is_err());
+        assert!(parse_ase(b"RIFF", "file".to_string()).is_err());
+    }
+
+    #[test]
+    fn test_read_palette_names_after_file() {
+        let temp = tempdir().unwrap();
+        let path = temp.path().join("Brand.gpl");
+        fs::write(&path, "GIMP Palette\n255 0 0 Red\n").unwrap();
+        let import = read_palette(&path).unwrap();
+        assert_eq!(import.palette.name, "Brand");
+
+        let (hue, saturation, lightness) = import.palette.colors[0].target().to_hsl();
+        assert_eq!((hue, saturation, lightness), (0.0, 1.0, 0.5));
+
+        let empty = temp.path().join("Empty.gpl");
+        fs::write(&empty, "GIMP Palette\n").unwrap();
+        assert!(matches!(read_palette(&empty), Err(Error::Palette(_))));
+        assert!(read_palette(&temp.path().join("colors.txt")).is_err());
+    }
+}
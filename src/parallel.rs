@@ -0,0 +1,161 @@
+//! A bounded pool of threads for applying icons to several folders at once.
+//!
+//! Setting a folder's icon mostly waits on the filesystem and the file
+//! manager, and each folder is independent of the others, so
+//! [`customize_folders_parallel`](crate::CustomizationContext::customize_folders_parallel)
+//! hands the operations to [`run_pool`] and handles their results as they
+//! come in.
+
+use crate::error::Result;
+use crate::queue::WorkGuard;
+
+use tokio::sync::mpsc::{unbounded_channel, UnboundedReceiver};
+
+use std::collections::VecDeque;
+use std::panic::{catch_unwind, AssertUnwindSafe};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::{Duration, Instant};
+
+/// An operation on one folder, run on a worker thread.
+pub(crate) type Job = Box<dyn FnOnce() -> Result<()> + Send>;
+
+/// Something that happened to a job of the pool.
+#[derive(Debug)]
+pub(crate) enum PoolEvent {
+    /// A worker started the job numbered `index`.
+    Started { index: usize },
+    /// The job numbered `index` finished after `elapsed`.
+    Finished {
+        index: usize,
+        result: Result<()>,
+        elapsed: Duration,
+    },
+}
+
+/// Runs `jobs` on at most `max_in_flight` threads, starting them in order,
+/// and returns the events of every job as they happen.
+///
+/// The receiver ends once every job finished or panicked; a job that
+/// panicked never reports [`PoolEvent::Finished`]. Dropping the receiver
+/// stops the workers after the jobs they're running. Each worker
+/// [yields](WorkGuard::yield_to_interactive) through `work` before it takes
+/// the next job, so background batches pause for interactive ones.
+pub(crate) fn run_pool(
+    jobs: Vec<(usize, Job)>,
+    max_in_flight: usize,
+    work: Arc<WorkGuard>,
+) -> UnboundedReceiver<PoolEvent> {
+    let (sender, receiver) = unbounded_channel();
+    let workers = max_in_flight.clamp(1, jobs.len().max(1));
+    let queue = Arc::new(Mutex::new(VecDeque::from(jobs)));
+
+    for _ in 0..workers {
+        let queue = Arc::clone(&queue);
+        let sender = sender.clone();
+        let work = Arc::clone(&work);
+        thread::spawn(move || {
+            loop {
+                work.yield_to_interactive();
+                let next = queue.lock().unwrap_or_else(|e| e.into_inner()).pop_front();
+                let Some((index, job)) = next else {
+                    return;
+                };
+                if sender.send(PoolEvent::Started { index }).is_err() {
+                    return;
+                }
+                let started = Instant::now();
+                // Keep the worker going for the other jobs
+                let Ok(result) = catch_unwind(AssertUnwindSafe(job)) else {
+                    continue;
+                };
+                let elapsed = started.elapsed();
+                let finished = PoolEvent::Finished {
+                    index,
+                    result,
+                    elapsed,
+                };
+                if sender.send(finished).is_err() {
+                    return;
+                }
+            }
+        });
+    }
+    receiver
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::error::Error;
+    use crate::queue::{Priority, WorkQueue};
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    fn work(priority: Priority) -> Arc<WorkGuard> {
+        Arc::new(Arc::new(WorkQueue::new()).enter(priority))
+    }
+
+    #[test]
+    fn test_pool_bounds_jobs_in_flight() {
+        let running = Arc::new(AtomicUsize::new(0));
+        let most = Arc::new(AtomicUsize::new(0));
+        let jobs = (0..12)
+            .map(|index| {
+                let running = Arc::clone(&running);
+                let most = Arc::clone(&most);
+                let job: Job = Box::new(move || {
+                    let now = running.fetch_add(1, Ordering::SeqCst) + 1;
+                    most.fetch_max(now, Ordering::SeqCst);
+                    thread::sleep(Duration::from_millis(10));
+                    running.fetch_sub(1, Ordering::SeqCst);
+                    if index == 5 {
+                        return Err(Error::Hook("refused".to_string()));
+                    }
+                    Ok(())
+                });
+                (index, job)
+            })
+            .collect();
+
+        let mut events = run_pool(jobs, 3, work(Priority::Interactive));
+        let mut finished = Vec::new();
+        while let Some(event) = events.blocking_recv() {
+            if let PoolEvent::Finished { index, result, .. } = event {
+                finished.push((index, result.is_ok()));
+            }
+        }
+        finished.sort();
+        assert_eq!(finished.len(), 12);
+        assert_eq!(finished[5], (5, false));
+        assert!(most.load(Ordering::SeqCst) <= 3);
+    }
+
+    #[test]
+    fn test_panicking_job_does_not_finish() {
+        let jobs: Vec<(usize, Job)> = vec![
+            (0, Box::new(|| panic!("job failed"))),
+            (1, Box::new(|| Ok(()))),
+        ];
+        let mut events = run_pool(jobs, 1, work(Priority::Interactive));
+        let mut finished = Vec::new();
+        while let Some(event) = events.blocking_recv() {
+            if let PoolEvent::Finished { index, .. } = event {
+                finished.push(index);
+            }
+        }
+        assert_eq!(finished, [1]);
+    }
+
+    #[test]
+    fn test_background_workers_yield_to_interactive_work() {
+        let queue = Arc::new(WorkQueue::new());
+        let interactive = queue.enter(Priority::Interactive);
+        let jobs: Vec<(usize, Job)> = vec![(0, Box::new(|| Ok(())))];
+        let mut events = run_pool(jobs, 1, Arc::new(queue.enter(Priority::Background)));
+
+        thread::sleep(Duration::from_millis(20));
+        assert!(events.try_recv().is_err());
+        drop(interactive);
+        assert!(matches!(events.blocking_recv(), Some(PoolEvent::Started { index: 0 })));
+    }
+}
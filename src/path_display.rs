@@ -0,0 +1,147 @@
+//! Short, readable paths for progress lines.
+//!
+//! Batches often reach deeply nested folders, whose full paths wrap a
+//! terminal line or overflow a status bar. A [`PathDisplay`] shortens them
+//! the same way for every frontend: the home directory becomes `~`, and
+//! paths still too long lose their middle, keeping the folder's own name.
+//! [`Progress::display_path`](crate::progress::Progress::display_path)
+//! applies it to the path of a progress event.
+
+use std::path::{Path, PathBuf};
+
+/// Default [`PathDisplay`] width, in characters.
+pub const DEFAULT_PATH_WIDTH: usize = 60;
+
+/// The character that stands for the part of a path left out.
+const ELLIPSIS: char = '…';
+
+/// How paths are shortened for display.
+///
+/// # Example
+///
+/// ```
+/// use folco_core::PathDisplay;
+/// use std::path::Path;
+///
+/// let display = PathDisplay::new(28).with_home(None);
+/// let path = Path::new("/srv/share/Clients/Acme/2024/Invoices/Paid");
+/// assert_eq!(display.display(path), "/srv/shar…2024/Invoices/Paid");
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PathDisplay {
+    max_width: usize,
+    home: Option<PathBuf>,
+}
+
+impl Default for PathDisplay {
+    fn default() -> Self {
+        Self::new(DEFAULT_PATH_WIDTH)
+    }
+}
+
+impl PathDisplay {
+    /// Creates a display of at most `max_width` characters, showing paths
+    /// below the user's home directory relative to `~`.
+    pub fn new(max_width: usize) -> Self {
+        let home = directories::BaseDirs::new().map(|dirs| dirs.home_dir().to_path_buf());
+        Self { max_width, home }
+    }
+
+    /// Sets the directory shown as `~`, or `None` to show full paths.
+    pub fn with_home(mut self, home: Option<PathBuf>) -> Self {
+        self.home = home;
+        self
+    }
+
+    /// Returns the most characters a displayed path has.
+    pub fn max_width(&self) -> usize {
+        self.max_width
+    }
+
+    /// Returns `path` shortened for display.
+    pub fn display(&self, path: &Path) -> String {
+        truncate_middle(&self.home_relative(path), self.max_width)
+    }
+
+    /// Returns `path` with the home directory replaced by `~`.
+    fn home_relative(&self, path: &Path) -> String {
+        // A home of `/` would put every path below it
+        let relative = self
+            .home
+            .as_deref()
+            .filter(|home| home.parent().is_some())
+            .and_then(|home| path.strip_prefix(home).ok());
+        match relative {
+            Some(rest) if rest.as_os_str().is_empty() => "~".to_string(),
+            Some(rest) => format!("~{}{}", std::path::MAIN_SEPARATOR, rest.display()),
+            None => path.display().to_string(),
+        }
+    }
+}
+
+/// Shortens `text` to at most `max_width` characters by replacing its
+/// middle with an ellipsis.
+///
+/// Two thirds of the room go to the end of the text, which for a path is
+/// the folder's own name.
+pub fn truncate_middle(text: &str, max_width: usize) -> String {
+    let chars: Vec<char> = text.chars().collect();
+    if chars.len() <= max_width {
+        return text.to_string();
+    }
+    let Some(room) = max_width.checked_sub(1) else {
+        return String::new();
+    };
+    let tail = room - room / 3;
+    let head = room - tail;
+    let mut shortened: String = chars[..head].iter().collect();
+    shortened.push(ELLIPSIS);
+    shortened.extend(&chars[chars.len() - tail..]);
+    shortened
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_truncate_middle() {
+        assert_eq!(truncate_middle("short", 10), "short");
+        assert_eq!(truncate_middle("abcdefghij", 7), "ab…ghij");
+        assert_eq!(truncate_middle("abcdefghij", 1), "…");
+        assert_eq!(truncate_middle("abcdefghij", 0), "");
+        assert_eq!(truncate_middle("ééééé", 4).chars().count(), 4);
+    }
+
+    #[test]
+    fn test_paths_below_home_are_relative() {
+        let home = Path::new("/home/ada");
+        let display = PathDisplay::new(80).with_home(Some(home.to_path_buf()));
+        let sep = std::path::MAIN_SEPARATOR;
+        assert_eq!(display.display(&home.join("Music")), format!("~{sep}Music"));
+        assert_eq!(display.display(home), "~");
+        assert_eq!(display.display(Path::new("/srv/share")), "/srv/share");
+
+        let full = PathDisplay::new(80).with_home(None);
+        assert_eq!(
+            full.display(&home.join("Music")),
+            home.join("Music").display().to_string()
+        );
+    }
+
+    #[test]
+    fn test_progress_paths() {
+        use crate::progress::Progress;
+
+        let display = PathDisplay::new(8).with_home(None);
+        let processing = Progress::Processing {
+            current: 0,
+            path: PathBuf::from("/srv/share/Projects"),
+        };
+        assert_eq!(
+            processing.display_path(&display).as_deref(),
+            Some("/s…jects")
+        );
+        assert!(Progress::Rendering.display_path(&display).is_none());
+    }
+}
@@ -0,0 +1,162 @@
+//! Path normalization and deduplication for batch inputs.
+//!
+//! GUIs often collect folders from several sources (drag and drop, file
+//! pickers, saved selections), so the same folder can show up more than once
+//! under different spellings: `C:\Work\`, `c:\work` and `C:\Work\a\..` all
+//! name one folder. Batch operations normalize their inputs first so each
+//! folder is processed exactly once.
+
+use serde::{Deserialize, Serialize};
+
+use std::collections::HashMap;
+use std::path::{Component, Path, PathBuf};
+
+/// An input path that was merged into an earlier entry of the same batch.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct MergedDuplicate {
+    /// The path as it was given.
+    pub input: PathBuf,
+    /// The normalized path it was merged into.
+    pub merged_into: PathBuf,
+}
+
+/// A deduplicated list of folders.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct NormalizedFolders {
+    /// Unique normalized folders, in order of first appearance.
+    pub folders: Vec<PathBuf>,
+    /// Inputs that duplicated an earlier folder.
+    pub duplicates: Vec<MergedDuplicate>,
+}
+
+/// Normalizes a folder path for comparison and processing.
+///
+/// The path is made absolute, `.` and `..` components are resolved
+/// lexically, and trailing separators are dropped. Symlinks are not
+/// resolved, so the path that gets customized is the one the user chose.
+pub fn normalize_folder_path(path: &Path) -> PathBuf {
+    let absolute = std::path::absolute(path).unwrap_or_else(|_| path.to_path_buf());
+
+    let mut normalized = PathBuf::new();
+    for component in absolute.components() {
+        match component {
+            Component::CurDir => {}
+            Component::ParentDir => {
+                // Never pop past the root or a drive prefix
+                if matches!(
+                    normalized.components().next_back(),
+                    Some(Component::Normal(_))
+                ) {
+                    normalized.pop();
+                }
+            }
+            other => normalized.push(other.as_os_str()),
+        }
+    }
+    normalized
+}
+
+/// Normalizes and deduplicates a list of folders.
+///
+/// On Windows and macOS, where the default filesystems are case-insensitive,
+/// paths differing only in case are treated as duplicates. The first
+/// spelling encountered is kept.
+pub fn normalize_folders<P: AsRef<Path>>(folders: &[P]) -> NormalizedFolders {
+    let mut result = NormalizedFolders::default();
+    let mut seen: HashMap<String, usize> = HashMap::new();
+
+    for folder in folders {
+        let input = folder.as_ref();
+        let normalized = normalize_folder_path(input);
+
+        match seen.get(&comparison_key(&normalized)) {
+            Some(&index) => result.duplicates.push(MergedDuplicate {
+                input: input.to_path_buf(),
+                merged_into: result.folders[index].clone(),
+            }),
+            None => {
+                seen.insert(comparison_key(&normalized), result.folders.len());
+                result.folders.push(normalized);
+            }
+        }
+    }
+
+    result
+}
+
+/// Returns the key used to decide whether two normalized paths are the same.
+pub(crate) fn comparison_key(path: &Path) -> String {
+    let key = path.to_string_lossy();
+    if cfg!(any(target_os = "windows", target_os = "macos")) {
+        key.to_lowercase()
+    } else {
+        key.into_owned()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn root() -> PathBuf {
+        std::env::temp_dir()
+    }
+
+    #[test]
+    fn test_normalize_resolves_dots() {
+        let base = root();
+        let path = base.join("a").join(".").join("b").join("..").join("c");
+        assert_eq!(normalize_folder_path(&path), base.join("a").join("c"));
+    }
+
+    #[test]
+    fn test_normalize_strips_trailing_separator() {
+        let base = root();
+        let with_sep = PathBuf::from(format!(
+            "{}{}",
+            base.join("folder").display(),
+            std::path::MAIN_SEPARATOR
+        ));
+        assert_eq!(normalize_folder_path(&with_sep), base.join("folder"));
+    }
+
+    #[test]
+    fn test_normalize_makes_relative_paths_absolute() {
+        let normalized = normalize_folder_path(Path::new("some/relative/dir"));
+        assert!(normalized.is_absolute());
+        assert!(normalized.ends_with("some/relative/dir"));
+    }
+
+    #[test]
+    fn test_normalize_folders_merges_duplicates() {
+        let base = root();
+        let a = base.join("a");
+        let inputs = vec![a.clone(), base.join("b"), a.join("..").join("a")];
+
+        let normalized = normalize_folders(&inputs);
+        assert_eq!(normalized.folders, vec![a.clone(), base.join("b")]);
+        assert_eq!(
+            normalized.duplicates,
+            vec![MergedDuplicate {
+                input: inputs[2].clone(),
+                merged_into: a,
+            }]
+        );
+    }
+
+    #[test]
+    fn test_normalize_folders_case_handling() {
+        let base = root();
+        let inputs = vec![base.join("Projects"), base.join("projects")];
+        let normalized = normalize_folders(&inputs);
+
+        if cfg!(any(target_os = "windows", target_os = "macos")) {
+            assert_eq!(normalized.folders.len(), 1);
+            assert_eq!(normalized.duplicates.len(), 1);
+        } else {
+            assert_eq!(normalized.folders.len(), 2);
+            assert!(normalized.duplicates.is_empty());
+        }
+    }
+}
@@ -0,0 +1,341 @@
+//! Reading icons straight from the resources of Windows PE files.
+//!
+//! The default folder icon lives in the resources of `shell32.dll` and
+//! `imageres.dll`, or of their `.mun` counterparts under `SystemResources` on
+//! recent builds. Reading the resource section directly needs no Windows API,
+//! which makes it a dependable fallback when the shell's own extraction
+//! returns the wrong icon.
+
+use image::{DynamicImage, ImageFormat};
+
+use std::collections::BTreeMap;
+
+const RT_ICON: u32 = 3;
+const RT_GROUP_ICON: u32 = 14;
+
+/// Index of the resource table in the optional header's data directories.
+const RESOURCE_DIRECTORY: usize = 2;
+
+/// High bit of a resource directory entry, set for subdirectories.
+const SUBDIRECTORY: u32 = 0x8000_0000;
+
+/// Reads every size of the icon group `group_id` from a PE file.
+///
+/// When a group has several images of one size, only the one with the most
+/// colors is kept. Images are returned from smallest to largest.
+///
+/// # Errors
+///
+/// Returns a description of the problem if the file isn't a PE file, has no
+/// such icon group, or none of the group's images decode.
+pub(crate) fn read_group_icon(file: &[u8], group_id: u16) -> Result<Vec<DynamicImage>, String> {
+    let resources = Resources::parse(file)?;
+    let group = resources
+        .find(RT_GROUP_ICON, u32::from(group_id))
+        .ok_or_else(|| format!("no icon group {group_id}"))?;
+
+    let count = usize::from(u16_at(group, 4)?);
+    // Best entry per width: (bit count, icon resource ID)
+    let mut best: BTreeMap<u32, (u16, u16)> = BTreeMap::new();
+    for index in 0..count {
+        let entry = 6 + index * 14;
+        let width = match *group.get(entry).ok_or_else(truncated)? {
+            0 => 256,
+            width => u32::from(width),
+        };
+        let bit_count = u16_at(group, entry + 6)?;
+        let id = u16_at(group, entry + 12)?;
+        if best.get(&width).is_none_or(|(bits, _)| bit_count > *bits) {
+            best.insert(width, (bit_count, id));
+        }
+    }
+
+    let mut images = Vec::new();
+    let mut errors = Vec::new();
+    for (width, (bit_count, id)) in best {
+        let decoded = resources
+            .find(RT_ICON, u32::from(id))
+            .ok_or_else(|| format!("icon {id} is missing"))
+            .and_then(|data| decode_icon(data, width, bit_count));
+        match decoded {
+            Ok(image) => images.push(image),
+            Err(e) => errors.push(format!("{width}px: {e}")),
+        }
+    }
+    if images.is_empty() {
+        return Err(format!(
+            "no decodable images in icon group {group_id} ({})",
+            errors.join(", ")
+        ));
+    }
+    Ok(images)
+}
+
+/// Decodes one `RT_ICON` resource, which holds either a PNG or a headerless
+/// BMP, by wrapping it in a single-image ICO file.
+fn decode_icon(data: &[u8], width: u32, bit_count: u16) -> Result<DynamicImage, String> {
+    let size = u32::try_from(data.len()).map_err(|_| "icon is too large".to_string())?;
+    let dimension = if width >= 256 { 0 } else { width as u8 };
+
+    let mut ico = Vec::with_capacity(22 + data.len());
+    ico.extend_from_slice(&[0, 0, 1, 0, 1, 0]);
+    ico.extend_from_slice(&[dimension, dimension, 0, 0]);
+    ico.extend_from_slice(&1u16.to_le_bytes());
+    ico.extend_from_slice(&bit_count.to_le_bytes());
+    ico.extend_from_slice(&size.to_le_bytes());
+    ico.extend_from_slice(&22u32.to_le_bytes());
+    ico.extend_from_slice(data);
+
+    image::load_from_memory_with_format(&ico, ImageFormat::Ico).map_err(|e| e.to_string())
+}
+
+/// A section's placement in memory and in the file.
+struct Section {
+    virtual_address: u32,
+    virtual_size: u32,
+    raw_offset: u32,
+    raw_size: u32,
+}
+
+/// The resource tree of a PE file.
+struct Resources<'a> {
+    file: &'a [u8],
+    sections: Vec<Section>,
+    /// File offset of the root resource directory.
+    root: usize,
+}
+
+impl<'a> Resources<'a> {
+    fn parse(file: &'a [u8]) -> Result<Self, String> {
+        if !file.starts_with(b"MZ") {
+            return Err("not a PE file".to_string());
+        }
+        let pe = u32_at(file, 0x3C)? as usize;
+        if file.get(pe..pe + 4) != Some(&b"PE\0\0"[..]) {
+            return Err("not a PE file".to_string());
+        }
+
+        let coff = pe + 4;
+        let section_count = usize::from(u16_at(file, coff + 2)?);
+        let optional_size = usize::from(u16_at(file, coff + 16)?);
+        let optional = coff + 20;
+        let (rva_count_offset, directories) = match u16_at(file, optional)? {
+            0x10B => (92, 96),
+            0x20B => (108, 112),
+            magic => return Err(format!("unknown optional header magic {magic:#x}")),
+        };
+        if (u32_at(file, optional + rva_count_offset)? as usize) <= RESOURCE_DIRECTORY {
+            return Err("no resources".to_string());
+        }
+        let resource_rva = u32_at(file, optional + directories + RESOURCE_DIRECTORY * 8)?;
+        if resource_rva == 0 {
+            return Err("no resources".to_string());
+        }
+
+        let table = optional + optional_size;
+        let sections = (0..section_count)
+            .map(|index| {
+                let header = table + index * 40;
+                Ok(Section {
+                    virtual_size: u32_at(file, header + 8)?,
+                    virtual_address: u32_at(file, header + 12)?,
+                    raw_size: u32_at(file, header + 16)?,
+                    raw_offset: u32_at(file, header + 20)?,
+                })
+            })
+            .collect::<Result<Vec<_>, String>>()?;
+
+        let mut resources = Self {
+            file,
+            sections,
+            root: 0,
+        };
+        resources.root = resources
+            .rva_to_offset(resource_rva)
+            .ok_or_else(|| "resource table is outside every section".to_string())?;
+        Ok(resources)
+    }
+
+    fn rva_to_offset(&self, rva: u32) -> Option<usize> {
+        self.sections.iter().find_map(|section| {
+            let extent = section.virtual_size.max(section.raw_size);
+            let within = rva.checked_sub(section.virtual_address)?;
+            if within >= extent {
+                return None;
+            }
+            Some(section.raw_offset.checked_add(within)? as usize)
+        })
+    }
+
+    /// Returns the data of the resource with the given type and ID, in
+    /// whichever language comes first.
+    fn find(&self, kind: u32, id: u32) -> Option<&'a [u8]> {
+        let names = self.subdirectory(self.entry(self.root, Some(kind))?)?;
+        let languages = self.subdirectory(self.entry(names, Some(id))?)?;
+        let data_entry = self.entry(languages, None)?;
+        if data_entry & SUBDIRECTORY != 0 {
+            return None;
+        }
+
+        let data_entry = self.root + data_entry as usize;
+        let rva = u32_at(self.file, data_entry).ok()?;
+        let size = u32_at(self.file, data_entry + 4).ok()? as usize;
+        let offset = self.rva_to_offset(rva)?;
+        self.file.get(offset..offset.checked_add(size)?)
+    }
+
+    /// Returns the value of the directory entry with the given ID, or of the
+    /// first entry if `id` is `None`. Named entries are never matched by ID.
+    fn entry(&self, directory: usize, id: Option<u32>) -> Option<u32> {
+        let named = usize::from(u16_at(self.file, directory + 12).ok()?);
+        let ids = usize::from(u16_at(self.file, directory + 14).ok()?);
+        (0..named + ids).find_map(|index| {
+            let entry = directory + 16 + index * 8;
+            let name = u32_at(self.file, entry).ok()?;
+            let is_match = match id {
+                Some(id) => name & SUBDIRECTORY == 0 && name == id,
+                None => true,
+            };
+            if !is_match {
+                return None;
+            }
+            u32_at(self.file, entry + 4).ok()
+        })
+    }
+
+    fn subdirectory(&self, entry: u32) -> Option<usize> {
+        (entry & SUBDIRECTORY != 0).then(|| self.root + (entry & !SUBDIRECTORY) as usize)
+    }
+}
+
+fn truncated() -> String {
+    "file is truncated".to_string()
+}
+
+fn u16_at(bytes: &[u8], offset: usize) -> Result<u16, String> {
+    bytes
+        .get(offset..offset + 2)
+        .map(|b| u16::from_le_bytes([b[0], b[1]]))
+        .ok_or_else(truncated)
+}
+
+fn u32_at(bytes: &[u8], offset: usize) -> Result<u32, String> {
+    bytes
+        .get(offset..offset + 4)
+        .map(|b| u32::from_le_bytes([b[0], b[1], b[2], b[3]]))
+        .ok_or_else(truncated)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use image::RgbaImage;
+    use std::io::Cursor;
+
+    const SECTION_OFFSET: usize = 0x200;
+    const SECTION_RVA: u32 = 0x1000;
+
+    fn put(buf: &mut Vec<u8>, offset: usize, bytes: &[u8]) {
+        if buf.len() < offset + bytes.len() {
+            buf.resize(offset + bytes.len(), 0);
+        }
+        buf[offset..offset + bytes.len()].copy_from_slice(bytes);
+    }
+
+    fn put_directory(buf: &mut Vec<u8>, offset: usize, entries: &[(u32, u32)]) {
+        let at = SECTION_OFFSET + offset;
+        put(buf, at + 14, &(entries.len() as u16).to_le_bytes());
+        for (index, (name, value)) in entries.iter().enumerate() {
+            put(buf, at + 16 + index * 8, &name.to_le_bytes());
+            put(buf, at + 20 + index * 8, &value.to_le_bytes());
+        }
+    }
+
+    /// Builds a PE32+ file whose only resources are a 16px PNG icon (ID 1)
+    /// in icon group 4.
+    fn sample_pe() -> Vec<u8> {
+        let mut png = Vec::new();
+        RgbaImage::from_pixel(16, 16, image::Rgba([250, 200, 80, 255]))
+            .write_to(&mut Cursor::new(&mut png), ImageFormat::Png)
+            .unwrap();
+
+        let mut pe = Vec::new();
+        put(&mut pe, 0, b"MZ");
+        put(&mut pe, 0x3C, &0x40u32.to_le_bytes());
+        put(&mut pe, 0x40, b"PE\0\0");
+        let coff = 0x44;
+        put(&mut pe, coff + 2, &1u16.to_le_bytes());
+        put(&mut pe, coff + 16, &240u16.to_le_bytes());
+        let optional = coff + 20;
+        put(&mut pe, optional, &0x20Bu16.to_le_bytes());
+        put(&mut pe, optional + 108, &16u32.to_le_bytes());
+        put(&mut pe, optional + 112 + 16, &SECTION_RVA.to_le_bytes());
+        let section = optional + 240;
+        put(&mut pe, section, b".rsrc\0\0\0");
+        put(&mut pe, section + 8, &0x1000u32.to_le_bytes());
+        put(&mut pe, section + 12, &SECTION_RVA.to_le_bytes());
+        put(&mut pe, section + 16, &0x1000u32.to_le_bytes());
+        put(
+            &mut pe,
+            section + 20,
+            &(SECTION_OFFSET as u32).to_le_bytes(),
+        );
+
+        put_directory(
+            &mut pe,
+            0x00,
+            &[
+                (RT_ICON, SUBDIRECTORY | 0x20),
+                (RT_GROUP_ICON, SUBDIRECTORY | 0x50),
+            ],
+        );
+        put_directory(&mut pe, 0x20, &[(1, SUBDIRECTORY | 0x38)]);
+        put_directory(&mut pe, 0x38, &[(0x409, 0x80)]);
+        put_directory(&mut pe, 0x50, &[(4, SUBDIRECTORY | 0x68)]);
+        put_directory(&mut pe, 0x68, &[(0x409, 0x90)]);
+
+        put(
+            &mut pe,
+            SECTION_OFFSET + 0x80,
+            &(SECTION_RVA + 0xC0).to_le_bytes(),
+        );
+        put(
+            &mut pe,
+            SECTION_OFFSET + 0x84,
+            &(png.len() as u32).to_le_bytes(),
+        );
+        put(
+            &mut pe,
+            SECTION_OFFSET + 0x90,
+            &(SECTION_RVA + 0xA0).to_le_bytes(),
+        );
+        put(&mut pe, SECTION_OFFSET + 0x94, &20u32.to_le_bytes());
+
+        let mut group = vec![0, 0, 1, 0, 1, 0, 16, 16, 0, 0, 1, 0, 32, 0];
+        group.extend_from_slice(&(png.len() as u32).to_le_bytes());
+        group.extend_from_slice(&1u16.to_le_bytes());
+        put(&mut pe, SECTION_OFFSET + 0xA0, &group);
+        put(&mut pe, SECTION_OFFSET + 0xC0, &png);
+        pe
+    }
+
+    #[test]
+    fn test_reads_icon_group() {
+        let images = read_group_icon(&sample_pe(), 4).unwrap();
+        assert_eq!(images.len(), 1);
+        assert_eq!(images[0].width(), 16);
+        assert_eq!(images[0].to_rgba8().get_pixel(3, 3).0, [250, 200, 80, 255]);
+    }
+
+    #[test]
+    fn test_missing_group() {
+        let error = read_group_icon(&sample_pe(), 3).unwrap_err();
+        assert!(error.contains("no icon group 3"));
+    }
+
+    #[test]
+    fn test_rejects_other_files() {
+        assert!(read_group_icon(b"not an executable", 4).is_err());
+        assert!(read_group_icon(&sample_pe()[..0x100], 4).is_err());
+    }
+}
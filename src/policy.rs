@@ -0,0 +1,217 @@
+//! Admin policy for managed environments.
+//!
+//! Organizations deploying folco can place a [`Policy`] file in a
+//! system-wide location that users can't write to (see
+//! [`AppInfo::policy_path`](crate::AppInfo::policy_path)). It restricts
+//! which colors profiles may use, can forbid custom SVG decals and command
+//! hooks, and pins protected folders that folco won't customize or reset.
+//! The context
+//! loads it once at build time and enforces it whenever a profile is
+//! validated or applied; unlike the [`AppConfig`](crate::AppConfig), it
+//! can't be changed through the API.
+
+use crate::color::FolderColor;
+use crate::error::{Error, Result};
+use crate::hooks::CommandHook;
+use crate::paths::{comparison_key, normalize_folder_path};
+use crate::profile::{profile_hsl, profile_uses_svg};
+
+use folco_renderer::CustomizationProfile;
+use serde::{Deserialize, Serialize};
+
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// File name of the policy inside the system-wide policy directory.
+pub(crate) const POLICY_FILE_NAME: &str = "policy.json";
+
+/// Restrictions an administrator places on folco.
+///
+/// The default policy restricts nothing. Unknown fields are ignored, so a
+/// policy written for a newer version still loads.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct Policy {
+    /// Colors profiles may use, or `None` for any color. When set, custom
+    /// colors that aren't one of these presets are rejected too.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub allowed_colors: Option<Vec<FolderColor>>,
+    /// Rejects profiles with custom SVG decals.
+    pub forbid_custom_svg: bool,
+    /// Keeps the [`CommandHook`]s of the app config from running, and
+    /// rejects setting any.
+    pub forbid_command_hooks: bool,
+    /// Folders, and everything below them, that can't be customized or
+    /// reset.
+    pub protected_paths: Vec<PathBuf>,
+}
+
+impl Policy {
+    /// Loads the policy at `path`, or returns the default policy if it
+    /// doesn't exist.
+    pub fn load(path: &Path) -> Result<Self> {
+        if !path.exists() {
+            return Ok(Self::default());
+        }
+        let content = fs::read_to_string(path)?;
+        serde_json::from_str(&content).map_err(|e| Error::Serialization(e.to_string()))
+    }
+
+    /// Returns `true` if the policy restricts nothing.
+    pub fn is_empty(&self) -> bool {
+        *self == Self::default()
+    }
+
+    /// Returns `true` if profiles may use `color`.
+    pub fn allows_color(&self, color: FolderColor) -> bool {
+        self.allowed_colors
+            .as_ref()
+            .is_none_or(|allowed| allowed.contains(&color))
+    }
+
+    /// Returns the color presets profiles may use, for a color picker to
+    /// offer.
+    pub fn available_colors(&self) -> Vec<FolderColor> {
+        FolderColor::all()
+            .iter()
+            .copied()
+            .filter(|&color| self.allows_color(color))
+            .collect()
+    }
+
+    /// Returns `true` if `folder` is, or is inside, a protected path.
+    pub fn is_protected(&self, folder: &Path) -> bool {
+        let folder = comparison_key(&normalize_folder_path(folder));
+        self.protected_paths.iter().any(|protected| {
+            let protected = comparison_key(&normalize_folder_path(protected));
+            Path::new(&folder).starts_with(Path::new(&protected))
+        })
+    }
+
+    /// Checks that `profile` complies with the policy.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Error::Policy`] if the profile uses a color that isn't
+    /// allowed, or a custom SVG decal when those are forbidden.
+    pub fn validate_profile(&self, profile: &CustomizationProfile) -> Result<()> {
+        if let Some(settings) = profile_hsl(profile).filter(|settings| settings.enabled) {
+            match FolderColor::from_hsl_mutation_settings(&settings) {
+                Some(color) if !self.allows_color(color) => {
+                    return Err(Error::Policy(format!("the color {color} isn't allowed")));
+                }
+                None if self.allowed_colors.is_some() => {
+                    return Err(Error::Policy("custom colors aren't allowed".to_string()));
+                }
+                _ => {}
+            }
+        }
+        if self.forbid_custom_svg && profile_uses_svg(profile) {
+            return Err(Error::Policy(
+                "custom SVG decals aren't allowed".to_string(),
+            ));
+        }
+        Ok(())
+    }
+
+    /// Checks that `hooks` may be set.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Error::Policy`] if there are hooks and command hooks are
+    /// forbidden.
+    pub fn check_command_hooks(&self, hooks: &[CommandHook]) -> Result<()> {
+        if self.forbid_command_hooks && !hooks.is_empty() {
+            return Err(Error::Policy("command hooks aren't allowed".to_string()));
+        }
+        Ok(())
+    }
+
+    /// Checks that `folder` may be customized or reset.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Error::Policy`] if the folder is protected.
+    pub fn check_folder(&self, folder: &Path) -> Result<()> {
+        if self.is_protected(folder) {
+            return Err(Error::Policy(format!(
+                "folder '{}' is protected",
+                folder.display()
+            )));
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::profile::profile_with_color;
+    use tempfile::tempdir;
+
+    #[test]
+    fn test_missing_policy_is_empty() {
+        let temp = tempdir().unwrap();
+        let policy = Policy::load(&temp.path().join(POLICY_FILE_NAME)).unwrap();
+        assert!(policy.is_empty());
+    }
+
+    #[test]
+    fn test_load_policy() {
+        let temp = tempdir().unwrap();
+        let path = temp.path().join(POLICY_FILE_NAME);
+        fs::write(
+            &path,
+            r#"{"allowedColors": ["blue", "grey"], "forbidCustomSvg": true}"#,
+        )
+        .unwrap();
+
+        let policy = Policy::load(&path).unwrap();
+        assert!(policy.forbid_custom_svg);
+        assert!(policy.allows_color(FolderColor::Blue));
+        assert!(!policy.allows_color(FolderColor::Red));
+        assert_eq!(policy.available_colors().len(), 2);
+    }
+
+    #[test]
+    fn test_validate_profile_colors() {
+        let policy = Policy {
+            allowed_colors: Some(vec![FolderColor::Blue]),
+            ..Default::default()
+        };
+        let base = CustomizationProfile::default();
+
+        assert!(policy.validate_profile(&base).is_ok());
+        let blue = profile_with_color(&base, FolderColor::Blue).unwrap();
+        assert!(policy.validate_profile(&blue).is_ok());
+        let red = profile_with_color(&base, FolderColor::Red).unwrap();
+        assert!(matches!(
+            policy.validate_profile(&red),
+            Err(Error::Policy(_))
+        ));
+    }
+
+    #[test]
+    fn test_forbid_command_hooks() {
+        let policy: Policy = serde_json::from_str(r#"{"forbidCommandHooks": true}"#).unwrap();
+        assert!(policy.check_command_hooks(&[]).is_ok());
+        assert!(matches!(
+            policy.check_command_hooks(&[CommandHook::new("notify")]),
+            Err(Error::Policy(_))
+        ));
+    }
+
+    #[test]
+    fn test_protected_paths() {
+        let temp = tempdir().unwrap();
+        let policy = Policy {
+            protected_paths: vec![temp.path().join("Shared")],
+            ..Default::default()
+        };
+
+        assert!(policy.is_protected(&temp.path().join("Shared")));
+        assert!(policy.is_protected(&temp.path().join("Shared").join("Reports")));
+        assert!(!policy.is_protected(&temp.path().join("SharedNotes")));
+        assert!(policy.check_folder(&temp.path().join("Mine")).is_ok());
+    }
+}
@@ -0,0 +1,530 @@
+//! Named, saved customization profiles.
+//!
+//! The [`PresetLibrary`] stores profiles under user-chosen names such as
+//! "Work" or "Archive", so they can be applied by name from the GUI, the CLI
+//! and manifests. Presets can be tagged, categorized and marked as favorites,
+//! and the library counts how often each is used, so large collections stay
+//! navigable. Like the profile store, it's persisted through a
+//! [`StateStore`], by default as journaled JSON in the app data directory.
+
+use crate::curve::LightnessCurve;
+use crate::error::{Error, Result};
+use crate::state::{load_entries, save_entries, JsonStateStore, StateCollection, StateStore};
+use crate::store::now_unix_secs;
+
+use folco_renderer::CustomizationProfile;
+use serde::{Deserialize, Serialize};
+
+use std::collections::btree_map::Entry;
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+/// File name of the preset library inside the app data directory.
+pub(crate) const PRESETS_FILE_NAME: &str = "presets.json";
+
+/// A saved profile.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Preset {
+    /// Unique name of the preset.
+    pub name: String,
+    /// The saved profile.
+    pub profile: CustomizationProfile,
+    /// When the preset was first saved, in seconds since the Unix epoch.
+    pub created_at: u64,
+    /// When the preset was last saved, in seconds since the Unix epoch.
+    pub updated_at: u64,
+    /// Free-form tags, without duplicates (ignoring case).
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub tags: Vec<String>,
+    /// Category the preset is filed under, e.g. "Clients".
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub category: Option<String>,
+    /// Whether the user marked the preset as a favorite.
+    #[serde(default)]
+    pub favorite: bool,
+    /// How many times the preset has been applied.
+    #[serde(default)]
+    pub use_count: u64,
+    /// When the preset was last applied, in seconds since the Unix epoch.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub last_used_at: Option<u64>,
+    /// Lightness curve used instead of the config's when the preset's color
+    /// is a grey, white or black.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub lightness_curve: Option<LightnessCurve>,
+}
+
+impl Preset {
+    /// Returns `true` if the preset has the given tag, ignoring case.
+    pub fn has_tag(&self, tag: &str) -> bool {
+        self.tags.iter().any(|t| t.eq_ignore_ascii_case(tag))
+    }
+}
+
+/// Order of presets returned by [`PresetLibrary::query`].
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum PresetSort {
+    /// Alphabetically by name.
+    #[default]
+    Name,
+    /// Most recently used first; never-used presets last.
+    RecentlyUsed,
+    /// Most often used first.
+    FrequentlyUsed,
+}
+
+/// Filters for [`PresetLibrary::query`].
+///
+/// All filters are optional and combined with AND. Text comparisons ignore
+/// case.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct PresetQuery {
+    /// Only presets whose name contains this text.
+    pub text: Option<String>,
+    /// Only presets with all of these tags.
+    pub tags: Vec<String>,
+    /// Only presets in this category.
+    pub category: Option<String>,
+    /// Only favorite presets.
+    pub favorites_only: bool,
+    /// Result order.
+    pub sort: PresetSort,
+    /// Maximum number of results.
+    pub limit: Option<usize>,
+}
+
+impl PresetQuery {
+    /// Creates a query matching every preset, sorted by name.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Filters by name substring.
+    pub fn with_text(mut self, text: impl Into<String>) -> Self {
+        self.text = Some(text.into());
+        self
+    }
+
+    /// Requires a tag. Can be called repeatedly.
+    pub fn with_tag(mut self, tag: impl Into<String>) -> Self {
+        self.tags.push(tag.into());
+        self
+    }
+
+    /// Filters by category.
+    pub fn with_category(mut self, category: impl Into<String>) -> Self {
+        self.category = Some(category.into());
+        self
+    }
+
+    /// Keeps only favorites.
+    pub fn favorites_only(mut self) -> Self {
+        self.favorites_only = true;
+        self
+    }
+
+    /// Sets the result order.
+    pub fn with_sort(mut self, sort: PresetSort) -> Self {
+        self.sort = sort;
+        self
+    }
+
+    /// Limits the number of results.
+    pub fn with_limit(mut self, limit: usize) -> Self {
+        self.limit = Some(limit);
+        self
+    }
+
+    fn matches(&self, preset: &Preset) -> bool {
+        let text_matches = self
+            .text
+            .as_ref()
+            .is_none_or(|text| preset.name.to_lowercase().contains(&text.to_lowercase()));
+        let category_matches = self.category.as_ref().is_none_or(|category| {
+            preset
+                .category
+                .as_ref()
+                .is_some_and(|c| c.eq_ignore_ascii_case(category))
+        });
+
+        text_matches
+            && category_matches
+            && (!self.favorites_only || preset.favorite)
+            && self.tags.iter().all(|tag| preset.has_tag(tag))
+    }
+}
+
+/// The user's saved presets, keyed by name.
+///
+/// Changes are made in memory and written with [`save`](Self::save).
+#[derive(Debug, Clone)]
+pub struct PresetLibrary {
+    path: PathBuf,
+    presets: BTreeMap<String, Preset>,
+    state: Arc<dyn StateStore>,
+}
+
+impl PresetLibrary {
+    /// Opens the JSON library at `path`, loading existing presets if the
+    /// file exists and replaying its journal.
+    pub fn open(path: impl Into<PathBuf>) -> Result<Self> {
+        let path = path.into();
+        let dir = path.parent().map(Path::to_path_buf).unwrap_or_default();
+        let state = JsonStateStore::new(dir).with_file(StateCollection::Presets, path);
+        Self::with_state_store(Arc::new(state))
+    }
+
+    /// Opens the JSON library in the given app data directory.
+    pub fn in_data_dir(data_dir: &Path) -> Result<Self> {
+        Self::open(data_dir.join(PRESETS_FILE_NAME))
+    }
+
+    /// Opens the library kept by `state`, loading the existing presets.
+    pub fn with_state_store(state: Arc<dyn StateStore>) -> Result<Self> {
+        Ok(Self {
+            path: state.location(StateCollection::Presets),
+            presets: load_entries(state.as_ref(), StateCollection::Presets)?,
+            state,
+        })
+    }
+
+    /// Returns where the library is kept, e.g. the path of the library file.
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Returns the preset with the given name.
+    pub fn get(&self, name: &str) -> Option<&Preset> {
+        self.presets.get(name)
+    }
+
+    /// Returns the profile of the preset with the given name.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Error::PresetNotFound`] if there is no such preset.
+    pub fn profile(&self, name: &str) -> Result<&CustomizationProfile> {
+        self.get(name)
+            .map(|preset| &preset.profile)
+            .ok_or_else(|| Error::PresetNotFound(name.to_string()))
+    }
+
+    /// Saves `profile` under `name`, replacing any preset with that name.
+    ///
+    /// Replacing a preset keeps its tags, category, favorite flag, usage and
+    /// lightness curve.
+    pub fn insert(&mut self, name: impl Into<String>, profile: CustomizationProfile) -> &Preset {
+        let preset = self.updated(name.into(), profile);
+        self.put(preset)
+    }
+
+    /// Saves `profile` under `name` like [`insert`](Self::insert), and saves
+    /// the library with it.
+    ///
+    /// # Errors
+    ///
+    /// Returns the state store's error if the library can't be saved, in
+    /// which case the preset isn't added either.
+    pub fn insert_and_save(
+        &mut self,
+        name: impl Into<String>,
+        profile: CustomizationProfile,
+    ) -> Result<&Preset> {
+        let preset = self.updated(name.into(), profile);
+        let mut entries: BTreeMap<String, &Preset> =
+            self.presets.iter().map(|(name, preset)| (name.clone(), preset)).collect();
+        entries.insert(preset.name.clone(), &preset);
+        save_entries(self.state.as_ref(), StateCollection::Presets, &entries)?;
+        Ok(self.put(preset))
+    }
+
+    /// Returns the preset `name` with `profile`, keeping the rest of an
+    /// existing preset with that name.
+    fn updated(&self, name: String, profile: CustomizationProfile) -> Preset {
+        let now = now_unix_secs();
+        match self.presets.get(&name) {
+            Some(existing) => Preset {
+                profile,
+                updated_at: now,
+                ..existing.clone()
+            },
+            None => Preset {
+                name,
+                profile,
+                created_at: now,
+                updated_at: now,
+                tags: Vec::new(),
+                category: None,
+                favorite: false,
+                use_count: 0,
+                last_used_at: None,
+                lightness_curve: None,
+            },
+        }
+    }
+
+    /// Adds `preset`, replacing any preset with its name, and returns it.
+    fn put(&mut self, preset: Preset) -> &Preset {
+        match self.presets.entry(preset.name.clone()) {
+            Entry::Occupied(mut entry) => {
+                entry.insert(preset);
+                entry.into_mut()
+            }
+            Entry::Vacant(entry) => entry.insert(preset),
+        }
+    }
+
+    /// Adds `preset` as is, replacing any preset with its name.
+    pub(crate) fn insert_preset(&mut self, preset: Preset) {
+        self.presets.insert(preset.name.clone(), preset);
+    }
+
+    /// Replaces the tags of a preset.
+    ///
+    /// Tags are trimmed, and empty and duplicate tags (ignoring case) are
+    /// dropped.
+    pub fn set_tags<S: AsRef<str>>(&mut self, name: &str, tags: &[S]) -> Result<()> {
+        let preset = self.get_mut(name)?;
+        preset.tags.clear();
+        for tag in tags {
+            let tag = tag.as_ref().trim();
+            if !tag.is_empty() && !preset.has_tag(tag) {
+                preset.tags.push(tag.to_string());
+            }
+        }
+        Ok(())
+    }
+
+    /// Sets or clears the category of a preset.
+    pub fn set_category(&mut self, name: &str, category: Option<String>) -> Result<()> {
+        self.get_mut(name)?.category = category.filter(|c| !c.trim().is_empty());
+        Ok(())
+    }
+
+    /// Sets or clears the lightness curve of a preset.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Error::PresetNotFound`] if there is no such preset, or
+    /// [`Error::LightnessCurve`] if the curve is invalid.
+    pub fn set_lightness_curve(&mut self, name: &str, curve: Option<LightnessCurve>) -> Result<()> {
+        if let Some(curve) = &curve {
+            curve.validate()?;
+        }
+        self.get_mut(name)?.lightness_curve = curve;
+        Ok(())
+    }
+
+    /// Marks or unmarks a preset as a favorite.
+    pub fn set_favorite(&mut self, name: &str, favorite: bool) -> Result<()> {
+        self.get_mut(name)?.favorite = favorite;
+        Ok(())
+    }
+
+    /// Counts a use of the preset, e.g. after it was applied.
+    ///
+    /// Returns `false` if there is no such preset.
+    pub fn record_use(&mut self, name: &str) -> bool {
+        let Some(preset) = self.presets.get_mut(name) else {
+            return false;
+        };
+        preset.use_count += 1;
+        preset.last_used_at = Some(now_unix_secs());
+        true
+    }
+
+    /// Returns the presets matching `query`, in the requested order.
+    pub fn query(&self, query: &PresetQuery) -> Vec<&Preset> {
+        let mut presets: Vec<&Preset> = self.iter().filter(|p| query.matches(p)).collect();
+        match query.sort {
+            PresetSort::Name => {}
+            PresetSort::RecentlyUsed => {
+                presets.sort_by(|a, b| b.last_used_at.cmp(&a.last_used_at));
+            }
+            PresetSort::FrequentlyUsed => {
+                presets.sort_by(|a, b| b.use_count.cmp(&a.use_count));
+            }
+        }
+        if let Some(limit) = query.limit {
+            presets.truncate(limit);
+        }
+        presets
+    }
+
+    /// Returns every tag in use, sorted and without duplicates (ignoring case).
+    pub fn tags(&self) -> Vec<String> {
+        let mut tags: Vec<String> = Vec::new();
+        for tag in self.iter().flat_map(|p| &p.tags) {
+            if !tags.iter().any(|t| t.eq_ignore_ascii_case(tag)) {
+                tags.push(tag.clone());
+            }
+        }
+        tags.sort_by_key(|t| t.to_lowercase());
+        tags
+    }
+
+    /// Returns every category in use, sorted.
+    pub fn categories(&self) -> Vec<String> {
+        let mut categories: Vec<String> =
+            self.iter().filter_map(|p| p.category.clone()).collect();
+        categories.sort();
+        categories.dedup();
+        categories
+    }
+
+    fn get_mut(&mut self, name: &str) -> Result<&mut Preset> {
+        self.presets
+            .get_mut(name)
+            .ok_or_else(|| Error::PresetNotFound(name.to_string()))
+    }
+
+    /// Removes the preset with the given name, returning it if it existed.
+    pub fn remove(&mut self, name: &str) -> Option<Preset> {
+        self.presets.remove(name)
+    }
+
+    /// Returns all presets, sorted by name.
+    pub fn iter(&self) -> impl Iterator<Item = &Preset> {
+        self.presets.values()
+    }
+
+    /// Returns the number of presets.
+    pub fn len(&self) -> usize {
+        self.presets.len()
+    }
+
+    /// Returns `true` if there are no presets.
+    pub fn is_empty(&self) -> bool {
+        self.presets.is_empty()
+    }
+
+    /// Writes the changes since the last save.
+    ///
+    /// See [`ProfileStore::save`](crate::ProfileStore::save) for how changes
+    /// are journaled with the JSON backend.
+    pub fn save(&self) -> Result<()> {
+        save_entries(self.state.as_ref(), StateCollection::Presets, &self.presets)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    #[test]
+    fn test_insert_keeps_created_at() {
+        let temp = tempdir().unwrap();
+        let mut presets = PresetLibrary::in_data_dir(temp.path()).unwrap();
+
+        let created_at = presets.insert("Work", CustomizationProfile::default()).created_at;
+        let preset = presets.insert("Work", CustomizationProfile::default());
+        assert_eq!(preset.created_at, created_at);
+        assert_eq!(presets.len(), 1);
+    }
+
+    #[test]
+    fn test_insert_and_save() {
+        let temp = tempdir().unwrap();
+        let mut presets = PresetLibrary::in_data_dir(temp.path()).unwrap();
+        let preset = presets.insert_and_save("Work", CustomizationProfile::default()).unwrap();
+        assert_eq!(preset.name, "Work");
+
+        let reopened = PresetLibrary::in_data_dir(temp.path()).unwrap();
+        assert!(reopened.get("Work").is_some());
+    }
+
+    #[test]
+    fn test_insert_keeps_metadata() {
+        let temp = tempdir().unwrap();
+        let mut presets = PresetLibrary::in_data_dir(temp.path()).unwrap();
+        presets.insert("Work", CustomizationProfile::default());
+        presets.set_favorite("Work", true).unwrap();
+        presets.record_use("Work");
+
+        let preset = presets.insert("Work", CustomizationProfile::default());
+        assert!(preset.favorite);
+        assert_eq!(preset.use_count, 1);
+    }
+
+    #[test]
+    fn test_lightness_curve_is_validated() {
+        let temp = tempdir().unwrap();
+        let mut presets = PresetLibrary::in_data_dir(temp.path()).unwrap();
+        presets.insert("Snow", CustomizationProfile::default());
+        let curve = LightnessCurve::new(0.0, 0.6);
+        presets.set_lightness_curve("Snow", Some(curve)).unwrap();
+        assert!(presets
+            .set_lightness_curve("Snow", Some(LightnessCurve::new(2.0, 0.0)))
+            .is_err());
+
+        let preset = presets.insert("Snow", CustomizationProfile::default());
+        assert_eq!(preset.lightness_curve, Some(curve));
+    }
+
+    #[test]
+    fn test_tags_are_deduplicated() {
+        let temp = tempdir().unwrap();
+        let mut presets = PresetLibrary::in_data_dir(temp.path()).unwrap();
+        presets.insert("Work", CustomizationProfile::default());
+        presets.set_tags("Work", &["Client", " client ", "", "urgent"]).unwrap();
+
+        assert_eq!(presets.get("Work").unwrap().tags, vec!["Client", "urgent"]);
+        assert!(presets.set_tags("Nope", &["x"]).is_err());
+    }
+
+    #[test]
+    fn test_query() {
+        let temp = tempdir().unwrap();
+        let mut presets = PresetLibrary::in_data_dir(temp.path()).unwrap();
+        for name in ["Alpha", "Beta", "Gamma"] {
+            presets.insert(name, CustomizationProfile::default());
+        }
+        presets.set_tags("Beta", &["client"]).unwrap();
+        presets.set_tags("Gamma", &["Client"]).unwrap();
+        presets.set_favorite("Gamma", true).unwrap();
+        presets.record_use("Beta");
+        presets.record_use("Beta");
+        presets.record_use("Gamma");
+
+        let names = |query: &PresetQuery| -> Vec<String> {
+            presets.query(query).iter().map(|p| p.name.clone()).collect()
+        };
+        assert_eq!(names(&PresetQuery::new().with_tag("CLIENT")), ["Beta", "Gamma"]);
+        assert_eq!(names(&PresetQuery::new().favorites_only()), ["Gamma"]);
+        assert_eq!(
+            names(&PresetQuery::new().with_sort(PresetSort::FrequentlyUsed).with_limit(2)),
+            ["Beta", "Gamma"]
+        );
+        assert_eq!(names(&PresetQuery::new().with_text("ph")), ["Alpha"]);
+        assert_eq!(presets.tags(), vec!["client"]);
+    }
+
+    #[test]
+    fn test_missing_preset_is_an_error() {
+        let temp = tempdir().unwrap();
+        let presets = PresetLibrary::in_data_dir(temp.path()).unwrap();
+        assert!(matches!(presets.profile("Nope"), Err(Error::PresetNotFound(_))));
+    }
+
+    #[test]
+    fn test_save_and_reopen() {
+        let temp = tempdir().unwrap();
+        let mut presets = PresetLibrary::in_data_dir(temp.path()).unwrap();
+        presets.insert("Work", CustomizationProfile::default());
+        presets.save().unwrap();
+
+        let reopened = PresetLibrary::in_data_dir(temp.path()).unwrap();
+        assert!(reopened.get("Work").is_some());
+
+        // The file keeps the layout earlier versions wrote
+        let content = std::fs::read_to_string(reopened.path()).unwrap();
+        let file: serde_json::Value = serde_json::from_str(&content).unwrap();
+        assert_eq!(file["version"], 1);
+        assert_eq!(file["presets"]["Work"]["name"], "Work");
+    }
+}
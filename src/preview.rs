@@ -0,0 +1,270 @@
+//! Isolated customizers for previews.
+//!
+//! [`CustomizationContext::fork_for_preview`](crate::CustomizationContext::fork_for_preview)
+//! hands out a [`PreviewContext`]: it shares the context's base icons but
+//! has a customizer of its own, so a GUI can render two candidate profiles
+//! side by side, or on separate threads, without touching the layers of the
+//! main customizer. A preview can only render; it can't apply icons or
+//! change the profile store.
+//!
+//! Tools that have no use for a context of their own, such as a web service
+//! generating preview images, can call [`render_with_base`] with icons of
+//! their own.
+
+use crate::backend::{DefaultIconBackend, IconStyle};
+use crate::context::CustomizationContextBuilder;
+use crate::convert::{convert_icon_set_to_sys, convert_icon_set_with};
+use crate::error::{Error, Result};
+use crate::profile::profile_hash;
+use crate::sanitize::sanitized_or_default;
+use crate::warning::Warning;
+
+use folco_renderer::{CustomizationProfile, IconCustomizer, IconSet as RendererIconSet, RectPx};
+use icon_sys::IconSet as SysIconSet;
+use image::imageops::{self, FilterType};
+use image::RgbaImage;
+
+use std::collections::HashMap;
+use std::sync::Arc;
+
+/// A render-only handle with its own customizer, forked from a
+/// [`CustomizationContext`](crate::CustomizationContext).
+///
+/// Forking is cheap: the base icons are shared, and the customizer is only
+/// built on the first render.
+pub struct PreviewContext {
+    base: Arc<SysIconSet>,
+    style: IconStyle,
+    profile: CustomizationProfile,
+    customizer: Option<IconCustomizer>,
+}
+
+impl PreviewContext {
+    pub(crate) fn new(
+        base: Arc<SysIconSet>,
+        style: IconStyle,
+        profile: CustomizationProfile,
+    ) -> Self {
+        Self {
+            base,
+            style,
+            profile,
+            customizer: None,
+        }
+    }
+
+    /// Configures this preview's customizer with `profile`, with its
+    /// inline SVG decals sanitized.
+    ///
+    /// The context it was forked from is unaffected.
+    pub fn apply_profile(&mut self, profile: &CustomizationProfile) {
+        self.profile = sanitized_or_default(profile);
+        if let Some(customizer) = &mut self.customizer {
+            customizer.apply_profile(&self.profile);
+        }
+    }
+
+    /// Returns the profile this preview is configured with.
+    pub fn export_profile(&self) -> CustomizationProfile {
+        match &self.customizer {
+            Some(customizer) => customizer.export_profile(),
+            None => self.profile.clone(),
+        }
+    }
+
+    /// Returns this preview's customizer, e.g. to adjust individual layers.
+    pub fn customizer_mut(&mut self) -> &mut IconCustomizer {
+        let (base, style, profile) = (&self.base, &self.style, &self.profile);
+        self.customizer.get_or_insert_with(|| {
+            let mut customizer = IconCustomizer::new(style.icon_base(base));
+            customizer.apply_profile(profile);
+            customizer
+        })
+    }
+
+    /// Renders the icon set as this preview is configured, in
+    /// `folco-renderer` format.
+    pub fn render(&mut self) -> Result<RendererIconSet> {
+        Ok(self.customizer_mut().render_all()?)
+    }
+
+    /// Configures this preview with `profile` and renders it.
+    pub fn render_profile(&mut self, profile: &CustomizationProfile) -> Result<RendererIconSet> {
+        self.apply_profile(profile);
+        self.render()
+    }
+
+    /// Renders each of `profiles` at `size` pixels, like
+    /// [`CustomizationContext::render_profiles`](crate::CustomizationContext::render_profiles).
+    ///
+    /// This preview's own customizer is untouched.
+    pub fn render_profiles(
+        &self,
+        profiles: &[CustomizationProfile],
+        size: u32,
+    ) -> Result<Vec<RgbaImage>> {
+        render_profiles(&self.base, &self.style, profiles, size)
+    }
+}
+
+/// Renders `profile` on `base`, without setting up a context.
+///
+/// The profile is rendered as a context renders it for a folder, through a
+/// [render-only](CustomizationContextBuilder::render_only) context that
+/// keeps everything in memory: it's checked against the default
+/// [`PayloadLimits`](crate::PayloadLimits), its inline SVG decals are
+/// [sanitized](crate::sanitize_svg), and settings and sizes that fail are
+/// left out. The icons are tinted as this platform's folder icons are.
+///
+/// Returns the icons with the [`Warning`]s of the render, such as what was
+/// removed from the decals.
+///
+/// # Errors
+///
+/// Returns [`Error::PayloadLimit`] if the profile is over a limit, or the
+/// renderer's error if rendering fails.
+pub fn render_with_base(
+    base: RendererIconSet,
+    profile: &CustomizationProfile,
+) -> Result<(RendererIconSet, Vec<Warning>)> {
+    let base = Arc::new(ProvidedBase(base));
+    let mut context = CustomizationContextBuilder::new()
+        .with_default_icon_backend(base.clone())
+        .render_only()
+        .build()?;
+    let icons = context.render_finished(profile)?;
+    let icons = convert_icon_set_with(&icons, |width, height| base.content_bounds(width, height));
+    Ok((icons, context.take_warnings()))
+}
+
+/// Base icons handed to [`render_with_base`], with their content bounds.
+struct ProvidedBase(RendererIconSet);
+
+impl DefaultIconBackend for ProvidedBase {
+    fn default_folder_icon(&self) -> Result<SysIconSet> {
+        Ok(convert_icon_set_to_sys(&self.0))
+    }
+
+    fn content_bounds(&self, width: u32, height: u32) -> RectPx {
+        self.0
+            .iter()
+            .find(|image| image.data.width() == width && image.data.height() == height)
+            .map_or_else(
+                || crate::sys::get_folder_icon_content_bounds(width, height),
+                |image| image.content_bounds,
+            )
+    }
+}
+
+/// Renders each of `profiles` at `size` pixels from `base`.
+///
+/// Only the base image closest to `size` is converted and rendered, once
+/// for all profiles, and identical profiles are rendered once.
+pub(crate) fn render_profiles(
+    base: &SysIconSet,
+    style: &IconStyle,
+    profiles: &[CustomizationProfile],
+    size: u32,
+) -> Result<Vec<RgbaImage>> {
+    // The smallest image at least as large as `size`, so it's only ever
+    // scaled down; otherwise the largest
+    let source = base
+        .images
+        .iter()
+        .filter(|image| image.data.width() >= size)
+        .min_by_key(|image| image.data.width())
+        .or_else(|| base.images.iter().max_by_key(|image| image.data.width()))
+        .ok_or_else(|| Error::NotInitialized("no base icons to render".to_string()))?;
+    let single = SysIconSet {
+        images: vec![icon_sys::IconImage {
+            data: source.data.clone(),
+        }],
+    };
+    let mut customizer = IconCustomizer::new(style.icon_base(&single));
+
+    let mut rendered: HashMap<String, RgbaImage> = HashMap::new();
+    profiles
+        .iter()
+        .map(|profile| {
+            let hash = profile_hash(profile);
+            if let Some(image) = rendered.get(&hash) {
+                return Ok(image.clone());
+            }
+            customizer.apply_profile(&sanitized_or_default(profile));
+            let icons = customizer.render_all()?;
+            let image = icons
+                .iter()
+                .next()
+                .map(|image| image.data.clone())
+                .ok_or_else(|| {
+                    Error::NotInitialized("the renderer returned no images".to_string())
+                })?;
+            let image = if image.width() == size && image.height() == size {
+                image
+            } else {
+                imageops::resize(&image, size, size, FilterType::Lanczos3)
+            };
+            rendered.insert(hash, image.clone());
+            Ok(image)
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::color::FolderColor;
+    use crate::convert::convert_icon_set;
+    use crate::profile::{profile_color, profile_with_color, profile_with_decal};
+
+    use folco_renderer::DecalSettings;
+
+    #[test]
+    fn test_render_profiles_needs_base_icons() {
+        let base = SysIconSet { images: Vec::new() };
+        let profiles = [CustomizationProfile::default()];
+        assert!(matches!(
+            render_profiles(&base, &IconStyle::default(), &profiles, 32),
+            Err(Error::NotInitialized(_))
+        ));
+    }
+
+    #[test]
+    fn test_render_with_base() {
+        let base = crate::fixtures::fixture_icon_set(crate::fixtures::FixturePlatform::Windows);
+        let red = profile_with_color(&CustomizationProfile::default(), FolderColor::Red).unwrap();
+        let (rendered, warnings) = render_with_base(convert_icon_set(&base), &red).unwrap();
+        assert_eq!(rendered.iter().count(), base.images.len());
+        assert!(warnings.is_empty());
+    }
+
+    #[test]
+    fn test_render_with_base_reports_sanitized_decals() {
+        let base = crate::fixtures::fixture_icon_set(crate::fixtures::FixturePlatform::Windows);
+        let decal: DecalSettings = serde_json::from_value(serde_json::json!({
+            "source": { "svg": "<svg onclick=\"x()\"><circle r=\"4\"/></svg>" }
+        }))
+        .unwrap();
+        let profile = profile_with_decal(&CustomizationProfile::default(), Some(&decal)).unwrap();
+
+        let (_, warnings) = render_with_base(convert_icon_set(&base), &profile).unwrap();
+        assert!(matches!(warnings.as_slice(), [Warning::SanitizedSvg { .. }]));
+    }
+
+    #[test]
+    fn test_profile_is_kept_until_first_render() {
+        let base = Arc::new(SysIconSet { images: Vec::new() });
+        let style = IconStyle::default();
+        let mut preview =
+            PreviewContext::new(Arc::clone(&base), style, CustomizationProfile::default());
+        let red = profile_with_color(&CustomizationProfile::default(), FolderColor::Red).unwrap();
+        preview.apply_profile(&red);
+
+        assert!(preview.customizer.is_none());
+        assert_eq!(
+            profile_color(&preview.export_profile()),
+            Some(FolderColor::Red)
+        );
+        assert_eq!(Arc::strong_count(&base), 2);
+    }
+}
@@ -0,0 +1,553 @@
+//! Running folder operations in an elevated helper process.
+//!
+//! Some folders (system locations, other users' folders, protected network
+//! shares) can only be customized with elevated rights. Rather than failing
+//! each of them, the context hands them to a [`PrivilegedExecutor`]. The
+//! usual executor is a [`HelperProcess`]: a copy of the app started once
+//! with elevation (UAC, `pkexec`, an authorization prompt) that runs
+//! [`serve_helper`] and takes requests over stdio.
+//!
+//! The protocol is one JSON message per line. The client opens with
+//! [`HelperRequest::Hello`]; every request after that gets exactly one
+//! response carrying the request's ID.
+
+use crate::backend::{FolderIconBackend, PlatformFolderBackend};
+use crate::error::{Error, Result};
+
+use icon_sys::IconSet as SysIconSet;
+use image::ImageFormat;
+use serde::{Deserialize, Serialize};
+
+use std::ffi::OsString;
+use std::io::{BufRead, BufReader, Cursor, Write};
+use std::path::{Path, PathBuf};
+use std::process::{Child, ChildStdin, ChildStdout, Command, Stdio};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+
+/// Version of the helper protocol. The client and helper must agree on it.
+pub const HELPER_PROTOCOL_VERSION: u32 = 1;
+
+/// Performs folder operations that need elevated rights.
+pub trait PrivilegedExecutor: Send + Sync {
+    /// Applies `icons` to `folder`.
+    fn set_icon(&self, folder: &Path, icons: &SysIconSet) -> Result<()>;
+
+    /// Resets `folder` to the default icon.
+    fn reset_icon(&self, folder: &Path) -> Result<()>;
+}
+
+/// A message from the client to the helper.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(
+    tag = "type",
+    rename_all = "kebab-case",
+    rename_all_fields = "camelCase"
+)]
+pub enum HelperRequest {
+    /// Opens the session.
+    Hello {
+        /// The client's protocol version.
+        version: u32,
+    },
+    /// Applies an icon to a folder.
+    SetIcon {
+        /// Request ID, echoed in the response.
+        id: u64,
+        /// The folder to customize.
+        folder: PathBuf,
+        /// PNG-encoded images of the icon set.
+        images: Vec<Vec<u8>>,
+    },
+    /// Resets a folder to the default icon.
+    ResetIcon {
+        /// Request ID, echoed in the response.
+        id: u64,
+        /// The folder to reset.
+        folder: PathBuf,
+    },
+    /// Ends the session; the helper exits.
+    Shutdown,
+}
+
+/// A message from the helper to the client.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(
+    tag = "type",
+    rename_all = "kebab-case",
+    rename_all_fields = "camelCase"
+)]
+pub enum HelperResponse {
+    /// Accepts the session.
+    Hello {
+        /// The helper's protocol version.
+        version: u32,
+    },
+    /// The request succeeded.
+    Done {
+        /// ID of the request.
+        id: u64,
+    },
+    /// The request failed.
+    Failed {
+        /// ID of the request, or 0 if it couldn't be parsed.
+        id: u64,
+        /// Description of the failure.
+        error: String,
+    },
+}
+
+/// Applies folder operations directly with a [`FolderIconBackend`].
+///
+/// This is what [`serve_helper`] runs inside the elevated process.
+pub struct DirectExecutor {
+    backend: Arc<dyn FolderIconBackend>,
+}
+
+impl DirectExecutor {
+    /// Creates an executor using the platform's folder backend.
+    pub fn new() -> Self {
+        Self::with_backend(Arc::new(PlatformFolderBackend::new()))
+    }
+
+    /// Creates an executor applying folder operations with `backend`.
+    pub fn with_backend(backend: Arc<dyn FolderIconBackend>) -> Self {
+        Self { backend }
+    }
+}
+
+impl Default for DirectExecutor {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl std::fmt::Debug for DirectExecutor {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("DirectExecutor").finish_non_exhaustive()
+    }
+}
+
+impl PrivilegedExecutor for DirectExecutor {
+    fn set_icon(&self, folder: &Path, icons: &SysIconSet) -> Result<()> {
+        self.backend.set_icon(folder, icons)
+    }
+
+    fn reset_icon(&self, folder: &Path) -> Result<()> {
+        self.backend.reset_icon(folder)
+    }
+}
+
+/// Serves helper requests from `input`, answering on `output`, until the
+/// client shuts the session down or closes `input`.
+///
+/// Call this from the app's entry point when it's started as a helper, e.g.
+/// with a `--folco-helper` argument.
+///
+/// # Errors
+///
+/// Fails if the streams fail, the session doesn't open with a compatible
+/// [`HelperRequest::Hello`], or a message isn't valid JSON.
+pub fn serve_helper<R: BufRead, W: Write>(
+    input: R,
+    mut output: W,
+    executor: &dyn PrivilegedExecutor,
+) -> Result<()> {
+    let mut lines = input.lines();
+
+    let hello = match lines.next() {
+        Some(line) => parse_message(&line?)?,
+        None => return Ok(()),
+    };
+    match hello {
+        HelperRequest::Hello { version } if version == HELPER_PROTOCOL_VERSION => {}
+        HelperRequest::Hello { version } => {
+            return Err(Error::Helper(format!(
+                "client speaks protocol version {version}, helper speaks {HELPER_PROTOCOL_VERSION}"
+            )));
+        }
+        _ => return Err(Error::Helper("session must open with hello".to_string())),
+    }
+    write_message(
+        &mut output,
+        &HelperResponse::Hello {
+            version: HELPER_PROTOCOL_VERSION,
+        },
+    )?;
+
+    for line in lines {
+        let (id, result) = match parse_message(&line?)? {
+            HelperRequest::Shutdown => return Ok(()),
+            HelperRequest::Hello { .. } => {
+                (0, Err(Error::Helper("session is already open".to_string())))
+            }
+            HelperRequest::SetIcon { id, folder, images } => (
+                id,
+                decode_icons(&images).and_then(|icons| executor.set_icon(&folder, &icons)),
+            ),
+            HelperRequest::ResetIcon { id, folder } => (id, executor.reset_icon(&folder)),
+        };
+        let response = match result {
+            Ok(()) => HelperResponse::Done { id },
+            Err(e) => HelperResponse::Failed {
+                id,
+                error: e.to_string(),
+            },
+        };
+        write_message(&mut output, &response)?;
+    }
+    Ok(())
+}
+
+/// A helper process, started on first use and reused afterwards.
+///
+/// Starting the helper is what prompts the user for elevation, so it's
+/// deferred until a folder actually needs it and then kept running for the
+/// rest of the session. If the helper dies, the next request starts a new
+/// one.
+///
+/// # Example
+///
+/// ```ignore
+/// // Windows: a launcher that re-runs the app elevated via ShellExecute "runas"
+/// let helper = HelperProcess::new("folco-elevate.exe").with_arg("--folco-helper");
+/// let ctx = CustomizationContextBuilder::new()
+///     .with_privileged_executor(Arc::new(helper))
+///     .build()?;
+/// ```
+#[derive(Debug)]
+pub struct HelperProcess {
+    program: PathBuf,
+    args: Vec<OsString>,
+    connection: Mutex<Option<Connection>>,
+    next_id: AtomicU64,
+}
+
+#[derive(Debug)]
+struct Connection {
+    child: Child,
+    stdin: ChildStdin,
+    stdout: BufReader<ChildStdout>,
+}
+
+impl HelperProcess {
+    /// Creates a helper started by running `program`.
+    pub fn new(program: impl Into<PathBuf>) -> Self {
+        Self {
+            program: program.into(),
+            args: Vec::new(),
+            connection: Mutex::new(None),
+            next_id: AtomicU64::new(1),
+        }
+    }
+
+    /// Adds an argument to pass to the program.
+    pub fn with_arg(mut self, arg: impl Into<OsString>) -> Self {
+        self.args.push(arg.into());
+        self
+    }
+
+    /// Returns `true` if the helper process is running.
+    pub fn is_running(&self) -> bool {
+        self.lock().is_some()
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, Option<Connection>> {
+        self.connection.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    fn spawn(&self) -> Result<Connection> {
+        let mut child = Command::new(&self.program)
+            .args(&self.args)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .spawn()
+            .map_err(|e| {
+                Error::Helper(format!("failed to start {}: {e}", self.program.display()))
+            })?;
+        let (Some(stdin), Some(stdout)) = (child.stdin.take(), child.stdout.take()) else {
+            return Err(Error::Helper("helper has no stdio".to_string()));
+        };
+
+        let mut connection = Connection {
+            child,
+            stdin,
+            stdout: BufReader::new(stdout),
+        };
+        write_message(
+            &mut connection.stdin,
+            &HelperRequest::Hello {
+                version: HELPER_PROTOCOL_VERSION,
+            },
+        )?;
+        match read_response(&mut connection.stdout)? {
+            HelperResponse::Hello { version } if version == HELPER_PROTOCOL_VERSION => {
+                Ok(connection)
+            }
+            HelperResponse::Hello { version } => Err(Error::Helper(format!(
+                "helper speaks protocol version {version}, expected {HELPER_PROTOCOL_VERSION}"
+            ))),
+            other => Err(Error::Helper(format!(
+                "unexpected handshake response: {other:?}"
+            ))),
+        }
+    }
+
+    fn request(&self, make: impl FnOnce(u64) -> HelperRequest) -> Result<()> {
+        let id = self.next_id.fetch_add(1, Ordering::Relaxed);
+        let request = make(id);
+
+        let mut guard = self.lock();
+        let connection = match guard.take() {
+            Some(connection) => connection,
+            None => self.spawn()?,
+        };
+        let connection = guard.insert(connection);
+
+        let response = write_message(&mut connection.stdin, &request)
+            .and_then(|()| read_response(&mut connection.stdout));
+        let response = match response {
+            Ok(response) => response,
+            Err(e) => {
+                // Start afresh next time
+                *guard = None;
+                return Err(e);
+            }
+        };
+        match response {
+            HelperResponse::Done { id: done } if done == id => Ok(()),
+            HelperResponse::Failed { id: failed, error } if failed == id => {
+                Err(Error::Helper(error))
+            }
+            other => {
+                *guard = None;
+                Err(Error::Helper(format!("unexpected response: {other:?}")))
+            }
+        }
+    }
+}
+
+impl PrivilegedExecutor for HelperProcess {
+    fn set_icon(&self, folder: &Path, icons: &SysIconSet) -> Result<()> {
+        let images = encode_icons(icons)?;
+        self.request(|id| HelperRequest::SetIcon {
+            id,
+            folder: folder.to_path_buf(),
+            images,
+        })
+    }
+
+    fn reset_icon(&self, folder: &Path) -> Result<()> {
+        self.request(|id| HelperRequest::ResetIcon {
+            id,
+            folder: folder.to_path_buf(),
+        })
+    }
+}
+
+impl Drop for HelperProcess {
+    fn drop(&mut self) {
+        if let Some(mut connection) = self.lock().take() {
+            let _ = write_message(&mut connection.stdin, &HelperRequest::Shutdown);
+            drop(connection.stdin);
+            let _ = connection.child.wait();
+        }
+    }
+}
+
+/// Returns `true` if a failed folder operation looks like it was refused for
+/// lack of rights, and might succeed elevated.
+///
+/// Platform errors reach folco as text, so this looks for the phrases the
+/// platforms use for permission failures.
+pub(crate) fn needs_elevation(error: &Error) -> bool {
+    let message = match error {
+        Error::Io(e) => return e.kind() == std::io::ErrorKind::PermissionDenied,
+        Error::FolderCustomization(_, message) | Error::FolderReset(_, message) => message,
+        _ => return false,
+    };
+    let message = message.to_lowercase();
+    [
+        "permission denied",
+        "access is denied",
+        "operation not permitted",
+        "elevation",
+    ]
+    .iter()
+    .any(|phrase| message.contains(phrase))
+}
+
+fn encode_icons(icons: &SysIconSet) -> Result<Vec<Vec<u8>>> {
+    icons
+        .images
+        .iter()
+        .map(|image| {
+            let mut png = Vec::new();
+            image
+                .data
+                .write_to(&mut Cursor::new(&mut png), ImageFormat::Png)?;
+            Ok(png)
+        })
+        .collect()
+}
+
+fn decode_icons(images: &[Vec<u8>]) -> Result<SysIconSet> {
+    let images = images
+        .iter()
+        .map(|png| {
+            let data = image::load_from_memory_with_format(png, ImageFormat::Png)?;
+            Ok(icon_sys::IconImage { data })
+        })
+        .collect::<Result<Vec<_>>>()?;
+    Ok(SysIconSet { images })
+}
+
+fn parse_message<T: for<'de> Deserialize<'de>>(line: &str) -> Result<T> {
+    serde_json::from_str(line).map_err(|e| Error::Helper(format!("invalid message: {e}")))
+}
+
+fn write_message<W: Write, T: Serialize>(output: &mut W, message: &T) -> Result<()> {
+    let json = serde_json::to_string(message).map_err(|e| Error::Serialization(e.to_string()))?;
+    writeln!(output, "{json}")?;
+    output.flush()?;
+    Ok(())
+}
+
+fn read_response<R: BufRead>(input: &mut R) -> Result<HelperResponse> {
+    let mut line = String::new();
+    if input.read_line(&mut line)? == 0 {
+        return Err(Error::Helper("helper exited".to_string()));
+    }
+    parse_message(line.trim_end())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use image::{DynamicImage, RgbaImage};
+
+    /// Records the folders it was asked to change, and refuses `/denied`.
+    #[derive(Default)]
+    struct Recorder {
+        calls: Mutex<Vec<String>>,
+    }
+
+    impl PrivilegedExecutor for Recorder {
+        fn set_icon(&self, folder: &Path, icons: &SysIconSet) -> Result<()> {
+            if folder == Path::new("/denied") {
+                return Err(Error::FolderCustomization(
+                    folder.to_path_buf(),
+                    "nope".to_string(),
+                ));
+            }
+            let width = icons.images[0].data.width();
+            self.calls
+                .lock()
+                .unwrap()
+                .push(format!("set {} {width}", folder.display()));
+            Ok(())
+        }
+
+        fn reset_icon(&self, folder: &Path) -> Result<()> {
+            self.calls
+                .lock()
+                .unwrap()
+                .push(format!("reset {}", folder.display()));
+            Ok(())
+        }
+    }
+
+    fn request_lines(requests: &[HelperRequest]) -> String {
+        requests
+            .iter()
+            .map(|request| serde_json::to_string(request).unwrap() + "\n")
+            .collect()
+    }
+
+    #[test]
+    fn test_serve_helper_session() {
+        let icons = SysIconSet {
+            images: vec![icon_sys::IconImage {
+                data: DynamicImage::ImageRgba8(RgbaImage::new(32, 32)),
+            }],
+        };
+        let input = request_lines(&[
+            HelperRequest::Hello {
+                version: HELPER_PROTOCOL_VERSION,
+            },
+            HelperRequest::SetIcon {
+                id: 1,
+                folder: PathBuf::from("/srv/shared"),
+                images: encode_icons(&icons).unwrap(),
+            },
+            HelperRequest::SetIcon {
+                id: 2,
+                folder: PathBuf::from("/denied"),
+                images: encode_icons(&icons).unwrap(),
+            },
+            HelperRequest::ResetIcon {
+                id: 3,
+                folder: PathBuf::from("/srv/shared"),
+            },
+            HelperRequest::Shutdown,
+        ]);
+
+        let recorder = Recorder::default();
+        let mut output = Vec::new();
+        serve_helper(input.as_bytes(), &mut output, &recorder).unwrap();
+
+        let responses: Vec<HelperResponse> = String::from_utf8(output)
+            .unwrap()
+            .lines()
+            .map(|line| serde_json::from_str(line).unwrap())
+            .collect();
+        assert_eq!(
+            responses[0],
+            HelperResponse::Hello {
+                version: HELPER_PROTOCOL_VERSION
+            }
+        );
+        assert_eq!(responses[1], HelperResponse::Done { id: 1 });
+        assert!(matches!(responses[2], HelperResponse::Failed { id: 2, .. }));
+        assert_eq!(responses[3], HelperResponse::Done { id: 3 });
+        assert_eq!(
+            *recorder.calls.lock().unwrap(),
+            vec!["set /srv/shared 32", "reset /srv/shared"]
+        );
+    }
+
+    #[test]
+    fn test_serve_helper_rejects_other_versions() {
+        let input = request_lines(&[HelperRequest::Hello { version: 99 }]);
+        let result = serve_helper(input.as_bytes(), Vec::new(), &Recorder::default());
+        assert!(matches!(result, Err(Error::Helper(_))));
+    }
+
+    #[test]
+    fn test_needs_elevation() {
+        let denied = Error::FolderCustomization(
+            PathBuf::from("C:\\Windows"),
+            "Access is denied. (0x80070005)".to_string(),
+        );
+        assert!(needs_elevation(&denied));
+
+        let missing = Error::FolderReset(
+            PathBuf::from("/gone"),
+            "No such file or directory".to_string(),
+        );
+        assert!(!needs_elevation(&missing));
+        assert!(needs_elevation(&Error::Io(
+            std::io::ErrorKind::PermissionDenied.into()
+        )));
+    }
+
+    #[test]
+    fn test_wire_format() {
+        let json = serde_json::to_string(&HelperRequest::ResetIcon {
+            id: 7,
+            folder: PathBuf::from("/x"),
+        })
+        .unwrap();
+        assert_eq!(json, r#"{"type":"reset-icon","id":7,"folder":"/x"}"#);
+    }
+}
@@ -0,0 +1,395 @@
+//! Helpers for working with customization profiles.
+
+use crate::color::{FolderColor, TargetColor};
+use crate::error::{Error, Result};
+
+use folco_renderer::{CustomizationProfile, DecalSettings, HslMutationSettings};
+use serde_json::Value;
+
+/// Returns a stable identifier for a profile's settings.
+///
+/// Two profiles with identical settings always produce the same hash, across
+/// runs and machines, so the hash can be persisted and compared later.
+pub fn profile_hash(profile: &CustomizationProfile) -> String {
+    // Serialization of a profile can't fail: it contains no maps with
+    // non-string keys
+    let json = serde_json::to_string(profile).unwrap_or_default();
+    format!("{:016x}", fnv1a_64(json.as_bytes()))
+}
+
+/// Layers `overlay` on top of `base`.
+///
+/// Settings present in `overlay` win; settings it leaves unset are kept from
+/// `base`. This is how a new profile is merged into a folder's existing
+/// customization.
+pub fn merge_profiles(
+    base: &CustomizationProfile,
+    overlay: &CustomizationProfile,
+) -> Result<CustomizationProfile> {
+    let mut merged = to_value(base)?;
+    merge_json(&mut merged, to_value(overlay)?);
+    serde_json::from_value(merged).map_err(|e| Error::Serialization(e.to_string()))
+}
+
+fn to_value(profile: &CustomizationProfile) -> Result<Value> {
+    serde_json::to_value(profile).map_err(|e| Error::Serialization(e.to_string()))
+}
+
+/// Recursively merges `overlay` into `base`, skipping `null` overlay values.
+fn merge_json(base: &mut Value, overlay: Value) {
+    match (base, overlay) {
+        (_, Value::Null) => {}
+        (Value::Object(base), Value::Object(overlay)) => {
+            for (key, value) in overlay {
+                match base.get_mut(&key) {
+                    Some(existing) => merge_json(existing, value),
+                    None => {
+                        base.insert(key, value);
+                    }
+                }
+            }
+        }
+        (base, overlay) => *base = overlay,
+    }
+}
+
+/// Returns `profile` recolored with the given color preset.
+///
+/// The rest of the profile, such as decals and overlays, is kept.
+pub fn profile_with_color(
+    profile: &CustomizationProfile,
+    color: FolderColor,
+) -> Result<CustomizationProfile> {
+    profile_with_hsl(profile, &color.to_hsl_mutation_settings())
+}
+
+/// Returns `profile` recolored to `target`, such as a color from a
+/// [`UserPalette`](crate::UserPalette) or an OKLCH color.
+pub fn profile_with_target(
+    profile: &CustomizationProfile,
+    target: &TargetColor,
+) -> Result<CustomizationProfile> {
+    profile_with_hsl(profile, &target.to_hsl_mutation_settings())
+}
+
+/// Returns `profile` recolored with arbitrary HSL settings.
+pub(crate) fn profile_with_hsl(
+    profile: &CustomizationProfile,
+    settings: &HslMutationSettings,
+) -> Result<CustomizationProfile> {
+    let settings =
+        serde_json::to_value(settings).map_err(|e| Error::Serialization(e.to_string()))?;
+    replace_setting(profile, "hsl", "HSL mutation", settings)
+}
+
+/// Returns `profile` with its decal replaced by `decal`, or removed.
+pub(crate) fn profile_with_decal(
+    profile: &CustomizationProfile,
+    decal: Option<&DecalSettings>,
+) -> Result<CustomizationProfile> {
+    let decal = serde_json::to_value(decal).map_err(|e| Error::Serialization(e.to_string()))?;
+    replace_setting(profile, "decal", "decal", decal)
+}
+
+/// Returns `profile` with the top-level settings whose names contain `key`
+/// reset to their defaults.
+pub(crate) fn profile_without(
+    profile: &CustomizationProfile,
+    key: &str,
+) -> Result<CustomizationProfile> {
+    let key = key.to_ascii_lowercase();
+    let mut value = to_value(profile)?;
+    if let Some(object) = value.as_object_mut() {
+        object.retain(|name, _| !name.to_ascii_lowercase().contains(&key));
+    }
+    serde_json::from_value(value).map_err(|e| Error::Serialization(e.to_string()))
+}
+
+/// Returns the names of a profile's top-level settings that differ from the
+/// defaults, other than its HSL mutation.
+///
+/// These are the settings a render can do without: leaving one out still
+/// gives a recolored folder, while leaving out the color doesn't give the
+/// user anything they asked for.
+pub(crate) fn profile_optional_settings(profile: &CustomizationProfile) -> Vec<String> {
+    let (Ok(Value::Object(object)), Ok(defaults)) =
+        (to_value(profile), to_value(&CustomizationProfile::default()))
+    else {
+        return Vec::new();
+    };
+    object
+        .into_iter()
+        .filter(|(name, value)| {
+            !name.to_ascii_lowercase().contains("hsl") && defaults.get(name) != Some(value)
+        })
+        .map(|(name, _)| name)
+        .collect()
+}
+
+/// Returns `profile` with the top-level settings named in `names` reset to
+/// their defaults.
+pub(crate) fn profile_without_settings(
+    profile: &CustomizationProfile,
+    names: &[String],
+) -> Result<CustomizationProfile> {
+    let mut value = to_value(profile)?;
+    if let Some(object) = value.as_object_mut() {
+        object.retain(|name, _| !names.contains(name));
+    }
+    serde_json::from_value(value).map_err(|e| Error::Serialization(e.to_string()))
+}
+
+/// Replaces the top-level setting whose name contains `key`.
+///
+/// Settings are located by name so this doesn't depend on the field being
+/// set: unset settings serialize as null.
+fn replace_setting(
+    profile: &CustomizationProfile,
+    key: &str,
+    what: &str,
+    setting: Value,
+) -> Result<CustomizationProfile> {
+    let mut value = to_value(profile)?;
+    let slot = value.as_object_mut().and_then(|object| {
+        object
+            .iter_mut()
+            .find(|(name, _)| name.to_ascii_lowercase().contains(key))
+            .map(|(_, slot)| slot)
+    });
+    match slot {
+        // Profiles holding a list of decals get a list of one
+        Some(Value::Array(items)) if !setting.is_null() => *items = vec![setting],
+        Some(Value::Array(items)) => items.clear(),
+        Some(slot) => *slot = setting,
+        None => {
+            return Err(Error::Serialization(format!("profile has no {what} settings")));
+        }
+    }
+    serde_json::from_value(value).map_err(|e| Error::Serialization(e.to_string()))
+}
+
+/// Returns a profile's HSL mutation settings, if it has any set.
+pub(crate) fn profile_hsl(profile: &CustomizationProfile) -> Option<HslMutationSettings> {
+    let value = to_value(profile).ok()?;
+    let (_, settings) = value
+        .as_object()?
+        .iter()
+        .find(|(name, _)| name.to_ascii_lowercase().contains("hsl"))?;
+    serde_json::from_value(settings.clone()).ok()
+}
+
+/// Returns the color preset a profile applies, if it uses one.
+pub(crate) fn profile_color(profile: &CustomizationProfile) -> Option<FolderColor> {
+    let value = serde_json::to_value(profile).ok()?;
+    let mut color = None;
+    visit_objects(&value, &mut |object| {
+        let settings = serde_json::from_value::<HslMutationSettings>(object.clone()).ok();
+        color = color.or_else(|| settings.and_then(|s| FolderColor::from_hsl_mutation_settings(&s)));
+        color.is_none()
+    });
+    color
+}
+
+/// Returns the text of a profile's decals, such as emoji and icon names.
+///
+/// Used for searching; the strings are whatever the decal settings
+/// serialize to, so callers should match loosely.
+pub(crate) fn profile_glyphs(profile: &CustomizationProfile) -> Vec<String> {
+    let Ok(value) = serde_json::to_value(profile) else {
+        return Vec::new();
+    };
+    let mut glyphs = Vec::new();
+    visit_objects(&value, &mut |object| {
+        if serde_json::from_value::<DecalSettings>(object.clone()).is_ok() {
+            collect_strings(object, &mut glyphs);
+            return false;
+        }
+        true
+    });
+    glyphs
+}
+
+/// Returns `true` if any of a profile's decals uses a custom SVG.
+pub(crate) fn profile_uses_svg(profile: &CustomizationProfile) -> bool {
+    let Ok(value) = serde_json::to_value(profile) else {
+        return false;
+    };
+    let mut found = false;
+    visit_objects(&value, &mut |object| {
+        if serde_json::from_value::<DecalSettings>(object.clone()).is_ok() {
+            found |= mentions_svg(object);
+            return false;
+        }
+        !found
+    });
+    found
+}
+
+/// Returns the settings of each of a profile's decals, as JSON.
+pub(crate) fn profile_decals(profile: &CustomizationProfile) -> Vec<Value> {
+    let Ok(value) = serde_json::to_value(profile) else {
+        return Vec::new();
+    };
+    let mut decals = Vec::new();
+    visit_objects(&value, &mut |object| {
+        if serde_json::from_value::<DecalSettings>(object.clone()).is_ok() {
+            decals.push(object.clone());
+            return false;
+        }
+        true
+    });
+    decals
+}
+
+/// Returns `true` if `value` has an SVG key, such as an `svg` decal source,
+/// or a string naming an `.svg` file.
+fn mentions_svg(value: &Value) -> bool {
+    match value {
+        Value::String(s) => s.to_ascii_lowercase().ends_with(".svg"),
+        Value::Array(items) => items.iter().any(mentions_svg),
+        Value::Object(map) => map
+            .iter()
+            .any(|(key, item)| key.to_ascii_lowercase().contains("svg") || mentions_svg(item)),
+        _ => false,
+    }
+}
+
+/// Calls `visit` for every object in `value`, depth first.
+///
+/// Children of an object are only visited while `visit` returns `true`.
+fn visit_objects(value: &Value, visit: &mut impl FnMut(&Value) -> bool) {
+    match value {
+        Value::Object(map) => {
+            if visit(value) {
+                for child in map.values() {
+                    visit_objects(child, visit);
+                }
+            }
+        }
+        Value::Array(items) => {
+            for item in items {
+                visit_objects(item, visit);
+            }
+        }
+        _ => {}
+    }
+}
+
+fn collect_strings(value: &Value, out: &mut Vec<String>) {
+    match value {
+        Value::String(s) => out.push(s.clone()),
+        Value::Array(items) => items.iter().for_each(|item| collect_strings(item, out)),
+        Value::Object(map) => map.values().for_each(|item| collect_strings(item, out)),
+        _ => {}
+    }
+}
+
+/// 64-bit FNV-1a hash. Used instead of `DefaultHasher`, whose output is not
+/// guaranteed to be stable between Rust releases.
+pub(crate) fn fnv1a_64(bytes: &[u8]) -> u64 {
+    const OFFSET_BASIS: u64 = 0xcbf2_9ce4_8422_2325;
+    const PRIME: u64 = 0x0000_0100_0000_01b3;
+
+    bytes.iter().fold(OFFSET_BASIS, |hash, &byte| {
+        (hash ^ u64::from(byte)).wrapping_mul(PRIME)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_fnv1a_known_values() {
+        assert_eq!(fnv1a_64(b""), 0xcbf2_9ce4_8422_2325);
+        assert_eq!(fnv1a_64(b"a"), 0xaf63_dc4c_8601_ec8c);
+    }
+
+    #[test]
+    fn test_profile_hash_is_stable() {
+        let profile = CustomizationProfile::default();
+        assert_eq!(profile_hash(&profile), profile_hash(&profile.clone()));
+        assert_eq!(profile_hash(&profile).len(), 16);
+    }
+
+    #[test]
+    fn test_merge_json_overlay_wins() {
+        let mut base = json!({ "hsl": { "hue": 10, "enabled": true }, "decal": { "glyph": "x" } });
+        merge_json(&mut base, json!({ "hsl": { "hue": 200 }, "decal": null }));
+
+        assert_eq!(
+            base,
+            json!({ "hsl": { "hue": 200, "enabled": true }, "decal": { "glyph": "x" } })
+        );
+    }
+
+    #[test]
+    fn test_collect_strings() {
+        let mut out = Vec::new();
+        collect_strings(&json!({ "source": { "emoji": "🚀" }, "scale": 0.5, "tags": ["a"] }), &mut out);
+        out.sort();
+        assert_eq!(out, vec!["a".to_string(), "🚀".to_string()]);
+    }
+
+    #[test]
+    fn test_mentions_svg() {
+        assert!(mentions_svg(&json!({ "source": { "svg": "<svg/>" } })));
+        assert!(mentions_svg(&json!({ "source": { "image": "logo.SVG" } })));
+        assert!(!mentions_svg(&json!({ "source": { "emoji": "🚀" } })));
+        assert!(!profile_uses_svg(&CustomizationProfile::default()));
+    }
+
+    #[test]
+    fn test_default_profile_has_no_color() {
+        assert_eq!(profile_color(&CustomizationProfile::default()), None);
+    }
+
+    #[test]
+    fn test_profile_with_color() {
+        let profile = profile_with_color(&CustomizationProfile::default(), FolderColor::Teal).unwrap();
+        assert_eq!(profile_color(&profile), Some(FolderColor::Teal));
+    }
+
+    #[test]
+    fn test_profile_with_target() {
+        let target = FolderColor::Teal.target(crate::color::ColorSpace::Hsl);
+        let teal = profile_with_target(&CustomizationProfile::default(), &target).unwrap();
+        assert_eq!(profile_color(&teal), Some(FolderColor::Teal));
+    }
+
+    #[test]
+    fn test_profile_with_hsl_keeps_custom_color() {
+        let mut settings = FolderColor::Teal.to_hsl_mutation_settings();
+        settings.target_hue += 5.0;
+        let profile = profile_with_hsl(&CustomizationProfile::default(), &settings).unwrap();
+        assert_eq!(profile_color(&profile), None);
+        assert_ne!(profile_hash(&profile), profile_hash(&CustomizationProfile::default()));
+    }
+
+    #[test]
+    fn test_optional_settings_skip_color_and_defaults() {
+        let default = CustomizationProfile::default();
+        assert!(profile_optional_settings(&default).is_empty());
+
+        let colored = profile_with_color(&default, FolderColor::Teal).unwrap();
+        assert!(profile_optional_settings(&colored).is_empty());
+
+        let decal: DecalSettings =
+            serde_json::from_value(json!({ "source": { "emoji": "🚀" } })).unwrap();
+        let decorated = profile_with_decal(&colored, Some(&decal)).unwrap();
+        let optional = profile_optional_settings(&decorated);
+        assert_eq!(optional.len(), 1);
+        assert!(optional[0].to_ascii_lowercase().contains("decal"));
+
+        let stripped = profile_without_settings(&decorated, &optional).unwrap();
+        assert_eq!(profile_hash(&stripped), profile_hash(&colored));
+    }
+
+    #[test]
+    fn test_merge_json_adds_missing_keys() {
+        let mut base = json!({ "a": 1 });
+        merge_json(&mut base, json!({ "b": 2 }));
+        assert_eq!(base, json!({ "a": 1, "b": 2 }));
+    }
+}
@@ -3,7 +3,12 @@
 //! This module provides types for tracking progress of long-running operations
 //! like folder customization. Progress is reported via tokio channels.
 
-use std::path::PathBuf;
+use crate::conflict::ConflictKind;
+use crate::path_display::PathDisplay;
+use crate::paths::MergedDuplicate;
+use crate::warning::Warning;
+
+use std::path::{Path, PathBuf};
 
 /// Progress event for folder customization operations.
 #[derive(Debug, Clone)]
@@ -14,6 +19,25 @@ pub enum Progress {
         total: usize,
     },
 
+    /// Some input paths named the same folder and were merged.
+    ///
+    /// Sent right after [`Progress::Started`], only if duplicates were found.
+    /// `total` counts unique folders.
+    DuplicatesMerged {
+        /// The merged inputs.
+        duplicates: Vec<MergedDuplicate>,
+    },
+
+    /// The operation was cancelled before any folder was processed, such as
+    /// because the [`ConfirmationHandler`](crate::ConfirmationHandler)
+    /// declined it.
+    ///
+    /// Followed by [`Progress::Completed`] with every count at zero.
+    Cancelled {
+        /// Why the operation was cancelled.
+        reason: String,
+    },
+
     /// Rendering icons (happens once before processing folders).
     Rendering,
 
@@ -49,15 +73,77 @@ pub enum Progress {
         error: String,
     },
 
+    /// A folder was left untouched because of the conflict policy.
+    FolderSkipped {
+        /// Index of the skipped folder.
+        index: usize,
+        /// Path of the folder.
+        path: PathBuf,
+        /// The existing customization that caused the skip.
+        conflict: ConflictKind,
+    },
+
+    /// The batch saved its progress, every
+    /// [`checkpoint_interval`](crate::CustomizationContext::checkpoint_interval)
+    /// folders.
+    ///
+    /// Sent after the event of the folder that completed the interval.
+    Checkpoint {
+        /// Number of folders finished so far, whether they succeeded,
+        /// failed or were skipped.
+        completed: usize,
+        /// The [`ProgressSnapshot::id`](crate::ProgressSnapshot::id) the
+        /// progress was saved under.
+        snapshot_id: String,
+    },
+
+    /// The record of customized folders could not be saved.
+    ///
+    /// The folder operations themselves succeeded.
+    StoreSaveFailed {
+        /// Error message.
+        error: String,
+    },
+
+    /// The operation worked around problems, such as profile settings this
+    /// renderer build can't draw being left out.
+    ///
+    /// Sent right before [`Progress::Completed`], only if there were any.
+    Warnings {
+        /// The problems worked around.
+        warnings: Vec<Warning>,
+    },
+
     /// All operations completed.
     Completed {
         /// Number of successful operations.
         succeeded: usize,
         /// Number of failed operations.
         failed: usize,
+        /// Number of folders skipped because of the conflict policy.
+        skipped: usize,
     },
 }
 
+impl Progress {
+    /// Returns the folder the event is about, if any.
+    pub fn path(&self) -> Option<&Path> {
+        match self {
+            Progress::Processing { path, .. }
+            | Progress::FolderComplete { path, .. }
+            | Progress::FolderFailed { path, .. }
+            | Progress::FolderSkipped { path, .. } => Some(path),
+            _ => None,
+        }
+    }
+
+    /// Returns the folder the event is about shortened by `display`, for
+    /// progress lines.
+    pub fn display_path(&self, display: &PathDisplay) -> Option<String> {
+        self.path().map(|path| display.display(path))
+    }
+}
+
 /// A sender for progress updates.
 ///
 /// This is a re-export of `tokio::sync::mpsc::Sender<Progress>` for convenience.
@@ -92,7 +178,7 @@ pub type ProgressReceiver = tokio::sync::mpsc::Receiver<Progress>;
 ///             Progress::Processing { current, path } => {
 ///                 println!("Processing {}/{}: {:?}", current + 1, total, path);
 ///             }
-///             Progress::Completed { succeeded, failed } => {
+///             Progress::Completed { succeeded, failed, .. } => {
 ///                 println!("Done! {} succeeded, {} failed", succeeded, failed);
 ///             }
 ///             _ => {}
@@ -0,0 +1,204 @@
+//! Prioritization between interactive and background work.
+//!
+//! When a background job (e.g. the watcher re-applying icons) runs at the
+//! same time as a user-initiated operation, the background job should get out
+//! of the way. A [`WorkQueue`] shared between contexts tracks running
+//! interactive operations; background operations wait at each folder boundary
+//! until no interactive operation is active.
+
+use std::pin::pin;
+use std::sync::{Arc, Condvar, Mutex};
+
+use tokio::sync::Notify;
+
+/// Priority of an operation on a [`WorkQueue`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum Priority {
+    /// User-initiated work. Never waits for other work.
+    #[default]
+    Interactive,
+    /// Background work. Yields while any interactive work is running.
+    Background,
+}
+
+/// A two-priority work queue shared between customization contexts.
+///
+/// # Example
+///
+/// ```ignore
+/// use folco_core::{CustomizationContextBuilder, Priority, WorkQueue};
+/// use std::sync::Arc;
+///
+/// let queue = Arc::new(WorkQueue::new());
+///
+/// let interactive = CustomizationContextBuilder::new()
+///     .with_work_queue(queue.clone())
+///     .build()?;
+///
+/// // Batches on this context pause while `interactive` is working
+/// let background = CustomizationContextBuilder::new()
+///     .with_work_queue(queue)
+///     .with_priority(Priority::Background)
+///     .build()?;
+/// ```
+#[derive(Debug, Default)]
+pub struct WorkQueue {
+    interactive: Mutex<usize>,
+    idle: Condvar,
+    idle_async: Notify,
+}
+
+impl WorkQueue {
+    /// Creates a new work queue with no active work.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Registers an operation with the given priority.
+    ///
+    /// Interactive operations count as active until the returned guard is
+    /// dropped.
+    pub fn enter(self: &Arc<Self>, priority: Priority) -> WorkGuard {
+        if priority == Priority::Interactive {
+            *self.lock() += 1;
+        }
+        WorkGuard {
+            queue: Arc::clone(self),
+            priority,
+        }
+    }
+
+    /// Returns `true` if any interactive operation is running.
+    pub fn is_interactive_active(&self) -> bool {
+        *self.lock() > 0
+    }
+
+    /// Blocks the current thread while any interactive operation is running.
+    pub fn wait_for_interactive(&self) {
+        let mut active = self.lock();
+        while *active > 0 {
+            active = self.idle.wait(active).unwrap_or_else(|e| e.into_inner());
+        }
+    }
+
+    /// Waits asynchronously while any interactive operation is running.
+    pub async fn wait_for_interactive_async(&self) {
+        loop {
+            // Register for wakeups before checking, so a guard dropped in
+            // between can't be missed
+            let mut notified = pin!(self.idle_async.notified());
+            notified.as_mut().enable();
+            if !self.is_interactive_active() {
+                return;
+            }
+            notified.await;
+        }
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, usize> {
+        self.interactive.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    fn leave(&self, priority: Priority) {
+        if priority != Priority::Interactive {
+            return;
+        }
+        let mut active = self.lock();
+        *active = active.saturating_sub(1);
+        if *active == 0 {
+            self.idle.notify_all();
+            self.idle_async.notify_waiters();
+        }
+    }
+}
+
+/// Marks an operation as running on a [`WorkQueue`].
+///
+/// Created by [`WorkQueue::enter`]; the operation ends when this is dropped.
+#[derive(Debug)]
+pub struct WorkGuard {
+    queue: Arc<WorkQueue>,
+    priority: Priority,
+}
+
+impl WorkGuard {
+    /// Returns the priority of the guarded operation.
+    pub fn priority(&self) -> Priority {
+        self.priority
+    }
+
+    /// Waits at a work boundary if this is background work and interactive
+    /// work is running.
+    pub fn yield_to_interactive(&self) {
+        if self.priority == Priority::Background {
+            self.queue.wait_for_interactive();
+        }
+    }
+
+    /// Async version of [`yield_to_interactive`](Self::yield_to_interactive).
+    pub async fn yield_to_interactive_async(&self) {
+        if self.priority == Priority::Background {
+            self.queue.wait_for_interactive_async().await;
+        }
+    }
+}
+
+impl Drop for WorkGuard {
+    fn drop(&mut self) {
+        self.queue.leave(self.priority);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::mpsc;
+    use std::thread;
+    use std::time::Duration;
+
+    #[test]
+    fn test_interactive_guard_counts_as_active() {
+        let queue = Arc::new(WorkQueue::new());
+        assert!(!queue.is_interactive_active());
+
+        let first = queue.enter(Priority::Interactive);
+        let second = queue.enter(Priority::Interactive);
+        assert!(queue.is_interactive_active());
+
+        drop(first);
+        assert!(queue.is_interactive_active());
+        drop(second);
+        assert!(!queue.is_interactive_active());
+    }
+
+    #[test]
+    fn test_background_guard_is_not_active() {
+        let queue = Arc::new(WorkQueue::new());
+        let guard = queue.enter(Priority::Background);
+
+        assert!(!queue.is_interactive_active());
+        // Must not block
+        guard.yield_to_interactive();
+    }
+
+    #[test]
+    fn test_background_yields_until_interactive_done() {
+        let queue = Arc::new(WorkQueue::new());
+        let interactive = queue.enter(Priority::Interactive);
+
+        let (tx, rx) = mpsc::channel();
+        let background_queue = Arc::clone(&queue);
+        let handle = thread::spawn(move || {
+            let guard = background_queue.enter(Priority::Background);
+            guard.yield_to_interactive();
+            tx.send(()).unwrap();
+        });
+
+        // The background job is still waiting
+        assert!(rx.recv_timeout(Duration::from_millis(100)).is_err());
+
+        drop(interactive);
+        rx.recv_timeout(Duration::from_secs(5)).unwrap();
+        handle.join().unwrap();
+    }
+}
@@ -0,0 +1,182 @@
+//! Seeded random profiles.
+//!
+//! [`RandomProfile::random`] picks a color from a palette and, sometimes, a
+//! decal from a pool, so a shuffle button or `folco random` produces
+//! something that looks deliberate rather than arbitrary HSL noise. The same
+//! seed and constraints always produce the same profile, on every platform,
+//! so a result the user liked can be reproduced from its seed.
+
+use crate::color::FolderColor;
+use crate::error::Result;
+use crate::profile::{profile_with_decal, profile_with_hsl};
+
+use folco_renderer::{CustomizationProfile, DecalSettings};
+use serde::{Deserialize, Serialize};
+
+/// What [`RandomProfile::random`] may choose from.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct RandomConstraints {
+    /// Colors to pick from. Empty means every preset.
+    pub palette: Vec<FolderColor>,
+    /// How far, in degrees either way, the hue may drift from the chosen
+    /// preset. Zero keeps colors exactly on the palette.
+    pub hue_jitter: f32,
+    /// Decals to pick from. Empty means no decal is ever added.
+    pub decals: Vec<DecalSettings>,
+    /// Probability, from 0 to 1, that a decal from the pool is added.
+    pub decal_chance: f32,
+}
+
+impl Default for RandomConstraints {
+    fn default() -> Self {
+        Self {
+            palette: Vec::new(),
+            hue_jitter: 0.0,
+            decals: Vec::new(),
+            decal_chance: 0.5,
+        }
+    }
+}
+
+impl RandomConstraints {
+    /// Creates the default constraints: any preset color, no jitter, and no
+    /// decals.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Limits colors to `palette`.
+    pub fn with_palette(mut self, palette: impl IntoIterator<Item = FolderColor>) -> Self {
+        self.palette = palette.into_iter().collect();
+        self
+    }
+
+    /// Lets the hue drift up to `degrees` from the chosen preset.
+    pub fn with_hue_jitter(mut self, degrees: f32) -> Self {
+        self.hue_jitter = degrees.abs();
+        self
+    }
+
+    /// Sets the decals to pick from.
+    pub fn with_decals(mut self, decals: impl IntoIterator<Item = DecalSettings>) -> Self {
+        self.decals = decals.into_iter().collect();
+        self
+    }
+
+    /// Sets the probability that a decal is added, clamped to 0–1.
+    pub fn with_decal_chance(mut self, chance: f32) -> Self {
+        self.decal_chance = chance.clamp(0.0, 1.0);
+        self
+    }
+
+    fn colors(&self) -> &[FolderColor] {
+        if self.palette.is_empty() {
+            FolderColor::all()
+        } else {
+            &self.palette
+        }
+    }
+}
+
+/// Generation of random profiles within [`RandomConstraints`].
+pub trait RandomProfile: Sized {
+    /// Generates a profile from `seed`, deterministically.
+    ///
+    /// # Errors
+    ///
+    /// Fails if the generated settings can't be embedded in a profile.
+    fn random(seed: u64, constraints: &RandomConstraints) -> Result<Self>;
+}
+
+impl RandomProfile for CustomizationProfile {
+    fn random(seed: u64, constraints: &RandomConstraints) -> Result<Self> {
+        let mut rng = SplitMix64(seed);
+
+        let colors = constraints.colors();
+        let color = colors[rng.below(colors.len())];
+        let mut settings = color.to_hsl_mutation_settings();
+        // Hue is meaningless for greys, so don't let jitter tint them
+        if constraints.hue_jitter > 0.0 && settings.target_saturation > 0.0 {
+            let offset = (rng.unit() * 2.0 - 1.0) * constraints.hue_jitter;
+            settings.target_hue = (settings.target_hue + offset).rem_euclid(360.0);
+        }
+        let profile = profile_with_hsl(&CustomizationProfile::default(), &settings)?;
+
+        // Always draw, so the color for a seed doesn't depend on the pool
+        let roll = rng.unit();
+        let pick = rng.next_u64();
+        if constraints.decals.is_empty() || roll >= constraints.decal_chance {
+            return Ok(profile);
+        }
+        let decal = &constraints.decals[(pick % constraints.decals.len() as u64) as usize];
+        profile_with_decal(&profile, Some(decal))
+    }
+}
+
+/// The SplitMix64 generator: tiny, fast, and fully specified, so a seed
+/// means the same thing in every build.
+struct SplitMix64(u64);
+
+impl SplitMix64 {
+    fn next_u64(&mut self) -> u64 {
+        self.0 = self.0.wrapping_add(0x9e37_79b9_7f4a_7c15);
+        let mut z = self.0;
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+        z ^ (z >> 31)
+    }
+
+    /// Returns a value in `0..bound`. `bound` must be nonzero.
+    fn below(&mut self, bound: usize) -> usize {
+        (self.next_u64() % bound as u64) as usize
+    }
+
+    /// Returns a value in `[0, 1)`.
+    fn unit(&mut self) -> f32 {
+        (self.next_u64() >> 40) as f32 / (1u64 << 24) as f32
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::profile::{profile_color, profile_hash};
+
+    #[test]
+    fn test_splitmix_known_values() {
+        let mut rng = SplitMix64(0);
+        assert_eq!(rng.next_u64(), 0xe220_a839_7b1d_cdaf);
+        assert_eq!(rng.next_u64(), 0x6e78_9e6a_a1b9_65f4);
+    }
+
+    #[test]
+    fn test_same_seed_same_profile() {
+        let constraints = RandomConstraints::new().with_hue_jitter(10.0);
+        let a = CustomizationProfile::random(42, &constraints).unwrap();
+        let b = CustomizationProfile::random(42, &constraints).unwrap();
+        assert_eq!(profile_hash(&a), profile_hash(&b));
+    }
+
+    #[test]
+    fn test_colors_stay_on_palette() {
+        let constraints =
+            RandomConstraints::new().with_palette([FolderColor::Teal, FolderColor::Amber]);
+        for seed in 0..32 {
+            let profile = CustomizationProfile::random(seed, &constraints).unwrap();
+            let color = profile_color(&profile);
+            assert!(
+                matches!(color, Some(FolderColor::Teal | FolderColor::Amber)),
+                "{color:?}"
+            );
+        }
+    }
+
+    #[test]
+    fn test_unit_range() {
+        let mut rng = SplitMix64(7);
+        assert!((0..1000)
+            .map(|_| rng.unit())
+            .all(|x| (0.0..1.0).contains(&x)));
+    }
+}
@@ -0,0 +1,362 @@
+//! Reconciliation of the profile store with the filesystem.
+//!
+//! Folders in the profile store can disappear: users delete, move and rename
+//! them, or send them to the trash. [`reconcile_store`] finds entries whose
+//! folder no longer exists, tries to locate moved folders by their
+//! [`FileId`], and remaps or prunes entries accordingly.
+
+use crate::error::Result;
+use crate::file_id::FileId;
+use crate::store::ProfileStore;
+
+use serde::{Deserialize, Serialize};
+
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// Options for [`reconcile_store`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ReconcileOptions {
+    /// Remove entries for folders that are missing or in the trash.
+    ///
+    /// When `false` (the default), missing folders are only reported.
+    pub prune: bool,
+    /// Look for moved folders using the filesystem identifiers recorded
+    /// when they were customized.
+    pub follow_hints: bool,
+    /// Directories to search for moved folders.
+    ///
+    /// When empty, the nearest existing ancestor of each missing folder is
+    /// searched.
+    pub search_roots: Vec<PathBuf>,
+    /// How many directory levels below each search root to look.
+    pub max_depth: usize,
+}
+
+impl Default for ReconcileOptions {
+    fn default() -> Self {
+        Self {
+            prune: false,
+            follow_hints: true,
+            search_roots: Vec::new(),
+            max_depth: 3,
+        }
+    }
+}
+
+impl ReconcileOptions {
+    /// Creates the default options: report only, following hints.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Sets whether missing and trashed entries are removed.
+    pub fn with_prune(mut self, prune: bool) -> Self {
+        self.prune = prune;
+        self
+    }
+
+    /// Sets whether moved folders are searched for.
+    pub fn with_follow_hints(mut self, follow_hints: bool) -> Self {
+        self.follow_hints = follow_hints;
+        self
+    }
+
+    /// Adds a directory to search for moved folders.
+    pub fn with_search_root(mut self, root: impl Into<PathBuf>) -> Self {
+        self.search_roots.push(root.into());
+        self
+    }
+
+    /// Sets how deep below each search root to look.
+    pub fn with_max_depth(mut self, max_depth: usize) -> Self {
+        self.max_depth = max_depth;
+        self
+    }
+}
+
+/// A folder that was found at a new location.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RemappedFolder {
+    /// Where the store thought the folder was.
+    pub from: PathBuf,
+    /// Where the folder is now.
+    pub to: PathBuf,
+}
+
+/// A folder that was found in the trash.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TrashedFolder {
+    /// Where the store thought the folder was.
+    pub path: PathBuf,
+    /// Where the folder is in the trash.
+    pub trash_path: PathBuf,
+}
+
+/// The result of reconciling the profile store.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ReconcileReport {
+    /// Number of store entries checked.
+    pub checked: usize,
+    /// Entries moved to their folder's new location.
+    pub remapped: Vec<RemappedFolder>,
+    /// Entries whose folder is in the trash.
+    pub trashed: Vec<TrashedFolder>,
+    /// Entries whose folder could not be found.
+    pub missing: Vec<PathBuf>,
+    /// Entries removed from the store (only with [`ReconcileOptions::prune`]).
+    pub pruned: Vec<PathBuf>,
+}
+
+impl ReconcileReport {
+    /// Returns `true` if every entry's folder exists where the store expects.
+    pub fn is_clean(&self) -> bool {
+        self.remapped.is_empty() && self.trashed.is_empty() && self.missing.is_empty()
+    }
+}
+
+/// Reconciles the profile store with the filesystem.
+///
+/// Moved folders are always remapped. Missing and trashed folders are
+/// reported, and removed if [`ReconcileOptions::prune`] is set. The store is
+/// saved if anything changed.
+pub fn reconcile_store(store: &ProfileStore, options: &ReconcileOptions) -> Result<ReconcileReport> {
+    let entries = store.entries();
+    let mut report = ReconcileReport {
+        checked: entries.len(),
+        ..Default::default()
+    };
+
+    let missing: Vec<_> = entries
+        .into_iter()
+        .filter(|(path, _)| !path.exists())
+        .collect();
+    if missing.is_empty() {
+        return Ok(report);
+    }
+
+    // Find moved folders by identity
+    let mut found = HashMap::new();
+    if options.follow_hints {
+        let wanted: HashSet<FileId> = missing
+            .iter()
+            .filter_map(|(_, stored)| stored.file_id)
+            .collect();
+        if !wanted.is_empty() {
+            let roots = if options.search_roots.is_empty() {
+                default_search_roots(missing.iter().map(|(path, _)| path.as_path()))
+            } else {
+                options.search_roots.clone()
+            };
+            for root in roots {
+                find_by_id(&root, options.max_depth, &wanted, store, &mut found);
+            }
+            for dir in trash_dirs() {
+                find_by_id(&dir, 1, &wanted, store, &mut found);
+            }
+        }
+    }
+
+    let trash = trash_dirs();
+    for (path, stored) in missing {
+        let located = stored.file_id.and_then(|id| found.get(&id));
+        match located {
+            Some(new_path) if trash.iter().any(|dir| new_path.starts_with(dir)) => {
+                report.trashed.push(TrashedFolder {
+                    path,
+                    trash_path: new_path.clone(),
+                });
+            }
+            Some(new_path) => {
+                store.remap(&path, new_path);
+                report.remapped.push(RemappedFolder {
+                    from: path,
+                    to: new_path.clone(),
+                });
+            }
+            None => match find_in_trash_by_name(&path, &trash) {
+                Some(trash_path) => report.trashed.push(TrashedFolder { path, trash_path }),
+                None => report.missing.push(path),
+            },
+        }
+    }
+
+    if options.prune {
+        let gone = report
+            .trashed
+            .iter()
+            .map(|t| &t.path)
+            .chain(report.missing.iter());
+        for path in gone {
+            store.remove(path);
+            report.pruned.push(path.clone());
+        }
+    }
+
+    if !report.remapped.is_empty() || !report.pruned.is_empty() {
+        store.save()?;
+    }
+
+    Ok(report)
+}
+
+/// Returns the nearest existing ancestor of each missing folder, deduplicated.
+fn default_search_roots<'a>(missing: impl Iterator<Item = &'a Path>) -> Vec<PathBuf> {
+    let mut roots: Vec<PathBuf> = Vec::new();
+    for path in missing {
+        let Some(root) = path.ancestors().skip(1).find(|a| a.is_dir()) else {
+            continue;
+        };
+        if !roots.iter().any(|r| root.starts_with(r)) {
+            roots.retain(|r| !r.starts_with(root));
+            roots.push(root.to_path_buf());
+        }
+    }
+    roots
+}
+
+/// Walks `root` looking for directories with a wanted identifier.
+///
+/// Directories that already have a store entry are skipped, so entries are
+/// never remapped onto each other. Symlinks are not followed.
+fn find_by_id(
+    root: &Path,
+    max_depth: usize,
+    wanted: &HashSet<FileId>,
+    store: &ProfileStore,
+    found: &mut HashMap<FileId, PathBuf>,
+) {
+    let mut pending = vec![(root.to_path_buf(), 0usize)];
+    while let Some((dir, depth)) = pending.pop() {
+        if found.len() == wanted.len() {
+            return;
+        }
+        let Ok(read_dir) = fs::read_dir(&dir) else {
+            continue;
+        };
+        for entry in read_dir.flatten() {
+            let Ok(metadata) = entry.metadata() else {
+                continue;
+            };
+            if !metadata.is_dir() {
+                continue;
+            }
+            let path = entry.path();
+            let matched = FileId::from_metadata(&metadata)
+                .or_else(|| FileId::of(&path))
+                .filter(|id| wanted.contains(id) && !store.contains(&path));
+            if let Some(id) = matched {
+                found.entry(id).or_insert_with(|| path.clone());
+            }
+            if depth + 1 < max_depth {
+                pending.push((path, depth + 1));
+            }
+        }
+    }
+}
+
+/// Looks for a same-named folder in the trash.
+///
+/// This is a heuristic for folders without a recorded identifier.
+fn find_in_trash_by_name(path: &Path, trash: &[PathBuf]) -> Option<PathBuf> {
+    let name = path.file_name()?;
+    trash
+        .iter()
+        .map(|dir| dir.join(name))
+        .find(|candidate| candidate.is_dir())
+}
+
+/// Returns the user's trash directories on this platform.
+fn trash_dirs() -> Vec<PathBuf> {
+    let Some(dirs) = directories::BaseDirs::new() else {
+        return Vec::new();
+    };
+
+    if cfg!(target_os = "macos") {
+        vec![dirs.home_dir().join(".Trash")]
+    } else if cfg!(target_os = "linux") {
+        vec![dirs.data_dir().join("Trash").join("files")]
+    } else {
+        // The Windows recycle bin renames items, so they can't be found by
+        // name, and file indices aren't available yet
+        Vec::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use folco_renderer::CustomizationProfile;
+    use tempfile::tempdir;
+
+    #[test]
+    fn test_clean_store() {
+        let temp = tempdir().unwrap();
+        let folder = temp.path().join("folder");
+        fs::create_dir(&folder).unwrap();
+
+        let store = ProfileStore::in_data_dir(temp.path()).unwrap();
+        store.insert(&folder, &CustomizationProfile::default());
+
+        let report = reconcile_store(&store, &ReconcileOptions::new()).unwrap();
+        assert_eq!(report.checked, 1);
+        assert!(report.is_clean());
+    }
+
+    #[test]
+    fn test_missing_reported_and_pruned() {
+        let temp = tempdir().unwrap();
+        let store = ProfileStore::in_data_dir(temp.path()).unwrap();
+        let folder = temp.path().join("deleted-folder-for-reconcile");
+        store.insert(&folder, &CustomizationProfile::default());
+
+        let report = reconcile_store(&store, &ReconcileOptions::new()).unwrap();
+        assert_eq!(report.missing, vec![folder.clone()]);
+        assert!(store.contains(&folder));
+
+        let report = reconcile_store(&store, &ReconcileOptions::new().with_prune(true)).unwrap();
+        assert_eq!(report.pruned, vec![folder.clone()]);
+        assert!(!store.contains(&folder));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_moved_folder_is_remapped() {
+        let temp = tempdir().unwrap();
+        let before = temp.path().join("projects").join("old-name");
+        let after = temp.path().join("projects").join("new-name");
+        fs::create_dir_all(&before).unwrap();
+
+        let store = ProfileStore::in_data_dir(temp.path()).unwrap();
+        store.insert(&before, &CustomizationProfile::default());
+        fs::rename(&before, &after).unwrap();
+
+        let report = reconcile_store(&store, &ReconcileOptions::new()).unwrap();
+        assert_eq!(
+            report.remapped,
+            vec![RemappedFolder {
+                from: before.clone(),
+                to: after.clone(),
+            }]
+        );
+        assert!(store.contains(&after));
+        assert!(!store.contains(&before));
+    }
+
+    #[test]
+    fn test_default_search_roots_are_deduplicated() {
+        let temp = tempdir().unwrap();
+        let base = temp.path();
+        let missing = [
+            base.join("gone-a"),
+            base.join("gone-b").join("deeper"),
+        ];
+
+        let roots = default_search_roots(missing.iter().map(|p| p.as_path()));
+        assert_eq!(roots, vec![base.to_path_buf()]);
+    }
+}
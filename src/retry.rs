@@ -0,0 +1,259 @@
+//! Retries of folders that fail for a passing reason.
+//!
+//! Folders on network shares and cloud-synced drives fail now and then
+//! because another process has their files open. The [`RetryPolicy`] of
+//! the [`ApplyOptions`](crate::ApplyOptions) a batch runs with, which is
+//! the context's [`retry_policy`](crate::CustomizationContext::retry_policy)
+//! unless the batch was given options of its own, runs the whole folder
+//! operation again, timeout included, after a backoff that doubles with
+//! every try. The async batches wait without blocking the runtime. Only
+//! transient failures are retried: a folder that needs
+//! more rights, can't be customized at all, timed out or was refused by a
+//! hook fails the same way the next time.
+
+use crate::error::{Error, Result};
+use crate::log::CoreLog;
+use crate::privileged::needs_elevation;
+use crate::timeout::{run_with_timeout, run_with_timeout_async};
+
+use serde::{Deserialize, Serialize};
+
+use std::future::Future;
+use std::path::Path;
+use std::sync::Arc;
+use std::time::Duration;
+
+/// An operation on one folder, which can be run again for a retry.
+pub(crate) type FolderOp = Arc<dyn Fn() -> Result<()> + Send + Sync>;
+
+/// How the batches retry a folder that failed for a passing reason.
+///
+/// The default tries each folder once.
+///
+/// # Example
+///
+/// ```
+/// use folco_core::RetryPolicy;
+/// use std::time::Duration;
+///
+/// // Waits 200 ms, then 400 ms, before giving up on the third try
+/// let policy = RetryPolicy::new(3, Duration::from_millis(200));
+/// assert_eq!(policy.delay(2), Duration::from_millis(400));
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RetryPolicy {
+    /// Total number of tries, including the first. Zero behaves like one.
+    pub attempts: u32,
+    /// How long to wait before the first retry; every further retry waits
+    /// twice as long as the one before.
+    pub backoff: Duration,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self {
+            attempts: 1,
+            backoff: Duration::ZERO,
+        }
+    }
+}
+
+impl RetryPolicy {
+    /// Creates a policy of `attempts` tries, starting with a wait of
+    /// `backoff`.
+    pub fn new(attempts: u32, backoff: Duration) -> Self {
+        Self { attempts, backoff }
+    }
+
+    /// Returns how long to wait before the retry numbered `retry`, counting
+    /// from 1.
+    pub fn delay(&self, retry: u32) -> Duration {
+        let factor = 2u32.saturating_pow(retry.saturating_sub(1));
+        self.backoff.saturating_mul(factor)
+    }
+
+    /// Runs `op` until it succeeds, fails for good or the attempts run
+    /// out, calling `on_retry` with the error and the wait before each
+    /// retry.
+    pub(crate) fn run(
+        &self,
+        mut op: impl FnMut() -> Result<()>,
+        mut on_retry: impl FnMut(&Error, Duration),
+    ) -> Result<()> {
+        let mut retry = 0;
+        loop {
+            match op() {
+                Err(e) if retry + 1 < self.attempts && is_transient(&e) => {
+                    retry += 1;
+                    let delay = self.delay(retry);
+                    on_retry(&e, delay);
+                    std::thread::sleep(delay);
+                }
+                result => return result,
+            }
+        }
+    }
+
+    /// Async version of [`run`](Self::run), waiting on the runtime's timer.
+    pub(crate) async fn run_async<F, Fut>(
+        &self,
+        mut op: F,
+        mut on_retry: impl FnMut(&Error, Duration),
+    ) -> Result<()>
+    where
+        F: FnMut() -> Fut,
+        Fut: Future<Output = Result<()>>,
+    {
+        let mut retry = 0;
+        loop {
+            match op().await {
+                Err(e) if retry + 1 < self.attempts && is_transient(&e) => {
+                    retry += 1;
+                    let delay = self.delay(retry);
+                    on_retry(&e, delay);
+                    tokio::time::sleep(delay).await;
+                }
+                result => return result,
+            }
+        }
+    }
+}
+
+/// Runs `op` on `folder` as `policy` says, giving up on each try after
+/// `timeout`, and logging retries to `log`.
+pub(crate) fn run_folder_op(
+    policy: &RetryPolicy,
+    folder: &Path,
+    timeout: Option<Duration>,
+    op: &FolderOp,
+    log: &CoreLog,
+) -> Result<()> {
+    policy.run(
+        || {
+            let op = Arc::clone(op);
+            run_with_timeout(folder, timeout, move || op())
+        },
+        |e, delay| log_retry(log, folder, e, delay),
+    )
+}
+
+/// Async version of [`run_folder_op`].
+pub(crate) async fn run_folder_op_async(
+    policy: &RetryPolicy,
+    folder: &Path,
+    timeout: Option<Duration>,
+    op: &FolderOp,
+    log: &CoreLog,
+) -> Result<()> {
+    policy
+        .run_async(
+            || {
+                let op = Arc::clone(op);
+                run_with_timeout_async(folder, timeout, move || op())
+            },
+            |e, delay| log_retry(log, folder, e, delay),
+        )
+        .await
+}
+
+/// Returns `true` if a failed folder operation might succeed when tried
+/// again.
+pub(crate) fn is_transient(error: &Error) -> bool {
+    matches!(
+        error,
+        Error::Io(_) | Error::FolderCustomization(..) | Error::FolderReset(..)
+    ) && !needs_elevation(error)
+}
+
+/// Logs that `folder` failed with `error` and is tried again after `delay`.
+fn log_retry(log: &CoreLog, folder: &Path, error: &Error, delay: Duration) {
+    log.info(format_args!("retrying {} in {delay:?}: {error}", folder.display()));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::PathBuf;
+    use std::sync::atomic::{AtomicU32, Ordering};
+
+    fn busy() -> Error {
+        Error::FolderCustomization(
+            PathBuf::from("/share/photos"),
+            "the file is being used by another process".to_string(),
+        )
+    }
+
+    #[test]
+    fn test_backoff_doubles() {
+        let policy = RetryPolicy::new(4, Duration::from_millis(10));
+        let delays: Vec<_> = (1..=3).map(|retry| policy.delay(retry)).collect();
+        assert_eq!(delays, [10, 20, 40].map(Duration::from_millis));
+        assert_eq!(RetryPolicy::new(2, Duration::MAX).delay(40), Duration::MAX);
+    }
+
+    #[test]
+    fn test_transient_failures_are_retried() {
+        let policy = RetryPolicy::new(3, Duration::ZERO);
+        let mut calls = 0;
+        let mut retries = Vec::new();
+        let result = policy.run(
+            || {
+                calls += 1;
+                if calls < 3 { Err(busy()) } else { Ok(()) }
+            },
+            |e, _| retries.push(e.kind()),
+        );
+        assert!(result.is_ok());
+        assert_eq!(retries, ["folder-customization"; 2]);
+
+        let mut calls = 0;
+        let result = policy.run(
+            || {
+                calls += 1;
+                Err(busy())
+            },
+            |_, _| {},
+        );
+        assert!(result.is_err());
+        assert_eq!(calls, 3);
+    }
+
+    #[test]
+    fn test_folder_ops_are_run_again() {
+        let calls = Arc::new(AtomicU32::new(0));
+        let counted = Arc::clone(&calls);
+        let op: FolderOp = Arc::new(move || {
+            let call = counted.fetch_add(1, Ordering::SeqCst);
+            if call < 2 { Err(busy()) } else { Ok(()) }
+        });
+        let policy = RetryPolicy::new(3, Duration::ZERO);
+        let folder = Path::new("/share/photos");
+        let timeout = Some(Duration::from_secs(5));
+        assert!(run_folder_op(&policy, folder, timeout, &op, &CoreLog::disabled()).is_ok());
+        assert_eq!(calls.load(Ordering::SeqCst), 3);
+    }
+
+    #[test]
+    fn test_lasting_failures_are_not_retried() {
+        let policy = RetryPolicy::new(5, Duration::ZERO);
+        let lasting = [
+            Error::FolderCustomization(PathBuf::from("/x"), "Access is denied.".to_string()),
+            Error::Unsupported(PathBuf::from("/x"), "no".to_string()),
+            Error::Timeout(PathBuf::from("/x"), Duration::from_secs(1)),
+            Error::Hook("refused".to_string()),
+        ];
+        for error in lasting {
+            let mut calls = 0;
+            let mut error = Some(error);
+            let _ = policy.run(
+                || {
+                    calls += 1;
+                    Err(error.take().unwrap_or_else(busy))
+                },
+                |_, _| {},
+            );
+            assert_eq!(calls, 1);
+        }
+    }
+}
@@ -0,0 +1,543 @@
+//! Rules that pick a customization for folders by name.
+//!
+//! A [`Rule`] pairs a glob pattern with a color and/or preset, e.g. "folders
+//! named `Invoices*` are green", optionally restricted by
+//! [`RuleCondition`]s such as "only if empty". Rules are kept in a [`RuleSet`] in the app
+//! config and are evaluated by priority, then in order.
+//!
+//! By default the winning rule alone decides a folder's customization. In
+//! [`RuleMode::MergeAll`] every matching rule contributes, with higher
+//! priorities taking precedence, and an exclusive rule stops lower ones from
+//! applying. Folders matched by rules with different colors are reported as
+//! [`RuleConflict`]s either way.
+//!
+//! [`simulate_rules`](crate::CustomizationContext::simulate_rules) shows what
+//! a rules pass over a tree would do without changing anything.
+
+use crate::color::FolderColor;
+use crate::conditions::RuleCondition;
+use crate::error::{Error, Result};
+
+use folco_renderer::CustomizationProfile;
+use serde::{Deserialize, Serialize};
+
+use std::fs;
+use std::path::{Component, Path, PathBuf};
+
+/// A pattern and the customization applied to folders matching it.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Rule {
+    /// Unique name of the rule.
+    pub name: String,
+    /// Glob pattern matched against folder names, ignoring case.
+    ///
+    /// Patterns containing `/` are matched against the trailing components
+    /// of the folder's path, so `Clients/*` matches every folder directly
+    /// inside any folder named `Clients`.
+    pub pattern: String,
+    /// Color preset to apply, on top of the preset if both are given.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub color: Option<FolderColor>,
+    /// Name of a saved preset to apply.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub preset: Option<String>,
+    /// Disabled rules never match.
+    #[serde(default = "default_enabled")]
+    pub enabled: bool,
+    /// Rules with a higher priority are evaluated first. Rules with equal
+    /// priorities are evaluated in order.
+    #[serde(default, skip_serializing_if = "is_zero")]
+    pub priority: i32,
+    /// When an exclusive rule matches, lower-priority rules are ignored for
+    /// that folder and aren't reported as conflicting with it.
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub exclusive: bool,
+    /// Conditions the folder must also meet, all of them, for the rule to
+    /// match.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub conditions: Vec<RuleCondition>,
+}
+
+fn is_zero(value: &i32) -> bool {
+    *value == 0
+}
+
+fn default_enabled() -> bool {
+    true
+}
+
+impl Rule {
+    /// Creates an enabled rule with nothing to apply yet.
+    pub fn new(name: impl Into<String>, pattern: impl Into<String>) -> Self {
+        Self {
+            name: name.into(),
+            pattern: pattern.into(),
+            color: None,
+            preset: None,
+            enabled: true,
+            priority: 0,
+            exclusive: false,
+            conditions: Vec::new(),
+        }
+    }
+
+    /// Sets the color preset to apply.
+    pub fn with_color(mut self, color: FolderColor) -> Self {
+        self.color = Some(color);
+        self
+    }
+
+    /// Sets the saved preset to apply.
+    pub fn with_preset(mut self, preset: impl Into<String>) -> Self {
+        self.preset = Some(preset.into());
+        self
+    }
+
+    /// Sets whether the rule is enabled.
+    pub fn with_enabled(mut self, enabled: bool) -> Self {
+        self.enabled = enabled;
+        self
+    }
+
+    /// Sets the rule's priority.
+    pub fn with_priority(mut self, priority: i32) -> Self {
+        self.priority = priority;
+        self
+    }
+
+    /// Sets whether the rule is exclusive.
+    pub fn with_exclusive(mut self, exclusive: bool) -> Self {
+        self.exclusive = exclusive;
+        self
+    }
+
+    /// Adds a condition the folder must meet.
+    pub fn with_condition(mut self, condition: RuleCondition) -> Self {
+        self.conditions.push(condition);
+        self
+    }
+
+    /// Checks that the rule has a valid pattern and something to apply.
+    pub fn validate(&self) -> Result<()> {
+        if self.name.trim().is_empty() {
+            return Err(Error::Rule("rule name cannot be empty".to_string()));
+        }
+        glob::Pattern::new(&self.pattern)
+            .map_err(|e| Error::Rule(format!("rule '{}': invalid pattern: {e}", self.name)))?;
+        if self.color.is_none() && self.preset.is_none() {
+            return Err(Error::Rule(format!(
+                "rule '{}' has no color or preset",
+                self.name
+            )));
+        }
+        if self
+            .conditions
+            .iter()
+            .any(|condition| matches!(condition, RuleCondition::Owner(owner) if owner.trim().is_empty()))
+        {
+            return Err(Error::Rule(format!("rule '{}' has an empty owner", self.name)));
+        }
+        Ok(())
+    }
+
+    /// Returns `true` if the rule is enabled, its pattern matches `folder`
+    /// and the folder meets its conditions.
+    ///
+    /// The pattern is checked first, so conditions are only evaluated for
+    /// folders with a matching name.
+    pub fn matches(&self, folder: &Path) -> bool {
+        if !self.enabled {
+            return false;
+        }
+        let Ok(pattern) = glob::Pattern::new(&self.pattern) else {
+            return false;
+        };
+        let options = glob::MatchOptions {
+            case_sensitive: false,
+            require_literal_separator: true,
+            require_literal_leading_dot: false,
+        };
+
+        let names: Vec<String> = folder
+            .components()
+            .filter_map(|c| match c {
+                Component::Normal(name) => Some(name.to_string_lossy().into_owned()),
+                _ => None,
+            })
+            .collect();
+        let depth = self.pattern.split('/').count();
+        if depth > names.len() {
+            return false;
+        }
+        let tail = names[names.len() - depth..].join("/");
+        pattern.matches_with(&tail, options)
+            && self.conditions.iter().all(|condition| condition.check(folder))
+    }
+}
+
+/// How the rules matching a folder are combined.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum RuleMode {
+    /// The highest-priority matching rule decides alone.
+    #[default]
+    FirstMatch,
+    /// Every matching rule contributes. The color and preset come from the
+    /// highest-priority rules that set them.
+    MergeAll,
+}
+
+/// Several rules matching one folder with different colors.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RuleConflict {
+    /// The folder the rules match.
+    pub path: PathBuf,
+    /// Name of the rule whose color is applied.
+    pub winner: String,
+    /// Names and colors of every matching rule that sets a color, in
+    /// evaluation order.
+    pub colors: Vec<(String, FolderColor)>,
+}
+
+/// What the rules decide for one folder.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RuleEvaluation {
+    /// The customization to apply, as a rule named after the winning rule
+    /// with the combined color and preset.
+    pub rule: Rule,
+    /// Names of the rules that contributed, in evaluation order.
+    pub applied: Vec<String>,
+    /// Rules that disagreed on the color, if any did.
+    pub conflict: Option<RuleConflict>,
+}
+
+/// A prioritized list of rules.
+///
+/// Serialized as `{"mode": ..., "rules": [...]}`. A plain list of rules, as
+/// written by earlier versions, is also accepted.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", from = "RuleSetRepr")]
+pub struct RuleSet {
+    mode: RuleMode,
+    rules: Vec<Rule>,
+}
+
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum RuleSetRepr {
+    List(Vec<Rule>),
+    Full {
+        #[serde(default)]
+        mode: RuleMode,
+        #[serde(default)]
+        rules: Vec<Rule>,
+    },
+}
+
+impl From<RuleSetRepr> for RuleSet {
+    fn from(repr: RuleSetRepr) -> Self {
+        match repr {
+            RuleSetRepr::List(rules) => Self {
+                mode: RuleMode::default(),
+                rules,
+            },
+            RuleSetRepr::Full { mode, rules } => Self { mode, rules },
+        }
+    }
+}
+
+impl RuleSet {
+    /// Creates an empty rule set.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Sets how matching rules are combined.
+    pub fn with_mode(mut self, mode: RuleMode) -> Self {
+        self.mode = mode;
+        self
+    }
+
+    /// Returns how matching rules are combined.
+    pub fn mode(&self) -> RuleMode {
+        self.mode
+    }
+
+    /// Sets how matching rules are combined.
+    pub fn set_mode(&mut self, mode: RuleMode) {
+        self.mode = mode;
+    }
+
+    /// Appends a rule.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Error::Rule`] if the rule is invalid or its name is taken.
+    pub fn push(&mut self, rule: Rule) -> Result<()> {
+        rule.validate()?;
+        if self.get(&rule.name).is_some() {
+            return Err(Error::Rule(format!(
+                "a rule named '{}' already exists",
+                rule.name
+            )));
+        }
+        self.rules.push(rule);
+        Ok(())
+    }
+
+    /// Removes the rule with the given name, returning it if it existed.
+    pub fn remove(&mut self, name: &str) -> Option<Rule> {
+        let index = self.rules.iter().position(|rule| rule.name == name)?;
+        Some(self.rules.remove(index))
+    }
+
+    /// Returns the rule with the given name.
+    pub fn get(&self, name: &str) -> Option<&Rule> {
+        self.rules.iter().find(|rule| rule.name == name)
+    }
+
+    /// Returns all rules, in the order they were added.
+    pub fn rules(&self) -> &[Rule] {
+        &self.rules
+    }
+
+    /// Returns the rules matching `folder`, in evaluation order.
+    ///
+    /// Rules after the first exclusive one are left out.
+    pub fn matching(&self, folder: &Path) -> Vec<&Rule> {
+        let mut matching: Vec<&Rule> = self.rules.iter().filter(|rule| rule.matches(folder)).collect();
+        // Stable, so equal priorities keep their order
+        matching.sort_by_key(|rule| std::cmp::Reverse(rule.priority));
+        if let Some(exclusive) = matching.iter().position(|rule| rule.exclusive) {
+            matching.truncate(exclusive + 1);
+        }
+        matching
+    }
+
+    /// Returns the highest-priority rule matching `folder`.
+    pub fn first_match(&self, folder: &Path) -> Option<&Rule> {
+        self.matching(folder).into_iter().next()
+    }
+
+    /// Decides the customization for `folder`, according to the mode.
+    ///
+    /// Returns `None` if no rule matches.
+    pub fn evaluate(&self, folder: &Path) -> Option<RuleEvaluation> {
+        let matching = self.matching(folder);
+        let winner = *matching.first()?;
+
+        let mut rule = winner.clone();
+        let mut applied = vec![winner.name.clone()];
+        if self.mode == RuleMode::MergeAll {
+            for other in &matching[1..] {
+                let contributes = (rule.color.is_none() && other.color.is_some())
+                    || (rule.preset.is_none() && other.preset.is_some());
+                if !contributes {
+                    continue;
+                }
+                rule.color = rule.color.or(other.color);
+                if rule.preset.is_none() {
+                    rule.preset.clone_from(&other.preset);
+                }
+                applied.push(other.name.clone());
+            }
+        }
+
+        let colors: Vec<(String, FolderColor)> = matching
+            .iter()
+            .filter_map(|rule| rule.color.map(|color| (rule.name.clone(), color)))
+            .collect();
+        let contradictory = colors.iter().any(|(_, color)| *color != colors[0].1);
+        let conflict = contradictory.then(|| RuleConflict {
+            path: folder.to_path_buf(),
+            winner: colors[0].0.clone(),
+            colors,
+        });
+
+        Some(RuleEvaluation {
+            rule,
+            applied,
+            conflict,
+        })
+    }
+
+    /// Returns `true` if there are no rules.
+    pub fn is_empty(&self) -> bool {
+        self.rules.is_empty()
+    }
+}
+
+/// A folder a rules pass would customize.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RuleMatch {
+    /// The matched folder.
+    pub path: PathBuf,
+    /// The customization the rules decide on, named after the winning rule.
+    pub rule: Rule,
+    /// Names of the rules that contributed, in evaluation order.
+    pub applied: Vec<String>,
+    /// The profile the rule would apply.
+    pub profile: CustomizationProfile,
+    /// Rules that disagreed on the folder's color, if any did.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub conflict: Option<RuleConflict>,
+    /// Whether folco has already customized the folder, in which case the
+    /// conflict policy decides what happens to it.
+    pub already_customized: bool,
+}
+
+/// Returns every folder below `root`, depth first and sorted by name.
+///
+/// `root` itself is not included, and symlinks are not followed. Unreadable
+/// directories are skipped.
+pub(crate) fn subfolders(root: &Path) -> Vec<PathBuf> {
+    let mut folders = Vec::new();
+    let mut pending = vec![root.to_path_buf()];
+    while let Some(dir) = pending.pop() {
+        let Ok(entries) = fs::read_dir(&dir) else {
+            continue;
+        };
+        let mut children: Vec<PathBuf> = entries
+            .flatten()
+            .filter(|entry| entry.file_type().is_ok_and(|t| t.is_dir()))
+            .map(|entry| entry.path())
+            .collect();
+        children.sort();
+        // Reversed so the stack pops them in name order
+        pending.extend(children.iter().rev().cloned());
+        folders.extend(children);
+    }
+    folders.sort();
+    folders
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_name_patterns() {
+        let rule = Rule::new("invoices", "invoice*").with_color(FolderColor::Green);
+        assert!(rule.matches(Path::new("/srv/finance/Invoices 2024")));
+        assert!(!rule.matches(Path::new("/srv/invoices/receipts")));
+        assert!(!rule.clone().with_enabled(false).matches(Path::new("/srv/invoices")));
+    }
+
+    #[test]
+    fn test_path_patterns() {
+        let rule = Rule::new("clients", "Clients/*").with_color(FolderColor::Blue);
+        assert!(rule.matches(Path::new("/home/user/Clients/acme")));
+        assert!(!rule.matches(Path::new("/home/user/Clients/acme/src")));
+        assert!(!rule.matches(Path::new("acme")));
+    }
+
+    #[test]
+    fn test_conditions() {
+        let temp = tempfile::tempdir().unwrap();
+        let empty = temp.path().join("empty");
+        let full = temp.path().join("full");
+        fs::create_dir(&empty).unwrap();
+        fs::create_dir(&full).unwrap();
+        fs::write(full.join("notes.txt"), "hi").unwrap();
+
+        let rule = Rule::new("empty", "*")
+            .with_color(FolderColor::Grey)
+            .with_condition(RuleCondition::Empty(true));
+        assert!(rule.matches(&empty));
+        assert!(!rule.matches(&full));
+
+        let bad_owner = rule.with_condition(RuleCondition::Owner(" ".to_string()));
+        assert!(bad_owner.validate().is_err());
+    }
+
+    #[test]
+    fn test_subfolders() {
+        let temp = tempfile::tempdir().unwrap();
+        fs::create_dir_all(temp.path().join("b").join("inner")).unwrap();
+        fs::create_dir(temp.path().join("a")).unwrap();
+        fs::write(temp.path().join("file.txt"), "").unwrap();
+
+        let folders = subfolders(temp.path());
+        assert_eq!(
+            folders,
+            vec![
+                temp.path().join("a"),
+                temp.path().join("b"),
+                temp.path().join("b").join("inner"),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_rule_set_validation_and_order() {
+        let mut rules = RuleSet::new();
+        assert!(rules.push(Rule::new("empty", "*")).is_err());
+        assert!(rules.push(Rule::new("bad", "[").with_color(FolderColor::Red)).is_err());
+
+        rules.push(Rule::new("first", "a*").with_color(FolderColor::Red)).unwrap();
+        rules.push(Rule::new("second", "*").with_color(FolderColor::Blue)).unwrap();
+        assert!(rules.push(Rule::new("first", "b").with_color(FolderColor::Red)).is_err());
+
+        assert_eq!(rules.first_match(Path::new("/x/apple")).unwrap().name, "first");
+        assert_eq!(rules.first_match(Path::new("/x/pear")).unwrap().name, "second");
+    }
+
+    #[test]
+    fn test_priority_and_conflicts() {
+        let mut rules = RuleSet::new();
+        rules.push(Rule::new("all", "*").with_color(FolderColor::Blue)).unwrap();
+        rules
+            .push(Rule::new("invoices", "invoice*").with_color(FolderColor::Green).with_priority(10))
+            .unwrap();
+
+        let evaluation = rules.evaluate(Path::new("/x/invoices")).unwrap();
+        assert_eq!(evaluation.rule.name, "invoices");
+        assert_eq!(evaluation.rule.color, Some(FolderColor::Green));
+        let conflict = evaluation.conflict.unwrap();
+        assert_eq!(conflict.winner, "invoices");
+        assert_eq!(conflict.colors.len(), 2);
+
+        assert!(rules.evaluate(Path::new("/x/photos")).unwrap().conflict.is_none());
+    }
+
+    #[test]
+    fn test_merge_all_and_exclusive() {
+        let mut rules = RuleSet::new().with_mode(RuleMode::MergeAll);
+        rules.push(Rule::new("style", "*").with_preset("Outline")).unwrap();
+        rules
+            .push(Rule::new("red", "urgent*").with_color(FolderColor::Red).with_priority(1))
+            .unwrap();
+
+        let evaluation = rules.evaluate(Path::new("/x/urgent")).unwrap();
+        assert_eq!(evaluation.applied, vec!["red", "style"]);
+        assert_eq!(evaluation.rule.color, Some(FolderColor::Red));
+        assert_eq!(evaluation.rule.preset.as_deref(), Some("Outline"));
+
+        rules
+            .push(
+                Rule::new("archive", "*old")
+                    .with_color(FolderColor::Grey)
+                    .with_priority(5)
+                    .with_exclusive(true),
+            )
+            .unwrap();
+        let evaluation = rules.evaluate(Path::new("/x/urgent old")).unwrap();
+        assert_eq!(evaluation.applied, vec!["archive"]);
+        assert!(evaluation.conflict.is_none());
+    }
+
+    #[test]
+    fn test_legacy_rule_list_loads() {
+        let json = r#"[{"name": "a", "pattern": "*", "color": "red"}]"#;
+        let rules: RuleSet = serde_json::from_str(json).unwrap();
+        assert_eq!(rules.mode(), RuleMode::FirstMatch);
+        assert_eq!(rules.rules().len(), 1);
+        assert_eq!(rules.rules()[0].priority, 0);
+
+        let saved = serde_json::to_string(&rules).unwrap();
+        assert_eq!(serde_json::from_str::<RuleSet>(&saved).unwrap(), rules);
+    }
+}
@@ -0,0 +1,194 @@
+//! Sharing rule sets as portable files.
+//!
+//! A ruleset file holds a [`RuleSet`] together with a format version, so a
+//! team can keep one standard set of rules for a shared drive and everyone
+//! can import it. Imports are validated as a whole before anything changes,
+//! and can be previewed with a dry run.
+
+use crate::error::{Error, Result};
+use crate::rules::{Rule, RuleMode, RuleSet};
+use crate::store::write_atomic;
+
+use serde::{Deserialize, Serialize};
+
+use std::fs;
+use std::path::Path;
+
+/// Conventional file name for an exported rule set.
+pub const RULESET_FILE_NAME: &str = "ruleset.json";
+
+/// Version of the ruleset file format written by this version of folco.
+pub const RULESET_FORMAT_VERSION: u32 = 1;
+
+/// On-disk layout of a ruleset file.
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct RuleSetFile {
+    version: u32,
+    #[serde(default)]
+    mode: RuleMode,
+    #[serde(default)]
+    rules: Vec<Rule>,
+}
+
+/// Writes `rules` to a ruleset file at `path`.
+pub fn export_ruleset(rules: &RuleSet, path: &Path) -> Result<()> {
+    let file = RuleSetFile {
+        version: RULESET_FORMAT_VERSION,
+        mode: rules.mode(),
+        rules: rules.rules().to_vec(),
+    };
+    let json = serde_json::to_string_pretty(&file)
+        .map_err(|e| Error::Serialization(e.to_string()))?;
+    write_atomic(path, &json)
+}
+
+/// Reads and validates the ruleset file at `path`.
+///
+/// # Errors
+///
+/// Returns [`Error::Rule`] if the file was written by a newer format version,
+/// or if any rule in it is invalid or duplicated. Every problem is listed,
+/// not just the first.
+pub fn read_ruleset(path: &Path) -> Result<RuleSet> {
+    let content = fs::read_to_string(path)?;
+    let file: RuleSetFile =
+        serde_json::from_str(&content).map_err(|e| Error::Serialization(e.to_string()))?;
+    if file.version > RULESET_FORMAT_VERSION {
+        return Err(Error::Rule(format!(
+            "ruleset file version {} is newer than the supported version {RULESET_FORMAT_VERSION}",
+            file.version
+        )));
+    }
+
+    let mut rules = RuleSet::new().with_mode(file.mode);
+    let mut problems = Vec::new();
+    for (index, rule) in file.rules.into_iter().enumerate() {
+        if let Err(e) = rules.push(rule) {
+            problems.push(format!("rule {}: {e}", index + 1));
+        }
+    }
+    if !problems.is_empty() {
+        return Err(Error::Rule(problems.join("; ")));
+    }
+    Ok(rules)
+}
+
+/// What importing a ruleset file changes, or would change.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RuleImportReport {
+    /// Names of rules that are new.
+    pub added: Vec<String>,
+    /// Names of rules that exist with different settings.
+    pub changed: Vec<String>,
+    /// Names of rules that exist with the same settings.
+    pub unchanged: Vec<String>,
+    /// Names of current rules the file doesn't have.
+    pub removed: Vec<String>,
+    /// Presets named by imported rules that don't exist here. Those rules
+    /// fail when applied until the presets are created.
+    pub missing_presets: Vec<String>,
+    /// Whether the matching mode differs from the current one.
+    pub mode_changed: bool,
+    /// Whether the rules were actually replaced.
+    pub applied: bool,
+}
+
+impl RuleImportReport {
+    /// Compares the current rules with the imported ones.
+    pub(crate) fn compare(current: &RuleSet, imported: &RuleSet) -> Self {
+        let mut report = Self {
+            mode_changed: current.mode() != imported.mode(),
+            ..Default::default()
+        };
+        for rule in imported.rules() {
+            match current.get(&rule.name) {
+                None => report.added.push(rule.name.clone()),
+                Some(existing) if existing == rule => report.unchanged.push(rule.name.clone()),
+                Some(_) => report.changed.push(rule.name.clone()),
+            }
+        }
+        report.removed = current
+            .rules()
+            .iter()
+            .filter(|rule| imported.get(&rule.name).is_none())
+            .map(|rule| rule.name.clone())
+            .collect();
+        report
+    }
+
+    /// Returns `true` if importing changes nothing.
+    pub fn is_noop(&self) -> bool {
+        self.added.is_empty()
+            && self.changed.is_empty()
+            && self.removed.is_empty()
+            && !self.mode_changed
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::color::FolderColor;
+    use tempfile::tempdir;
+
+    fn sample() -> RuleSet {
+        let mut rules = RuleSet::new().with_mode(RuleMode::MergeAll);
+        rules.push(Rule::new("invoices", "invoice*").with_color(FolderColor::Green)).unwrap();
+        rules.push(Rule::new("clients", "Clients/*").with_preset("Client")).unwrap();
+        rules
+    }
+
+    #[test]
+    fn test_export_and_read() {
+        let temp = tempdir().unwrap();
+        let path = temp.path().join(RULESET_FILE_NAME);
+        export_ruleset(&sample(), &path).unwrap();
+
+        let content = fs::read_to_string(&path).unwrap();
+        assert!(content.contains("\"version\": 1"));
+        assert_eq!(read_ruleset(&path).unwrap(), sample());
+    }
+
+    #[test]
+    fn test_invalid_rules_are_all_reported() {
+        let temp = tempdir().unwrap();
+        let path = temp.path().join(RULESET_FILE_NAME);
+        fs::write(
+            &path,
+            r#"{"version": 1, "rules": [
+                {"name": "a", "pattern": "[", "color": "red"},
+                {"name": "b", "pattern": "*"}
+            ]}"#,
+        )
+        .unwrap();
+
+        let message = read_ruleset(&path).unwrap_err().to_string();
+        assert!(message.contains("rule 1"));
+        assert!(message.contains("rule 2"));
+    }
+
+    #[test]
+    fn test_newer_version_is_rejected() {
+        let temp = tempdir().unwrap();
+        let path = temp.path().join(RULESET_FILE_NAME);
+        fs::write(&path, r#"{"version": 99, "rules": []}"#).unwrap();
+        assert!(matches!(read_ruleset(&path), Err(Error::Rule(_))));
+    }
+
+    #[test]
+    fn test_compare() {
+        let mut current = RuleSet::new();
+        current.push(Rule::new("invoices", "invoice*").with_color(FolderColor::Green)).unwrap();
+        current.push(Rule::new("old", "old*").with_color(FolderColor::Grey)).unwrap();
+
+        let report = RuleImportReport::compare(&current, &sample());
+        assert_eq!(report.added, vec!["clients"]);
+        assert_eq!(report.unchanged, vec!["invoices"]);
+        assert_eq!(report.removed, vec!["old"]);
+        assert!(report.mode_changed);
+        assert!(!report.is_noop());
+        assert!(RuleImportReport::compare(&sample(), &sample()).is_noop());
+    }
+}
@@ -0,0 +1,386 @@
+//! Black-box tests against a sandboxed context, with the `simulated`
+//! feature.
+//!
+//! [`TestSandbox`] builds a [`CustomizationContext`] whose cache, app data
+//! and folders all live in a fresh temporary directory. The context's
+//! backends are a [`SimulatedBackend`] of the sandbox's own, so its base
+//! icon is the Windows [fixture](crate::fixtures), icons are set in memory,
+//! and tests behave the same on every platform. Downstream apps can enable the feature in
+//! their dev-dependencies and test against folco-core's real behavior:
+//!
+//! ```ignore
+//! use folco_core::color::FolderColor;
+//! use folco_core::sandbox::TestSandbox;
+//!
+//! let mut sandbox = TestSandbox::with_folders(&["Projects", "Photos/2024"])?;
+//! sandbox.customize_with_color("Projects", FolderColor::Blue)?;
+//! sandbox.assert_color("Projects", FolderColor::Blue);
+//! sandbox.assert_default("Photos/2024");
+//! ```
+//!
+//! The sandbox's directory is removed when it's dropped.
+
+use crate::backend::FolderIconBackend;
+use crate::color::FolderColor;
+use crate::context::{CustomizationContext, CustomizationContextBuilder};
+use crate::error::Result;
+use crate::paths::normalize_folder_path;
+use crate::profile::{profile_color, profile_with_color};
+use crate::reconcile::{ReconcileOptions, ReconcileReport};
+use crate::simulated::SimulatedBackend;
+use crate::store::StoredProfile;
+
+use folco_renderer::CustomizationProfile;
+
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+
+/// Number of sandboxes created by this process, for unique directory names.
+static SANDBOX_COUNT: AtomicUsize = AtomicUsize::new(0);
+
+/// A context and folder tree in a temporary directory, for end-to-end
+/// tests.
+///
+/// Folders are named by their path relative to the [root](Self::root),
+/// with `/` separators.
+pub struct TestSandbox {
+    dir: PathBuf,
+    root: PathBuf,
+    backend: Arc<SimulatedBackend>,
+    context: CustomizationContext,
+}
+
+impl TestSandbox {
+    /// Creates an empty sandbox.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the temporary directory or the context can't be
+    /// created.
+    pub fn new() -> Result<Self> {
+        let count = SANDBOX_COUNT.fetch_add(1, Ordering::Relaxed);
+        let dir = normalize_folder_path(
+            &std::env::temp_dir().join(format!("folco-sandbox-{}-{count}", std::process::id())),
+        );
+        // A leftover from an earlier run with the same process ID
+        if dir.exists() {
+            fs::remove_dir_all(&dir)?;
+        }
+        let root = dir.join("folders");
+        fs::create_dir_all(&root)?;
+
+        let backend = Arc::new(SimulatedBackend::new());
+        let context = CustomizationContextBuilder::new()
+            .with_cache_dir(dir.join("cache"))
+            .with_data_dir(dir.join("data"))
+            .with_folder_backend(Arc::clone(&backend) as _)
+            .with_default_icon_backend(Arc::clone(&backend) as _)
+            .build()?;
+        Ok(Self {
+            dir,
+            root,
+            backend,
+            context,
+        })
+    }
+
+    /// Creates a sandbox with `folders` already created.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the sandbox or a folder can't be created.
+    pub fn with_folders(folders: &[&str]) -> Result<Self> {
+        let sandbox = Self::new()?;
+        for folder in folders {
+            sandbox.create_folder(folder)?;
+        }
+        Ok(sandbox)
+    }
+
+    /// Returns the directory the folder tree is in.
+    pub fn root(&self) -> &Path {
+        &self.root
+    }
+
+    /// Returns the full path of `folder`.
+    pub fn path(&self, folder: &str) -> PathBuf {
+        folder
+            .split('/')
+            .filter(|part| !part.is_empty())
+            .fold(self.root.clone(), |path, part| path.join(part))
+    }
+
+    /// Creates `folder` and any missing parents, returning its full path.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the folder can't be created.
+    pub fn create_folder(&self, folder: &str) -> Result<PathBuf> {
+        let path = self.path(folder);
+        fs::create_dir_all(&path)?;
+        Ok(path)
+    }
+
+    /// Returns the sandbox's context.
+    pub fn context(&self) -> &CustomizationContext {
+        &self.context
+    }
+
+    /// Returns the sandbox's context for modification.
+    pub fn context_mut(&mut self) -> &mut CustomizationContext {
+        &mut self.context
+    }
+
+    /// Returns the backend the context sets folder icons through.
+    pub fn backend(&self) -> &SimulatedBackend {
+        &self.backend
+    }
+
+    /// Customizes `folder` with `profile`.
+    ///
+    /// # Errors
+    ///
+    /// Returns the error customizing the folder failed with.
+    pub fn customize(&mut self, folder: &str, profile: &CustomizationProfile) -> Result<()> {
+        let path = self.path(folder);
+        self.context.customize_folder(path, profile)
+    }
+
+    /// Customizes `folder` with the default profile recolored to `color`.
+    ///
+    /// # Errors
+    ///
+    /// Returns the error customizing the folder failed with.
+    pub fn customize_with_color(&mut self, folder: &str, color: FolderColor) -> Result<()> {
+        let profile = profile_with_color(&CustomizationProfile::default(), color)?;
+        self.customize(folder, &profile)
+    }
+
+    /// Resets `folder` to the default icon.
+    ///
+    /// # Errors
+    ///
+    /// Returns the error resetting the folder failed with.
+    pub fn reset(&self, folder: &str) -> Result<()> {
+        self.context.reset_folder(self.path(folder))
+    }
+
+    /// Reconciles the profile store with the folder tree, reporting folders
+    /// that were moved or deleted without pruning them.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the store can't be saved after remapping.
+    pub fn scan(&self) -> Result<ReconcileReport> {
+        self.context
+            .reconcile_store(&ReconcileOptions::new().with_search_root(&self.root))
+    }
+
+    /// Returns `true` if `folder` has a custom icon.
+    pub fn is_customized(&self, folder: &str) -> bool {
+        self.backend.has_custom_icon(&self.full_path(folder))
+    }
+
+    /// Returns the profile recorded for `folder`, if any.
+    pub fn stored_profile(&self, folder: &str) -> Option<StoredProfile> {
+        self.context.store().get(&self.full_path(folder))
+    }
+
+    /// Returns every folder with a custom icon, relative to the root and
+    /// sorted.
+    pub fn customized_folders(&self) -> Vec<String> {
+        self.backend
+            .customized_folders()
+            .iter()
+            .filter_map(|path| path.strip_prefix(&self.root).ok())
+            .map(|path| {
+                let parts: Vec<_> = path.iter().map(|part| part.to_string_lossy()).collect();
+                parts.join("/")
+            })
+            .collect()
+    }
+
+    /// Asserts that `folder` has a custom icon and a stored profile.
+    ///
+    /// # Panics
+    ///
+    /// Panics if either is missing.
+    #[track_caller]
+    pub fn assert_customized(&self, folder: &str) {
+        assert!(
+            self.is_customized(folder),
+            "{folder} doesn't have a custom icon"
+        );
+        assert!(
+            self.stored_profile(folder).is_some(),
+            "{folder} has a custom icon but no stored profile"
+        );
+    }
+
+    /// Asserts that `folder` has the default icon and no stored profile.
+    ///
+    /// # Panics
+    ///
+    /// Panics if it has either.
+    #[track_caller]
+    pub fn assert_default(&self, folder: &str) {
+        assert!(!self.is_customized(folder), "{folder} has a custom icon");
+        assert!(
+            self.stored_profile(folder).is_none(),
+            "{folder} has the default icon but a stored profile"
+        );
+    }
+
+    /// Asserts that `folder` is customized with `color`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if it isn't customized, or with a different color.
+    #[track_caller]
+    pub fn assert_color(&self, folder: &str, color: FolderColor) {
+        self.assert_customized(folder);
+        let stored = self.stored_profile(folder).map(|stored| stored.profile);
+        let actual = stored.as_ref().and_then(profile_color);
+        assert_eq!(actual, Some(color), "{folder} has the wrong color");
+    }
+
+    /// Returns the normalized full path of `folder`, as the context records
+    /// it.
+    fn full_path(&self, folder: &str) -> PathBuf {
+        normalize_folder_path(&self.path(folder))
+    }
+}
+
+impl std::fmt::Debug for TestSandbox {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("TestSandbox")
+            .field("root", &self.root)
+            .finish_non_exhaustive()
+    }
+}
+
+impl Drop for TestSandbox {
+    fn drop(&mut self) {
+        let _ = fs::remove_dir_all(&self.dir);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::icon_state::FolderIconState;
+
+    #[test]
+    fn test_customize_and_reset() {
+        let mut sandbox = TestSandbox::with_folders(&["Projects", "Photos/2024"]).unwrap();
+        sandbox
+            .customize_with_color("Projects", FolderColor::Blue)
+            .unwrap();
+        sandbox.assert_color("Projects", FolderColor::Blue);
+        sandbox.assert_default("Photos/2024");
+        assert_eq!(sandbox.customized_folders(), ["Projects"]);
+
+        sandbox.reset("Projects").unwrap();
+        sandbox.assert_default("Projects");
+        assert!(sandbox.customized_folders().is_empty());
+    }
+
+    #[test]
+    fn test_scan_reports_deleted_folders() {
+        let mut sandbox = TestSandbox::with_folders(&["Old"]).unwrap();
+        sandbox
+            .customize_with_color("Old", FolderColor::Red)
+            .unwrap();
+        assert!(sandbox.scan().unwrap().is_clean());
+
+        fs::remove_dir(sandbox.path("Old")).unwrap();
+        let report = sandbox.scan().unwrap();
+        assert_eq!(report.missing, [sandbox.path("Old")]);
+    }
+
+    #[test]
+    fn test_folder_icon_state() {
+        let mut sandbox = TestSandbox::with_folders(&["Projects", "Photos"]).unwrap();
+        sandbox
+            .customize_with_color("Projects", FolderColor::Blue)
+            .unwrap();
+        let state = sandbox.context().folder_icon_state(&sandbox.path("Projects"));
+        assert!(matches!(state, FolderIconState::CustomizedByFolco { .. }));
+        let photos = sandbox.path("Photos");
+        assert_eq!(sandbox.context().folder_icon_state(&photos), FolderIconState::Default);
+    }
+
+    #[test]
+    fn test_scan_customized() {
+        let mut sandbox = TestSandbox::with_folders(&["Work/Acme", "Work/Old", "Music"]).unwrap();
+        sandbox
+            .customize_with_color("Work/Acme", FolderColor::Red)
+            .unwrap();
+        let found = sandbox
+            .context()
+            .scan_customized(&sandbox.path("Work"), &crate::ScanOptions::new())
+            .unwrap();
+        assert_eq!(found.len(), 1);
+        assert_eq!(found[0].path, sandbox.full_path("Work/Acme"));
+        assert!(found[0].is_folco());
+    }
+
+    #[test]
+    fn test_stacked_profile_keeps_the_color() {
+        let mut sandbox = TestSandbox::with_folders(&["Projects"]).unwrap();
+        sandbox
+            .customize_with_color("Projects", FolderColor::Blue)
+            .unwrap();
+        let decal =
+            crate::emoji::profile_with_emoji(&CustomizationProfile::default(), "\u{1F680}")
+                .unwrap();
+        let folder = sandbox.path("Projects");
+        let outcome = sandbox
+            .context_mut()
+            .customize_folders_stacked(&[&folder], &decal);
+        assert!(outcome.is_success());
+
+        sandbox.assert_color("Projects", FolderColor::Blue);
+        let stored = sandbox.stored_profile("Projects").unwrap().profile;
+        assert_eq!(crate::profile::profile_decals(&stored).len(), 1);
+    }
+
+    #[test]
+    fn test_render_only_leaves_the_data_dir_alone() {
+        let temp = tempfile::tempdir().unwrap();
+        // Below a plain file, so the directory can't be created or written to
+        let blocker = temp.path().join("blocker");
+        fs::write(&blocker, "").unwrap();
+        let data_dir = blocker.join("data");
+
+        let mut context = CustomizationContextBuilder::new()
+            .with_cache_dir(temp.path().join("cache"))
+            .with_data_dir(&data_dir)
+            .with_default_icon_backend(Arc::new(SimulatedBackend::new()))
+            .render_only()
+            .build()
+            .unwrap();
+        assert!(context.render().is_ok());
+        assert_eq!(context.log().path(), None);
+
+        let folder = temp.path().join("Projects");
+        fs::create_dir(&folder).unwrap();
+        let outcome = context.customize_folders(&[&folder], &CustomizationProfile::default());
+        let (_, error) = outcome.failed().next().unwrap();
+        assert_eq!(error.kind(), "unsupported");
+        assert!(blocker.is_file());
+    }
+
+    #[test]
+    fn test_drop_cleans_up() {
+        let mut sandbox = TestSandbox::with_folders(&["Music"]).unwrap();
+        sandbox
+            .customize_with_color("Music", FolderColor::Green)
+            .unwrap();
+        let dir = sandbox.dir.clone();
+        drop(sandbox);
+        assert!(!dir.exists());
+    }
+}
@@ -0,0 +1,977 @@
+//! Sanitizing user-provided SVG decals.
+//!
+//! Inline SVG in a profile comes from whoever shared the preset, so it's
+//! cleaned before the renderer sees it. [`sanitize_svg`] parses the markup
+//! into a tree, with limits on its size and depth, drops what has no place
+//! in a folder icon, and writes the rest back out:
+//!
+//! - the document type, along with any entities it declares, and
+//!   references to entities other than XML's own
+//! - scripts, foreign objects and embedded documents, animations that
+//!   retarget links, and event handler attributes
+//! - links, `url()` references and stylesheets pointing outside the
+//!   document; only fragment links and embedded raster images are kept
+//! - filters with too many primitives or a radius large enough to stall
+//!   the renderer
+//! - root `width` and `height` above [`MAX_SVG_DIMENSION`]
+//!
+//! Markup that can't be parsed, or is over a limit, is replaced by an
+//! empty SVG. Every removal is listed in the [`SanitizeReport`]. Contexts
+//! sanitize each profile they apply, so this is only needed directly to
+//! show users what a shared preset will lose.
+
+use crate::error::{Error, Result};
+
+use folco_renderer::CustomizationProfile;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use std::borrow::Cow;
+use std::fmt;
+
+/// Largest SVG, in bytes, that's parsed at all.
+pub const MAX_SVG_BYTES: usize = 1024 * 1024;
+
+/// Largest `width` or `height` kept on the root element, in pixels.
+pub const MAX_SVG_DIMENSION: f32 = 4096.0;
+
+/// Most elements an SVG may have.
+pub const MAX_SVG_ELEMENTS: usize = 10_000;
+
+/// Deepest elements may be nested.
+const MAX_DEPTH: usize = 64;
+
+/// Most primitives a filter may have.
+const MAX_FILTER_PRIMITIVES: usize = 16;
+
+/// Largest blur, morphology or shadow radius a filter may use.
+const MAX_FILTER_RADIUS: f32 = 64.0;
+
+/// Most octaves a turbulence primitive may use.
+const MAX_TURBULENCE_OCTAVES: f32 = 8.0;
+
+/// Largest convolution matrix a filter may use, in cells.
+const MAX_CONVOLVE_CELLS: f32 = 25.0;
+
+/// Elements removed with everything inside them.
+const BLOCKED_ELEMENTS: &[&str] = &[
+    "script",
+    "foreignobject",
+    "iframe",
+    "embed",
+    "object",
+    "handler",
+    "listener",
+];
+
+/// Raster images that may be embedded as `data:` links.
+const SAFE_DATA_PREFIXES: &[&str] = &[
+    "data:image/png",
+    "data:image/jpeg",
+    "data:image/gif",
+    "data:image/webp",
+];
+
+/// The markup left when an SVG has to be thrown out.
+const EMPTY_SVG: &str = r#"<svg xmlns="http://www.w3.org/2000/svg"/>"#;
+
+/// Something removed from an SVG.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(
+    tag = "kind",
+    rename_all = "kebab-case",
+    rename_all_fields = "camelCase"
+)]
+pub enum SanitizeRemoval {
+    /// The whole SVG was replaced by an empty one.
+    Rejected {
+        /// Why it couldn't be kept.
+        reason: String,
+    },
+    /// The document type declaration, and any entities it declared.
+    Doctype,
+    /// A reference to an entity other than XML's predefined ones.
+    Entity {
+        /// Name of the entity.
+        name: String,
+    },
+    /// A processing instruction, such as a stylesheet link.
+    ProcessingInstruction {
+        /// Its target, such as `xml-stylesheet`.
+        target: String,
+    },
+    /// An element that can run code or load documents, with its contents.
+    Element {
+        /// Name of the element.
+        name: String,
+    },
+    /// An event handler attribute.
+    EventHandler {
+        /// Element the attribute was on.
+        element: String,
+        /// Name of the attribute, such as `onload`.
+        attribute: String,
+    },
+    /// A link or stylesheet pointing outside the document.
+    ExternalReference {
+        /// Element the reference was on.
+        element: String,
+        /// The reference.
+        value: String,
+    },
+    /// A filter expensive enough to stall the renderer.
+    Filter {
+        /// ID of the filter, if it had one.
+        id: Option<String>,
+        /// What made it too expensive.
+        reason: String,
+    },
+    /// A root dimension over [`MAX_SVG_DIMENSION`].
+    Dimension {
+        /// `width` or `height`.
+        attribute: String,
+        /// The value it had.
+        value: String,
+    },
+}
+
+impl fmt::Display for SanitizeRemoval {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Rejected { reason } => write!(f, "discarded the SVG: {reason}"),
+            Self::Doctype => f.write_str("removed the document type declaration"),
+            Self::Entity { name } => write!(f, "removed a reference to entity {name}"),
+            Self::ProcessingInstruction { target } => {
+                write!(f, "removed a {target} processing instruction")
+            }
+            Self::Element { name } => write!(f, "removed a <{name}> element"),
+            Self::EventHandler { element, attribute } => {
+                write!(
+                    f,
+                    "removed the {attribute} handler of a <{element}> element"
+                )
+            }
+            Self::ExternalReference { element, value } => {
+                write!(
+                    f,
+                    "removed a reference to {value} from a <{element}> element"
+                )
+            }
+            Self::Filter {
+                id: Some(id),
+                reason,
+            } => write!(f, "removed filter {id}: {reason}"),
+            Self::Filter { id: None, reason } => write!(f, "removed a filter: {reason}"),
+            Self::Dimension { attribute, value } => {
+                write!(f, "removed the root {attribute} of {value}")
+            }
+        }
+    }
+}
+
+/// What sanitizing removed, from one SVG or a whole profile.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SanitizeReport {
+    /// Every construct removed, in document order.
+    pub removed: Vec<SanitizeRemoval>,
+}
+
+impl SanitizeReport {
+    /// Returns `true` if nothing was removed.
+    pub fn is_clean(&self) -> bool {
+        self.removed.is_empty()
+    }
+
+    fn remove(&mut self, removal: SanitizeRemoval) {
+        if !self.removed.contains(&removal) {
+            self.removed.push(removal);
+        }
+    }
+}
+
+/// Returns `svg` with everything unsafe removed, and what was removed.
+///
+/// Never fails: markup that can't be parsed is replaced by an empty SVG,
+/// which the report records as [`SanitizeRemoval::Rejected`].
+pub fn sanitize_svg(svg: &str) -> (String, SanitizeReport) {
+    let mut report = SanitizeReport::default();
+    let parsed = if svg.len() > MAX_SVG_BYTES {
+        Err(format!("it's over {MAX_SVG_BYTES} bytes"))
+    } else {
+        Parser::new(svg, &mut report).parse_document()
+    };
+    match parsed {
+        Ok(root) => {
+            let mut output = String::with_capacity(svg.len());
+            if let Some(root) = sanitize_element(root, true, &mut report) {
+                write_element(&root, &mut output);
+            } else {
+                output.push_str(EMPTY_SVG);
+            }
+            (output, report)
+        }
+        Err(reason) => {
+            // Whatever was found before the error no longer matters
+            report.removed = vec![SanitizeRemoval::Rejected { reason }];
+            (EMPTY_SVG.to_string(), report)
+        }
+    }
+}
+
+/// Returns `profile` with every inline SVG in it sanitized, and what was
+/// removed from all of them.
+///
+/// # Errors
+///
+/// Returns [`Error::Serialization`] if the sanitized profile can't be read
+/// back.
+pub fn sanitize_profile_svgs(
+    profile: &CustomizationProfile,
+) -> Result<(CustomizationProfile, SanitizeReport)> {
+    let (sanitized, report) = sanitized_for_render(profile)?;
+    Ok((sanitized.into_owned(), report))
+}
+
+/// Like [`sanitize_profile_svgs`], but borrows `profile` back when it has
+/// no inline SVG.
+pub(crate) fn sanitized_for_render(
+    profile: &CustomizationProfile,
+) -> Result<(Cow<'_, CustomizationProfile>, SanitizeReport)> {
+    let mut value =
+        serde_json::to_value(profile).map_err(|e| Error::Serialization(e.to_string()))?;
+    let mut report = SanitizeReport::default();
+    if !sanitize_strings(&mut value, &mut report) {
+        return Ok((Cow::Borrowed(profile), report));
+    }
+    let sanitized =
+        serde_json::from_value(value).map_err(|e| Error::Serialization(e.to_string()))?;
+    Ok((Cow::Owned(sanitized), report))
+}
+
+/// Returns `profile` with every inline SVG in it sanitized, for callers
+/// that can't report errors.
+///
+/// Falls back to the default profile if sanitizing fails, so unsanitized
+/// markup never reaches the renderer.
+pub(crate) fn sanitized_or_default(profile: &CustomizationProfile) -> CustomizationProfile {
+    sanitized_for_render(profile)
+        .map(|(sanitized, _)| sanitized.into_owned())
+        .unwrap_or_default()
+}
+
+/// Sanitizes every string in `value` that holds SVG markup, returning
+/// `true` if there were any.
+fn sanitize_strings(value: &mut Value, report: &mut SanitizeReport) -> bool {
+    match value {
+        Value::String(text) if looks_like_svg(text) => {
+            let (sanitized, removed) = sanitize_svg(text);
+            for removal in removed.removed {
+                report.remove(removal);
+            }
+            *text = sanitized;
+            true
+        }
+        Value::Array(items) => items
+            .iter_mut()
+            .fold(false, |found, item| sanitize_strings(item, report) | found),
+        Value::Object(map) => map
+            .values_mut()
+            .fold(false, |found, item| sanitize_strings(item, report) | found),
+        _ => false,
+    }
+}
+
+/// Returns `true` if `text` is SVG markup rather than, say, a path.
+fn looks_like_svg(text: &str) -> bool {
+    let text = text.trim_start();
+    text.starts_with('<') && text.to_ascii_lowercase().contains("<svg")
+}
+
+#[derive(Debug)]
+enum Node {
+    Element(Element),
+    Text(String),
+}
+
+#[derive(Debug)]
+struct Element {
+    name: String,
+    attributes: Vec<(String, String)>,
+    children: Vec<Node>,
+}
+
+impl Element {
+    fn attribute(&self, name: &str) -> Option<&str> {
+        self.attributes
+            .iter()
+            .find(|(key, _)| local_name(key).eq_ignore_ascii_case(name))
+            .map(|(_, value)| value.as_str())
+    }
+
+    fn child_elements(&self) -> impl Iterator<Item = &Element> {
+        self.children.iter().filter_map(|child| match child {
+            Node::Element(element) => Some(element),
+            Node::Text(_) => None,
+        })
+    }
+
+    fn text(&self) -> String {
+        self.children
+            .iter()
+            .filter_map(|child| match child {
+                Node::Text(text) => Some(text.as_str()),
+                Node::Element(_) => None,
+            })
+            .collect()
+    }
+}
+
+/// A parser for the subset of XML an SVG needs.
+///
+/// Text and attribute values are kept as written, with only their entity
+/// references checked, so writing them back out needs no re-escaping
+/// beyond quotes.
+struct Parser<'a> {
+    input: &'a str,
+    pos: usize,
+    elements: usize,
+    report: &'a mut SanitizeReport,
+}
+
+type ParseResult<T> = std::result::Result<T, String>;
+
+impl<'a> Parser<'a> {
+    fn new(input: &'a str, report: &'a mut SanitizeReport) -> Self {
+        Self {
+            input,
+            pos: 0,
+            elements: 0,
+            report,
+        }
+    }
+
+    fn rest(&self) -> &'a str {
+        &self.input[self.pos..]
+    }
+
+    fn starts_with(&self, prefix: &str) -> bool {
+        self.rest().starts_with(prefix)
+    }
+
+    fn skip_whitespace(&mut self) {
+        let rest = self.rest();
+        self.pos += rest.len() - rest.trim_start().len();
+    }
+
+    /// Skips past the next `end`, failing if there isn't one.
+    fn skip_past(&mut self, end: &str, what: &str) -> ParseResult<&'a str> {
+        let rest = self.rest();
+        let index = rest
+            .find(end)
+            .ok_or_else(|| format!("unterminated {what}"))?;
+        self.pos += index + end.len();
+        Ok(&rest[..index])
+    }
+
+    fn parse_document(&mut self) -> ParseResult<Element> {
+        let mut root = None;
+        loop {
+            self.skip_whitespace();
+            if self.pos == self.input.len() {
+                break;
+            }
+            if self.skip_misc()? {
+                continue;
+            }
+            if self.starts_with("<!") {
+                self.skip_doctype()?;
+            } else if self.starts_with("<") && root.is_none() {
+                root = Some(self.parse_element(0)?);
+            } else {
+                return Err("content outside the root element".to_string());
+            }
+        }
+        let root = root.ok_or_else(|| "no root element".to_string())?;
+        if !local_name(&root.name).eq_ignore_ascii_case("svg") {
+            return Err(format!("the root element is <{}>, not <svg>", root.name));
+        }
+        Ok(root)
+    }
+
+    /// Skips a comment or processing instruction, returning `false` if the
+    /// input isn't at one.
+    fn skip_misc(&mut self) -> ParseResult<bool> {
+        if self.starts_with("<!--") {
+            self.skip_past("-->", "comment")?;
+        } else if self.starts_with("<?") {
+            self.pos += 2;
+            let instruction = self.skip_past("?>", "processing instruction")?;
+            let target = instruction
+                .split(|c: char| c.is_whitespace())
+                .next()
+                .unwrap_or_default();
+            if !target.eq_ignore_ascii_case("xml") {
+                self.report.remove(SanitizeRemoval::ProcessingInstruction {
+                    target: target.to_string(),
+                });
+            }
+        } else {
+            return Ok(false);
+        }
+        Ok(true)
+    }
+
+    /// Skips a `<!DOCTYPE ...>`, including an internal subset, which may
+    /// hold `>` in brackets and quotes.
+    fn skip_doctype(&mut self) -> ParseResult<()> {
+        if !self.rest()[2..]
+            .get(..7)
+            .is_some_and(|keyword| keyword.eq_ignore_ascii_case("doctype"))
+        {
+            return Err("unexpected declaration".to_string());
+        }
+        let (mut depth, mut quote) = (0usize, None);
+        for (index, c) in self.rest().char_indices() {
+            match (quote, c) {
+                (Some(open), c) if c == open => quote = None,
+                (Some(_), _) => {}
+                (None, '"' | '\'') => quote = Some(c),
+                (None, '[') => depth += 1,
+                (None, ']') => depth = depth.saturating_sub(1),
+                (None, '>') if depth == 0 => {
+                    self.pos += index + 1;
+                    self.report.remove(SanitizeRemoval::Doctype);
+                    return Ok(());
+                }
+                _ => {}
+            }
+        }
+        Err("unterminated document type".to_string())
+    }
+
+    fn parse_name(&mut self) -> ParseResult<String> {
+        let rest = self.rest();
+        let end = rest.find(|c: char| !is_name_char(c)).unwrap_or(rest.len());
+        if end == 0 {
+            return Err("expected a name".to_string());
+        }
+        self.pos += end;
+        Ok(rest[..end].to_string())
+    }
+
+    fn expect(&mut self, token: &str) -> ParseResult<()> {
+        if !self.starts_with(token) {
+            return Err(format!("expected {token}"));
+        }
+        self.pos += token.len();
+        Ok(())
+    }
+
+    /// Parses the element starting at the input's `<`.
+    fn parse_element(&mut self, depth: usize) -> ParseResult<Element> {
+        if depth >= MAX_DEPTH {
+            return Err(format!("elements are nested over {MAX_DEPTH} deep"));
+        }
+        self.elements += 1;
+        if self.elements > MAX_SVG_ELEMENTS {
+            return Err(format!("it has over {MAX_SVG_ELEMENTS} elements"));
+        }
+
+        self.expect("<")?;
+        let name = self.parse_name()?;
+        let mut element = Element {
+            name,
+            attributes: Vec::new(),
+            children: Vec::new(),
+        };
+        loop {
+            self.skip_whitespace();
+            if self.starts_with("/>") {
+                self.pos += 2;
+                return Ok(element);
+            }
+            if self.starts_with(">") {
+                self.pos += 1;
+                break;
+            }
+            let key = self.parse_name()?;
+            self.skip_whitespace();
+            self.expect("=")?;
+            self.skip_whitespace();
+            let quote = match self.rest().chars().next() {
+                Some(quote @ ('"' | '\'')) => quote,
+                _ => return Err(format!("unquoted value for {key}")),
+            };
+            self.pos += 1;
+            let raw = self.skip_past(&quote.to_string(), "attribute value")?;
+            let value = self.check_entities(raw);
+            element.attributes.push((key, value));
+        }
+
+        loop {
+            if self.pos == self.input.len() {
+                return Err(format!("unclosed <{}>", element.name));
+            }
+            if self.starts_with("</") {
+                self.pos += 2;
+                let name = self.parse_name()?;
+                self.skip_whitespace();
+                self.expect(">")?;
+                if name != element.name {
+                    return Err(format!("<{}> closed by </{name}>", element.name));
+                }
+                return Ok(element);
+            }
+            if self.starts_with("<![CDATA[") {
+                self.pos += 9;
+                let text = self.skip_past("]]>", "CDATA section")?;
+                element.children.push(Node::Text(escape_text(text)));
+            } else if self.skip_misc()? {
+                // Comments and processing instructions are dropped
+            } else if self.starts_with("<!") {
+                return Err("unexpected declaration".to_string());
+            } else if self.starts_with("<") {
+                let child = self.parse_element(depth + 1)?;
+                element.children.push(Node::Element(child));
+            } else {
+                let rest = self.rest();
+                let end = rest.find('<').unwrap_or(rest.len());
+                self.pos += end;
+                let text = self.check_entities(&rest[..end]);
+                element.children.push(Node::Text(text));
+            }
+        }
+    }
+
+    /// Returns `raw` with references to anything but XML's predefined and
+    /// numeric entities removed, and stray ampersands escaped.
+    fn check_entities(&mut self, raw: &str) -> String {
+        let mut output = String::with_capacity(raw.len());
+        let mut rest = raw;
+        while let Some(index) = rest.find('&') {
+            output.push_str(&rest[..index]);
+            rest = &rest[index + 1..];
+            let name = rest
+                .find(';')
+                .filter(|&end| end > 0 && end <= 32)
+                .map(|end| &rest[..end])
+                .filter(|name| name.chars().all(|c| is_name_char(c) || c == '#'));
+            match name {
+                Some(name) if is_predefined_entity(name) => {
+                    output.push('&');
+                    output.push_str(name);
+                    output.push(';');
+                    rest = &rest[name.len() + 1..];
+                }
+                Some(name) => {
+                    self.report.remove(SanitizeRemoval::Entity {
+                        name: name.to_string(),
+                    });
+                    rest = &rest[name.len() + 1..];
+                }
+                None => output.push_str("&amp;"),
+            }
+        }
+        output.push_str(rest);
+        output
+    }
+}
+
+fn is_name_char(c: char) -> bool {
+    c.is_alphanumeric() || matches!(c, '-' | '_' | ':' | '.')
+}
+
+fn is_predefined_entity(name: &str) -> bool {
+    if let Some(number) = name.strip_prefix('#') {
+        let code = match number.strip_prefix(['x', 'X']) {
+            Some(hex) => u32::from_str_radix(hex, 16).ok(),
+            None => number.parse().ok(),
+        };
+        return code.and_then(char::from_u32).is_some_and(|c| c != '\0');
+    }
+    matches!(name, "amp" | "lt" | "gt" | "quot" | "apos")
+}
+
+fn escape_text(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+}
+
+/// Returns the name without its namespace prefix.
+fn local_name(name: &str) -> &str {
+    name.rsplit(':').next().unwrap_or(name)
+}
+
+/// Returns `element` without its unsafe parts, or `None` if the whole
+/// element has to go.
+fn sanitize_element(
+    mut element: Element,
+    is_root: bool,
+    report: &mut SanitizeReport,
+) -> Option<Element> {
+    let local = local_name(&element.name).to_ascii_lowercase();
+    if BLOCKED_ELEMENTS.contains(&local.as_str()) || retargets_link(&element, &local) {
+        report.remove(SanitizeRemoval::Element {
+            name: element.name.clone(),
+        });
+        return None;
+    }
+    if local == "filter"
+        && let Some(reason) = filter_problem(&element)
+    {
+        report.remove(SanitizeRemoval::Filter {
+            id: element.attribute("id").map(str::to_string),
+            reason,
+        });
+        return None;
+    }
+    if local == "style"
+        && let Some(value) = external_stylesheet_reference(&element.text())
+    {
+        report.remove(SanitizeRemoval::ExternalReference {
+            element: element.name.clone(),
+            value,
+        });
+        return None;
+    }
+
+    let name = element.name.clone();
+    element.attributes.retain(|(key, value)| {
+        let attribute = local_name(key).to_ascii_lowercase();
+        if attribute.starts_with("on") {
+            report.remove(SanitizeRemoval::EventHandler {
+                element: name.clone(),
+                attribute: key.clone(),
+            });
+            return false;
+        }
+        let reference = if matches!(attribute.as_str(), "href" | "src") && !is_safe_link(value) {
+            Some(value.clone())
+        } else {
+            external_url(value)
+        };
+        if let Some(value) = reference {
+            report.remove(SanitizeRemoval::ExternalReference {
+                element: name.clone(),
+                value,
+            });
+            return false;
+        }
+        if is_root && matches!(attribute.as_str(), "width" | "height") && !dimension_ok(value) {
+            report.remove(SanitizeRemoval::Dimension {
+                attribute: key.clone(),
+                value: value.clone(),
+            });
+            return false;
+        }
+        true
+    });
+
+    element.children = std::mem::take(&mut element.children)
+        .into_iter()
+        .filter_map(|child| match child {
+            Node::Element(child) => sanitize_element(child, false, report).map(Node::Element),
+            text => Some(text),
+        })
+        .collect();
+    Some(element)
+}
+
+/// Returns `true` for animations that change a link or event handler,
+/// which could bring back what sanitizing removed.
+fn retargets_link(element: &Element, local: &str) -> bool {
+    if local != "set" && !local.starts_with("animate") {
+        return false;
+    }
+    element.attribute("attributeName").is_some_and(|target| {
+        let target = local_name(target).to_ascii_lowercase();
+        target == "href" || target == "src" || target.starts_with("on")
+    })
+}
+
+/// Returns why `filter` is too expensive to render, if it is.
+fn filter_problem(filter: &Element) -> Option<String> {
+    let primitives: Vec<&Element> = filter.child_elements().collect();
+    if primitives.len() > MAX_FILTER_PRIMITIVES {
+        return Some(format!("it has {} primitives", primitives.len()));
+    }
+    for primitive in primitives {
+        let local = local_name(&primitive.name).to_ascii_lowercase();
+        let (attribute, limit) = match local.as_str() {
+            "fegaussianblur" | "fedropshadow" => ("stdDeviation", MAX_FILTER_RADIUS),
+            "femorphology" => ("radius", MAX_FILTER_RADIUS),
+            "feturbulence" => ("numOctaves", MAX_TURBULENCE_OCTAVES),
+            "feconvolvematrix" => ("order", MAX_CONVOLVE_CELLS.sqrt()),
+            _ => continue,
+        };
+        let Some(value) = primitive.attribute(attribute) else {
+            continue;
+        };
+        let too_large = value
+            .split(|c: char| c.is_whitespace() || c == ',')
+            .filter(|part| !part.is_empty())
+            .any(|part| {
+                !part
+                    .parse::<f32>()
+                    .is_ok_and(|n| n.is_finite() && n <= limit)
+            });
+        if too_large {
+            return Some(format!("{} {attribute} of {value}", primitive.name));
+        }
+    }
+    None
+}
+
+/// Returns `true` for links to a fragment of the document or an embedded
+/// raster image.
+fn is_safe_link(value: &str) -> bool {
+    let value = value.trim();
+    let lower = value.to_ascii_lowercase();
+    value.starts_with('#')
+        || SAFE_DATA_PREFIXES
+            .iter()
+            .any(|prefix| lower.starts_with(prefix))
+}
+
+/// Returns the first `url()` in `value` that points outside the document.
+fn external_url(value: &str) -> Option<String> {
+    let lower = value.to_ascii_lowercase();
+    let mut start = 0;
+    while let Some(index) = lower[start..].find("url(") {
+        let open = start + index + 4;
+        let close = lower[open..]
+            .find(')')
+            .map_or(lower.len(), |end| open + end);
+        let target = value[open..close].trim().trim_matches(['"', '\'']).trim();
+        if !is_safe_link(target) {
+            return Some(target.to_string());
+        }
+        start = close;
+    }
+    None
+}
+
+/// Returns the first import or external `url()` in a stylesheet.
+fn external_stylesheet_reference(css: &str) -> Option<String> {
+    if css.to_ascii_lowercase().contains("@import") {
+        return Some("@import".to_string());
+    }
+    external_url(css)
+}
+
+/// Returns `true` if a root `width` or `height` is within
+/// [`MAX_SVG_DIMENSION`].
+///
+/// Percentages, and values that aren't numbers the renderer would read,
+/// are left alone.
+fn dimension_ok(value: &str) -> bool {
+    let value = value.trim();
+    if value.ends_with('%') {
+        return true;
+    }
+    let number = value.trim_end_matches(|c: char| c.is_ascii_alphabetic());
+    match number.parse::<f32>() {
+        Ok(number) => number.is_finite() && number > 0.0 && number <= MAX_SVG_DIMENSION,
+        Err(_) => true,
+    }
+}
+
+fn write_element(element: &Element, output: &mut String) {
+    output.push('<');
+    output.push_str(&element.name);
+    for (key, value) in &element.attributes {
+        output.push(' ');
+        output.push_str(key);
+        output.push_str("=\"");
+        output.push_str(&value.replace('<', "&lt;").replace('"', "&quot;"));
+        output.push('"');
+    }
+    if element.children.is_empty() {
+        output.push_str("/>");
+        return;
+    }
+    output.push('>');
+    for child in &element.children {
+        match child {
+            Node::Element(child) => write_element(child, output),
+            Node::Text(text) => output.push_str(text),
+        }
+    }
+    output.push_str("</");
+    output.push_str(&element.name);
+    output.push('>');
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn kinds(report: &SanitizeReport) -> Vec<String> {
+        report
+            .removed
+            .iter()
+            .map(|removal| serde_json::to_value(removal).unwrap()["kind"].to_string())
+            .collect()
+    }
+
+    #[test]
+    fn test_clean_svg_is_kept() {
+        let svg = r##"<?xml version="1.0"?>
+<svg xmlns="http://www.w3.org/2000/svg" viewBox="0 0 16 16" width="16" height="16">
+  <defs><linearGradient id="g"><stop offset="0" stop-color="#fff"/></linearGradient></defs>
+  <rect width="16" height="16" fill="url(#g)"/>
+  <text>a &amp; b</text>
+</svg>"##;
+        let (sanitized, report) = sanitize_svg(svg);
+        assert!(report.is_clean(), "{report:?}");
+        assert!(sanitized.starts_with("<svg "));
+        assert!(sanitized.contains(r##"fill="url(#g)""##));
+        assert!(sanitized.contains("a &amp; b"));
+    }
+
+    #[test]
+    fn test_scripts_and_handlers_are_removed() {
+        let svg = r#"<svg onload="alert(1)"><script>alert(2)</script>
+            <SCRIPT/><foreignObject><div/></foreignObject>
+            <a href="javascript:alert(3)"><circle r="1"/></a>
+            <set attributeName="href" to="javascript:alert(4)"/></svg>"#;
+        let (sanitized, report) = sanitize_svg(svg);
+        let lower = sanitized.to_ascii_lowercase();
+        assert!(!lower.contains("alert"), "{sanitized}");
+        assert!(!lower.contains("script"));
+        assert!(!lower.contains("foreignobject"));
+        assert!(sanitized.contains("<circle"));
+        assert!(kinds(&report).contains(&"\"event-handler\"".to_string()));
+        assert!(kinds(&report).contains(&"\"element\"".to_string()));
+    }
+
+    #[test]
+    fn test_external_references_are_removed() {
+        let svg = r#"<svg xmlns:xlink="http://www.w3.org/1999/xlink">
+            <image xlink:href="https://example.com/track.png"/>
+            <image href="data:image/png;base64,iVBORw0KGgo="/>
+            <rect style="fill: url('http://example.com/p.svg#x')"/>
+            <style>@import url(evil.css);</style>
+            </svg>"#;
+        let (sanitized, report) = sanitize_svg(svg);
+        assert!(!sanitized.contains("example.com"), "{sanitized}");
+        assert!(!sanitized.contains("@import"));
+        assert!(sanitized.contains("data:image/png"));
+        assert_eq!(report.removed.len(), 3);
+    }
+
+    #[test]
+    fn test_entities_are_not_expanded() {
+        let svg = r#"<!DOCTYPE svg [
+            <!ENTITY lol "lol">
+            <!ENTITY lol2 "&lol;&lol;&lol;&lol;&lol;&lol;&lol;&lol;&lol;&lol;">
+            <!ENTITY lol3 "&lol2;&lol2;&lol2;&lol2;&lol2;&lol2;&lol2;&lol2;&lol2;">
+        ]><svg><text>&lol3; &#65; & done</text></svg>"#;
+        let (sanitized, report) = sanitize_svg(svg);
+        assert_eq!(sanitized, "<svg><text> &#65; &amp; done</text></svg>");
+        assert_eq!(
+            report.removed,
+            [
+                SanitizeRemoval::Doctype,
+                SanitizeRemoval::Entity {
+                    name: "lol3".to_string()
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_expensive_filters_are_removed() {
+        let primitives = "<feOffset/>".repeat(MAX_FILTER_PRIMITIVES + 1);
+        let svg = format!(
+            r#"<svg><filter id="many">{primitives}</filter>
+            <filter id="blur"><feGaussianBlur stdDeviation="5 100000"/></filter>
+            <filter id="ok"><feGaussianBlur stdDeviation="2"/></filter></svg>"#
+        );
+        let (sanitized, report) = sanitize_svg(&svg);
+        assert!(!sanitized.contains(r#"id="many""#));
+        assert!(!sanitized.contains(r#"id="blur""#));
+        assert!(sanitized.contains(r#"id="ok""#));
+        let ids: Vec<_> = report
+            .removed
+            .iter()
+            .map(|removal| match removal {
+                SanitizeRemoval::Filter { id, .. } => id.clone(),
+                other => panic!("unexpected {other:?}"),
+            })
+            .collect();
+        assert_eq!(ids, [Some("many".to_string()), Some("blur".to_string())]);
+    }
+
+    #[test]
+    fn test_absurd_dimensions_are_removed() {
+        let svg =
+            r#"<svg width="100000000" height="50%" viewBox="0 0 1 1"><svg width="9e9"/></svg>"#;
+        let (sanitized, report) = sanitize_svg(svg);
+        assert_eq!(
+            sanitized,
+            r#"<svg height="50%" viewBox="0 0 1 1"><svg width="9e9"/></svg>"#
+        );
+        assert_eq!(report.removed.len(), 1);
+    }
+
+    #[test]
+    fn test_invalid_markup_is_rejected() {
+        let deep = "<g>".repeat(MAX_DEPTH + 1);
+        let oversized = format!("<svg>{}</svg>", " ".repeat(MAX_SVG_BYTES));
+        for svg in [
+            "<svg><g></svg>",
+            "<svg",
+            r#"<svg a=1/>"#,
+            "<svg/><svg/>",
+            "<g/>",
+            "not markup",
+            &format!("<svg>{deep}</svg>"),
+            &oversized,
+        ] {
+            let (sanitized, report) = sanitize_svg(svg);
+            assert_eq!(sanitized, EMPTY_SVG);
+            assert!(matches!(
+                report.removed.as_slice(),
+                [SanitizeRemoval::Rejected { .. }]
+            ));
+        }
+    }
+
+    #[test]
+    fn test_arbitrary_input_never_panics() {
+        // A cheap fuzz: every prefix of some hostile markup, cut anywhere,
+        // including inside multi-byte characters' neighbours
+        let svg = concat!(
+            r#"<?x?><!DOCTYPE s [<!ENTITY e "]>">]>"#,
+            r#"<svg a='"' b="'">é<![CDATA[<]]>&#x1F600;&#0;&;&e</svg>"#,
+        );
+        for end in (0..=svg.len()).filter(|&end| svg.is_char_boundary(end)) {
+            let (sanitized, _) = sanitize_svg(&svg[..end]);
+            assert!(sanitized.starts_with("<svg"));
+        }
+    }
+
+    #[test]
+    fn test_profile_without_svg_is_borrowed() {
+        let profile = CustomizationProfile::default();
+        let (sanitized, report) = sanitized_for_render(&profile).unwrap();
+        assert!(matches!(sanitized, Cow::Borrowed(_)));
+        assert!(report.is_clean());
+    }
+
+    #[test]
+    fn test_sanitize_strings_in_profile_json() {
+        let mut value = serde_json::json!({
+            "decal": { "source": { "svg": "<svg onclick=\"x()\"><circle/></svg>" } },
+            "name": "keep <this>",
+        });
+        let mut report = SanitizeReport::default();
+        assert!(sanitize_strings(&mut value, &mut report));
+        assert_eq!(value["decal"]["source"]["svg"], "<svg><circle/></svg>");
+        assert_eq!(value["name"], "keep <this>");
+        assert_eq!(report.removed.len(), 1);
+    }
+}
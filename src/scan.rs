@@ -0,0 +1,157 @@
+//! Finding the customized folders below a directory.
+//!
+//! The profile store knows which folders folco customized, but not whether
+//! their icons are still there, and a "manage my folders" screen usually
+//! starts from a directory rather than from the store.
+//! [`scan_customized`](crate::CustomizationContext::scan_customized) walks
+//! the tree and returns each folder that shows a custom icon, with the
+//! profile folco applied to it. Its paths can be handed straight to
+//! [`reset_folders`](crate::CustomizationContext::reset_folders).
+
+use crate::error::Result;
+use crate::paths::normalize_folder_path;
+use crate::store::{ProfileStore, StoredProfile};
+
+use serde::{Deserialize, Serialize};
+
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// Options for [`scan_customized`](crate::CustomizationContext::scan_customized).
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ScanOptions {
+    /// How many directory levels below the root to look, or `None` for the
+    /// whole tree. The root itself is always checked.
+    pub max_depth: Option<usize>,
+    /// Also report folders with a custom icon folco didn't apply.
+    pub include_foreign: bool,
+}
+
+impl ScanOptions {
+    /// Creates the default options: the whole tree, folco's icons only.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Sets how deep below the root to look.
+    pub fn with_max_depth(mut self, max_depth: usize) -> Self {
+        self.max_depth = Some(max_depth);
+        self
+    }
+
+    /// Sets whether folders with icons folco didn't apply are reported.
+    pub fn with_include_foreign(mut self, include_foreign: bool) -> Self {
+        self.include_foreign = include_foreign;
+        self
+    }
+}
+
+/// A folder with a custom icon, found by a scan.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CustomizedFolder {
+    /// The folder, normalized.
+    pub path: PathBuf,
+    /// The profile folco applied, or `None` for an icon folco didn't apply.
+    pub stored: Option<StoredProfile>,
+}
+
+impl CustomizedFolder {
+    /// Returns `true` if folco applied the folder's icon.
+    pub fn is_folco(&self) -> bool {
+        self.stored.is_some()
+    }
+}
+
+/// Walks `root` and returns the folders `has_custom_icon` reports, sorted
+/// by path.
+///
+/// Folders are matched to `store` by path only, so a folder moved since it
+/// was customized counts as foreign until the store is reconciled. Symlinks
+/// are not followed, and unreadable directories are skipped.
+///
+/// # Errors
+///
+/// Returns an error if `root` can't be read.
+pub(crate) fn scan_customized(
+    root: &Path,
+    options: &ScanOptions,
+    store: &ProfileStore,
+    has_custom_icon: impl Fn(&Path) -> bool,
+) -> Result<Vec<CustomizedFolder>> {
+    let root = normalize_folder_path(root);
+    fs::read_dir(&root)?;
+
+    let mut found = Vec::new();
+    let mut pending = vec![(root, 0usize)];
+    while let Some((dir, depth)) = pending.pop() {
+        if has_custom_icon(&dir) {
+            let stored = store.get(&dir);
+            if stored.is_some() || options.include_foreign {
+                found.push(CustomizedFolder {
+                    path: dir.clone(),
+                    stored,
+                });
+            }
+        }
+        if options
+            .max_depth
+            .is_some_and(|max_depth| depth >= max_depth)
+        {
+            continue;
+        }
+        let Ok(entries) = fs::read_dir(&dir) else {
+            continue;
+        };
+        for entry in entries.flatten() {
+            if entry.file_type().is_ok_and(|t| t.is_dir()) {
+                pending.push((entry.path(), depth + 1));
+            }
+        }
+    }
+    found.sort_by(|a, b| a.path.cmp(&b.path));
+    Ok(found)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use folco_renderer::CustomizationProfile;
+
+    #[test]
+    fn test_scan_finds_customized_folders() {
+        let temp = tempfile::tempdir().unwrap();
+        let root = normalize_folder_path(temp.path());
+        for folder in ["Work/Clients/Acme", "Work/Old", "Music"] {
+            fs::create_dir_all(root.join(folder)).unwrap();
+        }
+        let store = ProfileStore::open(root.join("profiles.json")).unwrap();
+        let acme = root.join("Work/Clients/Acme");
+        let old = root.join("Work/Old");
+        let music = root.join("Music");
+        store.insert(&acme, &CustomizationProfile::default());
+        // A record whose icon was removed behind folco's back
+        store.insert(&old, &CustomizationProfile::default());
+        let custom = [acme.clone(), music.clone()];
+        let has_custom_icon = |folder: &Path| custom.iter().any(|c| c == folder);
+
+        let found = scan_customized(&root, &ScanOptions::new(), &store, has_custom_icon).unwrap();
+        let paths: Vec<_> = found.iter().map(|folder| folder.path.clone()).collect();
+        assert_eq!(paths, [acme.clone()]);
+        assert!(found[0].is_folco());
+
+        let options = ScanOptions::new().with_include_foreign(true);
+        let found = scan_customized(&root, &options, &store, has_custom_icon).unwrap();
+        let paths: Vec<_> = found.iter().map(|folder| folder.path.clone()).collect();
+        assert_eq!(paths, [music, acme]);
+        assert!(!found[0].is_folco());
+
+        let shallow = ScanOptions::new().with_max_depth(2);
+        assert!(
+            scan_customized(&root, &shallow, &store, has_custom_icon)
+                .unwrap()
+                .is_empty()
+        );
+        assert!(scan_customized(&root.join("gone"), &shallow, &store, has_custom_icon).is_err());
+    }
+}
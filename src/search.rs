@@ -0,0 +1,321 @@
+//! Searching the folders folco has customized.
+//!
+//! [`search_customized`] filters the [`ProfileStore`] by color, glyph, preset,
+//! path, date, notes and metadata, and returns one page of results at a time.
+
+use crate::color::FolderColor;
+use crate::profile::{profile_color, profile_glyphs};
+use crate::store::{ProfileStore, StoredProfile};
+
+use serde::{Deserialize, Serialize};
+
+use std::collections::BTreeMap;
+use std::path::PathBuf;
+
+/// Default number of results per page.
+pub const DEFAULT_PAGE_SIZE: usize = 50;
+
+/// Filters and paging for [`search_customized`].
+///
+/// All filters are optional and combined with AND. Text filters are
+/// case-insensitive.
+///
+/// # Example
+///
+/// ```ignore
+/// let query = SearchQuery::new()
+///     .with_color(FolderColor::Red)
+///     .with_path_contains("clients");
+/// let page = ctx.search_customized(&query);
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct SearchQuery {
+    /// Only folders whose profile uses this color preset.
+    pub color: Option<FolderColor>,
+    /// Only folders with a decal containing this text, e.g. an emoji.
+    pub glyph: Option<String>,
+    /// Only folders customized from this preset.
+    pub preset: Option<String>,
+    /// Only folders whose path contains this text.
+    pub path_contains: Option<String>,
+    /// Only folders customized at or after this time (seconds since the Unix epoch).
+    pub applied_after: Option<u64>,
+    /// Only folders customized before this time (seconds since the Unix epoch).
+    pub applied_before: Option<u64>,
+    /// Only folders whose notes contain this text.
+    pub notes_contains: Option<String>,
+    /// Only folders with each of these metadata keys, with a value
+    /// containing the given text. An empty text only requires the key.
+    pub metadata: BTreeMap<String, String>,
+    /// Number of matching results to skip.
+    pub offset: usize,
+    /// Maximum number of results to return.
+    pub limit: usize,
+}
+
+impl Default for SearchQuery {
+    fn default() -> Self {
+        Self {
+            color: None,
+            glyph: None,
+            preset: None,
+            path_contains: None,
+            applied_after: None,
+            applied_before: None,
+            notes_contains: None,
+            metadata: BTreeMap::new(),
+            offset: 0,
+            limit: DEFAULT_PAGE_SIZE,
+        }
+    }
+}
+
+impl SearchQuery {
+    /// Creates a query matching everything, returning the first page.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Filters by color preset.
+    pub fn with_color(mut self, color: FolderColor) -> Self {
+        self.color = Some(color);
+        self
+    }
+
+    /// Filters by decal text.
+    pub fn with_glyph(mut self, glyph: impl Into<String>) -> Self {
+        self.glyph = Some(glyph.into());
+        self
+    }
+
+    /// Filters by preset name.
+    pub fn with_preset(mut self, preset: impl Into<String>) -> Self {
+        self.preset = Some(preset.into());
+        self
+    }
+
+    /// Filters by path substring.
+    pub fn with_path_contains(mut self, text: impl Into<String>) -> Self {
+        self.path_contains = Some(text.into());
+        self
+    }
+
+    /// Filters by when the profile was applied, as a half-open range.
+    pub fn with_applied_between(mut self, after: Option<u64>, before: Option<u64>) -> Self {
+        self.applied_after = after;
+        self.applied_before = before;
+        self
+    }
+
+    /// Filters by notes substring.
+    pub fn with_notes_contains(mut self, text: impl Into<String>) -> Self {
+        self.notes_contains = Some(text.into());
+        self
+    }
+
+    /// Filters by a metadata key whose value contains `text`. An empty
+    /// `text` matches any value.
+    pub fn with_metadata(mut self, key: impl Into<String>, text: impl Into<String>) -> Self {
+        self.metadata.insert(key.into(), text.into());
+        self
+    }
+
+    /// Selects a page of results.
+    pub fn with_page(mut self, offset: usize, limit: usize) -> Self {
+        self.offset = offset;
+        self.limit = limit;
+        self
+    }
+
+    fn matches(&self, path: &str, stored: &StoredProfile, color: Option<FolderColor>) -> bool {
+        if self.color.is_some() && color != self.color {
+            return false;
+        }
+        if self
+            .applied_after
+            .is_some_and(|after| stored.applied_at < after)
+            || self
+                .applied_before
+                .is_some_and(|before| stored.applied_at >= before)
+        {
+            return false;
+        }
+        if let Some(text) = &self.path_contains
+            && !contains_ignore_case(path, text)
+        {
+            return false;
+        }
+        if let Some(preset) = &self.preset
+            && !stored
+                .preset
+                .as_deref()
+                .is_some_and(|name| name.eq_ignore_ascii_case(preset))
+        {
+            return false;
+        }
+        if let Some(glyph) = &self.glyph
+            && !profile_glyphs(&stored.profile)
+                .iter()
+                .any(|text| contains_ignore_case(text, glyph))
+        {
+            return false;
+        }
+        if let Some(text) = &self.notes_contains
+            && !stored
+                .notes
+                .as_deref()
+                .is_some_and(|notes| contains_ignore_case(notes, text))
+        {
+            return false;
+        }
+        self.metadata.iter().all(|(key, text)| {
+            stored
+                .metadata
+                .get(key)
+                .is_some_and(|value| contains_ignore_case(value, text))
+        })
+    }
+}
+
+/// A customized folder matching a search.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SearchResult {
+    /// The customized folder.
+    pub path: PathBuf,
+    /// The color preset its profile uses, if any.
+    pub color: Option<FolderColor>,
+    /// The stored record for the folder.
+    pub stored: StoredProfile,
+}
+
+/// One page of search results.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SearchPage {
+    /// Results on this page, sorted by path.
+    pub results: Vec<SearchResult>,
+    /// Total number of matching folders across all pages.
+    pub total: usize,
+    /// Offset of the first result on this page.
+    pub offset: usize,
+}
+
+impl SearchPage {
+    /// Returns `true` if there are matching results after this page.
+    pub fn has_more(&self) -> bool {
+        self.offset + self.results.len() < self.total
+    }
+}
+
+/// Searches the profile store.
+///
+/// Results are sorted by path, so paging through them is stable as long as
+/// the store doesn't change.
+pub fn search_customized(store: &ProfileStore, query: &SearchQuery) -> SearchPage {
+    let mut total = 0;
+    let mut results = Vec::new();
+
+    for (path, stored) in store.entries() {
+        let color = profile_color(&stored.profile);
+        if !query.matches(&path.to_string_lossy(), &stored, color) {
+            continue;
+        }
+        if total >= query.offset && results.len() < query.limit {
+            results.push(SearchResult {
+                path,
+                color,
+                stored,
+            });
+        }
+        total += 1;
+    }
+
+    SearchPage {
+        results,
+        total,
+        offset: query.offset,
+    }
+}
+
+fn contains_ignore_case(haystack: &str, needle: &str) -> bool {
+    haystack.to_lowercase().contains(&needle.to_lowercase())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use folco_renderer::CustomizationProfile;
+    use tempfile::tempdir;
+
+    fn store_with(folders: &[&str]) -> (tempfile::TempDir, ProfileStore) {
+        let temp = tempdir().unwrap();
+        let store = ProfileStore::in_data_dir(temp.path()).unwrap();
+        for folder in folders {
+            store.insert(&temp.path().join(folder), &CustomizationProfile::default());
+        }
+        (temp, store)
+    }
+
+    #[test]
+    fn test_path_filter_and_paging() {
+        let (_temp, store) = store_with(&["Clients-a", "clients-b", "clients-c", "personal"]);
+
+        let query = SearchQuery::new().with_path_contains("CLIENTS").with_page(1, 1);
+        let page = search_customized(&store, &query);
+
+        assert_eq!(page.total, 3);
+        assert_eq!(page.results.len(), 1);
+        assert!(page.results[0].path.ends_with("clients-b"));
+        assert!(page.has_more());
+    }
+
+    #[test]
+    fn test_preset_filter() {
+        let (temp, store) = store_with(&["a", "b"]);
+        store.set_preset(&temp.path().join("a"), Some("Work".to_string()));
+
+        let page = search_customized(&store, &SearchQuery::new().with_preset("work"));
+        assert_eq!(page.total, 1);
+        assert!(page.results[0].path.ends_with("a"));
+    }
+
+    #[test]
+    fn test_date_filter() {
+        let (_temp, store) = store_with(&["a"]);
+        let applied = store.entries()[0].1.applied_at;
+
+        let before = SearchQuery::new().with_applied_between(None, Some(applied));
+        assert_eq!(search_customized(&store, &before).total, 0);
+
+        let after = SearchQuery::new().with_applied_between(Some(applied), None);
+        assert_eq!(search_customized(&store, &after).total, 1);
+    }
+
+    #[test]
+    fn test_notes_and_metadata_filters() {
+        let (temp, store) = store_with(&["a", "b", "c"]);
+        store.set_notes(&temp.path().join("a"), Some("Archive after Q3".to_string()));
+        store.set_metadata(&temp.path().join("a"), "client", Some("Acme Corp".to_string()));
+        store.set_metadata(&temp.path().join("b"), "client", Some("Globex".to_string()));
+
+        let notes = SearchQuery::new().with_notes_contains("archive");
+        assert_eq!(search_customized(&store, &notes).total, 1);
+
+        let acme = SearchQuery::new().with_metadata("client", "acme");
+        let page = search_customized(&store, &acme);
+        assert_eq!(page.total, 1);
+        assert!(page.results[0].path.ends_with("a"));
+
+        let any_client = SearchQuery::new().with_metadata("client", "");
+        assert_eq!(search_customized(&store, &any_client).total, 2);
+    }
+
+    #[test]
+    fn test_query_deserializes_with_defaults() {
+        let query: SearchQuery = serde_json::from_str(r#"{"color": "red"}"#).unwrap();
+        assert_eq!(query.color, Some(FolderColor::Red));
+        assert_eq!(query.limit, DEFAULT_PAGE_SIZE);
+    }
+}
@@ -0,0 +1,246 @@
+//! Seasonal and holiday preset packs.
+//!
+//! Each pack in `assets/seasonal/` is a small set of presets, such as a
+//! pumpkin for Halloween or a snowflake for December, plus the window of the
+//! year it's relevant in. [`current_seasonal_presets`] returns the presets
+//! whose window contains a date, so the GUI can suggest them and scheduled
+//! rotation can pick from them. The packs are compiled in with the
+//! `seasonal` feature.
+
+use crate::color::FolderColor;
+use crate::error::{Error, Result};
+use crate::profile::{profile_with_color, profile_with_decal};
+use crate::store::now_unix_secs;
+
+use folco_renderer::{CustomizationProfile, DecalSettings};
+use serde::{Deserialize, Serialize};
+
+const SECONDS_PER_DAY: u64 = 24 * 60 * 60;
+
+/// The bundled packs, as `(file name, contents)`.
+const PACKS: &[(&str, &str)] = &[
+    (
+        "winter-holidays.json",
+        include_str!("../assets/seasonal/winter-holidays.json"),
+    ),
+    (
+        "valentines.json",
+        include_str!("../assets/seasonal/valentines.json"),
+    ),
+    (
+        "spring.json",
+        include_str!("../assets/seasonal/spring.json"),
+    ),
+    (
+        "autumn.json",
+        include_str!("../assets/seasonal/autumn.json"),
+    ),
+    (
+        "halloween.json",
+        include_str!("../assets/seasonal/halloween.json"),
+    ),
+];
+
+/// A day of the year, without a year.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+pub struct MonthDay {
+    /// Month, from 1 to 12.
+    pub month: u8,
+    /// Day of the month, from 1 to 31.
+    pub day: u8,
+}
+
+impl MonthDay {
+    /// Creates a day of the year, or `None` if it doesn't exist. February
+    /// 29th is allowed.
+    pub fn new(month: u8, day: u8) -> Option<Self> {
+        let days_in_month = match month {
+            2 => 29,
+            4 | 6 | 9 | 11 => 30,
+            1..=12 => 31,
+            _ => return None,
+        };
+        (1..=days_in_month)
+            .contains(&day)
+            .then_some(Self { month, day })
+    }
+
+    /// Returns today's date in UTC.
+    pub fn today() -> Self {
+        Self::from_unix_secs(now_unix_secs())
+    }
+
+    /// Returns the UTC date of a time in seconds since the Unix epoch.
+    pub fn from_unix_secs(secs: u64) -> Self {
+        // Howard Hinnant's civil-from-days, shifted so years start in March
+        let days = secs / SECONDS_PER_DAY + 719_468;
+        let day_of_era = days % 146_097;
+        let year_of_era =
+            (day_of_era - day_of_era / 1460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+        let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+        let shifted_month = (5 * day_of_year + 2) / 153;
+        let day = day_of_year - (153 * shifted_month + 2) / 5 + 1;
+        let month = if shifted_month < 10 {
+            shifted_month + 3
+        } else {
+            shifted_month - 9
+        };
+        Self {
+            month: month as u8,
+            day: day as u8,
+        }
+    }
+}
+
+/// The part of the year a pack is relevant in, inclusive at both ends.
+///
+/// A window whose end comes before its start wraps around the new year, e.g.
+/// December 1st to January 6th.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SeasonalWindow {
+    /// First day of the window.
+    pub start: MonthDay,
+    /// Last day of the window.
+    pub end: MonthDay,
+}
+
+impl SeasonalWindow {
+    /// Returns `true` if `date` falls in the window.
+    pub fn contains(&self, date: MonthDay) -> bool {
+        if self.start <= self.end {
+            self.start <= date && date <= self.end
+        } else {
+            date >= self.start || date <= self.end
+        }
+    }
+}
+
+/// A bundled set of presets for one season or holiday.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SeasonalPack {
+    /// Stable identifier, e.g. `"halloween"`.
+    pub id: String,
+    /// Display name, e.g. "Halloween".
+    pub name: String,
+    /// When the pack is relevant.
+    pub window: SeasonalWindow,
+    /// The pack's presets.
+    pub presets: Vec<SeasonalPreset>,
+}
+
+/// One preset of a [`SeasonalPack`].
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SeasonalPreset {
+    /// Display name, e.g. "Pumpkin".
+    pub name: String,
+    /// The folder color.
+    pub color: FolderColor,
+    /// The decal drawn on the folder, if any.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub decal: Option<DecalSettings>,
+}
+
+impl SeasonalPreset {
+    /// Builds the profile this preset applies.
+    pub fn profile(&self) -> Result<CustomizationProfile> {
+        let profile = profile_with_color(&CustomizationProfile::default(), self.color)?;
+        match &self.decal {
+            Some(decal) => profile_with_decal(&profile, Some(decal)),
+            None => Ok(profile),
+        }
+    }
+}
+
+/// Returns every bundled pack, in calendar order of their windows.
+///
+/// # Errors
+///
+/// Fails if a bundled pack can't be parsed, which means the pack files and
+/// the renderer's decal format have drifted apart.
+pub fn seasonal_packs() -> Result<Vec<SeasonalPack>> {
+    let mut packs = PACKS
+        .iter()
+        .map(|(file, json)| {
+            serde_json::from_str::<SeasonalPack>(json)
+                .map_err(|e| Error::Serialization(format!("seasonal pack {file}: {e}")))
+        })
+        .collect::<Result<Vec<_>>>()?;
+    packs.sort_by_key(|pack| pack.window.start);
+    Ok(packs)
+}
+
+/// Returns the presets of every pack relevant on `date`.
+///
+/// Overlapping packs, such as Autumn and Halloween in October, both
+/// contribute, in pack order. Pass [`MonthDay::today`] for the current
+/// suggestions.
+pub fn current_seasonal_presets(date: MonthDay) -> Result<Vec<SeasonalPreset>> {
+    Ok(seasonal_packs()?
+        .into_iter()
+        .filter(|pack| pack.window.contains(date))
+        .flat_map(|pack| pack.presets)
+        .collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn day(month: u8, day: u8) -> MonthDay {
+        MonthDay::new(month, day).unwrap()
+    }
+
+    #[test]
+    fn test_month_day_validation() {
+        assert!(MonthDay::new(2, 29).is_some());
+        assert!(MonthDay::new(4, 31).is_none());
+        assert!(MonthDay::new(13, 1).is_none());
+        assert!(MonthDay::new(1, 0).is_none());
+    }
+
+    #[test]
+    fn test_from_unix_secs() {
+        assert_eq!(MonthDay::from_unix_secs(0), day(1, 1));
+        // 2024-02-29T12:00:00Z
+        assert_eq!(MonthDay::from_unix_secs(1_709_208_000), day(2, 29));
+        // 2023-12-31T23:59:59Z
+        assert_eq!(MonthDay::from_unix_secs(1_704_067_199), day(12, 31));
+    }
+
+    #[test]
+    fn test_window_wraps_new_year() {
+        let window = SeasonalWindow {
+            start: day(12, 1),
+            end: day(1, 6),
+        };
+        assert!(window.contains(day(12, 25)));
+        assert!(window.contains(day(1, 6)));
+        assert!(!window.contains(day(1, 7)));
+        assert!(!window.contains(day(11, 30)));
+    }
+
+    #[test]
+    fn test_bundled_packs_parse() {
+        let packs = seasonal_packs().unwrap();
+        assert_eq!(packs.len(), PACKS.len());
+        for preset in packs.iter().flat_map(|pack| &pack.presets) {
+            preset.profile().unwrap();
+        }
+    }
+
+    #[test]
+    fn test_current_presets_follow_the_calendar() {
+        let names = |date| -> Vec<String> {
+            current_seasonal_presets(date)
+                .unwrap()
+                .into_iter()
+                .map(|preset| preset.name)
+                .collect()
+        };
+        assert!(names(day(10, 31)).contains(&"Pumpkin".to_string()));
+        assert!(names(day(12, 15)).contains(&"Snowflake".to_string()));
+        assert!(!names(day(7, 1)).contains(&"Pumpkin".to_string()));
+    }
+}
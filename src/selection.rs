@@ -0,0 +1,467 @@
+//! Shared folder selection logic for command-line consumers.
+//!
+//! Every folco-cli command that takes folders accepts the same kinds of
+//! input, resolved by [`resolve_folder_selection`]:
+//!
+//! - Plain paths: `~/Projects/client-a`
+//! - Glob patterns: `~/Projects/*/src`
+//! - List files: `@folders.txt`, one path or pattern per line
+//! - Standard input: `-`, one path or pattern per line
+//!
+//! In list files and standard input, blank lines and lines starting with `#`
+//! are ignored.
+//!
+//! Library consumers build a [`FolderSelector`] instead, from paths and
+//! patterns, and hand it to
+//! [`customize_selected`](crate::CustomizationContext::customize_selected) or
+//! [`reset_selected`](crate::CustomizationContext::reset_selected).
+
+use crate::batch::BatchOutcome;
+use crate::error::Error;
+use crate::paths::{normalize_folders, MergedDuplicate};
+
+use serde::{Deserialize, Serialize};
+
+use std::fmt;
+use std::io::{self, BufRead};
+use std::path::{Path, PathBuf};
+
+/// The result of resolving a folder selection.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct FolderSelection {
+    /// Existing, normalized, deduplicated folders.
+    pub folders: Vec<PathBuf>,
+    /// Inputs that could not be used, with the reason why.
+    pub rejects: Vec<SelectionReject>,
+    /// Inputs that named an already selected folder.
+    pub duplicates: Vec<MergedDuplicate>,
+    /// Paths a glob pattern matched that aren't directories, left out of
+    /// the selection.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub non_folders: Vec<PathBuf>,
+}
+
+impl FolderSelection {
+    /// Adds the selection's rejects to `outcome` as failed results, and its
+    /// duplicates to the outcome's.
+    pub(crate) fn merge_into(self, outcome: &mut BatchOutcome) {
+        outcome.duplicates.extend(self.duplicates);
+        outcome.results.extend(self.rejects.into_iter().map(|reject| {
+            let input = PathBuf::from(&reject.input);
+            (input, Err(Error::Selection(reject)))
+        }));
+    }
+}
+
+/// An input that [`resolve_folder_selection`] rejected.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SelectionReject {
+    /// The input as given (a path, pattern, or `@file` argument).
+    pub input: String,
+    /// Why the input was rejected.
+    pub reason: RejectReason,
+}
+
+impl fmt::Display for SelectionReject {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}: {}", self.input, self.reason)
+    }
+}
+
+/// Why a selection input was rejected.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "kind", content = "detail", rename_all = "kebab-case")]
+pub enum RejectReason {
+    /// The path does not exist.
+    NotFound,
+    /// The path exists but is not a directory.
+    NotADirectory,
+    /// The glob pattern is malformed.
+    InvalidPattern(String),
+    /// The glob pattern matched nothing.
+    NoMatches,
+    /// A list file or standard input could not be read.
+    Unreadable(String),
+}
+
+impl fmt::Display for RejectReason {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            RejectReason::NotFound => f.write_str("no such file or directory"),
+            RejectReason::NotADirectory => f.write_str("not a directory"),
+            RejectReason::InvalidPattern(e) => write!(f, "invalid pattern: {}", e),
+            RejectReason::NoMatches => f.write_str("pattern matched no folders"),
+            RejectReason::Unreadable(e) => write!(f, "could not be read: {}", e),
+        }
+    }
+}
+
+/// Folders to operate on, as paths and glob patterns.
+///
+/// Unlike command-line arguments, paths are taken literally, even if they
+/// contain glob characters, and a leading `~` is only expanded in
+/// patterns.
+///
+/// # Example
+///
+/// ```ignore
+/// use folco_core::FolderSelector;
+///
+/// let selector = FolderSelector::glob("~/Projects/*/src").with_path("/srv/shared");
+/// let outcome = ctx.customize_selected(&selector, &profile);
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct FolderSelector {
+    /// The paths and patterns, in order.
+    pub entries: Vec<SelectorEntry>,
+}
+
+/// One entry of a [`FolderSelector`].
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "kind", content = "value", rename_all = "kebab-case")]
+pub enum SelectorEntry {
+    /// A single folder.
+    Path(PathBuf),
+    /// A glob pattern, such as `~/Projects/*/src`.
+    Glob(String),
+}
+
+impl FolderSelector {
+    /// Creates a selector that selects nothing.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Creates a selector of the folders `pattern` matches.
+    pub fn glob(pattern: impl Into<String>) -> Self {
+        Self::new().with_glob(pattern)
+    }
+
+    /// Creates a selector of the folders in `paths`.
+    pub fn paths<P: AsRef<Path>>(paths: &[P]) -> Self {
+        paths
+            .iter()
+            .fold(Self::new(), |selector, path| selector.with_path(path))
+    }
+
+    /// Adds the folders `pattern` matches.
+    pub fn with_glob(mut self, pattern: impl Into<String>) -> Self {
+        self.entries.push(SelectorEntry::Glob(pattern.into()));
+        self
+    }
+
+    /// Adds a single folder.
+    pub fn with_path(mut self, path: impl AsRef<Path>) -> Self {
+        self.entries
+            .push(SelectorEntry::Path(path.as_ref().to_path_buf()));
+        self
+    }
+
+    /// Expands the patterns and checks the paths, returning the existing
+    /// folders without duplicates.
+    ///
+    /// Missing paths, paths that aren't directories and patterns that are
+    /// malformed or match nothing are rejected. Files a pattern matched are
+    /// listed in [`FolderSelection::non_folders`].
+    pub fn resolve(&self) -> FolderSelection {
+        let mut resolved = Resolved::default();
+        for entry in &self.entries {
+            match entry {
+                SelectorEntry::Path(path) => match validate_folder(path) {
+                    Ok(()) => resolved.candidates.push(path.clone()),
+                    Err(reason) => resolved.reject(&path.display().to_string(), reason),
+                },
+                SelectorEntry::Glob(pattern) => resolve_glob(pattern, &mut resolved),
+            }
+        }
+        resolved.finish()
+    }
+}
+
+/// Inputs found so far while resolving a selection.
+#[derive(Default)]
+struct Resolved {
+    candidates: Vec<PathBuf>,
+    rejects: Vec<SelectionReject>,
+    non_folders: Vec<PathBuf>,
+}
+
+impl Resolved {
+    fn reject(&mut self, input: &str, reason: RejectReason) {
+        self.rejects.push(SelectionReject {
+            input: input.to_string(),
+            reason,
+        });
+    }
+
+    fn finish(self) -> FolderSelection {
+        let normalized = normalize_folders(&self.candidates);
+        FolderSelection {
+            folders: normalized.folders,
+            rejects: self.rejects,
+            duplicates: normalized.duplicates,
+            non_folders: self.non_folders,
+        }
+    }
+}
+
+/// Resolves a list of command-line folder arguments into folders.
+///
+/// Standard input is only read if one of the arguments is `-`.
+///
+/// # Example
+///
+/// ```ignore
+/// use folco_core::resolve_folder_selection;
+///
+/// let selection = resolve_folder_selection(&["~/Projects/*", "@more.txt"]);
+/// for reject in &selection.rejects {
+///     eprintln!("skipping {}: {}", reject.input, reject.reason);
+/// }
+/// ctx.customize_folders(&selection.folders, &profile);
+/// ```
+pub fn resolve_folder_selection<S: AsRef<str>>(spec: &[S]) -> FolderSelection {
+    resolve_with_stdin(spec, || io::stdin().lock())
+}
+
+/// Implementation of [`resolve_folder_selection`] with an injectable stdin.
+fn resolve_with_stdin<S, R, F>(spec: &[S], stdin: F) -> FolderSelection
+where
+    S: AsRef<str>,
+    R: BufRead,
+    F: FnOnce() -> R,
+{
+    let mut resolved = Resolved::default();
+    let mut stdin = Some(stdin);
+
+    for arg in spec {
+        let arg = arg.as_ref();
+        if arg == "-" {
+            // Standard input can only be consumed once
+            if let Some(open) = stdin.take() {
+                read_list(open(), arg, &mut resolved);
+            }
+        } else if let Some(list_path) = arg.strip_prefix('@') {
+            match std::fs::File::open(expand_home(list_path)) {
+                Ok(file) => read_list(io::BufReader::new(file), arg, &mut resolved),
+                Err(e) => resolved.reject(arg, RejectReason::Unreadable(e.to_string())),
+            }
+        } else {
+            resolve_entry(arg, &mut resolved);
+        }
+    }
+
+    resolved.finish()
+}
+
+/// Reads one path or pattern per line from a list.
+fn read_list<R: BufRead>(reader: R, source: &str, resolved: &mut Resolved) {
+    for line in reader.lines() {
+        match line {
+            Ok(line) => {
+                let entry = line.trim();
+                if !entry.is_empty() && !entry.starts_with('#') {
+                    resolve_entry(entry, resolved);
+                }
+            }
+            Err(e) => {
+                resolved.reject(source, RejectReason::Unreadable(e.to_string()));
+                return;
+            }
+        }
+    }
+}
+
+/// Resolves a single path or glob pattern.
+fn resolve_entry(entry: &str, resolved: &mut Resolved) {
+    if is_glob(entry) {
+        resolve_glob(entry, resolved);
+        return;
+    }
+    let expanded = expand_home(entry);
+    match validate_folder(&expanded) {
+        Ok(()) => resolved.candidates.push(expanded),
+        Err(reason) => resolved.reject(entry, reason),
+    }
+}
+
+/// Resolves a glob pattern, after expanding a leading `~`.
+fn resolve_glob(pattern: &str, resolved: &mut Resolved) {
+    let paths = match glob::glob(&expand_home(pattern).to_string_lossy()) {
+        Ok(paths) => paths,
+        Err(e) => {
+            resolved.reject(pattern, RejectReason::InvalidPattern(e.to_string()));
+            return;
+        }
+    };
+
+    let mut matched = false;
+    for path in paths {
+        match path {
+            Ok(path) if path.is_dir() => {
+                matched = true;
+                resolved.candidates.push(path);
+            }
+            // Files matched by a pattern are left out, but reported
+            Ok(path) => resolved.non_folders.push(path),
+            Err(e) => resolved.rejects.push(SelectionReject {
+                input: e.path().display().to_string(),
+                reason: RejectReason::Unreadable(e.error().to_string()),
+            }),
+        }
+    }
+
+    if !matched {
+        resolved.reject(pattern, RejectReason::NoMatches);
+    }
+}
+
+/// Checks that `path` exists and is a directory.
+fn validate_folder(path: &Path) -> std::result::Result<(), RejectReason> {
+    match std::fs::metadata(path) {
+        Ok(meta) if meta.is_dir() => Ok(()),
+        Ok(_) => Err(RejectReason::NotADirectory),
+        Err(_) => Err(RejectReason::NotFound),
+    }
+}
+
+/// Returns `true` if `entry` contains glob metacharacters.
+fn is_glob(entry: &str) -> bool {
+    entry.contains(['*', '?', '['])
+}
+
+/// Expands a leading `~` to the user's home directory.
+pub(crate) fn expand_home(entry: &str) -> PathBuf {
+    let rest = match entry.strip_prefix('~') {
+        Some(rest) if rest.is_empty() || rest.starts_with(['/', '\\']) => rest,
+        _ => return PathBuf::from(entry),
+    };
+
+    match directories::BaseDirs::new() {
+        Some(dirs) => dirs.home_dir().join(rest.trim_start_matches(['/', '\\'])),
+        None => PathBuf::from(entry),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+    use tempfile::tempdir;
+
+    fn no_stdin() -> io::Cursor<Vec<u8>> {
+        panic!("stdin should not be read")
+    }
+
+    #[test]
+    fn test_plain_paths_are_validated() {
+        let temp = tempdir().unwrap();
+        let folder = temp.path().join("folder");
+        let file = temp.path().join("file.txt");
+        fs::create_dir(&folder).unwrap();
+        fs::write(&file, "").unwrap();
+        let missing = temp.path().join("missing");
+
+        let spec = [
+            folder.to_string_lossy().to_string(),
+            file.to_string_lossy().to_string(),
+            missing.to_string_lossy().to_string(),
+        ];
+        let selection = resolve_with_stdin(&spec, no_stdin);
+
+        assert_eq!(selection.folders.len(), 1);
+        assert_eq!(selection.rejects.len(), 2);
+        assert_eq!(selection.rejects[0].reason, RejectReason::NotADirectory);
+        assert_eq!(selection.rejects[1].reason, RejectReason::NotFound);
+    }
+
+    #[test]
+    fn test_glob_matches_only_directories() {
+        let temp = tempdir().unwrap();
+        fs::create_dir(temp.path().join("a")).unwrap();
+        fs::create_dir(temp.path().join("b")).unwrap();
+        fs::write(temp.path().join("c.txt"), "").unwrap();
+
+        let pattern = temp.path().join("*").to_string_lossy().to_string();
+        let selection = resolve_with_stdin(&[pattern], no_stdin);
+
+        assert_eq!(selection.folders.len(), 2);
+        assert!(selection.rejects.is_empty());
+        assert_eq!(selection.non_folders, [temp.path().join("c.txt")]);
+    }
+
+    #[test]
+    fn test_glob_without_matches_is_rejected() {
+        let temp = tempdir().unwrap();
+        let pattern = temp.path().join("nothing-*").to_string_lossy().to_string();
+        let selection = resolve_with_stdin(&[pattern], no_stdin);
+
+        assert!(selection.folders.is_empty());
+        assert_eq!(selection.rejects[0].reason, RejectReason::NoMatches);
+    }
+
+    #[test]
+    fn test_list_file_and_stdin() {
+        let temp = tempdir().unwrap();
+        let a = temp.path().join("a");
+        let b = temp.path().join("b");
+        fs::create_dir(&a).unwrap();
+        fs::create_dir(&b).unwrap();
+
+        let list = temp.path().join("list.txt");
+        fs::write(&list, format!("# comment\n\n{}\n", a.display())).unwrap();
+
+        let spec = [format!("@{}", list.display()), "-".to_string()];
+        let stdin = format!("{}\n{}\n", b.display(), a.display());
+        let selection = resolve_with_stdin(&spec, || io::Cursor::new(stdin.into_bytes()));
+
+        assert_eq!(selection.folders.len(), 2);
+        assert_eq!(selection.duplicates.len(), 1);
+        assert!(selection.rejects.is_empty());
+    }
+
+    #[test]
+    fn test_missing_list_file_is_rejected() {
+        let selection = resolve_with_stdin(&["@/definitely/not/here.txt"], no_stdin);
+        assert!(matches!(
+            selection.rejects[0].reason,
+            RejectReason::Unreadable(_)
+        ));
+    }
+
+    #[test]
+    fn test_folder_selector() {
+        let temp = tempdir().unwrap();
+        let literal = temp.path().join("[draft]");
+        fs::create_dir(&literal).unwrap();
+        fs::create_dir(temp.path().join("a")).unwrap();
+        fs::write(temp.path().join("b.txt"), "").unwrap();
+
+        let selector = FolderSelector::glob(temp.path().join("*").to_string_lossy())
+            .with_path(&literal)
+            .with_path(temp.path().join("missing"));
+        let selection = selector.resolve();
+        assert_eq!(selection.folders.len(), 2);
+        assert_eq!(selection.duplicates.len(), 1);
+        assert_eq!(selection.non_folders, [temp.path().join("b.txt")]);
+        assert_eq!(selection.rejects.len(), 1);
+        assert_eq!(selection.rejects[0].reason, RejectReason::NotFound);
+
+        let mut outcome = BatchOutcome::default();
+        selection.merge_into(&mut outcome);
+        assert_eq!(outcome.failed_count(), 1);
+        assert!(matches!(outcome.results[0].1, Err(Error::Selection(_))));
+    }
+
+    #[test]
+    fn test_expand_home() {
+        assert_eq!(expand_home("/abs/path"), PathBuf::from("/abs/path"));
+        assert_eq!(expand_home("~user/x"), PathBuf::from("~user/x"));
+        if let Some(dirs) = directories::BaseDirs::new() {
+            assert_eq!(expand_home("~/x"), dirs.home_dir().join("x"));
+        }
+    }
+}
@@ -0,0 +1,151 @@
+//! Sharpening of small icon sizes after rendering.
+//!
+//! Recoloring softens edges, which barely shows at 256 pixels but makes 16-
+//! to 24-pixel icons look muddy in Explorer's list and details views. The
+//! optional pass in [`SharpenOptions`] runs on the rendered images of the
+//! small sizes only, before they're applied: an unsharp mask followed by a
+//! slight contrast boost. Transparency is left alone, so the folder's
+//! outline doesn't change.
+
+use icon_sys::IconSet as SysIconSet;
+use image::{DynamicImage, RgbaImage};
+use serde::{Deserialize, Serialize};
+
+/// Settings of the sharpening pass for small icons.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct SharpenOptions {
+    /// How strongly edges are sharpened, from 0 (not at all) to about 2.
+    pub amount: f32,
+    /// How much contrast is added, from 0 (none) to 1 (doubled).
+    pub contrast: f32,
+    /// Largest size, in pixels, that's sharpened.
+    pub max_size: u32,
+}
+
+impl Default for SharpenOptions {
+    fn default() -> Self {
+        Self {
+            amount: 0.6,
+            contrast: 0.1,
+            max_size: 24,
+        }
+    }
+}
+
+impl SharpenOptions {
+    /// Creates the default settings, which sharpen 16-, 20- and 24-pixel
+    /// icons.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Sets how strongly edges are sharpened.
+    pub fn with_amount(mut self, amount: f32) -> Self {
+        self.amount = amount;
+        self
+    }
+
+    /// Sets how much contrast is added.
+    pub fn with_contrast(mut self, contrast: f32) -> Self {
+        self.contrast = contrast;
+        self
+    }
+
+    /// Sets the largest size that's sharpened.
+    pub fn with_max_size(mut self, max_size: u32) -> Self {
+        self.max_size = max_size;
+        self
+    }
+}
+
+/// Sharpens the images of `icons` up to `options.max_size` pixels.
+pub(crate) fn sharpen_small_icons(icons: &mut SysIconSet, options: &SharpenOptions) {
+    for image in &mut icons.images {
+        if image.data.width() <= options.max_size {
+            let sharpened = sharpen(&image.data.to_rgba8(), options);
+            image.data = DynamicImage::ImageRgba8(sharpened);
+        }
+    }
+}
+
+/// Applies an unsharp mask with a 3×3 box blur, then the contrast boost, to
+/// the color channels of `image`.
+///
+/// Only visible neighbors are averaged, so the transparent canvas
+/// around the folder doesn't darken its edges.
+fn sharpen(image: &RgbaImage, options: &SharpenOptions) -> RgbaImage {
+    let (width, height) = image.dimensions();
+    let mut out = image.clone();
+    for y in 0..height {
+        for x in 0..width {
+            let pixel = image.get_pixel(x, y).0;
+            if pixel[3] == 0 {
+                continue;
+            }
+
+            let mut sum = [0.0f32; 3];
+            let mut count = 0.0f32;
+            for ny in y.saturating_sub(1)..=(y + 1).min(height - 1) {
+                for nx in x.saturating_sub(1)..=(x + 1).min(width - 1) {
+                    let neighbor = image.get_pixel(nx, ny).0;
+                    if neighbor[3] == 0 {
+                        continue;
+                    }
+                    for (total, channel) in sum.iter_mut().zip(neighbor) {
+                        *total += f32::from(channel);
+                    }
+                    count += 1.0;
+                }
+            }
+
+            let sharpened = out.get_pixel_mut(x, y);
+            for channel in 0..3 {
+                let value = f32::from(pixel[channel]);
+                let blurred = sum[channel] / count;
+                let value = value + options.amount * (value - blurred);
+                let value = (value - 128.0) * (1.0 + options.contrast) + 128.0;
+                sharpened.0[channel] = value.round().clamp(0.0, 255.0) as u8;
+            }
+        }
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use image::Rgba;
+
+    #[test]
+    fn test_only_small_sizes_are_sharpened() {
+        let mut edge = RgbaImage::from_pixel(16, 16, Rgba([100, 100, 100, 255]));
+        edge.put_pixel(8, 8, Rgba([160, 160, 160, 255]));
+        let mut large = RgbaImage::from_pixel(32, 32, Rgba([100, 100, 100, 255]));
+        large.put_pixel(8, 8, Rgba([160, 160, 160, 255]));
+        let mut icons = SysIconSet {
+            images: [edge, large.clone()]
+                .into_iter()
+                .map(|image| icon_sys::IconImage {
+                    data: DynamicImage::ImageRgba8(image),
+                })
+                .collect(),
+        };
+
+        sharpen_small_icons(&mut icons, &SharpenOptions::new().with_contrast(0.0));
+        let small = icons.images[0].data.to_rgba8();
+        // The bright pixel gets brighter, its neighbors darker
+        assert!(small.get_pixel(8, 8).0[0] > 160);
+        assert!(small.get_pixel(7, 8).0[0] < 100);
+        assert_eq!(icons.images[1].data.to_rgba8(), large);
+    }
+
+    #[test]
+    fn test_transparency_is_kept() {
+        let mut image = RgbaImage::from_pixel(4, 4, Rgba([0, 0, 0, 0]));
+        image.put_pixel(1, 1, Rgba([200, 50, 50, 255]));
+        let sharpened = sharpen(&image, &SharpenOptions::new());
+        assert_eq!(sharpened.get_pixel(0, 0).0, [0, 0, 0, 0]);
+        assert_eq!(sharpened.get_pixel(1, 1).0[3], 255);
+    }
+}
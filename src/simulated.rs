@@ -0,0 +1,125 @@
+//! An in-memory folder backend, with the `simulated` feature.
+//!
+//! [`SimulatedBackend`] sets folder icons in memory instead of on disk, and
+//! serves the Windows [fixture](crate::fixtures) as the default folder
+//! icon, with the Windows content bounds and folder color. Passed to a context with
+//! [`with_folder_backend`](crate::CustomizationContextBuilder::with_folder_backend)
+//! and
+//! [`with_default_icon_backend`](crate::CustomizationContextBuilder::with_default_icon_backend),
+//! it lets a developer run the Windows-flavored pipeline end to end on any
+//! OS, and a GUI run a demo without touching real folders. Other contexts in the same
+//! process keep using the real platform.
+
+use crate::backend::{DefaultIconBackend, FolderIconBackend};
+use crate::error::Result;
+use crate::fixtures::{fixture_icon_set, FixturePlatform, FIXTURE_SURFACE_HSL};
+
+use folco_renderer::RectPx;
+use icon_sys::IconSet as SysIconSet;
+use image::DynamicImage;
+
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use std::sync::{Mutex, MutexGuard};
+
+/// Sets and resets folder icons in memory.
+///
+/// Each backend has its own folders. Share one between a context and its
+/// observers through an `Arc`.
+#[derive(Debug, Default)]
+pub struct SimulatedBackend {
+    folders: Mutex<BTreeMap<PathBuf, Vec<DynamicImage>>>,
+}
+
+impl SimulatedBackend {
+    /// Creates a backend with no customized folders.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Returns the custom icon of `folder`, if it has one.
+    pub fn icon(&self, folder: &Path) -> Option<SysIconSet> {
+        let images = self.folders().get(folder)?.clone();
+        Some(SysIconSet {
+            images: images
+                .into_iter()
+                .map(|data| icon_sys::IconImage { data })
+                .collect(),
+        })
+    }
+
+    /// Returns every folder with a custom icon, sorted.
+    pub fn customized_folders(&self) -> Vec<PathBuf> {
+        self.folders().keys().cloned().collect()
+    }
+
+    /// Resets every custom icon.
+    pub fn clear(&self) {
+        self.folders().clear();
+    }
+
+    fn folders(&self) -> MutexGuard<'_, BTreeMap<PathBuf, Vec<DynamicImage>>> {
+        self.folders.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+impl FolderIconBackend for SimulatedBackend {
+    fn set_icon(&self, folder: &Path, icons: &SysIconSet) -> Result<()> {
+        let images = icons
+            .images
+            .iter()
+            .map(|image| image.data.clone())
+            .collect();
+        self.folders().insert(folder.to_path_buf(), images);
+        Ok(())
+    }
+
+    fn reset_icon(&self, folder: &Path) -> Result<()> {
+        self.folders().remove(folder);
+        Ok(())
+    }
+
+    fn has_custom_icon(&self, folder: &Path) -> bool {
+        self.folders().contains_key(folder)
+    }
+}
+
+impl DefaultIconBackend for SimulatedBackend {
+    fn default_folder_icon(&self) -> Result<SysIconSet> {
+        Ok(fixture_icon_set(FixturePlatform::Windows))
+    }
+
+    fn content_bounds(&self, width: u32, _height: u32) -> RectPx {
+        FixturePlatform::Windows.content_bounds(width)
+    }
+
+    fn surface_hsl(&self) -> (f32, f32, f32) {
+        FIXTURE_SURFACE_HSL
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_backend_sets_and_resets_in_memory() {
+        let folder = Path::new("/simulated/Projects");
+        let backend = SimulatedBackend::new();
+        assert!(!backend.has_custom_icon(folder));
+
+        let icons = backend.default_folder_icon().unwrap();
+        backend.set_icon(folder, &icons).unwrap();
+        assert!(backend.has_custom_icon(folder));
+        assert_eq!(backend.customized_folders(), [folder.to_path_buf()]);
+        let icon = backend.icon(folder).unwrap();
+        assert_eq!(icon.images.len(), FixturePlatform::Windows.sizes().len());
+        assert!(!folder.exists());
+        // Folders are per backend, not per process
+        assert!(!SimulatedBackend::new().has_custom_icon(folder));
+
+        backend.reset_icon(folder).unwrap();
+        assert!(!backend.has_custom_icon(folder));
+        assert!(backend.icon(folder).is_none());
+    }
+}
@@ -0,0 +1,180 @@
+//! Profiles that vary by icon size.
+//!
+//! A decal or label that reads well at 256px is often an unreadable smudge
+//! at 16px. A [`SizedProfile`] pairs a profile with [`SizeOverride`]s that
+//! change or remove settings for a range of sizes, e.g. dropping the label
+//! below 48px or swapping in a simpler glyph at 16px. The overrides are
+//! flattened into a plain profile for each size at render time, so the
+//! renderer itself never sees them.
+
+use crate::error::Result;
+use crate::profile::{merge_profiles, profile_without};
+
+use folco_renderer::CustomizationProfile;
+use serde::{Deserialize, Serialize};
+
+/// A profile with size-conditional overrides.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SizedProfile {
+    /// The settings used at every size no override applies to.
+    pub profile: CustomizationProfile,
+    /// Overrides, applied in order on top of `profile` at the sizes they
+    /// cover.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub overrides: Vec<SizeOverride>,
+}
+
+impl SizedProfile {
+    /// Creates a sized profile without any overrides.
+    pub fn new(profile: CustomizationProfile) -> Self {
+        Self {
+            profile,
+            overrides: Vec::new(),
+        }
+    }
+
+    /// Adds an override, applied after the existing ones.
+    pub fn with_override(mut self, size_override: SizeOverride) -> Self {
+        self.overrides.push(size_override);
+        self
+    }
+
+    /// Returns the plain profile to render `size` with.
+    ///
+    /// # Errors
+    ///
+    /// Fails if an override can't be merged into the profile.
+    pub fn profile_for_size(&self, size: u32) -> Result<CustomizationProfile> {
+        flatten_for_size(&self.profile, &self.overrides, size)
+    }
+}
+
+impl From<CustomizationProfile> for SizedProfile {
+    fn from(profile: CustomizationProfile) -> Self {
+        Self::new(profile)
+    }
+}
+
+/// Settings that replace or remove parts of a profile for a range of sizes.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct SizeOverride {
+    /// Smallest size, in pixels, the override applies to. `None` means no
+    /// lower bound.
+    pub min_size: Option<u32>,
+    /// Largest size, in pixels, the override applies to. `None` means no
+    /// upper bound.
+    pub max_size: Option<u32>,
+    /// Settings layered on top of the profile, as by
+    /// [`merge_profiles`](crate::merge_profiles).
+    pub overlay: Option<CustomizationProfile>,
+    /// Top-level settings to remove, matched by name like `"decal"`. Removal
+    /// happens after the overlay is merged.
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub remove: Vec<String>,
+}
+
+impl SizeOverride {
+    /// Creates an override for sizes up to and including `max_size`.
+    pub fn at_most(max_size: u32) -> Self {
+        Self {
+            max_size: Some(max_size),
+            ..Self::default()
+        }
+    }
+
+    /// Creates an override for sizes from `min_size` up.
+    pub fn at_least(min_size: u32) -> Self {
+        Self {
+            min_size: Some(min_size),
+            ..Self::default()
+        }
+    }
+
+    /// Creates an override for exactly `size`.
+    pub fn exactly(size: u32) -> Self {
+        Self {
+            min_size: Some(size),
+            max_size: Some(size),
+            ..Self::default()
+        }
+    }
+
+    /// Layers `overlay` on top of the profile at the covered sizes.
+    pub fn with_overlay(mut self, overlay: CustomizationProfile) -> Self {
+        self.overlay = Some(overlay);
+        self
+    }
+
+    /// Removes the setting named like `name` at the covered sizes.
+    pub fn removing(mut self, name: impl Into<String>) -> Self {
+        self.remove.push(name.into());
+        self
+    }
+
+    /// Returns `true` if the override applies to `size`.
+    pub fn covers(&self, size: u32) -> bool {
+        self.min_size.is_none_or(|min| size >= min) && self.max_size.is_none_or(|max| size <= max)
+    }
+}
+
+/// Flattens `overrides` into `profile` for one size.
+pub(crate) fn flatten_for_size(
+    profile: &CustomizationProfile,
+    overrides: &[SizeOverride],
+    size: u32,
+) -> Result<CustomizationProfile> {
+    let mut flattened = profile.clone();
+    for size_override in overrides.iter().filter(|o| o.covers(size)) {
+        if let Some(overlay) = &size_override.overlay {
+            flattened = merge_profiles(&flattened, overlay)?;
+        }
+        for name in &size_override.remove {
+            flattened = profile_without(&flattened, name)?;
+        }
+    }
+    Ok(flattened)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::color::FolderColor;
+    use crate::profile::{profile_color, profile_hash, profile_with_color};
+
+    #[test]
+    fn test_covers() {
+        assert!(SizeOverride::at_most(32).covers(16));
+        assert!(SizeOverride::at_most(32).covers(32));
+        assert!(!SizeOverride::at_most(32).covers(48));
+        assert!(SizeOverride::at_least(48).covers(256));
+        assert!(!SizeOverride::exactly(16).covers(24));
+        assert!(SizeOverride::default().covers(1));
+    }
+
+    #[test]
+    fn test_overrides_apply_only_at_covered_sizes() {
+        let base = profile_with_color(&CustomizationProfile::default(), FolderColor::Blue).unwrap();
+        let small = profile_with_color(&CustomizationProfile::default(), FolderColor::Red).unwrap();
+        let sized = SizedProfile::new(base.clone())
+            .with_override(SizeOverride::at_most(16).with_overlay(small));
+
+        let at_16 = sized.profile_for_size(16).unwrap();
+        assert_eq!(profile_color(&at_16), Some(FolderColor::Red));
+        let at_256 = sized.profile_for_size(256).unwrap();
+        assert_eq!(profile_hash(&at_256), profile_hash(&base));
+    }
+
+    #[test]
+    fn test_remove_setting() {
+        let base = profile_with_color(&CustomizationProfile::default(), FolderColor::Teal).unwrap();
+        let sized =
+            SizedProfile::new(base).with_override(SizeOverride::at_most(32).removing("hsl"));
+        assert_eq!(profile_color(&sized.profile_for_size(16).unwrap()), None);
+        assert_eq!(
+            profile_color(&sized.profile_for_size(64).unwrap()),
+            Some(FolderColor::Teal)
+        );
+    }
+}
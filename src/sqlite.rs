@@ -0,0 +1,282 @@
+//! SQLite state backend (`storage-sqlite` feature).
+//!
+//! With tens of thousands of customized folders, rewriting and reparsing
+//! JSON files gets slow. [`SqliteStateStore`] keeps every collection and
+//! blob in one SQLite database instead: saves update only the changed rows
+//! inside a transaction, and entries are indexed by collection and key, so
+//! lookups and prefix queries don't need the whole collection in memory.
+
+use crate::error::{Error, Result};
+use crate::journal::{diff_entries, entry_hashes};
+use crate::state::{StateCollection, StateStore};
+
+use rusqlite::{params, Connection, OptionalExtension};
+use serde_json::Value;
+
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use std::sync::{Mutex, MutexGuard};
+
+/// File name of the database inside the app data directory.
+pub const SQLITE_FILE_NAME: &str = "state.sqlite3";
+
+const SCHEMA: &str = "
+    CREATE TABLE IF NOT EXISTS entries (
+        collection TEXT NOT NULL,
+        key TEXT NOT NULL,
+        value TEXT NOT NULL,
+        PRIMARY KEY (collection, key)
+    ) WITHOUT ROWID;
+    CREATE TABLE IF NOT EXISTS blobs (
+        key TEXT PRIMARY KEY,
+        data BLOB NOT NULL
+    ) WITHOUT ROWID;
+";
+
+/// Stores folco's state in a single SQLite database.
+#[derive(Debug)]
+pub struct SqliteStateStore {
+    path: PathBuf,
+    db: Mutex<Database>,
+}
+
+#[derive(Debug)]
+struct Database {
+    connection: Connection,
+    /// Hashes of the rows each collection had when last loaded or saved.
+    persisted: BTreeMap<StateCollection, BTreeMap<String, u64>>,
+}
+
+impl SqliteStateStore {
+    /// Opens the database at `path`, creating it if needed.
+    pub fn open(path: impl Into<PathBuf>) -> Result<Self> {
+        let path = path.into();
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        let connection = Connection::open(&path)?;
+        // The WAL journal mode keeps the database readable while a save
+        // commits, and makes commits survive crashes without a full rewrite
+        connection.pragma_update(None, "journal_mode", "WAL")?;
+        connection.execute_batch(SCHEMA)?;
+
+        Ok(Self {
+            path,
+            db: Mutex::new(Database {
+                connection,
+                persisted: BTreeMap::new(),
+            }),
+        })
+    }
+
+    /// Opens the database in the given app data directory.
+    pub fn in_data_dir(data_dir: &Path) -> Result<Self> {
+        Self::open(data_dir.join(SQLITE_FILE_NAME))
+    }
+
+    /// Returns the path of the database file.
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Looks up a single entry, without loading the collection.
+    pub fn get(&self, collection: StateCollection, key: &str) -> Result<Option<Value>> {
+        let db = self.lock();
+        let value: Option<String> = db
+            .connection
+            .query_row(
+                "SELECT value FROM entries WHERE collection = ?1 AND key = ?2",
+                params![collection.name(), key],
+                |row| row.get(0),
+            )
+            .optional()?;
+        value.map(|value| parse_value(&value)).transpose()
+    }
+
+    /// Returns the keys in `collection` starting with `prefix`, in order.
+    ///
+    /// For the profile store, whose keys are paths, this finds every
+    /// customized folder below a directory.
+    pub fn keys_with_prefix(
+        &self,
+        collection: StateCollection,
+        prefix: &str,
+    ) -> Result<Vec<String>> {
+        let db = self.lock();
+        // A range over the primary key rather than LIKE, which can't use the
+        // index and treats `%` and `_` in paths as wildcards
+        let mut statement = db
+            .connection
+            .prepare("SELECT key FROM entries WHERE collection = ?1 AND key >= ?2 ORDER BY key")?;
+        let rows = statement.query_map(params![collection.name(), prefix], |row| row.get(0))?;
+        let mut keys = Vec::new();
+        for key in rows {
+            let key: String = key?;
+            if !key.starts_with(prefix) {
+                break;
+            }
+            keys.push(key);
+        }
+        Ok(keys)
+    }
+
+    fn lock(&self) -> MutexGuard<'_, Database> {
+        self.db.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+impl StateStore for SqliteStateStore {
+    fn load(&self, collection: StateCollection) -> Result<BTreeMap<String, Value>> {
+        let mut db = self.lock();
+        let mut entries = BTreeMap::new();
+        {
+            let mut statement = db
+                .connection
+                .prepare("SELECT key, value FROM entries WHERE collection = ?1")?;
+            let rows = statement.query_map(params![collection.name()], |row| {
+                Ok((row.get::<_, String>(0)?, row.get::<_, String>(1)?))
+            })?;
+            for row in rows {
+                let (key, value) = row?;
+                entries.insert(key, parse_value(&value)?);
+            }
+        }
+        db.persisted.insert(collection, entry_hashes(&entries)?);
+        Ok(entries)
+    }
+
+    fn save(&self, collection: StateCollection, entries: &BTreeMap<String, Value>) -> Result<()> {
+        let mut db = self.lock();
+        let Database {
+            connection,
+            persisted,
+        } = &mut *db;
+
+        // Without a previous load, nothing is known about the rows, so
+        // they're all replaced
+        let known = persisted.get(&collection);
+        let diff = diff_entries(known.unwrap_or(&BTreeMap::new()), entries)?;
+        if known.is_some() && diff.is_empty() {
+            return Ok(());
+        }
+
+        let transaction = connection.transaction()?;
+        if known.is_none() {
+            transaction.execute(
+                "DELETE FROM entries WHERE collection = ?1",
+                params![collection.name()],
+            )?;
+        }
+        {
+            let mut upsert = transaction.prepare(
+                "INSERT INTO entries (collection, key, value) VALUES (?1, ?2, ?3)
+                 ON CONFLICT (collection, key) DO UPDATE SET value = excluded.value",
+            )?;
+            for (key, value) in &diff.puts {
+                upsert.execute(params![collection.name(), key, value.to_string()])?;
+            }
+            let mut delete =
+                transaction.prepare("DELETE FROM entries WHERE collection = ?1 AND key = ?2")?;
+            for key in &diff.deletes {
+                delete.execute(params![collection.name(), key])?;
+            }
+        }
+        transaction.commit()?;
+
+        persisted.insert(collection, diff.hashes);
+        Ok(())
+    }
+
+    fn read_blob(&self, key: &str) -> Result<Option<Vec<u8>>> {
+        let db = self.lock();
+        let data = db
+            .connection
+            .query_row(
+                "SELECT data FROM blobs WHERE key = ?1",
+                params![key],
+                |row| row.get(0),
+            )
+            .optional()?;
+        Ok(data)
+    }
+
+    fn write_blob(&self, key: &str, data: &[u8]) -> Result<()> {
+        self.lock().connection.execute(
+            "INSERT INTO blobs (key, data) VALUES (?1, ?2)
+             ON CONFLICT (key) DO UPDATE SET data = excluded.data",
+            params![key, data],
+        )?;
+        Ok(())
+    }
+
+    fn remove_blob(&self, key: &str) -> Result<()> {
+        self.lock()
+            .connection
+            .execute("DELETE FROM blobs WHERE key = ?1", params![key])?;
+        Ok(())
+    }
+
+    fn location(&self, _collection: StateCollection) -> PathBuf {
+        self.path.clone()
+    }
+
+    fn compact(&self, _collection: StateCollection) -> Result<()> {
+        let db = self.lock();
+        db.connection
+            .execute_batch("PRAGMA wal_checkpoint(TRUNCATE); VACUUM;")?;
+        Ok(())
+    }
+}
+
+fn parse_value(value: &str) -> Result<Value> {
+    serde_json::from_str(value).map_err(|e| Error::Serialization(e.to_string()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+    use tempfile::tempdir;
+
+    #[test]
+    fn test_save_load_and_query() {
+        let temp = tempdir().unwrap();
+        let store = SqliteStateStore::in_data_dir(temp.path()).unwrap();
+        assert!(store.load(StateCollection::Profiles).unwrap().is_empty());
+
+        let mut entries = BTreeMap::new();
+        entries.insert("/data/a".to_string(), json!({ "profileHash": "1" }));
+        entries.insert("/data/a/b".to_string(), json!({ "profileHash": "2" }));
+        entries.insert("/other".to_string(), json!({ "profileHash": "3" }));
+        store.save(StateCollection::Profiles, &entries).unwrap();
+
+        entries.remove("/other");
+        store.save(StateCollection::Profiles, &entries).unwrap();
+
+        let reopened = SqliteStateStore::in_data_dir(temp.path()).unwrap();
+        assert_eq!(reopened.load(StateCollection::Profiles).unwrap(), entries);
+        assert_eq!(
+            reopened
+                .get(StateCollection::Profiles, "/data/a/b")
+                .unwrap(),
+            Some(json!({ "profileHash": "2" }))
+        );
+        assert_eq!(
+            reopened
+                .keys_with_prefix(StateCollection::Profiles, "/data/")
+                .unwrap(),
+            vec!["/data/a".to_string(), "/data/a/b".to_string()]
+        );
+    }
+
+    #[test]
+    fn test_blobs() {
+        let temp = tempdir().unwrap();
+        let store = SqliteStateStore::in_data_dir(temp.path()).unwrap();
+
+        store.write_blob("thumb", b"png").unwrap();
+        assert_eq!(store.read_blob("thumb").unwrap(), Some(b"png".to_vec()));
+        store.remove_blob("thumb").unwrap();
+        assert_eq!(store.read_blob("thumb").unwrap(), None);
+    }
+}
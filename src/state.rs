@@ -0,0 +1,500 @@
+//! Pluggable persistence for folco's state.
+//!
+//! Everything folco remembers between sessions is a collection of keyed
+//! JSON entries, such as the profile store's records and the saved presets,
+//! plus a few blobs: the app config, which also holds the rules, and preset
+//! thumbnails. A [`StateStore`] persists them. The default
+//! [`JsonStateStore`] keeps each collection in a journaled JSON file in the
+//! app data directory, and [`MemoryStateStore`] keeps everything in memory,
+//! for tests and for apps that persist state their own way. Other backends,
+//! such as the SQLite one behind the `storage-sqlite` feature or one built
+//! on a frontend's own store, are handed to the context with
+//! [`with_state_store`](crate::CustomizationContextBuilder::with_state_store).
+
+use crate::error::{Error, Result};
+use crate::journal::{log_path, Journal};
+use crate::migrate::{backup_file, Migrations};
+use crate::store::write_atomic;
+
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use serde_json::{Map, Value};
+
+use std::collections::btree_map::Entry;
+use std::collections::BTreeMap;
+use std::fmt::Debug;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex, MutexGuard};
+
+/// A collection of persistent state.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[non_exhaustive]
+pub enum StateCollection {
+    /// The profile store's records of customized folders, keyed by path.
+    Profiles,
+    /// The preset library, keyed by preset name.
+    Presets,
+}
+
+impl StateCollection {
+    /// Returns a stable name for the collection, for backends to use as a
+    /// table or file name.
+    pub fn name(&self) -> &'static str {
+        match self {
+            StateCollection::Profiles => "profiles",
+            StateCollection::Presets => "presets",
+        }
+    }
+}
+
+/// Persists collections of keyed JSON entries and binary blobs.
+///
+/// Entries are loaded all at once and saved as a whole map; backends
+/// compare it with what they last persisted and write only the changes.
+pub trait StateStore: Debug + Send + Sync {
+    /// Loads every entry of `collection`. A collection that was never saved
+    /// is empty.
+    fn load(&self, collection: StateCollection) -> Result<BTreeMap<String, Value>>;
+
+    /// Persists `entries` as the new contents of `collection`.
+    fn save(&self, collection: StateCollection, entries: &BTreeMap<String, Value>) -> Result<()>;
+
+    /// Reads the blob stored under `key`, if any.
+    fn read_blob(&self, key: &str) -> Result<Option<Vec<u8>>>;
+
+    /// Stores `data` under `key`, replacing any previous blob.
+    fn write_blob(&self, key: &str, data: &[u8]) -> Result<()>;
+
+    /// Removes the blob stored under `key`, if any.
+    fn remove_blob(&self, key: &str) -> Result<()>;
+
+    /// Returns where `collection` is stored, for display. Backends that
+    /// don't store to a file return an empty path.
+    fn location(&self, collection: StateCollection) -> PathBuf;
+
+    /// Reorganizes the storage of `collection` for faster loading, if the
+    /// backend supports it.
+    fn compact(&self, _collection: StateCollection) -> Result<()> {
+        Ok(())
+    }
+}
+
+/// Loads `collection` from `state` and parses each entry as a `V`.
+///
+/// Entries that can't be parsed, e.g. ones written by a newer version,
+/// fail the whole load rather than being dropped on the next save.
+pub(crate) fn load_entries<V: DeserializeOwned>(
+    state: &dyn StateStore,
+    collection: StateCollection,
+) -> Result<BTreeMap<String, V>> {
+    state
+        .load(collection)?
+        .into_iter()
+        .map(|(key, value)| {
+            let entry =
+                serde_json::from_value(value).map_err(|e| Error::Serialization(e.to_string()))?;
+            Ok((key, entry))
+        })
+        .collect()
+}
+
+/// Serializes `entries` and saves them as `collection` in `state`.
+pub(crate) fn save_entries<V: Serialize>(
+    state: &dyn StateStore,
+    collection: StateCollection,
+    entries: &BTreeMap<String, V>,
+) -> Result<()> {
+    let values = entries
+        .iter()
+        .map(|(key, entry)| {
+            let value =
+                serde_json::to_value(entry).map_err(|e| Error::Serialization(e.to_string()))?;
+            Ok((key.clone(), value))
+        })
+        .collect::<Result<_>>()?;
+    state.save(collection, &values)
+}
+
+/// Upgrade path of the profile store file.
+static PROFILES_MIGRATIONS: Migrations = Migrations::new("profile store", &[]);
+
+/// Upgrade path of the preset library file.
+static PRESETS_MIGRATIONS: Migrations = Migrations::new("preset library", &[]);
+
+/// The default backend: one journaled JSON file per collection, and a file
+/// per blob, all in one directory.
+///
+/// The files keep the layout of earlier versions, e.g. `profiles.json`
+/// holds `{"version": 1, "entries": {...}}`, so existing data loads as is.
+#[derive(Debug)]
+pub struct JsonStateStore {
+    dir: PathBuf,
+    files: BTreeMap<StateCollection, PathBuf>,
+    journals: Mutex<BTreeMap<StateCollection, Journal>>,
+}
+
+impl JsonStateStore {
+    /// Creates a store keeping its files in `dir`.
+    pub fn new(dir: impl Into<PathBuf>) -> Self {
+        Self {
+            dir: dir.into(),
+            files: BTreeMap::new(),
+            journals: Mutex::new(BTreeMap::new()),
+        }
+    }
+
+    /// Stores `collection` in the file at `path` instead of its default
+    /// file in the directory.
+    pub fn with_file(mut self, collection: StateCollection, path: impl Into<PathBuf>) -> Self {
+        self.files.insert(collection, path.into());
+        self
+    }
+
+    /// Returns the directory the store keeps its files in.
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+
+    /// Opens the journal of `collection` and returns its entries, upgrading
+    /// the snapshot first if an older version wrote it.
+    fn open(&self, collection: StateCollection) -> Result<(Journal, BTreeMap<String, Value>)> {
+        let migrations = snapshot_migrations(collection);
+        let field = snapshot_field(collection);
+        let path = self.location(collection);
+        let mut version = migrations.current();
+        let (mut journal, entries) = Journal::open(path.clone(), |content| {
+            let mut snapshot: Value =
+                serde_json::from_str(content).map_err(|e| Error::Serialization(e.to_string()))?;
+            version = migrations.version_of(&snapshot);
+            migrations.check_supported(version)?;
+            match snapshot.get_mut(field) {
+                Some(entries) => serde_json::from_value(entries.take())
+                    .map_err(|e| Error::Serialization(e.to_string())),
+                None => Ok(BTreeMap::new()),
+            }
+        })?;
+        if version == migrations.current() {
+            return Ok((journal, entries));
+        }
+
+        // Every version keeps the entries under the same field, so the log
+        // can be replayed before upgrading; the steps then see the complete
+        // old document
+        let mut document = Map::new();
+        document.insert("version".to_string(), Value::from(version));
+        document.insert(
+            field.to_string(),
+            Value::Object(entries.into_iter().collect()),
+        );
+        let mut upgraded = migrations.upgrade(Value::Object(document), version)?;
+        let entries: BTreeMap<String, Value> = match upgraded.get_mut(field) {
+            Some(entries) => serde_json::from_value(entries.take())
+                .map_err(|e| Error::Serialization(e.to_string()))?,
+            None => BTreeMap::new(),
+        };
+
+        backup_file(&path, version)?;
+        backup_file(&log_path(&path), version)?;
+        journal.write_snapshot(|| snapshot_json(collection, &entries))?;
+        // Reopened so the journal diffs the next save against the upgraded
+        // entries
+        self.open(collection)
+    }
+
+    fn lock(&self) -> MutexGuard<'_, BTreeMap<StateCollection, Journal>> {
+        self.journals.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    fn blob_path(&self, key: &str) -> PathBuf {
+        self.dir.join(key)
+    }
+}
+
+impl StateStore for JsonStateStore {
+    fn load(&self, collection: StateCollection) -> Result<BTreeMap<String, Value>> {
+        let (journal, entries) = self.open(collection)?;
+        self.lock().insert(collection, journal);
+        Ok(entries)
+    }
+
+    fn save(&self, collection: StateCollection, entries: &BTreeMap<String, Value>) -> Result<()> {
+        let mut journals = self.lock();
+        let journal = match journals.entry(collection) {
+            Entry::Occupied(entry) => entry.into_mut(),
+            Entry::Vacant(entry) => entry.insert(self.open(collection)?.0),
+        };
+        journal.save(entries, || snapshot_json(collection, entries))
+    }
+
+    fn read_blob(&self, key: &str) -> Result<Option<Vec<u8>>> {
+        match fs::read(self.blob_path(key)) {
+            Ok(data) => Ok(Some(data)),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(None),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    fn write_blob(&self, key: &str, data: &[u8]) -> Result<()> {
+        write_atomic(&self.blob_path(key), data)
+    }
+
+    fn remove_blob(&self, key: &str) -> Result<()> {
+        match fs::remove_file(self.blob_path(key)) {
+            Err(e) if e.kind() != std::io::ErrorKind::NotFound => Err(e.into()),
+            _ => Ok(()),
+        }
+    }
+
+    fn location(&self, collection: StateCollection) -> PathBuf {
+        match self.files.get(&collection) {
+            Some(path) => path.clone(),
+            None => self.dir.join(format!("{}.json", collection.name())),
+        }
+    }
+
+    fn compact(&self, collection: StateCollection) -> Result<()> {
+        let mut journals = self.lock();
+        let Some(journal) = journals.get_mut(&collection) else {
+            return Ok(());
+        };
+        // Only the journal's own snapshot and log are read here, so what's
+        // compacted is exactly what was last saved
+        let (_, entries) = self.open(collection)?;
+        journal.write_snapshot(|| snapshot_json(collection, &entries))
+    }
+}
+
+/// A backend keeping everything in memory.
+///
+/// Nothing outlives the store, so tests using it never touch the disk.
+/// Clones share the same state.
+#[derive(Debug, Clone, Default)]
+pub struct MemoryStateStore {
+    inner: Arc<Mutex<MemoryState>>,
+}
+
+#[derive(Debug, Default)]
+struct MemoryState {
+    collections: BTreeMap<StateCollection, BTreeMap<String, Value>>,
+    blobs: BTreeMap<String, Vec<u8>>,
+}
+
+impl MemoryStateStore {
+    /// Creates an empty store.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    fn lock(&self) -> MutexGuard<'_, MemoryState> {
+        self.inner.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+impl StateStore for MemoryStateStore {
+    fn load(&self, collection: StateCollection) -> Result<BTreeMap<String, Value>> {
+        Ok(self
+            .lock()
+            .collections
+            .get(&collection)
+            .cloned()
+            .unwrap_or_default())
+    }
+
+    fn save(&self, collection: StateCollection, entries: &BTreeMap<String, Value>) -> Result<()> {
+        self.lock().collections.insert(collection, entries.clone());
+        Ok(())
+    }
+
+    fn read_blob(&self, key: &str) -> Result<Option<Vec<u8>>> {
+        Ok(self.lock().blobs.get(key).cloned())
+    }
+
+    fn write_blob(&self, key: &str, data: &[u8]) -> Result<()> {
+        self.lock().blobs.insert(key.to_string(), data.to_vec());
+        Ok(())
+    }
+
+    fn remove_blob(&self, key: &str) -> Result<()> {
+        self.lock().blobs.remove(key);
+        Ok(())
+    }
+
+    fn location(&self, _collection: StateCollection) -> PathBuf {
+        PathBuf::new()
+    }
+}
+
+/// A [`StateStore`] that rejects writes, for read-only contexts.
+#[derive(Debug)]
+pub(crate) struct ReadOnlyStateStore {
+    inner: Arc<dyn StateStore>,
+}
+
+impl ReadOnlyStateStore {
+    pub(crate) fn new(inner: Arc<dyn StateStore>) -> Self {
+        Self { inner }
+    }
+}
+
+impl StateStore for ReadOnlyStateStore {
+    fn load(&self, collection: StateCollection) -> Result<BTreeMap<String, Value>> {
+        self.inner.load(collection)
+    }
+
+    fn save(&self, collection: StateCollection, _entries: &BTreeMap<String, Value>) -> Result<()> {
+        Err(Error::ReadOnly(format!("save {}", collection.name())))
+    }
+
+    fn read_blob(&self, key: &str) -> Result<Option<Vec<u8>>> {
+        self.inner.read_blob(key)
+    }
+
+    fn write_blob(&self, key: &str, _data: &[u8]) -> Result<()> {
+        Err(Error::ReadOnly(format!("write '{key}'")))
+    }
+
+    fn remove_blob(&self, key: &str) -> Result<()> {
+        Err(Error::ReadOnly(format!("remove '{key}'")))
+    }
+
+    fn location(&self, collection: StateCollection) -> PathBuf {
+        self.inner.location(collection)
+    }
+
+    fn compact(&self, collection: StateCollection) -> Result<()> {
+        Err(Error::ReadOnly(format!("compact {}", collection.name())))
+    }
+}
+
+/// Returns the upgrade path of a collection's snapshot file.
+fn snapshot_migrations(collection: StateCollection) -> &'static Migrations {
+    match collection {
+        StateCollection::Profiles => &PROFILES_MIGRATIONS,
+        StateCollection::Presets => &PRESETS_MIGRATIONS,
+    }
+}
+
+/// Returns the field of a collection's snapshot file that holds its
+/// entries.
+fn snapshot_field(collection: StateCollection) -> &'static str {
+    match collection {
+        StateCollection::Profiles => "entries",
+        StateCollection::Presets => "presets",
+    }
+}
+
+/// Serializes `entries` in the snapshot file format of `collection`.
+fn snapshot_json(collection: StateCollection, entries: &BTreeMap<String, Value>) -> Result<String> {
+    let mut snapshot = Map::new();
+    let version = snapshot_migrations(collection).current();
+    snapshot.insert("version".to_string(), Value::from(version));
+    let entries = entries
+        .iter()
+        .map(|(k, v)| (k.clone(), v.clone()))
+        .collect();
+    snapshot.insert(
+        snapshot_field(collection).to_string(),
+        Value::Object(entries),
+    );
+    serde_json::to_string_pretty(&snapshot).map_err(|e| Error::Serialization(e.to_string()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::thumbnails::THUMBNAILS_DIR_NAME;
+    use serde_json::json;
+    use tempfile::tempdir;
+
+    #[test]
+    fn test_json_store_round_trip() {
+        let temp = tempdir().unwrap();
+        let store = JsonStateStore::new(temp.path());
+        assert!(store.load(StateCollection::Profiles).unwrap().is_empty());
+
+        let mut entries = BTreeMap::new();
+        entries.insert("/a".to_string(), json!({ "profileHash": "1" }));
+        store.save(StateCollection::Profiles, &entries).unwrap();
+
+        let content = fs::read_to_string(temp.path().join("profiles.json")).unwrap();
+        let snapshot: Value = serde_json::from_str(&content).unwrap();
+        assert_eq!(snapshot["version"], 1);
+        assert_eq!(snapshot["entries"]["/a"]["profileHash"], "1");
+
+        let reopened = JsonStateStore::new(temp.path());
+        assert_eq!(reopened.load(StateCollection::Profiles).unwrap(), entries);
+    }
+
+    #[test]
+    fn test_json_store_refuses_newer_snapshot() {
+        let temp = tempdir().unwrap();
+        let path = temp.path().join("presets.json");
+        fs::write(&path, r#"{"version": 99, "presets": {}}"#).unwrap();
+
+        let store = JsonStateStore::new(temp.path());
+        assert!(matches!(
+            store.load(StateCollection::Presets),
+            Err(Error::Migration(_))
+        ));
+        assert!(store
+            .save(StateCollection::Presets, &BTreeMap::new())
+            .is_err());
+        assert!(fs::read_to_string(&path).unwrap().contains("99"));
+    }
+
+    #[test]
+    fn test_json_store_blobs() {
+        let temp = tempdir().unwrap();
+        let store = JsonStateStore::new(temp.path());
+
+        assert_eq!(store.read_blob("thumbs/a.png").unwrap(), None);
+        store.write_blob("thumbs/a.png", b"png").unwrap();
+        assert_eq!(
+            store.read_blob("thumbs/a.png").unwrap(),
+            Some(b"png".to_vec())
+        );
+        store.remove_blob("thumbs/a.png").unwrap();
+        store.remove_blob("thumbs/a.png").unwrap();
+        assert_eq!(store.read_blob("thumbs/a.png").unwrap(), None);
+    }
+
+    #[test]
+    fn test_memory_store_is_shared_between_clones() {
+        let store = MemoryStateStore::new();
+        let mut entries = BTreeMap::new();
+        entries.insert("Work".to_string(), json!({ "name": "Work" }));
+        store.save(StateCollection::Presets, &entries).unwrap();
+        store.write_blob("config.json", b"{}").unwrap();
+
+        let clone = store.clone();
+        assert_eq!(clone.load(StateCollection::Presets).unwrap(), entries);
+        assert!(clone.load(StateCollection::Profiles).unwrap().is_empty());
+        assert_eq!(
+            clone.read_blob("config.json").unwrap(),
+            Some(b"{}".to_vec())
+        );
+        assert_eq!(clone.location(StateCollection::Presets), PathBuf::new());
+    }
+
+    #[test]
+    fn test_read_only_store_rejects_writes() {
+        let inner = Arc::new(MemoryStateStore::new());
+        inner.write_blob("config.json", b"{}").unwrap();
+        let store = ReadOnlyStateStore::new(inner.clone());
+
+        assert_eq!(store.read_blob("config.json").unwrap(), Some(b"{}".to_vec()));
+        assert!(matches!(
+            store.save(StateCollection::Presets, &BTreeMap::new()),
+            Err(Error::ReadOnly(_))
+        ));
+        assert!(store.write_blob("config.json", b"[]").is_err());
+        assert!(store.remove_blob("config.json").is_err());
+        assert_eq!(inner.read_blob("config.json").unwrap(), Some(b"{}".to_vec()));
+
+        // Read-only contexts render thumbnails into memory instead
+        let thumbnail = format!("{THUMBNAILS_DIR_NAME}/Work.png");
+        assert!(store.write_blob(&thumbnail, b"png").is_err());
+        assert!(inner.read_blob(&thumbnail).unwrap().is_none());
+    }
+}
@@ -0,0 +1,329 @@
+//! Summary statistics for dashboards and reports.
+//!
+//! [`LibraryStats`] gathers everything a dashboard shows in one serializable
+//! struct, so the GUI and `folco stats` present the same numbers.
+
+use crate::cache::{CacheInfo, IconCache};
+use crate::color::FolderColor;
+use crate::library::Library;
+use crate::profile::profile_color;
+use crate::store::ProfileStore;
+
+use serde::{Deserialize, Serialize};
+
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::time::Duration;
+
+/// Number of recently customized folders included in [`LibraryStats`].
+pub const RECENT_FOLDER_COUNT: usize = 10;
+
+/// Counts of batch operation results since the context was created.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct OperationStats {
+    /// Folders customized successfully.
+    pub customize_succeeded: u64,
+    /// Folders that failed to customize.
+    pub customize_failed: u64,
+    /// Folders left untouched by the conflict policy.
+    pub customize_skipped: u64,
+    /// Folders reset successfully.
+    pub reset_succeeded: u64,
+    /// Folders that failed to reset.
+    pub reset_failed: u64,
+    /// Renders served from the cache of finished icons.
+    #[serde(default)]
+    pub artifact_cache_hits: u64,
+    /// Renders that weren't in the cache of finished icons, and so ran the
+    /// renderer.
+    #[serde(default)]
+    pub artifact_cache_misses: u64,
+    /// Renders that were timed, leaving out those served from the cache of
+    /// finished icons.
+    #[serde(default)]
+    pub renders_timed: u64,
+    /// Total time of the timed renders, in microseconds.
+    #[serde(default)]
+    pub render_micros: u64,
+    /// Folders whose icon was applied and timed.
+    #[serde(default)]
+    pub applies_timed: u64,
+    /// Total time of the timed applications, in microseconds.
+    #[serde(default)]
+    pub apply_micros: u64,
+}
+
+impl OperationStats {
+    /// Returns the fraction of attempted customizations that failed.
+    ///
+    /// Skipped folders don't count as attempts. Returns `0.0` if nothing was
+    /// attempted.
+    pub fn customize_failure_rate(&self) -> f64 {
+        failure_rate(self.customize_succeeded, self.customize_failed)
+    }
+
+    /// Returns the fraction of attempted resets that failed.
+    pub fn reset_failure_rate(&self) -> f64 {
+        failure_rate(self.reset_succeeded, self.reset_failed)
+    }
+
+    /// Returns the mean time of a render, or `None` if none was timed.
+    pub fn mean_render_time(&self) -> Option<Duration> {
+        mean(self.render_micros, self.renders_timed)
+    }
+
+    /// Returns the mean time to apply an icon to a folder, or `None` if
+    /// none was timed.
+    pub fn mean_apply_time(&self) -> Option<Duration> {
+        mean(self.apply_micros, self.applies_timed)
+    }
+
+    pub(crate) fn record_customize(&mut self, succeeded: usize, failed: usize, skipped: usize) {
+        self.customize_succeeded += succeeded as u64;
+        self.customize_failed += failed as u64;
+        self.customize_skipped += skipped as u64;
+    }
+
+    pub(crate) fn record_reset(&mut self, succeeded: usize, failed: usize) {
+        self.reset_succeeded += succeeded as u64;
+        self.reset_failed += failed as u64;
+    }
+
+    pub(crate) fn record_artifact_lookup(&mut self, hit: bool) {
+        if hit {
+            self.artifact_cache_hits += 1;
+        } else {
+            self.artifact_cache_misses += 1;
+        }
+    }
+
+    pub(crate) fn record_render(&mut self, duration: Duration) {
+        self.renders_timed += 1;
+        self.render_micros = self.render_micros.saturating_add(micros(duration));
+    }
+
+    pub(crate) fn record_apply(&mut self, duration: Duration) {
+        self.applies_timed += 1;
+        self.apply_micros = self.apply_micros.saturating_add(micros(duration));
+    }
+}
+
+fn micros(duration: Duration) -> u64 {
+    u64::try_from(duration.as_micros()).unwrap_or(u64::MAX)
+}
+
+fn mean(total_micros: u64, count: u64) -> Option<Duration> {
+    (count > 0).then(|| Duration::from_micros(total_micros / count))
+}
+
+fn failure_rate(succeeded: u64, failed: u64) -> f64 {
+    let attempted = succeeded + failed;
+    if attempted == 0 {
+        0.0
+    } else {
+        failed as f64 / attempted as f64
+    }
+}
+
+/// Number of folders using a color.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ColorCount {
+    /// The color preset, or `None` for custom and uncolored profiles.
+    pub color: Option<FolderColor>,
+    /// Number of folders.
+    pub count: usize,
+}
+
+/// Number of folders customized from a preset.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PresetCount {
+    /// The preset name, or `None` for folders not customized from a preset.
+    pub preset: Option<String>,
+    /// Number of folders.
+    pub count: usize,
+}
+
+/// Number of customized folders below a library root.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RootCount {
+    /// Name of the library root.
+    pub name: String,
+    /// Number of folders.
+    pub count: usize,
+}
+
+/// A recently customized folder.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RecentFolder {
+    /// The customized folder.
+    pub path: PathBuf,
+    /// When the profile was applied, in seconds since the Unix epoch.
+    pub applied_at: u64,
+    /// The color preset its profile uses, if any.
+    pub color: Option<FolderColor>,
+    /// The preset it was customized from, if any.
+    pub preset: Option<String>,
+}
+
+/// Dashboard summary of the customized folders and recent activity.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct LibraryStats {
+    /// Number of folders in the profile store.
+    pub total_customized: usize,
+    /// Folder counts per color, most used first.
+    pub by_color: Vec<ColorCount>,
+    /// Folder counts per preset, most used first.
+    pub by_preset: Vec<PresetCount>,
+    /// Folder counts per library root, in library order.
+    pub by_root: Vec<RootCount>,
+    /// Customized folders outside every library root.
+    pub outside_library: usize,
+    /// The most recently customized folders, newest first.
+    pub recent: Vec<RecentFolder>,
+    /// Batch operation counts since the context was created.
+    pub operations: OperationStats,
+    /// See [`OperationStats::customize_failure_rate`].
+    pub customize_failure_rate: f64,
+    /// See [`OperationStats::reset_failure_rate`].
+    pub reset_failure_rate: f64,
+    /// The icon cache.
+    pub cache: CacheInfo,
+}
+
+impl LibraryStats {
+    pub(crate) fn collect(
+        store: &ProfileStore,
+        library: &Library,
+        operations: OperationStats,
+        cache: &IconCache,
+    ) -> Self {
+        let entries = store.entries();
+
+        let mut colors: HashMap<Option<FolderColor>, usize> = HashMap::new();
+        let mut presets: HashMap<Option<String>, usize> = HashMap::new();
+        let mut roots: Vec<RootCount> = library
+            .roots()
+            .iter()
+            .map(|root| RootCount {
+                name: root.name.clone(),
+                count: 0,
+            })
+            .collect();
+        let mut outside_library = 0;
+        let mut recent = Vec::with_capacity(entries.len());
+
+        for (path, stored) in &entries {
+            let color = profile_color(&stored.profile);
+            *colors.entry(color).or_default() += 1;
+            *presets.entry(stored.preset.clone()).or_default() += 1;
+
+            let root_index = library
+                .roots()
+                .iter()
+                .position(|root| path.starts_with(&root.path));
+            match root_index {
+                Some(index) => roots[index].count += 1,
+                None => outside_library += 1,
+            }
+
+            recent.push(RecentFolder {
+                path: path.clone(),
+                applied_at: stored.applied_at,
+                color,
+                preset: stored.preset.clone(),
+            });
+        }
+
+        recent.sort_by(|a, b| b.applied_at.cmp(&a.applied_at));
+        recent.truncate(RECENT_FOLDER_COUNT);
+
+        let mut by_color: Vec<_> = colors
+            .into_iter()
+            .map(|(color, count)| ColorCount { color, count })
+            .collect();
+        by_color.sort_by(|a, b| {
+            b.count
+                .cmp(&a.count)
+                .then_with(|| color_order(a.color).cmp(&color_order(b.color)))
+        });
+
+        let mut by_preset: Vec<_> = presets
+            .into_iter()
+            .map(|(preset, count)| PresetCount { preset, count })
+            .collect();
+        by_preset.sort_by(|a, b| b.count.cmp(&a.count).then_with(|| a.preset.cmp(&b.preset)));
+
+        Self {
+            total_customized: entries.len(),
+            by_color,
+            by_preset,
+            by_root: roots,
+            outside_library,
+            recent,
+            operations,
+            customize_failure_rate: operations.customize_failure_rate(),
+            reset_failure_rate: operations.reset_failure_rate(),
+            cache: cache.info(),
+        }
+    }
+}
+
+/// Sorts colors in picker order, with custom colors last.
+fn color_order(color: Option<FolderColor>) -> usize {
+    color
+        .and_then(|c| FolderColor::all().iter().position(|&other| other == c))
+        .unwrap_or(usize::MAX)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cache::CacheConfig;
+    use folco_renderer::CustomizationProfile;
+    use std::fs;
+    use tempfile::tempdir;
+
+    #[test]
+    fn test_failure_rates() {
+        let mut stats = OperationStats::default();
+        assert_eq!(stats.customize_failure_rate(), 0.0);
+
+        stats.record_customize(3, 1, 2);
+        stats.record_reset(1, 1);
+        assert_eq!(stats.customize_failure_rate(), 0.25);
+        assert_eq!(stats.reset_failure_rate(), 0.5);
+        assert_eq!(stats.customize_skipped, 2);
+    }
+
+    #[test]
+    fn test_collect_counts() {
+        let temp = tempdir().unwrap();
+        let projects = temp.path().join("Projects");
+        fs::create_dir(&projects).unwrap();
+
+        let mut library = Library::new();
+        library.add_root("Projects", &projects).unwrap();
+
+        let store = ProfileStore::in_data_dir(temp.path()).unwrap();
+        store.insert(&projects.join("a"), &CustomizationProfile::default());
+        store.insert(&projects.join("b"), &CustomizationProfile::default());
+        store.insert(&temp.path().join("elsewhere"), &CustomizationProfile::default());
+        store.set_preset(&projects.join("a"), Some("Work".to_string()));
+
+        let cache = IconCache::new(CacheConfig::new(temp.path().join("cache")));
+        let stats = LibraryStats::collect(&store, &library, OperationStats::default(), &cache);
+
+        assert_eq!(stats.total_customized, 3);
+        assert_eq!(stats.by_root[0].count, 2);
+        assert_eq!(stats.outside_library, 1);
+        assert_eq!(stats.by_preset[0], PresetCount { preset: None, count: 2 });
+        assert_eq!(stats.recent.len(), 3);
+        assert_eq!(stats.by_color.iter().map(|c| c.count).sum::<usize>(), 3);
+    }
+}
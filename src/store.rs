@@ -0,0 +1,747 @@
+//! Persistent record of the folders folco has customized.
+//!
+//! Every successful customization records the applied profile in the
+//! [`ProfileStore`], keyed by the folder's normalized path. This is what lets
+//! folco tell its own customizations apart from icons set by hand or by
+//! other tools, and what later reset and inspection features build on.
+//! The records are persisted through a [`StateStore`]; with the default
+//! JSON backend, saves go through a write-ahead journal, so a crash
+//! mid-save loses at most the changes being saved.
+
+use crate::color::FolderColor;
+use crate::error::Result;
+use crate::file_id::FileId;
+use crate::paths::{comparison_key, normalize_folder_path};
+use crate::profile::{profile_hash, profile_hsl};
+use crate::state::{load_entries, save_entries, JsonStateStore, StateCollection, StateStore};
+
+use folco_renderer::CustomizationProfile;
+use serde::{Deserialize, Serialize};
+
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::fs;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex, MutexGuard};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// File name of the profile store inside the app data directory.
+pub(crate) const STORE_FILE_NAME: &str = "profiles.json";
+
+/// Most earlier profiles kept in a folder's
+/// [history](StoredProfile::history).
+pub const FOLDER_HISTORY_LIMIT: usize = 20;
+
+/// A profile once applied to a folder.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct HistoryEntry {
+    /// The folder the profile was applied to.
+    ///
+    /// It isn't stored, but filled in from the record's path when the
+    /// history is read, so it's right even after the folder was moved.
+    #[serde(skip)]
+    pub folder: PathBuf,
+    /// The applied profile.
+    pub profile: CustomizationProfile,
+    /// Hash of the applied profile.
+    pub profile_hash: String,
+    /// When the profile was applied, in seconds since the Unix epoch.
+    pub applied_at: u64,
+    /// Name of the preset the profile came from, if any.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub preset: Option<String>,
+}
+
+/// The profile folco applied to a folder.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct StoredProfile {
+    /// The applied profile.
+    pub profile: CustomizationProfile,
+    /// Hash of the applied profile, see [`profile_hash`](crate::profile_hash).
+    pub profile_hash: String,
+    /// When the profile was applied, in seconds since the Unix epoch.
+    pub applied_at: u64,
+    /// Filesystem identity of the folder when the profile was applied, used
+    /// to find the folder again if it's moved.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub file_id: Option<FileId>,
+    /// Name of the preset the profile came from, if any.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub preset: Option<String>,
+    /// Whether the profile was colored with
+    /// [`FolderColor::SystemAccent`], so the folder follows accent changes.
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub system_accent: bool,
+    /// Free-text notes about the folder, such as why it's colored this way.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub notes: Option<String>,
+    /// Arbitrary key-value metadata, such as a ticket number or client name.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub metadata: BTreeMap<String, String>,
+    /// Profiles applied to the folder before this one, most recent first,
+    /// up to [`FOLDER_HISTORY_LIMIT`] of them.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub history: Vec<HistoryEntry>,
+}
+
+impl StoredProfile {
+    /// Creates a record for `profile` applied now.
+    pub fn new(profile: CustomizationProfile) -> Self {
+        let system_accent = profile_hsl(&profile).is_some_and(|settings| {
+            FolderColor::from_hsl_mutation_settings(&settings) == Some(FolderColor::SystemAccent)
+        });
+        Self {
+            profile_hash: profile_hash(&profile),
+            profile,
+            applied_at: now_unix_secs(),
+            file_id: None,
+            preset: None,
+            system_accent,
+            notes: None,
+            metadata: BTreeMap::new(),
+            history: Vec::new(),
+        }
+    }
+
+    /// Returns this record as an entry of the history of `folder`.
+    fn to_history_entry(&self, folder: &Path) -> HistoryEntry {
+        HistoryEntry {
+            folder: folder.to_path_buf(),
+            profile: self.profile.clone(),
+            profile_hash: self.profile_hash.clone(),
+            applied_at: self.applied_at,
+            preset: self.preset.clone(),
+        }
+    }
+}
+
+/// Persistent mapping from customized folders to their applied profiles.
+///
+/// `ProfileStore` is a cheap handle: clones share the same underlying state,
+/// so a store can be handed to background tasks while the context keeps
+/// using it. Changes are made in memory and written with [`save`](Self::save).
+#[derive(Debug, Clone)]
+pub struct ProfileStore {
+    path: PathBuf,
+    entries: Arc<Mutex<Entries>>,
+    state: Arc<dyn StateStore>,
+}
+
+impl ProfileStore {
+    /// Opens the JSON store at `path`, loading existing entries if the file
+    /// exists and replaying its journal.
+    pub fn open(path: impl Into<PathBuf>) -> Result<Self> {
+        let path = path.into();
+        let dir = path.parent().map(Path::to_path_buf).unwrap_or_default();
+        let state = JsonStateStore::new(dir).with_file(StateCollection::Profiles, path);
+        Self::with_state_store(Arc::new(state))
+    }
+
+    /// Opens the JSON store in the given app data directory.
+    pub fn in_data_dir(data_dir: &Path) -> Result<Self> {
+        Self::open(data_dir.join(STORE_FILE_NAME))
+    }
+
+    /// Opens the store kept by `state`, loading the existing entries.
+    ///
+    /// Records that can't be parsed, e.g. ones written by a newer version,
+    /// fail the whole load rather than being dropped on the next save.
+    pub fn with_state_store(state: Arc<dyn StateStore>) -> Result<Self> {
+        let entries = load_entries(state.as_ref(), StateCollection::Profiles)?;
+        Ok(Self {
+            path: state.location(StateCollection::Profiles),
+            entries: Arc::new(Mutex::new(Entries::new(entries))),
+            state,
+        })
+    }
+
+    /// Returns where the store is kept, e.g. the path of the store file.
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Returns the profile recorded for `folder`, if any.
+    pub fn get(&self, folder: &Path) -> Option<StoredProfile> {
+        self.lock().get(&store_key(folder)).cloned()
+    }
+
+    /// Returns the profiles applied to `folder`, most recent first, starting
+    /// with the current one. Empty if no profile is recorded for it.
+    pub fn history(&self, folder: &Path) -> Vec<HistoryEntry> {
+        let key = store_key(folder);
+        let Some(stored) = self.lock().get(&key).cloned() else {
+            return Vec::new();
+        };
+        let path = PathBuf::from(key);
+        let mut history = vec![stored.to_history_entry(&path)];
+        history.extend(stored.history.into_iter().map(|entry| HistoryEntry {
+            folder: path.clone(),
+            ..entry
+        }));
+        history
+    }
+
+    /// Returns the record for `folder`, with the path it's recorded under.
+    ///
+    /// A folder without a record of its own is matched by its [`FileId`]
+    /// against records whose path no longer exists, so a folder moved while
+    /// nothing was watching is recognized before
+    /// [`reconcile_store`](crate::reconcile_store) remaps it. Since file
+    /// systems reuse the IDs of deleted folders, such a match is only a
+    /// guess, and nothing is changed in the store because of it.
+    pub fn resolve(&self, folder: &Path) -> Option<(PathBuf, StoredProfile)> {
+        let key = store_key(folder);
+        let entries = self.lock();
+        if let Some(stored) = entries.get(&key) {
+            return Some((PathBuf::from(key), stored.clone()));
+        }
+        let moved_from = moved_key(&entries, FileId::of(folder)?, &key)?;
+        let stored = entries.get(&moved_from)?.clone();
+        Some((PathBuf::from(moved_from), stored))
+    }
+
+    /// Returns `true` if a profile is recorded for `folder`.
+    pub fn contains(&self, folder: &Path) -> bool {
+        self.lock().contains_key(&store_key(folder))
+    }
+
+    /// Records `profile` as applied to `folder` now.
+    ///
+    /// The notes and metadata of any previous record for the folder are
+    /// kept, and its profile is added to the folder's history unless it's
+    /// the same one. On Windows and macOS, a record for the same folder under
+    /// a spelling differing only in case is replaced too.
+    ///
+    /// Records of folders that were moved aren't looked up by [`FileId`]
+    /// here, since file systems reuse IDs of deleted folders; a moved folder
+    /// keeps its record through [`remap`](Self::remap), e.g. from
+    /// [`reconcile_store`](crate::reconcile_store).
+    pub fn insert(&self, folder: &Path, profile: &CustomizationProfile) -> StoredProfile {
+        let mut stored = StoredProfile {
+            file_id: FileId::of(folder),
+            ..StoredProfile::new(profile.clone())
+        };
+        let key = store_key(folder);
+        let mut entries = self.lock();
+        let variants = entries.case_variants(&key);
+        let previous = entries
+            .get(&key)
+            .or_else(|| entries.get(variants.first()?));
+        if let Some(previous) = previous {
+            stored.notes = previous.notes.clone();
+            stored.metadata = previous.metadata.clone();
+            if previous.profile_hash != stored.profile_hash {
+                stored.history.push(previous.to_history_entry(folder));
+            }
+            stored.history.extend(previous.history.iter().cloned());
+            stored.history.truncate(FOLDER_HISTORY_LIMIT);
+        }
+        for variant in &variants {
+            entries.remove(variant);
+        }
+        entries.insert(key, stored.clone());
+        stored
+    }
+
+    /// Records which preset the profile applied to `folder` came from.
+    ///
+    /// Returns `false` if there is no record for `folder`.
+    pub fn set_preset(&self, folder: &Path, preset: Option<String>) -> bool {
+        match self.lock().get_mut(&store_key(folder)) {
+            Some(stored) => {
+                stored.preset = preset;
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Sets the notes on the record for `folder`, or clears them with `None`.
+    ///
+    /// Returns `false` if there is no record for `folder`.
+    pub fn set_notes(&self, folder: &Path, notes: Option<String>) -> bool {
+        match self.lock().get_mut(&store_key(folder)) {
+            Some(stored) => {
+                stored.notes = notes;
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Sets a metadata value on the record for `folder`, or removes the key
+    /// with `None`.
+    ///
+    /// Returns `false` if there is no record for `folder`.
+    pub fn set_metadata(&self, folder: &Path, key: &str, value: Option<String>) -> bool {
+        match self.lock().get_mut(&store_key(folder)) {
+            Some(stored) => {
+                match value {
+                    Some(value) => stored.metadata.insert(key.to_string(), value),
+                    None => stored.metadata.remove(key),
+                };
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Removes the record for `folder`, returning it if it existed.
+    pub fn remove(&self, folder: &Path) -> Option<StoredProfile> {
+        self.lock().remove(&store_key(folder))
+    }
+
+    /// Moves the record for `from` to `to`, e.g. after the folder was renamed.
+    ///
+    /// Any existing record for `to` is replaced. Returns `false` if there was
+    /// no record for `from`.
+    pub fn remap(&self, from: &Path, to: &Path) -> bool {
+        let mut entries = self.lock();
+        match entries.remove(&store_key(from)) {
+            Some(stored) => {
+                entries.insert(store_key(to), stored);
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Moves the records for `from` and every folder below it to `to`.
+    ///
+    /// Used when a folder is renamed or moved, which also moves any
+    /// customized subfolders. Returns the `(old, new)` path of each moved
+    /// record.
+    pub fn remap_tree(&self, from: &Path, to: &Path) -> Vec<(PathBuf, PathBuf)> {
+        let from = normalize_folder_path(from);
+        let to = normalize_folder_path(to);
+        let mut entries = self.lock();
+
+        let moved_keys: Vec<String> = entries
+            .keys()
+            .filter(|key| Path::new(key.as_str()).starts_with(&from))
+            .cloned()
+            .collect();
+
+        let mut moved = Vec::with_capacity(moved_keys.len());
+        for key in moved_keys {
+            let old_path = PathBuf::from(&key);
+            let Ok(relative) = old_path.strip_prefix(&from) else {
+                continue;
+            };
+            let new_path = if relative.as_os_str().is_empty() {
+                to.clone()
+            } else {
+                to.join(relative)
+            };
+            if let Some(stored) = entries.remove(&key) {
+                entries.insert(store_key(&new_path), stored);
+                moved.push((old_path, new_path));
+            }
+        }
+        moved
+    }
+
+    /// Returns the recorded path of the folder with the given identity.
+    pub fn find_by_file_id(&self, id: FileId) -> Option<PathBuf> {
+        self.lock()
+            .iter()
+            .find(|(_, stored)| stored.file_id == Some(id))
+            .map(|(path, _)| PathBuf::from(path))
+    }
+
+    /// Returns all recorded folders and their profiles, sorted by path.
+    pub fn entries(&self) -> Vec<(PathBuf, StoredProfile)> {
+        self.lock()
+            .iter()
+            .map(|(path, stored)| (PathBuf::from(path), stored.clone()))
+            .collect()
+    }
+
+    /// Returns the number of recorded folders.
+    pub fn len(&self) -> usize {
+        self.lock().len()
+    }
+
+    /// Returns `true` if no folders are recorded.
+    pub fn is_empty(&self) -> bool {
+        self.lock().is_empty()
+    }
+
+    /// Writes the changes since the last save to disk.
+    ///
+    /// With the JSON backend, changed records are appended to the store's
+    /// journal and flushed before this returns; every so often the journal
+    /// is compacted into a fresh store file, which is written to a temporary
+    /// path and renamed over the previous version. Either way, an
+    /// interrupted save leaves the previously saved records intact.
+    pub fn save(&self) -> Result<()> {
+        save_entries(self.state.as_ref(), StateCollection::Profiles, &self.lock())
+    }
+
+    /// Saves the store and compacts its storage. With the JSON backend, this
+    /// rewrites the store file from the current records and clears the
+    /// journal.
+    ///
+    /// Saving compacts automatically; this is for callers that want the
+    /// store file itself up to date, such as before a backup.
+    pub fn compact(&self) -> Result<()> {
+        self.save()?;
+        self.state.compact(StateCollection::Profiles)
+    }
+
+    fn lock(&self) -> MutexGuard<'_, Entries> {
+        self.entries.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+/// The records of a store by key, with an index of their keys by
+/// [`comparison_key`] on Windows and macOS, so spellings of a folder that
+/// differ only in case are found without scanning every record.
+///
+/// Reads go through the records; changes go through
+/// [`insert`](Self::insert) and [`remove`](Self::remove), which keep the
+/// index up to date.
+#[derive(Debug, Default)]
+struct Entries {
+    records: BTreeMap<String, StoredProfile>,
+    folded: HashMap<String, BTreeSet<String>>,
+}
+
+impl Entries {
+    fn new(records: BTreeMap<String, StoredProfile>) -> Self {
+        let mut entries = Self::default();
+        for (key, stored) in records {
+            entries.insert(key, stored);
+        }
+        entries
+    }
+
+    fn get_mut(&mut self, key: &str) -> Option<&mut StoredProfile> {
+        self.records.get_mut(key)
+    }
+
+    fn insert(&mut self, key: String, stored: StoredProfile) -> Option<StoredProfile> {
+        if let Some(folded) = fold(&key) {
+            self.folded.entry(folded).or_default().insert(key.clone());
+        }
+        self.records.insert(key, stored)
+    }
+
+    fn remove(&mut self, key: &str) -> Option<StoredProfile> {
+        let removed = self.records.remove(key)?;
+        if let Some(folded) = fold(key)
+            && let Some(keys) = self.folded.get_mut(&folded)
+        {
+            keys.remove(key);
+            if keys.is_empty() {
+                self.folded.remove(&folded);
+            }
+        }
+        Some(removed)
+    }
+
+    /// Returns the other keys that spell `key` differently only in case, on
+    /// platforms whose file systems ignore case.
+    fn case_variants(&self, key: &str) -> Vec<String> {
+        let Some(keys) = fold(key).and_then(|folded| self.folded.get(&folded)) else {
+            return Vec::new();
+        };
+        keys.iter().filter(|existing| *existing != key).cloned().collect()
+    }
+}
+
+impl std::ops::Deref for Entries {
+    type Target = BTreeMap<String, StoredProfile>;
+
+    fn deref(&self) -> &Self::Target {
+        &self.records
+    }
+}
+
+/// Returns the key `key` is indexed under for case-insensitive lookups, on
+/// platforms whose file systems ignore case.
+fn fold(key: &str) -> Option<String> {
+    cfg!(any(target_os = "windows", target_os = "macos")).then(|| comparison_key(Path::new(key)))
+}
+
+/// Returns the key of the record with identity `id` whose folder no longer
+/// exists, other than `key`: the folder at `key` before it was moved.
+fn moved_key(entries: &BTreeMap<String, StoredProfile>, id: FileId, key: &str) -> Option<String> {
+    entries
+        .iter()
+        .find(|(existing, stored)| {
+            stored.file_id == Some(id) && *existing != key && !Path::new(existing).exists()
+        })
+        .map(|(existing, _)| existing.clone())
+}
+
+/// Returns the key under which `folder` is stored.
+fn store_key(folder: &Path) -> String {
+    normalize_folder_path(folder).to_string_lossy().into_owned()
+}
+
+/// Writes `contents` to a temporary file next to `path`, flushes it to
+/// disk, then renames it into place, creating the parent directory if
+/// needed.
+pub(crate) fn write_atomic(path: &Path, contents: impl AsRef<[u8]>) -> Result<()> {
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    let mut tmp_name = path.as_os_str().to_owned();
+    tmp_name.push(".tmp");
+    let tmp_path = PathBuf::from(tmp_name);
+    let mut file = fs::File::create(&tmp_path)?;
+    file.write_all(contents.as_ref())?;
+    file.sync_all()?;
+    fs::rename(&tmp_path, path)?;
+    Ok(())
+}
+
+/// Returns the current time in seconds since the Unix epoch.
+pub(crate) fn now_unix_secs() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::profile::profile_with_color;
+    use crate::state::MemoryStateStore;
+    use tempfile::tempdir;
+
+    #[test]
+    fn test_open_missing_store_is_empty() {
+        let temp = tempdir().unwrap();
+        let store = ProfileStore::in_data_dir(temp.path()).unwrap();
+        assert!(store.is_empty());
+        assert_eq!(store.path(), temp.path().join(STORE_FILE_NAME));
+    }
+
+    #[test]
+    fn test_insert_get_remove() {
+        let temp = tempdir().unwrap();
+        let store = ProfileStore::in_data_dir(temp.path()).unwrap();
+        let folder = temp.path().join("folder");
+
+        let profile = CustomizationProfile::default();
+        let stored = store.insert(&folder, &profile);
+        assert_eq!(stored.profile_hash, profile_hash(&profile));
+
+        // Lookups are normalized
+        assert!(store.contains(&folder.join("..").join("folder")));
+        assert_eq!(store.len(), 1);
+
+        assert!(store.remove(&folder).is_some());
+        assert!(store.get(&folder).is_none());
+    }
+
+    #[test]
+    fn test_system_accent_profiles_are_marked() {
+        let default = CustomizationProfile::default();
+        assert!(!StoredProfile::new(default.clone()).system_accent);
+        let blue = profile_with_color(&default, FolderColor::Blue).unwrap();
+        assert!(!StoredProfile::new(blue).system_accent);
+        let accent = profile_with_color(&default, FolderColor::SystemAccent).unwrap();
+        assert!(StoredProfile::new(accent).system_accent);
+    }
+
+    #[test]
+    fn test_notes_and_metadata_survive_reapply() {
+        let temp = tempdir().unwrap();
+        let store = ProfileStore::in_data_dir(temp.path()).unwrap();
+        let folder = temp.path().join("folder");
+        assert!(!store.set_notes(&folder, Some("no record".to_string())));
+
+        store.insert(&folder, &CustomizationProfile::default());
+        assert!(store.set_notes(&folder, Some("Q3 audit".to_string())));
+        assert!(store.set_metadata(&folder, "ticket", Some("OPS-42".to_string())));
+        assert!(store.set_metadata(&folder, "client", Some("Acme".to_string())));
+        assert!(store.set_metadata(&folder, "client", None));
+
+        let blue = profile_with_color(&CustomizationProfile::default(), FolderColor::Blue).unwrap();
+        store.insert(&folder, &blue);
+        store.save().unwrap();
+
+        let stored = ProfileStore::in_data_dir(temp.path())
+            .unwrap()
+            .get(&folder)
+            .unwrap();
+        assert_eq!(stored.profile_hash, profile_hash(&blue));
+        assert_eq!(stored.notes.as_deref(), Some("Q3 audit"));
+        assert_eq!(stored.metadata.len(), 1);
+        assert_eq!(stored.metadata["ticket"], "OPS-42");
+    }
+
+    #[test]
+    fn test_history_is_bounded_and_skips_repeats() {
+        let temp = tempdir().unwrap();
+        let store = ProfileStore::in_data_dir(temp.path()).unwrap();
+        let folder = temp.path().join("folder");
+        assert!(store.history(&folder).is_empty());
+
+        let default = CustomizationProfile::default();
+        let colors = [FolderColor::Red, FolderColor::Green, FolderColor::Blue];
+        for color in colors {
+            store.insert(&folder, &profile_with_color(&default, color).unwrap());
+        }
+        store.insert(&folder, &profile_with_color(&default, FolderColor::Blue).unwrap());
+
+        let history = store.history(&folder);
+        let hashes: Vec<_> = history.iter().map(|entry| entry.profile_hash.clone()).collect();
+        let expected: Vec<_> = colors
+            .iter()
+            .rev()
+            .map(|&color| profile_hash(&profile_with_color(&default, color).unwrap()))
+            .collect();
+        assert_eq!(hashes, expected);
+        assert!(history.iter().all(|entry| entry.folder == normalize_folder_path(&folder)));
+
+        for _ in 0..FOLDER_HISTORY_LIMIT {
+            store.insert(&folder, &default);
+            store.insert(&folder, &profile_with_color(&default, FolderColor::Red).unwrap());
+        }
+        assert_eq!(store.history(&folder).len(), FOLDER_HISTORY_LIMIT + 1);
+    }
+
+    #[test]
+    fn test_moved_folder_is_recognized_by_identity() {
+        let temp = tempdir().unwrap();
+        let store = ProfileStore::in_data_dir(temp.path()).unwrap();
+        let before = temp.path().join("before");
+        let after = temp.path().join("after");
+        fs::create_dir(&before).unwrap();
+        store.insert(&before, &CustomizationProfile::default());
+        store.set_notes(&before, Some("keep me".to_string()));
+        if store.get(&before).unwrap().file_id.is_none() {
+            return;
+        }
+
+        fs::rename(&before, &after).unwrap();
+        let (recorded, _) = store.resolve(&after).unwrap();
+        assert_eq!(recorded, normalize_folder_path(&before));
+
+        let blue = profile_with_color(&CustomizationProfile::default(), FolderColor::Blue).unwrap();
+        let stored = store.insert(&after, &blue);
+        assert_eq!(stored.notes, None);
+        assert!(stored.history.is_empty());
+        assert_eq!(store.len(), 2);
+        assert_eq!(store.resolve(&after).unwrap().0, normalize_folder_path(&after));
+        assert!(store.contains(&before));
+    }
+
+    #[test]
+    fn test_save_and_reopen() {
+        let temp = tempdir().unwrap();
+        let folder = temp.path().join("folder");
+        {
+            let store = ProfileStore::in_data_dir(temp.path()).unwrap();
+            store.insert(&folder, &CustomizationProfile::default());
+            store.save().unwrap();
+        }
+
+        let reopened = ProfileStore::in_data_dir(temp.path()).unwrap();
+        assert!(reopened.contains(&folder));
+        assert_eq!(reopened.entries()[0].0, normalize_folder_path(&folder));
+    }
+
+    #[test]
+    fn test_removal_survives_reopen_through_journal() {
+        let temp = tempdir().unwrap();
+        let kept = temp.path().join("kept");
+        let removed = temp.path().join("removed");
+        {
+            let store = ProfileStore::in_data_dir(temp.path()).unwrap();
+            store.insert(&kept, &CustomizationProfile::default());
+            store.insert(&removed, &CustomizationProfile::default());
+            store.save().unwrap();
+            store.remove(&removed);
+            store.save().unwrap();
+        }
+
+        let reopened = ProfileStore::in_data_dir(temp.path()).unwrap();
+        assert!(reopened.contains(&kept));
+        assert!(!reopened.contains(&removed));
+
+        reopened.compact().unwrap();
+        let content = fs::read_to_string(reopened.path()).unwrap();
+        assert!(!content.contains("removed"));
+    }
+
+    #[test]
+    fn test_memory_backend_touches_no_files() {
+        let temp = tempdir().unwrap();
+        let state = Arc::new(MemoryStateStore::new());
+        let folder = temp.path().join("folder");
+        {
+            let store = ProfileStore::with_state_store(state.clone()).unwrap();
+            store.insert(&folder, &CustomizationProfile::default());
+            store.save().unwrap();
+        }
+
+        let reopened = ProfileStore::with_state_store(state).unwrap();
+        assert!(reopened.contains(&folder));
+        assert_eq!(reopened.path(), Path::new(""));
+        assert_eq!(fs::read_dir(temp.path()).unwrap().count(), 0);
+    }
+
+    #[test]
+    fn test_remap() {
+        let temp = tempdir().unwrap();
+        let store = ProfileStore::in_data_dir(temp.path()).unwrap();
+        let from = temp.path().join("old");
+        let to = temp.path().join("new");
+
+        store.insert(&from, &CustomizationProfile::default());
+        assert!(store.remap(&from, &to));
+        assert!(!store.contains(&from));
+        assert!(store.contains(&to));
+        assert!(!store.remap(&from, &to));
+    }
+
+    #[test]
+    fn test_remap_tree_moves_descendants() {
+        let temp = tempdir().unwrap();
+        let store = ProfileStore::in_data_dir(temp.path()).unwrap();
+        let from = temp.path().join("projects");
+        let to = temp.path().join("archive").join("projects");
+        let unrelated = temp.path().join("projects-old");
+
+        store.insert(&from, &CustomizationProfile::default());
+        store.insert(&from.join("client"), &CustomizationProfile::default());
+        store.insert(&unrelated, &CustomizationProfile::default());
+
+        let moved = store.remap_tree(&from, &to);
+        assert_eq!(moved.len(), 2);
+        assert!(store.contains(&to));
+        assert!(store.contains(&to.join("client")));
+        assert!(store.contains(&unrelated));
+        assert!(!store.contains(&from));
+    }
+
+    #[cfg(any(target_os = "windows", target_os = "macos"))]
+    #[test]
+    fn test_insert_replaces_case_variant() {
+        let temp = tempdir().unwrap();
+        let store = ProfileStore::in_data_dir(temp.path()).unwrap();
+
+        store.insert(&temp.path().join("Projects"), &CustomizationProfile::default());
+        store.insert(&temp.path().join("projects"), &CustomizationProfile::default());
+        assert_eq!(store.len(), 1);
+        assert_eq!(store.entries()[0].0, temp.path().join("projects"));
+    }
+
+    #[test]
+    fn test_clones_share_state() {
+        let temp = tempdir().unwrap();
+        let store = ProfileStore::in_data_dir(temp.path()).unwrap();
+        let handle = store.clone();
+
+        handle.insert(&temp.path().join("a"), &CustomizationProfile::default());
+        assert_eq!(store.len(), 1);
+    }
+}
@@ -0,0 +1,220 @@
+//! Support bundles for bug reports.
+//!
+//! [`create_support_bundle`](crate::CustomizationContext::create_support_bundle)
+//! writes everything a maintainer asks for first into one zip file that a
+//! GUI user can attach to an issue. Paths below the user's home directory
+//! are written starting with `~`, and wherever else the user name or the
+//! machine name makes up a whole path component, such as in
+//! `/media/alice/USB` or `\\DESKTOP-1\share`, it's replaced with `<user>`
+//! or `<machine>`. Names spelled differently, such as in another case, and
+//! names inside other text aren't caught.
+
+use crate::case_audit::CaseAuditReport;
+use crate::error::{Error, Result};
+use crate::store::{write_atomic, ProfileStore};
+
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use zip::write::SimpleFileOptions;
+use zip::{CompressionMethod, ZipWriter};
+
+use std::env;
+use std::fs;
+use std::io::{Cursor, Write};
+use std::path::{Path, PathBuf};
+
+/// Number of recent log entries in a support bundle.
+pub const SUPPORT_LOG_ENTRIES: usize = 500;
+
+/// Read-only health checks of the profile store, for a support bundle.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SupportChecks {
+    /// Number of folders in the profile store.
+    pub stored_folders: usize,
+    /// Recorded folders that no longer exist.
+    pub missing_folders: Vec<PathBuf>,
+    /// Store entries whose spelling differs from the folder on disk, found
+    /// without fixing them.
+    pub case_audit: CaseAuditReport,
+}
+
+impl SupportChecks {
+    pub(crate) fn run(store: &ProfileStore, case_audit: CaseAuditReport) -> Self {
+        let entries = store.entries();
+        Self {
+            stored_folders: entries.len(),
+            missing_folders: entries
+                .into_iter()
+                .map(|(path, _)| path)
+                .filter(|path| !path.exists())
+                .collect(),
+            case_audit,
+        }
+    }
+
+    /// Returns `true` if no check found a problem.
+    pub fn is_clean(&self) -> bool {
+        self.missing_folders.is_empty() && self.case_audit.is_clean()
+    }
+}
+
+/// Writes a zip file at `path` with one JSON file per entry of `files`,
+/// anonymized as described in the [module docs](self).
+pub(crate) fn write_support_bundle(path: &Path, files: &[(&str, Value)]) -> Result<()> {
+    let identity = Identity::current();
+    let mut zip = ZipWriter::new(Cursor::new(Vec::new()));
+    let options = SimpleFileOptions::default().compression_method(CompressionMethod::Deflated);
+    for (name, value) in files {
+        let json =
+            serde_json::to_string_pretty(value).map_err(|e| Error::Serialization(e.to_string()))?;
+        zip.start_file(*name, options).map_err(zip_error)?;
+        zip.write_all(anonymize(&json, &identity).as_bytes())?;
+    }
+    let data = zip.finish().map_err(zip_error)?.into_inner();
+    write_atomic(path, data)
+}
+
+pub(crate) fn to_value<V: Serialize>(value: &V) -> Result<Value> {
+    serde_json::to_value(value).map_err(|e| Error::Serialization(e.to_string()))
+}
+
+fn zip_error(e: zip::result::ZipError) -> Error {
+    Error::Io(std::io::Error::other(e))
+}
+
+/// What identifies the user in the paths of a support bundle.
+#[derive(Debug, Default)]
+struct Identity {
+    home: Option<PathBuf>,
+    user: Option<String>,
+    machine: Option<String>,
+}
+
+impl Identity {
+    /// Returns the current user's home directory, user name and machine
+    /// name, as far as they can be found.
+    fn current() -> Self {
+        let home = directories::BaseDirs::new().map(|dirs| dirs.home_dir().to_path_buf());
+        let user = env::var("USER")
+            .or_else(|_| env::var("USERNAME"))
+            .ok()
+            .or_else(|| {
+                let name = home.as_deref()?.file_name()?;
+                Some(name.to_string_lossy().into_owned())
+            });
+        let machine = env::var("COMPUTERNAME")
+            .or_else(|_| env::var("HOSTNAME"))
+            .ok()
+            .or_else(|| Some(fs::read_to_string("/etc/hostname").ok()?.trim().to_string()));
+        Self {
+            home,
+            user: user.filter(|user| !user.is_empty()),
+            machine: machine.filter(|machine| !machine.is_empty()),
+        }
+    }
+}
+
+/// Replaces the home directory in the JSON text `json` with `~`, and the
+/// user and machine names with placeholders wherever else they make up a
+/// whole path component.
+fn anonymize(json: &str, identity: &Identity) -> String {
+    let mut json = json.to_string();
+    let home = identity.home.as_deref().and_then(Path::to_str);
+    if let Some(home) = home.filter(|home| home.len() > 1) {
+        json = json.replace(&escape(home.trim_end_matches(['/', '\\'])), "~");
+    }
+    if let Some(user) = &identity.user {
+        json = replace_component(&json, &escape(user), "<user>");
+    }
+    if let Some(machine) = &identity.machine {
+        json = replace_component(&json, &escape(machine), "<machine>");
+    }
+    json
+}
+
+/// Escapes `text` as it appears in JSON strings, so backslashes in
+/// Windows paths line up.
+fn escape(text: &str) -> String {
+    let escaped = serde_json::to_string(text).unwrap_or_default();
+    escaped.trim_matches('"').to_string()
+}
+
+/// Replaces `name` with `placeholder` in `json` where it's a whole path
+/// component: after a separator, and before another or the end of the
+/// string.
+fn replace_component(json: &str, name: &str, placeholder: &str) -> String {
+    if name.is_empty() {
+        return json.to_string();
+    }
+    let mut out = String::with_capacity(json.len());
+    let mut rest = json;
+    while let Some(index) = rest.find(name) {
+        let (before, after) = (&rest[..index], &rest[index + name.len()..]);
+        let starts = before.ends_with(['/', '\\']);
+        let ends = after.is_empty() || after.starts_with(['/', '\\', '"']);
+        out.push_str(before);
+        out.push_str(if starts && ends { placeholder } else { name });
+        rest = after;
+    }
+    out.push_str(rest);
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+    use tempfile::tempdir;
+
+    fn alice() -> Identity {
+        Identity {
+            home: Some(PathBuf::from("/home/alice/")),
+            user: Some("alice".to_string()),
+            machine: Some("DESKTOP-1".to_string()),
+        }
+    }
+
+    #[test]
+    fn test_anonymize() {
+        let json = json!({ "path": "/home/alice/Projects" }).to_string();
+        let text = anonymize(&json, &alice());
+        assert_eq!(text, r#"{"path":"~/Projects"}"#);
+        assert_eq!(anonymize(&json, &Identity::default()), json);
+
+        let windows = json!({ "path": r"C:\Users\alice\Music" }).to_string();
+        let identity = Identity {
+            home: Some(PathBuf::from(r"C:\Users\alice")),
+            ..Identity::default()
+        };
+        assert_eq!(anonymize(&windows, &identity), r#"{"path":"~\\Music"}"#);
+    }
+
+    #[test]
+    fn test_anonymize_names_outside_home() {
+        let json = json!({
+            "usb": "/media/alice/USB",
+            "share": r"\\DESKTOP-1\alice",
+            "other": "/srv/alicea/malice",
+        })
+        .to_string();
+        let text = anonymize(&json, &alice());
+        assert!(text.contains(r#""usb":"/media/<user>/USB""#), "{text}");
+        assert!(text.contains(r#""share":"\\\\<machine>\\<user>""#), "{text}");
+        assert!(text.contains(r#""other":"/srv/alicea/malice""#), "{text}");
+    }
+
+    #[test]
+    fn test_write_support_bundle() {
+        let temp = tempdir().unwrap();
+        let path = temp.path().join("bundle.zip");
+        write_support_bundle(&path, &[("about.json", json!({ "ok": true }))]).unwrap();
+
+        let mut archive = zip::ZipArchive::new(std::fs::File::open(&path).unwrap()).unwrap();
+        let mut entry = archive.by_name("about.json").unwrap();
+        assert_eq!(entry.compression(), CompressionMethod::Deflated);
+        let mut json = String::new();
+        std::io::Read::read_to_string(&mut entry, &mut json).unwrap();
+        assert_eq!(serde_json::from_str::<Value>(&json).unwrap(), json!({ "ok": true }));
+    }
+}
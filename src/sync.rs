@@ -0,0 +1,591 @@
+//! Syncing presets, rules and customizations between machines.
+//!
+//! Users who sync their app data folder with Dropbox, Syncthing and the like
+//! would corrupt it if two machines wrote the same files. Instead, each
+//! machine writes a [`SyncState`] to a file of its own, and merges the other
+//! machines' files into its own state with
+//! [`merge_synced_state`](crate::CustomizationContext::merge_synced_state).
+//!
+//! Every item in a sync file carries a hash of its content, so unchanged
+//! items are skipped without comparing them field by field. When both sides
+//! changed a preset, the newer version keeps the name and the other is kept
+//! as a conflict copy, so neither is lost; rules, which have no timestamps,
+//! keep the local version. Conflicts are listed in the [`SyncReport`].
+//! Deletions aren't synced: a preset removed here comes back from a machine
+//! that still has it.
+
+use crate::about::command_output;
+use crate::error::{Error, Result};
+use crate::presets::{Preset, PresetLibrary};
+use crate::profile::fnv1a_64;
+use crate::rules::{Rule, RuleMode, RuleSet};
+use crate::store::{now_unix_secs, write_atomic, ProfileStore, StoredProfile};
+
+use folco_renderer::CustomizationProfile;
+use serde::{Deserialize, Serialize};
+
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// Version of the sync file format written by this version of folco.
+pub const SYNC_FORMAT_VERSION: u32 = 1;
+
+/// Extension of sync files, which are named after their machine.
+pub const SYNC_FILE_EXTENSION: &str = "sync.json";
+
+/// An item of a sync file with the hash of its content.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SyncEntry<T> {
+    /// Hash of the synced content of `value`.
+    pub hash: String,
+    /// The item.
+    pub value: T,
+}
+
+/// One machine's presets, rules and customizations, as written to its sync
+/// file.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SyncState {
+    /// Format version of the file.
+    pub version: u32,
+    /// Name of the machine that wrote the file.
+    pub machine: String,
+    /// When the state was exported, in seconds since the Unix epoch.
+    pub exported_at: u64,
+    /// Presets by name.
+    #[serde(default)]
+    pub presets: BTreeMap<String, SyncEntry<Preset>>,
+    /// How the rules are combined.
+    #[serde(default)]
+    pub rule_mode: RuleMode,
+    /// Rules by name, in priority order of their position in `rule_order`.
+    #[serde(default)]
+    pub rules: BTreeMap<String, SyncEntry<Rule>>,
+    /// Rule names in the order they're evaluated.
+    #[serde(default)]
+    pub rule_order: Vec<String>,
+    /// Customized folders by normalized path.
+    #[serde(default)]
+    pub profiles: BTreeMap<String, SyncEntry<StoredProfile>>,
+}
+
+impl SyncState {
+    /// Exports the given state as written by `machine`.
+    pub(crate) fn export(
+        machine: &str,
+        presets: &PresetLibrary,
+        rules: &RuleSet,
+        store: &ProfileStore,
+    ) -> Self {
+        Self {
+            version: SYNC_FORMAT_VERSION,
+            machine: machine.to_string(),
+            exported_at: now_unix_secs(),
+            presets: presets
+                .iter()
+                .map(|preset| (preset.name.clone(), preset_entry(preset)))
+                .collect(),
+            rule_mode: rules.mode(),
+            rules: rules
+                .rules()
+                .iter()
+                .map(|rule| (rule.name.clone(), rule_entry(rule)))
+                .collect(),
+            rule_order: rules.rules().iter().map(|rule| rule.name.clone()).collect(),
+            profiles: store
+                .entries()
+                .into_iter()
+                .map(|(path, mut stored)| {
+                    // File IDs only mean something on the machine they were read on
+                    stored.file_id = None;
+                    let entry = SyncEntry {
+                        hash: stored.profile_hash.clone(),
+                        value: stored,
+                    };
+                    (path.to_string_lossy().into_owned(), entry)
+                })
+                .collect(),
+        }
+    }
+}
+
+/// Kind of item two machines disagree on.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum SyncItem {
+    /// A preset.
+    Preset,
+    /// A rule.
+    Rule,
+    /// How the rules are combined.
+    RuleMode,
+}
+
+/// Two machines changing the same item differently.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SyncConflict {
+    /// Kind of the item.
+    pub item: SyncItem,
+    /// Name of the item.
+    pub name: String,
+    /// The other machine.
+    pub remote_machine: String,
+    /// Whether the other machine's version was taken.
+    pub took_remote: bool,
+    /// Name the losing version of a preset was kept under.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub conflict_copy: Option<String>,
+}
+
+/// What merging another machine's sync file changed.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SyncReport {
+    /// Presets that were new here.
+    pub added_presets: Vec<String>,
+    /// Presets replaced by a newer version from the other machine.
+    pub updated_presets: Vec<String>,
+    /// Rules that were new here, added after the existing ones.
+    pub added_rules: Vec<String>,
+    /// Items changed differently on both machines.
+    pub conflicts: Vec<SyncConflict>,
+    /// Folders that exist here and were customized more recently on the
+    /// other machine, with the profile they got there. They aren't applied
+    /// by the merge; pass them to
+    /// [`customize_folders`](crate::CustomizationContext::customize_folders)
+    /// to catch up.
+    pub to_apply: Vec<(PathBuf, CustomizationProfile)>,
+}
+
+impl SyncReport {
+    /// Returns `true` if the merge changed nothing and found nothing to
+    /// apply.
+    pub fn is_noop(&self) -> bool {
+        self.added_presets.is_empty()
+            && self.updated_presets.is_empty()
+            && self.added_rules.is_empty()
+            && self.conflicts.is_empty()
+            && self.to_apply.is_empty()
+    }
+
+    /// Returns `true` if presets or rules changed and need saving.
+    pub(crate) fn changed_presets(&self) -> bool {
+        !self.added_presets.is_empty()
+            || !self.updated_presets.is_empty()
+            || self.conflicts.iter().any(|c| c.conflict_copy.is_some())
+    }
+}
+
+/// Merges `remote` into the local presets and rules, and lists the
+/// customizations to catch up on.
+pub(crate) fn merge_state(
+    presets: &mut PresetLibrary,
+    rules: &mut RuleSet,
+    store: &ProfileStore,
+    remote: &SyncState,
+) -> SyncReport {
+    let mut report = SyncReport::default();
+
+    for (name, entry) in &remote.presets {
+        let Some(local) = presets.get(name) else {
+            presets.insert_preset(entry.value.clone());
+            report.added_presets.push(name.clone());
+            continue;
+        };
+        if preset_entry(local).hash == entry.hash {
+            continue;
+        }
+
+        // The newer version keeps the name, and the older is kept aside
+        // unless either machine already has a copy of it
+        let local = local.clone();
+        let took_remote = entry.value.updated_at > local.updated_at;
+        let (winner, mut loser) = if took_remote {
+            let winner = Preset {
+                use_count: local.use_count,
+                last_used_at: local.last_used_at,
+                ..entry.value.clone()
+            };
+            (winner, local)
+        } else {
+            (local, entry.value.clone())
+        };
+        let loser_hash = preset_entry(&loser).hash;
+        let kept_elsewhere = presets
+            .iter()
+            .chain(remote.presets.values().map(|entry| &entry.value))
+            .any(|preset| preset.name != *name && preset_entry(preset).hash == loser_hash);
+        let conflict_copy =
+            (!kept_elsewhere).then(|| conflict_name(presets, name, &remote.machine));
+        presets.insert_preset(winner);
+        if let Some(copy_name) = &conflict_copy {
+            loser.name = copy_name.clone();
+            loser.use_count = 0;
+            loser.last_used_at = None;
+            presets.insert_preset(loser);
+        }
+        if took_remote {
+            report.updated_presets.push(name.clone());
+        }
+        report.conflicts.push(SyncConflict {
+            item: SyncItem::Preset,
+            name: name.clone(),
+            remote_machine: remote.machine.clone(),
+            took_remote,
+            conflict_copy,
+        });
+    }
+
+    if remote.rule_mode != rules.mode() {
+        report.conflicts.push(SyncConflict {
+            item: SyncItem::RuleMode,
+            name: "mode".to_string(),
+            remote_machine: remote.machine.clone(),
+            took_remote: false,
+            conflict_copy: None,
+        });
+    }
+    for name in &remote.rule_order {
+        let Some(entry) = remote.rules.get(name) else {
+            continue;
+        };
+        match rules.get(name) {
+            None => {
+                if rules.push(entry.value.clone()).is_ok() {
+                    report.added_rules.push(name.clone());
+                }
+            }
+            Some(local) if rule_entry(local).hash != entry.hash => {
+                report.conflicts.push(SyncConflict {
+                    item: SyncItem::Rule,
+                    name: name.clone(),
+                    remote_machine: remote.machine.clone(),
+                    took_remote: false,
+                    conflict_copy: None,
+                });
+            }
+            Some(_) => {}
+        }
+    }
+
+    for (path, entry) in &remote.profiles {
+        let path = PathBuf::from(path);
+        if !path.is_dir() {
+            continue;
+        }
+        let outdated = store.get(&path).is_none_or(|local| {
+            local.profile_hash != entry.hash && local.applied_at < entry.value.applied_at
+        });
+        if outdated {
+            report.to_apply.push((path, entry.value.profile.clone()));
+        }
+    }
+
+    report
+}
+
+/// Reads the sync file at `path`.
+///
+/// # Errors
+///
+/// Returns [`Error::Sync`] if the file was written by a newer format
+/// version.
+pub fn read_synced_state(path: &Path) -> Result<SyncState> {
+    let content = fs::read_to_string(path)?;
+    let state: SyncState =
+        serde_json::from_str(&content).map_err(|e| Error::Serialization(e.to_string()))?;
+    if state.version > SYNC_FORMAT_VERSION {
+        return Err(Error::Sync(format!(
+            "sync file version {} is newer than the supported version {SYNC_FORMAT_VERSION}",
+            state.version
+        )));
+    }
+    Ok(state)
+}
+
+/// Writes `state` to `dir`, in a file named after its machine, and returns
+/// the file's path.
+pub fn write_synced_state(state: &SyncState, dir: &Path) -> Result<PathBuf> {
+    fs::create_dir_all(dir)?;
+    let path = dir.join(format!("{}.{SYNC_FILE_EXTENSION}", state.machine));
+    let json =
+        serde_json::to_string_pretty(state).map_err(|e| Error::Serialization(e.to_string()))?;
+    write_atomic(&path, &json)?;
+    Ok(path)
+}
+
+/// Returns the paths of the sync files in `dir`, except `machine`'s own.
+pub(crate) fn remote_sync_files(dir: &Path, machine: &str) -> Result<Vec<PathBuf>> {
+    let own = format!("{machine}.{SYNC_FILE_EXTENSION}");
+    let mut files: Vec<PathBuf> = fs::read_dir(dir)?
+        .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+        .filter(|path| {
+            path.file_name()
+                .and_then(|name| name.to_str())
+                .is_some_and(|name| name.ends_with(SYNC_FILE_EXTENSION) && name != own)
+        })
+        .collect();
+    files.sort();
+    Ok(files)
+}
+
+/// Returns a name for this machine, safe to use in a file name.
+///
+/// Uses the host name, or `"unknown"` if it can't be read.
+pub fn machine_name() -> String {
+    let name = std::env::var("COMPUTERNAME")
+        .ok()
+        .or_else(|| command_output("hostname", &[]))
+        .unwrap_or_default();
+    let name: String = name
+        .trim()
+        .chars()
+        .map(|c| {
+            if c.is_ascii_alphanumeric() || c == '-' || c == '_' {
+                c
+            } else {
+                '_'
+            }
+        })
+        .collect();
+    if name.is_empty() {
+        "unknown".to_string()
+    } else {
+        name
+    }
+}
+
+/// Returns the sync entry of a preset. Its name is left out of the hash, so
+/// conflict copies can be recognized, and so is its usage, since every
+/// machine counts its own.
+fn preset_entry(preset: &Preset) -> SyncEntry<Preset> {
+    let content = Preset {
+        name: String::new(),
+        created_at: 0,
+        updated_at: 0,
+        use_count: 0,
+        last_used_at: None,
+        ..preset.clone()
+    };
+    SyncEntry {
+        hash: content_hash(&content),
+        value: preset.clone(),
+    }
+}
+
+fn rule_entry(rule: &Rule) -> SyncEntry<Rule> {
+    SyncEntry {
+        hash: content_hash(rule),
+        value: rule.clone(),
+    }
+}
+
+fn content_hash(value: &impl Serialize) -> String {
+    let json = serde_json::to_string(value).unwrap_or_default();
+    format!("{:016x}", fnv1a_64(json.as_bytes()))
+}
+
+/// Returns an unused name for the conflict copy of preset `name`.
+fn conflict_name(presets: &PresetLibrary, name: &str, machine: &str) -> String {
+    let base = format!("{name} (conflict with {machine})");
+    let mut candidate = base.clone();
+    let mut counter = 2;
+    while presets.get(&candidate).is_some() {
+        candidate = format!("{base} {counter}");
+        counter += 1;
+    }
+    candidate
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::color::FolderColor;
+    use crate::profile::{profile_color, profile_with_color};
+    use crate::state::MemoryStateStore;
+    use std::sync::Arc;
+    use tempfile::tempdir;
+
+    struct Machine {
+        presets: PresetLibrary,
+        rules: RuleSet,
+        store: ProfileStore,
+    }
+
+    impl Machine {
+        fn new() -> Self {
+            let state = Arc::new(MemoryStateStore::default());
+            Self {
+                presets: PresetLibrary::with_state_store(state.clone()).unwrap(),
+                rules: RuleSet::new(),
+                store: ProfileStore::with_state_store(state).unwrap(),
+            }
+        }
+
+        fn export(&self, machine: &str) -> SyncState {
+            SyncState::export(machine, &self.presets, &self.rules, &self.store)
+        }
+
+        fn merge(&mut self, remote: &SyncState) -> SyncReport {
+            merge_state(&mut self.presets, &mut self.rules, &self.store, remote)
+        }
+    }
+
+    fn colored(color: FolderColor) -> CustomizationProfile {
+        profile_with_color(&CustomizationProfile::default(), color).unwrap()
+    }
+
+    #[test]
+    fn test_new_items_are_added() {
+        let mut laptop = Machine::new();
+        laptop.presets.insert("Work", colored(FolderColor::Blue));
+        laptop
+            .rules
+            .push(Rule::new("invoices", "invoice*").with_color(FolderColor::Green))
+            .unwrap();
+
+        let mut desktop = Machine::new();
+        let report = desktop.merge(&laptop.export("laptop"));
+        assert_eq!(report.added_presets, vec!["Work"]);
+        assert_eq!(report.added_rules, vec!["invoices"]);
+        assert!(report.conflicts.is_empty());
+
+        // Merging again changes nothing
+        assert!(desktop.merge(&laptop.export("laptop")).is_noop());
+    }
+
+    #[test]
+    fn test_usage_alone_is_not_a_conflict() {
+        let mut laptop = Machine::new();
+        laptop.presets.insert("Work", colored(FolderColor::Blue));
+        let mut desktop = Machine::new();
+        desktop.merge(&laptop.export("laptop"));
+
+        laptop.presets.record_use("Work");
+        assert!(desktop.merge(&laptop.export("laptop")).is_noop());
+    }
+
+    #[test]
+    fn test_conflicting_presets_keep_both_versions() {
+        let mut laptop = Machine::new();
+        laptop.presets.insert("Work", colored(FolderColor::Blue));
+        let mut desktop = Machine::new();
+        desktop.presets.insert("Work", colored(FolderColor::Red));
+
+        let mut remote = laptop.export("laptop");
+        remote.presets.get_mut("Work").unwrap().value.updated_at += 60;
+        let report = desktop.merge(&remote);
+
+        assert_eq!(report.updated_presets, vec!["Work"]);
+        let conflict = &report.conflicts[0];
+        assert!(conflict.took_remote);
+        let copy = conflict.conflict_copy.clone().unwrap();
+        assert_eq!(copy, "Work (conflict with laptop)");
+        let color = |name: &str| profile_color(desktop.presets.profile(name).unwrap());
+        assert_eq!(color("Work"), Some(FolderColor::Blue));
+        assert_eq!(color(&copy), Some(FolderColor::Red));
+
+        // The laptop already has the winning version, and gets the copy
+        let report = laptop.merge(&desktop.export("desktop"));
+        assert!(report.conflicts.is_empty());
+        assert_eq!(report.added_presets, vec![copy]);
+    }
+
+    #[test]
+    fn test_conflicts_are_not_copied_twice() {
+        let mut laptop = Machine::new();
+        laptop.presets.insert("Work", colored(FolderColor::Blue));
+        let mut desktop = Machine::new();
+        desktop.presets.insert("Work", colored(FolderColor::Red));
+
+        // Neither version is newer, so the desktop keeps its own
+        let mut remote = laptop.export("laptop");
+        remote.presets.get_mut("Work").unwrap().value.updated_at =
+            desktop.presets.get("Work").unwrap().updated_at;
+        let report = desktop.merge(&remote);
+        assert!(!report.conflicts[0].took_remote);
+        assert!(report.conflicts[0].conflict_copy.is_some());
+
+        let report = desktop.merge(&remote);
+        assert_eq!(report.conflicts[0].conflict_copy, None);
+        assert_eq!(desktop.presets.len(), 2);
+    }
+
+    #[test]
+    fn test_conflicting_rules_keep_the_local_version() {
+        let mut laptop = Machine::new();
+        laptop
+            .rules
+            .push(Rule::new("invoices", "invoice*").with_color(FolderColor::Green))
+            .unwrap();
+        let mut desktop = Machine::new();
+        desktop
+            .rules
+            .push(Rule::new("invoices", "invoice*").with_color(FolderColor::Red))
+            .unwrap();
+
+        let report = desktop.merge(&laptop.export("laptop"));
+        assert_eq!(report.conflicts[0].item, SyncItem::Rule);
+        assert!(!report.conflicts[0].took_remote);
+        assert_eq!(
+            desktop.rules.get("invoices").unwrap().color,
+            Some(FolderColor::Red)
+        );
+    }
+
+    #[test]
+    fn test_newer_customizations_are_listed_to_apply() {
+        let temp = tempdir().unwrap();
+        let shared = temp.path().join("Shared");
+        fs::create_dir(&shared).unwrap();
+
+        let laptop = Machine::new();
+        laptop.store.insert(&shared, &colored(FolderColor::Teal));
+        laptop
+            .store
+            .insert(&temp.path().join("elsewhere"), &colored(FolderColor::Red));
+
+        let mut desktop = Machine::new();
+        let report = desktop.merge(&laptop.export("laptop"));
+        assert_eq!(report.to_apply.len(), 1);
+        assert_eq!(
+            profile_color(&report.to_apply[0].1),
+            Some(FolderColor::Teal)
+        );
+    }
+
+    #[test]
+    fn test_sync_files() {
+        let temp = tempdir().unwrap();
+        let laptop = Machine::new();
+        let path = write_synced_state(&laptop.export("laptop"), temp.path()).unwrap();
+        write_synced_state(&laptop.export("desktop"), temp.path()).unwrap();
+        assert_eq!(path, temp.path().join("laptop.sync.json"));
+        assert_eq!(read_synced_state(&path).unwrap().machine, "laptop");
+        assert_eq!(
+            remote_sync_files(temp.path(), "desktop").unwrap(),
+            vec![path]
+        );
+
+        let newer = temp.path().join("newer.sync.json");
+        fs::write(
+            &newer,
+            r#"{"version": 99, "machine": "x", "exportedAt": 0}"#,
+        )
+        .unwrap();
+        assert!(matches!(read_synced_state(&newer), Err(Error::Sync(_))));
+    }
+
+    #[test]
+    fn test_machine_name_is_file_safe() {
+        let name = machine_name();
+        assert!(!name.is_empty());
+        assert!(name
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_'));
+    }
+}
@@ -0,0 +1,105 @@
+//! Scaled stand-ins for icon sizes a render lacks.
+//!
+//! Third-party base icons often come in only a few sizes, while the
+//! platform picks from a fixed set of them for its views: Explorer uses
+//! 16, 32, 48 and 256 pixels. Rather than failing to apply such icons,
+//! the missing sizes are scaled from the nearest size there is, and the
+//! batch reports a [`Warning::SynthesizedSizes`](crate::Warning::SynthesizedSizes).
+
+use icon_sys::IconSet as SysIconSet;
+use image::imageops::FilterType;
+
+/// Returns `icons` with each of `sizes` it lacks scaled from the nearest
+/// larger image, or from the largest if none is larger, together with the
+/// sizes that were added.
+///
+/// Returns `None` if nothing is missing, or if there are no images to
+/// scale from.
+pub(crate) fn synthesize_missing_sizes(
+    icons: &SysIconSet,
+    sizes: &[u32],
+) -> Option<(SysIconSet, Vec<u32>)> {
+    let missing: Vec<u32> = sizes
+        .iter()
+        .copied()
+        .filter(|&size| icons.images.iter().all(|image| image.data.width() != size))
+        .collect();
+    let largest = icons.images.iter().max_by_key(|image| image.data.width())?;
+    if missing.is_empty() {
+        return None;
+    }
+
+    let mut filled = SysIconSet {
+        images: icons
+            .images
+            .iter()
+            .map(|image| icon_sys::IconImage {
+                data: image.data.clone(),
+            })
+            .collect(),
+    };
+    for &size in &missing {
+        // Scaling down keeps more detail than scaling up
+        let source = icons
+            .images
+            .iter()
+            .filter(|image| image.data.width() > size)
+            .min_by_key(|image| image.data.width())
+            .unwrap_or(largest);
+        filled.images.push(icon_sys::IconImage {
+            data: source.data.resize_exact(size, size, FilterType::Lanczos3),
+        });
+    }
+    filled.images.sort_by_key(|image| image.data.width());
+    Some((filled, missing))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use image::{DynamicImage, Rgba, RgbaImage};
+
+    fn icon_set(sizes: &[u32]) -> SysIconSet {
+        SysIconSet {
+            images: sizes
+                .iter()
+                .map(|&size| icon_sys::IconImage {
+                    data: DynamicImage::ImageRgba8(RgbaImage::from_pixel(
+                        size,
+                        size,
+                        Rgba([size as u8, 0, 0, 255]),
+                    )),
+                })
+                .collect(),
+        }
+    }
+
+    fn widths(icons: &SysIconSet) -> Vec<u32> {
+        icons
+            .images
+            .iter()
+            .map(|image| image.data.width())
+            .collect()
+    }
+
+    #[test]
+    fn test_missing_sizes_are_scaled_from_the_nearest_larger() {
+        let icons = icon_set(&[32, 128]);
+        let (filled, added) = synthesize_missing_sizes(&icons, &[16, 32, 48, 256]).unwrap();
+        assert_eq!(added, [16, 48, 256]);
+        assert_eq!(widths(&filled), [16, 32, 48, 128, 256]);
+
+        // 16 comes from 32 and 48 from 128, each keeping its source's color
+        let red = |index: usize| filled.images[index].data.to_rgba8().get_pixel(0, 0).0[0];
+        assert_eq!((red(0), red(2)), (32, 128));
+        assert_eq!(red(4), 128);
+    }
+
+    #[test]
+    fn test_nothing_to_synthesize() {
+        let icons = icon_set(&[16, 32]);
+        assert!(synthesize_missing_sizes(&icons, &[16, 32]).is_none());
+        assert!(synthesize_missing_sizes(&icons, &[]).is_none());
+        assert!(synthesize_missing_sizes(&icon_set(&[]), &[16]).is_none());
+    }
+}
@@ -2,6 +2,12 @@
 
 use folco_renderer::RectPx;
 
+use std::path::Path;
+
+/// Linux file managers scale whichever image the folder points at, so no
+/// size is needed in particular.
+pub(crate) const PREFERRED_SIZES: &[u32] = &[];
+
 /// Returns the content bounds for a Linux system folder icon.
 ///
 /// Linux folder icons from icon themes may have specific content regions
@@ -24,7 +30,32 @@ pub fn get_folder_icon_content_bounds(width: u32, height: u32) -> RectPx {
     )
 }
 
+/// Returns `true` if the folder has a custom icon configured.
+///
+/// Checks for an `Icon=` entry in the folder's `.directory` file, as used by
+/// KDE Dolphin and other freedesktop-compliant file managers.
+pub fn has_custom_folder_icon(folder: &Path) -> bool {
+    match std::fs::read_to_string(folder.join(".directory")) {
+        Ok(content) => content
+            .lines()
+            .any(|line| line.trim_start().starts_with("Icon=")),
+        Err(_) => false,
+    }
+}
+
 #[cfg(test)]
 mod tests {
-    // Tests will be added once bounds are implemented
+    use super::*;
+    use tempfile::tempdir;
+
+    // Content bounds tests will be added once bounds are implemented
+
+    #[test]
+    fn test_has_custom_folder_icon() {
+        let temp = tempdir().unwrap();
+        assert!(!has_custom_folder_icon(temp.path()));
+
+        std::fs::write(temp.path().join(".directory"), "[Desktop Entry]\nIcon=folder-blue\n").unwrap();
+        assert!(has_custom_folder_icon(temp.path()));
+    }
 }
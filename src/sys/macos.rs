@@ -2,6 +2,12 @@
 
 use folco_renderer::RectPx;
 
+use std::path::Path;
+
+/// Sizes of the icon families Finder picks from, which every applied icon
+/// should have.
+pub(crate) const PREFERRED_SIZES: &[u32] = &[16, 32, 128, 256, 512];
+
 /// Returns the content bounds for a macOS system folder icon.
 ///
 /// macOS folder icons may have specific content regions depending on
@@ -24,7 +30,27 @@ pub fn get_folder_icon_content_bounds(width: u32, height: u32) -> RectPx {
     )
 }
 
+/// Returns `true` if the folder has a custom icon configured.
+///
+/// Finder stores custom folder icons in a hidden `Icon\r` file inside the
+/// folder, holding the icon in its resource fork.
+pub fn has_custom_folder_icon(folder: &Path) -> bool {
+    folder.join("Icon\r").exists()
+}
+
 #[cfg(test)]
 mod tests {
-    // Tests will be added once bounds are implemented
+    use super::*;
+    use tempfile::tempdir;
+
+    // Content bounds tests will be added once bounds are implemented
+
+    #[test]
+    fn test_has_custom_folder_icon() {
+        let temp = tempdir().unwrap();
+        assert!(!has_custom_folder_icon(temp.path()));
+
+        std::fs::write(temp.path().join("Icon\r"), "").unwrap();
+        assert!(has_custom_folder_icon(temp.path()));
+    }
 }
@@ -15,12 +15,36 @@ pub mod linux;
 
 // Re-export the platform-specific implementation under a common alias
 #[cfg(target_os = "windows")]
-pub use windows::get_folder_icon_content_bounds;
+pub use windows::{get_folder_icon_content_bounds, has_custom_folder_icon};
 #[cfg(target_os = "windows")]
 pub use windows::SURFACE_COLOR;
+#[cfg(target_os = "windows")]
+pub(crate) use windows::SURFACE_HSL;
+#[cfg(target_os = "windows")]
+pub(crate) use windows::{add_jumbo_sizes, PREFERRED_SIZES};
+
+/// Only the Windows folder color has been measured so far; the other
+/// platforms use it until theirs is, or pass their own through a
+/// [`DefaultIconBackend`](crate::DefaultIconBackend).
+#[cfg(not(target_os = "windows"))]
+pub(crate) const SURFACE_HSL: (f32, f32, f32) = crate::fixtures::FIXTURE_SURFACE_HSL;
+/// [`SURFACE_HSL`] as the renderer's surface color.
+#[cfg(not(target_os = "windows"))]
+pub const SURFACE_COLOR: folco_renderer::SurfaceColor = crate::fixtures::FIXTURE_SURFACE_COLOR;
 
+/// Jumbo sizes are a Windows feature; elsewhere the icons are kept as they
+/// are.
+#[cfg(not(target_os = "windows"))]
+pub(crate) fn add_jumbo_sizes(icons: icon_sys::IconSet) -> icon_sys::IconSet {
+    icons
+}
+
+#[cfg(target_os = "macos")]
+pub use macos::{get_folder_icon_content_bounds, has_custom_folder_icon};
 #[cfg(target_os = "macos")]
-pub use macos::get_folder_icon_content_bounds;
+pub(crate) use macos::PREFERRED_SIZES;
 
 #[cfg(target_os = "linux")]
-pub use linux::get_folder_icon_content_bounds;
+pub use linux::{get_folder_icon_content_bounds, has_custom_folder_icon};
+#[cfg(target_os = "linux")]
+pub(crate) use linux::PREFERRED_SIZES;
@@ -2,12 +2,32 @@
 
 use folco_renderer::{RectPx, SurfaceColor};
 use icon_sys::icon::sys::windows::WindowsIconSize;
+use icon_sys::IconSet as SysIconSet;
+use image::imageops::FilterType;
+
+use std::path::Path;
 
 /// The default Windows folder icon surface color: HSL(44°, 100%, 72%).
 ///
 /// This is the golden-yellow hue of the standard Windows folder icon,
 /// used as the reference point for computing HSL mutation deltas.
-pub const SURFACE_COLOR: SurfaceColor = SurfaceColor::new(44.0, 1.0, 0.72);
+pub const SURFACE_COLOR: SurfaceColor =
+    SurfaceColor::new(SURFACE_HSL.0, SURFACE_HSL.1, SURFACE_HSL.2);
+
+/// [`SURFACE_COLOR`] as `(hue, saturation, lightness)`, for checking
+/// extracted icons against it.
+pub(crate) const SURFACE_HSL: (f32, f32, f32) = (44.0, 1.0, 0.72);
+
+/// Sizes above 256 pixels, used by Explorer's extra-large views and some
+/// third-party shells. The system's own folder icon stops at 256.
+pub const JUMBO_SIZES: [u32; 2] = [512, 768];
+
+/// Sizes Explorer picks from for its views, which every applied icon
+/// should have.
+pub(crate) const PREFERRED_SIZES: &[u32] = &[16, 32, 48, 256];
+
+/// Size the jumbo sizes are scaled from.
+const LARGEST_SYSTEM_SIZE: u32 = 256;
 
 /// Returns the content bounds for a Windows system folder icon.
 ///
@@ -23,11 +43,17 @@ pub const SURFACE_COLOR: SurfaceColor = SurfaceColor::new(44.0, 1.0, 0.72);
 /// # Returns
 ///
 /// A `RectPx` describing the region containing the actual icon content.
+/// The [jumbo sizes](JUMBO_SIZES) get the 256-pixel bounds, scaled.
 ///
 /// # Panics
 ///
-/// Panics if `dimension` is not a valid Windows icon size (16, 20, 24, 32, 40, 48, 64, or 256).
+/// Panics if `dimension` is not a valid Windows icon size (16, 20, 24, 32, 40, 48, 64, 256, 512
+/// or 768).
 pub fn get_folder_icon_content_bounds(dimension: u32, _height: u32) -> RectPx {
+    if JUMBO_SIZES.contains(&dimension) {
+        let scale = |value: u32| value * dimension / LARGEST_SYSTEM_SIZE;
+        return RectPx::new(scale(16), scale(62), scale(224), scale(144));
+    }
     let size = WindowsIconSize::from_dimension(dimension)
         .expect("Invalid Windows icon dimension");
 
@@ -43,6 +69,73 @@ pub fn get_folder_icon_content_bounds(dimension: u32, _height: u32) -> RectPx {
     }
 }
 
+/// Returns `icons` with the missing [jumbo sizes](JUMBO_SIZES) added,
+/// scaled up from the largest image.
+///
+/// The folder outline blurs slightly, but decals are rendered at the full
+/// jumbo size, so they stay sharp.
+pub(crate) fn add_jumbo_sizes(mut icons: SysIconSet) -> SysIconSet {
+    let Some(largest) = icons
+        .images
+        .iter()
+        .filter(|image| image.data.width() >= LARGEST_SYSTEM_SIZE)
+        .max_by_key(|image| image.data.width())
+        .map(|image| image.data.clone())
+    else {
+        return icons;
+    };
+    for size in JUMBO_SIZES {
+        if icons.images.iter().all(|image| image.data.width() != size) {
+            icons.images.push(icon_sys::IconImage {
+                data: largest.resize_exact(size, size, FilterType::Lanczos3),
+            });
+        }
+    }
+    icons
+}
+
+/// Returns `true` if the folder has a custom icon configured.
+///
+/// Explorer reads custom folder icons from the `IconResource` (or legacy
+/// `IconFile`) key of the folder's `desktop.ini`.
+pub fn has_custom_folder_icon(folder: &Path) -> bool {
+    match std::fs::read(folder.join("desktop.ini")) {
+        Ok(bytes) => desktop_ini_has_icon(&decode_ini(&bytes)),
+        Err(_) => false,
+    }
+}
+
+/// Decodes `desktop.ini` contents, which Explorer writes as either UTF-16LE
+/// (with a byte order mark) or an ANSI code page.
+pub(crate) fn decode_ini(bytes: &[u8]) -> String {
+    match bytes.strip_prefix(&[0xFF, 0xFE]) {
+        Some(utf16) => {
+            let units: Vec<u16> = utf16
+                .chunks_exact(2)
+                .map(|pair| u16::from_le_bytes([pair[0], pair[1]]))
+                .collect();
+            String::from_utf16_lossy(&units)
+        }
+        None => String::from_utf8_lossy(bytes).into_owned(),
+    }
+}
+
+/// Returns `true` if the `[.ShellClassInfo]` section sets an icon.
+fn desktop_ini_has_icon(ini: &str) -> bool {
+    let mut in_shell_class_info = false;
+    for line in ini.lines().map(str::trim) {
+        if line.starts_with('[') {
+            in_shell_class_info = line.eq_ignore_ascii_case("[.ShellClassInfo]");
+        } else if in_shell_class_info {
+            let key = line.split('=').next().unwrap_or("").trim();
+            if key.eq_ignore_ascii_case("IconResource") || key.eq_ignore_ascii_case("IconFile") {
+                return true;
+            }
+        }
+    }
+    false
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -74,6 +167,53 @@ mod tests {
         assert_eq!(bounds.height, 144);
     }
 
+    #[test]
+    fn test_jumbo_bounds_scale_256() {
+        let bounds = get_folder_icon_content_bounds(512, 512);
+        assert_eq!((bounds.x, bounds.y), (32, 124));
+        assert_eq!((bounds.width, bounds.height), (448, 288));
+        for dim in JUMBO_SIZES {
+            let bounds = get_folder_icon_content_bounds(dim, dim);
+            assert!(bounds.x + bounds.width <= dim);
+            assert!(bounds.y + bounds.height <= dim);
+        }
+    }
+
+    #[test]
+    fn test_add_jumbo_sizes() {
+        let image = image::DynamicImage::new_rgba8(256, 256);
+        let icons = SysIconSet {
+            images: vec![icon_sys::IconImage { data: image }],
+        };
+        let widths: Vec<u32> = add_jumbo_sizes(icons)
+            .images
+            .iter()
+            .map(|image| image.data.width())
+            .collect();
+        assert_eq!(widths, vec![256, 512, 768]);
+
+        let empty = add_jumbo_sizes(SysIconSet { images: Vec::new() });
+        assert!(empty.images.is_empty());
+    }
+
+    #[test]
+    fn test_desktop_ini_has_icon() {
+        let ini = "[.ShellClassInfo]\r\nIconResource=C:\\icons\\blue.ico,0\r\n";
+        assert!(desktop_ini_has_icon(ini));
+
+        let ini = "[ViewState]\r\nIconResource=ignored\r\n[.ShellClassInfo]\r\nInfoTip=hi\r\n";
+        assert!(!desktop_ini_has_icon(ini));
+    }
+
+    #[test]
+    fn test_decode_utf16_ini() {
+        let text = "[.ShellClassInfo]\nIconFile=a.ico";
+        let mut bytes = vec![0xFF, 0xFE];
+        bytes.extend(text.encode_utf16().flat_map(u16::to_le_bytes));
+
+        assert_eq!(decode_ini(&bytes), text);
+    }
+
     #[test]
     fn test_all_sizes_valid() {
         for size in WindowsIconSize::all() {
@@ -0,0 +1,147 @@
+//! Opt-in telemetry through a consumer-provided sink.
+//!
+//! folco-core ships no network code. A frontend that offers analytics
+//! implements [`TelemetrySink`] and hands it to the context, which calls it
+//! with [`OperationMetrics`] after every batch. The metrics are anonymized
+//! by construction: they hold counts, durations and error kinds, never
+//! paths, profile settings or error messages, so a sink can forward them
+//! as they are.
+
+use crate::batch::BatchOutcome;
+use crate::error::Error;
+
+use serde::{Deserialize, Serialize};
+
+use std::collections::BTreeMap;
+use std::fmt;
+use std::time::Duration;
+
+/// Receives anonymized metrics about the operations a context performs.
+///
+/// Called synchronously at the end of each batch, so implementations
+/// should queue or batch their own work rather than block.
+pub trait TelemetrySink: Send + Sync {
+    /// Records the metrics of one finished operation.
+    fn record(&self, metrics: &OperationMetrics);
+}
+
+/// A kind of batch operation.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum OperationKind {
+    /// Folders were customized.
+    Customize,
+    /// Folders were reset to the default icon.
+    Reset,
+}
+
+impl fmt::Display for OperationKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Self::Customize => "customize",
+            Self::Reset => "reset",
+        })
+    }
+}
+
+/// Anonymized metrics of one batch operation.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct OperationMetrics {
+    /// What the batch did.
+    pub operation: OperationKind,
+    /// Folders processed successfully.
+    pub succeeded: usize,
+    /// Folders that failed.
+    pub failed: usize,
+    /// Folders left untouched by the conflict policy.
+    pub skipped: usize,
+    /// How long the batch took, in milliseconds.
+    pub duration_ms: u64,
+    /// Number of errors of each [`Error::kind`], including batch-level
+    /// errors such as a failed render.
+    pub error_kinds: BTreeMap<String, usize>,
+}
+
+impl OperationMetrics {
+    /// Creates empty metrics for an operation.
+    pub(crate) fn new(operation: OperationKind) -> Self {
+        Self {
+            operation,
+            succeeded: 0,
+            failed: 0,
+            skipped: 0,
+            duration_ms: 0,
+            error_kinds: BTreeMap::new(),
+        }
+    }
+
+    /// Summarizes a finished batch.
+    pub(crate) fn from_outcome(
+        operation: OperationKind,
+        outcome: &BatchOutcome,
+        duration: Duration,
+    ) -> Self {
+        let mut metrics = Self::new(operation).with_duration(duration);
+        metrics.succeeded = outcome.succeeded_count();
+        metrics.failed = outcome.failed_count();
+        metrics.skipped = outcome.skipped.len();
+        let errors = outcome.results.iter().filter_map(|(_, r)| r.as_ref().err());
+        for error in errors.chain(&outcome.error) {
+            metrics.count_error(error);
+        }
+        metrics
+    }
+
+    /// Sets the duration.
+    pub(crate) fn with_duration(mut self, duration: Duration) -> Self {
+        self.duration_ms = u64::try_from(duration.as_millis()).unwrap_or(u64::MAX);
+        self
+    }
+
+    /// Counts one error by its kind.
+    pub(crate) fn count_error(&mut self, error: &Error) {
+        *self
+            .error_kinds
+            .entry(error.kind().to_string())
+            .or_default() += 1;
+    }
+
+    /// Returns the total number of errors counted.
+    pub fn error_count(&self) -> usize {
+        self.error_kinds.values().sum()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::PathBuf;
+
+    #[test]
+    fn test_metrics_from_outcome_hold_no_paths() {
+        let secret = PathBuf::from("/home/someone/Secret Project");
+        let outcome = BatchOutcome {
+            results: vec![
+                (PathBuf::from("/a"), Ok(())),
+                (
+                    secret.clone(),
+                    Err(Error::Timeout(secret.clone(), Duration::from_secs(5))),
+                ),
+            ],
+            ..Default::default()
+        };
+
+        let metrics = OperationMetrics::from_outcome(
+            OperationKind::Customize,
+            &outcome,
+            Duration::from_millis(1500),
+        );
+        assert_eq!((metrics.succeeded, metrics.failed), (1, 1));
+        assert_eq!(metrics.duration_ms, 1500);
+        assert_eq!(metrics.error_kinds.get("timeout"), Some(&1));
+
+        let json = serde_json::to_string(&metrics).unwrap();
+        assert!(!json.contains("Secret"));
+    }
+}
@@ -0,0 +1,210 @@
+//! Throttling for large batch operations.
+//!
+//! Stamping tens of thousands of folders on a network share as fast as
+//! possible can overload the file server and trigger rescans in sync clients.
+//! [`ThrottleConfig`] limits how quickly batch operations apply icons.
+
+use std::time::{Duration, Instant};
+
+/// Configuration for throttling batch operations.
+///
+/// The default configuration performs no throttling.
+///
+/// # Example
+///
+/// ```
+/// use folco_core::ThrottleConfig;
+/// use std::time::Duration;
+///
+/// // At most 20 folders per second, with a 2 second break every 500 folders
+/// let throttle = ThrottleConfig::new()
+///     .with_max_per_second(20.0)
+///     .with_pause(500, Duration::from_secs(2));
+/// ```
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ThrottleConfig {
+    /// Maximum number of folders processed per second, if limited.
+    ///
+    /// Values that aren't positive numbers don't limit the rate, and rates
+    /// too small to wait out are treated as waiting forever.
+    pub max_per_second: Option<f64>,
+    /// Number of folders to process between pauses, if pausing is enabled.
+    pub pause_every: Option<usize>,
+    /// How long to pause after every `pause_every` folders.
+    pub pause_duration: Duration,
+}
+
+impl ThrottleConfig {
+    /// Creates a configuration that performs no throttling.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Limits processing to at most `max_per_second` folders per second.
+    ///
+    /// Non-positive values disable the rate limit.
+    pub fn with_max_per_second(mut self, max_per_second: f64) -> Self {
+        self.max_per_second = (max_per_second > 0.0).then_some(max_per_second);
+        self
+    }
+
+    /// Pauses for `duration` after every `every` folders.
+    ///
+    /// An `every` of zero disables pausing.
+    pub fn with_pause(mut self, every: usize, duration: Duration) -> Self {
+        self.pause_every = (every > 0).then_some(every);
+        self.pause_duration = duration;
+        self
+    }
+
+    /// Returns `true` if this configuration throttles at all.
+    pub fn is_enabled(&self) -> bool {
+        self.rate().is_some() || (self.pause_every.is_some() && !self.pause_duration.is_zero())
+    }
+
+    /// The rate limit, if it's a positive number.
+    fn rate(&self) -> Option<f64> {
+        self.max_per_second.filter(|rate| *rate > 0.0)
+    }
+
+    /// Minimum interval between two consecutive folders.
+    fn min_interval(&self) -> Duration {
+        self.rate()
+            .map(|rate| Duration::try_from_secs_f64(1.0 / rate).unwrap_or(Duration::MAX))
+            .unwrap_or(Duration::ZERO)
+    }
+}
+
+/// Per-batch throttling state.
+///
+/// Call [`Throttle::delay`] before processing each folder and wait for the
+/// returned duration.
+#[derive(Debug)]
+pub(crate) struct Throttle {
+    config: ThrottleConfig,
+    /// When the last folder was allowed, and how long after that the next
+    /// one has to wait.
+    last: Option<(Instant, Duration)>,
+    processed: usize,
+}
+
+impl Throttle {
+    /// Creates throttling state for a new batch.
+    pub(crate) fn new(config: ThrottleConfig) -> Self {
+        Self {
+            config,
+            last: None,
+            processed: 0,
+        }
+    }
+
+    /// Returns how long to wait before processing the next folder.
+    ///
+    /// The caller is expected to wait for the full duration; the state is
+    /// updated as if it had.
+    pub(crate) fn delay(&mut self) -> Duration {
+        if !self.config.is_enabled() {
+            return Duration::ZERO;
+        }
+
+        let now = Instant::now();
+        let mut delay = self
+            .last
+            .map(|(at, wait)| wait.saturating_sub(now.saturating_duration_since(at)))
+            .unwrap_or(Duration::ZERO);
+
+        let pause_due = self
+            .config
+            .pause_every
+            .is_some_and(|every| self.processed > 0 && self.processed.is_multiple_of(every));
+        if pause_due {
+            delay = delay.saturating_add(self.config.pause_duration);
+        }
+
+        self.processed += 1;
+        self.last = self
+            .config
+            .rate()
+            .map(|_| (now, delay.saturating_add(self.config.min_interval())));
+        delay
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_default_is_disabled() {
+        let config = ThrottleConfig::new();
+        assert!(!config.is_enabled());
+
+        let mut throttle = Throttle::new(config);
+        for _ in 0..10 {
+            assert_eq!(throttle.delay(), Duration::ZERO);
+        }
+    }
+
+    #[test]
+    fn test_invalid_values_disable_throttling() {
+        let config = ThrottleConfig::new()
+            .with_max_per_second(0.0)
+            .with_pause(0, Duration::from_secs(1));
+        assert!(!config.is_enabled());
+    }
+
+    #[test]
+    fn test_unusable_rates_set_directly_do_not_panic() {
+        for rate in [0.0, -3.0, f64::NAN] {
+            let config = ThrottleConfig {
+                max_per_second: Some(rate),
+                ..ThrottleConfig::default()
+            };
+            assert!(!config.is_enabled());
+            assert_eq!(Throttle::new(config).delay(), Duration::ZERO);
+        }
+
+        let config = ThrottleConfig {
+            max_per_second: Some(f64::MIN_POSITIVE),
+            ..ThrottleConfig::default()
+        };
+        let mut throttle = Throttle::new(config);
+        assert_eq!(throttle.delay(), Duration::ZERO);
+        assert!(throttle.delay() > Duration::from_secs(3600));
+    }
+
+    #[test]
+    fn test_rate_limit_spaces_folders() {
+        let config = ThrottleConfig::new().with_max_per_second(10.0);
+        let mut throttle = Throttle::new(config);
+
+        // The first folder never waits
+        assert_eq!(throttle.delay(), Duration::ZERO);
+
+        // An immediate second folder waits for (almost) the full interval
+        let delay = throttle.delay();
+        assert!(delay > Duration::from_millis(50));
+        assert!(delay <= Duration::from_millis(100));
+    }
+
+    #[test]
+    fn test_pause_every_n_folders() {
+        let pause = Duration::from_millis(250);
+        let config = ThrottleConfig::new().with_pause(3, pause);
+        let mut throttle = Throttle::new(config);
+
+        let delays: Vec<Duration> = (0..7).map(|_| throttle.delay()).collect();
+        assert_eq!(
+            delays,
+            vec![
+                Duration::ZERO,
+                Duration::ZERO,
+                Duration::ZERO,
+                pause,
+                Duration::ZERO,
+                Duration::ZERO,
+                pause,
+            ]
+        );
+    }
+}
@@ -0,0 +1,235 @@
+//! Cached preview thumbnails for presets.
+//!
+//! A thumbnail is rendered once when a preset is saved and kept as a PNG
+//! blob in the [`StateStore`]. It and the other sizes are written out as
+//! files in the app data directory on first request, so a preset gallery
+//! can load without rendering anything. With the JSON backend, the blob
+//! already is the base file. A cache without a directory, as render-only
+//! contexts use, writes no files and hands out PNG bytes instead.
+
+use crate::error::Result;
+use crate::profile::fnv1a_64;
+use crate::state::StateStore;
+
+use folco_renderer::IconSet as RendererIconSet;
+use image::imageops::{self, FilterType};
+use image::{ImageFormat, RgbaImage};
+
+use std::fs;
+use std::io::Cursor;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+/// Directory name for preset thumbnails inside the app data directory.
+pub(crate) const THUMBNAILS_DIR_NAME: &str = "preset_thumbnails";
+
+/// Size of the thumbnail rendered when a preset is saved. Requested sizes are
+/// scaled from it.
+pub(crate) const BASE_THUMBNAIL_SIZE: u32 = 256;
+
+/// Thumbnails for presets, keyed by preset name.
+#[derive(Debug, Clone)]
+pub(crate) struct ThumbnailCache {
+    state: Arc<dyn StateStore>,
+    dir: Option<PathBuf>,
+}
+
+impl ThumbnailCache {
+    pub(crate) fn new(state: Arc<dyn StateStore>, data_dir: &Path) -> Self {
+        Self {
+            state,
+            dir: Some(data_dir.join(THUMBNAILS_DIR_NAME)),
+        }
+    }
+
+    /// Creates a cache that keeps thumbnails in `state` only, writing no
+    /// files.
+    pub(crate) fn in_memory(state: Arc<dyn StateStore>) -> Self {
+        Self { state, dir: None }
+    }
+
+    /// Returns `true` if thumbnails are written out as files.
+    pub(crate) fn has_files(&self) -> bool {
+        self.dir.is_some()
+    }
+
+    /// Stores the base thumbnail for a preset, dropping any files of the
+    /// previous one.
+    pub(crate) fn store(&self, name: &str, image: &RgbaImage) -> Result<()> {
+        self.remove(name)?;
+        let mut png = Vec::new();
+        image.write_to(&mut Cursor::new(&mut png), ImageFormat::Png)?;
+        self.state.write_blob(&blob_key(name), &png)
+    }
+
+    /// Returns the thumbnail file of a preset at `size`, writing it from
+    /// the stored base thumbnail if needed.
+    ///
+    /// Returns `None` if no base thumbnail was stored for the preset, or if
+    /// the cache writes no files.
+    pub(crate) fn get(&self, name: &str, size: u32) -> Result<Option<PathBuf>> {
+        let Some(dir) = &self.dir else {
+            return Ok(None);
+        };
+        let path = if size == BASE_THUMBNAIL_SIZE {
+            self.base_path(dir, name)
+        } else {
+            self.sized_path(dir, name, size)
+        };
+        if path.exists() {
+            return Ok(Some(path));
+        }
+
+        let Some(base) = self.base(name)? else {
+            return Ok(None);
+        };
+        fs::create_dir_all(dir)?;
+        scaled(&base, size).save(&path)?;
+        Ok(Some(path))
+    }
+
+    /// Returns the thumbnail of a preset at `size` as PNG bytes, scaled from
+    /// the stored base thumbnail without writing any files.
+    ///
+    /// Returns `None` if no base thumbnail was stored for the preset.
+    pub(crate) fn png(&self, name: &str, size: u32) -> Result<Option<Vec<u8>>> {
+        match self.base(name)? {
+            Some(base) => encode_thumbnail(&base, size).map(Some),
+            None => Ok(None),
+        }
+    }
+
+    /// Removes every thumbnail of a preset.
+    pub(crate) fn remove(&self, name: &str) -> Result<()> {
+        self.state.remove_blob(&blob_key(name))?;
+        let Some(Ok(entries)) = self.dir.as_ref().map(fs::read_dir) else {
+            return Ok(());
+        };
+        let prefix = file_stem(name);
+        for entry in entries.flatten() {
+            let file_name = entry.file_name();
+            let file_name = file_name.to_string_lossy();
+            if file_name == format!("{prefix}.png") || file_name.starts_with(&format!("{prefix}_")) {
+                fs::remove_file(entry.path())?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Reads the stored base thumbnail of a preset.
+    fn base(&self, name: &str) -> Result<Option<RgbaImage>> {
+        let Some(png) = self.state.read_blob(&blob_key(name))? else {
+            return Ok(None);
+        };
+        Ok(Some(image::load_from_memory_with_format(&png, ImageFormat::Png)?.to_rgba8()))
+    }
+
+    fn base_path(&self, dir: &Path, name: &str) -> PathBuf {
+        dir.join(format!("{}.png", file_stem(name)))
+    }
+
+    fn sized_path(&self, dir: &Path, name: &str, size: u32) -> PathBuf {
+        dir.join(format!("{}_{size}.png", file_stem(name)))
+    }
+}
+
+/// Returns a base thumbnail scaled to `size`.
+fn scaled(base: &RgbaImage, size: u32) -> RgbaImage {
+    if size == BASE_THUMBNAIL_SIZE {
+        base.clone()
+    } else {
+        imageops::resize(base, size, size, FilterType::Lanczos3)
+    }
+}
+
+/// Encodes a base thumbnail scaled to `size` as PNG.
+pub(crate) fn encode_thumbnail(base: &RgbaImage, size: u32) -> Result<Vec<u8>> {
+    let mut png = Vec::new();
+    scaled(base, size).write_to(&mut Cursor::new(&mut png), ImageFormat::Png)?;
+    Ok(png)
+}
+
+/// Returns a file name stem for a preset. Preset names can contain any
+/// characters, so they're hashed.
+fn file_stem(name: &str) -> String {
+    format!("{:016x}", fnv1a_64(name.as_bytes()))
+}
+
+/// Returns the blob key of a preset's base thumbnail. It's relative to the
+/// app data directory, so the JSON backend stores it as the base file.
+fn blob_key(name: &str) -> String {
+    format!("{THUMBNAILS_DIR_NAME}/{}.png", file_stem(name))
+}
+
+/// Picks the rendered image to use as a base thumbnail and scales it to
+/// [`BASE_THUMBNAIL_SIZE`].
+///
+/// The smallest image at least as large as the thumbnail is preferred, so
+/// it's only ever scaled down; otherwise the largest is scaled up.
+pub(crate) fn thumbnail_from_icons(icons: &RendererIconSet) -> Option<RgbaImage> {
+    let images: Vec<&RgbaImage> = icons.iter().map(|image| &image.data).collect();
+    let source = images
+        .iter()
+        .filter(|image| image.width() >= BASE_THUMBNAIL_SIZE)
+        .min_by_key(|image| image.width())
+        .or_else(|| images.iter().max_by_key(|image| image.width()))?;
+
+    if source.width() == BASE_THUMBNAIL_SIZE && source.height() == BASE_THUMBNAIL_SIZE {
+        return Some((*source).clone());
+    }
+    Some(imageops::resize(
+        *source,
+        BASE_THUMBNAIL_SIZE,
+        BASE_THUMBNAIL_SIZE,
+        FilterType::Lanczos3,
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::state::{JsonStateStore, MemoryStateStore};
+    use tempfile::tempdir;
+
+    fn solid(size: u32) -> RgbaImage {
+        RgbaImage::from_pixel(size, size, image::Rgba([40, 120, 200, 255]))
+    }
+
+    #[test]
+    fn test_store_and_scale() {
+        let temp = tempdir().unwrap();
+        let state = Arc::new(JsonStateStore::new(temp.path()));
+        let cache = ThumbnailCache::new(state, temp.path());
+        assert!(cache.get("Work", 64).unwrap().is_none());
+
+        cache.store("Work", &solid(BASE_THUMBNAIL_SIZE)).unwrap();
+        let path = cache.get("Work", 64).unwrap().unwrap();
+        assert_eq!(image::open(&path).unwrap().width(), 64);
+    }
+
+    #[test]
+    fn test_store_drops_scaled_copies() {
+        let temp = tempdir().unwrap();
+        let state = Arc::new(JsonStateStore::new(temp.path()));
+        let cache = ThumbnailCache::new(state, temp.path());
+        cache.store("Work", &solid(BASE_THUMBNAIL_SIZE)).unwrap();
+        let scaled = cache.get("Work", 32).unwrap().unwrap();
+
+        cache.store("Work", &solid(BASE_THUMBNAIL_SIZE)).unwrap();
+        assert!(!scaled.exists());
+
+        cache.remove("Work").unwrap();
+        assert!(cache.get("Work", BASE_THUMBNAIL_SIZE).unwrap().is_none());
+    }
+
+    #[test]
+    fn test_in_memory_cache_hands_out_bytes() {
+        let cache = ThumbnailCache::in_memory(Arc::new(MemoryStateStore::new()));
+        assert!(cache.png("Work", 64).unwrap().is_none());
+
+        cache.store("Work", &solid(BASE_THUMBNAIL_SIZE)).unwrap();
+        assert!(cache.get("Work", 64).unwrap().is_none());
+        let png = cache.png("Work", 64).unwrap().unwrap();
+        assert_eq!(image::load_from_memory(&png).unwrap().width(), 64);
+    }
+}
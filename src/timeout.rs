@@ -0,0 +1,133 @@
+//! Timeouts for blocking per-folder operations.
+//!
+//! Filesystem calls against a hung network mount can block indefinitely and
+//! can't be cancelled. These helpers run the operation on its own thread and
+//! stop waiting for it once the timeout elapses, so the rest of a batch can
+//! continue. A timed-out operation keeps running in the background until the
+//! underlying call returns.
+
+use crate::error::{Error, Result};
+
+use std::path::Path;
+use std::sync::mpsc::{self, RecvTimeoutError};
+use std::thread;
+use std::time::Duration;
+
+/// Runs `op`, giving up with [`Error::Timeout`] after `timeout`.
+///
+/// With no timeout, `op` runs on the current thread.
+pub(crate) fn run_with_timeout<F>(path: &Path, timeout: Option<Duration>, op: F) -> Result<()>
+where
+    F: FnOnce() -> Result<()> + Send + 'static,
+{
+    let Some(timeout) = timeout else {
+        return op();
+    };
+
+    let (tx, rx) = mpsc::channel();
+    let handle = thread::spawn(move || {
+        let _ = tx.send(op());
+    });
+
+    match rx.recv_timeout(timeout) {
+        Ok(result) => result,
+        Err(RecvTimeoutError::Timeout) => Err(Error::Timeout(path.to_path_buf(), timeout)),
+        // The sender was dropped without sending, so `op` panicked
+        Err(RecvTimeoutError::Disconnected) => match handle.join() {
+            Err(payload) => std::panic::resume_unwind(payload),
+            Ok(()) => unreachable!("operation finished without sending a result"),
+        },
+    }
+}
+
+/// Async version of [`run_with_timeout`].
+///
+/// The operation always runs on a dedicated thread, with or without a
+/// timeout, so that it never blocks the async runtime.
+pub(crate) async fn run_with_timeout_async<F>(
+    path: &Path,
+    timeout: Option<Duration>,
+    op: F,
+) -> Result<()>
+where
+    F: FnOnce() -> Result<()> + Send + 'static,
+{
+    let (tx, rx) = tokio::sync::oneshot::channel();
+    let handle = thread::spawn(move || {
+        let _ = tx.send(op());
+    });
+
+    let received = match timeout {
+        Some(timeout) => match tokio::time::timeout(timeout, rx).await {
+            Ok(received) => received,
+            Err(_) => return Err(Error::Timeout(path.to_path_buf(), timeout)),
+        },
+        None => rx.await,
+    };
+    match received {
+        Ok(result) => result,
+        // The sender was dropped without sending, so `op` panicked
+        Err(_) => match handle.join() {
+            Err(payload) => std::panic::resume_unwind(payload),
+            Ok(()) => unreachable!("operation finished without sending a result"),
+        },
+    }
+}
+
+/// Runs `op` on a dedicated thread and waits for it without blocking the
+/// async runtime, such as for hooks that call into app code.
+pub(crate) async fn run_blocking_async<F, T>(op: F) -> T
+where
+    F: FnOnce() -> T + Send + 'static,
+    T: Send + 'static,
+{
+    let (tx, rx) = tokio::sync::oneshot::channel();
+    let handle = thread::spawn(move || {
+        let _ = tx.send(op());
+    });
+    match rx.await {
+        Ok(value) => value,
+        // The sender was dropped without sending, so `op` panicked
+        Err(_) => match handle.join() {
+            Err(payload) => std::panic::resume_unwind(payload),
+            Ok(()) => unreachable!("operation finished without sending a result"),
+        },
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::PathBuf;
+
+    #[test]
+    fn test_no_timeout_runs_inline() {
+        let result = run_with_timeout(Path::new("/tmp"), None, || Ok(()));
+        assert!(result.is_ok());
+    }
+
+    #[test]
+    fn test_fast_operation_completes() {
+        let result = run_with_timeout(Path::new("/tmp"), Some(Duration::from_secs(5)), || {
+            Err(Error::FolderReset(PathBuf::from("/tmp"), "boom".to_string()))
+        });
+        assert!(matches!(result, Err(Error::FolderReset(_, _))));
+    }
+
+    #[test]
+    fn test_slow_operation_times_out() {
+        let timeout = Duration::from_millis(50);
+        let result = run_with_timeout(Path::new("/mnt/hung"), Some(timeout), || {
+            thread::sleep(Duration::from_secs(2));
+            Ok(())
+        });
+
+        match result {
+            Err(Error::Timeout(path, elapsed)) => {
+                assert_eq!(path, PathBuf::from("/mnt/hung"));
+                assert_eq!(elapsed, timeout);
+            }
+            other => panic!("expected timeout, got {:?}", other),
+        }
+    }
+}
@@ -0,0 +1,225 @@
+//! Removing folco's traces from a system.
+//!
+//! [`CustomizationContext::uninstall_cleanup`](crate::CustomizationContext::uninstall_cleanup)
+//! backs both a GUI's uninstall flow and a CLI `purge` command: it can reset
+//! every folder in the profile store, then delete the app data and icon
+//! cache, and reports anything left behind. folco-core registers no shell
+//! integrations or scheduled tasks itself; frontends that add them remove
+//! their own after this returns.
+//!
+//! Only the files folco writes are deleted from the app data directory, so
+//! anything else a user or another app keeps there survives an uninstall,
+//! along with the directory itself.
+
+use crate::batch::BatchOutcome;
+use crate::checkpoint::CHECKPOINTS_FILE_NAME;
+use crate::config::CONFIG_FILE_NAME;
+use crate::error::Result;
+use crate::log::LOG_FILE_NAME;
+use crate::presets::PRESETS_FILE_NAME;
+use crate::store::STORE_FILE_NAME;
+use crate::thumbnails::THUMBNAILS_DIR_NAME;
+
+use serde::{Deserialize, Serialize};
+
+use std::fs;
+use std::io::ErrorKind;
+use std::path::{Path, PathBuf};
+
+/// Names of the files and directories folco keeps in its data directory.
+const APP_DATA_NAMES: &[&str] = &[
+    STORE_FILE_NAME,
+    PRESETS_FILE_NAME,
+    CONFIG_FILE_NAME,
+    CHECKPOINTS_FILE_NAME,
+    LOG_FILE_NAME,
+    THUMBNAILS_DIR_NAME,
+];
+
+/// Names of the files folco keeps in its data directory in builds with
+/// optional storage backends.
+#[cfg(feature = "storage-sqlite")]
+const OPTIONAL_APP_DATA_NAMES: &[&str] = &[crate::sqlite::SQLITE_FILE_NAME];
+#[cfg(not(feature = "storage-sqlite"))]
+const OPTIONAL_APP_DATA_NAMES: &[&str] = &[];
+
+/// Words of the suffixes folco adds to its file names, such as the journal
+/// of a state file or a temporary file it writes before renaming.
+const APP_DATA_SUFFIXES: &[&str] = &["wal", "tmp", "bak", "shm", "journal"];
+
+/// What [`uninstall_cleanup`](crate::CustomizationContext::uninstall_cleanup)
+/// does.
+///
+/// The default does nothing but report what's there, as a dry run.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct UninstallOptions {
+    /// Resets every folder recorded in the profile store to the default
+    /// icon.
+    pub reset_folders: bool,
+    /// Deletes the profile store, presets, config, thumbnails and icon
+    /// cache.
+    pub remove_app_data: bool,
+    /// Deletes the app data even if some folders couldn't be reset, which
+    /// forgets that folco customized them.
+    pub force: bool,
+}
+
+impl UninstallOptions {
+    /// Creates options that change nothing.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Resets every customized folder, and deletes all app data.
+    pub fn purge() -> Self {
+        Self::new()
+            .with_reset_folders(true)
+            .with_remove_app_data(true)
+    }
+
+    /// Sets whether customized folders are reset.
+    pub fn with_reset_folders(mut self, reset: bool) -> Self {
+        self.reset_folders = reset;
+        self
+    }
+
+    /// Sets whether the app data is deleted.
+    pub fn with_remove_app_data(mut self, remove: bool) -> Self {
+        self.remove_app_data = remove;
+        self
+    }
+
+    /// Sets whether the app data is deleted even when folders remain
+    /// customized.
+    pub fn with_force(mut self, force: bool) -> Self {
+        self.force = force;
+        self
+    }
+}
+
+/// What [`uninstall_cleanup`](crate::CustomizationContext::uninstall_cleanup)
+/// did, and what remains.
+#[derive(Debug, Default)]
+pub struct UninstallReport {
+    /// The outcome of resetting the recorded folders.
+    pub reset: BatchOutcome,
+    /// Recorded folders that no longer exist, which were forgotten rather
+    /// than reset.
+    pub forgotten: Vec<PathBuf>,
+    /// Files and directories that were deleted.
+    pub removed: Vec<PathBuf>,
+    /// Folders that are still customized, because they weren't reset or
+    /// resetting them failed.
+    pub remaining_folders: Vec<PathBuf>,
+    /// Files and directories left behind, such as app data that was kept
+    /// or the administrator's policy file.
+    pub remaining_paths: Vec<PathBuf>,
+}
+
+impl UninstallReport {
+    /// Returns `true` if nothing of folco's remains.
+    pub fn is_complete(&self) -> bool {
+        self.remaining_folders.is_empty() && self.remaining_paths.is_empty()
+    }
+}
+
+/// Deletes folco's own files from `data_dir`, then `data_dir` itself if
+/// nothing else is left in it. Returns what was deleted.
+pub(crate) fn remove_app_files(data_dir: &Path) -> Result<Vec<PathBuf>> {
+    let entries = match fs::read_dir(data_dir) {
+        Ok(entries) => entries,
+        Err(e) if e.kind() == ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(e) => return Err(e.into()),
+    };
+    let mut removed = Vec::new();
+    for entry in entries {
+        let entry = entry?;
+        if !is_app_file(&entry.file_name().to_string_lossy()) {
+            continue;
+        }
+        let path = entry.path();
+        if entry.file_type()?.is_dir() {
+            fs::remove_dir_all(&path)?;
+        } else {
+            fs::remove_file(&path)?;
+        }
+        removed.push(path);
+    }
+    if fs::read_dir(data_dir)?.next().is_none() {
+        fs::remove_dir(data_dir)?;
+        removed.push(data_dir.to_path_buf());
+    }
+    Ok(removed)
+}
+
+/// Returns `true` if `name` is one of folco's files, possibly with
+/// suffixes folco adds, e.g. `profiles.json.wal`, `folco.log.2` or
+/// `config.json.v1.bak`.
+fn is_app_file(name: &str) -> bool {
+    APP_DATA_NAMES.iter().chain(OPTIONAL_APP_DATA_NAMES).any(|own| {
+        let Some(suffix) = name.strip_prefix(own) else {
+            return false;
+        };
+        let Some(words) = suffix.strip_prefix(['.', '-']) else {
+            return suffix.is_empty();
+        };
+        words.split(['.', '-']).all(|word| {
+            let number = word.strip_prefix('v').unwrap_or(word);
+            APP_DATA_SUFFIXES.contains(&word)
+                || (!number.is_empty() && number.bytes().all(|b| b.is_ascii_digit()))
+        })
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    #[test]
+    fn test_options() {
+        let dry_run = UninstallOptions::new();
+        assert!(!dry_run.reset_folders && !dry_run.remove_app_data);
+
+        let purge = UninstallOptions::purge();
+        assert!(purge.reset_folders && purge.remove_app_data && !purge.force);
+    }
+
+    #[test]
+    fn test_app_files() {
+        for name in ["profiles.json", "profiles.json.wal", "folco.log.2", "config.json.v1.bak"] {
+            assert!(is_app_file(name), "{name}");
+        }
+        for name in ["profiles.json.old", "notes.txt", "folco.logs", "presets.json."] {
+            assert!(!is_app_file(name), "{name}");
+        }
+    }
+
+    #[test]
+    fn test_remove_app_files_keeps_other_files() {
+        let temp = tempdir().unwrap();
+        let data_dir = temp.path().join("data");
+        fs::create_dir_all(data_dir.join(THUMBNAILS_DIR_NAME)).unwrap();
+        fs::write(data_dir.join(STORE_FILE_NAME), "{}").unwrap();
+        fs::write(data_dir.join("notes.txt"), "mine").unwrap();
+
+        let removed = remove_app_files(&data_dir).unwrap();
+        assert_eq!(removed.len(), 2);
+        assert!(data_dir.join("notes.txt").exists());
+
+        fs::remove_file(data_dir.join("notes.txt")).unwrap();
+        assert_eq!(remove_app_files(&data_dir).unwrap(), [data_dir.clone()]);
+        assert!(!data_dir.exists());
+    }
+
+    #[test]
+    fn test_report_completeness() {
+        let mut report = UninstallReport::default();
+        assert!(report.is_complete());
+        report
+            .remaining_paths
+            .push(PathBuf::from("/etc/folco/policy.json"));
+        assert!(!report.is_complete());
+    }
+}
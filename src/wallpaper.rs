@@ -0,0 +1,268 @@
+//! Folder palettes suggested from the desktop wallpaper.
+//!
+//! [`suggest_palette_from_wallpaper`] finds the wallpaper's dominant color,
+//! snaps it to the closest [`FolderColor`] preset and hands that to the
+//! harmony generator, so a user can color their folders to match their
+//! desktop in one click. The dominant color is the average of the most
+//! prominent hue among the wallpaper's colorful pixels; wallpapers with
+//! almost no color get a neutral suggestion instead, which has no
+//! harmonies.
+
+use crate::about::{command_output, system_theme, SystemTheme};
+use crate::color::{is_neutral, FolderColor, Oklch, Palette};
+use crate::error::{Error, Result};
+use crate::oklch::{from_linear, srgb_to_hsl, to_linear};
+
+use image::DynamicImage;
+use serde::{Deserialize, Serialize};
+
+use std::path::{Path, PathBuf};
+
+/// Size the wallpaper is scaled down to before sampling.
+const SAMPLE_SIZE: u32 = 64;
+
+/// Number of hue buckets colorful pixels are sorted into.
+const HUE_BINS: usize = 12;
+
+/// Minimum saturation of a pixel counted as colorful.
+const MIN_SATURATION: f32 = 0.25;
+
+/// Share of the wallpaper, weighted by saturation, the most prominent hue
+/// must cover to be used over the overall average.
+const MIN_COLORFUL_SHARE: f32 = 0.05;
+
+/// A folder palette suggested from an image.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct WallpaperPalette {
+    /// The image the palette was derived from.
+    pub wallpaper: PathBuf,
+    /// The image's dominant color in sRGB.
+    pub dominant: [u8; 3],
+    /// The preset closest to the dominant color.
+    pub base: FolderColor,
+    /// Every harmony of `base`, empty if it's a neutral color.
+    pub palettes: Vec<Palette>,
+}
+
+/// Returns the path of the current desktop wallpaper, if it's an image file.
+///
+/// Reads the Windows desktop settings, asks System Events on macOS, and
+/// reads the GNOME background settings elsewhere, preferring the dark
+/// variant while a dark theme is on.
+pub fn wallpaper_path() -> Option<PathBuf> {
+    let path = if cfg!(target_os = "windows") {
+        let output = command_output(
+            "reg",
+            &["query", r"HKCU\Control Panel\Desktop", "/v", "WallPaper"],
+        )?;
+        parse_windows_wallpaper(&output)?
+    } else if cfg!(target_os = "macos") {
+        let output = command_output(
+            "osascript",
+            &[
+                "-e",
+                r#"tell application "System Events" to get picture of current desktop"#,
+            ],
+        )?;
+        PathBuf::from(output)
+    } else {
+        let gnome_uri = |key| {
+            command_output("gsettings", &["get", "org.gnome.desktop.background", key])
+                .and_then(|output| file_uri_to_path(&output))
+        };
+        let dark = (system_theme() == SystemTheme::Dark)
+            .then(|| gnome_uri("picture-uri-dark"))
+            .flatten();
+        dark.or_else(|| gnome_uri("picture-uri"))?
+    };
+    path.is_file().then_some(path)
+}
+
+/// Suggests folder palettes matching the current desktop wallpaper.
+///
+/// # Errors
+///
+/// Returns [`Error::Wallpaper`] if the wallpaper can't be found, such as
+/// with a solid color background, and [`Error::Image`] if it can't be
+/// decoded.
+pub fn suggest_palette_from_wallpaper() -> Result<WallpaperPalette> {
+    let path = wallpaper_path()
+        .ok_or_else(|| Error::Wallpaper("no wallpaper image is set".to_string()))?;
+    suggest_palette_from_image(&path)
+}
+
+/// Suggests folder palettes matching the image at `path`.
+///
+/// # Errors
+///
+/// Returns [`Error::Image`] if the image can't be read or decoded.
+pub fn suggest_palette_from_image(path: &Path) -> Result<WallpaperPalette> {
+    let image = image::open(path)?;
+    let (dominant, colorful) = dominant_color(&image);
+    let base = closest_preset(dominant, colorful);
+    Ok(WallpaperPalette {
+        wallpaper: path.to_path_buf(),
+        dominant,
+        base,
+        palettes: Palette::harmonies(base),
+    })
+}
+
+/// Returns the dominant color of `image`, and whether it's from the
+/// image's colorful pixels rather than its overall average.
+fn dominant_color(image: &DynamicImage) -> ([u8; 3], bool) {
+    let sample = image.thumbnail(SAMPLE_SIZE, SAMPLE_SIZE).to_rgb8();
+    let total = (sample.width() * sample.height()).max(1) as f32;
+
+    // Colors are averaged in linear light, weighted by saturation
+    let mut bins = [([0.0f32; 3], 0.0f32); HUE_BINS];
+    let mut overall = [0.0f32; 3];
+    for pixel in sample.pixels() {
+        let srgb = pixel.0.map(|c| f32::from(c) / 255.0);
+        let linear = srgb.map(to_linear);
+        for (sum, channel) in overall.iter_mut().zip(linear) {
+            *sum += channel;
+        }
+
+        let (hue, saturation, lightness) = srgb_to_hsl(srgb);
+        if saturation < MIN_SATURATION || !(0.15..=0.85).contains(&lightness) {
+            continue;
+        }
+        let (sum, weight) = &mut bins[(hue / 360.0 * HUE_BINS as f32) as usize % HUE_BINS];
+        for (sum, channel) in sum.iter_mut().zip(linear) {
+            *sum += channel * saturation;
+        }
+        *weight += saturation;
+    }
+
+    let (sum, weight) = bins
+        .into_iter()
+        .max_by(|a, b| a.1.total_cmp(&b.1))
+        .unwrap_or_default();
+    let colorful = weight >= MIN_COLORFUL_SHARE * total;
+    let linear = if colorful {
+        sum.map(|c| c / weight)
+    } else {
+        overall.map(|c| c / total)
+    };
+    let rgb = linear.map(|c| (from_linear(c.clamp(0.0, 1.0)) * 255.0).round() as u8);
+    (rgb, colorful)
+}
+
+/// Returns the preset perceptually closest to `rgb`, only considering
+/// chromatic presets if `colorful`.
+fn closest_preset(rgb: [u8; 3], colorful: bool) -> FolderColor {
+    let color = Oklch::from_linear_srgb(rgb.map(|c| to_linear(f32::from(c) / 255.0)));
+    let distance = |preset: &FolderColor| color.delta_e(&preset.target_oklch());
+    FolderColor::all()
+        .iter()
+        .copied()
+        .filter(|preset| *preset != FolderColor::SystemAccent)
+        .filter(|preset| !colorful || !is_neutral(*preset))
+        .min_by(|a, b| distance(a).total_cmp(&distance(b)))
+        .unwrap_or(FolderColor::Grey)
+}
+
+/// Parses `reg query` output for the `WallPaper` string, which is empty
+/// for a solid color background.
+fn parse_windows_wallpaper(output: &str) -> Option<PathBuf> {
+    let line = output.lines().find(|line| line.contains("WallPaper"))?;
+    let (_, value) = line.split_once("REG_SZ")?;
+    let value = value.trim();
+    (!value.is_empty()).then(|| PathBuf::from(value))
+}
+
+/// Converts a quoted `file://` URI, as `gsettings` prints it, to a path.
+fn file_uri_to_path(output: &str) -> Option<PathBuf> {
+    let path = output.trim().trim_matches('\'').strip_prefix("file://")?;
+    let bytes = path.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        let escaped = (bytes[i] == b'%')
+            .then(|| path.get(i + 1..i + 3))
+            .flatten()
+            .and_then(|hex| u8::from_str_radix(hex, 16).ok());
+        match escaped {
+            Some(byte) => {
+                decoded.push(byte);
+                i += 3;
+            }
+            None => {
+                decoded.push(bytes[i]);
+                i += 1;
+            }
+        }
+    }
+    String::from_utf8(decoded).ok().map(PathBuf::from)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use image::{Rgb, RgbImage};
+    use tempfile::tempdir;
+
+    /// The Red preset, HSL(4°, 90%, 58%), in sRGB.
+    const RED: [u8; 3] = [244, 67, 54];
+
+    /// The Grey preset in sRGB.
+    const GREY: [u8; 3] = [158, 158, 158];
+
+    #[test]
+    fn test_dominant_hue_wins_over_grey() {
+        let mut image = RgbImage::from_pixel(16, 16, Rgb(GREY));
+        for x in 0..4 {
+            for y in 0..16 {
+                image.put_pixel(x, y, Rgb(RED));
+            }
+        }
+        let (dominant, colorful) = dominant_color(&DynamicImage::ImageRgb8(image));
+        assert!(colorful);
+        assert_eq!(closest_preset(dominant, colorful), FolderColor::Red);
+    }
+
+    #[test]
+    fn test_grey_wallpaper_gets_a_neutral_suggestion() {
+        let image = RgbImage::from_pixel(16, 16, Rgb(GREY));
+        let (dominant, colorful) = dominant_color(&DynamicImage::ImageRgb8(image));
+        assert!(!colorful);
+        assert_eq!(closest_preset(dominant, colorful), FolderColor::Grey);
+    }
+
+    #[test]
+    fn test_suggest_palette_from_image() {
+        let temp = tempdir().unwrap();
+        let path = temp.path().join("wallpaper.png");
+        RgbImage::from_pixel(8, 8, Rgb(RED)).save(&path).unwrap();
+
+        let suggestion = suggest_palette_from_image(&path).unwrap();
+        assert_eq!(suggestion.base, FolderColor::Red);
+        assert!(!suggestion.palettes.is_empty());
+        assert!(suggestion
+            .palettes
+            .iter()
+            .all(|palette| palette.colors[0] == FolderColor::Red));
+    }
+
+    #[test]
+    fn test_parse_windows_wallpaper() {
+        let output = "\r\nHKEY_CURRENT_USER\\Control Panel\\Desktop\r\n    \
+                      WallPaper    REG_SZ    C:\\Users\\me\\Pictures\\Lake view.jpg\r\n";
+        assert_eq!(
+            parse_windows_wallpaper(output),
+            Some(PathBuf::from(r"C:\Users\me\Pictures\Lake view.jpg"))
+        );
+        assert_eq!(parse_windows_wallpaper("    WallPaper    REG_SZ    "), None);
+    }
+
+    #[test]
+    fn test_file_uri_to_path() {
+        assert_eq!(
+            file_uri_to_path("'file:///home/me/Pictures/Lake%20view.jpg'\n"),
+            Some(PathBuf::from("/home/me/Pictures/Lake view.jpg"))
+        );
+        assert_eq!(file_uri_to_path("''"), None);
+    }
+}
@@ -0,0 +1,106 @@
+//! Problems that didn't stop an operation.
+//!
+//! Presets are shared between frontends built with different renderer
+//! builds: a profile made in folco-gui may use an effect that folco-cli's
+//! build, or a WASM build, can't draw. Rather than failing the whole batch,
+//! folco drops the settings the renderer rejects and renders the rest,
+//! reporting a [`Warning`] for each setting it dropped. Likewise, a size
+//! that fails to render on its own is left out of the icon set, and sizes
+//! the platform needs but the icons lack are scaled from the others.
+
+use crate::sanitize::SanitizeRemoval;
+
+use serde::{Deserialize, Serialize};
+
+use std::fmt;
+
+/// Something an operation worked around instead of failing.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "kebab-case")]
+pub enum Warning {
+    /// A profile setting this renderer build can't draw was left out.
+    DegradedFeature {
+        /// The profile setting that was left out, such as `"decal"`.
+        setting: String,
+        /// Why the renderer rejected it.
+        reason: String,
+    },
+    /// An icon size failed to render and was left out of the icon set.
+    PartialRender {
+        /// Width of the size that was left out, in pixels.
+        size: u32,
+        /// Why rendering it failed.
+        reason: String,
+    },
+    /// Icon sizes the platform prefers were missing, and were scaled from
+    /// the sizes there were.
+    SynthesizedSizes {
+        /// Widths of the sizes that were scaled, in pixels.
+        sizes: Vec<u32>,
+    },
+    /// Unsafe parts of an inline SVG decal were removed before rendering.
+    SanitizedSvg {
+        /// What was removed.
+        removed: Vec<SanitizeRemoval>,
+    },
+}
+
+impl fmt::Display for Warning {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Warning::DegradedFeature { setting, reason } => {
+                write!(
+                    f,
+                    "left out the {setting} setting, which this build can't render: {reason}"
+                )
+            }
+            Warning::PartialRender { size, reason } => {
+                write!(f, "left out the {size}px icon, which failed to render: {reason}")
+            }
+            Warning::SynthesizedSizes { sizes } => {
+                let sizes: Vec<String> = sizes.iter().map(|size| format!("{size}px")).collect();
+                write!(f, "scaled the missing {} icons from other sizes", sizes.join(", "))
+            }
+            Warning::SanitizedSvg { removed } => {
+                let removed: Vec<String> = removed.iter().map(ToString::to_string).collect();
+                write!(f, "sanitized an SVG decal: {}", removed.join("; "))
+            }
+        }
+    }
+}
+
+/// Adds `warning` to `warnings` unless it's already there, since the same
+/// profile may be rendered several times in a batch.
+pub(crate) fn push_unique(warnings: &mut Vec<Warning>, warning: Warning) {
+    if !warnings.contains(&warning) {
+        warnings.push(warning);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_warnings_are_deduplicated() {
+        let warning = Warning::DegradedFeature {
+            setting: "effects".to_string(),
+            reason: "needs a GPU".to_string(),
+        };
+        let mut warnings = Vec::new();
+        push_unique(&mut warnings, warning.clone());
+        push_unique(&mut warnings, warning);
+        assert_eq!(warnings.len(), 1);
+
+        let json = serde_json::to_value(&warnings[0]).unwrap();
+        assert_eq!(json["kind"], "degraded-feature");
+        assert!(warnings[0].to_string().contains("effects"));
+
+        let partial = Warning::PartialRender {
+            size: 16,
+            reason: "SVG rasterization failed".to_string(),
+        };
+        assert_eq!(serde_json::to_value(&partial).unwrap()["kind"], "partial-render");
+        assert!(partial.to_string().starts_with("left out the 16px icon"));
+    }
+}
@@ -0,0 +1,335 @@
+//! Tracking customized folders as they're moved and renamed.
+//!
+//! A [`FolderWatcher`] watches directory trees for renames and updates the
+//! [`ProfileStore`] as they happen, so records follow their folders instead of
+//! being orphaned. Moves that happen while nothing is watching are picked up
+//! later by [`reconcile_store`](crate::reconcile_store).
+//!
+//! Only available with the `watch` feature.
+
+use crate::error::Result;
+use crate::file_id::FileId;
+use crate::log::CoreLog;
+use crate::paths::normalize_folder_path;
+use crate::store::ProfileStore;
+
+use notify::event::{EventKind, ModifyKind, RenameMode};
+use notify::{Event, RecommendedWatcher, RecursiveMode, Watcher};
+
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::{self, Receiver};
+
+/// Something the watcher noticed about a folder.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum WatchEvent {
+    /// A customized folder was moved or renamed, and its store record now
+    /// points at the new location.
+    FolderMoved {
+        /// Previous path of the folder.
+        from: PathBuf,
+        /// New path of the folder.
+        to: PathBuf,
+    },
+    /// A folder without customized folders below it was renamed. The store
+    /// is unchanged.
+    FolderRenamed {
+        /// Previous path of the folder.
+        from: PathBuf,
+        /// New path of the folder.
+        to: PathBuf,
+    },
+    /// A customized folder was removed. Its record is kept, since the folder
+    /// may have been moved somewhere that isn't watched.
+    FolderRemoved {
+        /// Path of the removed folder.
+        path: PathBuf,
+    },
+    /// A new folder appeared under a watched root.
+    FolderCreated {
+        /// Path of the new folder.
+        path: PathBuf,
+    },
+    /// The watcher backend reported an error, or the store couldn't be saved.
+    Error(String),
+}
+
+/// Watches directory trees and keeps the profile store in sync with renames.
+///
+/// Events are delivered on a channel available through
+/// [`events`](Self::events). The store is updated and saved from the
+/// watcher's own thread; the events are informational.
+///
+/// # Example
+///
+/// ```ignore
+/// let watcher = ctx.watch_folders(&["/home/user/Projects"])?;
+/// for event in watcher.events() {
+///     if let WatchEvent::FolderMoved { from, to } = event {
+///         println!("{} -> {}", from.display(), to.display());
+///     }
+/// }
+/// ```
+pub struct FolderWatcher {
+    watcher: RecommendedWatcher,
+    events: Receiver<WatchEvent>,
+}
+
+impl FolderWatcher {
+    /// Creates a watcher that updates `store`. No directories are watched
+    /// until [`watch`](Self::watch) is called.
+    pub fn new(store: ProfileStore) -> Result<Self> {
+        Self::with_log(store, CoreLog::disabled())
+    }
+
+    /// Creates a watcher that updates `store` and writes every event to
+    /// `log`.
+    pub fn with_log(store: ProfileStore, log: CoreLog) -> Result<Self> {
+        let (tx, events) = mpsc::channel();
+        let mut tracker = RenameTracker::new(store);
+
+        let watcher = notify::recommended_watcher(move |res: notify::Result<Event>| {
+            let watch_events = match res {
+                Ok(event) => tracker.handle(event),
+                Err(e) => vec![WatchEvent::Error(e.to_string())],
+            };
+            for event in watch_events {
+                log_event(&log, &event);
+                // Nobody listening is fine; the store is still updated
+                let _ = tx.send(event);
+            }
+        })?;
+
+        Ok(Self { watcher, events })
+    }
+
+    /// Starts watching `root` and everything below it.
+    pub fn watch(&mut self, root: &Path) -> Result<()> {
+        self.watcher.watch(root, RecursiveMode::Recursive)?;
+        Ok(())
+    }
+
+    /// Stops watching `root`.
+    pub fn unwatch(&mut self, root: &Path) -> Result<()> {
+        self.watcher.unwatch(root)?;
+        Ok(())
+    }
+
+    /// Returns the channel on which watch events are delivered.
+    pub fn events(&self) -> &Receiver<WatchEvent> {
+        &self.events
+    }
+}
+
+/// Writes `event` to `log`, leaving out new folders, which are too common
+/// to be worth a line each.
+fn log_event(log: &CoreLog, event: &WatchEvent) {
+    match event {
+        WatchEvent::FolderMoved { from, to } => {
+            log.info(format_args!("watcher: moved {} to {}", from.display(), to.display()));
+        }
+        WatchEvent::FolderRenamed { from, to } => {
+            log.debug(format_args!("watcher: renamed {} to {}", from.display(), to.display()));
+        }
+        WatchEvent::FolderRemoved { path } => {
+            log.warn(format_args!("watcher: customized folder {} removed", path.display()));
+        }
+        WatchEvent::FolderCreated { .. } => {}
+        WatchEvent::Error(e) => log.error(format_args!("watcher: {e}")),
+    }
+}
+
+impl std::fmt::Debug for FolderWatcher {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("FolderWatcher").finish_non_exhaustive()
+    }
+}
+
+/// Turns raw filesystem events into store updates.
+///
+/// Platforms report renames differently: inotify pairs both paths in one
+/// event, Windows sends separate "from" and "to" events, and FSEvents sends
+/// one unqualified event per path. Paths that no longer exist are treated as
+/// the old name and paired with the next path that does.
+struct RenameTracker {
+    store: ProfileStore,
+    pending_from: Option<PathBuf>,
+}
+
+impl RenameTracker {
+    fn new(store: ProfileStore) -> Self {
+        Self {
+            store,
+            pending_from: None,
+        }
+    }
+
+    fn handle(&mut self, event: Event) -> Vec<WatchEvent> {
+        match event.kind {
+            EventKind::Modify(ModifyKind::Name(RenameMode::Both)) if event.paths.len() == 2 => {
+                self.moved(&event.paths[0], &event.paths[1])
+            }
+            EventKind::Modify(ModifyKind::Name(_)) => event
+                .paths
+                .iter()
+                .flat_map(|path| self.renamed_path(path))
+                .collect(),
+            EventKind::Create(_) => event
+                .paths
+                .iter()
+                .filter(|path| path.is_dir())
+                .flat_map(|path| {
+                    // A move into a watched root from outside it is reported
+                    // as a create on some platforms
+                    let adopted = self.adopt(path);
+                    if adopted.is_empty() {
+                        vec![WatchEvent::FolderCreated { path: path.clone() }]
+                    } else {
+                        adopted
+                    }
+                })
+                .collect(),
+            EventKind::Remove(_) => event
+                .paths
+                .iter()
+                .filter(|path| self.store.contains(path))
+                .map(|path| WatchEvent::FolderRemoved { path: path.clone() })
+                .collect(),
+            _ => Vec::new(),
+        }
+    }
+
+    /// Handles one side of a rename whose other side is reported separately.
+    fn renamed_path(&mut self, path: &Path) -> Vec<WatchEvent> {
+        if !path.exists() {
+            self.pending_from = Some(path.to_path_buf());
+            return Vec::new();
+        }
+        match self.pending_from.take() {
+            Some(from) => self.moved(&from, path),
+            None => self.adopt(path),
+        }
+    }
+
+    /// Remaps a stored folder that turned up at `path`, matched by identity.
+    fn adopt(&mut self, path: &Path) -> Vec<WatchEvent> {
+        let normalized = normalize_folder_path(path);
+        let previous = FileId::of(path)
+            .and_then(|id| self.store.find_by_file_id(id))
+            .filter(|old| *old != normalized && !old.exists());
+        match previous {
+            Some(old) => self.moved(&old, path),
+            None => Vec::new(),
+        }
+    }
+
+    fn moved(&mut self, from: &Path, to: &Path) -> Vec<WatchEvent> {
+        let moved = self.store.remap_tree(from, to);
+        if moved.is_empty() {
+            if !to.is_dir() {
+                return Vec::new();
+            }
+            return vec![WatchEvent::FolderRenamed {
+                from: from.to_path_buf(),
+                to: to.to_path_buf(),
+            }];
+        }
+
+        let mut events: Vec<_> = moved
+            .into_iter()
+            .map(|(from, to)| WatchEvent::FolderMoved { from, to })
+            .collect();
+        if let Err(e) = self.store.save() {
+            events.push(WatchEvent::Error(e.to_string()));
+        }
+        events
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use folco_renderer::CustomizationProfile;
+    use std::fs;
+    use tempfile::tempdir;
+
+    fn rename_event(mode: RenameMode, paths: &[&Path]) -> Event {
+        paths.iter().fold(
+            Event::new(EventKind::Modify(ModifyKind::Name(mode))),
+            |event, path| event.add_path(path.to_path_buf()),
+        )
+    }
+
+    #[test]
+    fn test_paired_rename_remaps_store() {
+        let temp = tempdir().unwrap();
+        let store = ProfileStore::in_data_dir(temp.path()).unwrap();
+        let from = temp.path().join("before");
+        let to = temp.path().join("after");
+        store.insert(&from, &CustomizationProfile::default());
+
+        let mut tracker = RenameTracker::new(store.clone());
+        let events = tracker.handle(rename_event(RenameMode::Both, &[&from, &to]));
+
+        assert_eq!(
+            events,
+            vec![WatchEvent::FolderMoved {
+                from: from.clone(),
+                to: to.clone(),
+            }]
+        );
+        assert!(store.contains(&to));
+        assert!(store.path().exists());
+    }
+
+    #[test]
+    fn test_split_rename_is_paired() {
+        let temp = tempdir().unwrap();
+        let store = ProfileStore::in_data_dir(temp.path()).unwrap();
+        let from = temp.path().join("before");
+        let to = temp.path().join("after");
+        fs::create_dir(&to).unwrap();
+        store.insert(&from, &CustomizationProfile::default());
+
+        let mut tracker = RenameTracker::new(store.clone());
+        assert!(tracker.handle(rename_event(RenameMode::From, &[&from])).is_empty());
+        let events = tracker.handle(rename_event(RenameMode::To, &[&to]));
+
+        assert_eq!(events.len(), 1);
+        assert!(store.contains(&to));
+        assert!(!store.contains(&from));
+    }
+
+    #[test]
+    fn test_unrelated_rename_is_reported() {
+        let temp = tempdir().unwrap();
+        let store = ProfileStore::in_data_dir(temp.path()).unwrap();
+        let mut tracker = RenameTracker::new(store.clone());
+        let from = temp.path().join("New folder");
+        let to = temp.path().join("Invoices");
+        fs::create_dir(&to).unwrap();
+
+        let events = tracker.handle(rename_event(RenameMode::Both, &[&from, &to]));
+        assert_eq!(events, vec![WatchEvent::FolderRenamed { from, to }]);
+        assert!(!store.path().exists());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_moved_in_folder_is_adopted_by_id() {
+        let temp = tempdir().unwrap();
+        let store = ProfileStore::in_data_dir(temp.path()).unwrap();
+        let before = temp.path().join("outside");
+        let after = temp.path().join("watched");
+        fs::create_dir(&before).unwrap();
+        store.insert(&before, &CustomizationProfile::default());
+        fs::rename(&before, &after).unwrap();
+
+        let mut tracker = RenameTracker::new(store.clone());
+        let event = Event::new(EventKind::Create(notify::event::CreateKind::Folder))
+            .add_path(after.clone());
+        let events = tracker.handle(event);
+
+        assert_eq!(events.len(), 1);
+        assert!(store.contains(&after));
+    }
+}
@@ -0,0 +1,220 @@
+//! Awareness of folders shared between Windows and WSL.
+//!
+//! Under WSL, the same folder can be reached from both sides: Windows sees a
+//! distribution's files at `\\wsl$\<distro>\...` (or `\\wsl.localhost\...`),
+//! and Linux sees Windows drives at `/mnt/<drive>/...`. Only the file manager
+//! on the side that owns the filesystem is worth customizing for, and the
+//! platform provider of the side folco runs on may not reach it:
+//!
+//! - Windows drives mounted in WSL are shown by Explorer, which reads
+//!   `desktop.ini`. The Linux provider's metadata would be invisible there,
+//!   so these folders are routed to a host executor: a Windows build of the
+//!   helper (see [`serve_helper`](crate::serve_helper)) launched through WSL
+//!   interop, which receives the translated Windows path.
+//! - Folders inside a distribution, seen from Windows, live on a filesystem
+//!   that can't carry the attributes Explorer needs, so they can't be
+//!   customized at all.
+
+use serde::{Deserialize, Serialize};
+
+use std::path::{Path, PathBuf};
+use std::sync::OnceLock;
+
+/// Where a folder lives, relative to the WSL boundary.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(
+    tag = "kind",
+    rename_all = "kebab-case",
+    rename_all_fields = "camelCase"
+)]
+pub enum PathLocation {
+    /// On the native filesystem of the side folco runs on.
+    Native,
+    /// Inside a WSL distribution, seen from Windows.
+    WslDistribution {
+        /// The distribution name, such as `Ubuntu`.
+        distro: String,
+    },
+    /// On a Windows drive, seen from WSL.
+    WindowsDrive {
+        /// The uppercase drive letter.
+        drive: char,
+    },
+}
+
+/// Whether a folder's icon can be made visible in its host's file manager.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "kebab-case")]
+pub enum ApplyCapability {
+    /// The platform provider customizes the folder directly.
+    Native,
+    /// The folder is customized through the host executor.
+    Host,
+    /// The folder can't be customized from here.
+    Unsupported {
+        /// Why, suitable for showing to the user.
+        reason: String,
+    },
+}
+
+impl ApplyCapability {
+    /// Returns `true` unless the folder can't be customized.
+    pub fn is_supported(&self) -> bool {
+        !matches!(self, ApplyCapability::Unsupported { .. })
+    }
+}
+
+/// The side of the WSL boundary folco is running on.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Host {
+    Windows,
+    WslGuest,
+    Other,
+}
+
+impl Host {
+    fn current() -> Self {
+        if cfg!(target_os = "windows") {
+            Host::Windows
+        } else if running_in_wsl() {
+            Host::WslGuest
+        } else {
+            Host::Other
+        }
+    }
+}
+
+/// Returns `true` when running inside a WSL distribution.
+///
+/// The result is computed once per process.
+pub fn running_in_wsl() -> bool {
+    static IN_WSL: OnceLock<bool> = OnceLock::new();
+    *IN_WSL.get_or_init(|| {
+        if !cfg!(target_os = "linux") {
+            return false;
+        }
+        std::env::var_os("WSL_DISTRO_NAME").is_some()
+            || std::fs::read_to_string("/proc/sys/kernel/osrelease")
+                .is_ok_and(|release| release.to_lowercase().contains("microsoft"))
+    })
+}
+
+/// Classifies `path` relative to the WSL boundary.
+///
+/// Expects a normalized path, such as one from
+/// [`normalize_folder_path`](crate::normalize_folder_path). Only Windows
+/// drives under the default `/mnt` automount root are recognized.
+pub fn classify_path(path: &Path) -> PathLocation {
+    classify(&path.to_string_lossy(), Host::current())
+}
+
+/// Translates a Windows drive path seen from WSL to its Windows form, such
+/// as `/mnt/c/Users/me` to `C:\Users\me`.
+///
+/// Returns `None` if `path` isn't under a drive mount.
+pub fn to_windows_path(path: &Path) -> Option<PathBuf> {
+    let path = path.to_str()?;
+    let (drive, rest) = split_drive_mount(path)?;
+    let rest = rest.trim_start_matches('/').replace('/', "\\");
+    Some(PathBuf::from(format!("{drive}:\\{rest}")))
+}
+
+fn classify(path: &str, host: Host) -> PathLocation {
+    match host {
+        Host::Windows => wsl_distro(path)
+            .map(|distro| PathLocation::WslDistribution { distro })
+            .unwrap_or(PathLocation::Native),
+        Host::WslGuest => split_drive_mount(path)
+            .map(|(drive, _)| PathLocation::WindowsDrive { drive })
+            .unwrap_or(PathLocation::Native),
+        Host::Other => PathLocation::Native,
+    }
+}
+
+/// Returns the distribution named by a `\\wsl$\<distro>` or
+/// `\\wsl.localhost\<distro>` path, in any of the UNC spellings.
+fn wsl_distro(path: &str) -> Option<String> {
+    let rest = [r"\\?\UNC\", r"\\", "//"].iter().find_map(|prefix| {
+        path.get(..prefix.len())
+            .is_some_and(|start| start.eq_ignore_ascii_case(prefix))
+            .then(|| &path[prefix.len()..])
+    })?;
+    let mut parts = rest.split(['\\', '/']);
+    let server = parts.next()?;
+    if !server.eq_ignore_ascii_case("wsl$") && !server.eq_ignore_ascii_case("wsl.localhost") {
+        return None;
+    }
+    parts
+        .next()
+        .filter(|distro| !distro.is_empty())
+        .map(str::to_string)
+}
+
+/// Splits `/mnt/<drive>/rest` into the uppercase drive letter and `/rest`.
+fn split_drive_mount(path: &str) -> Option<(char, &str)> {
+    let rest = path.strip_prefix("/mnt/")?;
+    let mut chars = rest.chars();
+    let drive = chars.next().filter(char::is_ascii_alphabetic)?;
+    let rest = chars.as_str();
+    (rest.is_empty() || rest.starts_with('/')).then(|| (drive.to_ascii_uppercase(), rest))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_classify_wsl_share_from_windows() {
+        for path in [
+            r"\\wsl$\Ubuntu\home\me\src",
+            r"\\wsl.localhost\Ubuntu\home\me",
+            r"\\?\UNC\wsl$\Ubuntu\home",
+        ] {
+            assert_eq!(
+                classify(path, Host::Windows),
+                PathLocation::WslDistribution {
+                    distro: "Ubuntu".to_string()
+                },
+                "{path}"
+            );
+        }
+        assert_eq!(
+            classify(r"\\server\share\dir", Host::Windows),
+            PathLocation::Native
+        );
+        assert_eq!(
+            classify(r"C:\Users\me", Host::Windows),
+            PathLocation::Native
+        );
+    }
+
+    #[test]
+    fn test_classify_drive_mount_from_wsl() {
+        assert_eq!(
+            classify("/mnt/c/Users/me", Host::WslGuest),
+            PathLocation::WindowsDrive { drive: 'C' }
+        );
+        assert_eq!(
+            classify("/mnt/data/photos", Host::WslGuest),
+            PathLocation::Native
+        );
+        assert_eq!(classify("/home/me", Host::WslGuest), PathLocation::Native);
+        assert_eq!(
+            classify("/mnt/c/Users/me", Host::Other),
+            PathLocation::Native
+        );
+    }
+
+    #[test]
+    fn test_to_windows_path() {
+        assert_eq!(
+            to_windows_path(Path::new("/mnt/d/Projects/app")),
+            Some(PathBuf::from(r"D:\Projects\app"))
+        );
+        assert_eq!(
+            to_windows_path(Path::new("/mnt/c")),
+            Some(PathBuf::from(r"C:\"))
+        );
+        assert_eq!(to_windows_path(Path::new("/home/me")), None);
+    }
+}